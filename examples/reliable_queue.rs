@@ -0,0 +1,165 @@
+// A reliable queue on a stream consumer group, consumed at least once.
+//
+// Jobs are added to the `jobs` stream and read by the workers of the `workers` group. A
+// worker acknowledges a job once it is done, the jobs it read but didn't acknowledge stay
+// pending. Here one worker crashes after reading a few jobs, the other one reclaims the jobs
+// it left pending with XAUTOCLAIM once they have been idle long enough, so every job is done
+// at least once. Results are LPUSHed to the `done` list and collected with BRPOP.
+//
+//     cargo run --example reliable_queue [host:port]
+//
+// Without an address it runs against a server started in the process.
+
+use anyhow::Result;
+use redis::aio::MultiplexedConnection;
+use simple_redis::Server;
+use std::{collections::BTreeSet, time::Duration};
+
+const JOBS: usize = 10;
+const GROUP: &str = "workers";
+// a pending job is reclaimed once its worker has held it this long
+const MIN_IDLE_MS: u64 = 200;
+
+// an entry ID and its flat list of fields and values
+type Entry = (String, Vec<String>);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let addr = match std::env::args().nth(1) {
+        Some(addr) => addr,
+        None => {
+            let server = Server::builder().addr("127.0.0.1:0").build().await?;
+            let addr = server.local_addr()?;
+            tokio::spawn(server.run());
+            addr.to_string()
+        }
+    };
+    let client = redis::Client::open(format!("redis://{}", addr))?;
+    let mut con = client.get_multiplexed_async_connection().await?;
+
+    redis::cmd("DEL")
+        .arg("jobs")
+        .arg("done")
+        .query_async::<()>(&mut con)
+        .await?;
+    redis::cmd("XGROUP")
+        .arg("CREATE")
+        .arg("jobs")
+        .arg(GROUP)
+        .arg("$")
+        .arg("MKSTREAM")
+        .query_async::<()>(&mut con)
+        .await?;
+    for job in 0..JOBS {
+        redis::cmd("XADD")
+            .arg("jobs")
+            .arg("*")
+            .arg("job")
+            .arg(job)
+            .query_async::<String>(&mut con)
+            .await?;
+    }
+
+    // takes a few jobs and dies before doing them
+    let mut flaky = client.get_multiplexed_async_connection().await?;
+    let taken = read_new(&mut flaky, "flaky", 3).await?;
+    println!("flaky took {} jobs and crashed", taken.len());
+
+    let steady = client.get_multiplexed_async_connection().await?;
+    let worker = tokio::spawn(work(steady, "steady"));
+
+    // each connection blocks on its own, BRPOP would hold up the worker's commands otherwise
+    let mut results = client.get_multiplexed_async_connection().await?;
+    let mut done = BTreeSet::new();
+    while done.len() < JOBS {
+        let reply: Option<(String, String)> = redis::cmd("BRPOP")
+            .arg("done")
+            .arg(5)
+            .query_async(&mut results)
+            .await?;
+        match reply {
+            Some((_, job)) => {
+                println!("job {} done", job);
+                done.insert(job.parse::<usize>()?);
+            }
+            None => anyhow::bail!("gave up waiting, {} of {} jobs done", done.len(), JOBS),
+        }
+    }
+    worker.abort();
+    assert_eq!(done, (0..JOBS).collect());
+    println!("all {} jobs done", JOBS);
+    Ok(())
+}
+
+// reclaim the jobs other workers left pending for too long, otherwise read new ones. A job is
+// acknowledged after it is done, so a crash before that leaves it to be reclaimed.
+async fn work(mut con: MultiplexedConnection, consumer: &str) -> Result<()> {
+    let mut cursor = "0-0".to_string();
+    loop {
+        let (next, claimed, _deleted): (String, Vec<Entry>, Vec<String>) = redis::cmd("XAUTOCLAIM")
+            .arg("jobs")
+            .arg(GROUP)
+            .arg(consumer)
+            .arg(MIN_IDLE_MS)
+            .arg(&cursor)
+            .arg("COUNT")
+            .arg(10)
+            .query_async(&mut con)
+            .await?;
+        cursor = next;
+        if !claimed.is_empty() {
+            println!("{} reclaimed {} jobs", consumer, claimed.len());
+        }
+        let entries = match claimed.is_empty() {
+            true => read_new(&mut con, consumer, 10).await?,
+            false => claimed,
+        };
+
+        for (id, fields) in entries {
+            let job = fields
+                .chunks(2)
+                .find(|pair| pair[0] == "job")
+                .and_then(|pair| pair.get(1))
+                .cloned()
+                .unwrap_or_default();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            redis::cmd("LPUSH")
+                .arg("done")
+                .arg(job)
+                .query_async::<i64>(&mut con)
+                .await?;
+            redis::cmd("XACK")
+                .arg("jobs")
+                .arg(GROUP)
+                .arg(id)
+                .query_async::<i64>(&mut con)
+                .await?;
+        }
+    }
+}
+
+// jobs never delivered to the group, waiting a little for them
+async fn read_new(
+    con: &mut MultiplexedConnection,
+    consumer: &str,
+    count: usize,
+) -> Result<Vec<Entry>> {
+    let reply: Option<Vec<(String, Vec<Entry>)>> = redis::cmd("XREADGROUP")
+        .arg("GROUP")
+        .arg(GROUP)
+        .arg(consumer)
+        .arg("COUNT")
+        .arg(count)
+        .arg("BLOCK")
+        .arg(100)
+        .arg("STREAMS")
+        .arg("jobs")
+        .arg(">")
+        .query_async(con)
+        .await?;
+    Ok(reply
+        .into_iter()
+        .flatten()
+        .flat_map(|(_, entries)| entries)
+        .collect())
+}
@@ -0,0 +1,35 @@
+// Feeds arbitrary bytes to both decoders, with small limits so hostile headers are hit:
+//
+//   cargo +nightly fuzz run decode
+//
+// Neither may panic nor allocate past the limits. The streaming decoder gets the input in
+// two reads to also go through its partial frame states, and must recover from errors.
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use simple_redis::{DecoderLimits, RespDecode, RespFrame, RespFrameDecoder};
+
+const LIMITS: DecoderLimits = DecoderLimits {
+    max_bulk_len: 64 * 1024,
+    max_nesting_depth: 16,
+    max_aggregate_len: 1024,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = RespFrame::decode(&mut BytesMut::from(data));
+
+    let split = data.first().map_or(0, |&b| b as usize % (data.len() + 1));
+    let mut decoder = RespFrameDecoder::with_limits(LIMITS);
+    let mut buf = BytesMut::new();
+    for chunk in [&data[..split], &data[split..]] {
+        buf.extend_from_slice(chunk);
+        loop {
+            match decoder.decode(&mut buf) {
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(_) => decoder.recover(&mut buf),
+            }
+        }
+    }
+});
@@ -0,0 +1,283 @@
+// Bit operations on string values. A bitmap is the string's bytes, bit 0 is the most
+// significant bit of the first byte, the same layout as Redis.
+
+use super::{string::string_bytes, Backend, Key, KeyType, Value};
+use crate::{BulkString, RespFrame};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+// whether the range of BITCOUNT and BITPOS is in bytes or bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitUnit {
+    #[default]
+    Byte,
+    Bit,
+}
+
+// start and end are inclusive and may be negative to count from the end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitRange {
+    pub start: i64,
+    pub end: Option<i64>,
+    pub unit: BitUnit,
+}
+
+fn get_bit(bytes: &[u8], offset: u64) -> bool {
+    match bytes.get((offset / 8) as usize) {
+        Some(byte) => byte & (0x80 >> (offset % 8)) != 0,
+        None => false,
+    }
+}
+
+// turn start and end into indexes in 0..len, None when the range is empty
+fn normalize_range(start: i64, end: i64, len: u64) -> Option<(u64, u64)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let end = if end < 0 { (len + end).max(0) } else { end };
+    let end = end.min(len - 1);
+    (len > 0 && start <= end).then_some((start as u64, end as u64))
+}
+
+// the bit positions covered by the range
+fn bit_range(range: Option<BitRange>, bytes: &[u8]) -> Option<(u64, u64)> {
+    let bits = bytes.len() as u64 * 8;
+    match range {
+        None => (bits > 0).then(|| (0, bits - 1)),
+        Some(BitRange {
+            start,
+            end,
+            unit: BitUnit::Byte,
+        }) => normalize_range(start, end.unwrap_or(-1), bytes.len() as u64)
+            .map(|(start, end)| (start * 8, end * 8 + 7)),
+        Some(BitRange {
+            start,
+            end,
+            unit: BitUnit::Bit,
+        }) => normalize_range(start, end.unwrap_or(-1), bits),
+    }
+}
+
+fn bitcount(bytes: &[u8], range: Option<BitRange>) -> u64 {
+    let Some((start, end)) = bit_range(range, bytes) else {
+        return 0;
+    };
+    let (first, last) = ((start / 8) as usize, (end / 8) as usize);
+    // whole bytes, then the bits outside the range in the first and last bytes
+    let mut count: u64 = bytes[first..=last]
+        .iter()
+        .map(|b| b.count_ones() as u64)
+        .sum();
+    for offset in first as u64 * 8..start {
+        count -= get_bit(bytes, offset) as u64;
+    }
+    for offset in end + 1..(last as u64 + 1) * 8 {
+        count -= get_bit(bytes, offset) as u64;
+    }
+    count
+}
+
+// position of the first bit set to bit, -1 if there is none. Looking for a 0 without an
+// explicit end finds the first bit after the string when all the bits are set.
+fn bitpos(bytes: &[u8], bit: bool, range: Option<BitRange>) -> i64 {
+    // a missing key is all zeros
+    if bytes.is_empty() {
+        return if bit { -1 } else { 0 };
+    }
+    let explicit_end = range.is_some_and(|range| range.end.is_some());
+    let Some((start, end)) = bit_range(range, bytes) else {
+        return -1;
+    };
+    match (start..=end).find(|offset| get_bit(bytes, *offset) == bit) {
+        Some(offset) => offset as i64,
+        None if !bit && !explicit_end => (end + 1) as i64,
+        None => -1,
+    }
+}
+
+fn bitop(op: BitOperation, sources: &[Vec<u8>]) -> Vec<u8> {
+    let len = sources.iter().map(|s| s.len()).max().unwrap_or(0);
+    // missing bytes of the shorter strings count as zeros
+    let byte = |source: &Vec<u8>, i: usize| source.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| {
+            let mut bytes = sources.iter().map(|s| byte(s, i));
+            let first = bytes.next().unwrap_or(0);
+            match op {
+                BitOperation::And => bytes.fold(first, |acc, b| acc & b),
+                BitOperation::Or => bytes.fold(first, |acc, b| acc | b),
+                BitOperation::Xor => bytes.fold(first, |acc, b| acc ^ b),
+                BitOperation::Not => !first,
+            }
+        })
+        .collect()
+}
+
+impl Backend {
+    // set or clear a bit, growing the string with zeros as needed. Returns the old bit.
+    pub fn setbit(&self, key: Key, offset: u64, bit: bool) -> bool {
+        let mut value =
+            self.value_or_insert_with::<RespFrame>(key, || BulkString::new(Vec::new()).into());
+        let mut bytes = match value.value_mut() {
+            RespFrame::BulkString(s) => Vec::from(std::mem::take(&mut s.0)),
+            frame => string_bytes(frame).into_owned(),
+        };
+        let index = (offset / 8) as usize;
+        if bytes.len() <= index {
+            bytes.resize(index + 1, 0);
+        }
+        let mask = 0x80 >> (offset % 8);
+        let old = bytes[index] & mask != 0;
+        if bit {
+            bytes[index] |= mask;
+        } else {
+            bytes[index] &= !mask;
+        }
+        *value = BulkString::new(bytes).into();
+        drop(value);
+        self.bump_string_epoch();
+        old
+    }
+
+    pub fn getbit(&self, key: &[u8], offset: u64) -> bool {
+        match self.value::<RespFrame>(key) {
+            Some(value) => get_bit(&string_bytes(&value), offset),
+            None => false,
+        }
+    }
+
+    pub fn bitcount(&self, key: &[u8], range: Option<BitRange>) -> u64 {
+        match self.value::<RespFrame>(key) {
+            Some(value) => bitcount(&string_bytes(&value), range),
+            None => 0,
+        }
+    }
+
+    pub fn bitpos(&self, key: &[u8], bit: bool, range: Option<BitRange>) -> i64 {
+        match self.value::<RespFrame>(key) {
+            Some(value) => bitpos(&string_bytes(&value), bit, range),
+            None => bitpos(&[], bit, range),
+        }
+    }
+
+    // store the result of the operation in dest, an empty result deletes dest.
+    // Returns the length of the result.
+    pub fn bitop(&self, op: BitOperation, dest: Key, keys: &[Key]) -> usize {
+        let sources: Vec<Vec<u8>> = keys
+            .iter()
+            .map(|key| {
+                self.value::<RespFrame>(key)
+                    .map(|value| string_bytes(&value).into_owned())
+                    .unwrap_or_default()
+            })
+            .collect();
+        let result = bitop(op, &sources);
+        let len = result.len();
+        if result.is_empty() {
+            self.remove_keys(std::slice::from_ref(&dest));
+        } else {
+            // a string keeps its TTL, a key of another type is replaced with it
+            let keep_ttl = self.key_type(&dest) == Some(KeyType::String);
+            self.replace_value(
+                dest,
+                Value::String(BulkString::new(result).into()),
+                keep_ttl,
+            );
+        }
+        self.bump_string_epoch();
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitcount() {
+        let bytes = b"foobar";
+        assert_eq!(bitcount(bytes, None), 26);
+        let range = |start, end, unit| {
+            Some(BitRange {
+                start,
+                end: Some(end),
+                unit,
+            })
+        };
+        assert_eq!(bitcount(bytes, range(0, 0, BitUnit::Byte)), 4);
+        assert_eq!(bitcount(bytes, range(1, 1, BitUnit::Byte)), 6);
+        assert_eq!(bitcount(bytes, range(-2, -1, BitUnit::Byte)), 7);
+        assert_eq!(bitcount(bytes, range(5, 30, BitUnit::Bit)), 17);
+        assert_eq!(bitcount(bytes, range(3, 1, BitUnit::Byte)), 0);
+        assert_eq!(bitcount(b"", None), 0);
+    }
+
+    #[test]
+    fn test_bitpos() {
+        assert_eq!(bitpos(&[0xff, 0xf0, 0x00], false, None), 12);
+        assert_eq!(bitpos(&[0x00, 0xff, 0xf0], true, None), 8);
+        let range = |start, end, unit| Some(BitRange { start, end, unit });
+        assert_eq!(
+            bitpos(&[0x00, 0xff, 0xf0], true, range(2, None, BitUnit::Byte)),
+            16
+        );
+        assert_eq!(
+            bitpos(&[0x00, 0xff, 0xf0], true, range(7, Some(15), BitUnit::Bit)),
+            8
+        );
+        // all ones: the first zero is right after the string unless the end is given
+        assert_eq!(bitpos(&[0xff], false, None), 8);
+        assert_eq!(
+            bitpos(&[0xff], false, range(0, Some(-1), BitUnit::Byte)),
+            -1
+        );
+        assert_eq!(bitpos(&[], false, None), 0);
+        assert_eq!(bitpos(&[], true, None), -1);
+    }
+
+    #[test]
+    fn test_bitop() {
+        let sources = vec![b"abc".to_vec(), b"a".to_vec()];
+        assert_eq!(bitop(BitOperation::And, &sources), vec![b'a', 0, 0]);
+        assert_eq!(bitop(BitOperation::Or, &sources), b"abc".to_vec());
+        assert_eq!(bitop(BitOperation::Xor, &sources), vec![0, b'b', b'c']);
+        assert_eq!(bitop(BitOperation::Not, &sources[1..]), vec![!b'a']);
+    }
+
+    #[test]
+    fn test_setbit_getbit() {
+        let backend = Backend::new();
+        assert!(!backend.setbit("k".into(), 7, true));
+        assert!(backend.setbit("k".into(), 7, true));
+        assert_eq!(backend.get(b"k"), Some(BulkString::new(vec![1]).into()));
+        assert!(backend.getbit(b"k", 7));
+        assert!(!backend.getbit(b"k", 100));
+
+        backend.setbit("k".into(), 17, true);
+        assert_eq!(
+            backend.get(b"k"),
+            Some(BulkString::new(vec![1, 0, 0x40]).into())
+        );
+        assert_eq!(backend.bitcount(b"k", None), 2);
+
+        assert_eq!(
+            backend.bitop(BitOperation::Not, "dest".into(), &["k".into()]),
+            3
+        );
+        assert_eq!(backend.bitcount(b"dest", None), 22);
+        assert_eq!(
+            backend.bitop(BitOperation::Or, "dest".into(), &["nope".into()]),
+            0
+        );
+        assert_eq!(backend.get(b"dest"), None);
+    }
+}
@@ -0,0 +1,98 @@
+use super::{Backend, Key};
+use futures::future::select_all;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::Notify,
+    time::{timeout_at, Instant},
+};
+
+impl Backend {
+    // wake up the clients blocked on key, they check again whether they can proceed
+    pub(crate) fn signal_key_ready(&self, key: &[u8]) {
+        if let Some(notify) = self.key_waiters.get(key) {
+            notify.notify_waiters();
+        }
+    }
+
+    // call `f` until it returns Some, waiting for one of the keys to be signaled in between.
+    // Returns None when the timeout expires, a timeout of None waits forever.
+    pub(crate) async fn block_on_keys<T>(
+        &self,
+        keys: &[Key],
+        timeout: Option<Duration>,
+        mut f: impl FnMut() -> Option<T>,
+    ) -> Option<T> {
+        // timeouts are checked when parsed, one that overflows anyway waits forever
+        let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+        let notifies: Vec<Arc<Notify>> = keys
+            .iter()
+            .map(|key| self.key_waiters.entry(key.clone()).or_default().clone())
+            .collect();
+
+        let ret = loop {
+            // register for wake-ups before checking the keys so no signal is missed
+            let mut notified: Vec<_> = notifies.iter().map(|n| Box::pin(n.notified())).collect();
+            for n in notified.iter_mut() {
+                n.as_mut().enable();
+            }
+
+            if let Some(ret) = f() {
+                break Some(ret);
+            }
+
+            let wait = select_all(notified);
+            match deadline {
+                Some(deadline) => {
+                    if timeout_at(deadline, wait).await.is_err() {
+                        break None;
+                    }
+                }
+                None => {
+                    wait.await;
+                }
+            }
+        };
+
+        drop(notifies);
+        for key in keys {
+            self.key_waiters
+                .remove_if(key, |_, notify| Arc::strong_count(notify) == 1);
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_block_on_keys_timeout() {
+        let backend = Backend::new();
+        let ret: Option<()> = backend
+            .block_on_keys(&["a".into()], Some(Duration::from_millis(10)), || None)
+            .await;
+        assert_eq!(ret, None);
+        assert!(backend.key_waiters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_on_keys_signaled() {
+        let backend = Backend::new();
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move {
+            let mut checks = 0;
+            cloned
+                .block_on_keys(&["a".into()], None, || {
+                    checks += 1;
+                    (checks > 1).then_some(checks)
+                })
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        backend.signal_key_ready(b"a");
+        assert_eq!(handle.await.unwrap(), Some(2));
+        assert!(backend.key_waiters.is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+// Optional per-connection cache of the most recently read string values.
+//
+// Entries are tagged with the backend's string epoch, which every write to a
+// string key bumps, so a cached value is only served while no string key has
+// changed since it was read. That keeps invalidation to a single atomic load,
+// at the price of dropping the whole cache on any write: it only pays off for
+// read-mostly workloads, which the hit/miss counters are there to confirm.
+
+use super::{Backend, Key};
+use crate::RespFrame;
+use std::{collections::VecDeque, sync::atomic::Ordering};
+
+#[derive(Debug, Default)]
+pub struct GetCache {
+    // most recently used first
+    entries: VecDeque<(Key, u64, RespFrame)>,
+    capacity: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GetCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl GetCache {
+    pub fn new(capacity: usize) -> Self {
+        GetCache {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.entries.truncate(capacity);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&mut self, key: &[u8], epoch: u64) -> Option<RespFrame> {
+        let pos = self
+            .entries
+            .iter()
+            .position(|(k, _, _)| k.as_bytes() == key)?;
+        if self.entries[pos].1 != epoch {
+            self.entries.remove(pos);
+            return None;
+        }
+        let entry = self.entries.remove(pos)?;
+        let value = entry.2.clone();
+        self.entries.push_front(entry);
+        Some(value)
+    }
+
+    pub fn insert(&mut self, key: Key, epoch: u64, value: RespFrame) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.entries.retain(|(k, _, _)| *k != key);
+        self.entries.truncate(self.capacity - 1);
+        self.entries.push_front((key, epoch, value));
+    }
+}
+
+impl GetCacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+impl Backend {
+    pub fn string_epoch(&self) -> u64 {
+        self.string_epoch.load(Ordering::Acquire)
+    }
+
+    // called on every write to the string keys, invalidates all the GET caches
+    pub(crate) fn bump_string_epoch(&self) {
+        self.string_epoch.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub fn get_cache_size(&self) -> usize {
+        self.get_cache_size.load(Ordering::Relaxed)
+    }
+
+    pub fn set_get_cache_size(&self, size: usize) {
+        self.get_cache_size.store(size, Ordering::Relaxed);
+    }
+
+    pub fn get_cache_stats(&self) -> GetCacheStats {
+        GetCacheStats {
+            hits: self.get_cache_hits.load(Ordering::Relaxed),
+            misses: self.get_cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    // GET through the connection's cache when get-cache-size is not 0
+    pub fn get_cached(&self, key: &[u8], cache: &mut GetCache) -> Option<RespFrame> {
+        let size = self.get_cache_size();
+        if cache.capacity() != size {
+            cache.set_capacity(size);
+        }
+        if size == 0 {
+            return self.get(key);
+        }
+
+        // read the epoch before the value, a write in between makes the entry stale
+        let epoch = self.string_epoch();
+        if let Some(value) = cache.get(key, epoch) {
+            self.get_cache_hits.fetch_add(1, Ordering::Relaxed);
+            return Some(value);
+        }
+        self.get_cache_misses.fetch_add(1, Ordering::Relaxed);
+        let value = self.get(key)?;
+        cache.insert(Key::from(key), epoch, value.clone());
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_get_cache_lru() {
+        let mut cache = GetCache::new(2);
+        cache.insert("a".into(), 0, BulkString::from("1").into());
+        cache.insert("b".into(), 0, BulkString::from("2").into());
+        assert_eq!(cache.get(b"a", 0), Some(BulkString::from("1").into()));
+
+        // b is the least recently used one
+        cache.insert("c".into(), 0, BulkString::from("3").into());
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(b"b", 0), None);
+        assert_eq!(cache.get(b"a", 1), None);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_get_cached() {
+        let backend = Backend::new();
+        let mut cache = GetCache::default();
+        backend.set("k".into(), BulkString::from("v1").into());
+
+        // disabled by default
+        assert_eq!(
+            backend.get_cached(b"k", &mut cache),
+            Some(BulkString::from("v1").into())
+        );
+        assert!(cache.is_empty());
+
+        backend.set_get_cache_size(4);
+        backend.get_cached(b"k", &mut cache);
+        backend.get_cached(b"k", &mut cache);
+        assert_eq!(
+            backend.get_cache_stats(),
+            GetCacheStats { hits: 1, misses: 1 }
+        );
+
+        backend.set("k".into(), BulkString::from("v2").into());
+        assert_eq!(
+            backend.get_cached(b"k", &mut cache),
+            Some(BulkString::from("v2").into())
+        );
+        assert_eq!(backend.get_cache_stats().hit_rate(), 1.0 / 3.0);
+    }
+}
@@ -0,0 +1,29 @@
+// Wall clock of the server, for stream IDs, idle times and the like. Durations and
+// timeouts use tokio's clock instead, which tests can pause. In tests the wall clock can
+// be replaced per thread, so a simulation on a current-thread runtime gets reproducible
+// timestamps.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(test)]
+thread_local! {
+    static MOCK_NOW_MS: std::cell::Cell<Option<u64>> = const { std::cell::Cell::new(None) };
+}
+
+// unix time in milliseconds
+pub(crate) fn now_ms() -> u64 {
+    #[cfg(test)]
+    if let Some(now) = MOCK_NOW_MS.with(|now| now.get()) {
+        return now;
+    }
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// None goes back to the system time
+#[cfg(test)]
+pub(crate) fn set_mock_now_ms(now: Option<u64>) {
+    MOCK_NOW_MS.with(|mock| mock.set(now));
+}
@@ -0,0 +1,369 @@
+// Cluster mode without a cluster bus: every instance is given the same static topology
+// with cluster-topology and routes keys by it. Commands on keys of slots the instance
+// doesn't own get MOVED, and a slot being migrated gets ASK for keys that are gone
+// already, so cluster-aware clients can be tested against a few instances of this server.
+//
+// The topology lists the nodes separated by spaces, each as host:port@slots where slots
+// is a comma separated list of ranges (0-5460) and single slots. A slot followed by
+// >host:port is owned by the node and being migrated to the other one:
+//
+//   127.0.0.1:7000@0-8191,8192>127.0.0.1:7001 127.0.0.1:7001@8193-16383
+//
+// An instance finds itself in the topology by its port.
+
+use super::{Backend, Key};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+};
+use thiserror::Error;
+
+pub const CLUSTER_SLOTS: u16 = 16384;
+
+#[derive(Debug, Default)]
+pub struct Cluster {
+    enabled: AtomicBool,
+    topology: RwLock<ClusterTopology>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClusterTopology {
+    pub nodes: Vec<ClusterNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterNode {
+    pub id: String,
+    pub host: String,
+    pub port: u16,
+    // inclusive ranges, in the order given
+    pub slots: Vec<(u16, u16)>,
+    // slots being migrated and the host:port they go to
+    pub migrating: BTreeMap<u16, String>,
+}
+
+impl Cluster {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn topology(&self) -> ClusterTopology {
+        self.topology.read().unwrap().clone()
+    }
+
+    pub fn set_topology(&self, topology: ClusterTopology) {
+        *self.topology.write().unwrap() = topology;
+    }
+}
+
+impl ClusterTopology {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut nodes = Vec::new();
+        let mut owned = vec![false; CLUSTER_SLOTS as usize];
+        for node in s.split_whitespace() {
+            let (addr, ranges) = node
+                .split_once('@')
+                .ok_or_else(|| format!("Invalid cluster node '{}'", node))?;
+            let (host, port) = parse_addr(addr)?;
+            let mut node = ClusterNode {
+                id: node_id(&host, port),
+                host,
+                port,
+                slots: Vec::new(),
+                migrating: BTreeMap::new(),
+            };
+            for range in ranges.split(',').filter(|range| !range.is_empty()) {
+                let (range, target) = match range.split_once('>') {
+                    Some((slot, target)) => {
+                        let (host, port) = parse_addr(target)?;
+                        (slot, Some(format!("{}:{}", host, port)))
+                    }
+                    None => (range, None),
+                };
+                let (start, end) = match range.split_once('-') {
+                    Some((start, end)) if target.is_none() => {
+                        (parse_slot(start)?, parse_slot(end)?)
+                    }
+                    Some(_) => return Err(format!("Only a single slot can migrate: '{}'", range)),
+                    None => (parse_slot(range)?, parse_slot(range)?),
+                };
+                if start > end {
+                    return Err(format!("Invalid slot range '{}'", range));
+                }
+                for slot in start..=end {
+                    if std::mem::replace(&mut owned[slot as usize], true) {
+                        return Err(format!("Slot {} is assigned more than once", slot));
+                    }
+                }
+                if let Some(target) = target {
+                    node.migrating.insert(start, target);
+                }
+                node.slots.push((start, end));
+            }
+            nodes.push(node);
+        }
+        Ok(ClusterTopology { nodes })
+    }
+
+    pub fn owner(&self, slot: u16) -> Option<&ClusterNode> {
+        self.nodes.iter().find(|node| {
+            node.slots
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&slot))
+        })
+    }
+
+    pub fn node_by_port(&self, port: u16) -> Option<&ClusterNode> {
+        self.nodes.iter().find(|node| node.port == port)
+    }
+
+    pub fn slots_assigned(&self) -> usize {
+        self.nodes
+            .iter()
+            .flat_map(|node| node.slots.iter())
+            .map(|(start, end)| (end - start) as usize + 1)
+            .sum()
+    }
+}
+
+impl std::fmt::Display for ClusterTopology {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let nodes: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let slots: Vec<String> = node
+                    .slots
+                    .iter()
+                    .map(|(start, end)| match node.migrating.get(start) {
+                        Some(target) => format!("{}>{}", start, target),
+                        None if start == end => start.to_string(),
+                        None => format!("{}-{}", start, end),
+                    })
+                    .collect();
+                format!("{}:{}@{}", node.host, node.port, slots.join(","))
+            })
+            .collect();
+        write!(f, "{}", nodes.join(" "))
+    }
+}
+
+// why a command can't run here, each encoded with the prefix cluster clients match on
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ClusterError {
+    #[error("CROSSSLOT Keys in request don't hash to the same slot")]
+    CrossSlot,
+    #[error("CLUSTERDOWN Hash slot not served")]
+    Down,
+    #[error("MOVED {slot} {addr}")]
+    Moved { slot: u16, addr: String },
+    #[error("ASK {slot} {addr}")]
+    Ask { slot: u16, addr: String },
+}
+
+impl ClusterNode {
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+impl Backend {
+    // check that the command on keys can run here, the error to reply with if it can't.
+    // asking is set after ASKING, to accept a slot being migrated to this node.
+    pub fn cluster_route(&self, keys: &[Key], asking: bool) -> Result<(), ClusterError> {
+        let Some(slot) = keys.first().map(|key| key_hash_slot(key.as_bytes())) else {
+            return Ok(());
+        };
+        if keys.iter().any(|key| key_hash_slot(key.as_bytes()) != slot) {
+            return Err(ClusterError::CrossSlot);
+        }
+        let topology = self.cluster.topology.read().unwrap();
+        let Some(owner) = topology.owner(slot) else {
+            return Err(ClusterError::Down);
+        };
+        if owner.port == self.port() {
+            // keys that already moved are asked for at the target
+            return match owner.migrating.get(&slot) {
+                Some(target) if self.exists(keys) < keys.len() => Err(ClusterError::Ask {
+                    slot,
+                    addr: target.clone(),
+                }),
+                _ => Ok(()),
+            };
+        }
+        match owner.migrating.get(&slot) {
+            Some(target) if asking && target.ends_with(&format!(":{}", self.port())) => Ok(()),
+            _ => Err(ClusterError::Moved {
+                slot,
+                addr: owner.addr(),
+            }),
+        }
+    }
+}
+
+// the slot of a key is CRC16 of the key mod 16384, or of the part between the first { and
+// the next } when it isn't empty, so related keys can be kept in one slot
+pub fn key_hash_slot(key: &[u8]) -> u16 {
+    let key = match key.iter().position(|&b| b == b'{') {
+        Some(start) => match key[start + 1..].iter().position(|&b| b == b'}') {
+            Some(len) if len > 0 => &key[start + 1..start + 1 + len],
+            _ => key,
+        },
+        None => key,
+    };
+    crc16(key) % CLUSTER_SLOTS
+}
+
+// CRC16-CCITT (XMODEM), the variant Redis Cluster uses
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+// nodes have no state to keep an ID in, derive it from the address so every instance
+// given the topology agrees on it
+fn node_id(host: &str, port: u16) -> String {
+    let addr = format!("{}:{}", host, port);
+    (0..5u64)
+        .map(|seed| {
+            let hash = addr.bytes().fold(
+                0xcbf29ce484222325u64 ^ seed.wrapping_mul(0x9e3779b97f4a7c15),
+                |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3),
+            );
+            format!("{:08x}", hash as u32)
+        })
+        .collect()
+}
+
+fn parse_addr(addr: &str) -> Result<(String, u16), String> {
+    let (host, port) = addr
+        .rsplit_once(':')
+        .ok_or_else(|| format!("Invalid node address '{}'", addr))?;
+    let port = port
+        .parse()
+        .map_err(|_| format!("Invalid node address '{}'", addr))?;
+    Ok((host.to_string(), port))
+}
+
+fn parse_slot(s: &str) -> Result<u16, String> {
+    match s.parse::<u16>() {
+        Ok(slot) if slot < CLUSTER_SLOTS => Ok(slot),
+        _ => Err(format!("Invalid slot '{}'", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_key_hash_slot() {
+        // from the Redis Cluster specification
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(key_hash_slot(b"foo"), 12182);
+        assert_eq!(
+            key_hash_slot(b"{user1000}.following"),
+            key_hash_slot(b"user1000")
+        );
+        // an empty tag hashes the whole key
+        assert_eq!(
+            key_hash_slot(b"foo{}{bar}"),
+            crc16(b"foo{}{bar}") % CLUSTER_SLOTS
+        );
+        assert_eq!(key_hash_slot(b"foo{{bar}}zap"), key_hash_slot(b"{bar"));
+    }
+
+    #[test]
+    fn test_topology_parse() {
+        let s = "127.0.0.1:7000@0-8191,8192>127.0.0.1:7001 127.0.0.1:7001@8193-16383";
+        let topology = ClusterTopology::parse(s).unwrap();
+        assert_eq!(topology.nodes.len(), 2);
+        assert_eq!(topology.slots_assigned(), CLUSTER_SLOTS as usize);
+        assert_eq!(topology.owner(8192).unwrap().port, 7000);
+        assert_eq!(topology.owner(8193).unwrap().port, 7001);
+        assert_eq!(topology.nodes[0].id.len(), 40);
+        assert_ne!(topology.nodes[0].id, topology.nodes[1].id);
+        assert_eq!(topology.to_string(), s);
+
+        assert!(ClusterTopology::parse("127.0.0.1:7000@0-10 127.0.0.1:7001@10").is_err());
+        assert!(ClusterTopology::parse("127.0.0.1:7000@0-16384").is_err());
+        assert!(ClusterTopology::parse("127.0.0.1@0").is_err());
+    }
+
+    #[test]
+    fn test_cluster_route() {
+        let backend = Backend::new();
+        backend.set_port(7000);
+        let foo = key_hash_slot(b"foo");
+        backend.cluster.set_topology(
+            ClusterTopology::parse(&format!(
+                "127.0.0.1:7000@0-{},{}>127.0.0.1:7001 127.0.0.1:7001@{}-16383",
+                foo - 1,
+                foo,
+                foo + 1
+            ))
+            .unwrap(),
+        );
+        let keys = |keys: &[&str]| keys.iter().map(|k| Key::from(*k)).collect::<Vec<_>>();
+
+        assert_eq!(backend.cluster_route(&keys(&["b"]), false), Ok(()));
+        assert_eq!(
+            backend.cluster_route(&keys(&["b", "foo"]), false),
+            Err(ClusterError::CrossSlot)
+        );
+        assert_eq!(
+            backend.cluster_route(&keys(&["a"]), false),
+            Err(ClusterError::Moved {
+                slot: key_hash_slot(b"a"),
+                addr: "127.0.0.1:7001".to_string()
+            })
+        );
+        // foo is migrating: served while it is here, asked for at the target once it's gone
+        assert_eq!(
+            backend.cluster_route(&keys(&["foo"]), false),
+            Err(ClusterError::Ask {
+                slot: foo,
+                addr: "127.0.0.1:7001".to_string()
+            })
+        );
+        backend.set("foo".into(), BulkString::from("1").into());
+        assert_eq!(backend.cluster_route(&keys(&["foo"]), false), Ok(()));
+
+        // the target accepts it only after ASKING
+        backend.set_port(7001);
+        assert_eq!(
+            backend.cluster_route(&keys(&["foo"]), false),
+            Err(ClusterError::Moved {
+                slot: foo,
+                addr: "127.0.0.1:7000".to_string()
+            })
+        );
+        assert_eq!(
+            ClusterError::Ask {
+                slot: 12182,
+                addr: "127.0.0.1:7001".to_string()
+            }
+            .to_string(),
+            "ASK 12182 127.0.0.1:7001"
+        );
+        assert_eq!(backend.cluster_route(&keys(&["foo"]), true), Ok(()));
+    }
+}
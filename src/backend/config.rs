@@ -0,0 +1,753 @@
+// CONFIG parameters. Every parameter has a typed value, parsed from and printed to the
+// strings of CONFIG SET and CONFIG GET the way Redis does:
+//
+//   integers   plain decimal numbers
+//   booleans   yes or no
+//   byte sizes a number of bytes with an optional k, kb, m, mb, g or gb suffix (k is 1000,
+//              kb is 1024), always printed as a plain number of bytes
+//   durations  a number in the unit of the parameter
+//   enums      one of a fixed set of names, case insensitive
+
+use super::{
+    glob_match, notify_flags_from_str, notify_flags_to_string, Backend, ClusterTopology,
+    ConsumerPelPolicy, MaxmemoryPolicy, OutputBufferLimit, ShutdownPolicy, CONSUMER_PEL_POLICIES,
+    MAXMEMORY_POLICIES, SHUTDOWN_POLICIES,
+};
+use crate::logging::{
+    level_from_name, level_name, LogFormat, LogRotation, LOG_FORMATS, LOG_LEVELS, LOG_ROTATIONS,
+};
+use std::{fmt, sync::atomic::Ordering, time::Duration};
+use tracing::level_filters::LevelFilter;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigValue {
+    Integer(i64),
+    Bool(bool),
+    Bytes(u64),
+    Duration(Duration, DurationUnit),
+    Enum(&'static str),
+    String(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DurationUnit {
+    Micros,
+    Millis,
+    Seconds,
+}
+
+struct ConfigParam {
+    name: &'static str,
+    get: fn(&Backend) -> ConfigValue,
+    set: fn(&Backend, &str) -> Result<(), String>,
+}
+
+const CONFIG_PARAMS: &[ConfigParam] = &[
+    ConfigParam {
+        name: "slowlog-log-slower-than",
+        get: |backend| ConfigValue::Integer(backend.slowlog.log_slower_than()),
+        set: |backend, value| {
+            backend.slowlog.set_log_slower_than(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "slowlog-max-len",
+        get: |backend| ConfigValue::Integer(backend.slowlog.max_len() as i64),
+        set: |backend, value| {
+            backend.slowlog.set_max_len(parse_unsigned(value)? as usize);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "latency-monitor-threshold",
+        get: |backend| ConfigValue::Duration(backend.latency.threshold(), DurationUnit::Millis),
+        set: |backend, value| {
+            backend
+                .latency
+                .set_threshold(parse_duration(value, DurationUnit::Millis)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "notify-keyspace-events",
+        get: |backend| {
+            ConfigValue::String(notify_flags_to_string(backend.notify_keyspace_events()))
+        },
+        set: |backend, value| {
+            let flags = notify_flags_from_str(value)
+                .ok_or_else(|| format!("Invalid event class character: {}", value))?;
+            backend.set_notify_keyspace_events(flags);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "dbfilename",
+        get: |backend| ConfigValue::String(backend.dbfilename()),
+        set: |backend, value| {
+            if value.is_empty() {
+                return Err("dbfilename can't be empty".to_string());
+            }
+            backend.set_dbfilename(value.to_string());
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "get-cache-size",
+        get: |backend| ConfigValue::Integer(backend.get_cache_size() as i64),
+        set: |backend, value| {
+            backend.set_get_cache_size(parse_unsigned(value)? as usize);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "lazyfree-lazy-user-del",
+        get: |backend| ConfigValue::Bool(backend.lazyfree_lazy_user_del.load(Ordering::Relaxed)),
+        set: |backend, value| {
+            backend
+                .lazyfree_lazy_user_del
+                .store(parse_bool(value)?, Ordering::Relaxed);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "shutdown-on-sigterm",
+        get: |backend| ConfigValue::Enum(backend.shutdown_on_sigterm().name()),
+        set: |backend, value| {
+            let name = parse_enum(value, SHUTDOWN_POLICIES)?;
+            backend.set_shutdown_on_sigterm(ShutdownPolicy::from_name(name).unwrap_or_default());
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "shutdown-timeout",
+        get: |backend| ConfigValue::Duration(backend.shutdown_timeout(), DurationUnit::Seconds),
+        set: |backend, value| {
+            backend.set_shutdown_timeout(parse_duration(value, DurationUnit::Seconds)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "maxmemory",
+        get: |backend| ConfigValue::Bytes(backend.maxmemory()),
+        set: |backend, value| {
+            backend.set_maxmemory(parse_bytes(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "maxmemory-policy",
+        get: |backend| ConfigValue::Enum(backend.maxmemory_policy().name()),
+        set: |backend, value| {
+            let name = parse_enum(value, MAXMEMORY_POLICIES)?;
+            backend.set_maxmemory_policy(MaxmemoryPolicy::from_name(name).unwrap_or_default());
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "maxmemory-samples",
+        get: |backend| ConfigValue::Integer(backend.maxmemory_samples() as i64),
+        set: |backend, value| {
+            backend.set_maxmemory_samples(parse_limit(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "stream-consumer-idle-timeout",
+        get: |backend| {
+            ConfigValue::Duration(
+                backend.stream_consumer_idle_timeout(),
+                DurationUnit::Seconds,
+            )
+        },
+        set: |backend, value| {
+            backend.set_stream_consumer_idle_timeout(parse_duration(value, DurationUnit::Seconds)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "stream-consumer-idle-pel",
+        get: |backend| ConfigValue::Enum(backend.stream_consumer_idle_pel().name()),
+        set: |backend, value| {
+            let name = parse_enum(value, CONSUMER_PEL_POLICIES)?;
+            backend.set_stream_consumer_idle_pel(
+                ConsumerPelPolicy::from_name(name).unwrap_or_default(),
+            );
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "port",
+        get: |backend| ConfigValue::Integer(backend.port() as i64),
+        set: |backend, value| {
+            let port = parse_unsigned(value)?;
+            let port = u16::try_from(port).map_err(|_| format!("port {} is out of range", port))?;
+            backend.set_port(port);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "tcp-listeners",
+        get: |backend| ConfigValue::Integer(backend.tcp_listeners() as i64),
+        set: |backend, value| {
+            backend.set_tcp_listeners(parse_limit(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "unixsocket",
+        get: |backend| ConfigValue::String(backend.unixsocket()),
+        set: |backend, value| {
+            backend.set_unixsocket(value.to_string());
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "maxclients",
+        get: |backend| ConfigValue::Integer(backend.maxclients() as i64),
+        set: |backend, value| {
+            let maxclients = parse_unsigned(value)?;
+            if maxclients == 0 {
+                return Err("maxclients must be at least 1".to_string());
+            }
+            backend.set_maxclients(maxclients);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "trace-sample-rate",
+        get: |backend| ConfigValue::Integer(backend.trace_sample_rate() as i64),
+        set: |backend, value| {
+            backend.set_trace_sample_rate(parse_unsigned(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "proto-max-bulk-len",
+        get: |backend| ConfigValue::Integer(backend.decoder_limits().max_bulk_len as i64),
+        set: |backend, value| {
+            let len = parse_limit(value)?;
+            backend.update_decoder_limits(|limits| limits.max_bulk_len = len);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "proto-max-nesting-depth",
+        get: |backend| ConfigValue::Integer(backend.decoder_limits().max_nesting_depth as i64),
+        set: |backend, value| {
+            let depth = parse_limit(value)?;
+            backend.update_decoder_limits(|limits| limits.max_nesting_depth = depth);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "proto-max-aggregate-len",
+        get: |backend| ConfigValue::Integer(backend.decoder_limits().max_aggregate_len as i64),
+        set: |backend, value| {
+            let len = parse_limit(value)?;
+            backend.update_decoder_limits(|limits| limits.max_aggregate_len = len);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "set-max-listpack-entries",
+        get: |backend| ConfigValue::Integer(backend.set_listpack_limits().max_entries as i64),
+        set: |backend, value| {
+            let entries = parse_unsigned(value)? as usize;
+            backend.update_set_listpack_limits(|limits| limits.max_entries = entries);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "set-max-listpack-value",
+        get: |backend| ConfigValue::Integer(backend.set_listpack_limits().max_value as i64),
+        set: |backend, value| {
+            let len = parse_unsigned(value)? as usize;
+            backend.update_set_listpack_limits(|limits| limits.max_value = len);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "hash-max-listpack-entries",
+        get: |backend| ConfigValue::Integer(backend.hash_listpack_limits().max_entries as i64),
+        set: |backend, value| {
+            let entries = parse_unsigned(value)? as usize;
+            backend.update_hash_listpack_limits(|limits| limits.max_entries = entries);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "hash-max-listpack-value",
+        get: |backend| ConfigValue::Integer(backend.hash_listpack_limits().max_value as i64),
+        set: |backend, value| {
+            let len = parse_unsigned(value)? as usize;
+            backend.update_hash_listpack_limits(|limits| limits.max_value = len);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "list-max-listpack-size",
+        get: |backend| ConfigValue::Integer(backend.list_max_listpack_size()),
+        set: |backend, value| {
+            // -1 to -5 are sizes in bytes, 4KB to 64KB
+            match parse_integer(value)? {
+                size if size < -5 => Err(format!("argument must be -5 or more: {}", value)),
+                size => {
+                    backend.set_list_max_listpack_size(size);
+                    Ok(())
+                }
+            }
+        },
+    },
+    ConfigParam {
+        name: "timeout",
+        get: |backend| ConfigValue::Duration(backend.client_timeout(), DurationUnit::Seconds),
+        set: |backend, value| {
+            backend.set_client_timeout(parse_duration(value, DurationUnit::Seconds)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "loglevel",
+        get: |backend| ConfigValue::Enum(level_name(backend.log_config().level)),
+        set: |backend, value| {
+            let level = level_from_name(parse_enum(value, LOG_LEVELS)?).unwrap_or(LevelFilter::OFF);
+            backend.update_log_config(|config| config.level = level);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "logfile",
+        get: |backend| ConfigValue::String(backend.log_config().file),
+        set: |backend, value| {
+            backend.update_log_config(|config| config.file = value.to_string());
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "log-format",
+        get: |backend| ConfigValue::Enum(backend.log_config().format.name()),
+        set: |backend, value| {
+            let format = LogFormat::from_name(parse_enum(value, LOG_FORMATS)?).unwrap_or_default();
+            backend.update_log_config(|config| config.format = format);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "log-rotation",
+        get: |backend| ConfigValue::Enum(backend.log_config().rotation.name()),
+        set: |backend, value| {
+            let rotation =
+                LogRotation::from_name(parse_enum(value, LOG_ROTATIONS)?).unwrap_or_default();
+            backend.update_log_config(|config| config.rotation = rotation);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "tcp-keepalive",
+        get: |backend| ConfigValue::Duration(backend.tcp_keepalive(), DurationUnit::Seconds),
+        set: |backend, value| {
+            backend.set_tcp_keepalive(parse_duration(value, DurationUnit::Seconds)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "tcp-nodelay",
+        get: |backend| ConfigValue::Bool(backend.tcp_nodelay()),
+        set: |backend, value| {
+            backend.set_tcp_nodelay(parse_bool(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "read-only",
+        get: |backend| ConfigValue::Bool(backend.read_only()),
+        set: |backend, value| {
+            backend.set_read_only(parse_bool(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "replica-read-only",
+        get: |backend| ConfigValue::Bool(backend.replication.read_only()),
+        set: |backend, value| {
+            backend.replication.set_read_only(parse_bool(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "key-prefix-separator",
+        get: |backend| ConfigValue::String(backend.key_prefixes.separator()),
+        set: |backend, value| {
+            if value.is_empty() {
+                return Err("key-prefix-separator can't be empty".to_string());
+            }
+            backend.set_key_prefix_separator(value.to_string());
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "key-prefix-segments",
+        get: |backend| ConfigValue::Integer(backend.key_prefixes.segments() as i64),
+        set: |backend, value| {
+            backend.set_key_prefix_segments(parse_unsigned(value)? as usize);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "key-stats-sample-rate",
+        get: |backend| ConfigValue::Integer(backend.key_stats.sample_rate() as i64),
+        set: |backend, value| {
+            backend.key_stats.set_sample_rate(parse_unsigned(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "cluster-enabled",
+        get: |backend| ConfigValue::Bool(backend.cluster.is_enabled()),
+        set: |backend, value| {
+            backend.cluster.set_enabled(parse_bool(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "cluster-topology",
+        get: |backend| ConfigValue::String(backend.cluster.topology().to_string()),
+        set: |backend, value| {
+            backend.cluster.set_topology(ClusterTopology::parse(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "repl-backlog-size",
+        get: |backend| ConfigValue::Bytes(backend.replication.backlog_size() as u64),
+        set: |backend, value| {
+            backend
+                .replication
+                .set_backlog_size(parse_bytes(value)? as usize);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "enable-debug-command",
+        get: |backend| ConfigValue::Bool(backend.debug_command_enabled()),
+        set: |backend, value| {
+            backend.set_enable_debug_command(parse_bool(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "client-output-buffer-limit",
+        get: |backend| ConfigValue::String(backend.pubsub.output_buffer_limit().to_string()),
+        set: |backend, value| {
+            backend
+                .pubsub
+                .set_output_buffer_limit(OutputBufferLimit::parse(value)?);
+            Ok(())
+        },
+    },
+];
+
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigValue::Integer(v) => write!(f, "{}", v),
+            ConfigValue::Bool(v) => write!(f, "{}", if *v { "yes" } else { "no" }),
+            ConfigValue::Bytes(v) => write!(f, "{}", v),
+            ConfigValue::Duration(v, DurationUnit::Micros) => write!(f, "{}", v.as_micros()),
+            ConfigValue::Duration(v, DurationUnit::Millis) => write!(f, "{}", v.as_millis()),
+            ConfigValue::Duration(v, DurationUnit::Seconds) => write!(f, "{}", v.as_secs()),
+            ConfigValue::Enum(v) => write!(f, "{}", v),
+            ConfigValue::String(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+pub fn parse_integer(value: &str) -> Result<i64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("argument couldn't be parsed into an integer: {}", value))
+}
+
+fn parse_unsigned(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("argument must be a positive integer: {}", value))
+}
+
+// a decoder limit, at least 1 and no more than an i64 can show
+fn parse_limit(value: &str) -> Result<usize, String> {
+    match parse_unsigned(value)? {
+        0 => Err("argument must be at least 1".to_string()),
+        limit if limit > i64::MAX as u64 => Err(format!("argument is too large: {}", value)),
+        limit => Ok(limit as usize),
+    }
+}
+
+pub fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(format!("argument must be 'yes' or 'no': {}", value)),
+    }
+}
+
+pub fn parse_bytes(value: &str) -> Result<u64, String> {
+    let invalid = || format!("argument must be a memory value: {}", value);
+    let lower = value.to_ascii_lowercase();
+    let (number, multiplier) = [
+        ("gb", 1024 * 1024 * 1024),
+        ("mb", 1024 * 1024),
+        ("kb", 1024),
+        ("g", 1000 * 1000 * 1000),
+        ("m", 1000 * 1000),
+        ("k", 1000),
+        ("b", 1),
+    ]
+    .into_iter()
+    .find_map(|(suffix, multiplier)| Some((lower.strip_suffix(suffix)?, multiplier)))
+    .unwrap_or((lower.as_str(), 1));
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    number.checked_mul(multiplier).ok_or_else(invalid)
+}
+
+pub fn parse_duration(value: &str, unit: DurationUnit) -> Result<Duration, String> {
+    let v = parse_unsigned(value)?;
+    Ok(match unit {
+        DurationUnit::Micros => Duration::from_micros(v),
+        DurationUnit::Millis => Duration::from_millis(v),
+        DurationUnit::Seconds => Duration::from_secs(v),
+    })
+}
+
+pub fn parse_enum(value: &str, names: &[&'static str]) -> Result<&'static str, String> {
+    names
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(value))
+        .copied()
+        .ok_or_else(|| format!("argument must be one of {}: {}", names.join(", "), value))
+}
+
+fn find_param(name: &str) -> Option<&'static ConfigParam> {
+    CONFIG_PARAMS
+        .iter()
+        .find(|param| param.name.eq_ignore_ascii_case(name))
+}
+
+impl Backend {
+    pub fn config_get(&self, name: &str) -> Option<ConfigValue> {
+        find_param(name).map(|param| (param.get)(self))
+    }
+
+    // the parameters whose name matches the glob pattern, case insensitive
+    pub fn config_get_matching(&self, pattern: &str) -> Vec<(&'static str, ConfigValue)> {
+        CONFIG_PARAMS
+            .iter()
+            .filter(|param| glob_match(pattern.as_bytes(), param.name.as_bytes(), true))
+            .map(|param| (param.name, (param.get)(self)))
+            .collect()
+    }
+
+    pub fn config_set(&self, name: &str, value: &str) -> Result<(), String> {
+        match find_param(name) {
+            Some(param) => (param.set)(self, value),
+            None => Err(format!(
+                "Unknown option or number of arguments for CONFIG SET - '{}'",
+                name
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecoderLimits;
+
+    #[test]
+    fn test_config_get_set() {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.config_get("slowlog-log-slower-than"),
+            Some(ConfigValue::Integer(10000))
+        );
+
+        backend.config_set("SLOWLOG-MAX-LEN", "10").unwrap();
+        assert_eq!(
+            backend.config_get("slowlog-max-len").map(|v| v.to_string()),
+            Some("10".to_string())
+        );
+
+        assert!(backend.config_set("slowlog-max-len", "abc").is_err());
+        assert!(backend.config_set("slowlog-max-len", "-1").is_err());
+        backend
+            .config_set("latency-monitor-threshold", "100")
+            .unwrap();
+        assert_eq!(backend.latency.threshold(), Duration::from_millis(100));
+        assert!(backend.config_set("unknown", "1").is_err());
+        assert_eq!(backend.config_get("unknown"), None);
+
+        backend.config_set("lazyfree-lazy-user-del", "YES").unwrap();
+        assert_eq!(
+            backend
+                .config_get("lazyfree-lazy-user-del")
+                .map(|v| v.to_string()),
+            Some("yes".to_string())
+        );
+
+        backend.config_set("shutdown-on-sigterm", "NOSAVE").unwrap();
+        assert_eq!(backend.shutdown_on_sigterm(), ShutdownPolicy::NoSave);
+        assert!(backend.config_set("shutdown-on-sigterm", "later").is_err());
+
+        backend.config_set("maxmemory", "1mb").unwrap();
+        assert_eq!(backend.maxmemory(), 1024 * 1024);
+        backend
+            .config_set("maxmemory-policy", "allkeys-lru")
+            .unwrap();
+        assert_eq!(backend.maxmemory_policy(), MaxmemoryPolicy::AllKeysLru);
+        backend.config_set("maxmemory-samples", "10").unwrap();
+        assert_eq!(backend.maxmemory_samples(), 10);
+        assert!(backend.config_set("maxmemory-samples", "0").is_err());
+        backend
+            .config_set("stream-consumer-idle-timeout", "60")
+            .unwrap();
+        assert_eq!(
+            backend.stream_consumer_idle_timeout(),
+            Duration::from_secs(60)
+        );
+        backend
+            .config_set("stream-consumer-idle-pel", "DROP")
+            .unwrap();
+        assert_eq!(backend.stream_consumer_idle_pel(), ConsumerPelPolicy::Drop);
+        assert!(backend
+            .config_set("stream-consumer-idle-pel", "keep")
+            .is_err());
+        backend.config_set("shutdown-timeout", "3").unwrap();
+        assert_eq!(
+            backend.config_get("shutdown-timeout"),
+            Some(ConfigValue::Duration(
+                Duration::from_secs(3),
+                DurationUnit::Seconds
+            ))
+        );
+
+        backend.config_set("port", "6380").unwrap();
+        assert_eq!(backend.port(), 6380);
+        assert!(backend.config_set("port", "70000").is_err());
+        backend
+            .config_set("unixsocket", "/tmp/simple-redis.sock")
+            .unwrap();
+        assert_eq!(backend.unixsocket(), "/tmp/simple-redis.sock");
+        backend.config_set("maxclients", "2").unwrap();
+        assert_eq!(backend.maxclients(), 2);
+        assert!(backend.config_set("maxclients", "0").is_err());
+        backend.config_set("timeout", "300").unwrap();
+        assert_eq!(backend.client_timeout(), Duration::from_secs(300));
+        backend.config_set("trace-sample-rate", "100").unwrap();
+        assert_eq!(backend.trace_sample_rate(), 100);
+        backend.config_set("proto-max-bulk-len", "1024").unwrap();
+        backend.config_set("proto-max-nesting-depth", "4").unwrap();
+        backend.config_set("proto-max-aggregate-len", "16").unwrap();
+        assert_eq!(
+            backend.decoder_limits(),
+            DecoderLimits {
+                max_bulk_len: 1024,
+                max_nesting_depth: 4,
+                max_aggregate_len: 16,
+            }
+        );
+        assert!(backend.config_set("proto-max-nesting-depth", "0").is_err());
+        backend.config_set("replica-read-only", "no").unwrap();
+        assert!(!backend.replication.read_only());
+
+        backend.config_set("key-prefix-segments", "2").unwrap();
+        assert_eq!(backend.key_prefixes.segments(), 2);
+        assert!(backend.config_set("key-prefix-separator", "").is_err());
+        backend.config_set("key-stats-sample-rate", "10").unwrap();
+        assert_eq!(backend.key_stats.sample_rate(), 10);
+
+        backend.config_set("cluster-enabled", "yes").unwrap();
+        assert!(backend.cluster.is_enabled());
+        backend
+            .config_set("cluster-topology", "127.0.0.1:7000@0-16383")
+            .unwrap();
+        assert_eq!(
+            backend.config_get("cluster-topology"),
+            Some(ConfigValue::String("127.0.0.1:7000@0-16383".to_string()))
+        );
+        assert!(backend.config_set("cluster-topology", "nope").is_err());
+
+        backend.config_set("enable-debug-command", "yes").unwrap();
+        assert!(backend.debug_command_enabled());
+
+        assert_eq!(
+            backend.config_get("tcp-keepalive").map(|v| v.to_string()),
+            Some("300".to_string())
+        );
+        backend.config_set("tcp-keepalive", "0").unwrap();
+        assert!(backend.tcp_keepalive().is_zero());
+        backend.config_set("tcp-nodelay", "no").unwrap();
+        assert!(!backend.tcp_nodelay());
+        backend.config_set("read-only", "yes").unwrap();
+        assert!(backend.read_only());
+
+        backend
+            .config_set("client-output-buffer-limit", "pubsub 1mb 256kb 10")
+            .unwrap();
+        assert_eq!(
+            backend.config_get("client-output-buffer-limit"),
+            Some(ConfigValue::String("pubsub 1048576 262144 10".to_string()))
+        );
+        assert!(backend
+            .config_set("client-output-buffer-limit", "replica 0 0 0")
+            .is_err());
+    }
+
+    #[test]
+    fn test_config_get_matching() {
+        let backend = Backend::new();
+        let names = |pattern| {
+            backend
+                .config_get_matching(pattern)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names("slowlog-*"),
+            vec!["slowlog-log-slower-than", "slowlog-max-len"]
+        );
+        assert_eq!(names("DBFILENAME"), vec!["dbfilename"]);
+        assert_eq!(names("*").len(), CONFIG_PARAMS.len());
+        assert!(names("nope*").is_empty());
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_bytes("100"), Ok(100));
+        assert_eq!(parse_bytes("1k"), Ok(1000));
+        assert_eq!(parse_bytes("1KB"), Ok(1024));
+        assert_eq!(parse_bytes("2mb"), Ok(2 * 1024 * 1024));
+        assert_eq!(parse_bytes("1g"), Ok(1_000_000_000));
+        assert!(parse_bytes("1tb").is_err());
+        assert!(parse_bytes("-1").is_err());
+        assert_eq!(
+            ConfigValue::Bytes(parse_bytes("1kb").unwrap()).to_string(),
+            "1024"
+        );
+
+        assert_eq!(parse_bool("No"), Ok(false));
+        assert!(parse_bool("1").is_err());
+
+        let timeout = parse_duration("1500", DurationUnit::Millis).unwrap();
+        assert_eq!(timeout, Duration::from_millis(1500));
+        assert_eq!(
+            ConfigValue::Duration(timeout, DurationUnit::Millis).to_string(),
+            "1500"
+        );
+
+        let policies = &["noeviction", "allkeys-lru"];
+        assert_eq!(parse_enum("ALLKEYS-LRU", policies), Ok("allkeys-lru"));
+        assert!(parse_enum("lru", policies).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+// The keyspace: one table of every key, whatever its type. An entry holds the value of its
+// key, its expire time and what eviction keeps track of, so a key has one value and the
+// operations on keys of any type (TYPE, DEL, EXPIRE, COPY...) look it up once.
+//
+// The commands of a type see the values of their keys through typed views of the entries:
+// a view of a key holding another type is None, like a missing key. Commands replacing a
+// key whatever it held replace its value, the dispatcher refuses the others on keys of
+// another type with WRONGTYPE before they get here.
+
+use super::{
+    Backend, ElementList, FieldMap, HyperLogLog, Key, KeyMeta, KeyType, MemberSet, SortedSet,
+    Stream,
+};
+use crate::RespFrame;
+use dashmap::mapref::one::{MappedRef, MappedRefMut};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(RespFrame),
+    // fields are bulk strings, kept in the order they were first set
+    Hash(FieldMap),
+    Set(MemberSet),
+    List(ElementList),
+    ZSet(SortedSet),
+    Stream(Stream),
+    HyperLogLog(HyperLogLog),
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub value: Value,
+    // the unix time in milliseconds the key expires at, see expire.rs
+    pub expire: Option<u64>,
+    // size and last access, None until the dispatcher accounts for the key, see eviction.rs
+    pub meta: Option<KeyMeta>,
+}
+
+// the values of one type, seen in the entries holding that type
+pub(crate) trait TypedValue: Sized {
+    fn of(value: &Value) -> Option<&Self>;
+    fn of_mut(value: &mut Value) -> Option<&mut Self>;
+    fn from_value(value: Value) -> Option<Self>;
+    fn into_value(self) -> Value;
+}
+
+macro_rules! typed_value {
+    ($type:ty, $variant:ident) => {
+        impl TypedValue for $type {
+            fn of(value: &Value) -> Option<&Self> {
+                match value {
+                    Value::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+
+            fn of_mut(value: &mut Value) -> Option<&mut Self> {
+                match value {
+                    Value::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+
+            fn from_value(value: Value) -> Option<Self> {
+                match value {
+                    Value::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+
+            fn into_value(self) -> Value {
+                Value::$variant(self)
+            }
+        }
+    };
+}
+
+typed_value!(RespFrame, String);
+typed_value!(FieldMap, Hash);
+typed_value!(MemberSet, Set);
+typed_value!(ElementList, List);
+typed_value!(SortedSet, ZSet);
+typed_value!(Stream, Stream);
+typed_value!(HyperLogLog, HyperLogLog);
+
+impl Value {
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            Value::String(_) => KeyType::String,
+            Value::Hash(_) => KeyType::Hash,
+            Value::Set(_) => KeyType::Set,
+            Value::List(_) => KeyType::List,
+            Value::ZSet(_) => KeyType::ZSet,
+            Value::Stream(_) => KeyType::Stream,
+            Value::HyperLogLog(_) => KeyType::HyperLogLog,
+        }
+    }
+}
+
+impl Entry {
+    pub fn new(value: Value) -> Self {
+        Self {
+            value,
+            expire: None,
+            meta: None,
+        }
+    }
+}
+
+pub(crate) type ValueRef<'a, T> = MappedRef<'a, Key, Entry, T>;
+pub(crate) type ValueRefMut<'a, T> = MappedRefMut<'a, Key, Entry, T>;
+
+impl Backend {
+    // the value of the key if it holds a T
+    pub(crate) fn value<T: TypedValue>(&self, key: &[u8]) -> Option<ValueRef<'_, T>> {
+        self.db.get(key)?.try_map(|entry| T::of(&entry.value)).ok()
+    }
+
+    pub(crate) fn value_mut<T: TypedValue>(&self, key: &[u8]) -> Option<ValueRefMut<'_, T>> {
+        self.db
+            .get_mut(key)?
+            .try_map(|entry| T::of_mut(&mut entry.value))
+            .ok()
+    }
+
+    pub(crate) fn has_value<T: TypedValue>(&self, key: &[u8]) -> bool {
+        self.value::<T>(key).is_some()
+    }
+
+    // the value of the key, created by f when the key is missing or holds another type.
+    // The entry stays locked until the view is dropped.
+    pub(crate) fn value_or_insert_with<T: TypedValue>(
+        &self,
+        key: Key,
+        f: impl FnOnce() -> T,
+    ) -> ValueRefMut<'_, T> {
+        let mut f = Some(f);
+        let mut create = || f.take().expect("the value is created once")().into_value();
+        let mut entry = self.db.entry(key).or_insert_with(|| Entry::new(create()));
+        if T::of(&entry.value).is_none() {
+            if matches!(entry.value, Value::String(_)) {
+                self.bump_string_epoch();
+            }
+            entry.value = create();
+            let (key, entry) = entry.pair_mut();
+            self.set_entry_expire(key, entry, None);
+        }
+        entry.map(|entry| T::of_mut(&mut entry.value).expect("a value of the type"))
+    }
+
+    pub(crate) fn value_or_default<T: TypedValue + Default>(&self, key: Key) -> ValueRefMut<'_, T> {
+        self.value_or_insert_with(key, T::default)
+    }
+
+    // a new value of the key, which keeps its TTL
+    pub(crate) fn insert_value<T: TypedValue>(&self, key: Key, value: T) {
+        self.replace_value(key, value.into_value(), true);
+    }
+
+    // a new value of the key, its TTL is removed unless keep_ttl
+    pub(crate) fn replace_value(&self, key: Key, value: Value, keep_ttl: bool) {
+        match self.db.get_mut(&key) {
+            Some(mut entry) => {
+                entry.value = value;
+                if !keep_ttl {
+                    self.set_entry_expire(&key, &mut entry, None);
+                }
+            }
+            None => {
+                self.db.insert(key, Entry::new(value));
+            }
+        }
+    }
+
+    // remove the key if it holds a T
+    pub(crate) fn remove_value<T: TypedValue>(&self, key: &[u8]) -> Option<T> {
+        self.remove_entry_if(key, |value| T::of(value).is_some())
+            .and_then(|entry| T::from_value(entry.value))
+    }
+
+    // remove the key if it holds a T for which f is true, e.g. an empty collection
+    pub(crate) fn remove_value_if<T: TypedValue>(&self, key: &[u8], f: impl FnOnce(&T) -> bool) {
+        self.remove_entry_if(key, |value| T::of(value).is_some_and(f));
+    }
+
+    // a copy of every key, taken a shard at a time: each shard is copied under its read
+    // lock and iterated once released, so writers are only held off one shard while it is
+    // copied, never the whole keyspace or the caller's work on the keys. Every shard is
+    // seen at a single point in time, different shards at the points they were copied.
+    pub fn iter_snapshot(&self) -> impl Iterator<Item = (Key, Entry)> + '_ {
+        self.db.shards().iter().flat_map(|shard| {
+            shard
+                .read()
+                .iter()
+                .map(|(key, entry)| (key.clone(), entry.get().clone()))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    pub(crate) fn remove_entry_if(
+        &self,
+        key: &[u8],
+        f: impl FnOnce(&Value) -> bool,
+    ) -> Option<Entry> {
+        self.remove_entry_where(key, |entry| f(&entry.value))
+    }
+
+    // every removal of a key goes through here, to stop tracking its memory and TTL
+    pub(crate) fn remove_entry_where(
+        &self,
+        key: &[u8],
+        f: impl FnOnce(&Entry) -> bool,
+    ) -> Option<Entry> {
+        let (key, entry) = self.db.remove_if(key, |key, entry| {
+            let remove = f(entry);
+            if remove && entry.expire.is_some() {
+                self.volatile.remove(key);
+            }
+            remove
+        })?;
+        if let Some(meta) = entry.meta {
+            self.untrack_key(&key, &meta);
+        }
+        Some(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, ListEnd};
+
+    #[test]
+    fn test_typed_views() {
+        let backend = Backend::new();
+        let key = Key::from("k");
+        backend.set(key.clone(), BulkString::from("v").into());
+        backend.expire(&key, u64::MAX);
+        assert!(backend.has_value::<RespFrame>(b"k"));
+        assert!(backend.value::<FieldMap>(b"k").is_none());
+        assert!(backend.remove_value::<FieldMap>(b"k").is_none());
+
+        // a value of another type replaces the key with its TTL
+        backend.value_or_default::<ElementList>(key.clone()).push(
+            BulkString::from("x").into(),
+            ListEnd::Right,
+            -2,
+        );
+        assert_eq!(backend.key_type(b"k"), Some(KeyType::List));
+        assert_eq!(backend.expire_time(b"k"), None);
+
+        backend.expire(&key, u64::MAX);
+        backend.insert_value(key.clone(), ElementList::default());
+        assert_eq!(backend.expire_time(b"k"), Some(u64::MAX));
+        backend.remove_value_if::<ElementList>(b"k", |list| list.is_empty());
+        assert!(!backend.key_exists(b"k"));
+    }
+
+    #[test]
+    fn test_iter_snapshot() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.set(Key::from(i.to_string()), BulkString::from("v").into());
+        }
+        backend.expire(&Key::from("7"), u64::MAX);
+        let mut snapshot = backend.iter_snapshot();
+        let first = snapshot.next().unwrap();
+        // the shards not copied yet can be written to meanwhile
+        backend.set("new".into(), BulkString::from("v").into());
+        let mut keys: Vec<Key> = std::iter::once(first)
+            .chain(snapshot)
+            .map(|(key, entry)| {
+                assert_eq!(entry.expire.is_some(), key == "7");
+                key
+            })
+            .filter(|key| *key != "new")
+            .collect();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), 100);
+    }
+}
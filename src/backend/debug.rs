@@ -0,0 +1,86 @@
+// What the DEBUG command needs from the backend. DEBUG is meant for tests of clients, it
+// can stall a connection and shows internals, so it is refused unless
+// enable-debug-command is set.
+
+use super::{snapshot::record_kind, Backend, SnapshotError};
+use crate::{RespEncode, RespFrame};
+use std::sync::atomic::Ordering;
+
+pub const DEBUG_DISABLED_ERROR: &str =
+    "ERR DEBUG command not allowed, set the enable-debug-command option to enable it";
+
+impl Backend {
+    pub fn debug_command_enabled(&self) -> bool {
+        self.enable_debug_command.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enable_debug_command(&self, enabled: bool) {
+        self.enable_debug_command.store(enabled, Ordering::Relaxed);
+    }
+
+    // DEBUG SET-ACTIVE-EXPIRE, expired keys are only deleted lazily while it is off
+    pub fn active_expire(&self) -> bool {
+        self.active_expire.load(Ordering::Relaxed)
+    }
+
+    pub fn set_active_expire(&self, enabled: bool) {
+        self.active_expire.store(enabled, Ordering::Relaxed);
+    }
+
+    // DEBUG RELOAD: the data set is serialized to the snapshot format and loaded back in
+    // place of the keys, then serialized again to check nothing was lost or changed on the
+    // way. Writes of other clients meanwhile are lost, it is meant for tests.
+    pub fn debug_reload(&self) -> Result<(), SnapshotError> {
+        let before = self.sorted_records();
+        self.restore_snapshot(&self.snapshot())?;
+        if self.sorted_records() != before {
+            return Err(SnapshotError::Mismatch);
+        }
+        Ok(())
+    }
+
+    // the records of the values and TTLs, in the order of the keys in the shards, which a
+    // reload changes. Meta records are left out: the load starts tracking the accesses of
+    // the keys nobody accessed yet.
+    fn sorted_records(&self) -> Vec<Vec<u8>> {
+        let mut records: Vec<Vec<u8>> = self
+            .snapshot_records()
+            .into_iter()
+            .filter(|record| record_kind(record).is_ok_and(|kind| kind != "meta"))
+            .map(|record| RespFrame::from(record).encode())
+            .collect();
+        records.sort();
+        records
+    }
+
+    // the DEBUG OBJECT line of the key, None when it doesn't exist. Values aren't shared,
+    // the refcount is always 1, and the serialized length is the size of the DUMP payload.
+    pub fn debug_object(&self, key: &[u8]) -> Option<String> {
+        let encoding = self.object_encoding(key)?;
+        let serialized = self.dump(key).map(|payload| payload.len()).unwrap_or(0);
+        Some(format!(
+            "Value at:0x0 refcount:1 encoding:{} serializedlength:{} lru_seconds_idle:{}",
+            encoding,
+            serialized,
+            self.object_idletime(key).unwrap_or(0)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_debug_object() {
+        let backend = Backend::new();
+        assert_eq!(backend.debug_object(b"k"), None);
+
+        backend.set("k".into(), BulkString::from("v").into());
+        let line = backend.debug_object(b"k").unwrap();
+        assert!(line.starts_with("Value at:0x0 refcount:1 encoding:raw serializedlength:"));
+        let serialized = backend.dump(b"k").unwrap().len();
+        assert!(line.contains(&format!("serializedlength:{} ", serialized)));
+    }
+}
@@ -0,0 +1,138 @@
+// DUMP payload format, the value of a single key:
+//
+//   <record> <2 bytes version> <8 bytes CRC64>
+//
+// the record is the snapshot record of the value without its key, e.g. ["list", a, b],
+// the version is SNAPSHOT_VERSION and the CRC64 (Jones) covers everything before it,
+// both little endian like Redis. Payloads of an older version are migrated like
+// snapshots are.
+
+use super::{
+    snapshot::{migrate, LoadedData},
+    Backend, Key, SNAPSHOT_VERSION,
+};
+use crate::{BulkString, RespArray, RespDecode, RespEncode, RespFrame};
+use bytes::BytesMut;
+
+pub const DUMP_PAYLOAD_ERROR: &str = "ERR DUMP payload version or checksum are wrong";
+pub const BUSYKEY_ERROR: &str = "BUSYKEY Target key name already exists.";
+
+// 0xad93d23594c935a9 reflected
+const CRC64_POLY: u64 = 0x95ac9329ac4bc9b5;
+
+impl Backend {
+    // the serialized value of key, None when it doesn't exist
+    pub fn dump(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut payload = RespFrame::from(self.value_record(key)?).encode();
+        payload.extend_from_slice(&(SNAPSHOT_VERSION as u16).to_le_bytes());
+        let crc = crc64(&payload);
+        payload.extend_from_slice(&crc.to_le_bytes());
+        Some(payload)
+    }
+
+    // create key from a DUMP payload, an existing key is replaced only with replace
+    pub fn restore(&self, key: &[u8], payload: &[u8], replace: bool) -> Result<(), &'static str> {
+        let record = parse_payload(key, payload).ok_or(DUMP_PAYLOAD_ERROR)?;
+        let kind = record.first().cloned();
+        let data = LoadedData::default();
+        data.insert(record).map_err(|_| DUMP_PAYLOAD_ERROR)?;
+        // the key can't be created between the check and the write by another RESTORE
+        self.with_keys_locked(&[key], || {
+            if self.key_exists(key) {
+                if !replace {
+                    return Err(BUSYKEY_ERROR);
+                }
+                self.remove_keys(&[Key::from(key)]);
+            }
+            data.move_into(self);
+            Ok(())
+        })?;
+        match kind {
+            Some(RespFrame::BulkString(kind)) if &kind[..] == b"string" => self.bump_string_epoch(),
+            Some(RespFrame::BulkString(kind))
+                if matches!(&kind[..], b"list" | b"stream" | b"zset") =>
+            {
+                self.signal_key_ready(key)
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+// the snapshot record of the payload with key put in, None when it isn't valid
+fn parse_payload(key: &[u8], payload: &[u8]) -> Option<RespArray> {
+    let (body, crc) = payload.split_at_checked(payload.len().checked_sub(8)?)?;
+    if crc64(body).to_le_bytes() != crc {
+        return None;
+    }
+    let (record, version) = body.split_at_checked(body.len().checked_sub(2)?)?;
+    let version = u16::from_le_bytes([version[0], version[1]]) as u32;
+    if version == 0 || version > SNAPSHOT_VERSION {
+        return None;
+    }
+    let mut buf = BytesMut::from(record);
+    let RespFrame::Array(record) = RespFrame::decode(&mut buf).ok()? else {
+        return None;
+    };
+    if !buf.is_empty() || record.is_empty() {
+        return None;
+    }
+    let mut frames = record.0;
+    frames.insert(1, BulkString::from(key).into());
+    migrate(version, vec![RespArray::new(frames)]).ok()?.pop()
+}
+
+// CRC-64/Jones, reflected, as Redis uses for its DUMP payloads
+fn crc64(data: &[u8]) -> u64 {
+    let mut crc = 0u64;
+    for &byte in data {
+        crc ^= byte as u64;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ CRC64_POLY,
+                _ => crc >> 1,
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc64() {
+        // the check value Redis tests its crc64 with
+        assert_eq!(crc64(b"123456789"), 0xe9c6d914c4b8d9ca);
+    }
+
+    #[test]
+    fn test_dump_restore() {
+        let backend = Backend::new();
+        backend.rpush(
+            "l".into(),
+            vec![BulkString::from("a").into(), BulkString::from("b").into()],
+        );
+        assert_eq!(backend.dump(b"missing"), None);
+        let payload = backend.dump(b"l").unwrap();
+
+        let other = Backend::new();
+        other.restore(b"copy", &payload, false).unwrap();
+        assert_eq!(other.dump(b"copy"), Some(payload.clone()));
+        assert_eq!(other.restore(b"copy", &payload, false), Err(BUSYKEY_ERROR));
+        other.set("copy".into(), BulkString::from("v").into());
+        other.restore(b"copy", &payload, true).unwrap();
+        assert_eq!(other.get(b"copy"), None);
+
+        let mut corrupted = payload.clone();
+        corrupted[4] ^= 1;
+        assert_eq!(
+            other.restore(b"x", &corrupted, false),
+            Err(DUMP_PAYLOAD_ERROR)
+        );
+        assert_eq!(other.restore(b"x", b"", false), Err(DUMP_PAYLOAD_ERROR));
+        assert!(!other.key_exists(b"x"));
+    }
+}
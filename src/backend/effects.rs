@@ -0,0 +1,121 @@
+// The effects of writes. A write that changed something publishes its effect: the command
+// normalized to what it did (a BLPOP that popped is an LPOP, see propagated_command) and
+// the keys it touched. The subsystems that follow writes consume the effects instead of
+// being called by each command: the replication stream, and the subscribers, like an
+// append only file would be. Effects are published while the write barrier is held, so
+// every consumer sees them in the order the writes were done.
+//
+// Keyspace notifications are still sent by the commands, their events are named by what
+// each command did, which the normalized command doesn't always tell.
+
+use super::{Backend, Key};
+use crate::{cmd::lookup_command, RespArray, RespFrame};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Effect {
+    pub command: RespArray,
+    pub keys: Vec<Key>,
+}
+
+#[derive(Debug, Default)]
+pub struct EffectBus {
+    // no effect is built while nobody subscribed
+    subscribed: AtomicBool,
+    subscribers: Mutex<Vec<UnboundedSender<Arc<Effect>>>>,
+}
+
+impl Effect {
+    // the keys are found with the spec of the command, like for the command itself
+    pub fn new(command: RespArray) -> Self {
+        let keys = match command.first() {
+            Some(RespFrame::BulkString(name)) => lookup_command(name)
+                .map(|entry| entry.spec.keys(&command))
+                .unwrap_or_default(),
+            _ => vec![],
+        };
+        Effect { command, keys }
+    }
+}
+
+impl EffectBus {
+    pub fn is_subscribed(&self) -> bool {
+        self.subscribed.load(Ordering::Acquire)
+    }
+
+    // every effect published from now on, until the receiver is dropped
+    pub fn subscribe(&self) -> UnboundedReceiver<Arc<Effect>> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers.lock().unwrap().push(sender);
+        self.subscribed.store(true, Ordering::Release);
+        receiver
+    }
+
+    fn publish(&self, effect: Effect) {
+        let effect = Arc::new(effect);
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|sender| sender.send(effect.clone()).is_ok());
+        self.subscribed
+            .store(!subscribers.is_empty(), Ordering::Release);
+    }
+}
+
+impl Backend {
+    // whether writes have to publish their effects, so they only keep their arguments
+    // around when something consumes them
+    pub fn follows_writes(&self) -> bool {
+        self.replication.is_active() || self.effects.is_subscribed()
+    }
+
+    pub fn subscribe_effects(&self) -> UnboundedReceiver<Arc<Effect>> {
+        self.effects.subscribe()
+    }
+
+    // hand the normalized command of a write to every consumer
+    pub fn publish_effect(&self, command: RespArray) {
+        self.replication.propagate(&command);
+        if self.effects.is_subscribed() {
+            self.effects.publish(Effect::new(command));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[test]
+    fn test_publish_effect() {
+        let backend = Backend::new();
+        assert!(!backend.follows_writes());
+        // nobody is listening, nothing is kept
+        backend.publish_effect(command(&["set", "a", "1"]));
+
+        let mut effects = backend.subscribe_effects();
+        assert!(backend.follows_writes());
+        backend.publish_effect(command(&["del", "a", "b"]));
+        backend.publish_effect(command(&["lpop", "l"]));
+        let effect = effects.try_recv().unwrap();
+        assert_eq!(effect.command, command(&["del", "a", "b"]));
+        assert_eq!(effect.keys, vec!["a", "b"]);
+        assert_eq!(effects.try_recv().unwrap().keys, vec!["l"]);
+        assert!(effects.try_recv().is_err());
+
+        drop(effects);
+        backend.publish_effect(command(&["set", "a", "1"]));
+        assert!(!backend.follows_writes());
+    }
+}
@@ -0,0 +1,505 @@
+// maxmemory enforcement. The dispatcher reports the keys every command accessed, which
+// keeps the last access time of each key and, after writes, an estimate of its size.
+// Before a command that may grow memory runs, keys are evicted by the policy until the
+// estimate is back under maxmemory, or the command is refused.
+//
+// The key to evict is the best of a sample of maxmemory-samples keys, like in Redis, so
+// eviction doesn't look at the whole keyspace: keys of the shards from a random one on,
+// from a random position in each, or for volatile-ttl keys with a TTL, see expire.rs.
+//
+// Sizes of collections are estimated from a sample of their elements, so a write to a
+// large collection doesn't walk all of it. Writes made through the Backend API directly
+// rather than through commands aren't accounted for until the key is written again.
+
+use super::{clock::now_ms, stats::frame_size, Backend, Entry, Key, Value, NOTIFY_EVICTED};
+use crate::{BulkString, RespArray};
+use std::{
+    mem::size_of,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const MAXMEMORY_POLICIES: &[&str] = &[
+    "noeviction",
+    "allkeys-lru",
+    "allkeys-random",
+    "volatile-ttl",
+];
+pub const DEFAULT_MAXMEMORY_SAMPLES: usize = 5;
+pub const OOM_ERROR: &str = "OOM command not allowed when used memory > 'maxmemory'.";
+// elements looked at to estimate the size of a collection
+const SIZE_SAMPLES: usize = 16;
+// the access frequency counter is logarithmic like Redis' LFU counter with the default
+// lfu-log-factor and lfu-decay-time: new keys start at 5 so they aren't the first to go,
+// and the counter halves its odds to grow every 10 hits and loses 1 per idle minute
+const LFU_INIT_VAL: u8 = 5;
+const LFU_LOG_FACTOR: f64 = 10.0;
+const LFU_DECAY_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaxmemoryPolicy {
+    #[default]
+    NoEviction,
+    AllKeysLru,
+    AllKeysRandom,
+    // keys with the nearest expire time first, keys without a TTL are never evicted
+    VolatileTtl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyMeta {
+    // estimated bytes used by the key and its value
+    pub size: usize,
+    // unix time in milliseconds
+    pub last_access: u64,
+    // access frequency, see LFU_INIT_VAL
+    pub freq: u8,
+}
+
+impl KeyMeta {
+    fn decayed_freq(&self, now: u64) -> u8 {
+        let periods = now.saturating_sub(self.last_access) / LFU_DECAY_MS;
+        self.freq.saturating_sub(periods.min(u8::MAX as u64) as u8)
+    }
+
+    // the counter after one more access: the more it has grown, the less likely it grows
+    fn accessed_freq(&self, now: u64) -> u8 {
+        let freq = self.decayed_freq(now);
+        if freq == u8::MAX {
+            return freq;
+        }
+        let base = freq.saturating_sub(LFU_INIT_VAL) as f64;
+        let p = 1.0 / (base * LFU_LOG_FACTOR + 1.0);
+        let r = (random() >> 11) as f64 / (1u64 << 53) as f64;
+        match r < p {
+            true => freq + 1,
+            false => freq,
+        }
+    }
+}
+
+impl MaxmemoryPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MaxmemoryPolicy::NoEviction => "noeviction",
+            MaxmemoryPolicy::AllKeysLru => "allkeys-lru",
+            MaxmemoryPolicy::AllKeysRandom => "allkeys-random",
+            MaxmemoryPolicy::VolatileTtl => "volatile-ttl",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "noeviction" => Some(MaxmemoryPolicy::NoEviction),
+            "allkeys-lru" => Some(MaxmemoryPolicy::AllKeysLru),
+            "allkeys-random" => Some(MaxmemoryPolicy::AllKeysRandom),
+            "volatile-ttl" => Some(MaxmemoryPolicy::VolatileTtl),
+            _ => None,
+        }
+    }
+}
+
+impl Backend {
+    // in bytes, 0 is no limit
+    pub fn maxmemory(&self) -> u64 {
+        self.maxmemory.load(Ordering::Relaxed)
+    }
+
+    pub fn set_maxmemory(&self, maxmemory: u64) {
+        self.maxmemory.store(maxmemory, Ordering::Relaxed);
+    }
+
+    pub fn maxmemory_policy(&self) -> MaxmemoryPolicy {
+        *self.maxmemory_policy.lock().unwrap()
+    }
+
+    pub fn set_maxmemory_policy(&self, policy: MaxmemoryPolicy) {
+        *self.maxmemory_policy.lock().unwrap() = policy;
+    }
+
+    pub fn maxmemory_samples(&self) -> usize {
+        self.maxmemory_samples.load(Ordering::Relaxed)
+    }
+
+    pub fn set_maxmemory_samples(&self, samples: usize) {
+        self.maxmemory_samples.store(samples, Ordering::Relaxed);
+    }
+
+    // the memory used by the keys as tracked for maxmemory
+    pub fn tracked_memory(&self) -> usize {
+        self.tracked_memory.load(Ordering::Relaxed) as usize
+    }
+
+    pub fn key_meta(&self, key: &[u8]) -> Option<KeyMeta> {
+        self.db.get(key).and_then(|entry| entry.meta)
+    }
+
+    // seconds since the key was last accessed, None when it doesn't exist. Keys only
+    // written through the Backend API directly haven't been accessed since.
+    pub fn object_idletime(&self, key: &[u8]) -> Option<u64> {
+        self.object_encoding(key)?;
+        Some(
+            self.key_meta(key)
+                .map(|meta| now_ms().saturating_sub(meta.last_access) / 1000)
+                .unwrap_or_default(),
+        )
+    }
+
+    // the access frequency counter of the key, decayed to now
+    pub fn object_freq(&self, key: &[u8]) -> Option<u8> {
+        self.object_encoding(key)?;
+        Some(
+            self.key_meta(key)
+                .map(|meta| meta.decayed_freq(now_ms()))
+                .unwrap_or(LFU_INIT_VAL),
+        )
+    }
+
+    pub fn evicted_keys(&self) -> u64 {
+        self.evicted_keys.load(Ordering::Relaxed)
+    }
+
+    // called by the dispatcher after a command ran, with the keys it accessed. A deleted
+    // key stopped being tracked when it was removed.
+    pub fn record_key_access(&self, keys: &[Key], write: bool) {
+        let now = now_ms();
+        for key in keys {
+            if !write {
+                self.record_key_read(key, now);
+                continue;
+            }
+            let Some(mut entry) = self.db.get_mut(key) else {
+                continue;
+            };
+            let size = key.len() + value_size(&entry.value);
+            let freq = match entry.meta {
+                Some(meta) => meta.accessed_freq(now),
+                None => LFU_INIT_VAL,
+            };
+            let old = entry.meta.replace(KeyMeta {
+                size,
+                last_access: now,
+                freq,
+            });
+            match old {
+                Some(old) => self.adjust_tracked_memory(old.size, size),
+                None => {
+                    self.adjust_tracked_memory(0, size);
+                    self.key_prefixes.key_added(key);
+                }
+            }
+        }
+    }
+
+    // a read looks at the access clock under the shared lock of the key's shard, and takes
+    // the exclusive one only when the clock or the LFU counter moves: at most once a
+    // millisecond, and ever more rarely as the counter grows. The readers of a hot key
+    // don't queue up behind each other just to say they read it.
+    fn record_key_read(&self, key: &[u8], now: u64) {
+        let Some(meta) = self.db.get(key).and_then(|entry| entry.meta) else {
+            return;
+        };
+        let freq = meta.accessed_freq(now);
+        if freq == meta.freq && meta.last_access == now {
+            return;
+        }
+        if let Some(meta) = self
+            .db
+            .get_mut(key)
+            .as_deref_mut()
+            .and_then(|e| e.meta.as_mut())
+        {
+            meta.freq = freq;
+            meta.last_access = now;
+        }
+    }
+
+    // called when a tracked key is removed
+    pub(super) fn untrack_key(&self, key: &[u8], meta: &KeyMeta) {
+        self.adjust_tracked_memory(meta.size, 0);
+        self.key_prefixes.key_removed(key);
+    }
+
+    // the access time and counter a key had before it was loaded, e.g. from a snapshot.
+    // A key without meta is left alone.
+    pub(super) fn restore_key_access(&self, key: &[u8], last_access: u64, freq: u8) {
+        if let Some(meta) = self
+            .db
+            .get_mut(key)
+            .as_deref_mut()
+            .and_then(|e| e.meta.as_mut())
+        {
+            meta.last_access = last_access;
+            meta.freq = freq;
+        }
+    }
+
+    // estimate every key again, after the whole keyspace changed
+    pub fn reset_key_meta(&self) {
+        for mut entry in self.db.iter_mut() {
+            entry.meta = None;
+        }
+        self.tracked_memory.store(0, Ordering::Relaxed);
+        self.recount_key_prefixes();
+        let keys: Vec<Key> = self.db.iter().map(|entry| entry.key().clone()).collect();
+        self.record_key_access(&keys, true);
+    }
+
+    // make room before a command that may use more memory, Err if it must be refused
+    pub fn evict_for_write(&self) -> Result<(), String> {
+        let maxmemory = self.maxmemory();
+        if maxmemory == 0 {
+            return Ok(());
+        }
+        let policy = self.maxmemory_policy();
+        while self.tracked_memory() as u64 > maxmemory {
+            let Some(victim) = self.eviction_victim(policy) else {
+                return Err(OOM_ERROR.to_string());
+            };
+            self.del(std::slice::from_ref(&victim));
+            self.publish_effect(RespArray::new(vec![
+                BulkString::from("del").into(),
+                victim.clone().into(),
+            ]));
+            self.evicted_keys.fetch_add(1, Ordering::Relaxed);
+            self.notify_keyspace_event(NOTIFY_EVICTED, "evicted", &victim);
+        }
+        Ok(())
+    }
+
+    // the best key to evict of a sample
+    fn eviction_victim(&self, policy: MaxmemoryPolicy) -> Option<Key> {
+        let samples = self.maxmemory_samples();
+        match policy {
+            MaxmemoryPolicy::NoEviction => None,
+            MaxmemoryPolicy::VolatileTtl => self
+                .volatile
+                .sample(samples)
+                .into_iter()
+                .filter_map(|key| Some((self.expire_time(&key)?, key)))
+                .min()
+                .map(|(_, key)| key),
+            MaxmemoryPolicy::AllKeysLru => self
+                .sample_entries(samples, |entry| Some(entry.meta?.last_access))
+                .into_iter()
+                .min()
+                .map(|(_, key)| key),
+            MaxmemoryPolicy::AllKeysRandom => self
+                .sample_entries(1, |entry| entry.meta.map(|_| ()))
+                .pop()
+                .map(|(_, key)| key),
+        }
+    }
+
+    // up to count keys for which f gives something, of the shards from a random one on.
+    // They are taken from a random position in every shard, wrapping around.
+    fn sample_entries<T>(&self, count: usize, f: impl Fn(&Entry) -> Option<T>) -> Vec<(T, Key)> {
+        let shards = self.db.shards();
+        let first = random() as usize % shards.len();
+        let mut sample = Vec::with_capacity(count);
+        for i in 0..shards.len() {
+            if sample.len() == count {
+                break;
+            }
+            let shard = shards[(first + i) % shards.len()].read();
+            if shard.is_empty() {
+                continue;
+            }
+            let skip = random() as usize % shard.len();
+            sample.extend(
+                shard
+                    .iter()
+                    .skip(skip)
+                    .chain(shard.iter().take(skip))
+                    .filter_map(|(key, entry)| Some((f(entry.get())?, key.clone())))
+                    .take(count - sample.len()),
+            );
+        }
+        sample
+    }
+
+    fn adjust_tracked_memory(&self, old: usize, new: usize) {
+        if new >= old {
+            self.tracked_memory
+                .fetch_add((new - old) as u64, Ordering::Relaxed);
+        } else {
+            let _ =
+                self.tracked_memory
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                        Some(used.saturating_sub((old - new) as u64))
+                    });
+        }
+    }
+}
+
+// the estimated bytes used by the value
+fn value_size(value: &Value) -> usize {
+    match value {
+        Value::String(v) => frame_size(v),
+        Value::Hash(v) => sampled(
+            v.len(),
+            v.iter().map(|(f, value)| frame_size(f) + frame_size(value)),
+        ),
+        Value::Set(v) => sampled(v.len(), v.iter().map(frame_size)),
+        Value::List(v) => sampled(v.len(), v.iter().map(frame_size)),
+        Value::HyperLogLog(v) => v.size_in_bytes(),
+        Value::Stream(v) => sampled(
+            v.len(),
+            v.entries.values().map(|fields| {
+                fields
+                    .iter()
+                    .map(|(field, value)| field.len() + frame_size(value))
+                    .sum::<usize>()
+            }),
+        ),
+        Value::ZSet(v) => sampled(
+            v.len(),
+            v.iter()
+                .map(|(member, _)| 2 * (member.len() + size_of::<f64>())),
+        ),
+    }
+}
+
+// the size of len elements, from the average size of the first few
+fn sampled(len: usize, sizes: impl Iterator<Item = usize>) -> usize {
+    let (count, total) = sizes
+        .take(SIZE_SAMPLES)
+        .fold((0, 0), |(count, total), size| (count + 1, total + size));
+    match count {
+        0 => 0,
+        count => total * len / count,
+    }
+}
+
+// xorshift, good enough to pick keys to evict
+pub(super) fn random() -> u64 {
+    static STATE: AtomicU64 = AtomicU64::new(0);
+    let mut x = STATE.load(Ordering::Relaxed);
+    if x == 0 {
+        x = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0x2545f4914f6cdd1d)
+            | 1;
+    }
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    STATE.store(x, Ordering::Relaxed);
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::set_mock_now_ms;
+
+    fn write(backend: &Backend, key: &str) {
+        backend.set(key.into(), BulkString::from("x".repeat(100)).into());
+        backend.record_key_access(&[key.into()], true);
+    }
+
+    #[test]
+    fn test_memory_tracking() {
+        let backend = Backend::new();
+        write(&backend, "a");
+        let size = backend.key_meta(b"a").unwrap().size;
+        assert!(size > 100);
+        assert_eq!(backend.tracked_memory(), size);
+
+        backend.del(&["a".into()]);
+        backend.record_key_access(&["a".into()], true);
+        assert_eq!(backend.tracked_memory(), 0);
+        assert_eq!(backend.key_meta(b"a"), None);
+    }
+
+    #[test]
+    fn test_read_shares_the_lock() {
+        set_mock_now_ms(Some(1_000));
+        let backend = Backend::new();
+        write(&backend, "a");
+        backend
+            .db
+            .get_mut(b"a".as_slice())
+            .unwrap()
+            .meta
+            .as_mut()
+            .unwrap()
+            .freq = u8::MAX;
+
+        // nothing moves within the millisecond, a reader holding the key doesn't block it
+        {
+            let _reader = backend.db.get(b"a".as_slice()).unwrap();
+            backend.record_key_access(&["a".into()], false);
+        }
+        set_mock_now_ms(Some(1_005));
+        backend.record_key_access(&["a".into()], false);
+        assert_eq!(backend.key_meta(b"a").unwrap().last_access, 1_005);
+        set_mock_now_ms(None);
+    }
+
+    #[test]
+    fn test_evict_lru() {
+        let backend = Backend::new();
+        write(&backend, "a");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        write(&backend, "b");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        backend.record_key_access(&["a".into()], false);
+        let size = backend.key_meta(b"a").unwrap().size;
+
+        backend.set_maxmemory((size * 2) as u64);
+        assert_eq!(backend.evict_for_write(), Ok(()));
+        write(&backend, "c");
+        // over the limit, and noeviction refuses
+        assert_eq!(backend.evict_for_write(), Err(OOM_ERROR.to_string()));
+
+        backend.set_maxmemory_policy(MaxmemoryPolicy::AllKeysLru);
+        assert_eq!(backend.evict_for_write(), Ok(()));
+        assert!(!backend.key_exists(b"b"));
+        assert!(backend.key_exists(b"a"));
+        assert_eq!(backend.evicted_keys(), 1);
+    }
+
+    #[test]
+    fn test_evict_volatile_ttl() {
+        let backend = Backend::new();
+        let now = now_ms();
+        for (key, ttl) in [("a", 60_000), ("b", 10_000), ("c", 30_000)] {
+            write(&backend, key);
+            backend.expire(&key.into(), now + ttl);
+        }
+        // without a TTL, never evicted
+        write(&backend, "d");
+        let size = backend.key_meta(b"a").unwrap().size;
+
+        // a sample of every key with a TTL finds the nearest expire time
+        backend.set_maxmemory_samples(3);
+        backend.set_maxmemory_policy(MaxmemoryPolicy::VolatileTtl);
+        backend.set_maxmemory((size * 3) as u64);
+        assert_eq!(backend.evict_for_write(), Ok(()));
+        assert!(!backend.key_exists(b"b"));
+        assert!(backend.key_exists(b"a") && backend.key_exists(b"c"));
+        assert_eq!(backend.volatile_keys(), 2);
+
+        backend.set_maxmemory(1);
+        assert_eq!(backend.evict_for_write(), Err(OOM_ERROR.to_string()));
+        assert!(backend.key_exists(b"d"));
+        assert_eq!(backend.evicted_keys(), 3);
+        assert_eq!(backend.volatile_keys(), 0);
+    }
+
+    #[test]
+    fn test_evict_random() {
+        let backend = Backend::new();
+        for key in ["a", "b", "c"] {
+            write(&backend, key);
+        }
+        backend.set_maxmemory(1);
+        backend.set_maxmemory_policy(MaxmemoryPolicy::AllKeysRandom);
+        // everything has to go
+        assert_eq!(backend.evict_for_write(), Ok(()));
+        assert_eq!(backend.evicted_keys(), 3);
+        assert_eq!(backend.tracked_memory(), 0);
+    }
+}
@@ -0,0 +1,346 @@
+// Keys with a time to live. Their expire times, unix times in milliseconds, are kept in
+// their entries of the keyspace, see db.rs, and the keys having one in an index the
+// active expire cycle samples from. Expired keys
+// are deleted in two ways, like in Redis: lazily, when a command is about to access them,
+// and by the active expire cycle for the keys nobody accesses anymore.
+//
+// The cycle runs ACTIVE_EXPIRE_HZ times a second. It samples ACTIVE_EXPIRE_KEYS_PER_LOOP
+// keys with a TTL at a time, deletes the expired ones, and samples again as long as more
+// than ACTIVE_EXPIRE_ACCEPTABLE_STALE percent of the sample had expired, so the effort
+// follows how many keys are expired. A cycle stops after ACTIVE_EXPIRE_CYCLE_TIME anyway,
+// so it never holds up the writes for long.
+//
+// The index is updated under the lock of the entry whose TTL is set or removed, see
+// set_entry_expire, and when the entry is removed, see remove_entry_if. An expired key is
+// deleted only if it is still expired once its entry is locked, so a key given a new
+// value or TTL in the meantime is kept.
+//
+// Deleting an expired key is a write: it is propagated as a DEL. Replicas don't expire
+// keys themselves, they wait for the DEL of their master.
+
+use super::{
+    clock::now_ms, eviction::random, Backend, Entry, Key, Value, LATENCY_EXPIRE_CYCLE,
+    NOTIFY_EXPIRED,
+};
+use crate::{BulkString, RespArray};
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Mutex},
+    time::{Duration, Instant},
+};
+
+const ACTIVE_EXPIRE_HZ: u64 = 10;
+const ACTIVE_EXPIRE_KEYS_PER_LOOP: usize = 20;
+const ACTIVE_EXPIRE_ACCEPTABLE_STALE: usize = 10;
+// a quarter of the time between two cycles, like the slow cycle of Redis
+const ACTIVE_EXPIRE_CYCLE_TIME: Duration = Duration::from_millis(1000 / ACTIVE_EXPIRE_HZ / 4);
+
+// the keys with a TTL, in no particular order, with the position of each of them so one
+// is removed in constant time
+#[derive(Debug, Default)]
+pub(crate) struct VolatileKeys(Mutex<VolatileIndex>);
+
+#[derive(Debug, Default)]
+struct VolatileIndex {
+    keys: Vec<Key>,
+    positions: HashMap<Key, usize>,
+}
+
+impl VolatileKeys {
+    fn insert(&self, key: &[u8]) {
+        let mut index = self.0.lock().unwrap();
+        if index.positions.contains_key(key) {
+            return;
+        }
+        let key = Key::from(key);
+        let position = index.keys.len();
+        index.keys.push(key.clone());
+        index.positions.insert(key, position);
+    }
+
+    pub(super) fn remove(&self, key: &[u8]) {
+        let mut index = self.0.lock().unwrap();
+        let Some(position) = index.positions.remove(key) else {
+            return;
+        };
+        index.keys.swap_remove(position);
+        if let Some(moved) = index.keys.get(position).cloned() {
+            index.positions.insert(moved, position);
+        }
+    }
+
+    pub(super) fn clear(&self) {
+        let mut index = self.0.lock().unwrap();
+        index.keys.clear();
+        index.positions.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().keys.len()
+    }
+
+    // a run of up to count keys from a random position, wrapping around
+    pub(super) fn sample(&self, count: usize) -> Vec<Key> {
+        let index = self.0.lock().unwrap();
+        let len = index.keys.len();
+        if len == 0 {
+            return Vec::new();
+        }
+        let start = random() as usize % len;
+        (0..count.min(len))
+            .map(|i| index.keys[(start + i) % len].clone())
+            .collect()
+    }
+}
+
+impl Backend {
+    // set or remove the expire time of the entry of key, which the caller holds locked
+    pub(super) fn set_entry_expire(&self, key: &[u8], entry: &mut Entry, expire: Option<u64>) {
+        match (entry.expire.is_some(), expire.is_some()) {
+            (false, true) => self.volatile.insert(key),
+            (true, false) => self.volatile.remove(key),
+            _ => {}
+        }
+        entry.expire = expire;
+    }
+
+    // the unix time in milliseconds the key expires at, None when it has no TTL
+    pub fn expire_time(&self, key: &[u8]) -> Option<u64> {
+        self.db.get(key).and_then(|entry| entry.expire)
+    }
+
+    // the time to live of the key in milliseconds, -2 when it doesn't exist and -1 when it
+    // has no TTL, like PTTL replies
+    pub fn pttl(&self, key: &[u8]) -> i64 {
+        if !self.key_exists(key) {
+            return -2;
+        }
+        match self.expire_time(key) {
+            Some(at) => at.saturating_sub(now_ms()) as i64,
+            None => -1,
+        }
+    }
+
+    // the unix time in milliseconds the key expires at, -2 when it doesn't exist and -1
+    // when it has no TTL, like PEXPIRETIME replies
+    pub fn pexpiretime(&self, key: &[u8]) -> i64 {
+        match self.db.get(key) {
+            Some(entry) => entry
+                .expire
+                .map_or(-1, |at| i64::try_from(at).unwrap_or(i64::MAX)),
+            None => -2,
+        }
+    }
+
+    // set the expire time of an existing key, false when it doesn't exist. A time that
+    // has passed deletes the key right away.
+    pub fn expire(&self, key: &Key, at_ms: u64) -> bool {
+        if at_ms <= now_ms() {
+            return self.remove_expired_entry(key, |_| true);
+        }
+        match self.db.get_mut(key) {
+            Some(mut entry) => {
+                self.set_entry_expire(key, &mut entry, Some(at_ms));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // remove the TTL of the key, false when it had none
+    pub fn persist(&self, key: &[u8]) -> bool {
+        self.db.get_mut(key).is_some_and(|mut entry| {
+            let had_ttl = entry.expire.is_some();
+            self.set_entry_expire(key, &mut entry, None);
+            had_ttl
+        })
+    }
+
+    // the number of keys with a TTL
+    pub fn volatile_keys(&self) -> usize {
+        self.volatile.len()
+    }
+
+    pub fn expired_keys(&self) -> u64 {
+        self.expired_keys.load(Ordering::Relaxed)
+    }
+
+    // delete the keys that expired, before a command accesses them
+    pub fn expire_if_needed(&self, keys: &[Key]) {
+        if self.replication.is_replica() {
+            return;
+        }
+        let now = now_ms();
+        for key in keys {
+            if self.expire_time(key).is_some_and(|at| at <= now) {
+                self.delete_expired(key, now);
+            }
+        }
+    }
+
+    // one active expire cycle, returns the number of keys it deleted
+    pub async fn active_expire_cycle(&self) -> usize {
+        if !self.active_expire() || self.replication.is_replica() || self.is_paused(true) {
+            return 0;
+        }
+        let start = Instant::now();
+        let mut deleted = 0;
+        loop {
+            let now = now_ms();
+            let sample = self.volatile.sample(ACTIVE_EXPIRE_KEYS_PER_LOOP);
+            if sample.is_empty() {
+                break;
+            }
+            let sampled = sample.len();
+            let expired: Vec<Key> = sample
+                .into_iter()
+                .filter(|key| self.expire_time(key).is_some_and(|at| at <= now))
+                .collect();
+            if !expired.is_empty() {
+                let _barrier = self.replication.write_barrier().await;
+                for key in &expired {
+                    if self.delete_expired(key, now) {
+                        deleted += 1;
+                    }
+                }
+            }
+            if expired.len() * 100 <= sampled * ACTIVE_EXPIRE_ACCEPTABLE_STALE
+                || start.elapsed() >= ACTIVE_EXPIRE_CYCLE_TIME
+            {
+                break;
+            }
+        }
+        self.latency.record(LATENCY_EXPIRE_CYCLE, start.elapsed());
+        deleted
+    }
+
+    // run the active expire cycle until the task is aborted
+    pub async fn run_active_expire(self) {
+        let mut interval = tokio::time::interval(Duration::from_millis(1000 / ACTIVE_EXPIRE_HZ));
+        loop {
+            interval.tick().await;
+            self.active_expire_cycle().await;
+        }
+    }
+
+    // delete the key if it is still expired at now, false when it was given a new TTL or
+    // deleted in the meantime
+    fn delete_expired(&self, key: &Key, now: u64) -> bool {
+        if !self.remove_expired_entry(key, |entry| entry.expire.is_some_and(|at| at <= now)) {
+            return false;
+        }
+        self.expired_keys.fetch_add(1, Ordering::Relaxed);
+        self.publish_effect(RespArray::new(vec![
+            BulkString::from("del").into(),
+            key.clone().into(),
+        ]));
+        self.notify_keyspace_event(NOTIFY_EXPIRED, "expired", key);
+        true
+    }
+
+    // remove the key if f is true for its entry, checked under the entry's lock
+    fn remove_expired_entry(&self, key: &[u8], f: impl FnOnce(&Entry) -> bool) -> bool {
+        let Some(entry) = self.remove_entry_where(key, f) else {
+            return false;
+        };
+        if matches!(entry.value, Value::String(_)) {
+            self.bump_string_epoch();
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::set_mock_now_ms;
+    use crate::{PushSender, NOTIFY_KEYEVENT};
+
+    #[test]
+    fn test_expire_lazily() {
+        let now = 1_700_000_000_000;
+        set_mock_now_ms(Some(now));
+        let backend = Backend::new();
+        let key = Key::from("k");
+        assert!(!backend.expire(&key, now + 1000));
+        assert_eq!(backend.pttl(b"k"), -2);
+        backend.set(key.clone(), BulkString::from("v").into());
+        assert_eq!(backend.pttl(b"k"), -1);
+        assert_eq!(backend.pexpiretime(b"k"), -1);
+        assert!(backend.expire(&key, now + 1000));
+        assert_eq!(backend.pexpiretime(b"k"), (now + 1000) as i64);
+        assert_eq!(backend.expire_time(b"k"), Some(now + 1000));
+        assert_eq!(backend.pttl(b"k"), 1000);
+        assert_eq!(backend.volatile_keys(), 1);
+        // SET removes the TTL, and the key from the index
+        backend.set(key.clone(), BulkString::from("v").into());
+        assert_eq!(backend.volatile_keys(), 0);
+        assert!(backend.expire(&key, now + 1000));
+        assert!(backend.persist(b"k"));
+        assert!(!backend.persist(b"k"));
+        assert_eq!(backend.volatile_keys(), 0);
+        assert!(backend.expire(&key, now + 1000));
+
+        backend.expire_if_needed(std::slice::from_ref(&key));
+        assert!(backend.key_exists(b"k"));
+        set_mock_now_ms(Some(now + 1000));
+        backend.expire_if_needed(std::slice::from_ref(&key));
+        assert!(!backend.key_exists(b"k"));
+        assert_eq!(backend.expire_time(b"k"), None);
+        assert_eq!(backend.expired_keys(), 1);
+        assert_eq!(backend.volatile_keys(), 0);
+
+        // a time in the past deletes the key
+        backend.set(key.clone(), BulkString::from("v").into());
+        assert!(backend.expire(&key, now));
+        assert!(!backend.key_exists(b"k"));
+        assert!(!backend.expire(&key, now));
+
+        // a key given a new TTL since it was seen expired is kept
+        backend.set(key.clone(), BulkString::from("v").into());
+        assert!(backend.expire(&key, now + 5000));
+        assert!(!backend.delete_expired(&key, now + 2000));
+        assert!(backend.key_exists(b"k"));
+        assert_eq!(backend.expired_keys(), 1);
+        set_mock_now_ms(None);
+    }
+
+    #[tokio::test]
+    async fn test_active_expire_cycle() {
+        let now = now_ms();
+        let backend = Backend::new();
+        backend.set_notify_keyspace_events(NOTIFY_KEYEVENT | NOTIFY_EXPIRED);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        backend
+            .pubsub
+            .subscribe(b"__keyevent@0__:expired".to_vec(), 1, PushSender::new(tx));
+        for i in 0..100 {
+            let key = Key::from(format!("k{}", i).as_str());
+            backend.set(key.clone(), BulkString::from("v").into());
+            // half of them expired already, half expire much later
+            let at = if i % 2 == 0 { now - 1 } else { now + 60_000 };
+            let mut entry = backend.db.get_mut(&key).unwrap();
+            backend.set_entry_expire(&key, &mut entry, Some(at));
+        }
+
+        backend.set_active_expire(false);
+        assert_eq!(backend.active_expire_cycle().await, 0);
+        backend.set_active_expire(true);
+        // the cycle goes on while many keys are expired
+        let mut deleted = 0;
+        while deleted < 50 {
+            deleted += backend.active_expire_cycle().await;
+        }
+        assert_eq!(deleted, 50);
+        assert_eq!(backend.db.iter().filter(|e| e.expire.is_some()).count(), 50);
+        assert_eq!(backend.volatile_keys(), 50);
+        assert_eq!(backend.expired_keys(), 50);
+        assert_eq!(backend.active_expire_cycle().await, 0);
+        // an expired event for every key the cycle deleted
+        let mut events = 0;
+        while rx.try_recv().is_ok() {
+            events += 1;
+        }
+        assert_eq!(events, 50);
+    }
+}
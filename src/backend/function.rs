@@ -0,0 +1,117 @@
+// Server-side functions for embedders: named Rust functions registered through the
+// library API and called by clients with FCALL. A function gets the backend, the keys and
+// the remaining arguments, and its reply is sent to the client as is.
+
+use super::{Backend, Key};
+use crate::RespFrame;
+use dashmap::DashMap;
+use std::{fmt, sync::Arc};
+
+pub trait ServerFunction: Send + Sync {
+    fn call(&self, backend: &Backend, keys: &[Key], args: &[RespFrame]) -> RespFrame;
+}
+
+// plain closures can be registered directly
+impl<F> ServerFunction for F
+where
+    F: Fn(&Backend, &[Key], &[RespFrame]) -> RespFrame + Send + Sync,
+{
+    fn call(&self, backend: &Backend, keys: &[Key], args: &[RespFrame]) -> RespFrame {
+        self(backend, keys, args)
+    }
+}
+
+#[derive(Default)]
+pub struct Functions {
+    functions: DashMap<String, Arc<dyn ServerFunction>>,
+}
+
+impl fmt::Debug for Functions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(self.functions.iter().map(|entry| entry.key().clone()))
+            .finish()
+    }
+}
+
+impl Functions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // returns false if a function with the name already exists
+    pub fn register(&self, name: String, function: Arc<dyn ServerFunction>) -> bool {
+        match self.functions.entry(name) {
+            dashmap::mapref::entry::Entry::Occupied(_) => false,
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(function);
+                true
+            }
+        }
+    }
+
+    pub fn unregister(&self, name: &str) -> bool {
+        self.functions.remove(name).is_some()
+    }
+
+    // the function is cloned out so the registry isn't locked while it runs
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ServerFunction>> {
+        self.functions.get(name).map(|f| f.value().clone())
+    }
+}
+
+impl Backend {
+    // make the function callable with FCALL, returns false if the name is taken
+    pub fn register_function(
+        &self,
+        name: impl Into<String>,
+        function: impl ServerFunction + 'static,
+    ) -> bool {
+        self.functions.register(name.into(), Arc::new(function))
+    }
+
+    pub fn unregister_function(&self, name: &str) -> bool {
+        self.functions.unregister(name)
+    }
+
+    pub fn fcall(&self, name: &str, keys: &[Key], args: &[RespFrame]) -> Option<RespFrame> {
+        let function = self.functions.get(name)?;
+        Some(function.call(self, keys, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray};
+
+    #[test]
+    fn test_register_and_call() {
+        let backend = Backend::new();
+        let registered = backend.register_function(
+            "getall",
+            |backend: &Backend, keys: &[Key], _: &[RespFrame]| {
+                let values = keys
+                    .iter()
+                    .map(|key| backend.get(key).unwrap_or(RespFrame::Integer(0)))
+                    .collect::<Vec<_>>();
+                RespArray::new(values).into()
+            },
+        );
+        assert!(registered);
+        assert!(
+            !backend.register_function("getall", |_: &Backend, _: &[Key], _: &[RespFrame]| {
+                RespFrame::Integer(1)
+            })
+        );
+
+        backend.set("a".into(), BulkString::from("1").into());
+        assert_eq!(
+            backend.fcall("getall", &["a".into(), "b".to_string().into()], &[]),
+            Some(RespArray::new(vec![BulkString::from("1").into(), RespFrame::Integer(0)]).into())
+        );
+
+        assert!(backend.unregister_function("getall"));
+        assert_eq!(backend.fcall("getall", &[], &[]), None);
+    }
+}
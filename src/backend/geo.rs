@@ -0,0 +1,291 @@
+// Geospatial indexes, stored as sorted sets whose scores are 52 bit geohashes of the
+// members' coordinates, the same encoding as Redis: 26 bits of latitude interleaved with
+// 26 bits of longitude. Decoding returns the center of the geohash cell, so coordinates
+// come back slightly different from what was added.
+
+use super::{Backend, Key, SortedSet, ZAddCondition};
+
+pub const GEO_LONG_MIN: f64 = -180.0;
+pub const GEO_LONG_MAX: f64 = 180.0;
+pub const GEO_LAT_MIN: f64 = -85.05112878;
+pub const GEO_LAT_MAX: f64 = 85.05112878;
+const GEO_STEP: u32 = 26;
+// the earth radius used by Redis, in meters
+const EARTH_RADIUS: f64 = 6372797.560856;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GeoUnit {
+    #[default]
+    Meters,
+    Kilometers,
+    Miles,
+    Feet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    // radius in meters
+    Radius(f64),
+    // width and height in meters
+    Box(f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoSort {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoSearchOptions {
+    pub sort: Option<GeoSort>,
+    pub count: Option<usize>,
+    // stop as soon as count members are found instead of returning the closest ones
+    pub any: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch {
+    pub member: Vec<u8>,
+    // in meters
+    pub dist: f64,
+    pub hash: u64,
+    pub longitude: f64,
+    pub latitude: f64,
+}
+
+impl GeoUnit {
+    pub fn meters(&self) -> f64 {
+        match self {
+            GeoUnit::Meters => 1.0,
+            GeoUnit::Kilometers => 1000.0,
+            GeoUnit::Miles => 1609.34,
+            GeoUnit::Feet => 0.3048,
+        }
+    }
+}
+
+pub fn geo_valid(longitude: f64, latitude: f64) -> bool {
+    (GEO_LONG_MIN..=GEO_LONG_MAX).contains(&longitude)
+        && (GEO_LAT_MIN..=GEO_LAT_MAX).contains(&latitude)
+}
+
+pub fn geohash_encode(longitude: f64, latitude: f64) -> u64 {
+    let cells = (1u64 << GEO_STEP) as f64;
+    let lat = ((latitude - GEO_LAT_MIN) / (GEO_LAT_MAX - GEO_LAT_MIN) * cells) as u64;
+    let long = ((longitude - GEO_LONG_MIN) / (GEO_LONG_MAX - GEO_LONG_MIN) * cells) as u64;
+    // the maximum coordinates would fall in the cell past the last one
+    let max = (1u64 << GEO_STEP) - 1;
+    interleave(lat.min(max), long.min(max))
+}
+
+// longitude and latitude of the center of the cell
+pub fn geohash_decode(hash: u64) -> (f64, f64) {
+    let (lat, long) = deinterleave(hash);
+    let cells = (1u64 << GEO_STEP) as f64;
+    let center = |bits: u64, min: f64, max: f64| {
+        let lo = min + bits as f64 / cells * (max - min);
+        let hi = min + (bits + 1) as f64 / cells * (max - min);
+        ((lo + hi) / 2.0).clamp(min, max)
+    };
+    (
+        center(long, GEO_LONG_MIN, GEO_LONG_MAX),
+        center(lat, GEO_LAT_MIN, GEO_LAT_MAX),
+    )
+}
+
+// latitude bits go to the even positions, longitude bits to the odd ones
+fn interleave(lat: u64, long: u64) -> u64 {
+    (0..GEO_STEP).fold(0, |hash, i| {
+        hash | ((lat >> i) & 1) << (2 * i) | ((long >> i) & 1) << (2 * i + 1)
+    })
+}
+
+fn deinterleave(hash: u64) -> (u64, u64) {
+    (0..GEO_STEP).fold((0, 0), |(lat, long), i| {
+        (
+            lat | ((hash >> (2 * i)) & 1) << i,
+            long | ((hash >> (2 * i + 1)) & 1) << i,
+        )
+    })
+}
+
+// great-circle distance in meters
+pub fn geo_distance(long1: f64, lat1: f64, long2: f64, lat2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let v = ((long2 - long1).to_radians() / 2.0).sin();
+    2.0 * EARTH_RADIUS * (u * u + lat1.cos() * lat2.cos() * v * v).sqrt().asin()
+}
+
+impl GeoShape {
+    // the distance from the center if the point is within the shape
+    fn contains(&self, center: (f64, f64), point: (f64, f64)) -> Option<f64> {
+        let dist = geo_distance(center.0, center.1, point.0, point.1);
+        match *self {
+            GeoShape::Radius(radius) => (dist <= radius).then_some(dist),
+            GeoShape::Box(width, height) => {
+                // north-south distance, then east-west distance along the point's latitude
+                let lat_dist = EARTH_RADIUS * (point.1 - center.1).to_radians().abs();
+                let long_dist = geo_distance(center.0, point.1, point.0, point.1);
+                (lat_dist <= height / 2.0 && long_dist <= width / 2.0).then_some(dist)
+            }
+        }
+    }
+}
+
+impl Backend {
+    // add or update members at the given coordinates, the coordinates must be valid
+    pub fn geoadd(
+        &self,
+        key: Key,
+        members: Vec<(f64, f64, Vec<u8>)>,
+        condition: ZAddCondition,
+        ch: bool,
+    ) -> usize {
+        let members = members
+            .into_iter()
+            .map(|(long, lat, member)| (geohash_encode(long, lat) as f64, member))
+            .collect();
+        self.zadd(key, members, condition, ch)
+    }
+
+    pub fn geopos(&self, key: &[u8], member: &[u8]) -> Option<(f64, f64)> {
+        self.zscore(key, member)
+            .map(|score| geohash_decode(score as u64))
+    }
+
+    // distance in meters between two members
+    pub fn geodist(&self, key: &[u8], member1: &[u8], member2: &[u8]) -> Option<f64> {
+        let (long1, lat1) = self.geopos(key, member1)?;
+        let (long2, lat2) = self.geopos(key, member2)?;
+        Some(geo_distance(long1, lat1, long2, lat2))
+    }
+
+    // members within the shape around center. Every member is checked, which is fine for
+    // the sizes this server is meant for.
+    pub fn geosearch(
+        &self,
+        key: &[u8],
+        center: (f64, f64),
+        shape: GeoShape,
+        options: &GeoSearchOptions,
+    ) -> Vec<GeoMatch> {
+        let Some(zset) = self.value::<SortedSet>(key) else {
+            return Vec::new();
+        };
+        let mut matches = Vec::new();
+        for (member, score) in zset.iter() {
+            let hash = score as u64;
+            let (longitude, latitude) = geohash_decode(hash);
+            if let Some(dist) = shape.contains(center, (longitude, latitude)) {
+                matches.push(GeoMatch {
+                    member: member.to_vec(),
+                    dist,
+                    hash,
+                    longitude,
+                    latitude,
+                });
+                if options.any && Some(matches.len()) == options.count {
+                    break;
+                }
+            }
+        }
+        drop(zset);
+
+        // COUNT without ANY returns the closest members
+        let sort = match (options.sort, options.count, options.any) {
+            (None, Some(_), false) => Some(GeoSort::Asc),
+            (sort, _, _) => sort,
+        };
+        match sort {
+            Some(GeoSort::Asc) => matches.sort_by(|a, b| a.dist.total_cmp(&b.dist)),
+            Some(GeoSort::Desc) => matches.sort_by(|a, b| b.dist.total_cmp(&a.dist)),
+            None => {}
+        }
+        if let Some(count) = options.count {
+            matches.truncate(count);
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sicily(backend: &Backend) {
+        backend.geoadd(
+            "Sicily".into(),
+            vec![
+                (13.361389, 38.115556, b"Palermo".to_vec()),
+                (15.087269, 37.502669, b"Catania".to_vec()),
+            ],
+            ZAddCondition::Always,
+            false,
+        );
+    }
+
+    #[test]
+    fn test_geohash() {
+        // the score Redis stores for Palermo
+        assert_eq!(geohash_encode(13.361389, 38.115556), 3479099956230698);
+        let (long, lat) = geohash_decode(3479099956230698);
+        assert!((long - 13.361389).abs() < 1e-5);
+        assert!((lat - 38.115556).abs() < 1e-5);
+
+        assert!(geo_valid(180.0, GEO_LAT_MAX));
+        assert!(!geo_valid(180.1, 0.0));
+        assert!(!geo_valid(0.0, 86.0));
+    }
+
+    #[test]
+    fn test_geodist() {
+        let backend = Backend::new();
+        sicily(&backend);
+        let dist = backend.geodist(b"Sicily", b"Palermo", b"Catania").unwrap();
+        assert!((dist - 166274.1516).abs() < 0.01);
+        assert_eq!(backend.geodist(b"Sicily", b"Palermo", b"Rome"), None);
+    }
+
+    #[test]
+    fn test_geosearch() {
+        let backend = Backend::new();
+        sicily(&backend);
+        let options = GeoSearchOptions {
+            sort: Some(GeoSort::Asc),
+            count: None,
+            any: false,
+        };
+        let members =
+            |matches: Vec<GeoMatch>| matches.into_iter().map(|m| m.member).collect::<Vec<_>>();
+
+        let found = backend.geosearch(
+            b"Sicily",
+            (15.0, 37.0),
+            GeoShape::Radius(200.0 * 1000.0),
+            &options,
+        );
+        assert_eq!(members(found), [b"Catania", b"Palermo"]);
+
+        let found = backend.geosearch(
+            b"Sicily",
+            (15.0, 37.0),
+            GeoShape::Radius(100.0 * 1000.0),
+            &options,
+        );
+        assert_eq!(members(found), [b"Catania"]);
+
+        let found = backend.geosearch(
+            b"Sicily",
+            (15.0, 37.0),
+            GeoShape::Box(400.0 * 1000.0, 400.0 * 1000.0),
+            &GeoSearchOptions {
+                sort: Some(GeoSort::Desc),
+                count: Some(1),
+                any: false,
+            },
+        );
+        assert_eq!(members(found), [b"Palermo"]);
+    }
+}
@@ -0,0 +1,150 @@
+// glob-style pattern matching with the same rules as Redis' stringmatchlen:
+// `*`, `?`, `[abc]`, `[^abc]`, `[a-z]` and `\` to escape a special character
+pub fn glob_match(pattern: &[u8], string: &[u8], nocase: bool) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // position in the pattern after the last `*` and the position in string it matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < string.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                b'*' => {
+                    while p < pattern.len() && pattern[p] == b'*' {
+                        p += 1;
+                    }
+                    if p == pattern.len() {
+                        return true;
+                    }
+                    backtrack = Some((p, s));
+                    continue;
+                }
+                b'?' => {
+                    p += 1;
+                    s += 1;
+                    continue;
+                }
+                b'[' => {
+                    let (matched, next) = match_class(pattern, p + 1, string[s], nocase);
+                    if matched {
+                        p = next;
+                        s += 1;
+                        continue;
+                    }
+                }
+                b'\\' if p + 1 < pattern.len() => {
+                    if byte_eq(pattern[p + 1], string[s], nocase) {
+                        p += 2;
+                        s += 1;
+                        continue;
+                    }
+                }
+                c => {
+                    if byte_eq(c, string[s], nocase) {
+                        p += 1;
+                        s += 1;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        match backtrack {
+            Some((bp, bs)) => {
+                p = bp;
+                s = bs + 1;
+                backtrack = Some((bp, bs + 1));
+            }
+            None => return false,
+        }
+    }
+
+    while p < pattern.len() && pattern[p] == b'*' {
+        p += 1;
+    }
+    p == pattern.len()
+}
+
+// match a character class starting right after `[`, returns whether it matched
+// and the position in the pattern right after the closing `]`
+fn match_class(pattern: &[u8], mut p: usize, c: u8, nocase: bool) -> (bool, usize) {
+    let not = p < pattern.len() && pattern[p] == b'^';
+    if not {
+        p += 1;
+    }
+
+    let mut matched = false;
+    while p < pattern.len() && pattern[p] != b']' {
+        if pattern[p] == b'\\' && p + 1 < pattern.len() {
+            p += 1;
+            matched |= byte_eq(pattern[p], c, nocase);
+        } else if p + 2 < pattern.len() && pattern[p + 1] == b'-' && pattern[p + 2] != b']' {
+            let (mut start, mut end) = (pattern[p], pattern[p + 2]);
+            if start > end {
+                std::mem::swap(&mut start, &mut end);
+            }
+            let c = if nocase { c.to_ascii_lowercase() } else { c };
+            let (start, end) = if nocase {
+                (start.to_ascii_lowercase(), end.to_ascii_lowercase())
+            } else {
+                (start, end)
+            };
+            matched |= c >= start && c <= end;
+            p += 2;
+        } else {
+            matched |= byte_eq(pattern[p], c, nocase);
+        }
+        p += 1;
+    }
+
+    // an unterminated class is closed by the end of the pattern
+    (matched != not, (p + 1).min(pattern.len()))
+}
+
+fn byte_eq(a: u8, b: u8, nocase: bool) -> bool {
+    if nocase {
+        a.eq_ignore_ascii_case(&b)
+    } else {
+        a == b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match_wildcards() {
+        assert!(glob_match(b"*", b"anything", false));
+        assert!(glob_match(b"*", b"", false));
+        assert!(glob_match(b"h?llo", b"hello", false));
+        assert!(!glob_match(b"h?llo", b"hllo", false));
+        assert!(glob_match(b"h*llo", b"heeeello", false));
+        assert!(glob_match(
+            b"__keyspace@0__:*",
+            b"__keyspace@0__:foo",
+            false
+        ));
+        assert!(!glob_match(b"foo*bar", b"foobaz", false));
+        assert!(glob_match(b"*a*b*", b"xaybz", false));
+    }
+
+    #[test]
+    fn test_glob_match_classes() {
+        assert!(glob_match(b"h[ae]llo", b"hello", false));
+        assert!(glob_match(b"h[ae]llo", b"hallo", false));
+        assert!(!glob_match(b"h[ae]llo", b"hillo", false));
+        assert!(glob_match(b"h[^e]llo", b"hallo", false));
+        assert!(!glob_match(b"h[^e]llo", b"hello", false));
+        assert!(glob_match(b"h[a-b]llo", b"hbllo", false));
+        assert!(glob_match(b"h[b-a]llo", b"hallo", false));
+        assert!(!glob_match(b"h[a-b]llo", b"hcllo", false));
+    }
+
+    #[test]
+    fn test_glob_match_escape_and_case() {
+        assert!(glob_match(b"h\\*llo", b"h*llo", false));
+        assert!(!glob_match(b"h\\*llo", b"hello", false));
+        assert!(glob_match(b"SLOWLOG-*", b"slowlog-max-len", true));
+        assert!(!glob_match(b"SLOWLOG-*", b"slowlog-max-len", false));
+    }
+}
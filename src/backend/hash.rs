@@ -0,0 +1,176 @@
+// Hashes with few small fields are kept in the listpack encoding, the fields and values in a
+// vector that is searched linearly, like sets in set.rs. A hash is converted to the
+// hashtable encoding once it has more than hash-max-listpack-entries fields or gets a field
+// or value longer than hash-max-listpack-value bytes, and is never converted back. Both
+// keep the fields in the order they were first set.
+
+use super::ListpackLimits;
+use crate::{RespFrame, RespMap};
+
+pub const DEFAULT_HASH_MAX_LISTPACK_ENTRIES: usize = 128;
+pub const DEFAULT_HASH_MAX_LISTPACK_VALUE: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashEncoding {
+    Listpack,
+    Hashtable,
+}
+
+#[derive(Debug, Clone)]
+enum Fields {
+    Listpack(Vec<(RespFrame, RespFrame)>),
+    Hashtable(RespMap),
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldMap {
+    fields: Fields,
+}
+
+impl HashEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashEncoding::Listpack => "listpack",
+            HashEncoding::Hashtable => "hashtable",
+        }
+    }
+}
+
+impl Default for FieldMap {
+    fn default() -> Self {
+        FieldMap {
+            fields: Fields::Listpack(Vec::new()),
+        }
+    }
+}
+
+impl FieldMap {
+    pub fn from_entries(
+        entries: impl IntoIterator<Item = (RespFrame, RespFrame)>,
+        limits: ListpackLimits,
+    ) -> Self {
+        let mut hash = FieldMap::default();
+        for (field, value) in entries {
+            hash.insert(field, value, limits);
+        }
+        hash
+    }
+
+    pub fn encoding(&self) -> HashEncoding {
+        match self.fields {
+            Fields::Listpack(_) => HashEncoding::Listpack,
+            Fields::Hashtable(_) => HashEncoding::Hashtable,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.fields {
+            Fields::Listpack(fields) => fields.len(),
+            Fields::Hashtable(fields) => fields.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, field: &RespFrame) -> Option<&RespFrame> {
+        match &self.fields {
+            Fields::Listpack(fields) => fields.iter().find(|(f, _)| f == field).map(|(_, v)| v),
+            Fields::Hashtable(fields) => fields.get(field),
+        }
+    }
+
+    pub fn contains_key(&self, field: &RespFrame) -> bool {
+        self.get(field).is_some()
+    }
+
+    // a field that is already there keeps its position and gets the new value, the old one
+    // is returned
+    pub fn insert(
+        &mut self,
+        field: impl Into<RespFrame>,
+        value: RespFrame,
+        limits: ListpackLimits,
+    ) -> Option<RespFrame> {
+        let field = field.into();
+        if let Fields::Listpack(fields) = &mut self.fields {
+            let fits = limits.fits(&field) && limits.fits(&value);
+            match fields.iter().position(|(f, _)| *f == field) {
+                Some(i) if fits => return Some(std::mem::replace(&mut fields[i].1, value)),
+                None if fits && fields.len() < limits.max_entries => {
+                    fields.push((field, value));
+                    return None;
+                }
+                _ => self.fields = Fields::Hashtable(std::mem::take(fields).into_iter().collect()),
+            }
+        }
+        match &mut self.fields {
+            Fields::Hashtable(fields) => fields.insert(field, value),
+            Fields::Listpack(_) => unreachable!("converted above"),
+        }
+    }
+
+    // the entry at a position in the order the fields were first set
+    pub fn get_index(&self, index: usize) -> Option<(&RespFrame, &RespFrame)> {
+        match &self.fields {
+            Fields::Listpack(fields) => fields.get(index).map(|(field, value)| (field, value)),
+            Fields::Hashtable(fields) => fields.get_index(index),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (&RespFrame, &RespFrame)> + '_> {
+        match &self.fields {
+            Fields::Listpack(fields) => {
+                Box::new(fields.iter().map(|(field, value)| (field, value)))
+            }
+            Fields::Hashtable(fields) => Box::new(fields.iter()),
+        }
+    }
+
+    pub fn to_map(&self) -> RespMap {
+        match &self.fields {
+            Fields::Listpack(fields) => fields.iter().cloned().collect(),
+            Fields::Hashtable(fields) => fields.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_hash_encoding_conversion() {
+        let limits = ListpackLimits {
+            max_entries: 2,
+            max_value: 4,
+        };
+        let frame = |s: &str| RespFrame::from(BulkString::from(s));
+
+        let mut hash = FieldMap::default();
+        assert_eq!(hash.insert(frame("a"), frame("1"), limits), None);
+        assert_eq!(hash.insert(frame("b"), frame("2"), limits), None);
+        assert_eq!(
+            hash.insert(frame("a"), frame("3"), limits),
+            Some(frame("1"))
+        );
+        assert_eq!(hash.encoding(), HashEncoding::Listpack);
+        assert_eq!(hash.insert(frame("c"), frame("4"), limits), None);
+        assert_eq!(hash.encoding(), HashEncoding::Hashtable);
+        assert_eq!(hash.get(&frame("a")), Some(&frame("3")));
+        // the fields keep their order through the conversion
+        let fields: Vec<_> = hash.iter().map(|(field, _)| field.clone()).collect();
+        assert_eq!(fields, [frame("a"), frame("b"), frame("c")]);
+
+        // so does a long value replacing the one of a field
+        let mut hash = FieldMap::from_entries([(frame("a"), frame("1"))], limits);
+        assert_eq!(
+            hash.insert(frame("a"), frame("toolong"), limits),
+            Some(frame("1"))
+        );
+        assert_eq!(hash.encoding(), HashEncoding::Hashtable);
+        assert_eq!(hash.len(), 1);
+    }
+}
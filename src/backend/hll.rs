@@ -0,0 +1,351 @@
+// HyperLogLog with the same parameters as Redis: 2^14 registers, 6 bits of
+// rank per register, MurmurHash64A as the hash function.
+//
+// Small counters start in a sparse encoding that only stores the registers
+// which are not zero, and are promoted to the dense encoding once the sparse
+// form would grow beyond `HLL_SPARSE_MAX_BYTES`.
+
+const HLL_P: u32 = 14;
+const HLL_Q: u32 = 64 - HLL_P;
+const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_P_MASK: u64 = (HLL_REGISTERS - 1) as u64;
+const HLL_ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+const HLL_HASH_SEED: u64 = 0xadc8_3b19;
+
+// bytes used by one sparse entry: 2 bytes of register index, 1 byte of rank
+const HLL_SPARSE_ENTRY_BYTES: usize = 3;
+pub const HLL_SPARSE_MAX_BYTES: usize = 3000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HllEncoding {
+    Sparse,
+    Dense,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Registers {
+    // (register index, rank) pairs sorted by index, zero registers are omitted
+    Sparse(Vec<(u16, u8)>),
+    Dense(Box<[u8]>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Registers,
+    sparse_max_bytes: usize,
+}
+
+impl HllEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HllEncoding::Sparse => "sparse",
+            HllEncoding::Dense => "dense",
+        }
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        Self::with_sparse_max_bytes(HLL_SPARSE_MAX_BYTES)
+    }
+
+    pub fn with_sparse_max_bytes(sparse_max_bytes: usize) -> Self {
+        HyperLogLog {
+            registers: Registers::Sparse(Vec::new()),
+            sparse_max_bytes,
+        }
+    }
+
+    pub fn encoding(&self) -> HllEncoding {
+        match self.registers {
+            Registers::Sparse(_) => HllEncoding::Sparse,
+            Registers::Dense(_) => HllEncoding::Dense,
+        }
+    }
+
+    // Approximate number of bytes used by the registers.
+    pub fn size_in_bytes(&self) -> usize {
+        match &self.registers {
+            Registers::Sparse(entries) => entries.len() * HLL_SPARSE_ENTRY_BYTES,
+            Registers::Dense(regs) => regs.len(),
+        }
+    }
+
+    // Add an element, returns true if any register was changed.
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let (index, rank) = hll_pattern(element);
+        self.set_register(index, rank)
+    }
+
+    // Merge the registers of `other` into self, keeping the max of each register.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        if other.encoding() == HllEncoding::Dense {
+            self.promote();
+        }
+        match &other.registers {
+            Registers::Sparse(entries) => {
+                for &(index, rank) in entries {
+                    self.set_register(index as usize, rank);
+                }
+            }
+            Registers::Dense(regs) => {
+                for (index, &rank) in regs.iter().enumerate() {
+                    if rank > 0 {
+                        self.set_register(index, rank);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        let mut histogram = [0u32; HLL_Q as usize + 2];
+        match &self.registers {
+            Registers::Sparse(entries) => {
+                histogram[0] = (HLL_REGISTERS - entries.len()) as u32;
+                for &(_, rank) in entries {
+                    histogram[rank as usize] += 1;
+                }
+            }
+            Registers::Dense(regs) => {
+                for &rank in regs.iter() {
+                    histogram[rank as usize] += 1;
+                }
+            }
+        }
+
+        // the improved estimator from Otmar Ertl's paper, as used by Redis
+        let m = HLL_REGISTERS as f64;
+        let q = HLL_Q as usize;
+        let mut z = m * hll_tau((m - histogram[q + 1] as f64) / m);
+        for j in (1..=q).rev() {
+            z += histogram[j] as f64;
+            z *= 0.5;
+        }
+        z += m * hll_sigma(histogram[0] as f64 / m);
+        (HLL_ALPHA_INF * m * m / z).round() as u64
+    }
+
+    // Convert to the dense encoding, does nothing if already dense.
+    pub fn promote(&mut self) {
+        if let Registers::Sparse(entries) = &self.registers {
+            let mut regs = vec![0u8; HLL_REGISTERS].into_boxed_slice();
+            for &(index, rank) in entries {
+                regs[index as usize] = rank;
+            }
+            self.registers = Registers::Dense(regs);
+        }
+    }
+
+    pub fn registers(&self) -> Vec<u8> {
+        match &self.registers {
+            Registers::Sparse(entries) => {
+                let mut regs = vec![0u8; HLL_REGISTERS];
+                for &(index, rank) in entries {
+                    regs[index as usize] = rank;
+                }
+                regs
+            }
+            Registers::Dense(regs) => regs.to_vec(),
+        }
+    }
+
+    // Rebuild a counter from the output of `registers`, None if the length is wrong.
+    pub fn from_registers(registers: &[u8], encoding: HllEncoding) -> Option<Self> {
+        if registers.len() != HLL_REGISTERS {
+            return None;
+        }
+        let mut hll = HyperLogLog::new();
+        if encoding == HllEncoding::Dense {
+            hll.promote();
+        }
+        for (index, &rank) in registers.iter().enumerate() {
+            if rank > 0 {
+                hll.set_register(index, rank);
+            }
+        }
+        Some(hll)
+    }
+
+    fn set_register(&mut self, index: usize, rank: u8) -> bool {
+        match &mut self.registers {
+            Registers::Sparse(entries) => {
+                match entries.binary_search_by_key(&(index as u16), |&(i, _)| i) {
+                    Ok(pos) => {
+                        if entries[pos].1 >= rank {
+                            return false;
+                        }
+                        entries[pos].1 = rank;
+                    }
+                    Err(pos) => {
+                        entries.insert(pos, (index as u16, rank));
+                        if entries.len() * HLL_SPARSE_ENTRY_BYTES > self.sparse_max_bytes {
+                            self.promote();
+                        }
+                    }
+                }
+                true
+            }
+            Registers::Dense(regs) => {
+                if regs[index] >= rank {
+                    return false;
+                }
+                regs[index] = rank;
+                true
+            }
+        }
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog::new()
+    }
+}
+
+// return the register index and the rank (number of trailing zeros + 1) for an element
+fn hll_pattern(element: &[u8]) -> (usize, u8) {
+    let hash = murmur_hash64a(element, HLL_HASH_SEED);
+    let index = (hash & HLL_P_MASK) as usize;
+    // set the bit after the last usable one so the count is bounded to Q + 1
+    let hash = (hash >> HLL_P) | (1 << HLL_Q);
+    (index, hash.trailing_zeros() as u8 + 1)
+}
+
+fn hll_sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let z_prime = z;
+        z += x * y;
+        y += y;
+        if z_prime == z {
+            return z;
+        }
+    }
+}
+
+fn hll_tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let z_prime = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if z_prime == z {
+            return z / 3.0;
+        }
+    }
+}
+
+// MurmurHash2, 64-bit version by Austin Appleby, same as the one used by Redis
+fn murmur_hash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().expect("chunk is 8 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, &b) in rest.iter().enumerate() {
+            h ^= (b as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hll_count_small() {
+        let mut hll = HyperLogLog::new();
+        assert_eq!(hll.count(), 0);
+        assert!(hll.add(b"a"));
+        assert!(hll.add(b"b"));
+        assert!(hll.add(b"c"));
+        assert!(!hll.add(b"a"));
+        assert_eq!(hll.count(), 3);
+        assert_eq!(hll.encoding(), HllEncoding::Sparse);
+    }
+
+    #[test]
+    fn test_hll_promote_to_dense() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..10000 {
+            hll.add(format!("element:{}", i).as_bytes());
+        }
+        assert_eq!(hll.encoding(), HllEncoding::Dense);
+
+        let count = hll.count() as f64;
+        assert!((count - 10000.0).abs() / 10000.0 < 0.02, "count: {}", count);
+    }
+
+    #[test]
+    fn test_hll_sparse_and_dense_agree() {
+        let mut sparse = HyperLogLog::new();
+        for i in 0..500 {
+            sparse.add(format!("{}", i).as_bytes());
+        }
+        assert_eq!(sparse.encoding(), HllEncoding::Sparse);
+
+        let mut dense = sparse.clone();
+        dense.promote();
+        assert_eq!(dense.encoding(), HllEncoding::Dense);
+        assert_eq!(sparse.count(), dense.count());
+        assert_eq!(sparse.registers(), dense.registers());
+    }
+
+    #[test]
+    fn test_hll_merge() {
+        let mut a = HyperLogLog::new();
+        let mut b = HyperLogLog::new();
+        for i in 0..100 {
+            a.add(format!("{}", i).as_bytes());
+            b.add(format!("{}", i + 50).as_bytes());
+        }
+        a.merge(&b);
+        let count = a.count() as f64;
+        assert!((count - 150.0).abs() / 150.0 < 0.02, "count: {}", count);
+    }
+
+    #[test]
+    fn test_hll_from_registers() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..100 {
+            hll.add(format!("{}", i).as_bytes());
+        }
+        let restored = HyperLogLog::from_registers(&hll.registers(), hll.encoding()).unwrap();
+        assert_eq!(restored, hll);
+        assert!(HyperLogLog::from_registers(&[0; 10], HllEncoding::Dense).is_none());
+    }
+
+    #[test]
+    fn test_murmur_hash64a() {
+        assert_eq!(murmur_hash64a(b"", 0), 0);
+        assert_ne!(murmur_hash64a(b"hello", 0), murmur_hash64a(b"hello", 1));
+    }
+}
@@ -0,0 +1,126 @@
+// Keys are binary safe like in Redis, any bytes a bulk string can hold. Maps are looked up
+// by &[u8] through Borrow, and Display shows the key lossily for logs and messages.
+
+use crate::{BulkString, RespFrame};
+use bytes::Bytes;
+use std::{borrow::Borrow, fmt, ops::Deref};
+
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key(Bytes);
+
+impl Key {
+    pub fn new(key: impl Into<Bytes>) -> Self {
+        Key(key.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Deref for Key {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Key {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+// Bytes hashes and compares like its slice, so maps of keys can be queried by &[u8]
+impl Borrow<[u8]> for Key {
+    fn borrow(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl From<&str> for Key {
+    fn from(key: &str) -> Self {
+        Key(Bytes::copy_from_slice(key.as_bytes()))
+    }
+}
+
+impl From<String> for Key {
+    fn from(key: String) -> Self {
+        Key(Bytes::from(key))
+    }
+}
+
+impl From<&[u8]> for Key {
+    fn from(key: &[u8]) -> Self {
+        Key(Bytes::copy_from_slice(key))
+    }
+}
+
+impl From<Vec<u8>> for Key {
+    fn from(key: Vec<u8>) -> Self {
+        Key(Bytes::from(key))
+    }
+}
+
+impl From<BulkString> for Key {
+    fn from(key: BulkString) -> Self {
+        Key(key.0)
+    }
+}
+
+impl From<Key> for BulkString {
+    fn from(key: Key) -> Self {
+        BulkString::from(key.0)
+    }
+}
+
+impl From<Key> for RespFrame {
+    fn from(key: Key) -> Self {
+        BulkString::from(key).into()
+    }
+}
+
+impl PartialEq<str> for Key {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for Key {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dashmap::DashMap;
+
+    #[test]
+    fn test_binary_key() {
+        let key = Key::from(vec![0xff, 0x00, b'a']);
+        let map = DashMap::new();
+        map.insert(key.clone(), 1);
+        assert_eq!(map.get(&[0xff, 0x00, b'a'][..]).map(|v| *v), Some(1));
+        assert_eq!(key.to_string(), "\u{fffd}\u{0}a");
+        assert_eq!(
+            RespFrame::from(key),
+            BulkString::new(vec![0xff, 0x00, b'a']).into()
+        );
+        assert_eq!(Key::from("k"), "k");
+    }
+}
@@ -0,0 +1,105 @@
+// Locks on keys for operations on several keys at once. A single key is safe under the
+// lock of its shard, but an operation that reads one key and writes another (COPY, PFMERGE,
+// RESTORE's check-then-write) takes the shard locks one after the other, and two of them on
+// the same keys could interleave. with_keys_locked holds a lock for every key while the
+// operation runs, so those operations are atomic with respect to each other.
+//
+// The locks are a fixed set of stripes, a key locks the stripe its hash falls in. Stripes
+// are always taken in ascending order, so two operations can't each hold a lock the other
+// waits for. Commands on one key don't take them, they don't need to.
+
+use super::Backend;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{Mutex, MutexGuard},
+};
+
+const KEY_LOCK_STRIPES: usize = 1024;
+
+#[derive(Debug)]
+pub(crate) struct KeyLocks {
+    stripes: Vec<Mutex<()>>,
+}
+
+impl Default for KeyLocks {
+    fn default() -> Self {
+        KeyLocks {
+            stripes: (0..KEY_LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+        }
+    }
+}
+
+impl KeyLocks {
+    fn stripe(&self, key: &[u8]) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish() as usize % self.stripes.len()
+    }
+
+    // the stripes of the keys, each once and in ascending order
+    fn lock(&self, keys: &[&[u8]]) -> Vec<MutexGuard<'_, ()>> {
+        let mut stripes: Vec<usize> = keys.iter().map(|key| self.stripe(key)).collect();
+        stripes.sort_unstable();
+        stripes.dedup();
+        stripes
+            .into_iter()
+            // the lock guards no data, a panic while holding it leaves nothing inconsistent
+            .map(|stripe| {
+                self.stripes[stripe]
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+            })
+            .collect()
+    }
+}
+
+impl Backend {
+    // run f with the keys locked against the other operations that lock any of them. f must
+    // not lock keys itself, a stripe is not reentrant.
+    pub fn with_keys_locked<K: AsRef<[u8]>, T>(&self, keys: &[K], f: impl FnOnce() -> T) -> T {
+        let keys: Vec<&[u8]> = keys.iter().map(|key| key.as_ref()).collect();
+        let _guards = self.key_locks.lock(&keys);
+        f()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    #[test]
+    fn test_with_keys_locked() {
+        let backend = Backend::new();
+        // the same key twice is one stripe, locked once
+        assert_eq!(backend.with_keys_locked(&["a", "a", "b"], || 1), 1);
+
+        // moves in opposite directions over the same keys neither deadlock nor interleave
+        let inside = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let backend = backend.clone();
+                let inside = inside.clone();
+                thread::spawn(move || {
+                    let keys = if i % 2 == 0 { ["a", "b"] } else { ["b", "a"] };
+                    for _ in 0..100 {
+                        backend.with_keys_locked(&keys, || {
+                            assert_eq!(inside.fetch_add(1, Ordering::SeqCst), 0);
+                            inside.fetch_sub(1, Ordering::SeqCst);
+                        });
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+}
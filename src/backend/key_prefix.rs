@@ -0,0 +1,164 @@
+// Key counts grouped by prefix, to attribute keyspace growth to the features writing the
+// keys without scanning the keyspace. A key's prefix is its first key-prefix-segments
+// segments separated by key-prefix-separator, keys with fewer segments count under "".
+//
+// The counts follow the key tracking of eviction.rs: a key is counted when a command
+// creates it and uncounted when one deletes it or it is evicted. Like the size estimates
+// they miss writes made through the Backend API directly, so they are approximate.
+
+use super::Backend;
+use dashmap::DashMap;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+};
+
+const DEFAULT_SEPARATOR: &str = ":";
+const DEFAULT_SEGMENTS: usize = 1;
+
+#[derive(Debug)]
+pub struct KeyPrefixes {
+    separator: RwLock<String>,
+    // 0 disables the counts
+    segments: AtomicUsize,
+    counts: DashMap<String, usize>,
+}
+
+impl KeyPrefixes {
+    pub fn new() -> Self {
+        KeyPrefixes {
+            separator: RwLock::new(DEFAULT_SEPARATOR.to_string()),
+            segments: AtomicUsize::new(DEFAULT_SEGMENTS),
+            counts: DashMap::new(),
+        }
+    }
+
+    pub fn separator(&self) -> String {
+        self.separator.read().unwrap().clone()
+    }
+
+    pub fn segments(&self) -> usize {
+        self.segments.load(Ordering::Relaxed)
+    }
+
+    // the prefix key is counted under, None when the counts are disabled. Binary prefixes
+    // are shown lossily, like keys in logs.
+    pub fn prefix_of(&self, key: &[u8]) -> Option<String> {
+        let segments = self.segments();
+        if segments == 0 {
+            return None;
+        }
+        let separator = self.separator.read().unwrap();
+        let separator = separator.as_bytes();
+        let mut found = 0;
+        let mut start = 0;
+        while !separator.is_empty() && start + separator.len() <= key.len() {
+            if key[start..].starts_with(separator) {
+                found += 1;
+                if found == segments {
+                    return Some(String::from_utf8_lossy(&key[..start]).into_owned());
+                }
+                start += separator.len();
+            } else {
+                start += 1;
+            }
+        }
+        Some(String::new())
+    }
+
+    pub fn counts(&self) -> BTreeMap<String, usize> {
+        self.counts
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect()
+    }
+
+    pub(crate) fn key_added(&self, key: &[u8]) {
+        if let Some(prefix) = self.prefix_of(key) {
+            *self.counts.entry(prefix).or_default() += 1;
+        }
+    }
+
+    pub(crate) fn key_removed(&self, key: &[u8]) {
+        if let Some(prefix) = self.prefix_of(key) {
+            self.counts.remove_if_mut(&prefix, |_, count| {
+                *count = count.saturating_sub(1);
+                *count == 0
+            });
+        }
+    }
+}
+
+impl Default for KeyPrefixes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend {
+    pub fn set_key_prefix_separator(&self, separator: String) {
+        *self.key_prefixes.separator.write().unwrap() = separator;
+        self.recount_key_prefixes();
+    }
+
+    pub fn set_key_prefix_segments(&self, segments: usize) {
+        self.key_prefixes
+            .segments
+            .store(segments, Ordering::Relaxed);
+        self.recount_key_prefixes();
+    }
+
+    // count the tracked keys again after the rule changed
+    pub(crate) fn recount_key_prefixes(&self) {
+        self.key_prefixes.counts.clear();
+        for entry in self.db.iter().filter(|entry| entry.meta.is_some()) {
+            self.key_prefixes.key_added(entry.key());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, Key};
+
+    #[test]
+    fn test_prefix_of() {
+        let prefixes = KeyPrefixes::new();
+        assert_eq!(prefixes.prefix_of(b"user:1:name"), Some("user".to_string()));
+        assert_eq!(prefixes.prefix_of(b"counter"), Some(String::new()));
+        prefixes.segments.store(2, Ordering::Relaxed);
+        assert_eq!(
+            prefixes.prefix_of(b"user:1:name"),
+            Some("user:1".to_string())
+        );
+        assert_eq!(prefixes.prefix_of(b"user:1"), Some(String::new()));
+        prefixes.segments.store(0, Ordering::Relaxed);
+        assert_eq!(prefixes.prefix_of(b"user:1"), None);
+    }
+
+    #[test]
+    fn test_key_prefix_counts() {
+        let backend = Backend::new();
+        let keys = |keys: &[&str]| keys.iter().map(|k| Key::from(*k)).collect::<Vec<_>>();
+        for key in ["user:1", "user:2", "session/a", "user:1"] {
+            backend.set(key.into(), BulkString::from("v").into());
+            backend.record_key_access(&keys(&[key]), true);
+        }
+        let counts = backend.key_prefixes.counts();
+        assert_eq!(counts["user"], 2);
+        assert_eq!(counts[""], 1);
+
+        backend.del(&keys(&["user:1"]));
+        backend.record_key_access(&keys(&["user:1"]), true);
+        assert_eq!(backend.key_prefixes.counts()["user"], 1);
+
+        backend.set_key_prefix_separator("/".to_string());
+        let counts = backend.key_prefixes.counts();
+        assert_eq!(counts["session"], 1);
+        assert_eq!(counts[""], 1);
+    }
+}
@@ -0,0 +1,177 @@
+// Per-key access counts, to find the hot keys from the server itself. Counting every
+// access of every key would cost a table entry per key, so the counts are sampled: one
+// command in key-stats-sample-rate is counted, 0 (the default) counts none. The counts are
+// those of the sampled commands, they rank the keys rather than count their accesses.
+//
+// A read that finds its key is a hit, one that doesn't a miss, writes are counted apart.
+// Misses count keys that don't exist, so the table is capped at KEY_STATS_MAX_KEYS: a full
+// table makes room by dropping the least accessed of a few keys, like eviction samples.
+
+use super::{eviction::random, Backend, Key};
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const KEY_STATS_MAX_KEYS: usize = 10_000;
+const KEY_STATS_EVICTION_SAMPLES: usize = 5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyAccessCounts {
+    pub hits: u64,
+    pub misses: u64,
+    pub writes: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct KeyStats {
+    // one command in sample_rate is counted, 0 for none
+    sample_rate: AtomicU64,
+    counter: AtomicU64,
+    counts: DashMap<Key, KeyAccessCounts>,
+}
+
+impl KeyAccessCounts {
+    pub fn accesses(&self) -> u64 {
+        self.hits + self.misses + self.writes
+    }
+}
+
+impl KeyStats {
+    pub fn sample_rate(&self) -> u64 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    pub fn set_sample_rate(&self, rate: u64) {
+        self.sample_rate.store(rate, Ordering::Relaxed);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    // whether the next command is counted
+    pub(crate) fn sample(&self) -> bool {
+        match self.sample_rate() {
+            0 => false,
+            1 => true,
+            rate => self
+                .counter
+                .fetch_add(1, Ordering::Relaxed)
+                .is_multiple_of(rate),
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<KeyAccessCounts> {
+        self.counts.get(key).map(|counts| *counts)
+    }
+
+    // the count most accessed keys, the most accessed first
+    pub fn top(&self, count: usize) -> Vec<(Key, KeyAccessCounts)> {
+        let mut keys: Vec<(Key, KeyAccessCounts)> = self
+            .counts
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect();
+        keys.sort_by(|(a_key, a), (b_key, b)| {
+            b.accesses()
+                .cmp(&a.accesses())
+                .then_with(|| a_key.cmp(b_key))
+        });
+        keys.truncate(count);
+        keys
+    }
+
+    pub fn reset(&self) {
+        self.counts.clear();
+    }
+
+    fn record(&self, key: &Key, f: impl FnOnce(&mut KeyAccessCounts)) {
+        if let Some(mut counts) = self.counts.get_mut(key) {
+            f(&mut counts);
+            return;
+        }
+        if self.counts.len() >= KEY_STATS_MAX_KEYS {
+            self.drop_least_accessed();
+        }
+        f(&mut self.counts.entry(key.clone()).or_default());
+    }
+
+    fn drop_least_accessed(&self) {
+        let len = self.counts.len();
+        if len == 0 {
+            return;
+        }
+        let victim = self
+            .counts
+            .iter()
+            .skip(random() as usize % len)
+            .take(KEY_STATS_EVICTION_SAMPLES)
+            .min_by_key(|entry| entry.value().accesses())
+            .map(|entry| entry.key().clone());
+        if let Some(victim) = victim {
+            self.counts.remove(&victim);
+        }
+    }
+}
+
+impl Backend {
+    // called by the dispatcher after a sampled command ran, with the keys it accessed
+    pub fn record_key_stats(&self, keys: &[Key], write: bool) {
+        for key in keys {
+            match (write, self.key_exists(key)) {
+                (true, _) => self.key_stats.record(key, |counts| counts.writes += 1),
+                (false, true) => self.key_stats.record(key, |counts| counts.hits += 1),
+                (false, false) => self.key_stats.record(key, |counts| counts.misses += 1),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_key_stats() {
+        let backend = Backend::new();
+        assert!(!backend.key_stats.sample());
+        backend.key_stats.set_sample_rate(2);
+        let sampled = (0..10).filter(|_| backend.key_stats.sample()).count();
+        assert_eq!(sampled, 5);
+
+        backend.set("a".into(), BulkString::from("v").into());
+        let keys = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| Key::from(*name))
+                .collect::<Vec<_>>()
+        };
+        backend.record_key_stats(&keys(&["a", "missing"]), false);
+        backend.record_key_stats(&keys(&["a"]), false);
+        backend.record_key_stats(&keys(&["b"]), true);
+        assert_eq!(
+            backend.key_stats.get(b"a"),
+            Some(KeyAccessCounts {
+                hits: 2,
+                misses: 0,
+                writes: 0
+            })
+        );
+        assert_eq!(backend.key_stats.get(b"missing").unwrap().misses, 1);
+        assert_eq!(backend.key_stats.get(b"b").unwrap().writes, 1);
+
+        let top = backend.key_stats.top(2);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].0, "a");
+        assert_eq!(top[1].0, "b");
+
+        // a full table makes room for new keys
+        for i in 0..KEY_STATS_MAX_KEYS + 10 {
+            backend.record_key_stats(&keys(&[&format!("k{}", i)]), true);
+        }
+        assert_eq!(backend.key_stats.counts.len(), KEY_STATS_MAX_KEYS);
+
+        backend.key_stats.reset();
+        assert!(backend.key_stats.is_empty());
+    }
+}
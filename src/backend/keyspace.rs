@@ -0,0 +1,251 @@
+// Commands on keys of any type. The type of a key is the one of its value: the dispatcher
+// refuses a command on keys of another type than the one of the command table with
+// WRONGTYPE, and the commands replacing a key whatever it held replace its value.
+
+use super::{clock::now_ms, Backend, Key, Value};
+use std::{collections::BTreeSet, sync::atomic::Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    String,
+    Hash,
+    List,
+    Set,
+    ZSet,
+    Stream,
+    // a string for Redis, kept apart from the strings here so only the HyperLogLog
+    // commands access it
+    HyperLogLog,
+}
+
+impl KeyType {
+    // the name TYPE replies with
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyType::String | KeyType::HyperLogLog => "string",
+            KeyType::Hash => "hash",
+            KeyType::List => "list",
+            KeyType::Set => "set",
+            KeyType::ZSet => "zset",
+            KeyType::Stream => "stream",
+        }
+    }
+}
+
+// what there is to know about a key, read at once so that e.g. the TTL is the one of the
+// value, whatever other clients do with the key meanwhile
+#[derive(Debug, Clone)]
+pub struct EntryView {
+    pub value: Value,
+    pub key_type: KeyType,
+    // milliseconds to live, None without a TTL
+    pub ttl: Option<u64>,
+    // unix time in milliseconds, None until the dispatcher accounts for the key
+    pub last_access: Option<u64>,
+}
+
+impl Backend {
+    // the key with its TTL, type and last access, under a single lock of its entry. An
+    // expired key is missing, like for a command, but isn't deleted.
+    pub fn get_entry(&self, key: &[u8]) -> Option<EntryView> {
+        let entry = self.db.get(key)?;
+        let now = now_ms();
+        if entry.expire.is_some_and(|at| at <= now) {
+            return None;
+        }
+        Some(EntryView {
+            value: entry.value.clone(),
+            key_type: entry.value.key_type(),
+            ttl: entry.expire.map(|at| at - now),
+            last_access: entry.meta.map(|meta| meta.last_access),
+        })
+    }
+
+    // the number of keys, expired ones included until they are removed
+    pub fn dbsize(&self) -> usize {
+        self.db.len()
+    }
+
+    pub fn key_type(&self, key: &[u8]) -> Option<KeyType> {
+        self.db.get(key).map(|entry| entry.value.key_type())
+    }
+
+    // whether none of the keys exists with another type
+    pub fn keys_have_type(&self, keys: &[Key], key_type: KeyType) -> bool {
+        keys.iter()
+            .all(|key| self.key_type(key).is_none_or(|t| t == key_type))
+    }
+
+    pub fn key_exists(&self, key: &[u8]) -> bool {
+        self.db.contains_key(key)
+    }
+
+    // the number of keys that exist, a key given twice counts twice.
+    // Every distinct key is looked up once.
+    pub fn exists(&self, keys: &[Key]) -> usize {
+        let existing: BTreeSet<&Key> = unique(keys)
+            .into_iter()
+            .filter(|key| self.key_exists(key))
+            .collect();
+        keys.iter().filter(|key| existing.contains(key)).count()
+    }
+
+    // delete the keys, returns the distinct keys deleted
+    pub fn del(&self, keys: &[Key]) -> Vec<Key> {
+        if self.lazyfree_lazy_user_del.load(Ordering::Relaxed) {
+            return self.unlink(keys);
+        }
+        self.remove_keys(keys)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    // like del, but the values are dropped in the background so deleting large values
+    // doesn't hold up the connection
+    pub fn unlink(&self, keys: &[Key]) -> Vec<Key> {
+        let (keys, values): (Vec<Key>, Vec<_>) = self.remove_keys(keys).into_iter().unzip();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(move || drop(values));
+            }
+            Err(_) => drop(values),
+        }
+        keys
+    }
+
+    // the number of keys that exist, a key given twice counts twice
+    pub fn touch(&self, keys: &[Key]) -> usize {
+        self.exists(keys)
+    }
+
+    // copy the value of src to dst, with its own copy of every element and its TTL. dst is
+    // replaced only with replace, returns whether the value was copied.
+    pub fn copy(&self, src: &[u8], dst: &[u8], replace: bool) -> bool {
+        self.with_keys_locked(&[src, dst], || {
+            if !self.key_exists(src) || src == dst {
+                return false;
+            }
+            if self.key_exists(dst) {
+                if !replace {
+                    return false;
+                }
+                self.remove_keys(&[Key::from(dst)]);
+            }
+            let Some(mut entry) = self.db.get(src).map(|entry| entry.clone()) else {
+                // deleted in the meantime
+                return false;
+            };
+            // dst is accounted for when the dispatcher records its write
+            entry.meta = None;
+            let key_type = entry.value.key_type();
+            let dst = Key::from(dst);
+            let expire = entry.expire.take();
+            let mut inserted = self.db.entry(dst.clone()).insert(entry);
+            self.set_entry_expire(&dst, &mut inserted, expire);
+            drop(inserted);
+            match key_type {
+                KeyType::String => self.bump_string_epoch(),
+                KeyType::List | KeyType::Stream | KeyType::ZSet => self.signal_key_ready(&dst),
+                _ => {}
+            }
+            true
+        })
+    }
+
+    // remove every distinct key, returning the removed values
+    pub(super) fn remove_keys(&self, keys: &[Key]) -> Vec<(Key, Value)> {
+        let mut removed = Vec::new();
+        let mut string_removed = false;
+        for key in unique(keys) {
+            let Some(entry) = self.remove_entry_if(key, |_| true) else {
+                continue;
+            };
+            string_removed |= matches!(entry.value, Value::String(_));
+            removed.push((key.clone(), entry.value));
+        }
+        if string_removed {
+            self.bump_string_epoch();
+        }
+        removed
+    }
+}
+
+fn unique(keys: &[Key]) -> BTreeSet<&Key> {
+    keys.iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn keys(keys: &[&str]) -> Vec<Key> {
+        keys.iter().map(|key| Key::from(*key)).collect()
+    }
+
+    #[test]
+    fn test_exists_and_del() {
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("1").into());
+        backend.hset("b".into(), "f".into(), BulkString::from("v").into());
+
+        assert_eq!(backend.exists(&keys(&["a", "a", "b", "c"])), 3);
+        assert_eq!(backend.touch(&keys(&["a", "c"])), 1);
+
+        let epoch = backend.string_epoch();
+        assert_eq!(backend.del(&keys(&["a", "a", "c"])), keys(&["a"]));
+        assert!(backend.string_epoch() > epoch);
+        assert!(!backend.key_exists(b"a"));
+        assert!(backend.key_exists(b"b"));
+    }
+
+    #[test]
+    fn test_get_entry() {
+        let backend = Backend::new();
+        assert!(backend.get_entry(b"a").is_none());
+        backend.set("a".into(), BulkString::from("1").into());
+        let entry = backend.get_entry(b"a").unwrap();
+        assert!(matches!(entry.value, Value::String(_)));
+        assert_eq!(entry.key_type, KeyType::String);
+        assert_eq!((entry.ttl, entry.last_access), (None, None));
+
+        backend.expire(&"a".into(), now_ms() + 60_000);
+        backend.record_key_access(&keys(&["a"]), true);
+        let entry = backend.get_entry(b"a").unwrap();
+        assert!(entry.ttl.is_some_and(|ttl| ttl > 0 && ttl <= 60_000));
+        assert!(entry.last_access.is_some());
+
+        // expired, but not deleted until a command or the active cycle gets to it
+        backend.db.get_mut(b"a".as_slice()).unwrap().expire = Some(now_ms() - 1);
+        assert!(backend.get_entry(b"a").is_none());
+        assert!(backend.key_exists(b"a"));
+    }
+
+    #[test]
+    fn test_copy() {
+        let backend = Backend::new();
+        backend.hset("h".into(), "f".into(), BulkString::from("1").into());
+        backend.set("s".into(), BulkString::from("v").into());
+
+        assert!(backend.copy(b"h", b"h2", false));
+        // a deep copy, changing it leaves the source alone
+        backend.hset("h2".into(), "f".into(), BulkString::from("2").into());
+        assert_eq!(backend.hget(b"h", b"f"), Some(BulkString::from("1").into()));
+
+        assert!(!backend.copy(b"s", b"h2", false));
+        assert!(!backend.copy(b"missing", b"x", true));
+        assert!(!backend.copy(b"s", b"s", true));
+        assert!(backend.copy(b"s", b"h2", true));
+        assert_eq!(backend.get(b"h2"), Some(BulkString::from("v").into()));
+        assert_eq!(backend.hget(b"h2", b"f"), None);
+    }
+
+    #[tokio::test]
+    async fn test_unlink() {
+        let backend = Backend::new();
+        backend.lpush("l".into(), vec![BulkString::from("x").into()]);
+        assert_eq!(backend.unlink(&keys(&["l", "l", "m"])), keys(&["l"]));
+        assert_eq!(backend.exists(&keys(&["l"])), 0);
+    }
+}
@@ -0,0 +1,166 @@
+// The latency monitor, like in Redis: the server times the operations that may stall it
+// and records those that took at least latency-monitor-threshold milliseconds as spikes of
+// their event. An event keeps its last LATENCY_TS_LEN spikes, one per second at most (the
+// worst of that second), and the worst spike it ever had. 0 disables the monitor.
+//
+// The events:
+//
+//   command       a command run by the dispatcher, not counting the time it was blocked
+//   fast-command  a GET or SET run by the fast path
+//   expire-cycle  an active expire cycle
+//   snapshot-save writing the snapshot, the persistence there is instead of AOF fsyncs
+
+use super::clock::now_ms;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+pub const LATENCY_COMMAND: &str = "command";
+pub const LATENCY_FAST_COMMAND: &str = "fast-command";
+pub const LATENCY_EXPIRE_CYCLE: &str = "expire-cycle";
+pub const LATENCY_SNAPSHOT_SAVE: &str = "snapshot-save";
+
+const LATENCY_TS_LEN: usize = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySample {
+    // unix time in seconds
+    pub time: u64,
+    pub latency_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyEvent {
+    // oldest first
+    pub samples: VecDeque<LatencySample>,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct LatencyMonitor {
+    // in milliseconds, 0 disables the monitor
+    threshold_ms: AtomicU64,
+    events: Mutex<BTreeMap<&'static str, LatencyEvent>>,
+}
+
+impl LatencyMonitor {
+    pub fn threshold(&self) -> Duration {
+        Duration::from_millis(self.threshold_ms.load(Ordering::Relaxed))
+    }
+
+    pub fn set_threshold(&self, threshold: Duration) {
+        self.threshold_ms
+            .store(threshold.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    // record a spike of the event if the operation took at least the threshold
+    pub fn record(&self, event: &'static str, duration: Duration) {
+        let threshold = self.threshold_ms.load(Ordering::Relaxed);
+        let latency_ms = duration.as_millis() as u64;
+        if threshold == 0 || latency_ms < threshold {
+            return;
+        }
+        let time = now_ms() / 1000;
+        let mut events = self.events.lock().unwrap();
+        let event = events.entry(event).or_default();
+        event.max_ms = event.max_ms.max(latency_ms);
+        match event.samples.back_mut() {
+            Some(last) if last.time == time => last.latency_ms = last.latency_ms.max(latency_ms),
+            _ => {
+                event.samples.push_back(LatencySample { time, latency_ms });
+                if event.samples.len() > LATENCY_TS_LEN {
+                    event.samples.pop_front();
+                }
+            }
+        }
+    }
+
+    pub fn event(&self, event: &str) -> Option<LatencyEvent> {
+        self.events.lock().unwrap().get(event).cloned()
+    }
+
+    // the events with spikes, by name
+    pub fn events(&self) -> Vec<(&'static str, LatencyEvent)> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, event)| (*name, event.clone()))
+            .collect()
+    }
+
+    // forget the given events, all of them when none is given. Returns how many were reset.
+    pub fn reset(&self, events: &[String]) -> usize {
+        let mut all = self.events.lock().unwrap();
+        if events.is_empty() {
+            let len = all.len();
+            all.clear();
+            return len;
+        }
+        events
+            .iter()
+            .filter(|event| all.remove(event.as_str()).is_some())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::set_mock_now_ms;
+
+    #[test]
+    fn test_latency_monitor() {
+        let monitor = LatencyMonitor::default();
+        monitor.record(LATENCY_COMMAND, Duration::from_secs(1));
+        assert!(monitor.events().is_empty());
+
+        set_mock_now_ms(Some(1_000_000));
+        monitor.set_threshold(Duration::from_millis(100));
+        monitor.record(LATENCY_COMMAND, Duration::from_millis(99));
+        assert!(monitor.events().is_empty());
+        monitor.record(LATENCY_COMMAND, Duration::from_millis(150));
+        // the worst spike of a second is kept
+        monitor.record(LATENCY_COMMAND, Duration::from_millis(300));
+        monitor.record(LATENCY_COMMAND, Duration::from_millis(200));
+        set_mock_now_ms(Some(1_001_000));
+        monitor.record(LATENCY_COMMAND, Duration::from_millis(120));
+        monitor.record(LATENCY_EXPIRE_CYCLE, Duration::from_millis(100));
+
+        let event = monitor.event(LATENCY_COMMAND).unwrap();
+        assert_eq!(
+            event.samples,
+            vec![
+                LatencySample {
+                    time: 1000,
+                    latency_ms: 300
+                },
+                LatencySample {
+                    time: 1001,
+                    latency_ms: 120
+                },
+            ]
+        );
+        assert_eq!(event.max_ms, 300);
+
+        for i in 0..LATENCY_TS_LEN as u64 {
+            set_mock_now_ms(Some(2_000_000 + i * 1000));
+            monitor.record(LATENCY_COMMAND, Duration::from_millis(100));
+        }
+        let event = monitor.event(LATENCY_COMMAND).unwrap();
+        assert_eq!(event.samples.len(), LATENCY_TS_LEN);
+        assert_eq!(event.samples[0].time, 2000);
+        assert_eq!(event.max_ms, 300);
+
+        assert_eq!(monitor.reset(&["nope".to_string()]), 0);
+        assert_eq!(monitor.reset(&[LATENCY_EXPIRE_CYCLE.to_string()]), 1);
+        assert_eq!(monitor.reset(&[]), 1);
+        assert!(monitor.events().is_empty());
+        set_mock_now_ms(None);
+    }
+}
@@ -0,0 +1,462 @@
+// Lists with few small elements are kept in the listpack encoding, a vector the elements
+// are pushed into at either end, which costs less memory than a deque per list when there
+// are millions of tiny lists. list-max-listpack-size bounds it like in Redis: a positive
+// size is a number of elements, -1 to -5 a number of bytes, from 4KB to 64KB. A list
+// outgrowing it is converted to the quicklist encoding, a deque, and is never converted
+// back.
+
+use super::{string::string_bytes, Backend, Entry, Key, Value};
+use crate::RespFrame;
+use dashmap::SharedValue;
+use std::{collections::VecDeque, time::Duration};
+
+pub const DEFAULT_LIST_MAX_LISTPACK_SIZE: i64 = -2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEncoding {
+    Listpack,
+    Quicklist,
+}
+
+#[derive(Debug, Clone)]
+enum Elements {
+    Listpack(Vec<RespFrame>),
+    Quicklist(VecDeque<RespFrame>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ElementList {
+    elements: Elements,
+}
+
+impl ListEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListEncoding::Listpack => "listpack",
+            ListEncoding::Quicklist => "quicklist",
+        }
+    }
+}
+
+impl Default for ElementList {
+    fn default() -> Self {
+        ElementList {
+            elements: Elements::Listpack(Vec::new()),
+        }
+    }
+}
+
+// whether the elements fit in a listpack of list-max-listpack-size
+fn listpack_fits(elements: &[RespFrame], max_size: i64) -> bool {
+    if max_size >= 0 {
+        return elements.len() as u64 <= max_size as u64;
+    }
+    let max_bytes = 4096usize << (max_size.unsigned_abs().clamp(1, 5) - 1);
+    let mut bytes = 0;
+    elements.iter().all(|element| {
+        bytes += string_bytes(element).len();
+        bytes <= max_bytes
+    })
+}
+
+impl ElementList {
+    pub fn from_elements(elements: impl IntoIterator<Item = RespFrame>, max_size: i64) -> Self {
+        let mut list = ElementList::default();
+        for element in elements {
+            list.push(element, ListEnd::Right, max_size);
+        }
+        list
+    }
+
+    pub fn encoding(&self) -> ListEncoding {
+        match self.elements {
+            Elements::Listpack(_) => ListEncoding::Listpack,
+            Elements::Quicklist(_) => ListEncoding::Quicklist,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.elements {
+            Elements::Listpack(elements) => elements.len(),
+            Elements::Quicklist(elements) => elements.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push(&mut self, element: RespFrame, end: ListEnd, max_size: i64) {
+        if let Elements::Listpack(elements) = &mut self.elements {
+            match end {
+                ListEnd::Left => elements.insert(0, element),
+                ListEnd::Right => elements.push(element),
+            }
+            if !listpack_fits(elements, max_size) {
+                self.elements = Elements::Quicklist(std::mem::take(elements).into());
+            }
+            return;
+        }
+        if let Elements::Quicklist(elements) = &mut self.elements {
+            match end {
+                ListEnd::Left => elements.push_front(element),
+                ListEnd::Right => elements.push_back(element),
+            }
+        }
+    }
+
+    pub fn pop(&mut self, end: ListEnd) -> Option<RespFrame> {
+        match (&mut self.elements, end) {
+            (Elements::Listpack(elements), ListEnd::Left) => {
+                (!elements.is_empty()).then(|| elements.remove(0))
+            }
+            (Elements::Listpack(elements), ListEnd::Right) => elements.pop(),
+            (Elements::Quicklist(elements), ListEnd::Left) => elements.pop_front(),
+            (Elements::Quicklist(elements), ListEnd::Right) => elements.pop_back(),
+        }
+    }
+
+    // the elements between start and stop, both inclusive and within the list
+    pub fn range(&self, start: usize, stop: usize) -> Box<dyn Iterator<Item = &RespFrame> + '_> {
+        match &self.elements {
+            Elements::Listpack(elements) => Box::new(elements[start..=stop].iter()),
+            Elements::Quicklist(elements) => Box::new(elements.range(start..=stop)),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &RespFrame> + '_> {
+        match &self.elements {
+            Elements::Listpack(elements) => Box::new(elements.iter()),
+            Elements::Quicklist(elements) => Box::new(elements.iter()),
+        }
+    }
+}
+
+impl Backend {
+    pub fn lpush(&self, key: Key, values: Vec<RespFrame>) -> usize {
+        self.push(key, values, ListEnd::Left)
+    }
+
+    pub fn rpush(&self, key: Key, values: Vec<RespFrame>) -> usize {
+        self.push(key, values, ListEnd::Right)
+    }
+
+    pub fn push(&self, key: Key, values: Vec<RespFrame>, end: ListEnd) -> usize {
+        let max_size = self.list_max_listpack_size();
+        let len = {
+            let mut list = self.value_or_default::<ElementList>(key.clone());
+            for value in values {
+                list.push(value, end, max_size);
+            }
+            list.len()
+        };
+        self.signal_key_ready(&key);
+        len
+    }
+
+    // pop up to `count` elements, None if the list does not exist
+    pub fn pop(&self, key: &[u8], count: usize, end: ListEnd) -> Option<Vec<RespFrame>> {
+        let ret = {
+            let mut list = self.value_mut::<ElementList>(key)?;
+            let count = count.min(list.len());
+            let mut ret = Vec::with_capacity(count);
+            for _ in 0..count {
+                ret.extend(list.pop(end));
+            }
+            ret
+        };
+        self.remove_value_if::<ElementList>(key, |list| list.is_empty());
+        Some(ret)
+    }
+
+    pub fn llen(&self, key: &[u8]) -> usize {
+        self.value::<ElementList>(key)
+            .map(|list| list.len())
+            .unwrap_or(0)
+    }
+
+    // elements between start and stop (both inclusive), negative indexes count from the end
+    pub fn lrange(&self, key: &[u8], start: i64, stop: i64) -> Vec<RespFrame> {
+        let Some(list) = self.value::<ElementList>(key) else {
+            return Vec::new();
+        };
+        let len = list.len() as i64;
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let stop = if stop < 0 {
+            len + stop
+        } else {
+            stop.min(len - 1)
+        };
+        if start > stop || start >= len {
+            return Vec::new();
+        }
+        list.range(start as usize, stop as usize).cloned().collect()
+    }
+
+    // move an element from the `from` end of src to the `to` end of dst, None if src does
+    // not exist. The shards of both lists stay locked for the move, so no other command
+    // sees the element in neither or both of them.
+    pub fn lmove(&self, src: &[u8], dst: &[u8], from: ListEnd, to: ListEnd) -> Option<RespFrame> {
+        let max_size = self.list_max_listpack_size();
+        let value = {
+            let shards = self.db.shards();
+            let src_shard = self.db.determine_map(src);
+            let dst_shard = self.db.determine_map(dst);
+            // locked in shard order so two moves in opposite directions can't deadlock
+            let first = src_shard.min(dst_shard);
+            let mut low = shards[first].write();
+            let mut high =
+                (src_shard != dst_shard).then(|| shards[src_shard.max(dst_shard)].write());
+
+            let src_map = match high.as_deref_mut() {
+                Some(high) if src_shard != first => high,
+                _ => &mut *low,
+            };
+            let Value::List(list) = &mut src_map.get_mut(src)?.get_mut().value else {
+                return None;
+            };
+            let value = list.pop(from)?;
+            if list.is_empty() {
+                if let Some(entry) = src_map.remove(src) {
+                    let entry = entry.into_inner();
+                    if entry.expire.is_some() {
+                        self.volatile.remove(src);
+                    }
+                    if let Some(meta) = entry.meta {
+                        self.untrack_key(src, &meta);
+                    }
+                }
+            }
+
+            let dst_map = match high.as_deref_mut() {
+                Some(high) if dst_shard != first => high,
+                _ => &mut *low,
+            };
+            let entry = dst_map
+                .entry(Key::from(dst))
+                .or_insert_with(|| {
+                    SharedValue::new(Entry::new(Value::List(ElementList::default())))
+                })
+                .get_mut();
+            if !matches!(entry.value, Value::List(_)) {
+                entry.value = Value::List(ElementList::default());
+                self.set_entry_expire(dst, entry, None);
+            }
+            let Value::List(list) = &mut entry.value else {
+                unreachable!("dst holds a list");
+            };
+            list.push(value.clone(), to, max_size);
+            value
+        };
+        self.signal_key_ready(dst);
+        Some(value)
+    }
+
+    // like lmove, but wait until src gets an element or the timeout expires. A timeout of
+    // None waits forever.
+    pub async fn blocking_move(
+        &self,
+        src: &[u8],
+        dst: &[u8],
+        from: ListEnd,
+        to: ListEnd,
+        timeout: Option<Duration>,
+    ) -> Option<RespFrame> {
+        self.block_on_keys(&[Key::from(src)], timeout, || {
+            self.lmove(src, dst, from, to)
+        })
+        .await
+    }
+
+    // pop from the first non-empty list among keys, None if all of them are empty
+    pub fn pop_first(&self, keys: &[Key], end: ListEnd) -> Option<(Key, RespFrame)> {
+        keys.iter().find_map(|key| {
+            self.pop(key, 1, end)
+                .and_then(|mut values| values.pop())
+                .map(|value| (key.clone(), value))
+        })
+    }
+
+    // like pop_first, but wait until one of the lists gets an element or the timeout expires.
+    // A timeout of None waits forever.
+    pub async fn blocking_pop(
+        &self,
+        keys: &[Key],
+        timeout: Option<Duration>,
+        end: ListEnd,
+    ) -> Option<(Key, RespFrame)> {
+        self.block_on_keys(keys, timeout, || self.pop_first(keys, end))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn values(items: &[&str]) -> Vec<RespFrame> {
+        items.iter().map(|s| BulkString::from(*s).into()).collect()
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let backend = Backend::new();
+        assert_eq!(backend.rpush("list".into(), values(&["a", "b"])), 2);
+        assert_eq!(backend.lpush("list".into(), values(&["c"])), 3);
+        assert_eq!(backend.lrange(b"list", 0, -1), values(&["c", "a", "b"]));
+
+        assert_eq!(
+            backend.pop(b"list", 2, ListEnd::Right),
+            Some(values(&["b", "a"]))
+        );
+        assert_eq!(backend.pop(b"list", 5, ListEnd::Left), Some(values(&["c"])));
+        // empty lists are removed
+        assert_eq!(backend.pop(b"list", 1, ListEnd::Left), None);
+        assert_eq!(backend.llen(b"list"), 0);
+    }
+
+    #[test]
+    fn test_list_encoding_conversion() {
+        let mut list = ElementList::from_elements(values(&["a", "b"]), 3);
+        list.push(BulkString::from("c").into(), ListEnd::Left, 3);
+        assert_eq!(list.encoding(), ListEncoding::Listpack);
+        assert_eq!(list.pop(ListEnd::Left), Some(BulkString::from("c").into()));
+        list.push(BulkString::from("c").into(), ListEnd::Right, 3);
+        list.push(BulkString::from("d").into(), ListEnd::Right, 3);
+        assert_eq!(list.encoding(), ListEncoding::Quicklist);
+        let elements: Vec<_> = list.iter().cloned().collect();
+        assert_eq!(elements, values(&["a", "b", "c", "d"]));
+
+        // a negative size is in bytes, -1 is 4KB
+        let long = BulkString::new(vec![b'x'; 3000]);
+        let mut list = ElementList::from_elements([long.clone().into()], -1);
+        assert_eq!(list.encoding(), ListEncoding::Listpack);
+        list.push(long.into(), ListEnd::Left, -1);
+        assert_eq!(list.encoding(), ListEncoding::Quicklist);
+        assert_eq!(list.len(), 2);
+    }
+
+    #[test]
+    fn test_lrange() {
+        let backend = Backend::new();
+        backend.rpush("list".into(), values(&["a", "b", "c", "d"]));
+        assert_eq!(backend.lrange(b"list", 1, 2), values(&["b", "c"]));
+        assert_eq!(backend.lrange(b"list", -2, -1), values(&["c", "d"]));
+        assert_eq!(
+            backend.lrange(b"list", -100, 100),
+            values(&["a", "b", "c", "d"])
+        );
+        assert_eq!(backend.lrange(b"list", 3, 1), values(&[]));
+        assert_eq!(backend.lrange(b"list", 10, 20), values(&[]));
+        assert_eq!(backend.lrange(b"missing", 0, -1), values(&[]));
+    }
+
+    #[tokio::test]
+    async fn test_blocking_pop_timeout() {
+        let backend = Backend::new();
+        let ret = backend
+            .blocking_pop(
+                &["list".into()],
+                Some(Duration::from_millis(10)),
+                ListEnd::Left,
+            )
+            .await;
+        assert_eq!(ret, None);
+        assert!(backend.key_waiters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_blocking_pop_wakes_up_on_push() {
+        let backend = Backend::new();
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move {
+            cloned
+                .blocking_pop(&["a".into(), "b".to_string().into()], None, ListEnd::Left)
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        backend.rpush("b".into(), values(&["x"]));
+
+        let ret = handle.await.unwrap();
+        assert_eq!(ret, Some(("b".into(), BulkString::from("x").into())));
+        assert_eq!(backend.llen(b"b"), 0);
+    }
+
+    #[test]
+    fn test_lmove() {
+        let backend = Backend::new();
+        backend.rpush("a".into(), values(&["1", "2"]));
+        assert_eq!(
+            backend.lmove(b"a", b"b", ListEnd::Right, ListEnd::Left),
+            Some(BulkString::from("2").into())
+        );
+        assert_eq!(
+            backend.lmove(b"a", b"b", ListEnd::Left, ListEnd::Right),
+            Some(BulkString::from("1").into())
+        );
+        assert!(!backend.key_exists(b"a"));
+        assert_eq!(backend.lrange(b"b", 0, -1), values(&["2", "1"]));
+        assert_eq!(
+            backend.lmove(b"a", b"b", ListEnd::Left, ListEnd::Left),
+            None
+        );
+
+        // rotating a list
+        assert_eq!(
+            backend.lmove(b"b", b"b", ListEnd::Left, ListEnd::Right),
+            Some(BulkString::from("2").into())
+        );
+        assert_eq!(backend.lrange(b"b", 0, -1), values(&["1", "2"]));
+
+        // keys in every pair of shards, the same one included, don't deadlock
+        for i in 0..64 {
+            let (src, dst) = (format!("src{}", i), format!("dst{}", i));
+            backend.rpush(src.clone().into(), values(&["x"]));
+            assert!(backend
+                .lmove(src.as_bytes(), dst.as_bytes(), ListEnd::Left, ListEnd::Left)
+                .is_some());
+            assert!(backend
+                .lmove(dst.as_bytes(), src.as_bytes(), ListEnd::Left, ListEnd::Left)
+                .is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocking_move_wakes_up_on_push() {
+        let backend = Backend::new();
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move {
+            cloned
+                .blocking_move(b"a", b"b", ListEnd::Left, ListEnd::Left, None)
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        backend.rpush("a".into(), values(&["x"]));
+
+        assert_eq!(handle.await.unwrap(), Some(BulkString::from("x").into()));
+        assert_eq!(backend.lrange(b"b", 0, -1), values(&["x"]));
+        let ret = backend
+            .blocking_move(
+                b"a",
+                b"b",
+                ListEnd::Left,
+                ListEnd::Left,
+                Some(Duration::from_millis(10)),
+            )
+            .await;
+        assert_eq!(ret, None);
+    }
+}
@@ -1,16 +1,203 @@
-use crate::{RespArray, RespFrame};
+mod bitmap;
+mod blocking;
+mod cache;
+mod clock;
+mod cluster;
+mod config;
+mod db;
+mod debug;
+mod dump;
+mod effects;
+mod eviction;
+mod expire;
+mod function;
+mod geo;
+mod glob;
+mod hash;
+mod hll;
+mod key;
+mod key_lock;
+mod key_prefix;
+mod key_stats;
+mod keyspace;
+mod latency;
+mod list;
+mod notify;
+mod output_buffer;
+mod pause;
+mod pubsub;
+mod replication;
+mod set;
+mod shutdown;
+mod slowlog;
+mod snapshot;
+mod sort;
+mod stats;
+mod stream;
+mod stream_group;
+mod string;
+mod zset;
+
+use crate::{logging::LogConfig, BulkString, DecoderLimits, RespArray, RespFrame, RespMap};
 use dashmap::DashMap;
+use expire::VolatileKeys;
+use key_lock::KeyLocks;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicU16, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use string::{decode_string, encode_string};
+use tokio::sync::{watch, Notify};
+
+pub use bitmap::{BitOperation, BitRange, BitUnit};
+pub use cache::{GetCache, GetCacheStats};
+pub(crate) use clock::now_ms;
+#[cfg(test)]
+pub(crate) use clock::set_mock_now_ms;
+pub use cluster::{
+    key_hash_slot, Cluster, ClusterError, ClusterNode, ClusterTopology, CLUSTER_SLOTS,
+};
+pub use config::*;
+pub(crate) use db::TypedValue;
+pub use db::{Entry, Value};
+pub use debug::DEBUG_DISABLED_ERROR;
+pub use dump::{BUSYKEY_ERROR, DUMP_PAYLOAD_ERROR};
+pub use effects::{Effect, EffectBus};
+pub use eviction::{
+    KeyMeta, MaxmemoryPolicy, DEFAULT_MAXMEMORY_SAMPLES, MAXMEMORY_POLICIES, OOM_ERROR,
+};
+pub use function::{Functions, ServerFunction};
+pub use geo::*;
+pub use glob::glob_match;
+pub use hash::{
+    FieldMap, HashEncoding, DEFAULT_HASH_MAX_LISTPACK_ENTRIES, DEFAULT_HASH_MAX_LISTPACK_VALUE,
+};
+pub use hll::{HllEncoding, HyperLogLog};
+pub use key::Key;
+pub use key_prefix::KeyPrefixes;
+pub use key_stats::{KeyAccessCounts, KeyStats};
+pub use keyspace::{EntryView, KeyType};
+pub use latency::{
+    LatencyEvent, LatencyMonitor, LatencySample, LATENCY_COMMAND, LATENCY_EXPIRE_CYCLE,
+    LATENCY_FAST_COMMAND, LATENCY_SNAPSHOT_SAVE,
+};
+pub use list::{ElementList, ListEncoding, ListEnd, DEFAULT_LIST_MAX_LISTPACK_SIZE};
+pub use notify::*;
+pub use output_buffer::{OutputBufferLimit, PushSender};
+pub use pause::{ClientPause, PauseMode};
+pub use pubsub::PubSub;
+pub use replication::{
+    LinkState, MasterInfo, PsyncReply, ReplicaInfo, Replication, DEFAULT_REPL_BACKLOG_SIZE,
+};
+pub use set::{
+    ListpackLimits, MemberSet, SetEncoding, DEFAULT_SET_MAX_LISTPACK_ENTRIES,
+    DEFAULT_SET_MAX_LISTPACK_VALUE,
+};
+pub use shutdown::{ShutdownPolicy, DEFAULT_SHUTDOWN_TIMEOUT, SHUTDOWN_POLICIES};
+pub use slowlog::{SlowLog, SlowLogEntry};
+pub use snapshot::{SnapshotError, DEFAULT_DBFILENAME, SNAPSHOT_MAGIC, SNAPSHOT_VERSION};
+pub use sort::{SortOptions, SORT_NOT_A_NUMBER_ERROR};
+pub use stats::{CommandLatency, CommandStats, KeyCounts, ShardStats, Stats};
+pub use stream::{Stream, StreamFields, StreamId, StreamIdSpec};
+pub use stream_group::{
+    AutoClaim, Consumer, ConsumerGroup, ConsumerPelPolicy, PendingDetail, PendingEntry,
+    PendingSummary, StreamEntries, CONSUMER_PEL_POLICIES,
+};
+pub use string::{
+    SetCondition, NOT_INTEGER_ERROR, OVERFLOW_ERROR, STRING_TOO_LONG_ERROR, WRONGTYPE_ERROR,
+};
+pub use zset::{LexBound, Score, ScoreBound, SortedSet, ZAddCondition, ZAggregate};
+
+pub const DEFAULT_PORT: u16 = 6379;
+pub const DEFAULT_MAXCLIENTS: u64 = 10000;
+// the tcp-keepalive default of Redis
+pub const DEFAULT_TCP_KEEPALIVE: Duration = Duration::from_secs(300);
 
 #[derive(Debug, Clone)]
 pub struct Backend(Arc<BackendInner>);
 
 #[derive(Debug)]
 pub struct BackendInner {
-    pub(crate) map: DashMap<String, RespFrame>,
-    pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
-    pub(crate) set: DashMap<String, DashMap<RespFrame, ()>>,
+    // every key with its value, TTL and eviction meta, see db.rs
+    pub(crate) db: DashMap<Key, Entry>,
+    // the keys with a TTL, see expire.rs
+    pub(crate) volatile: VolatileKeys,
+    // clients blocked on a key (BLPOP, XREAD BLOCK) wait on its Notify until it gets new data
+    pub(crate) key_waiters: DashMap<Key, Arc<Notify>>,
+    // held by operations on several keys, see key_lock.rs
+    pub(crate) key_locks: KeyLocks,
+    pub(crate) slowlog: SlowLog,
+    pub(crate) latency: LatencyMonitor,
+    pub(crate) stats: CommandStats,
+    pub(crate) pubsub: PubSub,
+    pub(crate) functions: Functions,
+    pub(crate) replication: Replication,
+    // where writes publish their effects, see effects.rs
+    pub(crate) effects: EffectBus,
+    pub(crate) cluster: Cluster,
+    pub(crate) notify_flags: AtomicU32,
+    pub(crate) dbfilename: Mutex<String>,
+    // bumped on every write to the string keys, see GetCache
+    pub(crate) string_epoch: AtomicU64,
+    pub(crate) get_cache_size: AtomicUsize,
+    pub(crate) get_cache_hits: AtomicU64,
+    pub(crate) get_cache_misses: AtomicU64,
+    // DEL frees the values in the background like UNLINK
+    pub(crate) lazyfree_lazy_user_del: AtomicBool,
+    // a random id of this run of the server, see INFO
+    pub(crate) run_id: String,
+    // the TCP port the server listens on, read at startup
+    pub(crate) port: AtomicU16,
+    // the number of sockets accepting on the port, read at startup
+    pub(crate) tcp_listeners: AtomicUsize,
+    // the unix socket path the server also listens on, empty for none
+    pub(crate) unixsocket: Mutex<String>,
+    pub(crate) maxclients: AtomicU64,
+    // clients idle for longer are disconnected, zero never disconnects them
+    pub(crate) client_timeout: Mutex<Duration>,
+    // SO_KEEPALIVE probes after this idle time on accepted sockets, zero disables them
+    pub(crate) tcp_keepalive: Mutex<Duration>,
+    // TCP_NODELAY on accepted sockets, so small replies aren't held back by Nagle
+    pub(crate) tcp_nodelay: AtomicBool,
+    // writes are refused from every client, e.g. to serve a loaded snapshot as it is
+    pub(crate) read_only: AtomicBool,
+    pub(crate) log_config: Mutex<LogConfig>,
+    // one command in trace_sample_rate runs in a tracing span, 0 for none
+    pub(crate) trace_sample_rate: AtomicU64,
+    pub(crate) trace_counter: AtomicU64,
+    // what clients may send, for the connections opened from then on
+    pub(crate) decoder_limits: Mutex<DecoderLimits>,
+    // the largest sets kept in the listpack encoding, see set.rs
+    pub(crate) set_listpack_limits: Mutex<ListpackLimits>,
+    // the largest hashes kept in the listpack encoding, see hash.rs
+    pub(crate) hash_listpack_limits: Mutex<ListpackLimits>,
+    // list-max-listpack-size, see list.rs
+    pub(crate) list_max_listpack_size: AtomicI64,
+    pub(crate) shutdown_on_sigterm: Mutex<ShutdownPolicy>,
+    pub(crate) shutdown_timeout: Mutex<Duration>,
+    // the policy of the shutdown requested, see shutdown.rs
+    pub(crate) shutdown_request: watch::Sender<Option<ShutdownPolicy>>,
+    // the CLIENT PAUSE on, see pause.rs
+    pub(crate) client_pause: watch::Sender<Option<ClientPause>>,
+    pub(crate) maxmemory: AtomicU64,
+    pub(crate) maxmemory_policy: Mutex<MaxmemoryPolicy>,
+    // the keys looked at to pick one to evict, see eviction.rs
+    pub(crate) maxmemory_samples: AtomicUsize,
+    // consumers idle for longer are deleted, see stream_group.rs
+    pub(crate) stream_consumer_idle_timeout: Mutex<Duration>,
+    pub(crate) stream_consumer_idle_pel: Mutex<ConsumerPelPolicy>,
+    pub(crate) key_prefixes: KeyPrefixes,
+    // sampled per-key access counts, see key_stats.rs
+    pub(crate) key_stats: KeyStats,
+    pub(crate) tracked_memory: AtomicU64,
+    pub(crate) evicted_keys: AtomicU64,
+    pub(crate) expired_keys: AtomicU64,
+    pub(crate) enable_debug_command: AtomicBool,
+    pub(crate) active_expire: AtomicBool,
 }
 
 impl Deref for Backend {
@@ -27,12 +214,75 @@ impl Default for Backend {
     }
 }
 
+impl BackendInner {
+    // the keyspace with the given number of shards. Every shard has its own lock, more of them let more threads work on different keys at the same time.
+    fn with_shards(shards: usize) -> Self {
+        // dashmap wants a power of two, and at least 2
+        let shards = shards.max(2).next_power_of_two();
+        Self {
+            db: DashMap::with_shard_amount(shards),
+            ..Self::default()
+        }
+    }
+}
+
 impl Default for BackendInner {
     fn default() -> Self {
         Self {
-            map: DashMap::new(),
-            hmap: DashMap::new(),
-            set: DashMap::new(),
+            db: DashMap::new(),
+            volatile: VolatileKeys::default(),
+            key_waiters: DashMap::new(),
+            key_locks: KeyLocks::default(),
+            slowlog: SlowLog::new(),
+            latency: LatencyMonitor::default(),
+            stats: CommandStats::new(),
+            pubsub: PubSub::new(),
+            functions: Functions::new(),
+            replication: Replication::new(),
+            effects: EffectBus::default(),
+            cluster: Cluster::default(),
+            notify_flags: AtomicU32::new(0),
+            dbfilename: Mutex::new(DEFAULT_DBFILENAME.to_string()),
+            string_epoch: AtomicU64::new(0),
+            get_cache_size: AtomicUsize::new(0),
+            get_cache_hits: AtomicU64::new(0),
+            get_cache_misses: AtomicU64::new(0),
+            lazyfree_lazy_user_del: AtomicBool::new(false),
+            run_id: replication::new_replid(),
+            port: AtomicU16::new(DEFAULT_PORT),
+            tcp_listeners: AtomicUsize::new(1),
+            unixsocket: Mutex::new(String::new()),
+            maxclients: AtomicU64::new(DEFAULT_MAXCLIENTS),
+            client_timeout: Mutex::new(Duration::ZERO),
+            tcp_keepalive: Mutex::new(DEFAULT_TCP_KEEPALIVE),
+            tcp_nodelay: AtomicBool::new(true),
+            read_only: AtomicBool::new(false),
+            log_config: Mutex::new(LogConfig::default()),
+            trace_sample_rate: AtomicU64::new(0),
+            trace_counter: AtomicU64::new(0),
+            decoder_limits: Mutex::new(DecoderLimits::default()),
+            set_listpack_limits: Mutex::new(ListpackLimits::default()),
+            hash_listpack_limits: Mutex::new(ListpackLimits {
+                max_entries: DEFAULT_HASH_MAX_LISTPACK_ENTRIES,
+                max_value: DEFAULT_HASH_MAX_LISTPACK_VALUE,
+            }),
+            list_max_listpack_size: AtomicI64::new(DEFAULT_LIST_MAX_LISTPACK_SIZE),
+            shutdown_on_sigterm: Mutex::new(ShutdownPolicy::default()),
+            shutdown_timeout: Mutex::new(DEFAULT_SHUTDOWN_TIMEOUT),
+            shutdown_request: watch::Sender::new(None),
+            client_pause: watch::Sender::new(None),
+            maxmemory: AtomicU64::new(0),
+            maxmemory_policy: Mutex::new(MaxmemoryPolicy::default()),
+            maxmemory_samples: AtomicUsize::new(DEFAULT_MAXMEMORY_SAMPLES),
+            stream_consumer_idle_timeout: Mutex::new(Duration::ZERO),
+            stream_consumer_idle_pel: Mutex::new(ConsumerPelPolicy::default()),
+            key_prefixes: KeyPrefixes::new(),
+            key_stats: KeyStats::default(),
+            tracked_memory: AtomicU64::new(0),
+            evicted_keys: AtomicU64::new(0),
+            expired_keys: AtomicU64::new(0),
+            enable_debug_command: AtomicBool::new(false),
+            active_expire: AtomicBool::new(true),
         }
     }
 }
@@ -42,35 +292,222 @@ impl Backend {
         Self::default()
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+    // a backend whose keyspace has this many shards, rounded up to a power of two,
+    // instead of dashmap's default of four per core
+    pub fn with_shards(shards: usize) -> Self {
+        Self(Arc::new(BackendInner::with_shards(shards)))
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port.load(Ordering::Relaxed)
+    }
+
+    pub fn set_port(&self, port: u16) {
+        self.port.store(port, Ordering::Relaxed);
+    }
+
+    pub fn tcp_listeners(&self) -> usize {
+        self.tcp_listeners.load(Ordering::Relaxed)
+    }
+
+    pub fn set_tcp_listeners(&self, listeners: usize) {
+        self.tcp_listeners.store(listeners, Ordering::Relaxed);
+    }
+
+    pub fn unixsocket(&self) -> String {
+        self.unixsocket.lock().unwrap().clone()
+    }
+
+    pub fn set_unixsocket(&self, path: String) {
+        *self.unixsocket.lock().unwrap() = path;
+    }
+
+    pub fn maxclients(&self) -> u64 {
+        self.maxclients.load(Ordering::Relaxed)
+    }
+
+    pub fn set_maxclients(&self, maxclients: u64) {
+        self.maxclients.store(maxclients, Ordering::Relaxed);
+    }
+
+    pub fn client_timeout(&self) -> Duration {
+        *self.client_timeout.lock().unwrap()
+    }
+
+    pub fn set_client_timeout(&self, timeout: Duration) {
+        *self.client_timeout.lock().unwrap() = timeout;
+    }
+
+    pub fn tcp_keepalive(&self) -> Duration {
+        *self.tcp_keepalive.lock().unwrap()
+    }
+
+    pub fn set_tcp_keepalive(&self, keepalive: Duration) {
+        *self.tcp_keepalive.lock().unwrap() = keepalive;
+    }
+
+    pub fn tcp_nodelay(&self) -> bool {
+        self.tcp_nodelay.load(Ordering::Relaxed)
+    }
+
+    pub fn set_tcp_nodelay(&self, nodelay: bool) {
+        self.tcp_nodelay.store(nodelay, Ordering::Relaxed);
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    pub fn log_config(&self) -> LogConfig {
+        self.log_config.lock().unwrap().clone()
+    }
+
+    // the level applies at once to the installed subscriber, the rest when logging is set up
+    pub fn update_log_config(&self, update: impl FnOnce(&mut LogConfig)) {
+        let mut config = self.log_config.lock().unwrap();
+        update(&mut config);
+        crate::logging::reload_level(config.level);
+    }
+
+    pub fn trace_sample_rate(&self) -> u64 {
+        self.trace_sample_rate.load(Ordering::Relaxed)
+    }
+
+    pub fn set_trace_sample_rate(&self, rate: u64) {
+        self.trace_sample_rate.store(rate, Ordering::Relaxed);
+    }
+
+    pub fn decoder_limits(&self) -> DecoderLimits {
+        *self.decoder_limits.lock().unwrap()
+    }
+
+    pub fn update_decoder_limits(&self, f: impl FnOnce(&mut DecoderLimits)) {
+        f(&mut self.decoder_limits.lock().unwrap());
+    }
+
+    pub fn set_listpack_limits(&self) -> ListpackLimits {
+        *self.set_listpack_limits.lock().unwrap()
     }
 
-    pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+    pub fn update_set_listpack_limits(&self, f: impl FnOnce(&mut ListpackLimits)) {
+        f(&mut self.set_listpack_limits.lock().unwrap());
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    pub fn hash_listpack_limits(&self) -> ListpackLimits {
+        *self.hash_listpack_limits.lock().unwrap()
     }
 
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let hmap = self.hmap.entry(key).or_default();
-        hmap.insert(field, value);
+    pub fn update_hash_listpack_limits(&self, f: impl FnOnce(&mut ListpackLimits)) {
+        f(&mut self.hash_listpack_limits.lock().unwrap());
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    pub fn list_max_listpack_size(&self) -> i64 {
+        self.list_max_listpack_size.load(Ordering::Relaxed)
     }
 
-    pub fn hmget(&self, key: &str, fields: &[String]) -> Option<RespArray> {
-        self.hmap.get(key).map(|hmap| {
+    pub fn set_list_max_listpack_size(&self, size: i64) {
+        self.list_max_listpack_size.store(size, Ordering::Relaxed);
+    }
+
+    // whether the next command is traced
+    pub(crate) fn sample_trace(&self) -> bool {
+        match self.trace_sample_rate() {
+            0 => false,
+            1 => true,
+            rate => self
+                .trace_counter
+                .fetch_add(1, Ordering::Relaxed)
+                .is_multiple_of(rate),
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<RespFrame> {
+        self.value::<RespFrame>(key)
+            .map(|v| decode_string(v.value().clone()))
+    }
+
+    // a new value replaces the key with its TTL, like SET, whatever its type
+    pub fn set(&self, key: Key, value: RespFrame) {
+        self.replace_value(key, Value::String(encode_string(value)), false);
+        self.bump_string_epoch();
+    }
+
+    // the field is built before the hash's shard is locked, a hot hash holds its lock no
+    // longer than the lookup
+    pub fn hget(&self, key: &[u8], field: &[u8]) -> Option<RespFrame> {
+        let field = BulkString::new(field).into();
+        self.value::<FieldMap>(key)
+            .and_then(|v| v.get(&field).cloned())
+    }
+
+    pub fn hset(&self, key: Key, field: Vec<u8>, value: RespFrame) {
+        let limits = self.hash_listpack_limits();
+        let field = BulkString::new(field);
+        let mut hmap = self.value_or_default::<FieldMap>(key);
+        hmap.insert(field, value, limits);
+    }
+
+    // set the field only if the hash doesn't have it, under the lock of the hash's shard.
+    // True if it was set.
+    pub fn hsetnx(&self, key: Key, field: Vec<u8>, value: RespFrame) -> bool {
+        let limits = self.hash_listpack_limits();
+        let field: RespFrame = BulkString::new(field).into();
+        let mut hmap = self.value_or_default::<FieldMap>(key);
+        if hmap.contains_key(&field) {
+            return false;
+        }
+        hmap.insert(field, value, limits);
+        true
+    }
+
+    // random fields with their values, None for a missing hash. A positive count picks that
+    // many distinct fields, or all of them, a negative one may pick a field more than once.
+    pub fn hrandfield(&self, key: &[u8], count: i64) -> Option<Vec<(RespFrame, RespFrame)>> {
+        let hmap = self.value::<FieldMap>(key)?;
+        let entry = |index: usize| {
+            let (field, value) = hmap.get_index(index).expect("index within the hash");
+            (field.clone(), value.clone())
+        };
+        let len = hmap.len();
+        if len == 0 {
+            return Some(Vec::new());
+        }
+        if count < 0 {
+            let count = count.unsigned_abs() as usize;
+            return Some(
+                (0..count)
+                    .map(|_| entry(eviction::random() as usize % len))
+                    .collect(),
+            );
+        }
+        let count = (count as usize).min(len);
+        // the first count positions of a partial Fisher-Yates shuffle
+        let mut indexes: Vec<usize> = (0..len).collect();
+        for i in 0..count {
+            let j = i + eviction::random() as usize % (len - i);
+            indexes.swap(i, j);
+        }
+        Some(indexes[..count].iter().map(|&i| entry(i)).collect())
+    }
+
+    pub fn hgetall(&self, key: &[u8]) -> Option<RespMap> {
+        self.value::<FieldMap>(key).map(|v| v.to_map())
+    }
+
+    pub fn hmget(&self, key: &[u8], fields: &[Vec<u8>]) -> Option<RespArray> {
+        self.value::<FieldMap>(key).map(|hmap| {
             let mut data = Vec::with_capacity(fields.len());
             for field in fields {
-                match hmap.get(field) {
-                    Some(value) => data.push(value.value().clone()),
+                match hmap.get(&BulkString::new(field.as_slice()).into()) {
+                    Some(value) => data.push(value.clone()),
                     None => data.push(RespFrame::Null(crate::RespNull)),
                 }
             }
@@ -78,17 +515,133 @@ impl Backend {
         })
     }
 
-    pub fn sadd(&self, key: String, members: Vec<RespFrame>) {
-        let hset = self.set.entry(key).or_default();
+    pub fn sadd(&self, key: Key, members: Vec<RespFrame>) {
+        let limits = self.set_listpack_limits();
+        let mut set = self.value_or_default::<MemberSet>(key);
         for member in members {
-            hset.insert(member, ());
+            set.insert(member, limits);
+        }
+    }
+
+    pub fn s_is_member(&self, key: &[u8], member: RespFrame) -> bool {
+        match self.value::<MemberSet>(key) {
+            Some(set) => set.contains(&member),
+            None => false,
+        }
+    }
+
+    pub fn smismember(&self, key: &[u8], members: &[RespFrame]) -> Vec<bool> {
+        match self.value::<MemberSet>(key) {
+            Some(set) => members.iter().map(|m| set.contains(m)).collect(),
+            None => vec![false; members.len()],
+        }
+    }
+
+    // the size of the intersection of the sets, counting stops at limit unless it is 0. The
+    // smallest set is filtered by the others one at a time, a missing key is an empty set.
+    pub fn sintercard(&self, keys: &[Key], limit: usize) -> usize {
+        let mut sizes = Vec::with_capacity(keys.len());
+        for key in keys {
+            match self.value::<MemberSet>(key) {
+                Some(set) => sizes.push((set.len(), key)),
+                None => return 0,
+            }
+        }
+        sizes.sort();
+        let mut sizes = sizes.into_iter();
+        let Some((_, smallest)) = sizes.next() else {
+            return 0;
+        };
+        let mut members: Vec<RespFrame> = match self.value::<MemberSet>(smallest) {
+            Some(set) => set.iter().cloned().collect(),
+            None => return 0,
+        };
+        for (_, key) in sizes {
+            match self.value::<MemberSet>(key) {
+                Some(set) => members.retain(|m| set.contains(m)),
+                None => return 0,
+            }
+        }
+        match limit {
+            0 => members.len(),
+            limit => members.len().min(limit),
+        }
+    }
+
+    pub fn pfadd(&self, key: Key, elements: &[Vec<u8>]) -> bool {
+        let mut created = false;
+        let mut hll = self.value_or_insert_with(key, || {
+            created = true;
+            HyperLogLog::new()
+        });
+        let mut changed = false;
+        for element in elements {
+            changed |= hll.add(element);
+        }
+        created || changed
+    }
+
+    pub fn pfcount(&self, keys: &[Key]) -> u64 {
+        match keys {
+            [key] => self
+                .value::<HyperLogLog>(key)
+                .map(|hll| hll.count())
+                .unwrap_or(0),
+            _ => {
+                let mut union = HyperLogLog::new();
+                for key in keys {
+                    if let Some(hll) = self.value::<HyperLogLog>(key) {
+                        union.merge(&hll);
+                    }
+                }
+                union.count()
+            }
         }
     }
 
-    pub fn s_is_member(&self, key: &str, member: RespFrame) -> bool {
-        match self.set.get(key) {
-            Some(hset) => hset.contains_key(&member),
+    pub fn pfmerge(&self, dest: Key, sources: &[Key]) {
+        let mut keys = sources.to_vec();
+        keys.push(dest.clone());
+        // two merges into the same dest would otherwise both start from its old value
+        self.with_keys_locked(&keys, || {
+            let mut merged = self
+                .value::<HyperLogLog>(&dest)
+                .map(|v| v.clone())
+                .unwrap_or_default();
+            for key in sources {
+                if let Some(hll) = self.value::<HyperLogLog>(key) {
+                    merged.merge(&hll);
+                }
+            }
+            self.insert_value(dest, merged);
+        })
+    }
+
+    pub fn hll_encoding(&self, key: &[u8]) -> Option<HllEncoding> {
+        self.value::<HyperLogLog>(key).map(|hll| hll.encoding())
+    }
+
+    pub fn hll_promote(&self, key: &[u8]) -> bool {
+        match self.value_mut::<HyperLogLog>(key) {
+            Some(mut hll) => {
+                hll.promote();
+                true
+            }
             None => false,
         }
     }
+
+    pub fn object_encoding(&self, key: &[u8]) -> Option<&'static str> {
+        let entry = self.db.get(key)?;
+        Some(match &entry.value {
+            Value::String(RespFrame::Integer(_)) => "int",
+            Value::String(_) => "raw",
+            Value::Set(set) => set.encoding().as_str(),
+            Value::Hash(hash) => hash.encoding().as_str(),
+            Value::List(list) => list.encoding().as_str(),
+            Value::Stream(_) => "stream",
+            Value::ZSet(_) => "skiplist",
+            Value::HyperLogLog(hll) => hll.encoding().as_str(),
+        })
+    }
 }
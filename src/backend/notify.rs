@@ -0,0 +1,180 @@
+use super::Backend;
+use std::sync::atomic::Ordering;
+
+// keyspace notification classes, same letters as Redis' notify-keyspace-events
+pub const NOTIFY_KEYSPACE: u32 = 1 << 0; // K
+pub const NOTIFY_KEYEVENT: u32 = 1 << 1; // E
+pub const NOTIFY_GENERIC: u32 = 1 << 2; // g
+pub const NOTIFY_STRING: u32 = 1 << 3; // $
+pub const NOTIFY_LIST: u32 = 1 << 4; // l
+pub const NOTIFY_SET: u32 = 1 << 5; // s
+pub const NOTIFY_HASH: u32 = 1 << 6; // h
+pub const NOTIFY_ZSET: u32 = 1 << 7; // z
+pub const NOTIFY_EXPIRED: u32 = 1 << 8; // x
+pub const NOTIFY_EVICTED: u32 = 1 << 9; // e
+pub const NOTIFY_STREAM: u32 = 1 << 10; // t
+pub const NOTIFY_KEY_MISS: u32 = 1 << 11; // m
+pub const NOTIFY_NEW: u32 = 1 << 12; // n
+pub const NOTIFY_ALL: u32 = NOTIFY_GENERIC
+    | NOTIFY_STRING
+    | NOTIFY_LIST
+    | NOTIFY_SET
+    | NOTIFY_HASH
+    | NOTIFY_ZSET
+    | NOTIFY_EXPIRED
+    | NOTIFY_EVICTED
+    | NOTIFY_STREAM; // A
+
+pub fn notify_flags_from_str(s: &str) -> Option<u32> {
+    let mut flags = 0;
+    for c in s.chars() {
+        flags |= match c {
+            'A' => NOTIFY_ALL,
+            'g' => NOTIFY_GENERIC,
+            '$' => NOTIFY_STRING,
+            'l' => NOTIFY_LIST,
+            's' => NOTIFY_SET,
+            'h' => NOTIFY_HASH,
+            'z' => NOTIFY_ZSET,
+            'x' => NOTIFY_EXPIRED,
+            'e' => NOTIFY_EVICTED,
+            'K' => NOTIFY_KEYSPACE,
+            'E' => NOTIFY_KEYEVENT,
+            't' => NOTIFY_STREAM,
+            'm' => NOTIFY_KEY_MISS,
+            'n' => NOTIFY_NEW,
+            _ => return None,
+        };
+    }
+    Some(flags)
+}
+
+pub fn notify_flags_to_string(flags: u32) -> String {
+    let mut s = String::new();
+    if flags & NOTIFY_ALL == NOTIFY_ALL {
+        s.push('A');
+    } else {
+        for (flag, c) in [
+            (NOTIFY_GENERIC, 'g'),
+            (NOTIFY_STRING, '$'),
+            (NOTIFY_LIST, 'l'),
+            (NOTIFY_SET, 's'),
+            (NOTIFY_HASH, 'h'),
+            (NOTIFY_ZSET, 'z'),
+            (NOTIFY_EXPIRED, 'x'),
+            (NOTIFY_EVICTED, 'e'),
+            (NOTIFY_STREAM, 't'),
+        ] {
+            if flags & flag != 0 {
+                s.push(c);
+            }
+        }
+    }
+    for (flag, c) in [
+        (NOTIFY_KEYSPACE, 'K'),
+        (NOTIFY_KEYEVENT, 'E'),
+        (NOTIFY_KEY_MISS, 'm'),
+        (NOTIFY_NEW, 'n'),
+    ] {
+        if flags & flag != 0 {
+            s.push(c);
+        }
+    }
+    s
+}
+
+impl Backend {
+    pub fn notify_keyspace_events(&self) -> u32 {
+        self.notify_flags.load(Ordering::Relaxed)
+    }
+
+    pub fn set_notify_keyspace_events(&self, flags: u32) {
+        self.notify_flags.store(flags, Ordering::Relaxed);
+    }
+
+    // publish __keyspace@0__:<key> and __keyevent@0__:<event> messages if the class is enabled
+    pub fn notify_keyspace_event(&self, class: u32, event: &str, key: &[u8]) {
+        let flags = self.notify_keyspace_events();
+        if flags & class == 0 {
+            return;
+        }
+
+        // keys are binary safe, so are the channels named after them
+        if flags & NOTIFY_KEYSPACE != 0 {
+            let channel = [b"__keyspace@0__:".as_slice(), key].concat();
+            self.pubsub.publish(&channel, event.as_bytes());
+        }
+        if flags & NOTIFY_KEYEVENT != 0 {
+            let channel = [b"__keyevent@0__:".as_slice(), event.as_bytes()].concat();
+            self.pubsub.publish(&channel, key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, PushSender, RespArray};
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_notify_flags_conversion() {
+        assert_eq!(notify_flags_from_str(""), Some(0));
+        assert_eq!(
+            notify_flags_from_str("KEA"),
+            Some(NOTIFY_KEYSPACE | NOTIFY_KEYEVENT | NOTIFY_ALL)
+        );
+        assert_eq!(notify_flags_from_str("Kq"), None);
+        assert_eq!(
+            notify_flags_to_string(notify_flags_from_str("EKA").unwrap()),
+            "AKE"
+        );
+        assert_eq!(
+            notify_flags_to_string(notify_flags_from_str("Eg$").unwrap()),
+            "g$E"
+        );
+    }
+
+    #[test]
+    fn test_notify_keyspace_event() {
+        let backend = Backend::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        backend
+            .pubsub
+            .psubscribe(b"__key*__:*".to_vec(), 1, PushSender::new(tx));
+
+        // disabled by default
+        backend.notify_keyspace_event(NOTIFY_STRING, "set", b"foo");
+        assert!(rx.try_recv().is_err());
+
+        backend.set_notify_keyspace_events(notify_flags_from_str("K$").unwrap());
+        backend.notify_keyspace_event(NOTIFY_HASH, "hset", b"foo");
+        assert!(rx.try_recv().is_err());
+
+        backend.notify_keyspace_event(NOTIFY_STRING, "set", b"foo");
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespArray::new(vec![
+                BulkString::from("pmessage").into(),
+                BulkString::from("__key*__:*").into(),
+                BulkString::from("__keyspace@0__:foo").into(),
+                BulkString::from("set").into(),
+            ])
+            .into()
+        );
+        assert!(rx.try_recv().is_err());
+
+        // the channel of a key that isn't UTF-8 has the key as it is
+        backend.notify_keyspace_event(NOTIFY_STRING, "set", b"\xff\xfe");
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespArray::new(vec![
+                BulkString::from("pmessage").into(),
+                BulkString::from("__key*__:*").into(),
+                BulkString::from(b"__keyspace@0__:\xff\xfe".as_slice()).into(),
+                BulkString::from("set").into(),
+            ])
+            .into()
+        );
+    }
+}
@@ -0,0 +1,219 @@
+// client-output-buffer-limit for pub/sub clients. Messages are queued on the connection
+// until its task writes them, so a subscriber that doesn't read grows the queue without
+// bound. Every connection counts the bytes it has queued: past the hard limit, or past the
+// soft limit for longer than the soft seconds, a publish drops the message and the client
+// is disconnected, like in Redis.
+
+use super::{parse_bytes, stats::frame_size};
+use crate::RespFrame;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+// a limit of 0 is no limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBufferLimit {
+    pub hard: u64,
+    pub soft: u64,
+    pub soft_seconds: u64,
+}
+
+impl Default for OutputBufferLimit {
+    // the pubsub class defaults of Redis: 32mb 8mb 60
+    fn default() -> Self {
+        OutputBufferLimit {
+            hard: 32 * 1024 * 1024,
+            soft: 8 * 1024 * 1024,
+            soft_seconds: 60,
+        }
+    }
+}
+
+impl OutputBufferLimit {
+    // the CONFIG SET value, "<class> <hard> <soft> <soft seconds>" for one or more classes.
+    // Only pub/sub clients are limited, so pubsub is the only class accepted.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let words: Vec<&str> = value.split_whitespace().collect();
+        if words.is_empty() || !words.len().is_multiple_of(4) {
+            return Err("Wrong number of arguments in buffer limit configuration.".to_string());
+        }
+        let mut limit = None;
+        for class in words.chunks(4) {
+            if !class[0].eq_ignore_ascii_case("pubsub") {
+                return Err(format!("Invalid client class specified: {}", class[0]));
+            }
+            limit = Some(OutputBufferLimit {
+                hard: parse_bytes(class[1])?,
+                soft: parse_bytes(class[2])?,
+                soft_seconds: class[3]
+                    .parse()
+                    .map_err(|_| format!("argument must be a positive integer: {}", class[3]))?,
+            });
+        }
+        Ok(limit.unwrap_or_default())
+    }
+}
+
+impl fmt::Display for OutputBufferLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pubsub {} {} {}",
+            self.hard, self.soft, self.soft_seconds
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct OutputBuffer {
+    queued: AtomicUsize,
+    // when the queue went over the soft limit, None while under it
+    over_soft_since: Mutex<Option<Instant>>,
+    // the limit was hit, the connection closes instead of writing what is queued
+    overflowed: AtomicBool,
+}
+
+// the sending side of a connection's push channel, which counts what it queues
+#[derive(Debug, Clone)]
+pub struct PushSender {
+    sender: UnboundedSender<RespFrame>,
+    buffer: Arc<OutputBuffer>,
+}
+
+impl PushSender {
+    pub fn new(sender: UnboundedSender<RespFrame>) -> Self {
+        PushSender {
+            sender,
+            buffer: Arc::default(),
+        }
+    }
+
+    // queue a frame whatever the limits, e.g. a subscribe confirmation
+    pub fn send(&self, frame: RespFrame) -> bool {
+        let size = frame_size(&frame);
+        if self.sender.send(frame).is_err() {
+            return false;
+        }
+        self.buffer.queued.fetch_add(size, Ordering::Relaxed);
+        true
+    }
+
+    // queue a pub/sub message, unless it takes the queue over the limit. Then the message
+    // is dropped and the client marked to be disconnected.
+    pub fn send_limited(&self, frame: RespFrame, limit: OutputBufferLimit) -> bool {
+        if self.overflowed() {
+            return false;
+        }
+        let queued = (self.queued() + frame_size(&frame)) as u64;
+        if limit.hard > 0 && queued > limit.hard || self.over_soft_limit(queued, limit) {
+            self.buffer.overflowed.store(true, Ordering::Relaxed);
+            return false;
+        }
+        self.send(frame)
+    }
+
+    // the connection wrote a frame it got from the channel
+    pub fn sent(&self, frame: &RespFrame) {
+        let size = frame_size(frame);
+        let _ = self
+            .buffer
+            .queued
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |queued| {
+                Some(queued.saturating_sub(size))
+            });
+    }
+
+    pub fn queued(&self) -> usize {
+        self.buffer.queued.load(Ordering::Relaxed)
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.buffer.overflowed.load(Ordering::Relaxed)
+    }
+
+    fn over_soft_limit(&self, queued: u64, limit: OutputBufferLimit) -> bool {
+        let mut since = self.buffer.over_soft_since.lock().unwrap();
+        if limit.soft == 0 || queued <= limit.soft {
+            *since = None;
+            return false;
+        }
+        let since = since.get_or_insert_with(Instant::now);
+        since.elapsed() >= Duration::from_secs(limit.soft_seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_output_buffer_limit_parse() {
+        assert_eq!(
+            OutputBufferLimit::parse("pubsub 32mb 8mb 60"),
+            Ok(OutputBufferLimit::default())
+        );
+        let limit = OutputBufferLimit::parse("PUBSUB 1kb 0 10").unwrap();
+        assert_eq!(limit.to_string(), "pubsub 1024 0 10");
+        assert!(OutputBufferLimit::parse("pubsub 1kb 0").is_err());
+        assert!(OutputBufferLimit::parse("normal 0 0 0").is_err());
+        assert!(OutputBufferLimit::parse("pubsub 1kb 0 x").is_err());
+    }
+
+    #[test]
+    fn test_push_sender_hard_limit() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let sender = PushSender::new(tx);
+        let frame: RespFrame = BulkString::from("hello").into();
+        let size = frame_size(&frame) as u64;
+        let limit = OutputBufferLimit {
+            hard: 2 * size,
+            soft: 0,
+            soft_seconds: 0,
+        };
+
+        assert!(sender.send_limited(frame.clone(), limit));
+        assert!(sender.send_limited(frame.clone(), limit));
+        assert!(!sender.overflowed());
+        assert!(!sender.send_limited(frame.clone(), limit));
+        assert!(sender.overflowed());
+
+        let received = rx.try_recv().unwrap();
+        sender.sent(&received);
+        assert_eq!(sender.queued() as u64, size);
+        // once over the limit the client is on its way out, nothing more is queued
+        assert!(!sender.send_limited(frame, limit));
+    }
+
+    #[test]
+    fn test_push_sender_soft_limit() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let sender = PushSender::new(tx);
+        let frame: RespFrame = BulkString::from("hello").into();
+        let limit = OutputBufferLimit {
+            hard: 0,
+            soft: 1,
+            soft_seconds: 0,
+        };
+        // the first message over the soft limit starts the clock, 0 seconds are over at once
+        assert!(!sender.send_limited(frame.clone(), limit));
+        assert!(sender.overflowed());
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let sender = PushSender::new(tx);
+        let limit = OutputBufferLimit {
+            soft_seconds: 60,
+            ..limit
+        };
+        assert!(sender.send_limited(frame.clone(), limit));
+        assert!(sender.send_limited(frame, limit));
+        assert!(!sender.overflowed());
+    }
+}
@@ -0,0 +1,118 @@
+// CLIENT PAUSE: the commands of the clients are held, not refused, until the pause ends,
+// so that in a manual failover the replicas catch up with a master that takes no more
+// writes. WRITE holds the write commands only, ALL every command. The connections keep
+// reading what their clients send meanwhile and run it once the pause is over.
+//
+// The master link and the replicas are never paused. The active expire cycle is, or the
+// DELs of the keys it expires would go on changing the data set.
+
+use super::{clock::now_ms, Backend};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PauseMode {
+    Write,
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientPause {
+    pub mode: PauseMode,
+    // unix time in milliseconds
+    pub until: u64,
+}
+
+impl ClientPause {
+    fn holds(&self, write: bool) -> bool {
+        (write || self.mode == PauseMode::All) && self.until > now_ms()
+    }
+}
+
+impl Backend {
+    // pause the clients until the unix time in milliseconds. A pause already on goes on
+    // until the later of both ends, in the stricter of both modes.
+    pub fn pause_clients(&self, mode: PauseMode, until: u64) {
+        let pause = ClientPause { mode, until };
+        self.client_pause.send_modify(|current| {
+            *current = match *current {
+                Some(old) if old.until > now_ms() => Some(ClientPause {
+                    mode: old.mode.max(mode),
+                    until: old.until.max(until),
+                }),
+                _ => Some(pause),
+            }
+        });
+    }
+
+    pub fn unpause_clients(&self) {
+        self.client_pause.send_replace(None);
+    }
+
+    // the pause on, if any
+    pub fn client_pause(&self) -> Option<ClientPause> {
+        (*self.client_pause.borrow()).filter(|pause| pause.until > now_ms())
+    }
+
+    // whether a command, a write or not, is held by the pause
+    pub fn is_paused(&self, write: bool) -> bool {
+        self.client_pause
+            .borrow()
+            .is_some_and(|pause| pause.holds(write))
+    }
+
+    // resolves once the pause doesn't hold the command anymore
+    pub async fn wait_for_unpause(&self, write: bool) {
+        let mut receiver = self.client_pause.subscribe();
+        loop {
+            let Some(pause) = *receiver.borrow_and_update() else {
+                return;
+            };
+            if !pause.holds(write) {
+                return;
+            }
+            let left = Duration::from_millis(pause.until.saturating_sub(now_ms()));
+            tokio::select! {
+                _ = tokio::time::sleep(left) => {}
+                _ = receiver.changed() => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_client_pause() {
+        let backend = Backend::new();
+        assert!(!backend.is_paused(true));
+        backend.wait_for_unpause(true).await;
+
+        let now = now_ms();
+        backend.pause_clients(PauseMode::Write, now + 50);
+        assert!(backend.is_paused(true));
+        assert!(!backend.is_paused(false));
+        // a shorter pause doesn't end it sooner, but makes it stricter
+        backend.pause_clients(PauseMode::All, now + 10);
+        assert_eq!(
+            backend.client_pause(),
+            Some(ClientPause {
+                mode: PauseMode::All,
+                until: now + 50
+            })
+        );
+        backend.wait_for_unpause(false).await;
+        assert!(now_ms() >= now + 50);
+        assert_eq!(backend.client_pause(), None);
+
+        backend.pause_clients(PauseMode::All, now_ms() + 60_000);
+        let waiter = backend.clone();
+        let waiting = tokio::spawn(async move { waiter.wait_for_unpause(false).await });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        backend.unpause_clients();
+        waiting.await.unwrap();
+        assert!(!backend.is_paused(true));
+    }
+}
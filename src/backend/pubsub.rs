@@ -0,0 +1,243 @@
+use super::{glob_match, OutputBufferLimit, PushSender};
+use crate::{BulkString, RespArray, RespFrame};
+use dashmap::DashMap;
+use std::sync::Mutex;
+
+// client id -> sender of the client's push channel
+type Subscribers = DashMap<u64, PushSender>;
+
+#[derive(Debug, Default)]
+pub struct PubSub {
+    channels: DashMap<Vec<u8>, Subscribers>,
+    patterns: DashMap<Vec<u8>, Subscribers>,
+    // SSUBSCRIBE channels, apart from the others: in a cluster they belong to the slot of
+    // their name, here every shard channel is served by this node
+    shard_channels: DashMap<Vec<u8>, Subscribers>,
+    // client-output-buffer-limit of the pubsub class
+    output_buffer_limit: Mutex<OutputBufferLimit>,
+}
+
+impl PubSub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, channel: Vec<u8>, id: u64, sender: PushSender) {
+        self.channels.entry(channel).or_default().insert(id, sender);
+    }
+
+    pub fn unsubscribe(&self, channel: &[u8], id: u64) {
+        if let Some(subscribers) = self.channels.get(channel) {
+            subscribers.remove(&id);
+        }
+        self.channels
+            .remove_if(channel, |_, subscribers| subscribers.is_empty());
+    }
+
+    pub fn psubscribe(&self, pattern: Vec<u8>, id: u64, sender: PushSender) {
+        self.patterns.entry(pattern).or_default().insert(id, sender);
+    }
+
+    pub fn punsubscribe(&self, pattern: &[u8], id: u64) {
+        if let Some(subscribers) = self.patterns.get(pattern) {
+            subscribers.remove(&id);
+        }
+        self.patterns
+            .remove_if(pattern, |_, subscribers| subscribers.is_empty());
+    }
+
+    pub fn ssubscribe(&self, channel: Vec<u8>, id: u64, sender: PushSender) {
+        self.shard_channels
+            .entry(channel)
+            .or_default()
+            .insert(id, sender);
+    }
+
+    pub fn sunsubscribe(&self, channel: &[u8], id: u64) {
+        if let Some(subscribers) = self.shard_channels.get(channel) {
+            subscribers.remove(&id);
+        }
+        self.shard_channels
+            .remove_if(channel, |_, subscribers| subscribers.is_empty());
+    }
+
+    pub fn output_buffer_limit(&self) -> OutputBufferLimit {
+        *self.output_buffer_limit.lock().unwrap()
+    }
+
+    pub fn set_output_buffer_limit(&self, limit: OutputBufferLimit) {
+        *self.output_buffer_limit.lock().unwrap() = limit;
+    }
+
+    // deliver the message to channel and pattern subscribers, returns the number of receivers.
+    // Subscribers over their output buffer limit don't get it and are disconnected.
+    pub fn publish(&self, channel: &[u8], message: &[u8]) -> usize {
+        let limit = self.output_buffer_limit();
+        let mut receivers = 0;
+        if let Some(subscribers) = self.channels.get(channel) {
+            let frame: RespFrame = RespArray::new(vec![
+                BulkString::from("message").into(),
+                BulkString::from(channel).into(),
+                BulkString::from(message).into(),
+            ])
+            .into();
+            for subscriber in subscribers.iter() {
+                if subscriber.value().send_limited(frame.clone(), limit) {
+                    receivers += 1;
+                }
+            }
+        }
+
+        for entry in self.patterns.iter() {
+            let pattern = entry.key();
+            if !glob_match(pattern, channel, false) {
+                continue;
+            }
+            let frame: RespFrame = RespArray::new(vec![
+                BulkString::from("pmessage").into(),
+                BulkString::from(pattern.as_slice()).into(),
+                BulkString::from(channel).into(),
+                BulkString::from(message).into(),
+            ])
+            .into();
+            for subscriber in entry.value().iter() {
+                if subscriber.value().send_limited(frame.clone(), limit) {
+                    receivers += 1;
+                }
+            }
+        }
+        receivers
+    }
+
+    // deliver the message to the subscribers of the shard channel only, patterns don't
+    // match shard channels
+    pub fn spublish(&self, channel: &[u8], message: &[u8]) -> usize {
+        let Some(subscribers) = self.shard_channels.get(channel) else {
+            return 0;
+        };
+        let limit = self.output_buffer_limit();
+        let frame: RespFrame = RespArray::new(vec![
+            BulkString::from("smessage").into(),
+            BulkString::from(channel).into(),
+            BulkString::from(message).into(),
+        ])
+        .into();
+        subscribers
+            .iter()
+            .filter(|subscriber| subscriber.value().send_limited(frame.clone(), limit))
+            .count()
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        !self.channels.is_empty() || !self.patterns.is_empty() || !self.shard_channels.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_publish_to_channel_and_pattern() {
+        let pubsub = PubSub::new();
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (tx2, mut rx2) = mpsc::unbounded_channel();
+        pubsub.subscribe(b"news".to_vec(), 1, PushSender::new(tx1));
+        pubsub.psubscribe(b"n*".to_vec(), 2, PushSender::new(tx2));
+
+        assert_eq!(pubsub.publish(b"news", b"hello"), 2);
+        assert_eq!(
+            rx1.try_recv().unwrap(),
+            RespArray::new(vec![
+                BulkString::from("message").into(),
+                BulkString::from("news").into(),
+                BulkString::from("hello").into(),
+            ])
+            .into()
+        );
+        assert_eq!(
+            rx2.try_recv().unwrap(),
+            RespArray::new(vec![
+                BulkString::from("pmessage").into(),
+                BulkString::from("n*").into(),
+                BulkString::from("news").into(),
+                BulkString::from("hello").into(),
+            ])
+            .into()
+        );
+
+        assert_eq!(pubsub.publish(b"other", b"hello"), 0);
+    }
+
+    #[test]
+    fn test_shard_channels() {
+        let pubsub = PubSub::new();
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (tx2, mut rx2) = mpsc::unbounded_channel();
+        pubsub.ssubscribe(b"news".to_vec(), 1, PushSender::new(tx1));
+        pubsub.psubscribe(b"*".to_vec(), 2, PushSender::new(tx2));
+
+        // shard channels and the other channels don't see each other's messages
+        assert_eq!(pubsub.spublish(b"news", b"hello"), 1);
+        assert_eq!(
+            rx1.try_recv().unwrap(),
+            RespArray::new(vec![
+                BulkString::from("smessage").into(),
+                BulkString::from("news").into(),
+                BulkString::from("hello").into(),
+            ])
+            .into()
+        );
+        assert!(rx2.try_recv().is_err());
+        assert_eq!(pubsub.publish(b"news", b"hello"), 1);
+        assert!(rx1.try_recv().is_err());
+
+        pubsub.sunsubscribe(b"news", 1);
+        pubsub.punsubscribe(b"*", 2);
+        assert!(!pubsub.has_subscribers());
+        assert_eq!(pubsub.spublish(b"news", b"hello"), 0);
+    }
+
+    #[test]
+    fn test_unsubscribe() {
+        let pubsub = PubSub::new();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let sender = PushSender::new(tx);
+        pubsub.subscribe(b"news".to_vec(), 1, sender.clone());
+        pubsub.psubscribe(b"n*".to_vec(), 1, sender);
+        assert!(pubsub.has_subscribers());
+
+        pubsub.unsubscribe(b"news", 1);
+        pubsub.punsubscribe(b"n*", 1);
+        assert!(!pubsub.has_subscribers());
+        assert_eq!(pubsub.publish(b"news", b"hello"), 0);
+    }
+
+    #[test]
+    fn test_publish_over_output_buffer_limit() {
+        let pubsub = PubSub::new();
+        pubsub.set_output_buffer_limit(OutputBufferLimit {
+            hard: 1024,
+            soft: 0,
+            soft_seconds: 0,
+        });
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let sender = PushSender::new(tx);
+        pubsub.subscribe(b"news".to_vec(), 1, sender.clone());
+
+        // the subscriber doesn't read, messages pile up until the limit
+        let mut delivered = 0;
+        while pubsub.publish(b"news", b"hello") == 1 {
+            delivered += 1;
+        }
+        assert!(delivered > 0);
+        assert!(sender.overflowed());
+        assert!(sender.queued() <= 1024);
+        assert_eq!(pubsub.publish(b"news", b"hello"), 0);
+        for _ in 0..delivered {
+            assert!(rx.try_recv().is_ok());
+        }
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,546 @@
+// Master side of replication. Write commands are propagated as RESP arrays into the
+// replication stream, whose bytes are numbered by the replication offset. The last
+// repl-backlog-size bytes of the stream are kept in the backlog, so a replica that lost
+// its link can continue from its offset instead of loading a full snapshot again.
+//
+// Writes execute while holding the write barrier shared, and propagate before releasing
+// it. A full sync holds it exclusively while taking the snapshot and registering the
+// replica, so every write is either in the snapshot or in the stream sent after it,
+// never in both. Blocking commands don't hold it while they wait, see CommandFlags::BLOCKING.
+//
+// The server can also be a replica of another master, the link itself is run by
+// crate::replica. Every REPLICAOF starts a new generation of the link, a link task that
+// is no longer current stops without applying anything more.
+
+use super::{eviction::random, Backend};
+use crate::{BulkString, RespArray, RespEncode, RespFrame};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        Notify, RwLock, RwLockReadGuard,
+    },
+    task::AbortHandle,
+    time::{timeout_at, Instant},
+};
+
+pub const DEFAULT_REPL_BACKLOG_SIZE: usize = 1024 * 1024;
+const REPLID_LEN: usize = 40;
+
+#[derive(Debug)]
+pub struct Replication {
+    replid: String,
+    // there is no stream before the first replica connects
+    active: AtomicBool,
+    state: Mutex<ReplicationState>,
+    barrier: RwLock<()>,
+    // signaled when a replica acknowledges an offset, for WAIT
+    acked: Notify,
+    // the master this server replicates, None when it is a master itself
+    master: Mutex<Option<MasterLink>>,
+    generation: AtomicU64,
+    // replicas reject writes from clients other than their master
+    read_only: AtomicBool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    // waiting to connect, or to reconnect after the link dropped
+    Connect,
+    // connected, in the handshake
+    Connecting,
+    // receiving the snapshot
+    Sync,
+    // applying the stream
+    Connected,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MasterInfo {
+    pub host: String,
+    pub port: u16,
+    pub state: LinkState,
+    // the replication ID of the master and the offset in its stream applied so far, -1
+    // before the first sync
+    pub replid: Option<String>,
+    pub offset: i64,
+}
+
+#[derive(Debug)]
+struct MasterLink {
+    info: MasterInfo,
+    generation: u64,
+    task: Option<AbortHandle>,
+}
+
+#[derive(Debug)]
+struct ReplicationState {
+    // offset of the next byte of the stream
+    offset: u64,
+    backlog: VecDeque<u8>,
+    backlog_size: usize,
+    // by client id
+    replicas: HashMap<u64, Replica>,
+}
+
+#[derive(Debug)]
+struct Replica {
+    addr: String,
+    listening_port: Option<u16>,
+    sender: UnboundedSender<Vec<u8>>,
+    // the offset the replica last acknowledged
+    ack_offset: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaInfo {
+    pub id: u64,
+    pub addr: String,
+    pub listening_port: Option<u16>,
+    pub ack_offset: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PsyncReply {
+    // the snapshot, the stream follows from offset
+    FullResync {
+        replid: String,
+        offset: u64,
+        snapshot: Vec<u8>,
+    },
+    // the part of the backlog the replica is missing, the stream follows
+    Continue {
+        replid: String,
+        backlog: Vec<u8>,
+    },
+}
+
+impl Replication {
+    pub fn new() -> Self {
+        Replication {
+            replid: new_replid(),
+            active: AtomicBool::new(false),
+            state: Mutex::new(ReplicationState {
+                offset: 0,
+                backlog: VecDeque::new(),
+                backlog_size: DEFAULT_REPL_BACKLOG_SIZE,
+                replicas: HashMap::new(),
+            }),
+            barrier: RwLock::new(()),
+            acked: Notify::new(),
+            master: Mutex::new(None),
+            generation: AtomicU64::new(0),
+            read_only: AtomicBool::new(true),
+        }
+    }
+
+    pub fn replid(&self) -> &str {
+        &self.replid
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    // held by write commands from before they execute until they are propagated
+    pub async fn write_barrier(&self) -> RwLockReadGuard<'_, ()> {
+        self.barrier.read().await
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.state.lock().unwrap().offset
+    }
+
+    pub fn backlog_size(&self) -> usize {
+        self.state.lock().unwrap().backlog_size
+    }
+
+    pub fn set_backlog_size(&self, size: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.backlog_size = size;
+        state.trim_backlog();
+    }
+
+    pub fn replicas(&self) -> Vec<ReplicaInfo> {
+        let state = self.state.lock().unwrap();
+        let mut replicas: Vec<ReplicaInfo> = state
+            .replicas
+            .iter()
+            .map(|(id, replica)| ReplicaInfo {
+                id: *id,
+                addr: replica.addr.clone(),
+                listening_port: replica.listening_port,
+                ack_offset: replica.ack_offset,
+            })
+            .collect();
+        replicas.sort_by_key(|replica| replica.id);
+        replicas
+    }
+
+    // append a write command to the stream, a no-op until a replica connected once
+    pub fn propagate(&self, command: &RespArray) {
+        if !self.is_active() {
+            return;
+        }
+        let bytes = RespFrame::from(command.clone()).encode();
+        let mut state = self.state.lock().unwrap();
+        state.offset += bytes.len() as u64;
+        state.backlog.extend(&bytes);
+        state.trim_backlog();
+        // replicas whose link is gone are removed when their connection closes
+        for replica in state.replicas.values() {
+            let _ = replica.sender.send(bytes.clone());
+        }
+    }
+
+    pub fn ack(&self, id: u64, offset: u64) {
+        if let Some(replica) = self.state.lock().unwrap().replicas.get_mut(&id) {
+            replica.ack_offset = replica.ack_offset.max(offset);
+        }
+        self.acked.notify_waiters();
+    }
+
+    // the number of replicas that acknowledged the stream up to offset
+    pub fn acked_replicas(&self, offset: u64) -> usize {
+        let state = self.state.lock().unwrap();
+        state
+            .replicas
+            .values()
+            .filter(|replica| replica.ack_offset >= offset)
+            .count()
+    }
+
+    // wait until numreplicas replicas acknowledged the stream up to offset or the timeout
+    // expires, None waits forever. Returns the number of replicas that did.
+    pub async fn wait_for_acks(
+        &self,
+        numreplicas: usize,
+        offset: u64,
+        timeout: Option<Duration>,
+    ) -> usize {
+        // timeouts are checked when parsed, one that overflows anyway waits forever
+        let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+        let mut asked = false;
+        loop {
+            // register for wake-ups before counting so no ACK is missed
+            let notified = self.acked.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let acked = self.acked_replicas(offset);
+            if acked >= numreplicas {
+                return acked;
+            }
+            // replicas ACK every second anyway, ask them to do it now
+            if !asked {
+                self.propagate(&RespArray::new(vec![
+                    BulkString::from("replconf").into(),
+                    BulkString::from("getack").into(),
+                    BulkString::from("*").into(),
+                ]));
+                asked = true;
+            }
+            match deadline {
+                Some(deadline) => {
+                    if timeout_at(deadline, notified).await.is_err() {
+                        return self.acked_replicas(offset);
+                    }
+                }
+                None => notified.await,
+            }
+        }
+    }
+
+    pub fn remove_replica(&self, id: u64) {
+        self.state.lock().unwrap().replicas.remove(&id);
+    }
+}
+
+impl Replication {
+    pub fn master(&self) -> Option<MasterInfo> {
+        self.master
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|link| link.info.clone())
+    }
+
+    pub fn is_replica(&self) -> bool {
+        self.master.lock().unwrap().is_some()
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    // replicate host:port, or stop replicating with None. The link task of the previous
+    // master is aborted. Returns the generation of the new link.
+    pub(crate) fn set_master(&self, master: Option<(String, u16)>) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let link = master.map(|(host, port)| MasterLink {
+            info: MasterInfo {
+                host,
+                port,
+                state: LinkState::Connect,
+                replid: None,
+                offset: -1,
+            },
+            generation,
+            task: None,
+        });
+        let old = std::mem::replace(&mut *self.master.lock().unwrap(), link);
+        if let Some(task) = old.and_then(|link| link.task) {
+            task.abort();
+        }
+        generation
+    }
+
+    // the task running the link of the generation
+    pub(crate) fn attach_link_task(&self, generation: u64, task: AbortHandle) {
+        match self.master.lock().unwrap().as_mut() {
+            Some(link) if link.generation == generation => link.task = Some(task),
+            // replaced in the meantime
+            _ => task.abort(),
+        }
+    }
+
+    pub(crate) fn is_current_link(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::SeqCst) == generation
+    }
+
+    // update the link if it is still the current one
+    pub(crate) fn update_link(&self, generation: u64, update: impl FnOnce(&mut MasterInfo)) {
+        if let Some(link) = self.master.lock().unwrap().as_mut() {
+            if link.generation == generation {
+                update(&mut link.info);
+            }
+        }
+    }
+}
+
+impl LinkState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LinkState::Connect => "connect",
+            LinkState::Connecting => "connecting",
+            LinkState::Sync => "sync",
+            LinkState::Connected => "connected",
+        }
+    }
+}
+
+impl Default for Replication {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReplicationState {
+    fn trim_backlog(&mut self) {
+        let excess = self.backlog.len().saturating_sub(self.backlog_size);
+        self.backlog.drain(..excess);
+    }
+
+    // offset of the first byte in the backlog
+    fn backlog_start(&self) -> u64 {
+        self.offset - self.backlog.len() as u64
+    }
+}
+
+impl Backend {
+    // register a replica that sent PSYNC replid offset (? and -1 when it has nothing),
+    // returns what to send it first and the receiver of the stream that follows
+    pub async fn psync(
+        &self,
+        id: u64,
+        addr: String,
+        listening_port: Option<u16>,
+        replid: &str,
+        offset: i64,
+    ) -> (PsyncReply, UnboundedReceiver<Vec<u8>>) {
+        let replication = &self.replication;
+        let _barrier = replication.barrier.write().await;
+        replication.active.store(true, Ordering::Release);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut state = replication.state.lock().unwrap();
+        let continues = replid == replication.replid
+            && u64::try_from(offset)
+                .is_ok_and(|offset| offset >= state.backlog_start() && offset <= state.offset);
+        let reply = if continues {
+            let skip = offset as u64 - state.backlog_start();
+            PsyncReply::Continue {
+                replid: replication.replid.clone(),
+                backlog: state.backlog.iter().skip(skip as usize).copied().collect(),
+            }
+        } else {
+            PsyncReply::FullResync {
+                replid: replication.replid.clone(),
+                offset: state.offset,
+                snapshot: self.snapshot(),
+            }
+        };
+        state.replicas.insert(
+            id,
+            Replica {
+                addr,
+                listening_port,
+                sender,
+                ack_offset: 0,
+            },
+        );
+        (reply, receiver)
+    }
+}
+
+// 40 random hex digits, like the replication ids and run ids of Redis
+pub(super) fn new_replid() -> String {
+    (0..REPLID_LEN)
+        .map(|_| char::from_digit((random() % 16) as u32, 16).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_full_resync_then_stream() {
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("1").into());
+        // nothing is kept before the first replica
+        backend.replication.propagate(&command(&["set", "a", "1"]));
+        assert_eq!(backend.replication.offset(), 0);
+
+        let (reply, mut receiver) = backend.psync(1, "r".to_string(), None, "?", -1).await;
+        let PsyncReply::FullResync {
+            replid,
+            offset,
+            snapshot,
+        } = reply
+        else {
+            panic!("expected a full resync");
+        };
+        assert_eq!(replid, backend.replication.replid());
+        assert_eq!(offset, 0);
+        let replica = Backend::new();
+        replica.restore_snapshot(&snapshot).unwrap();
+        assert_eq!(replica.get(b"a"), Some(BulkString::from("1").into()));
+
+        let set = command(&["set", "b", "2"]);
+        backend.replication.propagate(&set);
+        let bytes = receiver.recv().await.unwrap();
+        assert_eq!(bytes, RespFrame::from(set).encode());
+        assert_eq!(backend.replication.offset(), bytes.len() as u64);
+
+        backend.replication.ack(1, bytes.len() as u64);
+        assert_eq!(
+            backend.replication.replicas()[0].ack_offset,
+            bytes.len() as u64
+        );
+        backend.replication.remove_replica(1);
+        assert!(backend.replication.replicas().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_partial_resync() {
+        let backend = Backend::new();
+        let (_, _receiver) = backend.psync(1, "r".to_string(), None, "?", -1).await;
+        let first = RespFrame::from(command(&["set", "a", "1"])).encode();
+        let second = RespFrame::from(command(&["set", "b", "2"])).encode();
+        backend.replication.propagate(&command(&["set", "a", "1"]));
+        backend.replication.propagate(&command(&["set", "b", "2"]));
+
+        let replid = backend.replication.replid().to_string();
+        let (reply, _) = backend
+            .psync(2, "r".to_string(), None, &replid, first.len() as i64)
+            .await;
+        assert_eq!(
+            reply,
+            PsyncReply::Continue {
+                replid: replid.clone(),
+                backlog: second.clone(),
+            }
+        );
+
+        // the start of the stream fell out of the backlog
+        backend.replication.set_backlog_size(second.len());
+        let (reply, _) = backend.psync(3, "r".to_string(), None, &replid, 0).await;
+        assert!(matches!(reply, PsyncReply::FullResync { .. }));
+        // another replid is another history
+        let (reply, _) = backend
+            .psync(4, "r".to_string(), None, "0".repeat(40).as_str(), 0)
+            .await;
+        assert!(matches!(reply, PsyncReply::FullResync { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_acks() {
+        let backend = Backend::new();
+        let (_, _first) = backend.psync(1, "r".to_string(), None, "?", -1).await;
+        let (_, mut second) = backend.psync(2, "r".to_string(), None, "?", -1).await;
+        backend.replication.propagate(&command(&["set", "a", "1"]));
+        let offset = backend.replication.offset();
+
+        let replication = &backend.replication;
+        let (acked, _) = tokio::join!(replication.wait_for_acks(1, offset, None), async {
+            // the waiter asked for an ACK after the write
+            second.recv().await;
+            let getack = second.recv().await.unwrap();
+            assert_eq!(
+                getack,
+                RespFrame::from(command(&["replconf", "getack", "*"])).encode()
+            );
+            replication.ack(2, offset);
+        });
+        assert_eq!(acked, 1);
+        assert_eq!(
+            replication
+                .wait_for_acks(2, offset, Some(Duration::from_millis(10)))
+                .await,
+            1
+        );
+        assert_eq!(replication.wait_for_acks(0, offset, None).await, 1);
+    }
+
+    #[test]
+    fn test_master_link_generations() {
+        let replication = Replication::new();
+        assert!(!replication.is_replica());
+        let first = replication.set_master(Some(("localhost".to_string(), 6379)));
+        let second = replication.set_master(Some(("localhost".to_string(), 6380)));
+        assert!(!replication.is_current_link(first));
+        assert!(replication.is_current_link(second));
+
+        // updates of a replaced link are dropped
+        replication.update_link(first, |info| info.state = LinkState::Connected);
+        replication.update_link(second, |info| info.offset = 10);
+        let master = replication.master().unwrap();
+        assert_eq!((master.port, master.state), (6380, LinkState::Connect));
+        assert_eq!(master.offset, 10);
+
+        replication.set_master(None);
+        assert!(!replication.is_replica());
+        assert!(!replication.is_current_link(second));
+    }
+}
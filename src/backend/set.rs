@@ -0,0 +1,163 @@
+// Sets with few small members are kept in the listpack encoding, the members in a vector
+// that is searched linearly. A hash table per set costs far more memory than its members
+// when there are millions of tiny sets. A set is converted to the hashtable encoding once
+// it has more than set-max-listpack-entries members or gets a member longer than
+// set-max-listpack-value bytes, and is never converted back, like in Redis.
+
+use crate::RespFrame;
+use std::collections::HashSet;
+
+pub const DEFAULT_SET_MAX_LISTPACK_ENTRIES: usize = 128;
+pub const DEFAULT_SET_MAX_LISTPACK_VALUE: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetEncoding {
+    Listpack,
+    Hashtable,
+}
+
+// the largest sets, and hashes, kept in the listpack encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListpackLimits {
+    pub max_entries: usize,
+    pub max_value: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Members {
+    Listpack(Vec<RespFrame>),
+    Hashtable(HashSet<RespFrame>),
+}
+
+#[derive(Debug, Clone)]
+pub struct MemberSet {
+    members: Members,
+}
+
+impl SetEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SetEncoding::Listpack => "listpack",
+            SetEncoding::Hashtable => "hashtable",
+        }
+    }
+}
+
+impl Default for ListpackLimits {
+    fn default() -> Self {
+        ListpackLimits {
+            max_entries: DEFAULT_SET_MAX_LISTPACK_ENTRIES,
+            max_value: DEFAULT_SET_MAX_LISTPACK_VALUE,
+        }
+    }
+}
+
+impl ListpackLimits {
+    pub(crate) fn fits(&self, member: &RespFrame) -> bool {
+        match member {
+            RespFrame::BulkString(s) => s.len() <= self.max_value,
+            _ => true,
+        }
+    }
+}
+
+impl Default for MemberSet {
+    fn default() -> Self {
+        MemberSet {
+            members: Members::Listpack(Vec::new()),
+        }
+    }
+}
+
+impl MemberSet {
+    pub fn from_members(
+        members: impl IntoIterator<Item = RespFrame>,
+        limits: ListpackLimits,
+    ) -> Self {
+        let mut set = MemberSet::default();
+        for member in members {
+            set.insert(member, limits);
+        }
+        set
+    }
+
+    pub fn encoding(&self) -> SetEncoding {
+        match self.members {
+            Members::Listpack(_) => SetEncoding::Listpack,
+            Members::Hashtable(_) => SetEncoding::Hashtable,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.members {
+            Members::Listpack(members) => members.len(),
+            Members::Hashtable(members) => members.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, member: &RespFrame) -> bool {
+        match &self.members {
+            Members::Listpack(members) => members.contains(member),
+            Members::Hashtable(members) => members.contains(member),
+        }
+    }
+
+    // false when the member was already there
+    pub fn insert(&mut self, member: RespFrame, limits: ListpackLimits) -> bool {
+        if let Members::Listpack(members) = &mut self.members {
+            if members.contains(&member) {
+                return false;
+            }
+            if members.len() < limits.max_entries && limits.fits(&member) {
+                members.push(member);
+                return true;
+            }
+            self.members = Members::Hashtable(std::mem::take(members).into_iter().collect());
+        }
+        match &mut self.members {
+            Members::Hashtable(members) => members.insert(member),
+            Members::Listpack(_) => unreachable!("converted above"),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &RespFrame> + '_> {
+        match &self.members {
+            Members::Listpack(members) => Box::new(members.iter()),
+            Members::Hashtable(members) => Box::new(members.iter()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_set_encoding_conversion() {
+        let limits = ListpackLimits {
+            max_entries: 2,
+            max_value: 4,
+        };
+        let member = |s: &str| RespFrame::from(BulkString::from(s));
+
+        let mut set = MemberSet::default();
+        assert!(set.insert(member("a"), limits));
+        assert!(set.insert(member("b"), limits));
+        assert!(!set.insert(member("a"), limits));
+        assert_eq!(set.encoding(), SetEncoding::Listpack);
+        assert!(set.insert(member("c"), limits));
+        assert_eq!(set.encoding(), SetEncoding::Hashtable);
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&member("a")) && set.contains(&member("c")));
+
+        // a long member converts it too
+        let set = MemberSet::from_members(vec![member("a"), member("toolong")], limits);
+        assert_eq!(set.encoding(), SetEncoding::Hashtable);
+        assert_eq!(set.len(), 2);
+    }
+}
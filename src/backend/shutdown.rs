@@ -0,0 +1,189 @@
+// What the server does with its data before exiting on SIGTERM, like Redis's
+// shutdown-on-sigterm. Saving runs on a blocking thread and is bounded by
+// shutdown-timeout, so a stuck disk can't keep the process from exiting forever.
+//
+// SHUTDOWN, SIGTERM and embedders all stop the server with request_shutdown: the accept
+// loop stops, connections close once their command in flight is done, then the data is
+// persisted as the policy of the request says.
+
+use super::{Backend, SnapshotError};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+pub const SHUTDOWN_POLICIES: &[&str] = &["default", "save", "nosave"];
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShutdownPolicy {
+    // the snapshot is the only persistence, so the default is to save it
+    #[default]
+    Default,
+    Save,
+    NoSave,
+}
+
+impl ShutdownPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShutdownPolicy::Default => "default",
+            ShutdownPolicy::Save => "save",
+            ShutdownPolicy::NoSave => "nosave",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(ShutdownPolicy::Default),
+            "save" => Some(ShutdownPolicy::Save),
+            "nosave" => Some(ShutdownPolicy::NoSave),
+            _ => None,
+        }
+    }
+
+    fn saves(&self) -> bool {
+        !matches!(self, ShutdownPolicy::NoSave)
+    }
+}
+
+impl Backend {
+    pub fn shutdown_on_sigterm(&self) -> ShutdownPolicy {
+        *self.shutdown_on_sigterm.lock().unwrap()
+    }
+
+    pub fn set_shutdown_on_sigterm(&self, policy: ShutdownPolicy) {
+        *self.shutdown_on_sigterm.lock().unwrap() = policy;
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        *self.shutdown_timeout.lock().unwrap()
+    }
+
+    pub fn set_shutdown_timeout(&self, timeout: Duration) {
+        *self.shutdown_timeout.lock().unwrap() = timeout;
+    }
+
+    // ask the server to shut down, the first request wins
+    pub fn request_shutdown(&self, policy: ShutdownPolicy) {
+        self.shutdown_request
+            .send_if_modified(|request| match request {
+                Some(_) => false,
+                None => {
+                    *request = Some(policy);
+                    true
+                }
+            });
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown_request.borrow().is_some()
+    }
+
+    // resolves with the policy once a shutdown is requested
+    pub async fn shutdown_requested(&self) -> ShutdownPolicy {
+        let mut receiver = self.shutdown_request.subscribe();
+        let policy = receiver
+            .wait_for(Option::is_some)
+            .await
+            .ok()
+            .and_then(|request| *request);
+        match policy {
+            Some(policy) => policy,
+            // the backend holds the sender, it can't be closed
+            None => std::future::pending().await,
+        }
+    }
+
+    // wait for the connections to close, up to shutdown-timeout. Returns whether they all
+    // did: a client blocked in BLPOP holds its connection until the command returns.
+    pub async fn drain_connections(&self) -> bool {
+        let deadline = Instant::now().checked_add(self.shutdown_timeout());
+        let mut interval = tokio::time::interval(Duration::from_millis(10));
+        loop {
+            if self.stats().connected_clients == 0 {
+                return true;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return false;
+            }
+            interval.tick().await;
+        }
+    }
+
+    // persist what the policy asks for before the process exits. The caller should
+    // exit with an error status if this fails, the latest writes may be lost.
+    pub async fn shutdown(&self, policy: ShutdownPolicy) -> Result<(), SnapshotError> {
+        if !policy.saves() {
+            info!("Exiting without saving the snapshot");
+            return Ok(());
+        }
+        let timeout = self.shutdown_timeout();
+        let backend = self.clone();
+        let save = tokio::task::spawn_blocking(move || backend.save());
+        match tokio::time::timeout(timeout, save).await {
+            Ok(Ok(Ok(()))) => {
+                info!("Saved the snapshot to {} before exiting", self.dbfilename());
+                Ok(())
+            }
+            Ok(Ok(Err(e))) => {
+                error!("Failed to save the snapshot before exiting: {}", e);
+                Err(e)
+            }
+            Ok(Err(e)) => {
+                error!("Failed to save the snapshot before exiting: {}", e);
+                Err(SnapshotError::Io(std::io::Error::other(e)))
+            }
+            Err(_) => {
+                error!(
+                    "Saving the snapshot didn't finish within {}s, exiting anyway",
+                    timeout.as_secs()
+                );
+                Err(SnapshotError::Timeout(timeout))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use std::{fs, path::Path};
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let path =
+            std::env::temp_dir().join(format!("simple-redis-shutdown-{}.srdb", std::process::id()));
+        let backend = Backend::new();
+        backend.set_dbfilename(path.to_string_lossy().into_owned());
+        backend.set("k".into(), BulkString::from("v").into());
+
+        backend.shutdown(ShutdownPolicy::NoSave).await.unwrap();
+        assert!(!Path::new(&path).exists());
+
+        assert_eq!(backend.shutdown_on_sigterm(), ShutdownPolicy::Default);
+        backend.shutdown(ShutdownPolicy::Default).await.unwrap();
+        assert!(Path::new(&path).exists());
+        fs::remove_file(&path).unwrap();
+
+        // a directory that doesn't exist
+        backend.set_dbfilename(path.join("dump.srdb").to_string_lossy().into_owned());
+        assert!(backend.shutdown(ShutdownPolicy::Save).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_request_shutdown() {
+        let backend = Backend::new();
+        assert!(!backend.is_shutting_down());
+        let requested = tokio::spawn({
+            let backend = backend.clone();
+            async move { backend.shutdown_requested().await }
+        });
+        backend.request_shutdown(ShutdownPolicy::NoSave);
+        backend.request_shutdown(ShutdownPolicy::Save);
+        assert!(backend.is_shutting_down());
+        assert_eq!(requested.await.unwrap(), ShutdownPolicy::NoSave);
+        assert_eq!(backend.shutdown_requested().await, ShutdownPolicy::NoSave);
+        assert!(backend.drain_connections().await);
+    }
+}
@@ -0,0 +1,219 @@
+use super::clock::now_ms;
+use crate::{BulkString, RespArray, RespFrame};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+const SLOWLOG_LOG_SLOWER_THAN: i64 = 10000;
+const SLOWLOG_MAX_LEN: usize = 128;
+// same limits as Redis to keep a single entry small
+const SLOWLOG_ENTRY_MAX_ARGC: usize = 32;
+const SLOWLOG_ENTRY_MAX_STRING: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowLogEntry {
+    pub id: u64,
+    pub timestamp: u64,
+    pub duration: Duration,
+    pub args: Vec<RespFrame>,
+    pub client_addr: String,
+}
+
+#[derive(Debug)]
+pub struct SlowLog {
+    // in microseconds, negative disables the slowlog, 0 logs every command
+    log_slower_than: AtomicI64,
+    max_len: AtomicUsize,
+    next_id: AtomicU64,
+    entries: Mutex<VecDeque<SlowLogEntry>>,
+}
+
+impl SlowLog {
+    pub fn new() -> Self {
+        SlowLog {
+            log_slower_than: AtomicI64::new(SLOWLOG_LOG_SLOWER_THAN),
+            max_len: AtomicUsize::new(SLOWLOG_MAX_LEN),
+            next_id: AtomicU64::new(0),
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn log_slower_than(&self) -> i64 {
+        self.log_slower_than.load(Ordering::Relaxed)
+    }
+
+    pub fn set_log_slower_than(&self, micros: i64) {
+        self.log_slower_than.store(micros, Ordering::Relaxed);
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_len(&self, max_len: usize) {
+        self.max_len.store(max_len, Ordering::Relaxed);
+        let mut entries = self.entries.lock().unwrap();
+        entries.truncate(max_len);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.log_slower_than() >= 0
+    }
+
+    // whether a command that took this long goes in the slowlog
+    pub fn is_slow(&self, duration: Duration) -> bool {
+        let threshold = self.log_slower_than();
+        threshold >= 0 && duration.as_micros() >= threshold as u128
+    }
+
+    // record the command if its execution took longer than the threshold
+    pub fn record(&self, args: &[RespFrame], duration: Duration, client_addr: &str) {
+        if !self.is_slow(duration) {
+            return;
+        }
+
+        let entry = SlowLogEntry {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: now_ms() / 1000,
+            duration,
+            args: truncate_args(args),
+            client_addr: client_addr.to_string(),
+        };
+
+        let max_len = self.max_len();
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_front(entry);
+        entries.truncate(max_len);
+    }
+
+    // newest entries first, `count` of None returns all of them
+    pub fn get(&self, count: Option<usize>) -> Vec<SlowLogEntry> {
+        let entries = self.entries.lock().unwrap();
+        let count = count.unwrap_or(entries.len());
+        entries.iter().take(count).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn reset(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+impl Default for SlowLog {
+    fn default() -> Self {
+        SlowLog::new()
+    }
+}
+
+impl From<SlowLogEntry> for RespFrame {
+    fn from(entry: SlowLogEntry) -> Self {
+        RespArray::new(vec![
+            RespFrame::Integer(entry.id as i64),
+            RespFrame::Integer(entry.timestamp as i64),
+            RespFrame::Integer(entry.duration.as_micros() as i64),
+            RespArray::new(entry.args).into(),
+            BulkString::from(entry.client_addr).into(),
+            BulkString::from("").into(),
+        ])
+        .into()
+    }
+}
+
+fn truncate_args(args: &[RespFrame]) -> Vec<RespFrame> {
+    let mut ret = Vec::with_capacity(args.len().min(SLOWLOG_ENTRY_MAX_ARGC));
+    for (i, arg) in args.iter().enumerate() {
+        if i == SLOWLOG_ENTRY_MAX_ARGC - 1 && args.len() > SLOWLOG_ENTRY_MAX_ARGC {
+            let more = args.len() - SLOWLOG_ENTRY_MAX_ARGC + 1;
+            ret.push(BulkString::from(format!("... ({} more arguments)", more)).into());
+            break;
+        }
+        match arg {
+            RespFrame::BulkString(s) if s.len() > SLOWLOG_ENTRY_MAX_STRING => {
+                let more = s.len() - SLOWLOG_ENTRY_MAX_STRING;
+                let mut data = s[..SLOWLOG_ENTRY_MAX_STRING].to_vec();
+                data.extend_from_slice(format!("... ({} more bytes)", more).as_bytes());
+                ret.push(BulkString::new(data).into());
+            }
+            _ => ret.push(arg.clone()),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slowlog_record_threshold() {
+        let slowlog = SlowLog::new();
+        slowlog.set_log_slower_than(1000);
+
+        let args = vec![BulkString::from("get").into(), BulkString::from("k").into()];
+        slowlog.record(&args, Duration::from_micros(10), "127.0.0.1:1234");
+        assert_eq!(slowlog.len(), 0);
+
+        slowlog.record(&args, Duration::from_micros(2000), "127.0.0.1:1234");
+        assert_eq!(slowlog.len(), 1);
+
+        let entries = slowlog.get(None);
+        assert_eq!(entries[0].id, 0);
+        assert_eq!(entries[0].duration, Duration::from_micros(2000));
+        assert_eq!(entries[0].args, args);
+
+        slowlog.set_log_slower_than(-1);
+        slowlog.record(&args, Duration::from_secs(1), "127.0.0.1:1234");
+        assert_eq!(slowlog.len(), 1);
+    }
+
+    #[test]
+    fn test_slowlog_ring_buffer() {
+        let slowlog = SlowLog::new();
+        slowlog.set_log_slower_than(0);
+        slowlog.set_max_len(2);
+
+        for i in 0..5 {
+            let args = vec![BulkString::from(format!("cmd{}", i)).into()];
+            slowlog.record(&args, Duration::from_micros(1), "");
+        }
+        let entries = slowlog.get(None);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, 4);
+        assert_eq!(entries[1].id, 3);
+        assert_eq!(slowlog.get(Some(1)).len(), 1);
+
+        slowlog.reset();
+        assert!(slowlog.is_empty());
+    }
+
+    #[test]
+    fn test_slowlog_truncate_args() {
+        let mut args: Vec<RespFrame> = (0..40)
+            .map(|i| BulkString::from(format!("{}", i)).into())
+            .collect();
+        args[0] = BulkString::new(vec![b'a'; 200]).into();
+
+        let ret = truncate_args(&args);
+        assert_eq!(ret.len(), SLOWLOG_ENTRY_MAX_ARGC);
+        assert_eq!(
+            ret[SLOWLOG_ENTRY_MAX_ARGC - 1],
+            BulkString::from("... (9 more arguments)").into()
+        );
+        match &ret[0] {
+            RespFrame::BulkString(s) => assert!(s.ends_with(b"... (72 more bytes)")),
+            _ => panic!("expect bulk string"),
+        }
+    }
+}
@@ -0,0 +1,648 @@
+// Snapshot file format:
+//
+//   "SREDIS" <4 ASCII digits version> <record>... ["eof"]
+//
+// every record is a RESP array whose first element is the type of the value:
+//
+//   ["string", key, value]
+//   ["hash", key, field, value, ...]
+//   ["set", key, member, ...]
+//   ["list", key, element, ...]
+//   ["hll", key, "sparse" | "dense", registers]
+//   ["stream", key, last-id, id, [field, value, ...], ...]
+//   ["zset", key, member, score, ...]                (since version 2)
+//   ["meta", key, last-access, freq]                 (since version 3)
+//   ["expire", key, expire-at]                       (since version 4)
+//
+// a meta record follows the value of its key and keeps what OBJECT IDLETIME and FREQ report: the
+// last access as unix time in milliseconds, so the idle time goes on counting while the
+// server is down, and the LFU counter as it was at that access. An expire record keeps the
+// unix time in milliseconds a key with a TTL expires at, not the time it had left, so a key
+// doesn't live longer for the time the server was down.
+//
+// stream consumer groups are not saved.
+//
+// Files written by an older version are migrated to the current record layout
+// on load, files written by a newer version are rejected instead of being
+// partially understood.
+
+use super::{
+    clock::now_ms, Backend, ElementList, FieldMap, HllEncoding, HyperLogLog, Key, ListpackLimits,
+    MemberSet, SortedSet, Stream, StreamId, Value, DEFAULT_HASH_MAX_LISTPACK_ENTRIES,
+    DEFAULT_HASH_MAX_LISTPACK_VALUE, DEFAULT_LIST_MAX_LISTPACK_SIZE, LATENCY_SNAPSHOT_SAVE,
+};
+use crate::{BulkString, RespArray, RespDecode, RespEncode, RespFrame};
+use bytes::BytesMut;
+use dashmap::DashMap;
+use std::{fs, path::Path, time::Instant};
+use thiserror::Error;
+
+pub const SNAPSHOT_MAGIC: &[u8] = b"SREDIS";
+pub const SNAPSHOT_VERSION: u32 = 4;
+pub const DEFAULT_DBFILENAME: &str = "dump.srdb";
+const VERSION_LEN: usize = 4;
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("not a snapshot file")]
+    InvalidMagic,
+    #[error("snapshot version {0} is newer than the supported version {SNAPSHOT_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("corrupted snapshot: {0}")]
+    Corrupted(String),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("timed out after {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("the data set loaded back differs from the one saved")]
+    Mismatch,
+}
+
+impl Backend {
+    // serialize all the keys into the snapshot format
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut buf = SNAPSHOT_MAGIC.to_vec();
+        buf.extend_from_slice(format!("{:04}", SNAPSHOT_VERSION).as_bytes());
+        for record in self.snapshot_records() {
+            buf.extend_from_slice(&RespFrame::from(record).encode());
+        }
+        buf.extend_from_slice(&RespFrame::from(record("eof", None, vec![])).encode());
+        buf
+    }
+
+    // replace all the keys with the content of a snapshot, returns the version it was
+    // written with. Nothing is changed if the snapshot can't be loaded.
+    pub fn restore_snapshot(&self, data: &[u8]) -> Result<u32, SnapshotError> {
+        let (version, records) = parse_snapshot(data)?;
+        let records = migrate(version, records)?;
+
+        let loaded = LoadedData::default();
+        let mut accesses = Vec::new();
+        let mut expires = Vec::new();
+        for record in records {
+            match record_kind(&record)?.as_str() {
+                "meta" => accesses.push(key_access(record)?),
+                "expire" => expires.push(key_expire(record)?),
+                _ => loaded.insert(record)?,
+            }
+        }
+
+        self.db.clear();
+        self.volatile.clear();
+        loaded.move_into(self);
+        // keys that expired while the server was down are dropped, unless a master will
+        // send their DELs, like in Redis
+        let now = now_ms();
+        let replica = self.replication.is_replica();
+        for (key, at) in expires {
+            if at <= now && !replica {
+                self.db.remove(&key);
+            } else if let Some(mut entry) = self.db.get_mut(&key) {
+                self.set_entry_expire(&key, &mut entry, Some(at));
+            }
+        }
+        self.bump_string_epoch();
+        self.reset_key_meta();
+        for (key, last_access, freq) in accesses {
+            self.restore_key_access(&key, last_access, freq);
+        }
+        Ok(version)
+    }
+
+    pub fn dbfilename(&self) -> String {
+        self.dbfilename.lock().unwrap().clone()
+    }
+
+    pub fn set_dbfilename(&self, dbfilename: String) {
+        *self.dbfilename.lock().unwrap() = dbfilename;
+    }
+
+    // write the snapshot to dbfilename, through a temporary file so a crash while
+    // saving never leaves a truncated snapshot behind
+    pub fn save(&self) -> Result<(), SnapshotError> {
+        let start = Instant::now();
+        let path = self.dbfilename();
+        let tmp = format!("{}.tmp", path);
+        fs::write(&tmp, self.snapshot())?;
+        fs::rename(&tmp, &path)?;
+        self.latency.record(LATENCY_SNAPSHOT_SAVE, start.elapsed());
+        Ok(())
+    }
+
+    // load the snapshot from dbfilename, returns false if there is no snapshot yet
+    pub fn load(&self) -> Result<bool, SnapshotError> {
+        let path = self.dbfilename();
+        if !Path::new(&path).exists() {
+            return Ok(false);
+        }
+        self.restore_snapshot(&fs::read(&path)?)?;
+        Ok(true)
+    }
+
+    pub(super) fn snapshot_records(&self) -> Vec<RespArray> {
+        let mut records = Vec::new();
+        for (key, entry) in self.iter_snapshot() {
+            let (kind, values) = value_values(&entry.value);
+            records.push(record(kind, Some(&key), values));
+            if let Some(meta) = entry.meta {
+                let values = vec![
+                    BulkString::from(meta.last_access.to_string()).into(),
+                    BulkString::from(meta.freq.to_string()).into(),
+                ];
+                records.push(record("meta", Some(&key), values));
+            }
+            if let Some(at) = entry.expire {
+                let values = vec![BulkString::from(at.to_string()).into()];
+                records.push(record("expire", Some(&key), values));
+            }
+        }
+        records
+    }
+
+    // the record of a single key, without the key, for DUMP
+    pub(super) fn value_record(&self, key: &[u8]) -> Option<RespArray> {
+        let entry = self.db.get(key)?;
+        let (kind, values) = value_values(&entry.value);
+        Some(record(kind, None, values))
+    }
+}
+
+// the record type of the value and what follows the key in its record
+fn value_values(value: &Value) -> (&'static str, Vec<RespFrame>) {
+    match value {
+        Value::String(v) => ("string", vec![v.clone()]),
+        Value::Hash(v) => ("hash", hash_values(v)),
+        Value::Set(v) => ("set", set_values(v)),
+        Value::List(v) => ("list", v.iter().cloned().collect()),
+        Value::HyperLogLog(v) => ("hll", hll_values(v)),
+        Value::Stream(v) => ("stream", stream_values(v)),
+        Value::ZSet(v) => ("zset", zset_values(v)),
+    }
+}
+
+fn hash_values(hash: &FieldMap) -> Vec<RespFrame> {
+    let mut values = Vec::with_capacity(hash.len() * 2);
+    for (field, value) in hash.iter() {
+        values.push(field.clone());
+        values.push(value.clone());
+    }
+    values
+}
+
+fn set_values(set: &MemberSet) -> Vec<RespFrame> {
+    set.iter().cloned().collect()
+}
+
+fn hll_values(hll: &HyperLogLog) -> Vec<RespFrame> {
+    vec![
+        BulkString::from(hll.encoding().as_str()).into(),
+        BulkString::new(hll.registers()).into(),
+    ]
+}
+
+fn stream_values(stream: &Stream) -> Vec<RespFrame> {
+    let mut values = vec![BulkString::from(stream.last_id.to_string()).into()];
+    for (id, fields) in stream.entries.iter() {
+        values.push(BulkString::from(id.to_string()).into());
+        let fields = fields
+            .iter()
+            .flat_map(|(field, value)| [BulkString::from(field.as_slice()).into(), value.clone()])
+            .collect::<Vec<RespFrame>>();
+        values.push(RespArray::new(fields).into());
+    }
+    values
+}
+
+fn zset_values(zset: &SortedSet) -> Vec<RespFrame> {
+    let mut values = Vec::with_capacity(zset.len() * 2);
+    for (member, score) in zset.iter() {
+        values.push(BulkString::from(member).into());
+        values.push(BulkString::from(score.to_string()).into());
+    }
+    values
+}
+
+pub(super) fn record(kind: &str, key: Option<&[u8]>, values: Vec<RespFrame>) -> RespArray {
+    let mut frames = Vec::with_capacity(values.len() + 2);
+    frames.push(BulkString::from(kind).into());
+    if let Some(key) = key {
+        frames.push(BulkString::new(key).into());
+    }
+    frames.extend(values);
+    RespArray::new(frames)
+}
+
+// split the snapshot into its version and the records before the eof marker
+fn parse_snapshot(data: &[u8]) -> Result<(u32, Vec<RespArray>), SnapshotError> {
+    let body = data
+        .strip_prefix(SNAPSHOT_MAGIC)
+        .ok_or(SnapshotError::InvalidMagic)?;
+    if body.len() < VERSION_LEN {
+        return Err(SnapshotError::InvalidMagic);
+    }
+    let version: u32 = std::str::from_utf8(&body[..VERSION_LEN])
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&v| v > 0)
+        .ok_or(SnapshotError::InvalidMagic)?;
+    if version > SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+
+    let mut buf = BytesMut::from(&body[VERSION_LEN..]);
+    let mut records = Vec::new();
+    loop {
+        if buf.is_empty() {
+            return Err(corrupted("missing end of file marker"));
+        }
+        let frame = RespFrame::decode(&mut buf).map_err(|e| corrupted(e.to_string()))?;
+        let record = match frame {
+            RespFrame::Array(record) => record,
+            _ => return Err(corrupted("record is not an array")),
+        };
+        if record_kind(&record)? == "eof" {
+            break;
+        }
+        records.push(record);
+    }
+    if !buf.is_empty() {
+        return Err(corrupted("unexpected data after the end of file marker"));
+    }
+    Ok((version, records))
+}
+
+// bring records written by an older version to the current layout, one version at a time
+pub(super) fn migrate(
+    version: u32,
+    records: Vec<RespArray>,
+) -> Result<Vec<RespArray>, SnapshotError> {
+    match version {
+        // every later format version adds its step here, e.g. `1 => migrate(2, v1_to_v2(records)?)`
+        // versions 2 to 4 only added the zset, meta and expire records, older records are
+        // still valid
+        1 => migrate(2, records),
+        2 => migrate(3, records),
+        3 => migrate(4, records),
+        SNAPSHOT_VERSION => Ok(records),
+        v => Err(SnapshotError::UnsupportedVersion(v)),
+    }
+}
+
+pub(super) fn record_kind(record: &RespArray) -> Result<String, SnapshotError> {
+    match record.first() {
+        Some(RespFrame::BulkString(kind)) => Ok(String::from_utf8_lossy(kind).into_owned()),
+        _ => Err(corrupted("record without a type")),
+    }
+}
+
+fn corrupted(reason: impl Into<String>) -> SnapshotError {
+    SnapshotError::Corrupted(reason.into())
+}
+
+// the values rebuilt from a snapshot, moved into the backend once everything is loaded
+#[derive(Default)]
+pub(super) struct LoadedData {
+    values: DashMap<Key, Value>,
+}
+
+impl LoadedData {
+    pub(super) fn insert(&self, record: RespArray) -> Result<(), SnapshotError> {
+        let kind = record_kind(&record)?;
+        let mut frames = record.0.into_iter().skip(1);
+        let key = match frames.next() {
+            Some(RespFrame::BulkString(key)) => Key::from(key),
+            _ => return Err(corrupted(format!("{} record without a key", kind))),
+        };
+        let values: Vec<RespFrame> = frames.collect();
+
+        match kind.as_str() {
+            "string" => {
+                let value = values
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| corrupted("string record without a value"))?;
+                self.values.insert(key, Value::String(value));
+            }
+            "hash" => {
+                let mut entries = Vec::with_capacity(values.len() / 2);
+                let mut values = values.into_iter();
+                while let (Some(field), Some(value)) = (values.next(), values.next()) {
+                    match field {
+                        RespFrame::BulkString(_) => entries.push((field, value)),
+                        _ => return Err(corrupted("hash field is not a bulk string")),
+                    };
+                }
+                let limits = ListpackLimits {
+                    max_entries: DEFAULT_HASH_MAX_LISTPACK_ENTRIES,
+                    max_value: DEFAULT_HASH_MAX_LISTPACK_VALUE,
+                };
+                let hash = FieldMap::from_entries(entries, limits);
+                self.values.insert(key, Value::Hash(hash));
+            }
+            "set" => {
+                let set = MemberSet::from_members(values, ListpackLimits::default());
+                self.values.insert(key, Value::Set(set));
+            }
+            "list" => {
+                let list = ElementList::from_elements(values, DEFAULT_LIST_MAX_LISTPACK_SIZE);
+                self.values.insert(key, Value::List(list));
+            }
+            "hll" => {
+                let mut values = values.into_iter();
+                let encoding = match values.next().map(bulk_string).transpose()?.as_deref() {
+                    Some("sparse") => HllEncoding::Sparse,
+                    Some("dense") => HllEncoding::Dense,
+                    _ => return Err(corrupted("invalid hll encoding")),
+                };
+                let registers = match values.next() {
+                    Some(RespFrame::BulkString(registers)) => registers.0,
+                    _ => return Err(corrupted("hll record without registers")),
+                };
+                let hll = HyperLogLog::from_registers(&registers, encoding)
+                    .ok_or_else(|| corrupted("invalid hll registers"))?;
+                self.values.insert(key, Value::HyperLogLog(hll));
+            }
+            "stream" => {
+                let mut values = values.into_iter();
+                let mut stream = Stream {
+                    last_id: stream_id(values.next())?,
+                    ..Default::default()
+                };
+                while let Some(id) = values.next() {
+                    let id = stream_id(Some(id))?;
+                    let fields = match values.next() {
+                        Some(RespFrame::Array(fields)) => fields.0,
+                        _ => return Err(corrupted("stream entry without fields")),
+                    };
+                    let mut fields = fields.into_iter();
+                    let mut entry = Vec::new();
+                    while let (Some(field), Some(value)) = (fields.next(), fields.next()) {
+                        entry.push((bulk_bytes(field)?, value));
+                    }
+                    stream.entries.insert(id, entry);
+                }
+                self.values.insert(key, Value::Stream(stream));
+            }
+            "zset" => {
+                let mut zset = SortedSet::new();
+                let mut values = values.into_iter();
+                while let (Some(member), Some(score)) = (values.next(), values.next()) {
+                    let score = bulk_string(score)?
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|score| !score.is_nan())
+                        .ok_or_else(|| corrupted("invalid zset score"))?;
+                    zset.insert(bulk_bytes(member)?, score);
+                }
+                self.values.insert(key, Value::ZSet(zset));
+            }
+            kind => return Err(corrupted(format!("unknown record type '{}'", kind))),
+        }
+        Ok(())
+    }
+
+    pub(super) fn move_into(self, backend: &Backend) {
+        for (key, value) in self.values {
+            backend.replace_value(key, value, false);
+        }
+    }
+}
+
+// the key, last access and LFU counter of a meta record
+fn key_access(record: RespArray) -> Result<(Key, u64, u8), SnapshotError> {
+    let mut frames = record.0.into_iter().skip(1);
+    let (Some(RespFrame::BulkString(key)), Some(last_access), Some(freq)) =
+        (frames.next(), frames.next(), frames.next())
+    else {
+        return Err(corrupted("incomplete meta record"));
+    };
+    let last_access = bulk_string(last_access)?
+        .parse()
+        .map_err(|_| corrupted("invalid last access time"))?;
+    let freq = bulk_string(freq)?
+        .parse()
+        .map_err(|_| corrupted("invalid access frequency"))?;
+    Ok((key.into(), last_access, freq))
+}
+
+// the key and expire time of an expire record
+fn key_expire(record: RespArray) -> Result<(Key, u64), SnapshotError> {
+    let mut frames = record.0.into_iter().skip(1);
+    let (Some(RespFrame::BulkString(key)), Some(at)) = (frames.next(), frames.next()) else {
+        return Err(corrupted("incomplete expire record"));
+    };
+    let at = bulk_string(at)?
+        .parse()
+        .map_err(|_| corrupted("invalid expire time"))?;
+    Ok((key.into(), at))
+}
+
+fn bulk_string(frame: RespFrame) -> Result<String, SnapshotError> {
+    match frame {
+        RespFrame::BulkString(s) => {
+            String::from_utf8(Vec::from(s.0)).map_err(|e| corrupted(e.to_string()))
+        }
+        _ => Err(corrupted("expected a bulk string")),
+    }
+}
+
+fn bulk_bytes(frame: RespFrame) -> Result<Vec<u8>, SnapshotError> {
+    match frame {
+        RespFrame::BulkString(s) => Ok(Vec::from(s)),
+        _ => Err(corrupted("expected a bulk string")),
+    }
+}
+
+fn stream_id(frame: Option<RespFrame>) -> Result<StreamId, SnapshotError> {
+    match frame {
+        Some(frame) => bulk_string(frame)?.parse().map_err(corrupted),
+        None => Err(corrupted("missing stream ID")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::clock::set_mock_now_ms, StreamIdSpec, ZAddCondition};
+
+    fn populated_backend() -> Backend {
+        let backend = Backend::new();
+        backend.set("str".into(), BulkString::from("value").into());
+        backend.set("int".into(), RespFrame::Integer(42));
+        backend.hset("hash".into(), "field".into(), BulkString::from("v").into());
+        backend.sadd(
+            "set".into(),
+            vec![BulkString::from("a").into(), BulkString::from("b").into()],
+        );
+        backend.rpush(
+            "list".into(),
+            vec![BulkString::from("x").into(), BulkString::from("y").into()],
+        );
+        backend.pfadd("hll".into(), &[b"a".to_vec(), b"b".to_vec()]);
+        backend
+            .xadd(
+                "stream".into(),
+                StreamIdSpec::Explicit(StreamId::new(1, 1)),
+                vec![(b"f".to_vec(), BulkString::from("v").into())],
+            )
+            .unwrap();
+        backend.zadd(
+            "zset".into(),
+            vec![(1.5, b"a".to_vec()), (-2.0, b"\xff\xfe".to_vec())],
+            ZAddCondition::Always,
+            false,
+        );
+        backend
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let backend = populated_backend();
+        let data = backend.snapshot();
+        assert!(data.starts_with(b"SREDIS0004"));
+
+        let restored = Backend::new();
+        restored.set("stale".into(), BulkString::from("gone").into());
+        assert_eq!(restored.restore_snapshot(&data).unwrap(), SNAPSHOT_VERSION);
+
+        assert_eq!(restored.get(b"stale"), None);
+        assert_eq!(restored.get(b"str"), Some(BulkString::from("value").into()));
+        assert_eq!(restored.get(b"int"), Some(BulkString::from("42").into()));
+        assert_eq!(restored.object_encoding(b"int"), Some("int"));
+        assert_eq!(
+            restored.hget(b"hash", b"field"),
+            Some(BulkString::from("v").into())
+        );
+        assert!(restored.s_is_member(b"set", BulkString::from("b").into()));
+        assert_eq!(
+            restored.lrange(b"list", 0, -1),
+            backend.lrange(b"list", 0, -1)
+        );
+        assert_eq!(restored.pfcount(&["hll".into()]), 2);
+        assert_eq!(
+            restored.xrange(b"stream", StreamId::MIN, StreamId::MAX, None, false),
+            backend.xrange(b"stream", StreamId::MIN, StreamId::MAX, None, false)
+        );
+        assert_eq!(restored.stream_last_id(b"stream"), StreamId::new(1, 1));
+        assert_eq!(
+            restored.zrange(b"zset", 0, -1, false),
+            backend.zrange(b"zset", 0, -1, false)
+        );
+    }
+
+    #[test]
+    fn test_snapshot_keeps_key_access() {
+        let now = 1_700_000_000_000;
+        set_mock_now_ms(Some(now));
+        let backend = populated_backend();
+        backend.reset_key_meta();
+        backend.restore_key_access(b"str", now - 10_000, 20);
+        let data = backend.snapshot();
+
+        // loaded a minute later, the key has been idle all that time
+        set_mock_now_ms(Some(now + 60_000));
+        let restored = Backend::new();
+        restored.restore_snapshot(&data).unwrap();
+        let meta = restored.key_meta(b"str").unwrap();
+        assert_eq!((meta.last_access, meta.freq), (now - 10_000, 20));
+        assert_eq!(restored.object_idletime(b"str"), Some(70));
+        assert_eq!(restored.object_freq(b"str"), backend.object_freq(b"str"));
+        assert_eq!(restored.object_idletime(b"list"), Some(60));
+        set_mock_now_ms(None);
+
+        // a snapshot of version 2 has no meta records and loads the same way
+        let mut data = populated_backend().snapshot();
+        data[SNAPSHOT_MAGIC.len()..SNAPSHOT_MAGIC.len() + VERSION_LEN].copy_from_slice(b"0002");
+        assert_eq!(restored.restore_snapshot(&data).unwrap(), 2);
+        assert!(restored.key_meta(b"str").is_some());
+    }
+
+    #[test]
+    fn test_snapshot_keeps_expire_times() {
+        let backend = populated_backend();
+        assert!(backend.expire(&"list".into(), u64::MAX));
+        let at = backend.expire_time(b"list");
+
+        let restored = Backend::new();
+        restored.restore_snapshot(&backend.snapshot()).unwrap();
+        assert_eq!(restored.expire_time(b"list"), at);
+        assert_eq!(restored.expire_time(b"str"), None);
+
+        // the TTL went on while the server was down
+        let now = 1_000_000;
+        set_mock_now_ms(Some(now));
+        assert!(backend.expire(&"list".into(), now + 1000));
+        assert!(backend.expire(&"str".into(), now + 2000));
+        let data = backend.snapshot();
+        set_mock_now_ms(Some(now + 1500));
+        restored.restore_snapshot(&data).unwrap();
+        set_mock_now_ms(None);
+        assert!(!restored.db.contains_key(b"list".as_slice()));
+        assert_eq!(restored.expire_time(b"str"), Some(now + 2000));
+    }
+
+    #[test]
+    fn test_snapshot_migrates_version_1() {
+        let backend = populated_backend();
+        backend.del(&["zset".into()]);
+        let mut data = backend.snapshot();
+        data[SNAPSHOT_MAGIC.len()..SNAPSHOT_MAGIC.len() + VERSION_LEN].copy_from_slice(b"0001");
+
+        let restored = Backend::new();
+        assert_eq!(restored.restore_snapshot(&data).unwrap(), 1);
+        assert_eq!(restored.get(b"str"), Some(BulkString::from("value").into()));
+    }
+
+    #[test]
+    fn test_snapshot_rejects_newer_version() {
+        let backend = populated_backend();
+        let mut data = backend.snapshot();
+        data[SNAPSHOT_MAGIC.len()..SNAPSHOT_MAGIC.len() + VERSION_LEN]
+            .copy_from_slice(format!("{:04}", SNAPSHOT_VERSION + 1).as_bytes());
+
+        let restored = Backend::new();
+        restored.set("kept".into(), BulkString::from("v").into());
+        assert!(matches!(
+            restored.restore_snapshot(&data),
+            Err(SnapshotError::UnsupportedVersion(v)) if v == SNAPSHOT_VERSION + 1
+        ));
+        // a rejected snapshot leaves the data untouched
+        assert_eq!(restored.get(b"kept"), Some(BulkString::from("v").into()));
+    }
+
+    #[test]
+    fn test_snapshot_rejects_invalid_data() {
+        let backend = Backend::new();
+        assert!(matches!(
+            backend.restore_snapshot(b"REDIS0011"),
+            Err(SnapshotError::InvalidMagic)
+        ));
+        assert!(matches!(
+            backend.restore_snapshot(b"SREDIS0000"),
+            Err(SnapshotError::InvalidMagic)
+        ));
+
+        // truncated before the eof marker
+        let data = populated_backend().snapshot();
+        assert!(matches!(
+            backend.restore_snapshot(&data[..data.len() - 5]),
+            Err(SnapshotError::Corrupted(_))
+        ));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("simple-redis-{}.srdb", std::process::id()));
+        let backend = populated_backend();
+        backend.set_dbfilename(path.to_string_lossy().into_owned());
+        backend.save().unwrap();
+
+        let restored = Backend::new();
+        restored.set_dbfilename(backend.dbfilename());
+        assert!(restored.load().unwrap());
+        assert_eq!(restored.get(b"str"), Some(BulkString::from("value").into()));
+        fs::remove_file(&path).unwrap();
+
+        assert!(!restored.load().unwrap());
+    }
+}
@@ -0,0 +1,295 @@
+// SORT: the elements of a list, set or sorted set, ordered as numbers or, with ALPHA, as
+// byte strings. The keys BY and GET patterns refer to are found by replacing the first *
+// of the pattern with the element, pattern->field refers to a field of a hash. A BY
+// pattern without * doesn't sort, GET # is the element itself.
+//
+// The elements are copied before anything else is looked up, so the key isn't locked
+// while the patterns are.
+
+use super::{
+    string::string_bytes, Backend, ElementList, FieldMap, Key, KeyType, MemberSet, SortedSet,
+    Value, WRONGTYPE_ERROR,
+};
+use crate::{BulkString, RespFrame, RespNull};
+use std::cmp::Ordering;
+
+pub const SORT_NOT_A_NUMBER_ERROR: &str = "ERR One or more scores can't be converted into double";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortOptions {
+    pub by: Option<Vec<u8>>,
+    // offset and count, a negative count takes every element from the offset on
+    pub limit: Option<(i64, i64)>,
+    pub get: Vec<Vec<u8>>,
+    pub desc: bool,
+    pub alpha: bool,
+}
+
+impl SortOptions {
+    fn sorts(&self) -> bool {
+        self.by.as_ref().is_none_or(|by| by.contains(&b'*'))
+    }
+}
+
+// what an element is sorted by
+enum SortKey {
+    Number(f64),
+    Bytes(Option<Vec<u8>>),
+}
+
+impl SortKey {
+    fn compare(&self, other: &SortKey) -> Ordering {
+        match (self, other) {
+            (SortKey::Number(a), SortKey::Number(b)) => a.total_cmp(b),
+            // a missing key comes first, like an empty string
+            (SortKey::Bytes(a), SortKey::Bytes(b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+impl Backend {
+    // the elements of the key in order, with what the GET patterns give for each of them
+    // instead when there are any, a null for a missing key
+    pub fn sort(&self, key: &[u8], options: &SortOptions) -> Result<Vec<RespFrame>, &'static str> {
+        let mut elements = self.sort_elements(key)?;
+        if options.sorts() {
+            let mut keyed = elements
+                .into_iter()
+                .map(|element| {
+                    let value = match &options.by {
+                        Some(by) => self.sort_lookup(by, &element),
+                        None => Some(element.clone()),
+                    };
+                    let key = match options.alpha {
+                        true => SortKey::Bytes(value),
+                        false => SortKey::Number(sort_number(value)?),
+                    };
+                    Ok((key, element))
+                })
+                .collect::<Result<Vec<_>, &'static str>>()?;
+            keyed.sort_by(|(a, _), (b, _)| match options.desc {
+                true => b.compare(a),
+                false => a.compare(b),
+            });
+            elements = keyed.into_iter().map(|(_, element)| element).collect();
+        }
+        if let Some((offset, count)) = options.limit {
+            let offset = offset.max(0) as usize;
+            let count = usize::try_from(count).unwrap_or(usize::MAX);
+            elements = elements.into_iter().skip(offset).take(count).collect();
+        }
+        if options.get.is_empty() {
+            return Ok(elements
+                .into_iter()
+                .map(|element| BulkString::new(element).into())
+                .collect());
+        }
+        Ok(elements
+            .iter()
+            .flat_map(|element| {
+                options
+                    .get
+                    .iter()
+                    .map(move |pattern| match self.sort_lookup(pattern, element) {
+                        Some(value) => BulkString::new(value).into(),
+                        None => RespNull.into(),
+                    })
+            })
+            .collect())
+    }
+
+    // store what SORT gives in a list at dest, whatever it held, a null as an empty
+    // string. An empty result deletes dest. Returns the length of the list.
+    pub fn sort_store(&self, dest: Key, values: Vec<RespFrame>) -> usize {
+        let list = ElementList::from_elements(
+            values.into_iter().map(|value| match value {
+                RespFrame::Null(_) => BulkString::from("").into(),
+                value => value,
+            }),
+            self.list_max_listpack_size(),
+        );
+        let len = list.len();
+        if len > 0 {
+            if self.key_type(&dest) == Some(KeyType::String) {
+                self.bump_string_epoch();
+            }
+            self.replace_value(dest.clone(), Value::List(list), false);
+            self.signal_key_ready(&dest);
+        } else {
+            self.remove_keys(std::slice::from_ref(&dest));
+        }
+        len
+    }
+
+    fn sort_elements(&self, key: &[u8]) -> Result<Vec<Vec<u8>>, &'static str> {
+        let Some(entry) = self.db.get(key) else {
+            return Ok(Vec::new());
+        };
+        let elements = match &entry.value {
+            Value::List(list) => list.iter().map(|e| string_bytes(e).into_owned()).collect(),
+            Value::Set(set) => set_elements(set),
+            Value::ZSet(zset) => zset_elements(zset),
+            _ => return Err(WRONGTYPE_ERROR),
+        };
+        Ok(elements)
+    }
+
+    // the value the pattern refers to for the element, None when the key or the field is
+    // missing
+    fn sort_lookup(&self, pattern: &[u8], element: &[u8]) -> Option<Vec<u8>> {
+        if pattern == b"#" {
+            return Some(element.to_vec());
+        }
+        let Some(star) = pattern.iter().position(|&b| b == b'*') else {
+            return self.sort_lookup_key(pattern, None);
+        };
+        // the field follows the last -> after the *, when it isn't empty
+        let (key_pattern, field) = match pattern[star..].windows(2).rposition(|w| w == b"->") {
+            Some(arrow) if star + arrow + 2 < pattern.len() => {
+                (&pattern[..star + arrow], Some(&pattern[star + arrow + 2..]))
+            }
+            _ => (pattern, None),
+        };
+        let mut key = key_pattern[..star].to_vec();
+        key.extend_from_slice(element);
+        key.extend_from_slice(&key_pattern[star + 1..]);
+        self.sort_lookup_key(&key, field)
+    }
+
+    fn sort_lookup_key(&self, key: &[u8], field: Option<&[u8]>) -> Option<Vec<u8>> {
+        let entry = self.db.get(key)?;
+        match (&entry.value, field) {
+            (Value::String(value), None) => Some(string_bytes(value).into_owned()),
+            (Value::Hash(hash), Some(field)) => hash_field(hash, field),
+            _ => None,
+        }
+    }
+}
+
+fn set_elements(set: &MemberSet) -> Vec<Vec<u8>> {
+    set.iter().map(|e| string_bytes(e).into_owned()).collect()
+}
+
+fn zset_elements(zset: &SortedSet) -> Vec<Vec<u8>> {
+    zset.iter().map(|(member, _)| member.to_vec()).collect()
+}
+
+fn hash_field(hash: &FieldMap, field: &[u8]) -> Option<Vec<u8>> {
+    hash.get(&BulkString::new(field).into())
+        .map(|value| string_bytes(value).into_owned())
+}
+
+// a missing value counts as 0, like in Redis
+fn sort_number(value: Option<Vec<u8>>) -> Result<f64, &'static str> {
+    let Some(value) = value else {
+        return Ok(0.0);
+    };
+    std::str::from_utf8(&value)
+        .ok()
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .filter(|n| !n.is_nan())
+        .ok_or(SORT_NOT_A_NUMBER_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<RespFrame> {
+        values
+            .iter()
+            .map(|value| BulkString::from(*value).into())
+            .collect()
+    }
+
+    fn list(backend: &Backend, key: &str, values: &[&str]) {
+        backend.insert_value(
+            Key::from(key),
+            ElementList::from_elements(strings(values), -2),
+        );
+    }
+
+    #[test]
+    fn test_sort() {
+        let backend = Backend::new();
+        list(&backend, "l", &["3", "10", "1", "2.5"]);
+        let sort = |options: SortOptions| backend.sort(b"l", &options);
+
+        assert_eq!(
+            sort(SortOptions::default()),
+            Ok(strings(&["1", "2.5", "3", "10"]))
+        );
+        let alpha = SortOptions {
+            alpha: true,
+            desc: true,
+            ..Default::default()
+        };
+        assert_eq!(sort(alpha), Ok(strings(&["3", "2.5", "10", "1"])));
+        let limit = SortOptions {
+            limit: Some((1, 2)),
+            ..Default::default()
+        };
+        assert_eq!(sort(limit), Ok(strings(&["2.5", "3"])));
+
+        list(&backend, "words", &["b", "a"]);
+        assert_eq!(
+            backend.sort(b"words", &SortOptions::default()),
+            Err(SORT_NOT_A_NUMBER_ERROR)
+        );
+        assert_eq!(
+            backend.sort(b"missing", &SortOptions::default()),
+            Ok(vec![])
+        );
+        backend.set("s".into(), BulkString::from("v").into());
+        assert_eq!(
+            backend.sort(b"s", &SortOptions::default()),
+            Err(WRONGTYPE_ERROR)
+        );
+    }
+
+    #[test]
+    fn test_sort_by_and_get() {
+        let backend = Backend::new();
+        list(&backend, "l", &["a", "b", "c"]);
+        backend.set("w_a".into(), BulkString::from("3").into());
+        backend.set("w_b".into(), BulkString::from("1").into());
+        backend.hset("h_a".into(), b"name".to_vec(), BulkString::from("A").into());
+        backend.hset("h_c".into(), b"name".to_vec(), BulkString::from("C").into());
+
+        // c has no weight, which counts as 0
+        let by = SortOptions {
+            by: Some(b"w_*".to_vec()),
+            get: vec![b"#".to_vec(), b"h_*->name".to_vec()],
+            ..Default::default()
+        };
+        assert_eq!(
+            backend.sort(b"l", &by),
+            Ok(vec![
+                BulkString::from("c").into(),
+                BulkString::from("C").into(),
+                BulkString::from("b").into(),
+                RespNull.into(),
+                BulkString::from("a").into(),
+                BulkString::from("A").into(),
+            ])
+        );
+        // without * in the pattern the elements keep their order
+        let nosort = SortOptions {
+            by: Some(b"nosort".to_vec()),
+            ..Default::default()
+        };
+        assert_eq!(backend.sort(b"l", &nosort), Ok(strings(&["a", "b", "c"])));
+
+        assert_eq!(
+            backend.sort_store(
+                "dest".into(),
+                vec![RespNull.into(), BulkString::from("x").into()]
+            ),
+            2
+        );
+        assert_eq!(backend.lrange(b"dest", 0, -1), strings(&["", "x"]));
+        assert_eq!(backend.sort_store("dest".into(), vec![]), 0);
+        assert!(!backend.key_exists(b"dest"));
+    }
+}
@@ -0,0 +1,293 @@
+// Server metrics for embedders, as plain data instead of INFO text so they can be
+// published through any telemetry stack. Command counters and latencies are recorded by
+// the connection handler for every command that parses, connection counters when a
+// connection opens and closes.
+
+use super::{Backend, GetCacheStats, Value};
+use crate::RespFrame;
+use dashmap::DashMap;
+use std::{
+    collections::BTreeMap,
+    mem::size_of,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+#[derive(Debug, Default)]
+pub struct CommandStats {
+    commands: DashMap<&'static str, CommandLatency>,
+    // the keys read commands looked up, found or not
+    keyspace_hits: AtomicU64,
+    keyspace_misses: AtomicU64,
+    total_connections: AtomicU64,
+    connected_clients: AtomicU64,
+    rejected_connections: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CommandLatency {
+    pub calls: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyCounts {
+    pub strings: usize,
+    pub hashes: usize,
+    pub sets: usize,
+    pub lists: usize,
+    pub hlls: usize,
+    pub streams: usize,
+    pub zsets: usize,
+}
+
+// how the keys spread over the shards of the keyspace. A shard with far more keys
+// than the average is a hot spot, its lock serializes the commands on all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ShardStats {
+    pub shards: usize,
+    pub keys: usize,
+    // the keys in the fullest shard
+    pub max_keys: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub total_commands: u64,
+    pub total_connections: u64,
+    pub connected_clients: u64,
+    // connections refused because maxclients were connected already
+    pub rejected_connections: u64,
+    pub keys: KeyCounts,
+    pub shards: ShardStats,
+    // approximate key counts by prefix, see key_prefix.rs
+    pub key_prefixes: BTreeMap<String, usize>,
+    // a rough estimate of the memory used by keys and values, in bytes
+    pub used_memory: usize,
+    pub evicted_keys: u64,
+    pub expired_keys: u64,
+    pub keyspace_hits: u64,
+    pub keyspace_misses: u64,
+    pub get_cache: GetCacheStats,
+    // by lowercase command name
+    pub commands: BTreeMap<String, CommandLatency>,
+}
+
+impl CommandStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, command: &'static str, duration: Duration) {
+        let mut latency = self.commands.entry(command).or_default();
+        latency.calls += 1;
+        latency.total += duration;
+        latency.max = latency.max.max(duration);
+    }
+
+    // returns the number of clients connected with this one
+    pub fn connection_opened(&self) -> u64 {
+        self.total_connections.fetch_add(1, Ordering::Relaxed);
+        self.connected_clients.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn connection_rejected(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn connection_closed(&self) {
+        self.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // a key a read command looked up
+    pub fn keyspace_lookup(&self, hit: bool) {
+        let counter = match hit {
+            true => &self.keyspace_hits,
+            false => &self.keyspace_misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.commands.clear();
+        self.keyspace_hits.store(0, Ordering::Relaxed);
+        self.keyspace_misses.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Stats {
+    // the share of the keys read commands looked up that existed, None before any lookup
+    pub fn keyspace_hit_ratio(&self) -> Option<f64> {
+        let lookups = self.keyspace_hits + self.keyspace_misses;
+        (lookups > 0).then(|| self.keyspace_hits as f64 / lookups as f64)
+    }
+}
+
+impl CommandLatency {
+    pub fn mean(&self) -> Duration {
+        match self.calls {
+            0 => Duration::ZERO,
+            calls => Duration::from_nanos((self.total.as_nanos() / calls as u128) as u64),
+        }
+    }
+}
+
+impl KeyCounts {
+    pub fn total(&self) -> usize {
+        self.strings + self.hashes + self.sets + self.lists + self.hlls + self.streams + self.zsets
+    }
+}
+
+impl Backend {
+    pub fn stats(&self) -> Stats {
+        let commands: BTreeMap<String, CommandLatency> = self
+            .stats
+            .commands
+            .iter()
+            .map(|entry| (entry.key().to_string(), *entry.value()))
+            .collect();
+        Stats {
+            total_commands: commands.values().map(|latency| latency.calls).sum(),
+            total_connections: self.stats.total_connections.load(Ordering::Relaxed),
+            connected_clients: self.stats.connected_clients.load(Ordering::Relaxed),
+            rejected_connections: self.stats.rejected_connections.load(Ordering::Relaxed),
+            keys: self.key_counts(),
+            shards: self.shard_stats(),
+            key_prefixes: self.key_prefixes.counts(),
+            used_memory: self.used_memory(),
+            evicted_keys: self.evicted_keys(),
+            expired_keys: self.expired_keys(),
+            keyspace_hits: self.stats.keyspace_hits.load(Ordering::Relaxed),
+            keyspace_misses: self.stats.keyspace_misses.load(Ordering::Relaxed),
+            get_cache: self.get_cache_stats(),
+            commands,
+        }
+    }
+
+    pub fn key_counts(&self) -> KeyCounts {
+        let mut counts = KeyCounts::default();
+        for entry in self.db.iter() {
+            let count = match entry.value().value {
+                Value::String(_) => &mut counts.strings,
+                Value::Hash(_) => &mut counts.hashes,
+                Value::Set(_) => &mut counts.sets,
+                Value::List(_) => &mut counts.lists,
+                Value::HyperLogLog(_) => &mut counts.hlls,
+                Value::Stream(_) => &mut counts.streams,
+                Value::ZSet(_) => &mut counts.zsets,
+            };
+            *count += 1;
+        }
+        counts
+    }
+
+    pub fn shard_stats(&self) -> ShardStats {
+        // a read lock at a time, writers to the other shards go on meanwhile
+        let sizes: Vec<usize> = self
+            .db
+            .shards()
+            .iter()
+            .map(|shard| shard.read().len())
+            .collect();
+        ShardStats {
+            shards: sizes.len(),
+            keys: sizes.iter().sum(),
+            max_keys: sizes.into_iter().max().unwrap_or(0),
+        }
+    }
+
+    // walks every key, meant for metrics scraped every few seconds, not for every command
+    pub fn used_memory(&self) -> usize {
+        let mut used = 0;
+        for entry in self.db.iter() {
+            used += entry.key().len();
+            used += match &entry.value().value {
+                Value::String(v) => frame_size(v),
+                Value::Hash(v) => v
+                    .iter()
+                    .map(|(field, value)| frame_size(field) + frame_size(value))
+                    .sum(),
+                Value::Set(v) => v.iter().map(frame_size).sum(),
+                Value::List(v) => v.iter().map(frame_size).sum(),
+                Value::HyperLogLog(v) => v.size_in_bytes(),
+                Value::Stream(v) => v
+                    .entries
+                    .values()
+                    .flat_map(|fields| fields.iter())
+                    .map(|(field, value)| field.len() + frame_size(value))
+                    .sum(),
+                // every member is stored twice, by name and by score
+                Value::ZSet(v) => v
+                    .iter()
+                    .map(|(member, _)| 2 * (member.len() + size_of::<f64>()))
+                    .sum(),
+            };
+        }
+        used
+    }
+}
+
+pub(crate) fn frame_size(frame: &RespFrame) -> usize {
+    size_of::<RespFrame>()
+        + match frame {
+            RespFrame::SimpleString(s) => s.len(),
+            RespFrame::Error(e) => e.len(),
+            RespFrame::BulkString(s) => s.len(),
+            RespFrame::Array(array) => array.iter().map(frame_size).sum(),
+            _ => 0,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_stats() {
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("1").into());
+        backend.sadd("s".into(), vec![BulkString::from("m").into()]);
+        backend.stats.record("set", Duration::from_micros(10));
+        backend.stats.record("set", Duration::from_micros(30));
+        backend.stats.record("get", Duration::from_micros(5));
+        backend.stats.connection_opened();
+        backend.stats.connection_opened();
+        backend.stats.connection_closed();
+
+        let stats = backend.stats();
+        assert_eq!(stats.total_commands, 3);
+        assert_eq!(stats.total_connections, 2);
+        assert_eq!(stats.connected_clients, 1);
+        assert_eq!(stats.keys.total(), 2);
+        assert_eq!(stats.keys.sets, 1);
+        assert!(stats.used_memory > 0);
+        assert_eq!(
+            stats.commands["set"],
+            CommandLatency {
+                calls: 2,
+                total: Duration::from_micros(40),
+                max: Duration::from_micros(30),
+            }
+        );
+        assert_eq!(stats.commands["set"].mean(), Duration::from_micros(20));
+    }
+
+    #[test]
+    fn test_shard_stats() {
+        let backend = Backend::with_shards(5);
+        for i in 0..100 {
+            backend.hset(
+                format!("h{}", i).into(),
+                b"f".to_vec(),
+                BulkString::from("v").into(),
+            );
+        }
+        let stats = backend.shard_stats();
+        assert_eq!(stats.shards, 8);
+        assert_eq!(stats.keys, 100);
+        assert!(stats.max_keys >= 100 / 8 && stats.max_keys < 100);
+        assert_eq!(backend.stats().shards, stats);
+    }
+}
@@ -0,0 +1,355 @@
+use super::{clock::now_ms, Backend, ConsumerGroup, Key};
+use crate::RespFrame;
+use std::{collections::BTreeMap, fmt, ops::Bound, str::FromStr, time::Duration};
+
+// stream entry ID, <milliseconds>-<sequence number>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+// the ID given to XADD: `*`, `<ms>-*` or a complete ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamIdSpec {
+    Auto,
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+pub type StreamFields = Vec<(Vec<u8>, RespFrame)>;
+
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    pub(crate) entries: BTreeMap<StreamId, StreamFields>,
+    pub(crate) last_id: StreamId,
+    pub(crate) groups: BTreeMap<Vec<u8>, ConsumerGroup>,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    // the smallest ID greater than this one
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => self.ms.checked_add(1).map(|ms| StreamId::new(ms, 0)),
+        }
+    }
+
+    // the greatest ID smaller than this one
+    pub fn prev(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => self.ms.checked_sub(1).map(|ms| StreamId::new(ms, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl FromStr for StreamId {
+    type Err = String;
+
+    // a complete `<ms>-<seq>` ID, or `<ms>` alone with a sequence number of 0
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || "Invalid stream ID specified as stream command argument".to_string();
+        match s.split_once('-') {
+            Some((ms, seq)) => Ok(StreamId::new(
+                ms.parse().map_err(|_| invalid())?,
+                seq.parse().map_err(|_| invalid())?,
+            )),
+            None => Ok(StreamId::new(s.parse().map_err(|_| invalid())?, 0)),
+        }
+    }
+}
+
+impl Stream {
+    // append an entry, the new ID must be greater than any ID in the stream
+    pub fn add(&mut self, id: StreamIdSpec, fields: StreamFields) -> Result<StreamId, String> {
+        let last = self.last_id;
+        let id = match id {
+            StreamIdSpec::Auto => {
+                let ms = now_ms();
+                if ms > last.ms {
+                    StreamId::new(ms, 0)
+                } else {
+                    last.next().ok_or_else(too_small)?
+                }
+            }
+            StreamIdSpec::AutoSeq(ms) => match ms.cmp(&last.ms) {
+                std::cmp::Ordering::Greater => StreamId::new(ms, 0),
+                std::cmp::Ordering::Equal => {
+                    StreamId::new(ms, last.seq.checked_add(1).ok_or_else(too_small)?)
+                }
+                std::cmp::Ordering::Less => return Err(too_small()),
+            },
+            StreamIdSpec::Explicit(id) => {
+                if id == StreamId::MIN {
+                    return Err("The ID specified in XADD must be greater than 0-0".to_string());
+                }
+                id
+            }
+        };
+        if id <= last {
+            return Err(too_small());
+        }
+
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        Ok(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // entries with start <= id <= end, in reverse order if rev
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Vec<(StreamId, StreamFields)> {
+        if start > end {
+            return Vec::new();
+        }
+        let range = self
+            .entries
+            .range((Bound::Included(start), Bound::Included(end)));
+        let count = count.unwrap_or(usize::MAX);
+        let clone = |(id, fields): (&StreamId, &StreamFields)| (*id, fields.clone());
+        if rev {
+            range.rev().take(count).map(clone).collect()
+        } else {
+            range.take(count).map(clone).collect()
+        }
+    }
+
+    // entries with an ID greater than id
+    pub fn after(&self, id: StreamId, count: Option<usize>) -> Vec<(StreamId, StreamFields)> {
+        match id.next() {
+            Some(start) => self.range(start, StreamId::MAX, count, false),
+            None => Vec::new(),
+        }
+    }
+}
+
+fn too_small() -> String {
+    "The ID specified in XADD is equal or smaller than the target stream top item".to_string()
+}
+
+impl Backend {
+    pub fn xadd(
+        &self,
+        key: Key,
+        id: StreamIdSpec,
+        fields: StreamFields,
+    ) -> Result<StreamId, String> {
+        let mut created = false;
+        let id = self
+            .value_or_insert_with(key.clone(), || {
+                created = true;
+                Stream::default()
+            })
+            .add(id, fields);
+        match id {
+            Ok(_) => self.signal_key_ready(&key),
+            // a failed XADD must not leave an empty stream behind
+            Err(_) if created => {
+                self.remove_value::<Stream>(&key);
+            }
+            Err(_) => {}
+        }
+        id
+    }
+
+    pub fn xlen(&self, key: &[u8]) -> usize {
+        self.value::<Stream>(key).map(|s| s.len()).unwrap_or(0)
+    }
+
+    pub fn xrange(
+        &self,
+        key: &[u8],
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Vec<(StreamId, StreamFields)> {
+        self.value::<Stream>(key)
+            .map(|s| s.range(start, end, count, rev))
+            .unwrap_or_default()
+    }
+
+    // the ID of the last entry added to the stream, used to resolve `$`
+    pub fn stream_last_id(&self, key: &[u8]) -> StreamId {
+        self.value::<Stream>(key)
+            .map(|s| s.last_id)
+            .unwrap_or_default()
+    }
+
+    // entries after the given ID for every stream, streams without new entries are left out
+    pub fn xread(
+        &self,
+        streams: &[(Key, StreamId)],
+        count: Option<usize>,
+    ) -> Vec<(Key, Vec<(StreamId, StreamFields)>)> {
+        streams
+            .iter()
+            .filter_map(|(key, id)| {
+                let entries = self.value::<Stream>(key)?.after(*id, count);
+                (!entries.is_empty()).then(|| (key.clone(), entries))
+            })
+            .collect()
+    }
+
+    // like xread, but wait until one of the streams gets new entries or the timeout expires.
+    // A timeout of None waits forever.
+    pub async fn blocking_xread(
+        &self,
+        streams: &[(Key, StreamId)],
+        count: Option<usize>,
+        timeout: Option<Duration>,
+    ) -> Vec<(Key, Vec<(StreamId, StreamFields)>)> {
+        let keys: Vec<Key> = streams.iter().map(|(key, _)| key.clone()).collect();
+        self.block_on_keys(&keys, timeout, || {
+            let ret = self.xread(streams, count);
+            (!ret.is_empty()).then_some(ret)
+        })
+        .await
+        .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn fields(items: &[(&str, &str)]) -> StreamFields {
+        items
+            .iter()
+            .map(|(f, v)| (f.as_bytes().to_vec(), BulkString::from(*v).into()))
+            .collect()
+    }
+
+    #[test]
+    fn test_stream_id_parse_and_display() {
+        assert_eq!("1-2".parse::<StreamId>(), Ok(StreamId::new(1, 2)));
+        assert_eq!("5".parse::<StreamId>(), Ok(StreamId::new(5, 0)));
+        assert!("a-1".parse::<StreamId>().is_err());
+        assert_eq!(StreamId::new(3, 4).to_string(), "3-4");
+        assert_eq!(StreamId::new(1, u64::MAX).next(), Some(StreamId::new(2, 0)));
+        assert_eq!(StreamId::new(2, 0).prev(), Some(StreamId::new(1, u64::MAX)));
+    }
+
+    #[test]
+    fn test_stream_add_ids() {
+        let mut stream = Stream::default();
+        assert!(stream
+            .add(StreamIdSpec::Explicit(StreamId::MIN), fields(&[("a", "1")]))
+            .is_err());
+        assert_eq!(
+            stream.add(StreamIdSpec::Explicit(StreamId::new(5, 1)), fields(&[])),
+            Ok(StreamId::new(5, 1))
+        );
+        assert_eq!(
+            stream.add(StreamIdSpec::AutoSeq(5), fields(&[])),
+            Ok(StreamId::new(5, 2))
+        );
+        assert!(stream.add(StreamIdSpec::AutoSeq(4), fields(&[])).is_err());
+        assert!(stream
+            .add(StreamIdSpec::Explicit(StreamId::new(5, 2)), fields(&[]))
+            .is_err());
+
+        let id = stream.add(StreamIdSpec::Auto, fields(&[])).unwrap();
+        assert!(id > StreamId::new(5, 2));
+        assert_eq!(stream.len(), 3);
+    }
+
+    #[test]
+    fn test_stream_range() {
+        let backend = Backend::new();
+        for i in 1..=5 {
+            backend
+                .xadd(
+                    "s".into(),
+                    StreamIdSpec::Explicit(StreamId::new(i, 0)),
+                    fields(&[("n", &i.to_string())]),
+                )
+                .unwrap();
+        }
+        assert_eq!(backend.xlen(b"s"), 5);
+
+        let ids = |entries: Vec<(StreamId, StreamFields)>| {
+            entries.into_iter().map(|(id, _)| id.ms).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(backend.xrange(b"s", StreamId::new(2, 0), StreamId::MAX, None, false)),
+            vec![2, 3, 4, 5]
+        );
+        assert_eq!(
+            ids(backend.xrange(b"s", StreamId::MIN, StreamId::MAX, Some(2), true)),
+            vec![5, 4]
+        );
+        assert_eq!(
+            ids(backend.xrange(b"s", StreamId::new(4, 0), StreamId::new(2, 0), None, false)),
+            Vec::<u64>::new()
+        );
+
+        let ret = backend.xread(
+            &[
+                ("s".into(), StreamId::new(3, 0)),
+                ("missing".into(), StreamId::MIN),
+            ],
+            None,
+        );
+        assert_eq!(ret.len(), 1);
+        assert_eq!(ids(ret[0].1.clone()), vec![4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_blocking_xread() {
+        let backend = Backend::new();
+        let ret = backend
+            .blocking_xread(
+                &[("s".into(), StreamId::MIN)],
+                None,
+                Some(Duration::from_millis(10)),
+            )
+            .await;
+        assert!(ret.is_empty());
+
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move {
+            cloned
+                .blocking_xread(&[("s".into(), StreamId::MIN)], None, None)
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let id = backend
+            .xadd("s".into(), StreamIdSpec::Auto, fields(&[("a", "1")]))
+            .unwrap();
+
+        let ret = handle.await.unwrap();
+        assert_eq!(ret.len(), 1);
+        assert_eq!(ret[0].1[0].0, id);
+    }
+}
@@ -0,0 +1,921 @@
+// Stream consumer groups.
+//
+// A group remembers the last entry ID it delivered and the entries delivered to
+// its consumers but not acknowledged yet, the pending entries list (PEL). The
+// PEL is kept once per group with the owning consumer in every entry, a
+// consumer's own PEL is the part of it that consumer owns.
+//
+// Consumers idle for longer than stream-consumer-idle-timeout are deleted by a background
+// task, when it isn't 0. Their pending entries are dropped like XGROUP DELCONSUMER does, or
+// with stream-consumer-idle-pel reassign given to the consumer of the group used last, as
+// if it claimed them with XCLAIM JUSTID. A consumer with pending entries and nobody to
+// give them to is kept. The task walks the keyspace once a second while it is enabled.
+//
+// Errors are complete RESP error messages since they don't all start with ERR
+// (BUSYGROUP, NOGROUP).
+
+use super::{clock::now_ms, Backend, Key, Stream, StreamFields, StreamId, Value};
+use crate::{BulkString, RespArray, RespFrame};
+use std::{collections::BTreeMap, ops::Bound, time::Duration};
+
+pub const CONSUMER_PEL_POLICIES: &[&str] = &["reassign", "drop"];
+const CONSUMER_CLEANUP_INTERVAL: Duration = Duration::from_secs(1);
+
+// what happens to the pending entries of a consumer deleted for being idle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConsumerPelPolicy {
+    #[default]
+    Reassign,
+    Drop,
+}
+
+impl ConsumerPelPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConsumerPelPolicy::Reassign => "reassign",
+            ConsumerPelPolicy::Drop => "drop",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "reassign" => Some(ConsumerPelPolicy::Reassign),
+            "drop" => Some(ConsumerPelPolicy::Drop),
+            _ => None,
+        }
+    }
+}
+
+// a consumer deleted for being idle, with the consumer its pending entries were given to
+struct IdleConsumer {
+    name: Vec<u8>,
+    reassigned: Option<(Vec<u8>, Vec<StreamId>)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConsumerGroup {
+    pub(crate) last_delivered: StreamId,
+    pub(crate) pending: BTreeMap<StreamId, PendingEntry>,
+    pub(crate) consumers: BTreeMap<Vec<u8>, Consumer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEntry {
+    pub consumer: Vec<u8>,
+    // unix time in milliseconds of the last delivery
+    pub delivered_at: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Consumer {
+    // unix time in milliseconds the consumer was last used
+    pub seen_time: u64,
+}
+
+// XPENDING without a range
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PendingSummary {
+    pub count: usize,
+    // smallest and greatest pending IDs
+    pub range: Option<(StreamId, StreamId)>,
+    // consumers with pending entries, with their number of entries
+    pub consumers: Vec<(Vec<u8>, usize)>,
+}
+
+// XPENDING with a range, one pending entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingDetail {
+    pub id: StreamId,
+    pub consumer: Vec<u8>,
+    pub idle: u64,
+    pub delivery_count: u64,
+}
+
+pub type StreamEntries = Vec<(StreamId, StreamFields)>;
+
+// XAUTOCLAIM scans at most this many pending entries per claimed entry asked for
+const AUTOCLAIM_ATTEMPTS_FACTOR: usize = 10;
+
+// one XAUTOCLAIM scan of the PEL
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AutoClaim {
+    // where the next scan starts, 0-0 once the whole PEL has been scanned
+    pub next: StreamId,
+    pub claimed: StreamEntries,
+    // pending entries deleted from the stream, dropped from the PEL
+    pub deleted: Vec<StreamId>,
+}
+
+impl ConsumerGroup {
+    pub fn new(last_delivered: StreamId) -> Self {
+        ConsumerGroup {
+            last_delivered,
+            ..Default::default()
+        }
+    }
+
+    // the consumer is created the first time it is used
+    fn touch_consumer(&mut self, consumer: &[u8], now: u64) {
+        self.consumers
+            .entry(consumer.to_vec())
+            .or_default()
+            .seen_time = now;
+    }
+
+    // deliver the entries never delivered to the group yet
+    fn read_new(
+        &mut self,
+        entries: &BTreeMap<StreamId, StreamFields>,
+        consumer: &[u8],
+        count: Option<usize>,
+        noack: bool,
+        now: u64,
+    ) -> StreamEntries {
+        let ret: StreamEntries = entries
+            .range((Bound::Excluded(self.last_delivered), Bound::Unbounded))
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect();
+        if let Some((id, _)) = ret.last() {
+            self.last_delivered = *id;
+        }
+        if !noack {
+            for (id, _) in ret.iter() {
+                self.pending.insert(
+                    *id,
+                    PendingEntry {
+                        consumer: consumer.to_vec(),
+                        delivered_at: now,
+                        delivery_count: 1,
+                    },
+                );
+            }
+        }
+        ret
+    }
+
+    // delete the consumers not used since deadline
+    fn remove_idle_consumers(
+        &mut self,
+        deadline: u64,
+        policy: ConsumerPelPolicy,
+        now: u64,
+    ) -> Vec<IdleConsumer> {
+        let idle: Vec<Vec<u8>> = self
+            .consumers
+            .iter()
+            .filter(|(_, consumer)| consumer.seen_time < deadline)
+            .map(|(name, _)| name.clone())
+            .collect();
+        if idle.is_empty() {
+            return Vec::new();
+        }
+        let target = match policy {
+            ConsumerPelPolicy::Reassign => self
+                .consumers
+                .iter()
+                .filter(|(_, consumer)| consumer.seen_time >= deadline)
+                .max_by_key(|(_, consumer)| consumer.seen_time)
+                .map(|(name, _)| name.clone()),
+            ConsumerPelPolicy::Drop => None,
+        };
+        let mut removed = Vec::new();
+        for name in idle {
+            let ids: Vec<StreamId> = self
+                .pending
+                .iter()
+                .filter(|(_, pending)| pending.consumer == name)
+                .map(|(id, _)| *id)
+                .collect();
+            let reassigned = match (policy, &target) {
+                (_, _) if ids.is_empty() => None,
+                (ConsumerPelPolicy::Reassign, None) => continue,
+                (ConsumerPelPolicy::Reassign, Some(target)) => {
+                    for id in &ids {
+                        if let Some(pending) = self.pending.get_mut(id) {
+                            pending.consumer = target.clone();
+                            pending.delivered_at = now;
+                        }
+                    }
+                    self.touch_consumer(target, now);
+                    Some((target.clone(), ids))
+                }
+                (ConsumerPelPolicy::Drop, _) => {
+                    for id in &ids {
+                        self.pending.remove(id);
+                    }
+                    None
+                }
+            };
+            self.consumers.remove(&name);
+            removed.push(IdleConsumer { name, reassigned });
+        }
+        removed
+    }
+
+    // the consumer's pending entries with an ID greater than id
+    fn read_history(
+        &self,
+        entries: &BTreeMap<StreamId, StreamFields>,
+        consumer: &[u8],
+        id: StreamId,
+        count: Option<usize>,
+    ) -> StreamEntries {
+        self.pending
+            .range((Bound::Excluded(id), Bound::Unbounded))
+            .filter(|(_, pending)| pending.consumer == consumer)
+            .filter_map(|(id, _)| entries.get(id).map(|fields| (*id, fields.clone())))
+            .take(count.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+impl Backend {
+    // consumers idle for longer are deleted, zero never deletes them
+    pub fn stream_consumer_idle_timeout(&self) -> Duration {
+        *self.stream_consumer_idle_timeout.lock().unwrap()
+    }
+
+    pub fn set_stream_consumer_idle_timeout(&self, timeout: Duration) {
+        *self.stream_consumer_idle_timeout.lock().unwrap() = timeout;
+    }
+
+    pub fn stream_consumer_idle_pel(&self) -> ConsumerPelPolicy {
+        *self.stream_consumer_idle_pel.lock().unwrap()
+    }
+
+    pub fn set_stream_consumer_idle_pel(&self, policy: ConsumerPelPolicy) {
+        *self.stream_consumer_idle_pel.lock().unwrap() = policy;
+    }
+
+    // delete the idle consumers of every group, returns how many were deleted. Their
+    // removal is propagated as the XCLAIM and XGROUP DELCONSUMER it amounts to.
+    pub async fn remove_idle_consumers(&self) -> usize {
+        let timeout = self.stream_consumer_idle_timeout();
+        if timeout.is_zero() || self.replication.is_replica() || self.is_paused(true) {
+            return 0;
+        }
+        let keys: Vec<Key> = self
+            .db
+            .iter()
+            .filter(|entry| matches!(&entry.value, Value::Stream(s) if !s.groups.is_empty()))
+            .map(|entry| entry.key().clone())
+            .collect();
+        if keys.is_empty() {
+            return 0;
+        }
+        let now = now_ms();
+        let deadline = now.saturating_sub(timeout.as_millis() as u64);
+        let policy = self.stream_consumer_idle_pel();
+        let _barrier = self.replication.write_barrier().await;
+        let mut deleted = 0;
+        for key in keys {
+            let mut effects = Vec::new();
+            if let Some(mut stream) = self.value_mut::<Stream>(&key) {
+                for (group, consumers) in stream.groups.iter_mut() {
+                    for consumer in consumers.remove_idle_consumers(deadline, policy, now) {
+                        if let Some((target, ids)) = consumer.reassigned {
+                            let mut args: Vec<Vec<u8>> = vec![
+                                "xclaim".into(),
+                                key.to_vec(),
+                                group.clone(),
+                                target,
+                                "0".into(),
+                            ];
+                            args.extend(ids.iter().map(|id| id.to_string().into()));
+                            args.push("justid".into());
+                            effects.push(args);
+                        }
+                        effects.push(vec![
+                            "xgroup".into(),
+                            "delconsumer".into(),
+                            key.to_vec(),
+                            group.clone(),
+                            consumer.name,
+                        ]);
+                        deleted += 1;
+                    }
+                }
+            }
+            for args in effects {
+                self.publish_effect(RespArray::new(
+                    args.into_iter()
+                        .map(|arg| RespFrame::from(BulkString::new(arg)))
+                        .collect::<Vec<_>>(),
+                ));
+            }
+        }
+        deleted
+    }
+
+    // delete idle consumers until the task is aborted
+    pub async fn run_consumer_cleanup(self) {
+        let mut interval = tokio::time::interval(CONSUMER_CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.remove_idle_consumers().await;
+        }
+    }
+
+    // run f on the group, NOGROUP when the key or the group doesn't exist
+    fn with_group<T>(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        f: impl FnOnce(&mut ConsumerGroup, &BTreeMap<StreamId, StreamFields>) -> T,
+    ) -> Result<T, String> {
+        let no_group = || {
+            format!(
+                "NOGROUP No such key '{}' or consumer group '{}'",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(group)
+            )
+        };
+        let mut stream = self.value_mut::<Stream>(key).ok_or_else(no_group)?;
+        let Stream {
+            entries, groups, ..
+        } = &mut *stream;
+        let group = groups.get_mut(group).ok_or_else(no_group)?;
+        Ok(f(group, entries))
+    }
+
+    // create a group starting after id, or after the last entry when id is None (`$`)
+    pub fn xgroup_create(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        id: Option<StreamId>,
+        mkstream: bool,
+    ) -> Result<(), String> {
+        if !mkstream && !self.has_value::<Stream>(key) {
+            return Err(
+                "ERR The XGROUP subcommand requires the key to exist. Note that for \
+                CREATE you may want to use the MKSTREAM option to create an empty stream \
+                automatically."
+                    .to_string(),
+            );
+        }
+        let mut stream = self.value_or_default::<Stream>(Key::from(key));
+        if stream.groups.contains_key(group) {
+            return Err("BUSYGROUP Consumer Group name already exists".to_string());
+        }
+        let id = id.unwrap_or(stream.last_id);
+        stream.groups.insert(group.to_vec(), ConsumerGroup::new(id));
+        Ok(())
+    }
+
+    pub fn xgroup_destroy(&self, key: &[u8], group: &[u8]) -> bool {
+        match self.value_mut::<Stream>(key) {
+            Some(mut stream) => stream.groups.remove(group).is_some(),
+            None => false,
+        }
+    }
+
+    // returns whether the consumer was created
+    pub fn xgroup_createconsumer(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+    ) -> Result<bool, String> {
+        self.with_group(key, group, |group, _| {
+            if group.consumers.contains_key(consumer) {
+                return false;
+            }
+            group.touch_consumer(consumer, now_ms());
+            true
+        })
+    }
+
+    // delete the consumer with its pending entries, returns the number of pending entries it had
+    pub fn xgroup_delconsumer(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+    ) -> Result<usize, String> {
+        self.with_group(key, group, |group, _| {
+            if group.consumers.remove(consumer).is_none() {
+                return 0;
+            }
+            let before = group.pending.len();
+            group
+                .pending
+                .retain(|_, pending| pending.consumer != consumer);
+            before - group.pending.len()
+        })
+    }
+
+    // read for a consumer from every stream. A None ID is `>`: the entries never delivered to
+    // the group, which are added to the PEL unless noack; otherwise the consumer's own pending
+    // entries after the ID. Streams without new entries are left out of the `>` reads.
+    pub fn xreadgroup(
+        &self,
+        group: &[u8],
+        consumer: &[u8],
+        streams: &[(Key, Option<StreamId>)],
+        count: Option<usize>,
+        noack: bool,
+    ) -> Result<Vec<(Key, StreamEntries)>, String> {
+        // check every group first, a failed read must not deliver anything
+        for (key, _) in streams {
+            self.with_group(key, group, |_, _| ())?;
+        }
+
+        let now = now_ms();
+        let mut ret = Vec::new();
+        for (key, id) in streams {
+            let entries = self.with_group(key, group, |group, entries| {
+                group.touch_consumer(consumer, now);
+                match id {
+                    Some(id) => Some(group.read_history(entries, consumer, *id, count)),
+                    None => {
+                        let new = group.read_new(entries, consumer, count, noack, now);
+                        (!new.is_empty()).then_some(new)
+                    }
+                }
+            })?;
+            if let Some(entries) = entries {
+                ret.push((key.clone(), entries));
+            }
+        }
+        Ok(ret)
+    }
+
+    // like xreadgroup, but wait for new entries when all the IDs are `>`.
+    // A timeout of None waits forever.
+    pub async fn blocking_xreadgroup(
+        &self,
+        group: &[u8],
+        consumer: &[u8],
+        streams: &[(Key, Option<StreamId>)],
+        count: Option<usize>,
+        noack: bool,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(Key, StreamEntries)>, String> {
+        // reading the history never blocks
+        if streams.iter().any(|(_, id)| id.is_some()) {
+            return self.xreadgroup(group, consumer, streams, count, noack);
+        }
+
+        let keys: Vec<Key> = streams.iter().map(|(key, _)| key.clone()).collect();
+        self.block_on_keys(&keys, timeout, || {
+            match self.xreadgroup(group, consumer, streams, count, noack) {
+                Ok(ret) if ret.is_empty() => None,
+                ret => Some(ret),
+            }
+        })
+        .await
+        .unwrap_or_else(|| Ok(Vec::new()))
+    }
+
+    // acknowledge entries, returns the number of entries removed from the PEL
+    pub fn xack(&self, key: &[u8], group: &[u8], ids: &[StreamId]) -> usize {
+        self.with_group(key, group, |group, _| {
+            ids.iter()
+                .filter(|id| group.pending.remove(id).is_some())
+                .count()
+        })
+        .unwrap_or(0)
+    }
+
+    pub fn xpending_summary(&self, key: &[u8], group: &[u8]) -> Result<PendingSummary, String> {
+        self.with_group(key, group, |group, _| {
+            let mut consumers: BTreeMap<&[u8], usize> = BTreeMap::new();
+            for pending in group.pending.values() {
+                *consumers.entry(&pending.consumer).or_default() += 1;
+            }
+            PendingSummary {
+                count: group.pending.len(),
+                range: group
+                    .pending
+                    .first_key_value()
+                    .zip(group.pending.last_key_value())
+                    .map(|((first, _), (last, _))| (*first, *last)),
+                consumers: consumers
+                    .into_iter()
+                    .map(|(consumer, count)| (consumer.to_vec(), count))
+                    .collect(),
+            }
+        })
+    }
+
+    // pending entries with start <= id <= end, optionally only the ones of a consumer or idle
+    // for at least min_idle milliseconds
+    #[allow(clippy::too_many_arguments)]
+    pub fn xpending_range(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+        consumer: Option<&[u8]>,
+        min_idle: u64,
+    ) -> Result<Vec<PendingDetail>, String> {
+        let now = now_ms();
+        self.with_group(key, group, |group, _| {
+            if start > end {
+                return Vec::new();
+            }
+            group
+                .pending
+                .range(start..=end)
+                .filter(|(_, pending)| consumer.is_none_or(|c| pending.consumer == c))
+                .map(|(id, pending)| PendingDetail {
+                    id: *id,
+                    consumer: pending.consumer.clone(),
+                    idle: now.saturating_sub(pending.delivered_at),
+                    delivery_count: pending.delivery_count,
+                })
+                .filter(|detail| detail.idle >= min_idle)
+                .take(count)
+                .collect()
+        })
+    }
+
+    // give the pending entries idle for at least min_idle milliseconds to consumer. Claimed
+    // entries count as delivered again, unless justid. Pending entries deleted from the stream
+    // are dropped from the PEL.
+    pub fn xclaim(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+        min_idle: u64,
+        ids: &[StreamId],
+        justid: bool,
+    ) -> Result<StreamEntries, String> {
+        let now = now_ms();
+        self.with_group(key, group, |group, entries| {
+            group.touch_consumer(consumer, now);
+            let mut ret = Vec::new();
+            for id in ids {
+                let Some(pending) = group.pending.get_mut(id) else {
+                    continue;
+                };
+                let Some(fields) = entries.get(id) else {
+                    group.pending.remove(id);
+                    continue;
+                };
+                if now.saturating_sub(pending.delivered_at) < min_idle {
+                    continue;
+                }
+                pending.consumer = consumer.to_vec();
+                pending.delivered_at = now;
+                if !justid {
+                    pending.delivery_count += 1;
+                }
+                ret.push((*id, fields.clone()));
+            }
+            ret
+        })
+    }
+
+    // like xclaim for the pending entries from start on that are idle for at least min_idle
+    // milliseconds, until count of them are claimed. The scan stops early after looking at
+    // count * 10 entries so a large PEL is claimed from over several calls.
+    #[allow(clippy::too_many_arguments)]
+    pub fn xautoclaim(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        justid: bool,
+    ) -> Result<AutoClaim, String> {
+        let now = now_ms();
+        self.with_group(key, group, |group, entries| {
+            group.touch_consumer(consumer, now);
+            let mut ret = AutoClaim::default();
+            let mut attempts = count.saturating_mul(AUTOCLAIM_ATTEMPTS_FACTOR);
+            let mut from = Bound::Included(start);
+            loop {
+                let next = group.pending.range((from, Bound::Unbounded)).next();
+                let Some(&id) = next.map(|(id, _)| id) else {
+                    break;
+                };
+                if attempts == 0 || ret.claimed.len() == count {
+                    ret.next = id;
+                    break;
+                }
+                attempts -= 1;
+                from = Bound::Excluded(id);
+                let Some(fields) = entries.get(&id) else {
+                    group.pending.remove(&id);
+                    ret.deleted.push(id);
+                    continue;
+                };
+                let Some(pending) = group.pending.get_mut(&id) else {
+                    continue;
+                };
+                if now.saturating_sub(pending.delivered_at) < min_idle {
+                    continue;
+                }
+                pending.consumer = consumer.to_vec();
+                pending.delivered_at = now;
+                if !justid {
+                    pending.delivery_count += 1;
+                }
+                ret.claimed.push((id, fields.clone()));
+            }
+            ret
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::set_mock_now_ms, StreamIdSpec};
+
+    fn add(backend: &Backend, key: &str, ms: u64) {
+        backend
+            .xadd(
+                key.into(),
+                StreamIdSpec::Explicit(StreamId::new(ms, 0)),
+                vec![(b"n".to_vec(), BulkString::from(ms.to_string()).into())],
+            )
+            .unwrap();
+    }
+
+    fn ids(entries: &StreamEntries) -> Vec<u64> {
+        entries.iter().map(|(id, _)| id.ms).collect()
+    }
+
+    #[test]
+    fn test_xgroup_create() {
+        let backend = Backend::new();
+        assert!(backend.xgroup_create(b"s", b"g", None, false).is_err());
+        assert_eq!(backend.xgroup_create(b"s", b"g", None, true), Ok(()));
+        assert_eq!(backend.xlen(b"s"), 0);
+        assert_eq!(
+            backend.xgroup_create(b"s", b"g", None, false),
+            Err("BUSYGROUP Consumer Group name already exists".to_string())
+        );
+
+        assert_eq!(backend.xgroup_createconsumer(b"s", b"g", b"c"), Ok(true));
+        assert_eq!(backend.xgroup_createconsumer(b"s", b"g", b"c"), Ok(false));
+        assert!(backend.xgroup_createconsumer(b"s", b"nope", b"c").is_err());
+        assert!(backend.xgroup_destroy(b"s", b"g"));
+        assert!(!backend.xgroup_destroy(b"s", b"g"));
+    }
+
+    #[tokio::test]
+    async fn test_remove_idle_consumers() {
+        let now = 1_700_000_000_000;
+        set_mock_now_ms(Some(now));
+        let backend = Backend::new();
+        for ms in 1..=3 {
+            add(&backend, "s", ms);
+        }
+        backend
+            .xgroup_create(b"s", b"g", Some(StreamId::MIN), false)
+            .unwrap();
+        let streams = [(Key::from("s"), None)];
+        backend
+            .xreadgroup(b"g", b"alice", &streams, Some(2), false)
+            .unwrap();
+        backend.xgroup_createconsumer(b"s", b"g", b"carol").unwrap();
+        // disabled by default
+        assert_eq!(backend.remove_idle_consumers().await, 0);
+
+        backend.set_stream_consumer_idle_timeout(Duration::from_secs(60));
+        set_mock_now_ms(Some(now + 30_000));
+        backend
+            .xreadgroup(b"g", b"bob", &streams, None, false)
+            .unwrap();
+        let mut effects = backend.subscribe_effects();
+        // alice and carol are idle, alice's entries go to bob, who was used last
+        set_mock_now_ms(Some(now + 61_000));
+        assert_eq!(backend.remove_idle_consumers().await, 2);
+        let summary = backend.xpending_summary(b"s", b"g").unwrap();
+        assert_eq!(summary.consumers, vec![(b"bob".to_vec(), 3)]);
+        let command = |args: &[&str]| {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+        };
+        let xclaim = ["xclaim", "s", "g", "bob", "0", "1-0", "2-0", "justid"];
+        assert_eq!(effects.try_recv().unwrap().command, command(&xclaim));
+        let delconsumer = ["xgroup", "delconsumer", "s", "g", "alice"];
+        assert_eq!(effects.try_recv().unwrap().command, command(&delconsumer));
+
+        // bob has nobody to give his entries to, they are dropped only with drop
+        set_mock_now_ms(Some(now + 200_000));
+        assert_eq!(backend.remove_idle_consumers().await, 0);
+        backend.set_stream_consumer_idle_pel(ConsumerPelPolicy::Drop);
+        assert_eq!(backend.remove_idle_consumers().await, 1);
+        assert_eq!(backend.xpending_summary(b"s", b"g").unwrap().count, 0);
+        set_mock_now_ms(None);
+    }
+
+    #[test]
+    fn test_xreadgroup_and_xack() {
+        let backend = Backend::new();
+        for ms in 1..=3 {
+            add(&backend, "s", ms);
+        }
+        backend
+            .xgroup_create(b"s", b"g", Some(StreamId::MIN), false)
+            .unwrap();
+
+        let streams = [(Key::from("s"), None)];
+        let ret = backend
+            .xreadgroup(b"g", b"alice", &streams, Some(2), false)
+            .unwrap();
+        assert_eq!(ids(&ret[0].1), vec![1, 2]);
+        let ret = backend
+            .xreadgroup(b"g", b"bob", &streams, None, false)
+            .unwrap();
+        assert_eq!(ids(&ret[0].1), vec![3]);
+        assert!(backend
+            .xreadgroup(b"g", b"bob", &streams, None, false)
+            .unwrap()
+            .is_empty());
+
+        // history only has the consumer's own entries
+        let history = [(Key::from("s"), Some(StreamId::MIN))];
+        let ret = backend
+            .xreadgroup(b"g", b"alice", &history, None, false)
+            .unwrap();
+        assert_eq!(ids(&ret[0].1), vec![1, 2]);
+
+        assert_eq!(
+            backend.xack(b"s", b"g", &[StreamId::new(1, 0), StreamId::new(9, 0)]),
+            1
+        );
+        let summary = backend.xpending_summary(b"s", b"g").unwrap();
+        assert_eq!(summary.count, 2);
+        assert_eq!(
+            summary.range,
+            Some((StreamId::new(2, 0), StreamId::new(3, 0)))
+        );
+        assert_eq!(
+            summary.consumers,
+            vec![(b"alice".to_vec(), 1), (b"bob".to_vec(), 1)]
+        );
+
+        assert!(backend
+            .xreadgroup(b"nope", b"alice", &streams, None, false)
+            .unwrap_err()
+            .starts_with("NOGROUP"));
+        assert_eq!(backend.xgroup_delconsumer(b"s", b"g", b"bob"), Ok(1));
+        assert_eq!(backend.xpending_summary(b"s", b"g").unwrap().count, 1);
+    }
+
+    #[test]
+    fn test_xpending_range_and_xclaim() {
+        let backend = Backend::new();
+        add(&backend, "s", 1);
+        add(&backend, "s", 2);
+        backend
+            .xgroup_create(b"s", b"g", Some(StreamId::MIN), false)
+            .unwrap();
+        backend
+            .xreadgroup(b"g", b"alice", &[("s".into(), None)], None, false)
+            .unwrap();
+
+        let pending = backend
+            .xpending_range(b"s", b"g", StreamId::MIN, StreamId::MAX, 10, None, 0)
+            .unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].consumer, b"alice");
+        assert_eq!(pending[0].delivery_count, 1);
+        assert!(backend
+            .xpending_range(
+                b"s",
+                b"g",
+                StreamId::MIN,
+                StreamId::MAX,
+                10,
+                Some(b"bob".as_slice()),
+                0
+            )
+            .unwrap()
+            .is_empty());
+
+        // not idle for long enough
+        let claimed = backend
+            .xclaim(b"s", b"g", b"bob", 60_000, &[StreamId::new(1, 0)], false)
+            .unwrap();
+        assert!(claimed.is_empty());
+
+        let claimed = backend
+            .xclaim(b"s", b"g", b"bob", 0, &[StreamId::new(1, 0)], false)
+            .unwrap();
+        assert_eq!(ids(&claimed), vec![1]);
+        let pending = backend
+            .xpending_range(
+                b"s",
+                b"g",
+                StreamId::MIN,
+                StreamId::MAX,
+                10,
+                Some(b"bob".as_slice()),
+                0,
+            )
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].delivery_count, 2);
+    }
+
+    #[test]
+    fn test_xautoclaim() {
+        let backend = Backend::new();
+        for ms in 1..=5 {
+            add(&backend, "s", ms);
+        }
+        backend
+            .xgroup_create(b"s", b"g", Some(StreamId::MIN), false)
+            .unwrap();
+        backend
+            .xreadgroup(b"g", b"alice", &[("s".into(), None)], None, false)
+            .unwrap();
+        backend
+            .value_mut::<Stream>(b"s")
+            .unwrap()
+            .entries
+            .remove(&StreamId::new(2, 0));
+
+        // not idle for long enough
+        let ret = backend
+            .xautoclaim(b"s", b"g", b"bob", 60_000, StreamId::MIN, 10, false)
+            .unwrap();
+        assert!(ret.claimed.is_empty());
+        assert_eq!(ret.deleted, vec![StreamId::new(2, 0)]);
+        assert_eq!(ret.next, StreamId::MIN);
+
+        // claimed in two rounds from the cursor
+        let ret = backend
+            .xautoclaim(b"s", b"g", b"bob", 0, StreamId::MIN, 2, false)
+            .unwrap();
+        assert_eq!(ids(&ret.claimed), vec![1, 3]);
+        assert_eq!(ret.next, StreamId::new(4, 0));
+        let ret = backend
+            .xautoclaim(b"s", b"g", b"bob", 0, ret.next, 2, true)
+            .unwrap();
+        assert_eq!(ids(&ret.claimed), vec![4, 5]);
+        assert_eq!(ret.next, StreamId::MIN);
+
+        let pending = backend
+            .xpending_range(
+                b"s",
+                b"g",
+                StreamId::MIN,
+                StreamId::MAX,
+                10,
+                Some(b"bob".as_slice()),
+                0,
+            )
+            .unwrap();
+        let counts: Vec<_> = pending.iter().map(|p| p.delivery_count).collect();
+        // justid doesn't count as a delivery
+        assert_eq!(counts, vec![2, 2, 1, 1]);
+
+        // the scan is bounded by count * 10 entries
+        let ret = backend
+            .xautoclaim(b"s", b"g", b"carol", 60_000, StreamId::MIN, 0, false)
+            .unwrap();
+        assert_eq!(ret.next, StreamId::new(1, 0));
+        assert!(backend
+            .xautoclaim(b"s", b"nope", b"bob", 0, StreamId::MIN, 1, false)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_blocking_xreadgroup() {
+        let backend = Backend::new();
+        backend.xgroup_create(b"s", b"g", None, true).unwrap();
+        let streams = [(Key::from("s"), None)];
+        let ret = backend
+            .blocking_xreadgroup(
+                b"g",
+                b"c",
+                &streams,
+                None,
+                false,
+                Some(Duration::from_millis(10)),
+            )
+            .await
+            .unwrap();
+        assert!(ret.is_empty());
+
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move {
+            cloned
+                .blocking_xreadgroup(b"g", b"c", &[("s".into(), None)], None, false, None)
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        add(&backend, "s", 1);
+
+        let ret = handle.await.unwrap().unwrap();
+        assert_eq!(ids(&ret[0].1), vec![1]);
+    }
+}
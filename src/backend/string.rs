@@ -0,0 +1,356 @@
+use super::{Backend, Entry, Key, TypedValue, Value};
+use crate::{BulkString, RespFrame};
+use dashmap::mapref::entry::Entry as MapEntry;
+use std::borrow::Cow;
+
+// Strings that are the canonical decimal form of an i64 are stored as RespFrame::Integer,
+// the int encoding of Redis: INCR works on the number without parsing it, and GET gives
+// the bytes back. Anything else, "007" or "+1" included, stays a bulk string.
+
+pub const WRONGTYPE_ERROR: &str =
+    "WRONGTYPE Operation against a key holding the wrong kind of value";
+pub const NOT_INTEGER_ERROR: &str = "ERR value is not an integer or out of range";
+pub const OVERFLOW_ERROR: &str = "ERR increment or decrement would overflow";
+// SETRANGE and APPEND don't grow a string past proto-max-bulk-len, no client could have
+// sent it whole
+pub const STRING_TOO_LONG_ERROR: &str =
+    "ERR string exceeds maximum allowed size (proto-max-bulk-len)";
+
+// the longest i64, -9223372036854775808
+const MAX_INTEGER_LEN: usize = 20;
+
+// when SET may write the key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SetCondition {
+    #[default]
+    Always,
+    // only when the key doesn't exist
+    Nx,
+    // only when the key exists
+    Xx,
+}
+
+impl Backend {
+    // set key if the condition holds, returns whether it was set and the value it had
+    pub fn set_with(
+        &self,
+        key: Key,
+        value: RespFrame,
+        condition: SetCondition,
+    ) -> (bool, Option<RespFrame>) {
+        let value = encode_string(value);
+        let (set, old) = match self.db.entry(key) {
+            // the key may exist with another type, it is replaced unless NX
+            MapEntry::Occupied(entry) => {
+                let mut entry = entry.into_ref();
+                let (key, entry) = entry.pair_mut();
+                match condition {
+                    SetCondition::Nx => (false, RespFrame::of(&entry.value).cloned()),
+                    _ => {
+                        let old = std::mem::replace(&mut entry.value, Value::String(value));
+                        self.set_entry_expire(key, entry, None);
+                        (true, RespFrame::from_value(old))
+                    }
+                }
+            }
+            MapEntry::Vacant(entry) => match condition {
+                SetCondition::Xx => (false, None),
+                _ => {
+                    entry.insert(Entry::new(Value::String(value)));
+                    (true, None)
+                }
+            },
+        };
+        if set {
+            self.bump_string_epoch();
+        }
+        (set, old.map(decode_string))
+    }
+
+    // INCRBY, DECRBY with a negative increment. A missing key counts as 0.
+    pub fn incr_by(&self, key: Key, increment: i64) -> Result<i64, &'static str> {
+        let value = match self.db.entry(key) {
+            MapEntry::Occupied(mut entry) => {
+                let Value::String(value) = &mut entry.get_mut().value else {
+                    return Err(WRONGTYPE_ERROR);
+                };
+                let current = match value {
+                    RespFrame::Integer(i) => *i,
+                    _ => return Err(NOT_INTEGER_ERROR),
+                };
+                let incremented = current.checked_add(increment).ok_or(OVERFLOW_ERROR)?;
+                *value = RespFrame::Integer(incremented);
+                incremented
+            }
+            MapEntry::Vacant(entry) => {
+                entry.insert(Entry::new(Value::String(RespFrame::Integer(increment))));
+                increment
+            }
+        };
+        self.bump_string_epoch();
+        Ok(value)
+    }
+
+    // overwrite the string from offset on, padding it with zeros up to offset. Returns the
+    // length of the string, a missing key stays missing when there is nothing to write.
+    pub fn setrange(&self, key: Key, offset: usize, bytes: &[u8]) -> Result<usize, &'static str> {
+        if bytes.is_empty() {
+            return Ok(self
+                .value::<RespFrame>(&key)
+                .map_or(0, |value| string_bytes(&value).len()));
+        }
+        let end = offset
+            .checked_add(bytes.len())
+            .filter(|end| *end <= self.decoder_limits().max_bulk_len)
+            .ok_or(STRING_TOO_LONG_ERROR)?;
+        self.update_string(key, |string| {
+            if string.len() < end {
+                string.resize(end, 0);
+            }
+            string[offset..end].copy_from_slice(bytes);
+            Ok(())
+        })
+    }
+
+    // append to the string, a missing key is created. Returns the length of the string.
+    pub fn append(&self, key: Key, bytes: &[u8]) -> Result<usize, &'static str> {
+        let max = self.decoder_limits().max_bulk_len;
+        // checked first so a missing key isn't created empty
+        if bytes.len() > max {
+            return Err(STRING_TOO_LONG_ERROR);
+        }
+        self.update_string(key, |string| {
+            if string.len() + bytes.len() > max {
+                return Err(STRING_TOO_LONG_ERROR);
+            }
+            string.extend_from_slice(bytes);
+            Ok(())
+        })
+    }
+
+    // change the bytes of the string in place, a missing key is an empty string. The
+    // string is left as it was when f fails.
+    fn update_string(
+        &self,
+        key: Key,
+        f: impl FnOnce(&mut Vec<u8>) -> Result<(), &'static str>,
+    ) -> Result<usize, &'static str> {
+        let mut entry = self
+            .db
+            .entry(key)
+            .or_insert_with(|| Entry::new(Value::String(BulkString::new(Vec::new()).into())));
+        let Value::String(value) = &mut entry.value else {
+            return Err(WRONGTYPE_ERROR);
+        };
+        let mut string = match &mut *value {
+            RespFrame::BulkString(s) => Vec::from(std::mem::take(&mut s.0)),
+            frame => string_bytes(frame).into_owned(),
+        };
+        let result = f(&mut string);
+        let len = string.len();
+        *value = encode_string(BulkString::new(string).into());
+        drop(entry);
+        result?;
+        self.bump_string_epoch();
+        Ok(len)
+    }
+
+    // the bytes of the string between start and end (both inclusive), negative offsets
+    // count from the end
+    pub fn getrange(&self, key: &[u8], start: i64, end: i64) -> BulkString {
+        let Some(value) = self.value::<RespFrame>(key) else {
+            return BulkString::new(Vec::new());
+        };
+        let integer;
+        let bytes: &[u8] = match value.value() {
+            RespFrame::BulkString(s) => s,
+            RespFrame::Integer(i) => {
+                integer = i.to_string();
+                integer.as_bytes()
+            }
+            _ => &[],
+        };
+        let len = bytes.len() as i64;
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let end = if end < 0 { len + end } else { end.min(len - 1) };
+        if start > end || start >= len {
+            return BulkString::new(Vec::new());
+        }
+        BulkString::new(bytes[start as usize..=end as usize].to_vec())
+    }
+}
+
+// the bytes of a string value, integers are stored by their decimal representation
+pub(super) fn string_bytes(frame: &RespFrame) -> Cow<'_, [u8]> {
+    match frame {
+        RespFrame::BulkString(s) => Cow::Borrowed(&s.0),
+        RespFrame::SimpleString(s) => Cow::Borrowed(s.0.as_bytes()),
+        RespFrame::Integer(i) => Cow::Owned(i.to_string().into_bytes()),
+        _ => Cow::Owned(Vec::new()),
+    }
+}
+
+// the value as it is stored, an integer when the bytes are one
+pub(crate) fn encode_string(value: RespFrame) -> RespFrame {
+    let RespFrame::BulkString(s) = &value else {
+        return value;
+    };
+    if s.is_empty() || s.len() > MAX_INTEGER_LEN {
+        return value;
+    }
+    let integer = std::str::from_utf8(s)
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok());
+    match integer {
+        // only when printing it gives the same bytes back
+        Some(i) if i.to_string().as_bytes() == &s[..] => RespFrame::Integer(i),
+        _ => value,
+    }
+}
+
+// the value as clients see it, a bulk string
+pub(crate) fn decode_string(value: RespFrame) -> RespFrame {
+    match value {
+        RespFrame::Integer(i) => BulkString::from(i.to_string()).into(),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_shares_value() {
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::new(vec![b'x'; 1024 * 1024]).into());
+        let (Some(RespFrame::BulkString(first)), Some(RespFrame::BulkString(second))) =
+            (backend.get(b"a"), backend.get(b"a"))
+        else {
+            panic!("a is a bulk string");
+        };
+        assert_eq!(first.as_ptr(), second.as_ptr());
+    }
+
+    #[test]
+    fn test_set_with() {
+        let backend = Backend::new();
+        let value = |s: &str| -> RespFrame { BulkString::from(s).into() };
+        assert_eq!(
+            backend.set_with("a".into(), value("1"), SetCondition::Xx),
+            (false, None)
+        );
+        assert_eq!(
+            backend.set_with("a".into(), value("1"), SetCondition::Nx),
+            (true, None)
+        );
+        assert_eq!(
+            backend.set_with("a".into(), value("2"), SetCondition::Nx),
+            (false, Some(value("1")))
+        );
+        assert_eq!(
+            backend.set_with("a".into(), value("2"), SetCondition::Always),
+            (true, Some(value("1")))
+        );
+        assert_eq!(backend.get(b"a"), Some(value("2")));
+
+        backend.rpush("l".into(), vec![value("x")]);
+        assert_eq!(
+            backend.set_with("l".into(), value("1"), SetCondition::Nx),
+            (false, None)
+        );
+    }
+
+    #[test]
+    fn test_setrange_append() {
+        let backend = Backend::new();
+        assert_eq!(backend.setrange("a".into(), 5, b""), Ok(0));
+        assert!(!backend.key_exists(b"a"));
+        assert_eq!(backend.setrange("a".into(), 2, b"llo"), Ok(5));
+        assert_eq!(
+            backend.get(b"a"),
+            Some(BulkString::new(b"\0\0llo".to_vec()).into())
+        );
+        assert_eq!(backend.setrange("a".into(), 0, b"he"), Ok(5));
+        assert_eq!(backend.append("a".into(), b" world"), Ok(11));
+        assert_eq!(
+            backend.get(b"a"),
+            Some(BulkString::from("hello world").into())
+        );
+
+        // still stored as an integer when the bytes are one
+        assert_eq!(backend.append("n".into(), b"12"), Ok(2));
+        assert_eq!(backend.append("n".into(), b"3"), Ok(3));
+        assert_eq!(backend.incr_by("n".into(), 1), Ok(124));
+
+        backend.update_decoder_limits(|limits| limits.max_bulk_len = 12);
+        assert_eq!(backend.append("a".into(), b"!"), Ok(12));
+        assert_eq!(backend.append("a".into(), b"!"), Err(STRING_TOO_LONG_ERROR));
+        assert_eq!(
+            backend.setrange("a".into(), 12, b"!"),
+            Err(STRING_TOO_LONG_ERROR)
+        );
+        assert_eq!(
+            backend.setrange("b".into(), usize::MAX, b"!"),
+            Err(STRING_TOO_LONG_ERROR)
+        );
+        assert_eq!(
+            backend.get(b"a"),
+            Some(BulkString::from("hello world!").into())
+        );
+        assert!(!backend.key_exists(b"b"));
+
+        backend.rpush("l".into(), vec![BulkString::from("x").into()]);
+        assert_eq!(backend.append("l".into(), b"x"), Err(WRONGTYPE_ERROR));
+    }
+
+    #[test]
+    fn test_getrange() {
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("Hello World").into());
+        assert_eq!(backend.getrange(b"a", 0, 4), BulkString::from("Hello"));
+        assert_eq!(backend.getrange(b"a", -5, -1), BulkString::from("World"));
+        assert_eq!(backend.getrange(b"a", 6, 100), BulkString::from("World"));
+        assert_eq!(backend.getrange(b"a", 5, 2), BulkString::from(""));
+        assert_eq!(backend.getrange(b"missing", 0, -1), BulkString::from(""));
+    }
+
+    #[test]
+    fn test_int_encoding() {
+        let backend = Backend::new();
+        let value = |s: &str| -> RespFrame { BulkString::from(s).into() };
+        backend.set("n".into(), value("-42"));
+        assert_eq!(
+            backend.value::<RespFrame>(b"n").unwrap().value(),
+            &RespFrame::Integer(-42)
+        );
+        assert_eq!(backend.object_encoding(b"n"), Some("int"));
+        assert_eq!(backend.get(b"n"), Some(value("-42")));
+        assert_eq!(backend.getrange(b"n", 1, -1), BulkString::from("42"));
+
+        for raw in ["007", "+1", "1.5", "", "99999999999999999999", " 1"] {
+            backend.set("s".into(), value(raw));
+            assert_eq!(backend.object_encoding(b"s"), Some("raw"), "{:?}", raw);
+            assert_eq!(backend.get(b"s"), Some(value(raw)));
+        }
+    }
+
+    #[test]
+    fn test_incr_by() {
+        let backend = Backend::new();
+        assert_eq!(backend.incr_by("n".into(), 1), Ok(1));
+        assert_eq!(backend.incr_by("n".into(), -3), Ok(-2));
+        assert_eq!(backend.get(b"n"), Some(BulkString::from("-2").into()));
+
+        backend.set("n".into(), BulkString::from(i64::MAX.to_string()).into());
+        assert_eq!(backend.incr_by("n".into(), 1), Err(OVERFLOW_ERROR));
+        backend.set("s".into(), BulkString::from("abc").into());
+        assert_eq!(backend.incr_by("s".into(), 1), Err(NOT_INTEGER_ERROR));
+        backend.rpush("l".into(), vec![BulkString::from("x").into()]);
+        assert_eq!(backend.incr_by("l".into(), 1), Err(WRONGTYPE_ERROR));
+    }
+}
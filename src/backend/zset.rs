@@ -0,0 +1,704 @@
+// Sorted sets: members ordered by a float score, then by member name for equal scores.
+
+use super::{Backend, Key, KeyType, Value};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
+    time::Duration,
+};
+
+// f64 with a total order, NaN scores are rejected before they get here
+#[derive(Debug, Clone, Copy)]
+pub struct Score(pub f64);
+
+// members with their scores
+type ScoredMembers = Vec<(Vec<u8>, f64)>;
+
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    scores: HashMap<Vec<u8>, f64>,
+    ordered: BTreeSet<(Score, Vec<u8>)>,
+}
+
+// which members ZADD may update
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZAddCondition {
+    #[default]
+    Always,
+    // only add new members
+    Nx,
+    // only update existing members
+    Xx,
+}
+
+// how ZUNIONSTORE and ZINTERSTORE combine the scores of a member in several sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZAggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+// one end of a score range, `(` in the command makes it exclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub score: f64,
+    pub exclusive: bool,
+}
+
+// one end of a lexicographic range: `-` and `+` or a `[` inclusive or `(` exclusive member
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(Vec<u8>),
+    Exclusive(Vec<u8>),
+}
+
+impl ScoreBound {
+    // is the score above this lower bound
+    fn above(&self, score: f64) -> bool {
+        match self.exclusive {
+            true => score > self.score,
+            false => score >= self.score,
+        }
+    }
+
+    // is the score below this upper bound
+    fn below(&self, score: f64) -> bool {
+        match self.exclusive {
+            true => score < self.score,
+            false => score <= self.score,
+        }
+    }
+}
+
+impl ZAggregate {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            // inf plus -inf is 0, like Redis
+            ZAggregate::Sum => zero_nan(a + b),
+            ZAggregate::Min => a.min(b),
+            ZAggregate::Max => a.max(b),
+        }
+    }
+}
+
+// 0 times inf and inf minus inf are NaN, which can't be a score
+fn zero_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+impl LexBound {
+    fn above(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(min) => member >= min.as_slice(),
+            LexBound::Exclusive(min) => member > min.as_slice(),
+        }
+    }
+
+    fn below(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(max) => member <= max.as_slice(),
+            LexBound::Exclusive(max) => member < max.as_slice(),
+        }
+    }
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl SortedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // add the member or update its score, returns the old score
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> Option<f64> {
+        let old = self.scores.insert(member.clone(), score);
+        if let Some(old) = old {
+            self.ordered.remove(&(Score(old), member.clone()));
+        }
+        self.ordered.insert((Score(score), member));
+        old
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.ordered.remove(&(Score(score), member.to_vec()));
+        Some(score)
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    // members in score order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&[u8], f64)> {
+        self.ordered
+            .iter()
+            .map(|(score, member)| (member.as_slice(), score.0))
+    }
+
+    // members between the start and stop ranks, inclusive. Negative ranks count from the
+    // end, rev ranks from the greatest score.
+    pub fn range_by_rank(&self, start: i64, stop: i64, rev: bool) -> Vec<(Vec<u8>, f64)> {
+        let len = self.len() as i64;
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let stop = if stop < 0 {
+            len + stop
+        } else {
+            stop.min(len - 1)
+        };
+        if start > stop || start >= len {
+            return Vec::new();
+        }
+        let (skip, take) = (start as usize, (stop - start + 1) as usize);
+        let clone = |(member, score): (&[u8], f64)| (member.to_vec(), score);
+        if rev {
+            self.iter().rev().skip(skip).take(take).map(clone).collect()
+        } else {
+            self.iter().skip(skip).take(take).map(clone).collect()
+        }
+    }
+
+    // remove up to count members with the lowest scores, or the highest if max
+    pub fn pop(&mut self, count: usize, max: bool) -> Vec<(Vec<u8>, f64)> {
+        let mut popped = Vec::with_capacity(count.min(self.len()));
+        while popped.len() < count {
+            let entry = match max {
+                true => self.ordered.pop_last(),
+                false => self.ordered.pop_first(),
+            };
+            let Some((score, member)) = entry else {
+                break;
+            };
+            self.scores.remove(&member);
+            popped.push((member, score.0));
+        }
+        popped
+    }
+
+    // number of members with a score between min and max
+    pub fn count_by_score(&self, min: ScoreBound, max: ScoreBound) -> usize {
+        self.ordered
+            .range((Score(min.score), Vec::new())..)
+            .skip_while(|(score, _)| !min.above(score.0))
+            .take_while(|(score, _)| max.below(score.0))
+            .count()
+    }
+
+    // members between min and max in member order, which is meant for sets where all the
+    // scores are equal. Skips offset members and returns at most count, all if it's None.
+    pub fn range_by_lex(
+        &self,
+        min: &LexBound,
+        max: &LexBound,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Vec<Vec<u8>> {
+        self.iter()
+            .skip_while(|(member, _)| !min.above(member))
+            .take_while(|(member, _)| max.below(member))
+            .skip(offset)
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(member, _)| member.to_vec())
+            .collect()
+    }
+}
+
+impl Backend {
+    // add or update members, returns the number of members added, or added and updated if ch
+    pub fn zadd(
+        &self,
+        key: Key,
+        members: Vec<(f64, Vec<u8>)>,
+        condition: ZAddCondition,
+        ch: bool,
+    ) -> usize {
+        if condition == ZAddCondition::Xx && !self.has_value::<SortedSet>(&key) {
+            return 0;
+        }
+        let mut zset = self.value_or_default::<SortedSet>(key.clone());
+        let mut changed = 0;
+        for (score, member) in members {
+            let exists = zset.score(&member);
+            match (condition, exists) {
+                (ZAddCondition::Nx, Some(_)) | (ZAddCondition::Xx, None) => continue,
+                (_, None) => changed += 1,
+                (_, Some(old)) if ch && old != score => changed += 1,
+                _ => {}
+            }
+            zset.insert(member, score);
+        }
+        // NX or XX may leave a new key empty
+        let empty = zset.is_empty();
+        drop(zset);
+        if empty {
+            self.remove_value_if::<SortedSet>(&key, |zset| zset.is_empty());
+        } else {
+            self.signal_key_ready(&key);
+        }
+        changed
+    }
+
+    // remove members, returns the number removed. The key goes with its last member.
+    pub fn zrem(&self, key: &[u8], members: &[Vec<u8>]) -> usize {
+        let Some(mut zset) = self.value_mut::<SortedSet>(key) else {
+            return 0;
+        };
+        let removed = members
+            .iter()
+            .filter(|member| zset.remove(member).is_some())
+            .count();
+        drop(zset);
+        self.remove_value_if::<SortedSet>(key, |zset| zset.is_empty());
+        removed
+    }
+
+    // replace the key, of whatever type, with a sorted set of the members. An empty set
+    // deletes the key. Returns the size of the stored set.
+    pub fn zstore(&self, key: Key, members: Vec<(f64, Vec<u8>)>) -> usize {
+        let mut zset = SortedSet::new();
+        for (score, member) in members {
+            zset.insert(member, score);
+        }
+        // a sorted set is swapped for the new one, so readers see either of them in full.
+        // It keeps the TTL of the sorted set it replaces.
+        let len = zset.len();
+        if len > 0 {
+            let keep_ttl = self.key_type(&key) == Some(KeyType::ZSet);
+            self.replace_value(key.clone(), Value::ZSet(zset), keep_ttl);
+            self.signal_key_ready(&key);
+        } else {
+            self.remove_keys(std::slice::from_ref(&key));
+        }
+        len
+    }
+
+    // add the increment to the member's score, a missing member starts from 0. Returns the
+    // new score, or an error when the sum is NaN, like inf plus -inf.
+    pub fn zincrby(&self, key: Key, increment: f64, member: Vec<u8>) -> Result<f64, String> {
+        let score = {
+            let mut zset = self.value_or_default::<SortedSet>(key.clone());
+            let score = zset.score(&member).unwrap_or(0.0) + increment;
+            if score.is_nan() {
+                drop(zset);
+                self.remove_value_if::<SortedSet>(&key, |zset| zset.is_empty());
+                return Err("resulting score is not a number (NaN)".to_string());
+            }
+            zset.insert(member, score);
+            score
+        };
+        self.signal_key_ready(&key);
+        Ok(score)
+    }
+
+    // pop up to count members with the lowest scores, or the highest if max. The key goes
+    // with its last member.
+    pub fn zpop(&self, key: &[u8], count: usize, max: bool) -> Vec<(Vec<u8>, f64)> {
+        let Some(mut zset) = self.value_mut::<SortedSet>(key) else {
+            return Vec::new();
+        };
+        let popped = zset.pop(count, max);
+        drop(zset);
+        self.remove_value_if::<SortedSet>(key, |zset| zset.is_empty());
+        popped
+    }
+
+    // pop one member from the first non-empty sorted set among keys, None if all of them
+    // are empty
+    pub fn zpop_first(&self, keys: &[Key], max: bool) -> Option<(Key, Vec<u8>, f64)> {
+        keys.iter().find_map(|key| {
+            self.zpop(key, 1, max)
+                .pop()
+                .map(|(member, score)| (key.clone(), member, score))
+        })
+    }
+
+    // like zpop_first, but wait until one of the sorted sets gets a member or the timeout
+    // expires. A timeout of None waits forever.
+    pub async fn blocking_zpop(
+        &self,
+        keys: &[Key],
+        timeout: Option<Duration>,
+        max: bool,
+    ) -> Option<(Key, Vec<u8>, f64)> {
+        self.block_on_keys(keys, timeout, || self.zpop_first(keys, max))
+            .await
+    }
+
+    pub fn zcard(&self, key: &[u8]) -> usize {
+        self.value::<SortedSet>(key)
+            .map(|zset| zset.len())
+            .unwrap_or(0)
+    }
+
+    pub fn zcount(&self, key: &[u8], min: ScoreBound, max: ScoreBound) -> usize {
+        self.value::<SortedSet>(key)
+            .map(|zset| zset.count_by_score(min, max))
+            .unwrap_or(0)
+    }
+
+    pub fn zrangebylex(
+        &self,
+        key: &[u8],
+        min: &LexBound,
+        max: &LexBound,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Vec<Vec<u8>> {
+        self.value::<SortedSet>(key)
+            .map(|zset| zset.range_by_lex(min, max, offset, count))
+            .unwrap_or_default()
+    }
+
+    // store the union of the sorted sets at keys in dest, each score multiplied by the
+    // weight of its set. Returns the size of the result.
+    pub fn zunionstore(
+        &self,
+        dest: Key,
+        keys: &[Key],
+        weights: &[f64],
+        aggregate: ZAggregate,
+    ) -> usize {
+        let mut union: HashMap<Vec<u8>, f64> = HashMap::new();
+        for (members, weight) in self.zweighted(keys, weights) {
+            for (member, score) in members {
+                let score = zero_nan(score * weight);
+                union
+                    .entry(member)
+                    .and_modify(|s| *s = aggregate.apply(*s, score))
+                    .or_insert(score);
+            }
+        }
+        let members = union.into_iter().map(|(m, s)| (s, m)).collect();
+        self.zstore(dest, members)
+    }
+
+    // store the members found in every sorted set at keys in dest, like zunionstore.
+    // Returns the size of the result.
+    pub fn zinterstore(
+        &self,
+        dest: Key,
+        keys: &[Key],
+        weights: &[f64],
+        aggregate: ZAggregate,
+    ) -> usize {
+        let mut sets = self.zweighted(keys, weights);
+        sets.sort_by_key(|(members, _)| members.len());
+        let mut sets = sets.into_iter();
+        let mut inter: HashMap<Vec<u8>, f64> = match sets.next() {
+            Some((members, weight)) => members
+                .into_iter()
+                .map(|(member, score)| (member, zero_nan(score * weight)))
+                .collect(),
+            None => HashMap::new(),
+        };
+        for (members, weight) in sets {
+            let members: HashMap<Vec<u8>, f64> = members.into_iter().collect();
+            inter.retain(|member, s| match members.get(member) {
+                Some(score) => {
+                    *s = aggregate.apply(*s, zero_nan(score * weight));
+                    true
+                }
+                None => false,
+            });
+        }
+        let members = inter.into_iter().map(|(m, s)| (s, m)).collect();
+        self.zstore(dest, members)
+    }
+
+    // the members of each sorted set with the weight of the set, missing weights are 1. The
+    // sets are copied one at a time, so dest may be among the keys.
+    fn zweighted(&self, keys: &[Key], weights: &[f64]) -> Vec<(ScoredMembers, f64)> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let members = self
+                    .value::<SortedSet>(key)
+                    .map(|zset| {
+                        zset.iter()
+                            .map(|(member, score)| (member.to_vec(), score))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (members, weights.get(i).copied().unwrap_or(1.0))
+            })
+            .collect()
+    }
+
+    pub fn zscore(&self, key: &[u8], member: &[u8]) -> Option<f64> {
+        self.value::<SortedSet>(key)
+            .and_then(|zset| zset.score(member))
+    }
+
+    pub fn zrange(&self, key: &[u8], start: i64, stop: i64, rev: bool) -> Vec<(Vec<u8>, f64)> {
+        self.value::<SortedSet>(key)
+            .map(|zset| zset.range_by_rank(start, stop, rev))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_set_order() {
+        let mut zset = SortedSet::new();
+        zset.insert(b"b".to_vec(), 1.0);
+        zset.insert(b"a".to_vec(), 1.0);
+        zset.insert(b"c".to_vec(), -2.5);
+        assert_eq!(zset.insert(b"c".to_vec(), 3.0), Some(-2.5));
+
+        let members: Vec<&[u8]> = zset.iter().map(|(m, _)| m).collect();
+        assert_eq!(members, [b"a", b"b", b"c"]);
+        assert_eq!(
+            zset.range_by_rank(-2, -1, true),
+            vec![(b"b".to_vec(), 1.0), (b"a".to_vec(), 1.0)]
+        );
+        assert!(zset.range_by_rank(3, 10, false).is_empty());
+
+        assert_eq!(zset.remove(b"a"), Some(1.0));
+        assert_eq!(zset.len(), 2);
+    }
+
+    #[test]
+    fn test_zrem_and_zstore() {
+        let backend = Backend::new();
+        let members = vec![(1.0, b"a".to_vec()), (2.0, b"b".to_vec())];
+        backend.zadd("z".into(), members.clone(), ZAddCondition::Always, false);
+        assert_eq!(backend.zrem(b"z", &[b"a".to_vec(), b"c".to_vec()]), 1);
+        assert_eq!(
+            backend.zrange(b"z", 0, -1, false),
+            vec![(b"b".to_vec(), 2.0)]
+        );
+        assert_eq!(backend.zrem(b"z", &[b"b".to_vec()]), 1);
+        assert!(!backend.key_exists(b"z"));
+        assert_eq!(backend.zrem(b"z", &[b"b".to_vec()]), 0);
+
+        backend.set("d".into(), crate::BulkString::from("x").into());
+        assert_eq!(backend.zstore("d".into(), members), 2);
+        assert_eq!(backend.get(b"d"), None);
+        assert_eq!(backend.zscore(b"d", b"b"), Some(2.0));
+        assert_eq!(backend.zstore("d".into(), vec![]), 0);
+        assert!(!backend.key_exists(b"d"));
+    }
+
+    #[test]
+    fn test_zadd() {
+        let backend = Backend::new();
+        let members = |items: &[(f64, &str)]| {
+            items
+                .iter()
+                .map(|(s, m)| (*s, m.as_bytes().to_vec()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            backend.zadd(
+                "z".into(),
+                members(&[(1.0, "a"), (2.0, "b")]),
+                ZAddCondition::Always,
+                false
+            ),
+            2
+        );
+        assert_eq!(
+            backend.zadd(
+                "z".into(),
+                members(&[(5.0, "a"), (1.0, "c")]),
+                ZAddCondition::Xx,
+                true
+            ),
+            1
+        );
+        assert_eq!(backend.zscore(b"z", b"a"), Some(5.0));
+        assert_eq!(backend.zscore(b"z", b"c"), None);
+
+        assert_eq!(
+            backend.zadd(
+                "missing".into(),
+                members(&[(1.0, "a")]),
+                ZAddCondition::Xx,
+                false
+            ),
+            0
+        );
+        assert!(!backend.key_exists(b"missing"));
+    }
+
+    #[test]
+    fn test_zincrby_zcount_and_lex_ranges() {
+        let backend = Backend::new();
+        assert_eq!(backend.zincrby("z".into(), 2.5, b"a".to_vec()), Ok(2.5));
+        assert_eq!(backend.zincrby("z".into(), -1.0, b"a".to_vec()), Ok(1.5));
+        backend
+            .zincrby("z".into(), f64::INFINITY, b"b".to_vec())
+            .unwrap();
+        assert!(backend
+            .zincrby("z".into(), f64::NEG_INFINITY, b"b".to_vec())
+            .is_err());
+        assert_eq!(backend.zscore(b"z", b"b"), Some(f64::INFINITY));
+        assert_eq!(backend.zcard(b"z"), 2);
+        assert_eq!(backend.zcard(b"missing"), 0);
+
+        let bound = |score, exclusive| ScoreBound { score, exclusive };
+        let all = (bound(f64::NEG_INFINITY, false), bound(f64::INFINITY, false));
+        assert_eq!(backend.zcount(b"z", all.0, all.1), 2);
+        assert_eq!(backend.zcount(b"z", bound(1.5, true), all.1), 1);
+        assert_eq!(
+            backend.zcount(b"z", bound(1.5, false), bound(1.5, false)),
+            1
+        );
+        assert_eq!(backend.zcount(b"z", all.0, bound(f64::INFINITY, true)), 1);
+
+        let members = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|m| (0.0, m.as_bytes().to_vec()))
+            .collect();
+        backend.zstore("lex".into(), members);
+        let lex = |min: &LexBound, max: &LexBound, offset, count| {
+            backend.zrangebylex(b"lex", min, max, offset, count)
+        };
+        let (b, d) = (b"b".to_vec(), b"d".to_vec());
+        assert_eq!(
+            lex(
+                &LexBound::Exclusive(b.clone()),
+                &LexBound::Inclusive(d),
+                0,
+                None
+            ),
+            [b"c", b"d"]
+        );
+        assert_eq!(
+            lex(&LexBound::Min, &LexBound::Exclusive(b), 0, None),
+            [b"a"]
+        );
+        assert_eq!(
+            lex(&LexBound::Min, &LexBound::Max, 1, Some(2)),
+            [b"b", b"c"]
+        );
+        assert!(lex(&LexBound::Max, &LexBound::Min, 0, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_zpop_and_blocking_zpop() {
+        let backend = Backend::new();
+        let members = vec![
+            (1.0, b"a".to_vec()),
+            (2.0, b"b".to_vec()),
+            (3.0, b"c".to_vec()),
+        ];
+        backend.zstore("z".into(), members);
+        assert_eq!(backend.zpop(b"z", 1, true), vec![(b"c".to_vec(), 3.0)]);
+        assert_eq!(
+            backend.zpop(b"z", 5, false),
+            vec![(b"a".to_vec(), 1.0), (b"b".to_vec(), 2.0)]
+        );
+        assert!(!backend.key_exists(b"z"));
+        assert!(backend.zpop(b"z", 1, false).is_empty());
+
+        let keys = vec![Key::from("x"), Key::from("y")];
+        let timeout = Some(Duration::from_millis(10));
+        assert_eq!(backend.blocking_zpop(&keys, timeout, false).await, None);
+
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move { cloned.blocking_zpop(&keys, None, false).await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        backend.zincrby("y".into(), 4.0, b"d".to_vec()).unwrap();
+        assert_eq!(
+            handle.await.unwrap(),
+            Some(("y".into(), b"d".to_vec(), 4.0))
+        );
+        assert!(backend.key_waiters.is_empty());
+    }
+
+    #[test]
+    fn test_zunionstore_and_zinterstore() {
+        let backend = Backend::new();
+        let members = |items: &[(f64, &str)]| {
+            items
+                .iter()
+                .map(|(s, m)| (*s, m.as_bytes().to_vec()))
+                .collect::<Vec<_>>()
+        };
+        backend.zstore("a".into(), members(&[(1.0, "x"), (2.0, "y")]));
+        backend.zstore("b".into(), members(&[(10.0, "y"), (f64::INFINITY, "z")]));
+        let keys = [Key::from("a"), Key::from("b"), Key::from("missing")];
+
+        assert_eq!(
+            backend.zunionstore("u".into(), &keys[..2], &[2.0, 1.0], ZAggregate::Sum),
+            3
+        );
+        assert_eq!(
+            backend.zrange(b"u", 0, -1, false),
+            vec![
+                (b"x".to_vec(), 2.0),
+                (b"y".to_vec(), 14.0),
+                (b"z".to_vec(), f64::INFINITY)
+            ]
+        );
+        // 0 times inf is 0
+        backend.zunionstore("u".into(), &keys[1..2], &[0.0], ZAggregate::Sum);
+        assert_eq!(backend.zscore(b"u", b"z"), Some(0.0));
+
+        assert_eq!(
+            backend.zinterstore("i".into(), &keys[..2], &[], ZAggregate::Max),
+            1
+        );
+        assert_eq!(
+            backend.zrange(b"i", 0, -1, false),
+            vec![(b"y".to_vec(), 10.0)]
+        );
+        // the destination may be one of the sources
+        assert_eq!(
+            backend.zinterstore("a".into(), &keys[..2], &[], ZAggregate::Min),
+            1
+        );
+        assert_eq!(backend.zscore(b"a", b"y"), Some(2.0));
+        assert_eq!(
+            backend.zinterstore("i".into(), &keys, &[], ZAggregate::Sum),
+            0
+        );
+        assert!(!backend.key_exists(b"i"));
+    }
+}
@@ -0,0 +1,585 @@
+// A redis-benchmark style load generator, to measure the decoder and the dispatcher:
+//
+//   benchmark [-h <host>] [-p <port>] [-c <clients>] [-n <requests>] [-P <pipeline>]
+//             [-d <value size>] [-r <keyspace>] [-t <set,get,incr,hset,hget>]
+//             [--mix <set:1,get:9>] [--contention [--shards <n>] | --dispatch]
+//
+// Each test sends its requests over the clients' connections, pipeline requests at a time,
+// and reports the throughput and the latency percentiles of the replies. A request's
+// latency runs from writing its pipeline to decoding its reply. --mix runs one more test
+// sending the commands at random with the given weights.
+//
+// Keys are key:<n> with n drawn from the keyspace, or always key:0 without -r, and INCR
+// uses counter:<n>. HSET and HGET write and read field:<n> of the one hash called hash, so
+// every client contends on the same key, e.g. -c 64 -t hset,hget -r 100000.
+//
+// --contention runs the tests in this process instead of against a server, one thread per
+// client calling the backend directly with the access tracking the dispatcher adds, so
+// what is measured is the locking of the keyspace alone, e.g. --contention -c 64 -t
+// hset,hget -r 100000. --shards sets the number of shards of its keyspace.
+//
+// --dispatch runs the tests in this process too, through the dispatcher a connection uses,
+// once as it is and once without the GET/SET fast path, to show what the fast path saves,
+// e.g. --dispatch -n 200000 -r 1000 -t set,get. With -d 1048576 it shows GET replying with
+// large values without copying them.
+
+use anyhow::{anyhow, Result};
+use bytes::BytesMut;
+use simple_redis::{
+    network::{dispatch, dispatch_generic},
+    Backend, BulkString, Key, RespArray, RespEncode, RespFrame, RespFrameDecoder, Session,
+};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Barrier,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::mpsc,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Set,
+    Get,
+    Incr,
+    Hset,
+    Hget,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    addr: String,
+    clients: usize,
+    requests: u64,
+    pipeline: usize,
+    value_size: usize,
+    keyspace: u64,
+    tests: Vec<Op>,
+    // the ops of a mixed test and their weights
+    mix: Vec<(Op, u32)>,
+    // in this process, on threads, see run_contention
+    contention: bool,
+    shards: Option<usize>,
+    // in this process, through the dispatcher, see run_dispatch
+    dispatch: bool,
+}
+
+#[derive(Debug, Default)]
+struct Report {
+    latencies: Vec<Duration>,
+    errors: u64,
+}
+
+impl Op {
+    fn parse(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "set" => Ok(Op::Set),
+            "get" => Ok(Op::Get),
+            "incr" => Ok(Op::Incr),
+            "hset" => Ok(Op::Hset),
+            "hget" => Ok(Op::Hget),
+            _ => Err(anyhow!(
+                "unknown test '{}', expected set, get, incr, hset or hget",
+                name
+            )),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Set => "SET",
+            Op::Get => "GET",
+            Op::Incr => "INCR",
+            Op::Hset => "HSET",
+            Op::Hget => "HGET",
+        }
+    }
+
+    fn encode(&self, key: u64, value: &[u8], buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.frame(key, value).encode());
+    }
+
+    fn frame(&self, key: u64, value: &[u8]) -> RespFrame {
+        let args: Vec<Vec<u8>> = match self {
+            Op::Set => vec![
+                b"SET".to_vec(),
+                format!("key:{}", key).into_bytes(),
+                value.to_vec(),
+            ],
+            Op::Get => vec![b"GET".to_vec(), format!("key:{}", key).into_bytes()],
+            Op::Incr => vec![b"INCR".to_vec(), format!("counter:{}", key).into_bytes()],
+            Op::Hset => vec![
+                b"HSET".to_vec(),
+                b"hash".to_vec(),
+                format!("field:{}", key).into_bytes(),
+                value.to_vec(),
+            ],
+            Op::Hget => vec![
+                b"HGET".to_vec(),
+                b"hash".to_vec(),
+                format!("field:{}", key).into_bytes(),
+            ],
+        };
+        RespArray::new(
+            args.into_iter()
+                .map(|arg| BulkString::new(arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+
+    // the op on the backend itself, then the access tracking of its key like the
+    // dispatcher does. False for an error reply.
+    fn run(&self, backend: &Backend, key: u64, value: &[u8]) -> bool {
+        let (name, write) = match self {
+            Op::Set | Op::Get => (format!("key:{}", key), *self == Op::Set),
+            Op::Incr => (format!("counter:{}", key), true),
+            Op::Hset | Op::Hget => ("hash".to_string(), *self == Op::Hset),
+        };
+        let name = Key::from(name.as_str());
+        let field = || format!("field:{}", key).into_bytes();
+        let ok = match self {
+            Op::Set => {
+                backend.set(name.clone(), BulkString::from(value).into());
+                true
+            }
+            Op::Get => {
+                std::hint::black_box(backend.get(&name));
+                true
+            }
+            Op::Incr => backend.incr_by(name.clone(), 1).is_ok(),
+            Op::Hset => {
+                backend.hset(name.clone(), field(), BulkString::from(value).into());
+                true
+            }
+            Op::Hget => {
+                std::hint::black_box(backend.hget(&name, &field()));
+                true
+            }
+        };
+        backend.record_key_access(&[name], write);
+        ok
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options> {
+    let mut host = "127.0.0.1".to_string();
+    let mut port = "6379".to_string();
+    let mut options = Options {
+        addr: String::new(),
+        clients: 50,
+        requests: 100_000,
+        pipeline: 1,
+        value_size: 3,
+        keyspace: 0,
+        tests: vec![Op::Set, Op::Get, Op::Incr],
+        mix: Vec::new(),
+        contention: false,
+        shards: None,
+        dispatch: false,
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow!("missing value for {}", arg))
+        };
+        let number = |value: String| {
+            value
+                .parse::<u64>()
+                .map_err(|_| anyhow!("invalid number '{}'", value))
+        };
+        match arg.as_str() {
+            "-h" => host = value()?,
+            "-p" => port = value()?,
+            "-c" => options.clients = number(value()?)?.max(1) as usize,
+            "-n" => options.requests = number(value()?)?,
+            "-P" => options.pipeline = number(value()?)?.max(1) as usize,
+            "-d" => options.value_size = number(value()?)? as usize,
+            "-r" => options.keyspace = number(value()?)?,
+            "-t" => {
+                options.tests = value()?
+                    .split(',')
+                    .map(Op::parse)
+                    .collect::<Result<Vec<Op>>>()?
+            }
+            "--mix" => {
+                options.mix = value()?
+                    .split(',')
+                    .map(|item| {
+                        let (name, weight) = item.split_once(':').unwrap_or((item, "1"));
+                        let weight = weight
+                            .parse::<u32>()
+                            .map_err(|_| anyhow!("invalid weight in '{}'", item))?;
+                        Ok((Op::parse(name)?, weight))
+                    })
+                    .collect::<Result<Vec<(Op, u32)>>>()?;
+                if options.mix.iter().all(|(_, weight)| *weight == 0) {
+                    return Err(anyhow!("--mix needs a weight above 0"));
+                }
+            }
+            "--contention" => options.contention = true,
+            "--dispatch" => options.dispatch = true,
+            "--shards" => options.shards = Some(number(value()?)?.max(1) as usize),
+            _ => return Err(anyhow!("unexpected argument '{}'", arg)),
+        }
+    }
+    options.addr = format!("{}:{}", host, port);
+    Ok(options)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let options = Arc::new(parse_args(std::env::args().skip(1))?);
+    let mut tests: Vec<(String, Vec<(Op, u32)>)> = options
+        .tests
+        .iter()
+        .map(|op| (op.name().to_string(), vec![(*op, 1)]))
+        .collect();
+    if !options.mix.is_empty() {
+        let name = options
+            .mix
+            .iter()
+            .map(|(op, weight)| format!("{}:{}", op.name(), weight))
+            .collect::<Vec<String>>()
+            .join(",");
+        tests.push((format!("MIX {}", name), options.mix.clone()));
+    }
+    if options.dispatch {
+        let backend = Backend::new();
+        for (name, ops) in tests {
+            for (path, generic) in [("fast path", false), ("generic", true)] {
+                let start = Instant::now();
+                let report = run_dispatch(&backend, &options, &ops, generic).await;
+                print_report(
+                    &format!("{} ({})", name, path),
+                    &options,
+                    report,
+                    start.elapsed(),
+                );
+            }
+        }
+        return Ok(());
+    }
+    if options.contention {
+        let backend = options
+            .shards
+            .map_or_else(Backend::new, Backend::with_shards);
+        for (name, ops) in tests {
+            let start = Instant::now();
+            let report = run_contention(&backend, &options, &ops);
+            print_report(&name, &options, report, start.elapsed());
+        }
+        let shards = backend.shard_stats();
+        println!(
+            "{} keys in {} shards, {} in the fullest",
+            shards.keys, shards.shards, shards.max_keys
+        );
+        return Ok(());
+    }
+    for (name, ops) in tests {
+        let start = Instant::now();
+        let report = run_test(&options, Arc::new(ops)).await?;
+        print_report(&name, &options, report, start.elapsed());
+    }
+    Ok(())
+}
+
+// the requests of a test one after the other on a single session, the way a connection
+// dispatches them, or without the GET/SET fast path when generic
+async fn run_dispatch(
+    backend: &Backend,
+    options: &Options,
+    ops: &[(Op, u32)],
+    generic: bool,
+) -> Report {
+    let mut session = Session::new(String::new(), mpsc::unbounded_channel().0);
+    let value = vec![b'x'; options.value_size];
+    let mut rng = XorShift(0x9e3779b97f4a7c15);
+    let mut report = Report::default();
+    for _ in 0..options.requests {
+        let (op, key) = pick(ops, options.keyspace, &mut rng);
+        let frame = op.frame(key, &value);
+        let sent = Instant::now();
+        let reply = match generic {
+            true => dispatch_generic(frame, backend, &mut session).await,
+            false => dispatch(frame, backend, &mut session).await,
+        };
+        report.latencies.push(sent.elapsed());
+        if matches!(reply, RespFrame::Error(_)) {
+            report.errors += 1;
+        }
+    }
+    report
+}
+
+// the requests of a test split over one thread per client, all started at once
+fn run_contention(backend: &Backend, options: &Options, ops: &[(Op, u32)]) -> Report {
+    let clients = options.clients as u64;
+    let start = Barrier::new(options.clients);
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..clients)
+            .map(|seed| {
+                let requests =
+                    options.requests / clients + u64::from(seed < options.requests % clients);
+                let start = &start;
+                scope.spawn(move || {
+                    let value = vec![b'x'; options.value_size];
+                    let mut rng = XorShift((seed + 1).wrapping_mul(0x9e3779b97f4a7c15) | 1);
+                    let mut report = Report::default();
+                    start.wait();
+                    for _ in 0..requests {
+                        let (op, key) = pick(ops, options.keyspace, &mut rng);
+                        let sent = Instant::now();
+                        if !op.run(backend, key, &value) {
+                            report.errors += 1;
+                        }
+                        report.latencies.push(sent.elapsed());
+                    }
+                    report
+                })
+            })
+            .collect();
+        let mut report = Report::default();
+        for thread in threads {
+            let thread = thread.join().expect("benchmark thread panicked");
+            report.latencies.extend(thread.latencies);
+            report.errors += thread.errors;
+        }
+        report
+    })
+}
+
+async fn run_test(options: &Arc<Options>, ops: Arc<Vec<(Op, u32)>>) -> Result<Report> {
+    let remaining = Arc::new(AtomicU64::new(options.requests));
+    let mut clients = Vec::with_capacity(options.clients);
+    for seed in 0..options.clients {
+        let stream = TcpStream::connect(&options.addr)
+            .await
+            .map_err(|e| anyhow!("Could not connect to {}: {}", options.addr, e))?;
+        stream.set_nodelay(true)?;
+        clients.push(tokio::spawn(run_client(
+            stream,
+            options.clone(),
+            ops.clone(),
+            remaining.clone(),
+            seed as u64 + 1,
+        )));
+    }
+    let mut report = Report::default();
+    for client in clients {
+        let client = client.await??;
+        report.latencies.extend(client.latencies);
+        report.errors += client.errors;
+    }
+    Ok(report)
+}
+
+// send pipelines until the requests of the test are used up
+async fn run_client(
+    mut stream: TcpStream,
+    options: Arc<Options>,
+    ops: Arc<Vec<(Op, u32)>>,
+    remaining: Arc<AtomicU64>,
+    seed: u64,
+) -> Result<Report> {
+    let value = vec![b'x'; options.value_size];
+    let mut rng = XorShift(seed.wrapping_mul(0x9e3779b97f4a7c15) | 1);
+    let mut decoder = RespFrameDecoder::new();
+    let mut read_buf = BytesMut::with_capacity(64 * 1024);
+    let mut write_buf = Vec::new();
+    let mut report = Report::default();
+    loop {
+        let count = take(&remaining, options.pipeline as u64);
+        if count == 0 {
+            return Ok(report);
+        }
+        write_buf.clear();
+        for _ in 0..count {
+            let (op, key) = pick(&ops, options.keyspace, &mut rng);
+            op.encode(key, &value, &mut write_buf);
+        }
+        let sent = Instant::now();
+        stream.write_all(&write_buf).await?;
+        let mut received = 0;
+        while received < count {
+            while let Some(frame) = decoder.decode(&mut read_buf)? {
+                report.latencies.push(sent.elapsed());
+                if matches!(frame, RespFrame::Error(_)) {
+                    report.errors += 1;
+                }
+                received += 1;
+            }
+            if received < count && stream.read_buf(&mut read_buf).await? == 0 {
+                return Err(anyhow!("Server closed the connection"));
+            }
+        }
+    }
+}
+
+// the op of the next request, at random with the weights of the ops, and its key
+fn pick(ops: &[(Op, u32)], keyspace: u64, rng: &mut XorShift) -> (Op, u64) {
+    let key = match keyspace {
+        0 => 0,
+        keyspace => rng.next() % keyspace,
+    };
+    let total_weight: u64 = ops.iter().map(|(_, weight)| *weight as u64).sum();
+    let mut pick = rng.next() % total_weight;
+    let op = ops
+        .iter()
+        .find(|(_, weight)| match pick.checked_sub(*weight as u64) {
+            Some(rest) => {
+                pick = rest;
+                false
+            }
+            None => true,
+        })
+        .map(|(op, _)| *op)
+        .unwrap_or(Op::Get);
+    (op, key)
+}
+
+// take up to n of the remaining requests
+fn take(remaining: &AtomicU64, n: u64) -> u64 {
+    let mut taken = 0;
+    let _ = remaining.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+        taken = left.min(n);
+        Some(left - taken)
+    });
+    taken
+}
+
+fn print_report(name: &str, options: &Options, mut report: Report, elapsed: Duration) {
+    report.latencies.sort_unstable();
+    let requests = report.latencies.len();
+    println!("====== {} ======", name);
+    println!(
+        "  {} requests completed in {:.2} seconds",
+        requests,
+        elapsed.as_secs_f64()
+    );
+    println!(
+        "  {} parallel clients, {} bytes payload, pipeline {}",
+        options.clients, options.value_size, options.pipeline
+    );
+    if report.errors > 0 {
+        println!("  {} replies were errors", report.errors);
+    }
+    println!(
+        "  throughput: {:.2} requests per second",
+        requests as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    let percentiles = [50.0, 95.0, 99.0, 99.9, 100.0]
+        .iter()
+        .map(|p| format!("p{}={:.3}", p, millis(percentile(&report.latencies, *p))))
+        .collect::<Vec<String>>();
+    println!("  latency (msec): {}", percentiles.join(" "));
+    println!();
+}
+
+// the latency p percent of the requests were faster than, of sorted latencies
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+// good enough to spread keys and ops, without a dependency for it
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() -> Result<()> {
+        let options = parse_args(args(
+            "-p 6380 -c 4 -n 1000 -P 16 -t set,get --mix set:1,get:9",
+        ))?;
+        assert_eq!(options.addr, "127.0.0.1:6380");
+        assert_eq!(options.clients, 4);
+        assert_eq!(options.requests, 1000);
+        assert_eq!(options.pipeline, 16);
+        assert_eq!(options.tests, vec![Op::Set, Op::Get]);
+        assert_eq!(options.mix, vec![(Op::Set, 1), (Op::Get, 9)]);
+        assert_eq!(parse_args(args("-t hset"))?.tests, vec![Op::Hset]);
+        let options = parse_args(args("--contention -c 64 -t hset,hget --shards 128"))?;
+        assert!(options.contention);
+        assert_eq!(options.clients, 64);
+        assert_eq!(options.tests, vec![Op::Hset, Op::Hget]);
+        assert_eq!(options.shards, Some(128));
+        assert!(parse_args(args("-t del")).is_err());
+        assert!(parse_args(args("-n")).is_err());
+        assert!(parse_args(args("--mix set:0")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_percentile() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&latencies, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_run_contention() -> Result<()> {
+        let options = parse_args(args("--contention -c 8 -n 1001 -r 100 --shards 4"))?;
+        let backend = Backend::with_shards(4);
+        let ops = [(Op::Hset, 1), (Op::Hget, 1)];
+        let report = run_contention(&backend, &options, &ops);
+        assert_eq!(report.latencies.len(), 1001);
+        assert_eq!(report.errors, 0);
+        assert_eq!(backend.shard_stats().keys, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_dispatch() -> Result<()> {
+        let options = parse_args(args("--dispatch -n 100 -r 10"))?;
+        assert!(options.dispatch);
+        let backend = Backend::new();
+        let ops = [(Op::Set, 1), (Op::Get, 1)];
+        for generic in [false, true] {
+            let report = run_dispatch(&backend, &options, &ops, generic).await;
+            assert_eq!(report.latencies.len(), 100);
+            assert_eq!(report.errors, 0);
+        }
+        let commands = backend.stats().commands;
+        assert_eq!(commands["set"].calls + commands["get"].calls, 200);
+        Ok(())
+    }
+
+    #[test]
+    fn test_take() {
+        let remaining = AtomicU64::new(5);
+        assert_eq!(take(&remaining, 3), 3);
+        assert_eq!(take(&remaining, 3), 2);
+        assert_eq!(take(&remaining, 3), 0);
+    }
+}
@@ -0,0 +1,341 @@
+// The prompt's line editor, for when stdin is a terminal. It puts the terminal in raw mode
+// and handles the keys itself:
+//
+//   Left/Right, Ctrl-B/F     move a character         Home/End, Ctrl-A/E  to the ends
+//   Backspace, Ctrl-H        delete before the cursor Delete, Ctrl-D      at the cursor
+//   Ctrl-U / Ctrl-K          kill to the start / end  Ctrl-W              the word before
+//   Up/Down, Ctrl-P/N        walk the history         Ctrl-L              clear the screen
+//
+// Ctrl-C, and Ctrl-D on an empty line, end the input like redis-cli. The history is kept in
+// a file so it survives the session.
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::PathBuf,
+};
+
+const HISTORY_LEN: usize = 1000;
+
+pub struct Editor {
+    history: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl Editor {
+    // the history is loaded from the file if there is one, and saved back as lines are added
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let history = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|s| s.lines().map(String::from).collect::<Vec<String>>())
+            .unwrap_or_default();
+        let skip = history.len().saturating_sub(HISTORY_LEN);
+        Self {
+            history: history.into_iter().skip(skip).collect(),
+            path,
+        }
+    }
+
+    // read a line from the terminal, None when the input ends. This blocks, it belongs on a
+    // thread of its own.
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let _raw = RawMode::enable()?;
+        let line = self.edit(&mut io::stdin().lock(), &mut io::stdout().lock(), prompt);
+        // the newline that ends the line isn't echoed in raw mode
+        println!();
+        line
+    }
+
+    // a line is remembered unless it's empty or repeats the one before
+    pub fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if self.history.len() > HISTORY_LEN {
+            self.history.remove(0);
+        }
+        if let Some(path) = &self.path {
+            let mut content = self.history.join("\n");
+            content.push('\n');
+            // losing the history isn't worth failing the session over
+            let _ = fs::write(path, content);
+        }
+    }
+
+    fn edit(
+        &self,
+        input: &mut impl Read,
+        output: &mut impl Write,
+        prompt: &str,
+    ) -> io::Result<Option<String>> {
+        let mut line = Line::default();
+        // the history entry shown, the line being typed is saved while walking it
+        let mut index = self.history.len();
+        let mut draft = Vec::new();
+        refresh(output, prompt, &line)?;
+        while let Some(key) = read_key(input)? {
+            match key {
+                Key::Enter => return Ok(Some(line.chars.into_iter().collect())),
+                Key::Interrupt => return Ok(None),
+                Key::Ctrl(b'd') if line.chars.is_empty() => return Ok(None),
+                Key::Char(c) => {
+                    line.chars.insert(line.pos, c);
+                    line.pos += 1;
+                }
+                Key::Left | Key::Ctrl(b'b') => line.pos = line.pos.saturating_sub(1),
+                Key::Right | Key::Ctrl(b'f') => line.pos = (line.pos + 1).min(line.chars.len()),
+                Key::Home | Key::Ctrl(b'a') => line.pos = 0,
+                Key::End | Key::Ctrl(b'e') => line.pos = line.chars.len(),
+                Key::Backspace if line.pos > 0 => {
+                    line.pos -= 1;
+                    line.chars.remove(line.pos);
+                }
+                Key::Delete | Key::Ctrl(b'd') if line.pos < line.chars.len() => {
+                    line.chars.remove(line.pos);
+                }
+                Key::Ctrl(b'u') => {
+                    line.chars.drain(..line.pos);
+                    line.pos = 0;
+                }
+                Key::Ctrl(b'k') => line.chars.truncate(line.pos),
+                Key::Ctrl(b'w') => {
+                    let mut start = line.pos;
+                    while start > 0 && line.chars[start - 1] == ' ' {
+                        start -= 1;
+                    }
+                    while start > 0 && line.chars[start - 1] != ' ' {
+                        start -= 1;
+                    }
+                    line.chars.drain(start..line.pos);
+                    line.pos = start;
+                }
+                Key::Up | Key::Ctrl(b'p') if index > 0 => {
+                    if index == self.history.len() {
+                        draft = std::mem::take(&mut line.chars);
+                    }
+                    index -= 1;
+                    line.chars = self.history[index].chars().collect();
+                    line.pos = line.chars.len();
+                }
+                Key::Down | Key::Ctrl(b'n') if index < self.history.len() => {
+                    index += 1;
+                    line.chars = match self.history.get(index) {
+                        Some(entry) => entry.chars().collect(),
+                        None => std::mem::take(&mut draft),
+                    };
+                    line.pos = line.chars.len();
+                }
+                Key::Ctrl(b'l') => output.write_all(b"\x1b[H\x1b[2J")?,
+                _ => continue,
+            }
+            refresh(output, prompt, &line)?;
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Default)]
+struct Line {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+// redraw the line and put the cursor back where it was
+fn refresh(output: &mut impl Write, prompt: &str, line: &Line) -> io::Result<()> {
+    let text: String = line.chars.iter().collect();
+    let column = prompt.chars().count() + line.pos;
+    write!(output, "\r{}{}\x1b[0K\r", prompt, text)?;
+    if column > 0 {
+        write!(output, "\x1b[{}C", column)?;
+    }
+    output.flush()
+}
+
+#[derive(Debug, PartialEq)]
+enum Key {
+    Char(char),
+    // a control key other than the ones with a name, by its letter
+    Ctrl(u8),
+    Enter,
+    Interrupt,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Unknown,
+}
+
+fn read_key(input: &mut impl Read) -> io::Result<Option<Key>> {
+    let Some(b) = read_byte(input)? else {
+        return Ok(None);
+    };
+    let key = match b {
+        b'\r' | b'\n' => Key::Enter,
+        3 => Key::Interrupt,
+        8 | 127 => Key::Backspace,
+        27 => read_escape(input)?,
+        1..=26 => Key::Ctrl(b'a' + b - 1),
+        0 | 28..=31 => Key::Unknown,
+        32..=126 => Key::Char(b as char),
+        _ => {
+            // the rest of a UTF-8 character, its first byte tells how many follow
+            let len = match b {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                _ => 4,
+            };
+            let mut buf = vec![b];
+            for _ in 1..len {
+                match read_byte(input)? {
+                    Some(b) => buf.push(b),
+                    None => break,
+                }
+            }
+            match std::str::from_utf8(&buf)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(c) => Key::Char(c),
+                None => Key::Unknown,
+            }
+        }
+    };
+    Ok(Some(key))
+}
+
+// the keys that send "ESC [" or "ESC O" and a code
+fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+    let Some(b'[' | b'O') = read_byte(input)? else {
+        return Ok(Key::Unknown);
+    };
+    let key = match read_byte(input)? {
+        Some(b'A') => Key::Up,
+        Some(b'B') => Key::Down,
+        Some(b'C') => Key::Right,
+        Some(b'D') => Key::Left,
+        Some(b'H') => Key::Home,
+        Some(b'F') => Key::End,
+        Some(digit @ b'0'..=b'9') => {
+            let mut code = vec![digit];
+            loop {
+                match read_byte(input)? {
+                    Some(b'~') | None => break,
+                    Some(b) => code.push(b),
+                }
+            }
+            match code.as_slice() {
+                b"1" | b"7" => Key::Home,
+                b"4" | b"8" => Key::End,
+                b"3" => Key::Delete,
+                _ => Key::Unknown,
+            }
+        }
+        _ => Key::Unknown,
+    };
+    Ok(key)
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut buf = [0];
+    loop {
+        return match input.read(&mut buf) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(buf[0])),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => Err(e),
+        };
+    }
+}
+
+// the terminal in raw mode: keys come as they're pressed, not echoed, and Ctrl-C is a key
+// rather than a signal. The settings it had are restored on drop.
+struct RawMode(libc::termios);
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        // SAFETY: termios is plain data, tcgetattr fills it in
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let original = termios;
+        termios.c_iflag &= !(libc::ICRNL | libc::IXON | libc::BRKINT | libc::ISTRIP);
+        termios.c_lflag &= !(libc::ECHO | libc::ICANON | libc::ISIG | libc::IEXTEN);
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self(original))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &self.0) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(editor: &Editor, keys: &[u8]) -> Option<String> {
+        editor.edit(&mut &keys[..], &mut Vec::new(), "> ").unwrap()
+    }
+
+    #[test]
+    fn test_edit_line() {
+        let editor = Editor::new(None);
+        assert_eq!(edit(&editor, b"get a\r").as_deref(), Some("get a"));
+        // left twice, insert, end, backspace
+        assert_eq!(
+            edit(&editor, b"gt ab\x1b[D\x1b[D\x1b[D\x1b[De\x05\x7f\r").as_deref(),
+            Some("get a")
+        );
+        // home, delete, Ctrl-W and Ctrl-U
+        assert_eq!(edit(&editor, b"xset\x01\x1b[3~\r").as_deref(), Some("set"));
+        assert_eq!(edit(&editor, b"set a b\x17c\r").as_deref(), Some("set a c"));
+        assert_eq!(edit(&editor, b"set\x15get\r").as_deref(), Some("get"));
+        assert_eq!(
+            edit(&editor, "set é\r".as_bytes()).as_deref(),
+            Some("set é")
+        );
+        // Ctrl-C, Ctrl-D on an empty line and the end of the input end it
+        assert_eq!(edit(&editor, b"get\x03"), None);
+        assert_eq!(edit(&editor, b"\x04"), None);
+        assert_eq!(edit(&editor, b"get"), None);
+    }
+
+    #[test]
+    fn test_history() {
+        let path = std::env::temp_dir().join(format!("cli-history-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut editor = Editor::new(Some(path.clone()));
+        editor.add_history("get a");
+        editor.add_history("get a");
+        editor.add_history(" ");
+        editor.add_history("set a 1");
+        assert_eq!(editor.history, vec!["get a", "set a 1"]);
+
+        // up walks back, down returns to the line being typed
+        assert_eq!(edit(&editor, b"\x1b[A\r").as_deref(), Some("set a 1"));
+        assert_eq!(
+            edit(&editor, b"\x1b[A\x1b[A\x1b[A\r").as_deref(),
+            Some("get a")
+        );
+        assert_eq!(edit(&editor, b"del\x10\x0e\x0e\r").as_deref(), Some("del"));
+        assert_eq!(edit(&editor, b"\x1b[A x\r").as_deref(), Some("set a 1 x"));
+
+        // it's kept for the next session
+        let editor = Editor::new(Some(path.clone()));
+        assert_eq!(editor.history, vec!["get a", "set a 1"]);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,328 @@
+// A small redis-cli to talk to this server, or any other, without installing redis-tools:
+//
+//   cli [-h <host>] [-p <port>] [command [arg ...]]
+//
+// Without a command it reads commands line by line and prints the replies the way
+// redis-cli does. Arguments are split on spaces, "double" and 'single' quotes keep them
+// together, with \" \\ \n \r \t and \xhh escapes inside double quotes.
+//
+// At a terminal the prompt has line editing and a history kept in ~/.simple_redis_cli_history,
+// see editor.rs. Piped input is read line by line without a prompt.
+
+mod editor;
+
+use anyhow::{anyhow, Result};
+use editor::Editor;
+use simple_redis::{client::Client, RespFrame};
+use std::{io::IsTerminal, path::PathBuf};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut host = "127.0.0.1".to_string();
+    let mut port = "6379".to_string();
+    let mut command = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" if command.is_empty() => {
+                host = args.next().ok_or_else(|| anyhow!("-h needs a host"))?
+            }
+            "-p" if command.is_empty() => {
+                port = args.next().ok_or_else(|| anyhow!("-p needs a port"))?
+            }
+            _ => command.push(arg.into_bytes()),
+        }
+    }
+    let addr = format!("{}:{}", host, port);
+    let mut conn = Client::connect(&addr)
+        .await
+        .map_err(|e| anyhow!("Could not connect to {}: {}", addr, e))?;
+
+    if !command.is_empty() {
+        return run(&mut conn, command).await;
+    }
+    if !std::io::stdin().is_terminal() {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Some(line) = lines.next_line().await? {
+            if !run_line(&mut conn, &line).await? {
+                break;
+            }
+        }
+        return Ok(());
+    }
+    let history =
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".simple_redis_cli_history"));
+    let mut editor = Editor::new(history);
+    loop {
+        let prompt = format!("{}> ", addr);
+        // the editor blocks reading the terminal, keep it off the runtime's threads
+        let (returned, line) = tokio::task::spawn_blocking(move || {
+            let line = editor.read_line(&prompt);
+            (editor, line)
+        })
+        .await?;
+        editor = returned;
+        let Some(line) = line? else {
+            return Ok(());
+        };
+        editor.add_history(&line);
+        if !run_line(&mut conn, &line).await? {
+            return Ok(());
+        }
+    }
+}
+
+// run the command on a line, false once it asks to quit
+async fn run_line(conn: &mut Client, line: &str) -> Result<bool> {
+    let args = match split_args(line) {
+        Ok(args) => args,
+        Err(e) => {
+            println!("Invalid argument(s): {}", e);
+            return Ok(true);
+        }
+    };
+    match args.first().map(|arg| arg.to_ascii_lowercase()) {
+        None => Ok(true),
+        Some(name) if name == b"quit" || name == b"exit" => Ok(false),
+        Some(_) => {
+            run(conn, args).await?;
+            Ok(true)
+        }
+    }
+}
+
+// send the command and print the reply. Subscribers keep printing the messages they get
+// until the connection closes.
+async fn run(conn: &mut Client, args: Vec<Vec<u8>>) -> Result<()> {
+    let name = args[0].to_ascii_lowercase();
+    let subscribe = matches!(
+        name.as_slice(),
+        b"subscribe" | b"psubscribe" | b"ssubscribe"
+    );
+    conn.send(&args).await?;
+    // the server closes the connection without a reply when it shuts down
+    if name == b"shutdown" {
+        if let Ok(frame) = conn.read_frame().await {
+            println!("{}", format_frame(&frame));
+        }
+        return Ok(());
+    }
+    if subscribe {
+        println!("Reading messages... (press Ctrl-C to quit)");
+        loop {
+            println!("{}", format_frame(&conn.read_frame().await?));
+        }
+    }
+    println!("{}", format_frame(&conn.read_frame().await?));
+    Ok(())
+}
+
+// split a line into arguments like redis-cli does
+fn split_args(line: &str) -> Result<Vec<Vec<u8>>, String> {
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            return Ok(args);
+        };
+        let mut arg = Vec::new();
+        match first {
+            '"' => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => arg.push(b'\n'),
+                            Some('r') => arg.push(b'\r'),
+                            Some('t') => arg.push(b'\t'),
+                            Some('x') => {
+                                let hex: String = chars.by_ref().take(2).collect();
+                                let byte = u8::from_str_radix(&hex, 16)
+                                    .map_err(|_| format!("invalid escape \\x{}", hex))?;
+                                arg.push(byte);
+                            }
+                            Some(c) => push_char(&mut arg, c),
+                            None => return Err("unbalanced quotes".to_string()),
+                        },
+                        Some(c) => push_char(&mut arg, c),
+                        None => return Err("unbalanced quotes".to_string()),
+                    }
+                }
+            }
+            '\'' => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            arg.push(b'\'');
+                        }
+                        Some(c) => push_char(&mut arg, c),
+                        None => return Err("unbalanced quotes".to_string()),
+                    }
+                }
+            }
+            _ => {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    push_char(&mut arg, c);
+                }
+                args.push(arg);
+                continue;
+            }
+        }
+        // a closing quote must end the argument
+        if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+            return Err("closing quote must be followed by a space".to_string());
+        }
+        args.push(arg);
+    }
+}
+
+fn push_char(arg: &mut Vec<u8>, c: char) {
+    let mut buf = [0; 4];
+    arg.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+}
+
+// the reply as redis-cli prints it. A null bulk string and an empty one are the same
+// frame, both print as (nil).
+fn format_frame(frame: &RespFrame) -> String {
+    match frame {
+        RespFrame::SimpleString(s) => s.to_string(),
+        RespFrame::Error(e) => format!("(error) {}", e.as_str()),
+        RespFrame::Integer(i) => format!("(integer) {}", i),
+        RespFrame::BulkString(s) if s.is_empty() => "(nil)".to_string(),
+        RespFrame::BulkString(s) => quote(s),
+        RespFrame::Null(_) | RespFrame::NullArray(_) => "(nil)".to_string(),
+        RespFrame::Boolean(b) => format!("({})", b),
+        RespFrame::Double(d) => format!("(double) {}", **d),
+        RespFrame::Array(array) => format_elements(array.iter(), ")", "(empty array)"),
+        RespFrame::Set(set) => format_elements(set.iter(), "~", "(empty set)"),
+        RespFrame::Push(push) => format_elements(push.iter(), ")", "(empty array)"),
+        RespFrame::Map(map) => {
+            if map.is_empty() {
+                return "(empty hash)".to_string();
+            }
+            let width = map.len().to_string().len();
+            let mut out = Vec::new();
+            for (i, (key, value)) in map.iter().enumerate() {
+                // string keys are quoted like bulk strings
+                let key = match key {
+                    RespFrame::SimpleString(s) => quote(s.as_bytes()),
+                    key => format_frame(key),
+                };
+                let prefix = format!("{:>width$}# {} => ", i + 1, key);
+                out.push(indent(&prefix, &format_frame(value)));
+            }
+            out.join("\n")
+        }
+    }
+}
+
+fn format_elements<'a>(
+    elements: impl ExactSizeIterator<Item = &'a RespFrame>,
+    marker: &str,
+    empty: &str,
+) -> String {
+    if elements.len() == 0 {
+        return empty.to_string();
+    }
+    let width = elements.len().to_string().len();
+    elements
+        .enumerate()
+        .map(|(i, frame)| {
+            let prefix = format!("{:>width$}{} ", i + 1, marker);
+            indent(&prefix, &format_frame(frame))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// prefix the first line, align the others under it
+fn indent(prefix: &str, s: &str) -> String {
+    let pad = " ".repeat(prefix.chars().count());
+    s.lines()
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => format!("{}{}", prefix, line),
+            _ => format!("{}{}", pad, line),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn quote(s: &[u8]) -> String {
+    let mut out = String::from("\"");
+    for &b in s {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            0x20..=0x7e => out.push(b as char),
+            _ => out.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_redis::{BulkString, RespArray, RespMap, SimpleError, SimpleString};
+
+    #[test]
+    fn test_split_args() {
+        let args = |line| {
+            split_args(line).map(|args| {
+                args.into_iter()
+                    .map(|arg| String::from_utf8(arg).unwrap())
+                    .collect::<Vec<String>>()
+            })
+        };
+        assert_eq!(args("set  a 1").unwrap(), vec!["set", "a", "1"]);
+        assert_eq!(
+            args(r#"set "a b" 'c d' "\x41\n""#).unwrap(),
+            vec!["set", "a b", "c d", "A\n"]
+        );
+        assert_eq!(args("").unwrap(), Vec::<String>::new());
+        assert!(args(r#"set "a"#).is_err());
+        assert!(args(r#"set "a"b"#).is_err());
+    }
+
+    #[test]
+    fn test_format_frame() {
+        assert_eq!(format_frame(&SimpleString::new("OK").into()), "OK");
+        assert_eq!(format_frame(&RespFrame::Integer(3)), "(integer) 3");
+        assert_eq!(
+            format_frame(&SimpleError::new("ERR nope").into()),
+            "(error) ERR nope"
+        );
+        assert_eq!(format_frame(&BulkString::null().into()), "(nil)");
+        let nested: RespFrame = RespArray::new(vec![
+            BulkString::from("a").into(),
+            RespArray::new(vec![BulkString::from("b\n").into(), RespFrame::Integer(1)]).into(),
+        ])
+        .into();
+        assert_eq!(
+            format_frame(&nested),
+            "1) \"a\"\n2) 1) \"b\\n\"\n   2) (integer) 1"
+        );
+        let mut map = RespMap::new();
+        map.insert("k", BulkString::from("v").into());
+        map.insert(RespFrame::Integer(2), BulkString::from("w").into());
+        assert_eq!(
+            format_frame(&map.into()),
+            "1# \"k\" => \"v\"\n2# (integer) 2 => \"w\""
+        );
+        assert_eq!(
+            format_frame(&RespArray::new(Vec::new()).into()),
+            "(empty array)"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+// Command line of the server:
+//
+//   simple-redis [--<config parameter> <value>]... [--shards <n>]
+//                [--check-config | --test-memory <megabytes>]
+//
+// config parameters are applied like CONFIG SET before anything else. --shards sets the
+// number of shards of the keyspace, which can't change once the server runs. --check-config
+// validates them and checks that the snapshot can be written, --test-memory allocates,
+// fills and verifies the given amount of memory. Both exit with a non-zero status on
+// failure so deployment pipelines can preflight a node before routing traffic to it.
+
+use crate::Backend;
+use std::{fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Serve,
+    CheckConfig,
+    TestMemory(usize),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Options {
+    pub mode: Mode,
+    pub config: Vec<(String, String)>,
+    pub shards: Option<usize>,
+}
+
+const MEGABYTE: usize = 1024 * 1024;
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut mode = Mode::Serve;
+    let mut config = Vec::new();
+    let mut shards = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let Some(name) = arg.strip_prefix("--") else {
+            return Err(format!("unexpected argument '{}'", arg));
+        };
+        match name {
+            "check-config" => mode = Mode::CheckConfig,
+            "test-memory" => {
+                let megabytes = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or("--test-memory needs the number of megabytes to test")?;
+                mode = Mode::TestMemory(megabytes);
+            }
+            "shards" => {
+                let n = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or("--shards needs a positive number of shards")?;
+                shards = Some(n);
+            }
+            _ => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("missing value for --{}", name))?;
+                config.push((name.to_string(), value));
+            }
+        }
+    }
+    Ok(Options {
+        mode,
+        config,
+        shards,
+    })
+}
+
+// apply the config parameters of the command line, stops at the first invalid one
+pub fn apply_config(backend: &Backend, config: &[(String, String)]) -> Result<(), String> {
+    for (name, value) in config {
+        backend
+            .config_set(name, value)
+            .map_err(|e| format!("--{} {}: {}", name, value, e))?;
+    }
+    Ok(())
+}
+
+// the configuration is already applied, what is left is checking that the snapshot
+// can be written where dbfilename points
+pub fn check_config(backend: &Backend) -> Result<(), String> {
+    let dbfilename = backend.dbfilename();
+    let dir = match Path::new(&dbfilename).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => Path::new(".").to_path_buf(),
+    };
+    let probe = dir.join(format!(".simple-redis-check-{}", std::process::id()));
+    fs::write(&probe, b"check")
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| format!("can't write the snapshot to {}: {}", dir.display(), e))
+}
+
+// allocate the memory one megabyte at a time, fill it with patterns and read them back
+pub fn test_memory(megabytes: usize) -> Result<(), String> {
+    let mut chunks: Vec<Vec<u8>> = Vec::new();
+    for pattern in [0x55u8, 0xaa, 0x00, 0xff] {
+        for i in 0..megabytes {
+            if chunks.len() <= i {
+                let mut chunk = Vec::new();
+                chunk
+                    .try_reserve_exact(MEGABYTE)
+                    .map_err(|_| format!("allocation failed after {} MB", i))?;
+                chunk.resize(MEGABYTE, 0);
+                chunks.push(chunk);
+            }
+            chunks[i].fill(pattern);
+        }
+        for (i, chunk) in chunks.iter().enumerate() {
+            if let Some(offset) = chunk.iter().position(|&b| b != pattern) {
+                return Err(format!(
+                    "memory error at megabyte {} offset {}: expected {:#04x}",
+                    i, offset, pattern
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse_args(args(&["--dbfilename", "a.srdb", "--check-config"])),
+            Ok(Options {
+                mode: Mode::CheckConfig,
+                config: vec![("dbfilename".to_string(), "a.srdb".to_string())],
+                shards: None,
+            })
+        );
+        assert_eq!(
+            parse_args(args(&["--shards", "64"])).map(|o| o.shards),
+            Ok(Some(64))
+        );
+        assert!(parse_args(args(&["--shards", "0"])).is_err());
+        assert_eq!(
+            parse_args(args(&["--test-memory", "2"])).map(|o| o.mode),
+            Ok(Mode::TestMemory(2))
+        );
+        assert!(parse_args(args(&["--test-memory"])).is_err());
+        assert!(parse_args(args(&["--dbfilename"])).is_err());
+        assert!(parse_args(args(&["dbfilename"])).is_err());
+    }
+
+    #[test]
+    fn test_check_config() {
+        let backend = Backend::new();
+        let config = vec![("slowlog-max-len".to_string(), "x".to_string())];
+        assert!(apply_config(&backend, &config).is_err());
+
+        let dir = std::env::temp_dir();
+        backend.set_dbfilename(dir.join("dump.srdb").to_string_lossy().into_owned());
+        assert_eq!(check_config(&backend), Ok(()));
+
+        backend.set_dbfilename(
+            dir.join("missing/dir/dump.srdb")
+                .to_string_lossy()
+                .into_owned(),
+        );
+        assert!(check_config(&backend).is_err());
+
+        assert_eq!(test_memory(2), Ok(()));
+    }
+}
@@ -0,0 +1,116 @@
+// A minimal client connection, for the cli and for applications embedding this crate.
+//
+// write_with_replicas is the strongly consistent write path: the write and a WAIT for
+// the wanted number of replicas are sent together, and it only succeeds once enough
+// replicas acknowledged the write. Replicas that acknowledged it serve it to reads, on a
+// replica that didn't the write may not be there yet.
+
+use crate::{BulkString, RespArray, RespEncode, RespError, RespFrame, RespFrameDecoder};
+use bytes::BytesMut;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Protocol(#[from] RespError),
+    #[error("Server closed the connection")]
+    Closed,
+    #[error("{0}")]
+    Server(String),
+    // the write was done on the master, but not on enough replicas in time
+    #[error("write acknowledged by {acked} of {required} replicas")]
+    NotReplicated { acked: usize, required: usize },
+}
+
+pub struct Client {
+    stream: TcpStream,
+    buf: BytesMut,
+    decoder: RespFrameDecoder,
+}
+
+impl Client {
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self, ClientError> {
+        Ok(Client {
+            stream: TcpStream::connect(addr).await?,
+            buf: BytesMut::with_capacity(4096),
+            decoder: RespFrameDecoder::new(),
+        })
+    }
+
+    pub async fn send<A: AsRef<[u8]>>(&mut self, args: &[A]) -> Result<(), ClientError> {
+        self.stream.write_all(&encode_command(args)).await?;
+        Ok(())
+    }
+
+    pub async fn read_frame(&mut self) -> Result<RespFrame, ClientError> {
+        loop {
+            if let Some(frame) = self.decoder.decode(&mut self.buf)? {
+                return Ok(frame);
+            }
+            if self.stream.read_buf(&mut self.buf).await? == 0 {
+                return Err(ClientError::Closed);
+            }
+        }
+    }
+
+    // send a command and read its reply, an error reply is returned as a frame
+    pub async fn command<A: AsRef<[u8]>>(&mut self, args: &[A]) -> Result<RespFrame, ClientError> {
+        self.send(args).await?;
+        self.read_frame().await
+    }
+
+    // run a write and wait until at least replicas replicas have it, or the timeout (None
+    // waits forever) is over. Fails with NotReplicated when too few did, the write is done
+    // on the master nevertheless, like with WAIT.
+    pub async fn write_with_replicas<A: AsRef<[u8]>>(
+        &mut self,
+        args: &[A],
+        replicas: usize,
+        timeout: Option<Duration>,
+    ) -> Result<RespFrame, ClientError> {
+        let millis = timeout.map_or(0, |timeout| timeout.as_millis().max(1));
+        let mut buf = encode_command(args);
+        buf.extend_from_slice(&encode_command(&[
+            "wait".to_string(),
+            replicas.to_string(),
+            millis.to_string(),
+        ]));
+        self.stream.write_all(&buf).await?;
+
+        // both replies are read, whatever the first is, to keep the connection in step
+        let reply = self.read_frame().await?;
+        let acked = self.read_frame().await?;
+        if let RespFrame::Error(e) = reply {
+            return Err(ClientError::Server(e.to_string()));
+        }
+        match acked {
+            RespFrame::Integer(acked) if acked as usize >= replicas => Ok(reply),
+            RespFrame::Integer(acked) => Err(ClientError::NotReplicated {
+                acked: acked as usize,
+                required: replicas,
+            }),
+            RespFrame::Error(e) => Err(ClientError::Server(e.to_string())),
+            frame => Err(ClientError::Server(format!(
+                "unexpected WAIT reply: {:?}",
+                frame
+            ))),
+        }
+    }
+}
+
+fn encode_command<A: AsRef<[u8]>>(args: &[A]) -> Vec<u8> {
+    let frame: RespFrame = RespArray::new(
+        args.iter()
+            .map(|arg| BulkString::new(arg.as_ref()).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into();
+    frame.encode()
+}
@@ -0,0 +1,137 @@
+// Legacy commands older clients still send, mapped onto the commands that replaced them.
+// The arguments are rewritten to the modern command, and where the two reply differently
+// the reply is converted back to what the legacy command replies.
+
+use super::{validate_command, Command, CommandError, CommandExecutor};
+use crate::{Backend, BulkString, RespArray, RespFrame, Session};
+
+#[derive(Debug)]
+pub(super) struct Alias {
+    pub(super) name: &'static str,
+    target: &'static str,
+    // appended to the arguments
+    options: &'static [&'static str],
+    reply: Option<fn(RespFrame) -> RespFrame>,
+}
+
+pub(super) const ALIASES: &[Alias] = &[
+    Alias {
+        name: "setnx",
+        target: "set",
+        options: &["nx"],
+        reply: Some(set_reply_as_integer),
+    },
+    Alias {
+        name: "getset",
+        target: "set",
+        options: &["get"],
+        reply: None,
+    },
+    Alias {
+        name: "substr",
+        target: "getrange",
+        options: &[],
+        reply: None,
+    },
+];
+
+// a legacy command, run as the modern one
+#[derive(Debug)]
+pub struct Legacy {
+    command: Box<Command>,
+    reply: fn(RespFrame) -> RespFrame,
+}
+
+impl CommandExecutor for Legacy {
+    async fn execute(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        let reply = Box::pin(self.command.execute(backend, session)).await;
+        (self.reply)(reply)
+    }
+}
+
+impl Alias {
+    // the modern command for the legacy one
+    pub(super) fn resolve(&self, value: RespArray) -> Result<Command, CommandError> {
+        validate_command(&value, &[self.name])?;
+        let mut args = value.0;
+        args[0] = BulkString::from(self.target).into();
+        args.extend(
+            self.options
+                .iter()
+                .map(|option| BulkString::from(*option).into()),
+        );
+        let command = Command::try_from(RespArray::new(args))?;
+        Ok(match self.reply {
+            Some(reply) => Legacy {
+                command: Box::new(command),
+                reply,
+            }
+            .into(),
+            None => command,
+        })
+    }
+}
+
+// SET NX replies OK or nil, SETNX 1 or 0
+fn set_reply_as_integer(reply: RespFrame) -> RespFrame {
+    match reply {
+        RespFrame::SimpleString(_) => RespFrame::Integer(1),
+        RespFrame::Null(_) => RespFrame::Integer(0),
+        reply => reply,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::command_spec;
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    async fn run(backend: &Backend, args: &[&str]) -> RespFrame {
+        match Command::try_from(command(args)) {
+            Ok(command) => command.execute(backend, &mut Session::default()).await,
+            Err(e) => panic!("{:?} failed to parse: {}", args, e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_legacy_commands() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["SETNX", "a", "1"]).await,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["setnx", "a", "2"]).await,
+            RespFrame::Integer(0)
+        );
+        assert_eq!(
+            run(&backend, &["getset", "a", "Hello World"]).await,
+            BulkString::from("1").into()
+        );
+        assert_eq!(
+            run(&backend, &["substr", "a", "0", "4"]).await,
+            BulkString::from("Hello").into()
+        );
+        // SET k NX would set k to "NX"
+        assert!(Command::try_from(command(&["setnx", "k"])).is_err());
+    }
+
+    #[test]
+    fn test_aliases_have_specs() {
+        for alias in ALIASES {
+            assert!(
+                command_spec(alias.name).is_some(),
+                "no spec for {}",
+                alias.name
+            );
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use super::{
+    extract_args, extract_integer, extract_keys, validate_command, BitCount, BitOp, BitPos,
+    CommandError, CommandExecutor, GetBit, SetBit,
+};
+use crate::{BitOperation, BitRange, BitUnit, RespArray, RespFrame, NOTIFY_STRING};
+
+// the largest string is 512MB, like in Redis
+const MAX_BIT_OFFSET: i64 = 512 * 1024 * 1024 * 8 - 1;
+
+impl CommandExecutor for SetBit {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let old = backend.setbit(self.key.clone(), self.offset, self.value);
+        backend.notify_keyspace_event(NOTIFY_STRING, "setbit", &self.key);
+        RespFrame::Integer(old as i64)
+    }
+}
+
+impl CommandExecutor for GetBit {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.getbit(&self.key, self.offset) as i64)
+    }
+}
+
+impl CommandExecutor for BitCount {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.bitcount(&self.key, self.range) as i64)
+    }
+}
+
+impl CommandExecutor for BitOp {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let len = backend.bitop(self.operation, self.dest.clone(), &self.keys);
+        let event = if len == 0 { "del" } else { "set" };
+        backend.notify_keyspace_event(NOTIFY_STRING, event, &self.dest);
+        RespFrame::Integer(len as i64)
+    }
+}
+
+impl CommandExecutor for BitPos {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.bitpos(&self.key, self.bit, self.range))
+    }
+}
+
+impl TryFrom<RespArray> for SetBit {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["setbit"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(offset), Some(value)) => Ok(SetBit {
+                key: key.into(),
+                offset: extract_offset(offset)?,
+                value: extract_bit(value)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, offset or value".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for GetBit {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["getbit"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(offset)) => Ok(GetBit {
+                key: key.into(),
+                offset: extract_offset(offset)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or offset".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BitCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bitcount"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.into(),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let range = extract_bit_range(args.collect())?;
+        if range.is_some_and(|range| range.end.is_none()) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(BitCount { key, range })
+    }
+}
+
+impl TryFrom<RespArray> for BitOp {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bitop"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (operation, dest) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(operation)), Some(RespFrame::BulkString(dest))) => {
+                let operation = match operation.to_ascii_lowercase().as_slice() {
+                    b"and" => BitOperation::And,
+                    b"or" => BitOperation::Or,
+                    b"xor" => BitOperation::Xor,
+                    b"not" => BitOperation::Not,
+                    _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                };
+                (operation, dest.into())
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid operation or destination key".to_string(),
+                ))
+            }
+        };
+        let keys = extract_keys(args.collect())?;
+        match (operation, keys.len()) {
+            (_, 0) => Err(CommandError::WrongArity("bitop".to_string())),
+            (BitOperation::Not, 2..) => Err(CommandError::InvalidArgument(
+                "BITOP NOT must be called with a single source key.".to_string(),
+            )),
+            _ => Ok(BitOp {
+                operation,
+                dest,
+                keys,
+            }),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BitPos {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bitpos"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, bit) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(bit)) => (key.into(), extract_bit(bit)?),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid key or bit".to_string(),
+                ))
+            }
+        };
+        Ok(BitPos {
+            key,
+            bit,
+            range: extract_bit_range(args.collect())?,
+        })
+    }
+}
+
+fn extract_offset(frame: RespFrame) -> Result<u64, CommandError> {
+    match extract_integer(frame) {
+        Ok(offset) if (0..=MAX_BIT_OFFSET).contains(&offset) => Ok(offset as u64),
+        _ => Err(CommandError::InvalidArgument(
+            "bit offset is not an integer or out of range".to_string(),
+        )),
+    }
+}
+
+fn extract_bit(frame: RespFrame) -> Result<bool, CommandError> {
+    match extract_integer(frame) {
+        Ok(0) => Ok(false),
+        Ok(1) => Ok(true),
+        _ => Err(CommandError::InvalidArgument(
+            "The bit argument must be 1 or 0.".to_string(),
+        )),
+    }
+}
+
+// [start [end [BYTE | BIT]]]
+fn extract_bit_range(args: Vec<RespFrame>) -> Result<Option<BitRange>, CommandError> {
+    let mut args = args.into_iter();
+    let start = match args.next() {
+        Some(start) => extract_integer(start)?,
+        None => return Ok(None),
+    };
+    let end = args.next().map(extract_integer).transpose()?;
+    let unit = match (args.next(), args.next()) {
+        (None, _) => BitUnit::Byte,
+        (Some(RespFrame::BulkString(unit)), None) => match unit.to_ascii_lowercase().as_slice() {
+            b"byte" => BitUnit::Byte,
+            b"bit" => BitUnit::Bit,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        },
+        _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+    };
+    Ok(Some(BitRange { start, end, unit }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, RespDecode, Session};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_bitcount_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$8\r\nbitcount\r\n$1\r\nk\r\n$1\r\n1\r\n$2\r\n-1\r\n$3\r\nBIT\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: BitCount = frame.try_into()?;
+        assert_eq!(result.key, "k");
+        assert_eq!(
+            result.range,
+            Some(BitRange {
+                start: 1,
+                end: Some(-1),
+                unit: BitUnit::Bit
+            })
+        );
+
+        // start without end
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$8\r\nbitcount\r\n$1\r\nk\r\n$1\r\n1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(BitCount::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitop_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nbitop\r\n$3\r\nXOR\r\n$1\r\nd\r\n$1\r\na\r\n$1\r\nb\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: BitOp = frame.try_into()?;
+        assert_eq!(result.operation, BitOperation::Xor);
+        assert_eq!(result.dest, "d");
+        assert_eq!(result.keys, ["a", "b"]);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nbitop\r\n$3\r\nnot\r\n$1\r\nd\r\n$1\r\na\r\n$1\r\nb\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(BitOp::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_setbit_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nsetbit\r\n$1\r\nk\r\n$2\r\n-1\r\n$1\r\n1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(SetBit::try_from(frame).is_err());
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nsetbit\r\n$1\r\nk\r\n$1\r\n7\r\n$1\r\n2\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(SetBit::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bitmap_commands() {
+        let backend = Backend::new();
+        backend.set("k".into(), BulkString::from("foobar").into());
+
+        let cmd = BitCount {
+            key: "k".into(),
+            range: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(26)
+        );
+
+        let cmd = SetBit {
+            key: "k".into(),
+            offset: 7,
+            value: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(0)
+        );
+        // 'f' is 0x66, now 0x67
+        assert_eq!(backend.get(b"k"), Some(BulkString::from("goobar").into()));
+
+        let cmd = GetBit {
+            key: "k".into(),
+            offset: 7,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(1)
+        );
+
+        let cmd = BitOp {
+            operation: BitOperation::And,
+            dest: "d".into(),
+            keys: vec!["k".into(), "missing".to_string().into()],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(6)
+        );
+
+        let cmd = BitPos {
+            key: "d".into(),
+            bit: true,
+            range: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(-1)
+        );
+    }
+}
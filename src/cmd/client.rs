@@ -0,0 +1,226 @@
+use super::{
+    extract_args, extract_timeout, validate_command, Client, ClientSubcommand, CommandError,
+    CommandExecutor, Quit, Reset, TimeUnit, RESP_OK,
+};
+use crate::{backend::now_ms, PauseMode, ReplyMode, RespArray, RespFrame, SimpleString};
+
+impl CommandExecutor for Client {
+    async fn execute(self, backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        match self.subcommand {
+            // only ON gets to see this reply, the connection drops it for OFF and SKIP
+            ClientSubcommand::Reply(mode) => session.set_reply_mode(mode),
+            ClientSubcommand::NoEvict(on) => session.set_no_evict(on),
+            ClientSubcommand::NoTouch(on) => session.set_no_touch(on),
+            // the timeout was checked to give a deadline that fits
+            ClientSubcommand::Pause(timeout, mode) => {
+                backend.pause_clients(mode, now_ms() + timeout.as_millis() as u64)
+            }
+            ClientSubcommand::Unpause => backend.unpause_clients(),
+        }
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for Reset {
+    async fn execute(self, backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        session.reset(backend);
+        SimpleString::new("RESET").into()
+    }
+}
+
+impl TryFrom<RespArray> for Client {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["client"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(RespFrame::BulkString(s)) => s.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid subcommand".to_string(),
+                ))
+            }
+        };
+        let subcommand = match (subcommand.as_slice(), args.next(), args.next(), args.next()) {
+            (b"reply", Some(RespFrame::BulkString(mode)), None, None) => {
+                let mode = match mode.to_ascii_lowercase().as_slice() {
+                    b"on" => ReplyMode::On,
+                    b"off" => ReplyMode::Off,
+                    b"skip" => ReplyMode::Skip,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "CLIENT REPLY mode must be ON, OFF or SKIP".to_string(),
+                        ))
+                    }
+                };
+                ClientSubcommand::Reply(mode)
+            }
+            (b"no-evict", Some(RespFrame::BulkString(on)), None, None) => {
+                ClientSubcommand::NoEvict(extract_on_off(&on)?)
+            }
+            (b"no-touch", Some(RespFrame::BulkString(on)), None, None) => {
+                ClientSubcommand::NoTouch(extract_on_off(&on)?)
+            }
+            // CLIENT PAUSE timeout [WRITE | ALL], the timeout in milliseconds
+            (b"pause", Some(timeout), mode, None) => {
+                let timeout = extract_timeout(timeout, TimeUnit::Millis)?;
+                let mode = match mode {
+                    None => PauseMode::All,
+                    Some(RespFrame::BulkString(mode)) if mode.eq_ignore_ascii_case(b"all") => {
+                        PauseMode::All
+                    }
+                    Some(RespFrame::BulkString(mode)) if mode.eq_ignore_ascii_case(b"write") => {
+                        PauseMode::Write
+                    }
+                    Some(_) => {
+                        return Err(CommandError::InvalidArgument("syntax error".to_string()))
+                    }
+                };
+                ClientSubcommand::Pause(timeout, mode)
+            }
+            (b"unpause", None, None, None) => ClientSubcommand::Unpause,
+            (s, _, _, _) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unknown subcommand or wrong number of arguments for '{}'",
+                    String::from_utf8_lossy(s)
+                )))
+            }
+        };
+        Ok(Client { subcommand })
+    }
+}
+
+impl TryFrom<RespArray> for Reset {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["reset"])?;
+        Ok(Reset)
+    }
+}
+
+impl CommandExecutor for Quit {
+    async fn execute(self, _backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RESP_OK.clone()
+    }
+}
+
+// QUIT [arg ...], like Redis it takes any arguments and ignores them
+impl TryFrom<RespArray> for Quit {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["quit"])?;
+        Ok(Quit)
+    }
+}
+
+fn extract_on_off(value: &[u8]) -> Result<bool, CommandError> {
+    match value.to_ascii_lowercase().as_slice() {
+        b"on" => Ok(true),
+        b"off" => Ok(false),
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecode, Session};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::time::Duration;
+
+    #[test]
+    fn test_client_reply_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nclient\r\n$5\r\nREPLY\r\n$4\r\nskip\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Client = frame.try_into()?;
+        assert_eq!(result.subcommand, ClientSubcommand::Reply(ReplyMode::Skip));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nclient\r\n$5\r\nreply\r\n$5\r\nmaybe\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(Client::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_reply_command() {
+        let backend = Backend::new();
+        let mut session = Session::default();
+
+        let cmd = Client {
+            subcommand: ClientSubcommand::Reply(ReplyMode::Off),
+        };
+        assert_eq!(cmd.execute(&backend, &mut session).await, RESP_OK.clone());
+        assert_eq!(session.reply_mode(), ReplyMode::Off);
+
+        let cmd = Client {
+            subcommand: ClientSubcommand::Reply(ReplyMode::On),
+        };
+        cmd.execute(&backend, &mut session).await;
+        assert!(session.replies_enabled());
+    }
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| crate::BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_client_no_evict_no_touch_and_reset() -> Result<()> {
+        let backend = Backend::new();
+        let mut session = Session::default();
+        for args in [
+            &["client", "NO-EVICT", "on"][..],
+            &["client", "no-touch", "ON"][..],
+        ] {
+            let cmd = Client::try_from(command(args))?;
+            assert_eq!(cmd.execute(&backend, &mut session).await, RESP_OK.clone());
+        }
+        assert!(session.no_evict() && session.no_touch());
+        assert!(Client::try_from(command(&["client", "no-touch", "maybe"])).is_err());
+        assert!(Client::try_from(command(&["client", "no-evict"])).is_err());
+
+        assert_eq!(
+            Client::try_from(command(&["client", "PAUSE", "100"]))?.subcommand,
+            ClientSubcommand::Pause(Duration::from_millis(100), PauseMode::All)
+        );
+        assert_eq!(
+            Client::try_from(command(&["client", "pause", "0", "write"]))?.subcommand,
+            ClientSubcommand::Pause(Duration::ZERO, PauseMode::Write)
+        );
+        assert!(Client::try_from(command(&["client", "pause", "-1"])).is_err());
+        // the deadline wouldn't fit in a unix time in milliseconds
+        assert_eq!(
+            Client::try_from(command(&["client", "pause", "9223372036854775807"]))
+                .unwrap_err()
+                .to_string(),
+            "ERR timeout is out of range"
+        );
+        assert!(Client::try_from(command(&["client", "pause", "1", "reads"])).is_err());
+        let cmd = Client::try_from(command(&["client", "pause", "60000", "write"]))?;
+        cmd.execute(&backend, &mut session).await;
+        assert!(backend.is_paused(true) && !backend.is_paused(false));
+        let cmd = Client::try_from(command(&["client", "unpause"]))?;
+        assert_eq!(cmd.execute(&backend, &mut session).await, RESP_OK.clone());
+        assert!(!backend.is_paused(true));
+
+        let reset = Reset::try_from(command(&["RESET"]))?;
+        assert_eq!(
+            reset.execute(&backend, &mut session).await,
+            SimpleString::new("RESET").into()
+        );
+        assert!(!session.no_evict() && !session.no_touch());
+        assert!(Reset::try_from(command(&["reset", "now"])).is_err());
+        Ok(())
+    }
+}
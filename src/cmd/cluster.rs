@@ -0,0 +1,276 @@
+use super::{
+    extract_args, validate_command, Asking, Cluster, ClusterSubcommand, CommandError,
+    CommandExecutor, RESP_OK,
+};
+use crate::{
+    key_hash_slot, Backend, BulkString, ClusterNode, RespArray, RespFrame, RespMap, Session,
+    SimpleError, CLUSTER_SLOTS,
+};
+
+impl CommandExecutor for Cluster {
+    async fn execute(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        if !backend.cluster.is_enabled() {
+            return SimpleError::new("ERR This instance has cluster support disabled").into();
+        }
+        let topology = backend.cluster.topology();
+        match self.subcommand {
+            ClusterSubcommand::Info => {
+                let assigned = topology.slots_assigned();
+                let state = match assigned == CLUSTER_SLOTS as usize {
+                    true => "ok",
+                    false => "fail",
+                };
+                let info = [
+                    ("cluster_enabled", "1".to_string()),
+                    ("cluster_state", state.to_string()),
+                    ("cluster_slots_assigned", assigned.to_string()),
+                    ("cluster_slots_ok", assigned.to_string()),
+                    ("cluster_slots_pfail", "0".to_string()),
+                    ("cluster_slots_fail", "0".to_string()),
+                    ("cluster_known_nodes", topology.nodes.len().to_string()),
+                    ("cluster_size", topology.nodes.len().to_string()),
+                    ("cluster_current_epoch", "0".to_string()),
+                    ("cluster_my_epoch", "0".to_string()),
+                ]
+                .iter()
+                .map(|(name, value)| format!("{}:{}\r\n", name, value))
+                .collect::<String>();
+                BulkString::from(info).into()
+            }
+            ClusterSubcommand::Slots => {
+                let slots = topology
+                    .nodes
+                    .iter()
+                    .flat_map(|node| {
+                        node.slots.iter().map(move |(start, end)| {
+                            RespArray::new(vec![
+                                RespFrame::Integer(*start as i64),
+                                RespFrame::Integer(*end as i64),
+                                RespArray::new(vec![
+                                    BulkString::from(node.host.as_str()).into(),
+                                    RespFrame::Integer(node.port as i64),
+                                    BulkString::from(node.id.as_str()).into(),
+                                ])
+                                .into(),
+                            ])
+                            .into()
+                        })
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(slots).into()
+            }
+            ClusterSubcommand::Shards => {
+                let shards = topology
+                    .nodes
+                    .iter()
+                    .map(|node| shard(node, session))
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(shards).into()
+            }
+            ClusterSubcommand::KeySlot(key) => {
+                RespFrame::Integer(key_hash_slot(key.as_bytes()) as i64)
+            }
+            ClusterSubcommand::MyId => match topology.node_by_port(backend.port()) {
+                Some(node) => BulkString::from(node.id.as_str()).into(),
+                None => SimpleError::new("ERR This node is not in cluster-topology").into(),
+            },
+        }
+    }
+}
+
+impl CommandExecutor for Asking {
+    async fn execute(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        if !backend.cluster.is_enabled() {
+            return SimpleError::new("ERR This instance has cluster support disabled").into();
+        }
+        session.set_asking();
+        RESP_OK.clone()
+    }
+}
+
+// a shard is a single master here, there are no replicas in the topology
+fn shard(node: &ClusterNode, session: &Session) -> RespFrame {
+    let slots = node
+        .slots
+        .iter()
+        .flat_map(|(start, end)| [*start, *end])
+        .map(|slot| RespFrame::Integer(slot as i64))
+        .collect::<Vec<RespFrame>>();
+    let fields = vec![
+        ("id", BulkString::from(node.id.as_str()).into()),
+        ("port", RespFrame::Integer(node.port as i64)),
+        ("ip", BulkString::from(node.host.as_str()).into()),
+        ("endpoint", BulkString::from(node.host.as_str()).into()),
+        ("role", BulkString::from("master").into()),
+        ("replication-offset", RespFrame::Integer(0)),
+        ("health", BulkString::from("online").into()),
+    ];
+    fields_frame(
+        vec![
+            ("slots", RespArray::new(slots).into()),
+            (
+                "nodes",
+                RespArray::new(vec![fields_frame(fields, session)]).into(),
+            ),
+        ],
+        session,
+    )
+}
+
+// a map under RESP3 and a flat array of name/value under RESP2, like HELLO
+fn fields_frame(fields: Vec<(&str, RespFrame)>, session: &Session) -> RespFrame {
+    if session.protocol() >= 3 {
+        let mut map = RespMap::new();
+        for (name, value) in fields {
+            map.insert(name, value);
+        }
+        map.into()
+    } else {
+        let mut array = Vec::with_capacity(fields.len() * 2);
+        for (name, value) in fields {
+            array.push(BulkString::from(name).into());
+            array.push(value);
+        }
+        RespArray::new(array).into()
+    }
+}
+
+impl TryFrom<RespArray> for Cluster {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cluster"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(RespFrame::BulkString(s)) => s.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid subcommand".to_string(),
+                ))
+            }
+        };
+        let subcommand = match (subcommand.as_slice(), args.next(), args.next()) {
+            (b"info", None, None) => ClusterSubcommand::Info,
+            (b"slots", None, None) => ClusterSubcommand::Slots,
+            (b"shards", None, None) => ClusterSubcommand::Shards,
+            (b"myid", None, None) => ClusterSubcommand::MyId,
+            (b"keyslot", Some(RespFrame::BulkString(key)), None) => {
+                ClusterSubcommand::KeySlot(key.into())
+            }
+            (s, _, _) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unknown subcommand or wrong number of arguments for '{}'",
+                    String::from_utf8_lossy(s)
+                )))
+            }
+        };
+        Ok(Cluster { subcommand })
+    }
+}
+
+impl TryFrom<RespArray> for Asking {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["asking"])?;
+        Ok(Asking)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClusterTopology;
+    use anyhow::Result;
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[test]
+    fn test_cluster_from_resp_array() -> Result<()> {
+        let result: Cluster = command(&["cluster", "INFO"]).try_into()?;
+        assert_eq!(result.subcommand, ClusterSubcommand::Info);
+        let result: Cluster = command(&["cluster", "keyslot", "foo"]).try_into()?;
+        assert_eq!(result.subcommand, ClusterSubcommand::KeySlot("foo".into()));
+        assert!(Cluster::try_from(command(&["cluster", "nope"])).is_err());
+        assert!(Cluster::try_from(command(&["cluster", "keyslot"])).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cluster_commands() {
+        let backend = Backend::new();
+        let mut session = Session::default();
+        let cluster = |subcommand| Cluster { subcommand };
+        assert!(matches!(
+            cluster(ClusterSubcommand::Info)
+                .execute(&backend, &mut session)
+                .await,
+            RespFrame::Error(_)
+        ));
+
+        backend.cluster.set_enabled(true);
+        backend.set_port(7000);
+        backend.cluster.set_topology(
+            ClusterTopology::parse("127.0.0.1:7000@0-8191 127.0.0.1:7001@8192-16383").unwrap(),
+        );
+        let RespFrame::BulkString(info) = cluster(ClusterSubcommand::Info)
+            .execute(&backend, &mut session)
+            .await
+        else {
+            panic!("CLUSTER INFO must reply with a bulk string");
+        };
+        let info = String::from_utf8(Vec::from(info.0)).unwrap();
+        assert!(info.contains("cluster_state:ok\r\n"));
+        assert!(info.contains("cluster_known_nodes:2\r\n"));
+
+        let RespFrame::Array(slots) = cluster(ClusterSubcommand::Slots)
+            .execute(&backend, &mut session)
+            .await
+        else {
+            panic!("CLUSTER SLOTS must reply with an array");
+        };
+        assert_eq!(slots.len(), 2);
+        let RespFrame::Array(first) = &slots[0] else {
+            panic!("every slot range is an array");
+        };
+        assert_eq!(first[0], RespFrame::Integer(0));
+        assert_eq!(first[1], RespFrame::Integer(8191));
+
+        let RespFrame::Array(shards) = cluster(ClusterSubcommand::Shards)
+            .execute(&backend, &mut session)
+            .await
+        else {
+            panic!("CLUSTER SHARDS must reply with an array");
+        };
+        let RespFrame::Array(shard) = &shards[1] else {
+            panic!("a shard is a flat array under RESP2");
+        };
+        assert_eq!(
+            shard[1],
+            RespArray::new(vec![RespFrame::Integer(8192), RespFrame::Integer(16383)]).into()
+        );
+
+        assert_eq!(
+            cluster(ClusterSubcommand::KeySlot("foo".into()))
+                .execute(&backend, &mut session)
+                .await,
+            RespFrame::Integer(12182)
+        );
+        let topology = backend.cluster.topology();
+        assert_eq!(
+            cluster(ClusterSubcommand::MyId)
+                .execute(&backend, &mut session)
+                .await,
+            BulkString::from(topology.nodes[0].id.as_str()).into()
+        );
+
+        Asking.execute(&backend, &mut session).await;
+        assert!(session.take_asking());
+        assert!(!session.take_asking());
+    }
+}
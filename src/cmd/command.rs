@@ -0,0 +1,176 @@
+use super::{
+    extract_args, table::COMMANDS, validate_command, CommandError, CommandExecutor, CommandInfo,
+    CommandInfoSubcommand, CommandSpec, KeySpec,
+};
+use crate::{BulkString, RespArray, RespFrame, RespNull, SimpleString};
+
+impl CommandExecutor for CommandInfo {
+    async fn execute(self, _backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match self.subcommand {
+            CommandInfoSubcommand::All => {
+                RespArray::new(commands().map(command_info).collect::<Vec<_>>()).into()
+            }
+            CommandInfoSubcommand::Count => RespFrame::Integer(commands().count() as i64),
+            CommandInfoSubcommand::Info(names) => RespArray::new(
+                names
+                    .iter()
+                    .map(|name| match commands().find(|spec| spec.name == name) {
+                        Some(spec) => command_info(spec),
+                        None => RespFrame::Null(RespNull),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            CommandInfoSubcommand::List => RespArray::new(
+                commands()
+                    .map(|spec| BulkString::from(spec.name).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for CommandInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["command"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            None => {
+                return Ok(CommandInfo {
+                    subcommand: CommandInfoSubcommand::All,
+                })
+            }
+            Some(RespFrame::BulkString(s)) => s.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid subcommand".to_string(),
+                ))
+            }
+        };
+        let subcommand = match subcommand.as_slice() {
+            b"count" if args.len() == 0 => CommandInfoSubcommand::Count,
+            b"list" if args.len() == 0 => CommandInfoSubcommand::List,
+            b"info" => CommandInfoSubcommand::Info(
+                args.map(|arg| match arg {
+                    RespFrame::BulkString(name) => {
+                        Ok(String::from_utf8(name.to_ascii_lowercase())?)
+                    }
+                    _ => Err(CommandError::InvalidArgument(
+                        "command name must be a bulk string".to_string(),
+                    )),
+                })
+                .collect::<Result<_, _>>()?,
+            ),
+            s => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unknown subcommand or wrong number of arguments for '{}'",
+                    String::from_utf8_lossy(s)
+                )))
+            }
+        };
+        Ok(CommandInfo { subcommand })
+    }
+}
+
+// the commands clients can call, subcommands are part of their command
+fn commands() -> impl Iterator<Item = &'static CommandSpec> {
+    COMMANDS.iter().filter(|spec| !spec.name.contains('|'))
+}
+
+// name, arity, flags, first key, last key and step, like COMMAND INFO of Redis before 7.
+// Keys that can't be described by a range are movablekeys, found by parsing the arguments.
+fn command_info(spec: &CommandSpec) -> RespFrame {
+    let mut flags: Vec<RespFrame> = spec
+        .flags
+        .names()
+        .map(|name| SimpleString::new(name).into())
+        .collect();
+    let (first, last, step) = match spec.keys {
+        KeySpec::None => (0, 0, 0),
+        KeySpec::Range(first, last, step) => (first as i64, last as i64, step as i64),
+        _ => {
+            flags.push(SimpleString::new("movablekeys").into());
+            (0, 0, 0)
+        }
+    };
+    RespArray::new(vec![
+        BulkString::from(spec.name).into(),
+        RespFrame::Integer(spec.arity as i64),
+        RespArray::new(flags).into(),
+        RespFrame::Integer(first),
+        RespFrame::Integer(last),
+        RespFrame::Integer(step),
+    ])
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, Session};
+
+    fn command(args: &[&str]) -> Result<CommandInfo, CommandError> {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .try_into()
+    }
+
+    #[tokio::test]
+    async fn test_command_info() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let mut session = Session::default();
+
+        let reply = command(&["command", "INFO", "get", "blmove", "nope"])?
+            .execute(&backend, &mut session)
+            .await;
+        let expected = RespArray::new(vec![
+            RespArray::new(vec![
+                BulkString::from("get").into(),
+                RespFrame::Integer(2),
+                RespArray::new(vec![SimpleString::new("readonly").into()]).into(),
+                RespFrame::Integer(1),
+                RespFrame::Integer(1),
+                RespFrame::Integer(1),
+            ])
+            .into(),
+            RespArray::new(vec![
+                BulkString::from("blmove").into(),
+                RespFrame::Integer(6),
+                RespArray::new(vec![
+                    SimpleString::new("write").into(),
+                    SimpleString::new("denyoom").into(),
+                    SimpleString::new("blocking").into(),
+                ])
+                .into(),
+                RespFrame::Integer(1),
+                RespFrame::Integer(2),
+                RespFrame::Integer(1),
+            ])
+            .into(),
+            RespFrame::Null(RespNull),
+        ]);
+        assert_eq!(reply, expected.into());
+
+        let count = command(&["command", "count"])?
+            .execute(&backend, &mut session)
+            .await;
+        let RespFrame::Array(list) = command(&["command", "list"])?
+            .execute(&backend, &mut session)
+            .await
+        else {
+            panic!("COMMAND LIST should reply an array");
+        };
+        assert_eq!(count, RespFrame::Integer(list.len() as i64));
+        assert!(list.contains(&BulkString::from("config").into()));
+        assert!(!list.contains(&BulkString::from("config|get").into()));
+
+        assert!(command(&["command", "count", "x"]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use super::{
+    extract_args, validate_command, CommandError, CommandExecutor, ConfigGet, ConfigSet, RESP_OK,
+};
+use crate::{BulkString, RespArray, RespFrame, SimpleError};
+use std::collections::HashSet;
+
+impl CommandExecutor for ConfigGet {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        // every parameter is a glob pattern, a parameter matched twice is only returned once
+        let mut seen = HashSet::new();
+        let mut data = Vec::new();
+        for pattern in self.parameters {
+            for (name, value) in backend.config_get_matching(&pattern) {
+                if seen.insert(name) {
+                    data.push(BulkString::from(name).into());
+                    data.push(BulkString::from(value.to_string()).into());
+                }
+            }
+        }
+        RespArray::new(data).into()
+    }
+}
+
+impl CommandExecutor for ConfigSet {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.config_set(&self.parameter, &self.value) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ConfigGet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["config", "get"])?;
+
+        let parameters = extract_args(value, 2)?
+            .into_iter()
+            .map(|p| match p {
+                RespFrame::BulkString(p) => Ok(String::from_utf8(Vec::from(p.0))?),
+                _ => Err(CommandError::InvalidArgument(
+                    "Invalid parameter".to_string(),
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if parameters.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "config get command must have at least 1 parameter".to_string(),
+            ));
+        }
+        Ok(ConfigGet { parameters })
+    }
+}
+
+impl TryFrom<RespArray> for ConfigSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["config", "set"])?;
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(parameter)), Some(RespFrame::BulkString(value))) => {
+                Ok(ConfigSet {
+                    parameter: String::from_utf8(Vec::from(parameter.0))?,
+                    value: String::from_utf8(Vec::from(value.0))?,
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid parameter or value".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Session;
+    use crate::{Backend, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_config_set_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$6\r\nconfig\r\n$3\r\nset\r\n$15\r\nslowlog-max-len\r\n$2\r\n10\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: ConfigSet = frame.try_into()?;
+        assert_eq!(result.parameter, "slowlog-max-len");
+        assert_eq!(result.value, "10");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_set_get_commands() {
+        let backend = Backend::new();
+        let cmd = ConfigSet {
+            parameter: "slowlog-log-slower-than".to_string(),
+            value: "100".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RESP_OK.clone()
+        );
+
+        let cmd = ConfigGet {
+            parameters: vec![
+                "slowlog-log-slower-than".to_string(),
+                "unknown".to_string(),
+                "SLOWLOG-*".to_string(),
+            ],
+        };
+        let expected = RespArray::new(vec![
+            BulkString::from("slowlog-log-slower-than").into(),
+            BulkString::from("100").into(),
+            BulkString::from("slowlog-max-len").into(),
+            BulkString::from("128").into(),
+        ]);
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            expected.into()
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use super::{
+    extract_args, extract_timeout, validate_command, CommandError, CommandExecutor, DebugCommand,
+    DebugSubcommand, TimeUnit, RESP_OK,
+};
+use crate::{RespArray, RespFrame, SimpleError, SimpleString, SnapshotError, DEBUG_DISABLED_ERROR};
+
+impl CommandExecutor for DebugCommand {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        if !backend.debug_command_enabled() {
+            return SimpleError::new(DEBUG_DISABLED_ERROR).into();
+        }
+        match self.subcommand {
+            // only this connection waits, the others are served meanwhile
+            DebugSubcommand::Sleep(duration) => {
+                tokio::time::sleep(duration).await;
+                RESP_OK.clone()
+            }
+            DebugSubcommand::Object(key) => match backend.debug_object(&key) {
+                Some(line) => SimpleString::new(line).into(),
+                None => SimpleError::new("ERR no such key").into(),
+            },
+            DebugSubcommand::SetActiveExpire(enabled) => {
+                backend.set_active_expire(enabled);
+                RESP_OK.clone()
+            }
+            DebugSubcommand::Reload => snapshot_reply(backend.debug_reload()),
+            DebugSubcommand::QuickSave => snapshot_reply(backend.save()),
+        }
+    }
+}
+
+fn snapshot_reply(result: Result<(), SnapshotError>) -> RespFrame {
+    match result {
+        Ok(()) => RESP_OK.clone(),
+        Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+    }
+}
+
+// DEBUG SLEEP seconds | OBJECT key | SET-ACTIVE-EXPIRE 0|1 | RELOAD | QUICKSAVE
+impl TryFrom<RespArray> for DebugCommand {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["debug"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(RespFrame::BulkString(s)) => s.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid subcommand".to_string(),
+                ))
+            }
+        };
+        let subcommand = match (subcommand.as_slice(), args.next(), args.next()) {
+            (b"sleep", Some(seconds), None) => {
+                DebugSubcommand::Sleep(extract_timeout(seconds, TimeUnit::Seconds)?)
+            }
+            (b"object", Some(RespFrame::BulkString(key)), None) => {
+                DebugSubcommand::Object(key.into())
+            }
+            (b"set-active-expire", Some(RespFrame::BulkString(flag)), None) => match &flag[..] {
+                b"0" => DebugSubcommand::SetActiveExpire(false),
+                b"1" => DebugSubcommand::SetActiveExpire(true),
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            (b"reload", None, None) => DebugSubcommand::Reload,
+            (b"quicksave", None, None) => DebugSubcommand::QuickSave,
+            (s, _, _) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unknown subcommand or wrong number of arguments for '{}'",
+                    String::from_utf8_lossy(s)
+                )))
+            }
+        };
+        Ok(DebugCommand { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, Session};
+    use anyhow::Result;
+    use std::time::Duration;
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[test]
+    fn test_debug_from_resp_array() -> Result<()> {
+        let result: DebugCommand = command(&["debug", "SLEEP", "0.25"]).try_into()?;
+        assert_eq!(
+            result.subcommand,
+            DebugSubcommand::Sleep(Duration::from_millis(250))
+        );
+        let result: DebugCommand = command(&["DEBUG", "object", "k"]).try_into()?;
+        assert_eq!(result.subcommand, DebugSubcommand::Object("k".into()));
+        let result: DebugCommand = command(&["debug", "set-active-expire", "0"]).try_into()?;
+        assert_eq!(result.subcommand, DebugSubcommand::SetActiveExpire(false));
+
+        assert!(DebugCommand::try_from(command(&["debug", "sleep", "-1"])).is_err());
+        assert!(DebugCommand::try_from(command(&["debug", "set-active-expire", "2"])).is_err());
+        let result: DebugCommand = command(&["debug", "RELOAD"]).try_into()?;
+        assert_eq!(result.subcommand, DebugSubcommand::Reload);
+        assert!(DebugCommand::try_from(command(&["debug", "quicksave", "x"])).is_err());
+        assert!(DebugCommand::try_from(command(&["debug", "jmap"])).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_debug_commands() -> Result<()> {
+        let backend = Backend::new();
+        let debug = |args: &[&str]| DebugCommand::try_from(command(args)).unwrap();
+
+        // refused until enabled
+        assert_eq!(
+            debug(&["debug", "sleep", "0"])
+                .execute(&backend, &mut Session::default())
+                .await,
+            SimpleError::new(DEBUG_DISABLED_ERROR).into()
+        );
+        backend.config_set("enable-debug-command", "yes").unwrap();
+
+        let start = tokio::time::Instant::now();
+        assert_eq!(
+            debug(&["debug", "sleep", "0.05"])
+                .execute(&backend, &mut Session::default())
+                .await,
+            RESP_OK.clone()
+        );
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        assert_eq!(
+            debug(&["debug", "object", "k"])
+                .execute(&backend, &mut Session::default())
+                .await,
+            SimpleError::new("ERR no such key").into()
+        );
+        backend.set("k".into(), BulkString::from("v").into());
+        let RespFrame::SimpleString(line) = debug(&["debug", "object", "k"])
+            .execute(&backend, &mut Session::default())
+            .await
+        else {
+            panic!("DEBUG OBJECT should reply with a simple string");
+        };
+        assert!(line.contains("encoding:raw"));
+
+        debug(&["debug", "set-active-expire", "0"])
+            .execute(&backend, &mut Session::default())
+            .await;
+        assert!(!backend.active_expire());
+
+        // the keys are all there after a reload, with their TTL
+        backend.hset("h".into(), b"f".to_vec(), BulkString::from("v").into());
+        backend.expire(&"k".into(), u64::MAX);
+        assert_eq!(
+            debug(&["debug", "reload"])
+                .execute(&backend, &mut Session::default())
+                .await,
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.dbsize(), 2);
+        assert_eq!(backend.expire_time(b"k"), Some(u64::MAX));
+        Ok(())
+    }
+}
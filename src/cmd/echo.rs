@@ -1,20 +1,34 @@
-use crate::{cmd::CommandError, BulkString, RespArray, RespFrame};
+use crate::{cmd::CommandError, BulkString, RespArray, RespFrame, SimpleString};
 
-use super::{extract_args, validate_command, CommandExecutor, Echo};
+use super::{extract_args, validate_command, CommandExecutor, Echo, Ping};
 
 impl CommandExecutor for Echo {
-    fn execute(self, _backend: &crate::Backend) -> RespFrame {
-        RespFrame::BulkString(BulkString(self.message.into_bytes()))
+    async fn execute(self, _backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::BulkString(self.message)
+    }
+}
+
+impl CommandExecutor for Ping {
+    async fn execute(self, _backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        // a RESP2 subscriber can only get arrays, Redis replies like a published message
+        if session.protocol() < 3 && session.is_subscribed() {
+            let message = self.message.unwrap_or_else(|| BulkString::from(""));
+            return RespArray::new(vec![BulkString::from("pong").into(), message.into()]).into();
+        }
+        match self.message {
+            Some(message) => message.into(),
+            None => SimpleString::new("PONG").into(),
+        }
     }
 }
 
 impl TryFrom<RespArray> for Echo {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["echo"], 1)?;
+        validate_command(&value, &["echo"])?;
         let mut args = extract_args(value, 1)?.into_iter();
         let message = match args.next() {
-            Some(RespFrame::BulkString(s)) => String::from_utf8(s.0)?,
+            Some(RespFrame::BulkString(message)) => message,
             _ => return Err(CommandError::InvalidArgument("Invalid message".to_string())),
         };
 
@@ -22,9 +36,28 @@ impl TryFrom<RespArray> for Echo {
     }
 }
 
+// PING [message]
+impl TryFrom<RespArray> for Ping {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ping"])?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let message = match (args.next(), args.next()) {
+            (None, None) => None,
+            (Some(RespFrame::BulkString(message)), None) => Some(message),
+            (Some(_), None) => {
+                return Err(CommandError::InvalidArgument("Invalid message".to_string()))
+            }
+            _ => return Err(CommandError::WrongArity("ping".to_string())),
+        };
+        Ok(Ping { message })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Session;
     use crate::{Backend, RespDecode};
     use anyhow::Result;
     use bytes::BytesMut;
@@ -35,20 +68,55 @@ mod tests {
         buf.extend_from_slice(b"*2\r\n$4\r\necho\r\n$5\r\nhello\r\n");
         let frame = RespArray::decode(&mut buf)?;
         let echo = Echo::try_from(frame)?;
-        assert_eq!(echo.message, "hello");
+        assert_eq!(echo.message, BulkString::from("hello"));
         Ok(())
     }
 
-    #[test]
-    fn test_echo_execute() {
+    #[tokio::test]
+    async fn test_echo_execute() {
         let backend = Backend::new();
         let echo = Echo {
-            message: "hello".to_string(),
+            message: BulkString::from("hello"),
+        };
+        let frame = echo.execute(&backend, &mut Session::default()).await;
+        assert_eq!(frame, RespFrame::BulkString(BulkString::from("hello")));
+    }
+
+    #[tokio::test]
+    async fn test_ping() -> Result<()> {
+        let backend = Backend::new();
+        let mut session = Session::default();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nPING\r\n");
+        let ping = Ping::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(
+            ping.execute(&backend, &mut session).await,
+            SimpleString::new("PONG").into()
+        );
+
+        buf.extend_from_slice(b"*2\r\n$4\r\nping\r\n$2\r\nhi\r\n");
+        let ping = Ping::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(
+            ping.execute(&backend, &mut session).await,
+            BulkString::from("hi").into()
+        );
+
+        buf.extend_from_slice(b"*3\r\n$4\r\nping\r\n$1\r\na\r\n$1\r\nb\r\n");
+        assert!(Ping::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        let subscribe = super::super::Subscribe {
+            channels: vec![b"news".to_vec()],
         };
-        let frame = echo.execute(&backend);
+        subscribe.execute(&backend, &mut session).await;
+        let ping = Ping { message: None };
         assert_eq!(
-            frame,
-            RespFrame::BulkString(BulkString("hello".to_string().into_bytes()))
+            ping.execute(&backend, &mut session).await,
+            RespArray::new(vec![
+                BulkString::from("pong").into(),
+                BulkString::from("").into()
+            ])
+            .into()
         );
+        Ok(())
     }
 }
@@ -0,0 +1,239 @@
+use super::{
+    extract_args, extract_expire_at, extract_integer, extract_keys, validate_command, CommandError,
+    CommandExecutor, Expire, ExpireTime, Persist, TimeUnit, Ttl,
+};
+use crate::{now_ms, RespArray, RespFrame, NOTIFY_GENERIC};
+
+impl CommandExecutor for Expire {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        if !backend.expire(&self.key, self.at_ms) {
+            return RespFrame::Integer(0);
+        }
+        // a time that has passed deleted the key
+        match backend.key_exists(&self.key) {
+            true => backend.notify_keyspace_event(NOTIFY_GENERIC, "expire", &self.key),
+            false => backend.notify_keyspace_event(NOTIFY_GENERIC, "del", &self.key),
+        }
+        RespFrame::Integer(1)
+    }
+}
+
+impl CommandExecutor for Ttl {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let ttl = backend.pttl(&self.key);
+        match (ttl, self.unit) {
+            (ttl, TimeUnit::Seconds) if ttl >= 0 => RespFrame::Integer((ttl + 500) / 1000),
+            (ttl, _) => RespFrame::Integer(ttl),
+        }
+    }
+}
+
+impl CommandExecutor for ExpireTime {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let at = backend.pexpiretime(&self.key);
+        match (at, self.unit) {
+            (at, TimeUnit::Seconds) if at >= 0 => RespFrame::Integer(at / 1000),
+            (at, _) => RespFrame::Integer(at),
+        }
+    }
+}
+
+impl CommandExecutor for Persist {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        if !backend.persist(&self.key) {
+            return RespFrame::Integer(0);
+        }
+        backend.notify_keyspace_event(NOTIFY_GENERIC, "persist", &self.key);
+        RespFrame::Integer(1)
+    }
+}
+
+// EXPIRE key seconds, PEXPIRE key milliseconds, EXPIREAT key unix-time-seconds and
+// PEXPIREAT key unix-time-milliseconds. A time that isn't positive deletes the key.
+impl TryFrom<RespArray> for Expire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(name)) => name.to_ascii_lowercase(),
+            _ => Vec::new(),
+        };
+        let (name, unit, absolute) = match name.as_slice() {
+            b"pexpire" => ("pexpire", TimeUnit::Millis, false),
+            b"expireat" => ("expireat", TimeUnit::Seconds, true),
+            b"pexpireat" => ("pexpireat", TimeUnit::Millis, true),
+            _ => ("expire", TimeUnit::Seconds, false),
+        };
+        validate_command(&value, &[name])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (Some(key), Some(time), None) = (args.next(), args.next(), args.next()) else {
+            return Err(CommandError::WrongArity(name.to_string()));
+        };
+        let key = extract_keys(vec![key])?.remove(0);
+        let time = extract_integer(time)?;
+        let at_ms = match time > 0 {
+            true => extract_expire_at(RespFrame::Integer(time), unit, absolute, now_ms(), name)?,
+            false => 0,
+        };
+        Ok(Expire { key, at_ms })
+    }
+}
+
+// TTL key and PTTL key
+impl TryFrom<RespArray> for Ttl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, unit) = match value.first() {
+            Some(RespFrame::BulkString(name)) if name.eq_ignore_ascii_case(b"pttl") => {
+                ("pttl", TimeUnit::Millis)
+            }
+            _ => ("ttl", TimeUnit::Seconds),
+        };
+        validate_command(&value, &[name])?;
+
+        let mut keys = extract_keys(extract_args(value, 1)?)?;
+        if keys.len() != 1 {
+            return Err(CommandError::WrongArity(name.to_string()));
+        }
+        Ok(Ttl {
+            key: keys.remove(0),
+            unit,
+        })
+    }
+}
+
+// EXPIRETIME key and PEXPIRETIME key
+impl TryFrom<RespArray> for ExpireTime {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, unit) = match value.first() {
+            Some(RespFrame::BulkString(name)) if name.eq_ignore_ascii_case(b"pexpiretime") => {
+                ("pexpiretime", TimeUnit::Millis)
+            }
+            _ => ("expiretime", TimeUnit::Seconds),
+        };
+        validate_command(&value, &[name])?;
+
+        let mut keys = extract_keys(extract_args(value, 1)?)?;
+        if keys.len() != 1 {
+            return Err(CommandError::WrongArity(name.to_string()));
+        }
+        Ok(ExpireTime {
+            key: keys.remove(0),
+            unit,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Persist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["persist"])?;
+
+        let mut keys = extract_keys(extract_args(value, 1)?)?;
+        if keys.len() != 1 {
+            return Err(CommandError::WrongArity("persist".to_string()));
+        }
+        Ok(Persist {
+            key: keys.remove(0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{set_mock_now_ms, Backend, BulkString, Session};
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_expire_commands() -> anyhow::Result<()> {
+        let now = 1_700_000_000_000;
+        set_mock_now_ms(Some(now));
+        let backend = Backend::new();
+        let mut session = Session::default();
+        backend.set("k".into(), BulkString::from("v").into());
+
+        let ttl = |backend: Backend, name: &'static str| async move {
+            let cmd = Ttl::try_from(command(&[name, "k"])).unwrap();
+            cmd.execute(&backend, &mut Session::default()).await
+        };
+        assert_eq!(ttl(backend.clone(), "ttl").await, RespFrame::Integer(-1));
+
+        let cmd = Expire::try_from(command(&["expire", "k", "10"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(ttl(backend.clone(), "ttl").await, RespFrame::Integer(10));
+        assert_eq!(
+            ttl(backend.clone(), "pttl").await,
+            RespFrame::Integer(10_000)
+        );
+
+        let at = (now + 5_000).to_string();
+        let cmd = Expire::try_from(command(&["PEXPIREAT", "k", &at]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            ttl(backend.clone(), "pttl").await,
+            RespFrame::Integer(5_000)
+        );
+        let cmd = ExpireTime::try_from(command(&["pexpiretime", "k"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(now as i64 + 5_000)
+        );
+        let cmd = ExpireTime::try_from(command(&["EXPIRETIME", "k"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(now as i64 / 1000 + 5)
+        );
+
+        let cmd = Persist::try_from(command(&["persist", "k"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(1)
+        );
+        let cmd = Persist::try_from(command(&["persist", "k"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(0)
+        );
+
+        // a negative time deletes the key
+        let cmd = Expire::try_from(command(&["pexpire", "k", "-1"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(ttl(backend.clone(), "ttl").await, RespFrame::Integer(-2));
+        let cmd = ExpireTime::try_from(command(&["expiretime", "k"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(-2)
+        );
+        let cmd = Expire::try_from(command(&["expire", "k", "10"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(0)
+        );
+
+        let max = i64::MAX.to_string();
+        assert!(Expire::try_from(command(&["expire", "k", &max])).is_err());
+        // the time it expires at must fit in a unix time in milliseconds
+        assert!(Expire::try_from(command(&["pexpire", "k", &max])).is_err());
+        assert!(Expire::try_from(command(&["pexpireat", "k", &max])).is_ok());
+        set_mock_now_ms(None);
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use super::{
+    extract_args, extract_integer, extract_keys, validate_command, CommandError, CommandExecutor,
+    FCall,
+};
+use crate::{RespArray, RespFrame, SimpleError};
+
+impl CommandExecutor for FCall {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.fcall(&self.function, &self.keys, &self.args) {
+            Some(reply) => reply,
+            None => SimpleError::new("ERR Function not found").into(),
+        }
+    }
+}
+
+// FCALL function numkeys [key ...] [arg ...]
+impl TryFrom<RespArray> for FCall {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["fcall"])?;
+
+        let mut args = extract_args(value, 1)?;
+        if args.len() < 2 {
+            return Err(CommandError::WrongArity("fcall".to_string()));
+        }
+        let mut rest = args.split_off(2);
+        let mut args = args.into_iter();
+        let function = match args.next() {
+            Some(RespFrame::BulkString(function)) => String::from_utf8(Vec::from(function.0))?,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid function".to_string(),
+                ))
+            }
+        };
+        let numkeys = args.next().map(extract_integer).transpose()?.unwrap_or(0);
+        if numkeys < 0 {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be negative".to_string(),
+            ));
+        }
+        if numkeys as usize > rest.len() {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let args = rest.split_off(numkeys as usize);
+        Ok(FCall {
+            function,
+            keys: extract_keys(rest)?,
+            args,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, Key, RespDecode, Session};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_fcall_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*5\r\n$5\r\nfcall\r\n$1\r\nf\r\n$1\r\n1\r\n$1\r\nk\r\n$1\r\nv\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: FCall = frame.try_into()?;
+        assert_eq!(result.function, "f");
+        assert_eq!(result.keys, ["k"]);
+        assert_eq!(result.args, vec![BulkString::from("v").into()]);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nfcall\r\n$1\r\nf\r\n$1\r\n2\r\n$1\r\nk\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(FCall::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fcall_commands() {
+        let backend = Backend::new();
+        backend.register_function("count", |_: &Backend, keys: &[Key], args: &[RespFrame]| {
+            RespFrame::Integer((keys.len() + args.len()) as i64)
+        });
+
+        let cmd = FCall {
+            function: "count".to_string(),
+            keys: vec!["a".into()],
+            args: vec![BulkString::from("x").into(), BulkString::from("y").into()],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(3)
+        );
+
+        let cmd = FCall {
+            function: "missing".to_string(),
+            keys: vec![],
+            args: vec![],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            SimpleError::new("ERR Function not found").into()
+        );
+    }
+}
@@ -0,0 +1,632 @@
+use super::{
+    extract_args, extract_bytes, extract_integer, validate_command,
+    zset::{extract_score, extract_zadd_flags},
+    CommandError, CommandExecutor, GeoAdd, GeoCenter, GeoDist, GeoPos, GeoSearch, GeoSearchStore,
+};
+use crate::{
+    geo_valid, BulkString, GeoMatch, GeoSearchOptions, GeoShape, GeoSort, GeoUnit, Key, RespArray,
+    RespFrame, RespNull, SimpleError, NOTIFY_GENERIC, NOTIFY_ZSET,
+};
+
+impl CommandExecutor for GeoAdd {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let changed = backend.geoadd(self.key.clone(), self.members, self.condition, self.ch);
+        if changed > 0 {
+            backend.notify_keyspace_event(NOTIFY_ZSET, "zadd", &self.key);
+        }
+        RespFrame::Integer(changed as i64)
+    }
+}
+
+impl CommandExecutor for GeoPos {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespArray::new(
+            self.members
+                .iter()
+                .map(|member| match backend.geopos(&self.key, member) {
+                    Some(coord) => coord_reply(coord),
+                    None => RespFrame::Null(RespNull),
+                })
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+}
+
+impl CommandExecutor for GeoDist {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.geodist(&self.key, &self.member1, &self.member2) {
+            Some(dist) => BulkString::from(format_dist(dist, self.unit)).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for GeoSearch {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let matches = match self.matches(backend) {
+            Ok(matches) => matches,
+            Err(e) => return e,
+        };
+        let plain = !(self.withcoord || self.withdist || self.withhash);
+        RespArray::new(
+            matches
+                .into_iter()
+                .map(|m| {
+                    let member = BulkString::from(m.member).into();
+                    if plain {
+                        return member;
+                    }
+                    let mut item = vec![member];
+                    if self.withdist {
+                        item.push(BulkString::from(format_dist(m.dist, self.unit)).into());
+                    }
+                    if self.withhash {
+                        item.push(RespFrame::Integer(m.hash as i64));
+                    }
+                    if self.withcoord {
+                        item.push(coord_reply((m.longitude, m.latitude)));
+                    }
+                    RespArray::new(item).into()
+                })
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+}
+
+impl CommandExecutor for GeoSearchStore {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let matches = match self.search.matches(backend) {
+            Ok(matches) => matches,
+            Err(e) => return e,
+        };
+        let unit = self.search.unit.meters();
+        let members = matches
+            .into_iter()
+            .map(|m| match self.storedist {
+                true => (m.dist / unit, m.member),
+                false => (m.hash as f64, m.member),
+            })
+            .collect();
+        let existed = backend.key_exists(&self.destination);
+        let stored = backend.zstore(self.destination.clone(), members);
+        if stored > 0 {
+            backend.notify_keyspace_event(NOTIFY_ZSET, "geosearchstore", &self.destination);
+        } else if existed {
+            backend.notify_keyspace_event(NOTIFY_GENERIC, "del", &self.destination);
+        }
+        RespFrame::Integer(stored as i64)
+    }
+}
+
+impl GeoSearch {
+    // the members in the shape, or the error reply when the center member doesn't exist
+    fn matches(&self, backend: &crate::Backend) -> Result<Vec<GeoMatch>, RespFrame> {
+        let center = match self.center {
+            GeoCenter::LonLat(longitude, latitude) => (longitude, latitude),
+            GeoCenter::Member(ref member) => match backend.geopos(&self.key, member) {
+                Some(center) => center,
+                None if backend.key_exists(&self.key) => {
+                    return Err(
+                        SimpleError::new("ERR could not decode requested zset member").into(),
+                    )
+                }
+                None => return Ok(vec![]),
+            },
+        };
+        Ok(backend.geosearch(&self.key, center, self.shape, &self.options))
+    }
+}
+
+fn coord_reply((longitude, latitude): (f64, f64)) -> RespFrame {
+    RespArray::new(vec![
+        BulkString::from(longitude.to_string()).into(),
+        BulkString::from(latitude.to_string()).into(),
+    ])
+    .into()
+}
+
+fn format_dist(meters: f64, unit: GeoUnit) -> String {
+    format!("{:.4}", meters / unit.meters())
+}
+
+impl TryFrom<RespArray> for GeoAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geoadd"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.into(),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let (condition, ch) = extract_zadd_flags(&mut args)?;
+
+        let args: Vec<RespFrame> = args.collect();
+        if args.is_empty() || !args.len().is_multiple_of(3) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let mut members = Vec::with_capacity(args.len() / 3);
+        let mut args = args.into_iter();
+        while let (Some(longitude), Some(latitude), Some(member)) =
+            (args.next(), args.next(), args.next())
+        {
+            let (longitude, latitude) = (extract_score(longitude)?, extract_score(latitude)?);
+            if !geo_valid(longitude, latitude) {
+                return Err(CommandError::InvalidArgument(format!(
+                    "invalid longitude,latitude pair {:.6},{:.6}",
+                    longitude, latitude
+                )));
+            }
+            match member {
+                RespFrame::BulkString(member) => {
+                    members.push((longitude, latitude, Vec::from(member)))
+                }
+                _ => return Err(CommandError::InvalidArgument("Invalid member".to_string())),
+            }
+        }
+        Ok(GeoAdd {
+            key,
+            condition,
+            ch,
+            members,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoPos {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geopos"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match args.next() {
+            Some(RespFrame::BulkString(key)) => Ok(GeoPos {
+                key: key.into(),
+                members: extract_bytes(args.collect())?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for GeoDist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geodist"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (
+            args.next(),
+            args.next(),
+            args.next(),
+            args.next(),
+            args.next(),
+        ) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(RespFrame::BulkString(member1)),
+                Some(RespFrame::BulkString(member2)),
+                unit,
+                None,
+            ) => Ok(GeoDist {
+                key: key.into(),
+                member1: Vec::from(member1),
+                member2: Vec::from(member2),
+                unit: unit.map(extract_unit).transpose()?.unwrap_or_default(),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, members or unit".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearch {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geosearch"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.into(),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let (search, _) = extract_search(key, args, false)?;
+        Ok(search)
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearchStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geosearchstore"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (destination, key) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(destination)), Some(RespFrame::BulkString(key))) => {
+                (destination.into(), key.into())
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid destination or key".to_string(),
+                ))
+            }
+        };
+        let (search, storedist) = extract_search(key, args, true)?;
+        Ok(GeoSearchStore {
+            destination,
+            search,
+            storedist,
+        })
+    }
+}
+
+// the options of GEOSEARCH, and of GEOSEARCHSTORE when store is set, which takes STOREDIST
+// instead of the WITH options
+fn extract_search(
+    key: Key,
+    mut args: impl Iterator<Item = RespFrame>,
+    store: bool,
+) -> Result<(GeoSearch, bool), CommandError> {
+    let command = if store { "GEOSEARCHSTORE" } else { "GEOSEARCH" };
+    let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+    let mut center = None;
+    let mut shape = None;
+    let mut options = GeoSearchOptions {
+        sort: None,
+        count: None,
+        any: false,
+    };
+    let (mut withcoord, mut withdist, mut withhash, mut storedist) = (false, false, false, false);
+    while let Some(arg) = args.next() {
+        let RespFrame::BulkString(arg) = arg else {
+            return Err(syntax_error());
+        };
+        match arg.to_ascii_lowercase().as_slice() {
+            b"frommember" if center.is_none() => match args.next() {
+                Some(RespFrame::BulkString(member)) => {
+                    center = Some(GeoCenter::Member(Vec::from(member)))
+                }
+                _ => return Err(syntax_error()),
+            },
+            b"fromlonlat" if center.is_none() => match (args.next(), args.next()) {
+                (Some(longitude), Some(latitude)) => {
+                    let (longitude, latitude) =
+                        (extract_score(longitude)?, extract_score(latitude)?);
+                    if !geo_valid(longitude, latitude) {
+                        return Err(CommandError::InvalidArgument(format!(
+                            "invalid longitude,latitude pair {:.6},{:.6}",
+                            longitude, latitude
+                        )));
+                    }
+                    center = Some(GeoCenter::LonLat(longitude, latitude));
+                }
+                _ => return Err(syntax_error()),
+            },
+            b"byradius" if shape.is_none() => match (args.next(), args.next()) {
+                (Some(radius), Some(unit)) => {
+                    let radius = extract_distance(radius)?;
+                    let unit = extract_unit(unit)?;
+                    shape = Some((GeoShape::Radius(radius * unit.meters()), unit));
+                }
+                _ => return Err(syntax_error()),
+            },
+            b"bybox" if shape.is_none() => match (args.next(), args.next(), args.next()) {
+                (Some(width), Some(height), Some(unit)) => {
+                    let (width, height) = (extract_distance(width)?, extract_distance(height)?);
+                    let unit = extract_unit(unit)?;
+                    shape = Some((
+                        GeoShape::Box(width * unit.meters(), height * unit.meters()),
+                        unit,
+                    ));
+                }
+                _ => return Err(syntax_error()),
+            },
+            b"asc" => options.sort = Some(GeoSort::Asc),
+            b"desc" => options.sort = Some(GeoSort::Desc),
+            b"count" => {
+                let count = args.next().ok_or_else(syntax_error)?;
+                match extract_integer(count)? {
+                    count if count > 0 => options.count = Some(count as usize),
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "COUNT must be > 0".to_string(),
+                        ))
+                    }
+                }
+            }
+            b"any" => options.any = true,
+            b"withcoord" if !store => withcoord = true,
+            b"withdist" if !store => withdist = true,
+            b"withhash" if !store => withhash = true,
+            b"storedist" if store => storedist = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    let Some(center) = center else {
+        return Err(CommandError::InvalidArgument(format!(
+            "exactly one of FROMMEMBER or FROMLONLAT can be specified for {}",
+            command
+        )));
+    };
+    let Some((shape, unit)) = shape else {
+        return Err(CommandError::InvalidArgument(format!(
+            "exactly one of BYRADIUS and BYBOX can be specified for {}",
+            command
+        )));
+    };
+    if options.any && options.count.is_none() {
+        return Err(CommandError::InvalidArgument(
+            "the ANY argument requires COUNT argument".to_string(),
+        ));
+    }
+    let search = GeoSearch {
+        key,
+        center,
+        shape,
+        unit,
+        options,
+        withcoord,
+        withdist,
+        withhash,
+    };
+    Ok((search, storedist))
+}
+
+fn extract_unit(frame: RespFrame) -> Result<GeoUnit, CommandError> {
+    match frame {
+        RespFrame::BulkString(unit) => match unit.to_ascii_lowercase().as_slice() {
+            b"m" => Ok(GeoUnit::Meters),
+            b"km" => Ok(GeoUnit::Kilometers),
+            b"mi" => Ok(GeoUnit::Miles),
+            b"ft" => Ok(GeoUnit::Feet),
+            _ => Err(CommandError::InvalidArgument(
+                "unsupported unit provided. please use M, KM, FT, MI".to_string(),
+            )),
+        },
+        _ => Err(CommandError::InvalidArgument("Invalid unit".to_string())),
+    }
+}
+
+fn extract_distance(frame: RespFrame) -> Result<f64, CommandError> {
+    match extract_score(frame)? {
+        distance if distance >= 0.0 => Ok(distance),
+        _ => Err(CommandError::InvalidArgument(
+            "need numeric radius, width or height".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecode, Session, ZAddCondition};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn sicily(backend: &Backend) {
+        backend.geoadd(
+            "Sicily".into(),
+            vec![
+                (13.361389, 38.115556, b"Palermo".to_vec()),
+                (15.087269, 37.502669, b"Catania".to_vec()),
+            ],
+            ZAddCondition::Always,
+            false,
+        );
+    }
+
+    #[test]
+    fn test_geoadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$6\r\ngeoadd\r\n$1\r\ng\r\n$2\r\nXX\r\n$9\r\n13.361389\r\n$9\r\n38.115556\r\n$7\r\nPalermo\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: GeoAdd = frame.try_into()?;
+        assert_eq!(result.condition, ZAddCondition::Xx);
+        assert_eq!(
+            result.members,
+            vec![(13.361389, 38.115556, b"Palermo".to_vec())]
+        );
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\ngeoadd\r\n$1\r\ng\r\n$1\r\n0\r\n$2\r\n90\r\n$1\r\np\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(GeoAdd::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*11\r\n$9\r\ngeosearch\r\n$1\r\ng\r\n$10\r\nFROMLONLAT\r\n$2\r\n15\r\n$2\r\n37\r\n$8\r\nBYRADIUS\r\n$3\r\n200\r\n$2\r\nkm\r\n$5\r\nCOUNT\r\n$1\r\n1\r\n$8\r\nWITHDIST\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: GeoSearch = frame.try_into()?;
+        assert_eq!(result.center, GeoCenter::LonLat(15.0, 37.0));
+        assert_eq!(result.shape, GeoShape::Radius(200000.0));
+        assert_eq!(result.unit, GeoUnit::Kilometers);
+        assert_eq!(result.options.count, Some(1));
+        assert!(result.withdist && !result.withcoord);
+
+        // no shape
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$9\r\ngeosearch\r\n$1\r\ng\r\n$10\r\nFROMMEMBER\r\n$1\r\na\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(GeoSearch::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearchstore_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*9\r\n$14\r\nGEOSEARCHSTORE\r\n$1\r\nd\r\n$1\r\ng\r\n$10\r\nFROMMEMBER\r\n$1\r\na\r\n$8\r\nBYRADIUS\r\n$1\r\n5\r\n$2\r\nmi\r\n$9\r\nSTOREDIST\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: GeoSearchStore = frame.try_into()?;
+        assert_eq!(result.destination, "d");
+        assert_eq!(result.search.key, "g");
+        assert_eq!(result.search.unit, GeoUnit::Miles);
+        assert!(result.storedist);
+
+        // the WITH options are for GEOSEARCH only, STOREDIST for GEOSEARCHSTORE only
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*9\r\n$14\r\nGEOSEARCHSTORE\r\n$1\r\nd\r\n$1\r\ng\r\n$10\r\nFROMMEMBER\r\n$1\r\na\r\n$8\r\nBYRADIUS\r\n$1\r\n5\r\n$2\r\nmi\r\n$8\r\nWITHDIST\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(GeoSearchStore::try_from(frame).is_err());
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*8\r\n$9\r\nGEOSEARCH\r\n$1\r\ng\r\n$10\r\nFROMMEMBER\r\n$1\r\na\r\n$8\r\nBYRADIUS\r\n$1\r\n5\r\n$2\r\nmi\r\n$9\r\nSTOREDIST\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(GeoSearch::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_geo_commands() {
+        let backend = Backend::new();
+        sicily(&backend);
+
+        let cmd = GeoDist {
+            key: "Sicily".into(),
+            member1: b"Palermo".to_vec(),
+            member2: b"Catania".to_vec(),
+            unit: GeoUnit::Kilometers,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            BulkString::from("166.2742").into()
+        );
+
+        let cmd = GeoPos {
+            key: "Sicily".into(),
+            members: vec![b"Rome".to_vec()],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![RespFrame::Null(RespNull)]).into()
+        );
+
+        let cmd = GeoSearch {
+            key: "Sicily".into(),
+            center: GeoCenter::Member(b"Palermo".to_vec()),
+            shape: GeoShape::Radius(200000.0),
+            unit: GeoUnit::Kilometers,
+            options: GeoSearchOptions {
+                sort: Some(GeoSort::Desc),
+                count: None,
+                any: false,
+            },
+            withcoord: false,
+            withdist: true,
+            withhash: false,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![
+                RespArray::new(vec![
+                    BulkString::from("Catania").into(),
+                    BulkString::from("166.2742").into(),
+                ])
+                .into(),
+                RespArray::new(vec![
+                    BulkString::from("Palermo").into(),
+                    BulkString::from("0.0000").into(),
+                ])
+                .into(),
+            ])
+            .into()
+        );
+
+        let cmd = GeoSearch {
+            key: "Sicily".into(),
+            center: GeoCenter::Member(b"Rome".to_vec()),
+            shape: GeoShape::Radius(1.0),
+            unit: GeoUnit::Meters,
+            options: GeoSearchOptions {
+                sort: None,
+                count: None,
+                any: false,
+            },
+            withcoord: false,
+            withdist: false,
+            withhash: false,
+        };
+        assert!(matches!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Error(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_geosearchstore() {
+        let backend = Backend::new();
+        sicily(&backend);
+        let search = |storedist| GeoSearchStore {
+            destination: "near".into(),
+            search: GeoSearch {
+                key: "Sicily".into(),
+                center: GeoCenter::LonLat(15.0, 37.0),
+                shape: GeoShape::Radius(100000.0),
+                unit: GeoUnit::Kilometers,
+                options: GeoSearchOptions {
+                    sort: None,
+                    count: None,
+                    any: false,
+                },
+                withcoord: false,
+                withdist: false,
+                withhash: false,
+            },
+            storedist,
+        };
+
+        assert_eq!(
+            search(false)
+                .execute(&backend, &mut Session::default())
+                .await,
+            RespFrame::Integer(1)
+        );
+        // the stored scores are geohashes, the result is a geo set itself
+        assert_eq!(
+            backend.geopos(b"near", b"Catania"),
+            backend.geopos(b"Sicily", b"Catania")
+        );
+
+        assert_eq!(
+            search(true)
+                .execute(&backend, &mut Session::default())
+                .await,
+            RespFrame::Integer(1)
+        );
+        let dist = backend.zscore(b"near", b"Catania").unwrap();
+        assert!((dist - 56.4413).abs() < 0.001);
+
+        // removing the member with ZREM removes it from the searches too
+        assert_eq!(backend.zrem(b"Sicily", &[b"Catania".to_vec()]), 1);
+        assert_eq!(backend.geopos(b"Sicily", b"Catania"), None);
+        assert_eq!(
+            search(false)
+                .execute(&backend, &mut Session::default())
+                .await,
+            RespFrame::Integer(0)
+        );
+        assert!(!backend.key_exists(b"near"));
+    }
+}
@@ -0,0 +1,126 @@
+use super::{
+    extract_args, extract_integer, validate_command, CommandError, CommandExecutor, Hello,
+};
+use crate::{BulkString, RespArray, RespFrame, RespMap, SimpleError};
+
+impl CommandExecutor for Hello {
+    async fn execute(self, backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        if let Some(protocol) = self.protocol {
+            if !(2..=3).contains(&protocol) {
+                return SimpleError::new("NOPROTO unsupported protocol version").into();
+            }
+            session.set_protocol(protocol as u8);
+        }
+
+        let mode = match backend.cluster.is_enabled() {
+            true => "cluster",
+            false => "standalone",
+        };
+        let fields: Vec<(&str, RespFrame)> = vec![
+            ("server", BulkString::from("redis").into()),
+            (
+                "version",
+                BulkString::from(env!("CARGO_PKG_VERSION")).into(),
+            ),
+            ("proto", RespFrame::Integer(session.protocol() as i64)),
+            ("id", RespFrame::Integer(session.id() as i64)),
+            ("mode", BulkString::from(mode).into()),
+            ("role", BulkString::from("master").into()),
+        ];
+        // RESP2 clients get the same fields as a flat array of name/value
+        if session.protocol() >= 3 {
+            let mut map = RespMap::new();
+            for (name, value) in fields {
+                map.insert(name, value);
+            }
+            map.into()
+        } else {
+            let mut array = Vec::with_capacity(fields.len() * 2);
+            for (name, value) in fields {
+                array.push(BulkString::from(name).into());
+                array.push(value);
+            }
+            RespArray::new(array).into()
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hello"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (None, _) => Ok(Hello { protocol: None }),
+            (Some(protocol), None) => Ok(Hello {
+                protocol: Some(extract_integer(protocol).map_err(|_| {
+                    CommandError::InvalidArgument(
+                        "Protocol version is not an integer or out of range".to_string(),
+                    )
+                })?),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "HELLO only supports the protocol version argument".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecode, Session};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_hello_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$5\r\nhello\r\n$1\r\n3\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Hello = frame.try_into()?;
+        assert_eq!(result.protocol, Some(3));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$5\r\nhello\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Hello = frame.try_into()?;
+        assert_eq!(result.protocol, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_command() {
+        let backend = Backend::new();
+        let mut session = Session::default();
+
+        let cmd = Hello { protocol: None };
+        match cmd.execute(&backend, &mut session).await {
+            RespFrame::Array(array) => {
+                assert_eq!(array[4], BulkString::from("proto").into());
+                assert_eq!(array[5], RespFrame::Integer(2));
+            }
+            frame => panic!("unexpected reply: {:?}", frame),
+        }
+
+        let cmd = Hello { protocol: Some(3) };
+        match cmd.execute(&backend, &mut session).await {
+            RespFrame::Map(map) => assert_eq!(map["proto"], RespFrame::Integer(3)),
+            frame => panic!("unexpected reply: {:?}", frame),
+        }
+        assert_eq!(session.protocol(), 3);
+
+        let cmd = Hello { protocol: Some(4) };
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            SimpleError::new("NOPROTO unsupported protocol version").into()
+        );
+        assert_eq!(session.protocol(), 3);
+    }
+}
@@ -0,0 +1,188 @@
+// <command> HELP: the usage of a command with subcommands, one status line each like
+// Redis replies. GUIs such as RedisInsight ask for it when they connect. The dispatcher
+// answers it for every command below before the command parses its own arguments.
+
+use super::{CommandExecutor, Help};
+use crate::{Backend, RespArray, RespFrame, Session, SimpleString};
+
+const USAGES: &[(&str, &[&str])] = &[
+    (
+        "CLIENT",
+        &[
+            "NO-EVICT (ON|OFF)",
+            "    Protection of the current client connection from eviction.",
+            "NO-TOUCH (ON|OFF)",
+            "    Will not touch LRU/LFU stats when this mode is on.",
+            "PAUSE <timeout> [WRITE|ALL]",
+            "    Hold the commands of the clients for <timeout> milliseconds, the write",
+            "    commands only with WRITE, every command with ALL (the default).",
+            "REPLY (ON|OFF|SKIP)",
+            "    Control the replies sent to the current connection.",
+            "UNPAUSE",
+            "    Stop the current client pause, resuming traffic.",
+        ],
+    ),
+    (
+        "CLUSTER",
+        &[
+            "INFO",
+            "    Return information about the cluster.",
+            "KEYSLOT <key>",
+            "    Return the hash slot for <key>.",
+            "MYID",
+            "    Return the node id.",
+            "SHARDS",
+            "    Return information about slot range mappings and the nodes serving them.",
+            "SLOTS",
+            "    Return information about slots range mappings.",
+        ],
+    ),
+    (
+        "COMMAND",
+        &[
+            "(no subcommand)",
+            "    Return details about all commands.",
+            "COUNT",
+            "    Return the total number of commands.",
+            "INFO [<command-name> ...]",
+            "    Return details about multiple commands, all of them when none is given.",
+            "LIST",
+            "    Return a list of all command names.",
+        ],
+    ),
+    (
+        "CONFIG",
+        &[
+            "GET <pattern> [<pattern> ...]",
+            "    Return parameters matching the glob-like <pattern> and their values.",
+            "SET <directive> <value>",
+            "    Set the configuration <directive> to <value>.",
+        ],
+    ),
+    (
+        "DEBUG",
+        &[
+            "OBJECT <key>",
+            "    Show low level info about the <key> and associated value.",
+            "QUICKSAVE",
+            "    Save the data set to the snapshot file, like SAVE.",
+            "RELOAD",
+            "    Save the data set in the snapshot format and load it back, checking that",
+            "    nothing changed.",
+            "SET-ACTIVE-EXPIRE (0|1)",
+            "    Setting it to 0 disables expiring keys in background when they are not accessed.",
+            "SLEEP <seconds>",
+            "    Stop the server for <seconds>. Decimals allowed.",
+        ],
+    ),
+    (
+        "LATENCY",
+        &[
+            "HISTORY <event>",
+            "    Return time-latency samples for the <event> class.",
+            "LATEST",
+            "    Return the latest latency samples for all events.",
+            "RESET [<event> ...]",
+            "    Reset latency data of one or more <event> classes, all of them by default.",
+        ],
+    ),
+    (
+        "OBJECT",
+        &[
+            "ENCODING <key>",
+            "    Return the kind of internal representation used in order to store the value",
+            "    associated with a <key>.",
+            "FREQ <key>",
+            "    Return the access frequency index of the <key>.",
+            "IDLETIME <key>",
+            "    Return the idle time of the <key>, that is the approximated number of",
+            "    seconds elapsed since the last access to the key.",
+        ],
+    ),
+    (
+        "SLOWLOG",
+        &[
+            "GET [<count>]",
+            "    Return top <count> entries from the slowlog (default: 10).",
+            "LEN",
+            "    Return the length of the slowlog.",
+            "RESET",
+            "    Reset the slowlog.",
+        ],
+    ),
+];
+
+impl CommandExecutor for Help {
+    async fn execute(self, _backend: &Backend, _session: &mut Session) -> RespFrame {
+        let header = format!(
+            "{} <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+            self.command
+        );
+        let lines = std::iter::once(header.as_str())
+            .chain(self.lines.iter().copied())
+            .chain(["HELP", "    Print this help."])
+            .map(|line| SimpleString::new(line).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(lines).into()
+    }
+}
+
+// the HELP of the command when that is what is asked, the command parses anything else
+pub(super) fn parse_help(value: &RespArray) -> Option<Help> {
+    let [RespFrame::BulkString(name), RespFrame::BulkString(subcommand)] = value.as_slice() else {
+        return None;
+    };
+    if !subcommand.eq_ignore_ascii_case(b"help") {
+        return None;
+    }
+    USAGES
+        .iter()
+        .find(|(command, _)| name.eq_ignore_ascii_case(command.as_bytes()))
+        .map(|(command, lines)| Help { command, lines })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, BulkString};
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_help() {
+        let help = parse_help(&command(&["config", "HELP"])).unwrap();
+        let RespFrame::Array(lines) = help.execute(&Backend::new(), &mut Session::default()).await
+        else {
+            panic!("HELP replies with an array");
+        };
+        assert_eq!(
+            lines.first(),
+            Some(
+                &SimpleString::new(
+                    "CONFIG <subcommand> [<arg> [value] [opt] ...]. Subcommands are:"
+                )
+                .into()
+            )
+        );
+        assert_eq!(
+            lines.last(),
+            Some(&SimpleString::new("    Print this help.").into())
+        );
+
+        assert!(parse_help(&command(&["config", "help", "x"])).is_none());
+        assert!(parse_help(&command(&["get", "help"])).is_none());
+        // every command with a usage has the HELP subcommand in the dispatcher
+        for (name, _) in USAGES {
+            assert!(matches!(
+                Command::try_from(command(&[name, "help"])),
+                Ok(Command::Help(_))
+            ));
+        }
+    }
+}
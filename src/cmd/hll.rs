@@ -0,0 +1,244 @@
+use super::{
+    extract_args, extract_keys, validate_command, CommandError, CommandExecutor, PfAdd, PfCount,
+    PfDebug, PfDebugSubcommand, PfMerge, RESP_OK,
+};
+use crate::{RespArray, RespFrame, SimpleError, SimpleString, NOTIFY_STRING};
+
+impl CommandExecutor for PfAdd {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let changed = backend.pfadd(self.key.clone(), &self.elements);
+        if changed {
+            backend.notify_keyspace_event(NOTIFY_STRING, "pfadd", &self.key);
+        }
+        RespFrame::Integer(changed as i64)
+    }
+}
+
+impl CommandExecutor for PfCount {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.pfcount(&self.keys) as i64)
+    }
+}
+
+impl CommandExecutor for PfMerge {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        backend.pfmerge(self.dest.clone(), &self.sources);
+        backend.notify_keyspace_event(NOTIFY_STRING, "pfadd", &self.dest);
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for PfDebug {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let Some(encoding) = backend.hll_encoding(&self.key) else {
+            return SimpleError::new("ERR The specified key does not exist").into();
+        };
+        match self.subcommand {
+            PfDebugSubcommand::Encoding => SimpleString::new(encoding.as_str()).into(),
+            PfDebugSubcommand::ToDense => {
+                backend.hll_promote(&self.key);
+                RespFrame::Integer((encoding == crate::HllEncoding::Sparse) as i64)
+            }
+            PfDebugSubcommand::GetReg => {
+                let registers = backend
+                    .value::<crate::HyperLogLog>(&self.key)
+                    .map(|hll| hll.registers())
+                    .unwrap_or_default();
+                RespArray::new(
+                    registers
+                        .into_iter()
+                        .map(|r| RespFrame::Integer(r as i64))
+                        .collect::<Vec<_>>(),
+                )
+                .into()
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PfAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pfadd"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match args.next() {
+            Some(RespFrame::BulkString(key)) => {
+                let elements = args
+                    .map(|e| match e {
+                        RespFrame::BulkString(e) => Ok(Vec::from(e.0)),
+                        _ => Err(CommandError::InvalidArgument("Invalid element".to_string())),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(PfAdd {
+                    key: key.into(),
+                    elements,
+                })
+            }
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PfCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pfcount"])?;
+
+        let keys = extract_keys(extract_args(value, 1)?)?;
+        if keys.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "pfcount command must have at least 1 key".to_string(),
+            ));
+        }
+        Ok(PfCount { keys })
+    }
+}
+
+impl TryFrom<RespArray> for PfMerge {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pfmerge"])?;
+
+        let mut keys = extract_keys(extract_args(value, 1)?)?.into_iter();
+        match keys.next() {
+            Some(dest) => Ok(PfMerge {
+                dest,
+                sources: keys.collect(),
+            }),
+            None => Err(CommandError::InvalidArgument(
+                "Invalid destination key".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PfDebug {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pfdebug"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(subcommand)), Some(RespFrame::BulkString(key))) => {
+                let subcommand = match subcommand.to_ascii_lowercase().as_slice() {
+                    b"encoding" => PfDebugSubcommand::Encoding,
+                    b"todense" => PfDebugSubcommand::ToDense,
+                    b"getreg" => PfDebugSubcommand::GetReg,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(format!(
+                            "Unknown PFDEBUG subcommand '{}'",
+                            String::from_utf8_lossy(&subcommand)
+                        )))
+                    }
+                };
+                Ok(PfDebug {
+                    subcommand,
+                    key: key.into(),
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid subcommand or key".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Session;
+    use crate::{Backend, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_pfadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\npfadd\r\n$3\r\nhll\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: PfAdd = frame.try_into()?;
+        assert_eq!(result.key, "hll");
+        assert_eq!(result.elements, vec![b"a".to_vec(), b"b".to_vec()]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pfadd_pfcount_commands() {
+        let backend = Backend::new();
+        let cmd = PfAdd {
+            key: "hll".into(),
+            elements: vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(1)
+        );
+
+        let cmd = PfAdd {
+            key: "hll".into(),
+            elements: vec![b"a".to_vec()],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(0)
+        );
+
+        let cmd = PfCount {
+            keys: vec!["hll".into()],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(3)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pfmerge_command() {
+        let backend = Backend::new();
+        backend.pfadd("a".into(), &[b"1".to_vec(), b"2".to_vec()]);
+        backend.pfadd("b".into(), &[b"2".to_vec(), b"3".to_vec()]);
+
+        let cmd = PfMerge {
+            dest: "c".into(),
+            sources: vec!["a".into(), "b".to_string().into()],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.pfcount(&["c".into()]), 3);
+    }
+
+    #[tokio::test]
+    async fn test_pfdebug_encoding_promotion() {
+        let backend = Backend::new();
+        backend.pfadd("hll".into(), &[b"a".to_vec()]);
+
+        let cmd = PfDebug {
+            subcommand: PfDebugSubcommand::Encoding,
+            key: "hll".into(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            SimpleString::new("sparse").into()
+        );
+
+        let elements = (0..5000)
+            .map(|i| format!("{}", i).into_bytes())
+            .collect::<Vec<_>>();
+        backend.pfadd("hll".into(), &elements);
+
+        let cmd = PfDebug {
+            subcommand: PfDebugSubcommand::Encoding,
+            key: "hll".into(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            SimpleString::new("dense").into()
+        );
+    }
+}
@@ -1,8 +1,16 @@
-use super::{extract_args, validate_command, CommandExecutor, HGet, HGetAll, HMGet, HSet, RESP_OK};
-use crate::{cmd::CommandError, BulkString, RespArray, RespFrame};
+use super::{
+    extract_args, extract_integer, validate_command, CommandExecutor, HGet, HGetAll, HMGet,
+    HRandField, HSet, HSetNx, RESP_OK,
+};
+use std::cmp::Ordering;
+
+use crate::{
+    cmd::CommandError, DecoderLimits, FieldMap, Key, RespArray, RespFrame, RespNullArray,
+    NOTIFY_HASH,
+};
 
 impl CommandExecutor for HGet {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
         match backend.hget(&self.key, &self.field) {
             Some(value) => value,
             None => RespFrame::Null(crate::RespNull),
@@ -11,22 +19,19 @@ impl CommandExecutor for HGet {
 }
 
 impl CommandExecutor for HGetAll {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
-        let hmap = backend.hmap.get(&self.key);
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let hmap = backend.value::<FieldMap>(&self.key);
 
         match hmap {
             Some(hmap) => {
-                let mut data = Vec::with_capacity(hmap.len());
-                for v in hmap.iter() {
-                    let key = v.key().to_owned();
-                    data.push((key, v.value().clone()));
-                }
+                // fields in the order they were first set, like a small hash in Redis
+                let mut data: Vec<_> = hmap.iter().collect();
                 if self.sort {
-                    data.sort_by(|a, b| a.0.cmp(&b.0));
+                    data.sort_by(|a, b| a.0.partial_cmp(b.0).unwrap_or(Ordering::Equal));
                 }
                 let ret = data
                     .into_iter()
-                    .flat_map(|(k, v)| vec![BulkString::from(k).into(), v])
+                    .flat_map(|(k, v)| [k.clone(), v.clone()])
                     .collect::<Vec<RespFrame>>();
 
                 RespArray::new(ret).into()
@@ -37,17 +42,50 @@ impl CommandExecutor for HGetAll {
 }
 
 impl CommandExecutor for HSet {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
-        backend.hset(self.key, self.field, self.value);
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        backend.hset(self.key.clone(), self.field, self.value);
+        backend.notify_keyspace_event(NOTIFY_HASH, "hset", &self.key);
         RESP_OK.clone()
     }
 }
 
+impl CommandExecutor for HSetNx {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        if !backend.hsetnx(self.key.clone(), self.field, self.value) {
+            return RespFrame::Integer(0);
+        }
+        backend.notify_keyspace_event(NOTIFY_HASH, "hset", &self.key);
+        RespFrame::Integer(1)
+    }
+}
+
+impl CommandExecutor for HRandField {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let Some(count) = self.count else {
+            return match backend
+                .hrandfield(&self.key, 1)
+                .and_then(|e| e.into_iter().next())
+            {
+                Some((field, _)) => field,
+                None => RespFrame::Null(crate::RespNull),
+            };
+        };
+        let entries = backend.hrandfield(&self.key, count).unwrap_or_default();
+        let with_values = self.with_values;
+        let ret = entries
+            .into_iter()
+            .flat_map(|(field, value)| [Some(field), with_values.then_some(value)])
+            .flatten()
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
 impl CommandExecutor for HMGet {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
         match backend.hmget(&self.key, &self.fields) {
             Some(it) => it.into(),
-            None => RespArray::null().into(),
+            None => RespNullArray.into(),
         }
     }
 }
@@ -55,13 +93,13 @@ impl CommandExecutor for HMGet {
 impl TryFrom<RespArray> for HGet {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hget"], 2)?;
+        validate_command(&value, &["hget"])?;
 
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => Ok(HGet {
-                key: String::from_utf8(key.0)?,
-                field: String::from_utf8(field.0)?,
+                key: key.into(),
+                field: Vec::from(field.0),
             }),
             _ => Err(CommandError::InvalidArgument(
                 "Invalid key or field".to_string(),
@@ -73,19 +111,19 @@ impl TryFrom<RespArray> for HGet {
 impl TryFrom<RespArray> for HMGet {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hmget"], value.len() - 1)?;
+        validate_command(&value, &["hmget"])?;
 
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(key)) => {
                 let fields = args
                     .filter_map(|f| match f {
-                        RespFrame::BulkString(f) => Some(String::from_utf8_lossy(&f.0).into()),
+                        RespFrame::BulkString(f) => Some(Vec::from(f.0)),
                         _ => None,
                     })
                     .collect();
                 Ok(HMGet {
-                    key: String::from_utf8_lossy(&key.0).into(),
+                    key: key.into(),
                     fields,
                 })
             }
@@ -99,12 +137,12 @@ impl TryFrom<RespArray> for HMGet {
 impl TryFrom<RespArray> for HGetAll {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hgetall"], 1)?;
+        validate_command(&value, &["hgetall"])?;
 
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(key)) => Ok(HGetAll {
-                key: String::from_utf8(key.0)?,
+                key: key.into(),
                 sort: false,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
@@ -115,27 +153,74 @@ impl TryFrom<RespArray> for HGetAll {
 impl TryFrom<RespArray> for HSet {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hset"], 3)?;
+        validate_command(&value, &["hset"])?;
+        let (key, field, value) = extract_field_value(value)?;
+        Ok(HSet { key, field, value })
+    }
+}
+
+impl TryFrom<RespArray> for HSetNx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hsetnx"])?;
+        let (key, field, value) = extract_field_value(value)?;
+        Ok(HSetNx { key, field, value })
+    }
+}
+
+impl TryFrom<RespArray> for HRandField {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hrandfield"])?;
 
         let mut args = extract_args(value, 1)?.into_iter();
-        match (args.next(), args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
-                Ok(HSet {
-                    key: String::from_utf8(key.0)?,
-                    field: String::from_utf8(field.0)?,
-                    value,
-                })
-            }
-            _ => Err(CommandError::InvalidArgument(
-                "Invalid key, field or value".to_string(),
-            )),
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.into(),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let count = args.next().map(extract_integer).transpose()?;
+        let with_values = match args.next() {
+            None => false,
+            Some(RespFrame::BulkString(arg)) if arg.eq_ignore_ascii_case(b"withvalues") => true,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        // a negative count repeats fields as many times as asked, the reply is built in
+        // memory so it gets no more elements than a client may send in an array
+        let max =
+            DecoderLimits::default().max_aggregate_len as i64 / if with_values { 2 } else { 1 };
+        if matches!(count, Some(count) if count < -max) {
+            return Err(CommandError::InvalidArgument(
+                "value is out of range".to_string(),
+            ));
+        }
+        Ok(HRandField {
+            key,
+            count,
+            with_values,
+        })
+    }
+}
+
+// the key, field and value of HSET and HSETNX
+fn extract_field_value(value: RespArray) -> Result<(Key, Vec<u8>, RespFrame), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    match (args.next(), args.next(), args.next()) {
+        (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
+            Ok((key.into(), Vec::from(field.0), value))
         }
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid key, field or value".to_string(),
+        )),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::RespDecode;
+    use crate::{BulkString, Session};
 
     use super::*;
     use anyhow::Result;
@@ -150,7 +235,7 @@ mod tests {
 
         let result: HGet = frame.try_into()?;
         assert_eq!(result.key, "map");
-        assert_eq!(result.field, "hello");
+        assert_eq!(result.field, b"hello");
 
         Ok(())
     }
@@ -177,42 +262,42 @@ mod tests {
 
         let result: HSet = frame.try_into()?;
         assert_eq!(result.key, "map");
-        assert_eq!(result.field, "hello");
+        assert_eq!(result.field, b"hello");
         assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
 
         Ok(())
     }
 
-    #[test]
-    fn test_hset_hget_hgetall_commands() -> Result<()> {
+    #[tokio::test]
+    async fn test_hset_hget_hgetall_commands() -> Result<()> {
         let backend = crate::Backend::new();
         let cmd = HSet {
-            key: "map".to_string(),
-            field: "hello".to_string(),
+            key: "map".into(),
+            field: "hello".into(),
             value: RespFrame::BulkString(b"world".into()),
         };
-        let result = cmd.execute(&backend);
+        let result = cmd.execute(&backend, &mut Session::default()).await;
         assert_eq!(result, RESP_OK.clone());
 
         let cmd = HSet {
-            key: "map".to_string(),
-            field: "hello1".to_string(),
+            key: "map".into(),
+            field: "hello1".into(),
             value: RespFrame::BulkString(b"world1".into()),
         };
-        cmd.execute(&backend);
+        cmd.execute(&backend, &mut Session::default()).await;
 
         let cmd = HGet {
-            key: "map".to_string(),
-            field: "hello".to_string(),
+            key: "map".into(),
+            field: "hello".into(),
         };
-        let result = cmd.execute(&backend);
+        let result = cmd.execute(&backend, &mut Session::default()).await;
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         let cmd = HGetAll {
-            key: "map".to_string(),
+            key: "map".into(),
             sort: true,
         };
-        let result = cmd.execute(&backend);
+        let result = cmd.execute(&backend, &mut Session::default()).await;
 
         let expected = RespArray::new([
             BulkString::from("hello").into(),
@@ -224,6 +309,27 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_hgetall_insertion_order() -> Result<()> {
+        let backend = crate::Backend::new();
+        for (field, value) in [("b", "1"), ("a", "2"), ("c", "3"), ("b", "4")] {
+            backend.hset("hash".into(), field.into(), BulkString::from(value).into());
+        }
+        let cmd = HGetAll {
+            key: "hash".into(),
+            sort: false,
+        };
+        let result = cmd.execute(&backend, &mut Session::default()).await;
+        // an updated field keeps its position
+        let expected = RespArray::new(
+            ["b", "4", "a", "2", "c", "3"]
+                .map(|s| BulkString::from(s).into())
+                .to_vec(),
+        );
+        assert_eq!(result, expected.into());
+        Ok(())
+    }
+
     #[test]
     fn test_hmget_from_resp_array() -> Result<()> {
         let mut buf = BytesMut::new();
@@ -233,33 +339,33 @@ mod tests {
 
         let result: HMGet = frame.try_into()?;
         assert_eq!(result.key, "hash");
-        assert_eq!(result.fields, vec!["hello", "world"]);
+        assert_eq!(result.fields, [b"hello", b"world"]);
 
         Ok(())
     }
 
-    #[test]
-    fn test_hmget_hset_commands() -> Result<()> {
+    #[tokio::test]
+    async fn test_hmget_hset_commands() -> Result<()> {
         let backend = crate::Backend::new();
         let cmd = HSet {
-            key: "hash".to_string(),
-            field: "field1".to_string(),
+            key: "hash".into(),
+            field: "field1".into(),
             value: RespFrame::BulkString(b"hello".into()),
         };
-        cmd.execute(&backend);
+        cmd.execute(&backend, &mut Session::default()).await;
 
         let cmd = HSet {
-            key: "hash".to_string(),
-            field: "field2".to_string(),
+            key: "hash".into(),
+            field: "field2".into(),
             value: RespFrame::BulkString(b"world".into()),
         };
-        cmd.execute(&backend);
+        cmd.execute(&backend, &mut Session::default()).await;
 
         let cmd = HMGet {
-            key: "hash".to_string(),
-            fields: vec!["field1".to_string(), "field2".to_string()],
+            key: "hash".into(),
+            fields: vec!["field1".into(), "field2".to_string().into()],
         };
-        let result = cmd.execute(&backend);
+        let result = cmd.execute(&backend, &mut Session::default()).await;
 
         let expected = RespArray::new([
             BulkString::from("hello").into(),
@@ -268,4 +374,104 @@ mod tests {
         assert_eq!(result, expected.into());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_hsetnx_command() -> Result<()> {
+        let backend = crate::Backend::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nhsetnx\r\n$4\r\nhash\r\n$1\r\nf\r\n$1\r\n1\r\n");
+        let cmd: HSetNx = RespArray::decode(&mut buf)?.try_into()?;
+        let result = cmd.execute(&backend, &mut Session::default()).await;
+        assert_eq!(result, RespFrame::Integer(1));
+
+        let cmd = HSetNx {
+            key: "hash".into(),
+            field: "f".into(),
+            value: BulkString::from("2").into(),
+        };
+        let result = cmd.execute(&backend, &mut Session::default()).await;
+        assert_eq!(result, RespFrame::Integer(0));
+        assert_eq!(
+            backend.hget(b"hash", b"f"),
+            Some(BulkString::from("1").into())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hrandfield_command() -> Result<()> {
+        let backend = crate::Backend::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$10\r\nhrandfield\r\n$4\r\nhash\r\n$2\r\n-5\r\n$10\r\nWITHVALUES\r\n",
+        );
+        let cmd: HRandField = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!((cmd.count, cmd.with_values), (Some(-5), true));
+        // a missing hash is a nil field, or no fields
+        let result = cmd.execute(&backend, &mut Session::default()).await;
+        assert_eq!(result, RespArray::new([]).into());
+        let cmd = HRandField {
+            key: "hash".into(),
+            count: None,
+            with_values: false,
+        };
+        let result = cmd.execute(&backend, &mut Session::default()).await;
+        assert_eq!(result, RespFrame::Null(crate::RespNull));
+
+        for (field, value) in [("a", "1"), ("b", "2"), ("c", "3")] {
+            backend.hset("hash".into(), field.into(), BulkString::from(value).into());
+        }
+        let fields = |frame: RespFrame| match frame {
+            RespFrame::Array(array) => array.0,
+            frame => panic!("not an array: {:?}", frame),
+        };
+
+        // distinct fields, never more than the hash has
+        for count in [2, 10] {
+            let cmd = HRandField {
+                key: "hash".into(),
+                count: Some(count),
+                with_values: false,
+            };
+            let mut result = fields(cmd.execute(&backend, &mut Session::default()).await);
+            let len = result.len();
+            assert_eq!(len, (count as usize).min(3));
+            result.sort();
+            result.dedup();
+            assert_eq!(result.len(), len);
+        }
+
+        // a negative count repeats fields, each with its value
+        let cmd = HRandField {
+            key: "hash".into(),
+            count: Some(-10),
+            with_values: true,
+        };
+        let result = fields(cmd.execute(&backend, &mut Session::default()).await);
+        assert_eq!(result.len(), 20);
+        for pair in result.chunks(2) {
+            let RespFrame::BulkString(field) = &pair[0] else {
+                panic!("not a field: {:?}", pair[0]);
+            };
+            assert_eq!(backend.hget(b"hash", field).as_ref(), Some(&pair[1]));
+        }
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$10\r\nhrandfield\r\n$4\r\nhash\r\n$1\r\n1\r\n$3\r\nfoo\r\n");
+        assert!(HRandField::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        // a count the reply can't hold is refused before anything is allocated
+        for count in ["-9223372036854775808", "-1000000000000"] {
+            let cmd = RespArray::new(
+                ["hrandfield", "hash", count]
+                    .map(|arg| BulkString::from(arg).into())
+                    .to_vec(),
+            );
+            assert!(matches!(
+                HRandField::try_from(cmd),
+                Err(CommandError::InvalidArgument(e)) if e == "value is out of range"
+            ));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,128 @@
+use super::{
+    extract_args, extract_integer, validate_command, CommandError, CommandExecutor, Hotkeys,
+    HotkeysSubcommand, RESP_OK,
+};
+use crate::{RespArray, RespFrame, SimpleError};
+
+const HOTKEYS_DEFAULT_COUNT: usize = 10;
+
+impl CommandExecutor for Hotkeys {
+    // HOTKEYS [count]: the most accessed keys, each as [key, hits, misses, writes]
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match self.subcommand {
+            HotkeysSubcommand::Get(count) => {
+                if backend.key_stats.sample_rate() == 0 && backend.key_stats.is_empty() {
+                    return SimpleError::new(
+                        "ERR key stats are disabled, set key-stats-sample-rate to enable them",
+                    )
+                    .into();
+                }
+                let keys = backend.key_stats.top(count);
+                RespArray::new(
+                    keys.into_iter()
+                        .map(|(key, counts)| {
+                            RespArray::new(vec![
+                                key.into(),
+                                RespFrame::Integer(counts.hits as i64),
+                                RespFrame::Integer(counts.misses as i64),
+                                RespFrame::Integer(counts.writes as i64),
+                            ])
+                            .into()
+                        })
+                        .collect::<Vec<RespFrame>>(),
+                )
+                .into()
+            }
+            HotkeysSubcommand::Reset => {
+                backend.key_stats.reset();
+                RESP_OK.clone()
+            }
+        }
+    }
+}
+
+// HOTKEYS [count] and HOTKEYS RESET
+impl TryFrom<RespArray> for Hotkeys {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hotkeys"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match (args.next(), args.next()) {
+            (None, _) => HotkeysSubcommand::Get(HOTKEYS_DEFAULT_COUNT),
+            (Some(RespFrame::BulkString(s)), None) if s.eq_ignore_ascii_case(b"reset") => {
+                HotkeysSubcommand::Reset
+            }
+            (Some(count), None) => match extract_integer(count)? {
+                count if count > 0 => HotkeysSubcommand::Get(count as usize),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "count should be greater than 0".to_string(),
+                    ))
+                }
+            },
+            _ => return Err(CommandError::WrongArity("hotkeys".to_string())),
+        };
+        Ok(Hotkeys { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, Key, Session};
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_hotkeys() -> anyhow::Result<()> {
+        assert_eq!(
+            Hotkeys::try_from(command(&["hotkeys"]))?.subcommand,
+            HotkeysSubcommand::Get(HOTKEYS_DEFAULT_COUNT)
+        );
+        assert_eq!(
+            Hotkeys::try_from(command(&["hotkeys", "RESET"]))?.subcommand,
+            HotkeysSubcommand::Reset
+        );
+        assert!(Hotkeys::try_from(command(&["hotkeys", "0"])).is_err());
+        assert!(Hotkeys::try_from(command(&["hotkeys", "1", "2"])).is_err());
+
+        let backend = Backend::new();
+        let mut session = Session::default();
+        let cmd = Hotkeys::try_from(command(&["hotkeys"]))?;
+        assert!(matches!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Error(_)
+        ));
+
+        backend.config_set("key-stats-sample-rate", "1").unwrap();
+        backend.set("hot".into(), BulkString::from("v").into());
+        for _ in 0..3 {
+            backend.record_key_stats(&[Key::from("hot")], false);
+        }
+        backend.record_key_stats(&[Key::from("cold")], false);
+        let cmd = Hotkeys::try_from(command(&["hotkeys", "1"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespArray::new(vec![RespArray::new(vec![
+                BulkString::from("hot").into(),
+                RespFrame::Integer(3),
+                RespFrame::Integer(0),
+                RespFrame::Integer(0),
+            ])
+            .into()])
+            .into()
+        );
+
+        let cmd = Hotkeys::try_from(command(&["hotkeys", "reset"]))?;
+        assert_eq!(cmd.execute(&backend, &mut session).await, RESP_OK.clone());
+        assert!(backend.key_stats.is_empty());
+        Ok(())
+    }
+}
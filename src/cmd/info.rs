@@ -0,0 +1,235 @@
+// INFO [section ...]: the state of the server as lines of field:value under # Section
+// headers. Only the fields there is something behind are given, enough for tools polling
+// INFO, Redis Sentinel among them: it reads role and run_id, the slaveN lines of a master
+// to find its replicas, and the master_* and slave_* fields of these replicas.
+
+use super::{extract_args, validate_command, CommandError, CommandExecutor, Info};
+use crate::{Backend, BulkString, LinkState, RespArray, RespFrame, Session};
+use std::fmt::Write;
+
+// writes the fields of a section
+type Section = fn(&Backend, &mut String);
+
+const SECTIONS: &[(&str, Section)] = &[
+    ("server", server),
+    ("clients", clients),
+    ("memory", memory),
+    ("stats", stats),
+    ("replication", replication),
+    ("keyspace", keyspace),
+];
+
+impl CommandExecutor for Info {
+    async fn execute(self, backend: &Backend, _session: &mut Session) -> RespFrame {
+        let all = self.sections.is_empty()
+            || self
+                .sections
+                .iter()
+                .any(|section| matches!(section.as_str(), "default" | "all" | "everything"));
+        let mut info = String::new();
+        for (name, section) in SECTIONS {
+            if all || self.sections.iter().any(|s| s == name) {
+                if !info.is_empty() {
+                    info.push_str("\r\n");
+                }
+                let mut title = name.to_string();
+                title[..1].make_ascii_uppercase();
+                info.push_str(&format!("# {}\r\n", title));
+                section(backend, &mut info);
+            }
+        }
+        BulkString::from(info).into()
+    }
+}
+
+fn field(info: &mut String, name: &str, value: impl std::fmt::Display) {
+    let _ = write!(info, "{}:{}\r\n", name, value);
+}
+
+fn server(backend: &Backend, info: &mut String) {
+    let mode = if backend.cluster.is_enabled() {
+        "cluster"
+    } else {
+        "standalone"
+    };
+    field(info, "redis_version", env!("CARGO_PKG_VERSION"));
+    field(info, "redis_mode", mode);
+    field(info, "process_id", std::process::id());
+    field(info, "run_id", backend.run_id());
+    field(info, "tcp_port", backend.port());
+}
+
+fn clients(backend: &Backend, info: &mut String) {
+    field(info, "connected_clients", backend.stats().connected_clients);
+    field(info, "maxclients", backend.maxclients());
+}
+
+fn memory(backend: &Backend, info: &mut String) {
+    field(info, "used_memory", backend.used_memory());
+    field(info, "maxmemory", backend.maxmemory());
+    field(info, "maxmemory_policy", backend.maxmemory_policy().name());
+}
+
+fn stats(backend: &Backend, info: &mut String) {
+    let stats = backend.stats();
+    field(info, "total_connections_received", stats.total_connections);
+    field(info, "total_commands_processed", stats.total_commands);
+    field(info, "rejected_connections", stats.rejected_connections);
+    field(info, "expired_keys", stats.expired_keys);
+    field(info, "evicted_keys", stats.evicted_keys);
+    field(info, "keyspace_hits", stats.keyspace_hits);
+    field(info, "keyspace_misses", stats.keyspace_misses);
+    // a shard far fuller than keys / shards is a hot spot, see ShardStats
+    field(info, "keyspace_shards", stats.shards.shards);
+    field(info, "keyspace_shard_max_keys", stats.shards.max_keys);
+}
+
+fn replication(backend: &Backend, info: &mut String) {
+    let replication = &backend.replication;
+    let replicas = replication.replicas();
+    match replication.master() {
+        Some(master) => {
+            let up = master.state == LinkState::Connected;
+            field(info, "role", "slave");
+            field(info, "master_host", &master.host);
+            field(info, "master_port", master.port);
+            field(info, "master_link_status", if up { "up" } else { "down" });
+            field(
+                info,
+                "master_sync_in_progress",
+                u8::from(master.state == LinkState::Sync),
+            );
+            field(info, "slave_repl_offset", master.offset.max(0));
+            // never promoted below the default priority, there is no setting for it
+            field(info, "slave_priority", 100);
+            field(info, "slave_read_only", u8::from(replication.read_only()));
+        }
+        None => field(info, "role", "master"),
+    }
+    field(info, "connected_slaves", replicas.len());
+    for (i, replica) in replicas.iter().enumerate() {
+        let (ip, port) = replica
+            .addr
+            .rsplit_once(':')
+            .unwrap_or((replica.addr.as_str(), ""));
+        let port = replica
+            .listening_port
+            .map(|port| port.to_string())
+            .unwrap_or_else(|| port.to_string());
+        field(
+            info,
+            &format!("slave{}", i),
+            format!(
+                "ip={},port={},state=online,offset={}",
+                ip, port, replica.ack_offset
+            ),
+        );
+    }
+    field(info, "master_replid", replication.replid());
+    field(info, "master_repl_offset", replication.offset());
+    field(
+        info,
+        "repl_backlog_active",
+        u8::from(replication.is_active()),
+    );
+    field(info, "repl_backlog_size", replication.backlog_size());
+}
+
+fn keyspace(backend: &Backend, info: &mut String) {
+    let keys = backend.dbsize();
+    // like Redis, an empty database isn't listed
+    if keys > 0 {
+        field(
+            info,
+            "db0",
+            format!("keys={},expires={}", keys, backend.volatile_keys()),
+        );
+    }
+}
+
+// INFO [section [section ...]], the sections are case-insensitive
+impl TryFrom<RespArray> for Info {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["info"])?;
+        let sections = extract_args(value, 1)?
+            .into_iter()
+            .map(|arg| match arg {
+                RespFrame::BulkString(s) => Ok(String::from_utf8_lossy(&s).to_ascii_lowercase()),
+                _ => Err(CommandError::InvalidArgument("Invalid section".to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Info { sections })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::{PSync, ReplConf};
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    async fn info(backend: &Backend, args: &[&str]) -> String {
+        let info: Info = command(args).try_into().unwrap();
+        match info.execute(backend, &mut Session::default()).await {
+            RespFrame::BulkString(s) => String::from_utf8(Vec::from(s.0)).unwrap(),
+            frame => panic!("unexpected reply {:?}", frame),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_info() {
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("1").into());
+        backend.set("b".into(), BulkString::from("2").into());
+        backend.expire(&"b".into(), u64::MAX);
+
+        let all = info(&backend, &["info"]).await;
+        assert!(all.starts_with("# Server\r\n"));
+        assert!(all.contains(&format!("run_id:{}\r\n", backend.run_id())));
+        assert!(all.contains("\r\n\r\n# Replication\r\nrole:master\r\nconnected_slaves:0\r\n"));
+        assert!(all.ends_with("# Keyspace\r\ndb0:keys=2,expires=1\r\n"));
+        let shards = backend.shard_stats();
+        assert!(all.contains(&format!(
+            "keyspace_shards:{}\r\nkeyspace_shard_max_keys:{}\r\n",
+            shards.shards, shards.max_keys
+        )));
+
+        let some = info(&backend, &["info", "KEYSPACE", "server"]).await;
+        assert!(some.starts_with("# Server\r\n"));
+        assert!(some.contains("# Keyspace\r\n"));
+        assert!(!some.contains("# Replication"));
+        assert_eq!(info(&backend, &["info", "nope"]).await, "");
+    }
+
+    #[tokio::test]
+    async fn test_info_replication() {
+        let backend = Backend::new();
+        let mut replica = Session::new("127.0.0.1:50000", tokio::sync::mpsc::unbounded_channel().0);
+        ReplConf::ListeningPort(6380)
+            .execute(&backend, &mut replica)
+            .await;
+        ReplConf::IpAddress("10.0.0.2".to_string())
+            .execute(&backend, &mut replica)
+            .await;
+        let psync: PSync = command(&["psync", "?", "-1"]).try_into().unwrap();
+        let (_head, _stream) = psync.start(&backend, &replica).await;
+        ReplConf::Ack(7).execute(&backend, &mut replica).await;
+
+        let replication = info(&backend, &["info", "replication"]).await;
+        assert!(replication.contains(
+            "role:master\r\nconnected_slaves:1\r\nslave0:ip=10.0.0.2,port=6380,state=online,offset=7\r\n"
+        ));
+        assert!(replication.contains(&format!(
+            "master_replid:{}\r\nmaster_repl_offset:0\r\nrepl_backlog_active:1\r\n",
+            backend.replication.replid()
+        )));
+    }
+}
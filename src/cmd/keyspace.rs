@@ -0,0 +1,497 @@
+use super::{
+    extract_args, extract_integer, extract_keys, validate_command, CommandError, CommandExecutor,
+    CopyKey, Del, Dump, Exists, Migrate, Restore, Touch, Type, Unlink, RESP_OK,
+};
+use crate::{
+    migrate::{migrate, MigrateOptions},
+    now_ms, BulkString, Key, RespArray, RespFrame, SimpleError, SimpleString, NOTIFY_GENERIC,
+};
+use std::time::Duration;
+
+// what Redis uses when MIGRATE is given a timeout of 0
+const DEFAULT_MIGRATE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+impl CommandExecutor for Exists {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.exists(&self.keys) as i64)
+    }
+}
+
+impl CommandExecutor for Type {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let key_type = backend.key_type(&self.key);
+        SimpleString::new(key_type.map_or("none", |t| t.as_str())).into()
+    }
+}
+
+impl CommandExecutor for Del {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        deleted_reply(backend, backend.del(&self.keys))
+    }
+}
+
+impl CommandExecutor for Unlink {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        deleted_reply(backend, backend.unlink(&self.keys))
+    }
+}
+
+impl CommandExecutor for Touch {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.touch(&self.keys) as i64)
+    }
+}
+
+impl CommandExecutor for CopyKey {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        if self.source == self.destination {
+            return SimpleError::new("ERR source and destination objects are the same").into();
+        }
+        let copied = backend.copy(&self.source, &self.destination, self.replace);
+        if copied {
+            backend.notify_keyspace_event(NOTIFY_GENERIC, "copy_to", &self.destination);
+        }
+        RespFrame::Integer(copied as i64)
+    }
+}
+
+impl CommandExecutor for Dump {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.dump(&self.key) {
+            Some(payload) => BulkString::new(payload).into(),
+            None => BulkString::null().into(),
+        }
+    }
+}
+
+impl CommandExecutor for Restore {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.restore(&self.key, &self.payload, self.replace) {
+            Ok(()) => {
+                if self.ttl > 0 {
+                    backend.expire(&self.key, now_ms().saturating_add(self.ttl));
+                }
+                backend.notify_keyspace_event(NOTIFY_GENERIC, "restore", &self.key);
+                RESP_OK.clone()
+            }
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for Migrate {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match migrate(backend, &self.host, self.port, &self.keys, self.options).await {
+            Ok(migrated) if migrated.is_empty() => SimpleString::new("NOKEY").into(),
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+fn deleted_reply(backend: &crate::Backend, deleted: Vec<Key>) -> RespFrame {
+    for key in deleted.iter() {
+        backend.notify_keyspace_event(NOTIFY_GENERIC, "del", key);
+    }
+    RespFrame::Integer(deleted.len() as i64)
+}
+
+impl TryFrom<RespArray> for Exists {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Exists {
+            keys: extract_command_keys(value, "exists")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Type {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut keys = extract_command_keys(value, "type")?;
+        if keys.len() != 1 {
+            return Err(CommandError::WrongArity("type".to_string()));
+        }
+        Ok(Type {
+            key: keys.remove(0),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Del {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Del {
+            keys: extract_command_keys(value, "del")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unlink {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Unlink {
+            keys: extract_command_keys(value, "unlink")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Touch {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Touch {
+            keys: extract_command_keys(value, "touch")?,
+        })
+    }
+}
+
+// COPY source destination [DB destination-db] [REPLACE], there is only database 0
+impl TryFrom<RespArray> for CopyKey {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["copy"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (Some(source), Some(destination)) = (args.next(), args.next()) else {
+            return Err(CommandError::WrongArity("copy".to_string()));
+        };
+        let mut keys = extract_keys(vec![source, destination])?.into_iter();
+        let (source, destination) = (keys.next().unwrap(), keys.next().unwrap());
+        let mut replace = false;
+        while let Some(arg) = args.next() {
+            match &arg {
+                RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"replace") => replace = true,
+                RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"db") => {
+                    let db = args.next().map(extract_integer).transpose()?;
+                    match db {
+                        Some(0) => {}
+                        Some(_) => {
+                            return Err(CommandError::InvalidArgument(
+                                "DB index is out of range".to_string(),
+                            ))
+                        }
+                        None => {
+                            return Err(CommandError::InvalidArgument("syntax error".to_string()))
+                        }
+                    }
+                }
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(CopyKey {
+            source,
+            destination,
+            replace,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Dump {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["dump"])?;
+
+        let key = extract_keys(extract_args(value, 1)?)?.remove(0);
+        Ok(Dump { key })
+    }
+}
+
+// RESTORE key ttl payload [REPLACE], the ttl in milliseconds with 0 for none
+impl TryFrom<RespArray> for Restore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["restore"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (Some(key), Some(ttl), Some(RespFrame::BulkString(payload))) =
+            (args.next(), args.next(), args.next())
+        else {
+            return Err(CommandError::WrongArity("restore".to_string()));
+        };
+        let key = extract_keys(vec![key])?.remove(0);
+        let ttl = match extract_integer(ttl)? {
+            ttl if ttl < 0 => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid TTL value, must be >= 0".to_string(),
+                ))
+            }
+            ttl => ttl as u64,
+        };
+        let mut replace = false;
+        for arg in args {
+            match &arg {
+                RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"replace") => replace = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(Restore {
+            key,
+            ttl,
+            payload: Vec::from(payload.0),
+            replace,
+        })
+    }
+}
+
+// MIGRATE host port key|"" destination-db timeout [COPY] [REPLACE] [KEYS key ...]
+impl TryFrom<RespArray> for Migrate {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["migrate"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (Some(host), Some(port), Some(key), Some(db), Some(timeout)) = (
+            args.next(),
+            args.next(),
+            args.next(),
+            args.next(),
+            args.next(),
+        ) else {
+            return Err(CommandError::WrongArity("migrate".to_string()));
+        };
+        let RespFrame::BulkString(host) = host else {
+            return Err(CommandError::InvalidArgument("Invalid host".to_string()));
+        };
+        let host = String::from_utf8(Vec::from(host.0))?;
+        let key = extract_keys(vec![key])?.remove(0);
+        let port = u16::try_from(extract_integer(port)?)
+            .map_err(|_| CommandError::InvalidArgument("invalid port".to_string()))?;
+        if extract_integer(db)? != 0 {
+            return Err(CommandError::InvalidArgument(
+                "DB index is out of range".to_string(),
+            ));
+        }
+        let timeout = match extract_integer(timeout)? {
+            ms if ms <= 0 => DEFAULT_MIGRATE_TIMEOUT,
+            ms => Duration::from_millis(ms as u64),
+        };
+        let mut options = MigrateOptions {
+            copy: false,
+            replace: false,
+            timeout,
+        };
+        let mut keys = vec![key];
+        while let Some(arg) = args.next() {
+            match &arg {
+                RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"copy") => options.copy = true,
+                RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"replace") => {
+                    options.replace = true
+                }
+                RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"keys") => {
+                    if !keys[0].is_empty() {
+                        return Err(CommandError::InvalidArgument(
+                            "When using MIGRATE KEYS option, the key argument must be set to the empty string".to_string(),
+                        ));
+                    }
+                    keys = extract_keys(args.by_ref().collect())?;
+                }
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        if keys.iter().any(|key| key.is_empty()) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(Migrate {
+            host,
+            port,
+            keys,
+            options,
+        })
+    }
+}
+
+// all the arguments are keys, at least one
+fn extract_command_keys(value: RespArray, name: &'static str) -> Result<Vec<Key>, CommandError> {
+    validate_command(&value, &[name])?;
+
+    let keys = extract_keys(extract_args(value, 1)?)?;
+    if keys.is_empty() {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have at least 1 key",
+            name
+        )));
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, RespDecode, Session};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_del_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$3\r\ndel\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Del = frame.try_into()?;
+        assert_eq!(result.keys, ["a", "b"]);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$6\r\nexists\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(Exists::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_copy_command() -> Result<()> {
+        let command = |args: &[&str]| {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+        };
+        let copy: CopyKey = command(&["copy", "a", "b", "DB", "0", "replace"]).try_into()?;
+        assert!(copy.replace);
+        assert!(CopyKey::try_from(command(&["copy", "a"])).is_err());
+        assert!(CopyKey::try_from(command(&["copy", "a", "b", "db", "1"])).is_err());
+        assert!(CopyKey::try_from(command(&["copy", "a", "b", "nx"])).is_err());
+
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("1").into());
+        backend.set("b".into(), BulkString::from("2").into());
+        let copy: CopyKey = command(&["copy", "a", "b"]).try_into()?;
+        assert_eq!(
+            copy.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(0)
+        );
+        let copy: CopyKey = command(&["copy", "a", "b", "replace"]).try_into()?;
+        assert_eq!(
+            copy.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(backend.get(b"b"), Some(BulkString::from("1").into()));
+        let copy: CopyKey = command(&["copy", "a", "a"]).try_into()?;
+        assert!(matches!(
+            copy.execute(&backend, &mut Session::default()).await,
+            RespFrame::Error(_)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dump_restore_commands() -> Result<()> {
+        let command = |args: Vec<BulkString>| {
+            RespArray::new(args.into_iter().map(RespFrame::from).collect::<Vec<_>>())
+        };
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("1").into());
+        let dump: Dump = command(vec!["dump".into(), "a".into()]).try_into()?;
+        let RespFrame::BulkString(payload) = dump.execute(&backend, &mut Session::default()).await
+        else {
+            panic!("DUMP should reply with a bulk string");
+        };
+        let dump: Dump = command(vec!["dump".into(), "missing".into()]).try_into()?;
+        assert_eq!(
+            dump.execute(&backend, &mut Session::default()).await,
+            BulkString::null().into()
+        );
+
+        let restore = |key: &str, ttl: &str, extra: &[&str]| {
+            let mut args = vec!["restore".into(), key.into(), ttl.into(), payload.clone()];
+            args.extend(extra.iter().map(|arg| BulkString::from(*arg)));
+            Restore::try_from(command(args))
+        };
+        assert!(restore("b", "-1", &[]).is_err());
+        assert!(restore("b", "0", &["absttl"]).is_err());
+        assert_eq!(
+            restore("b", "0", &[])?
+                .execute(&backend, &mut Session::default())
+                .await,
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.get(b"b"), Some(BulkString::from("1").into()));
+        assert!(matches!(
+            restore("b", "0", &[])?
+                .execute(&backend, &mut Session::default())
+                .await,
+            RespFrame::Error(_)
+        ));
+        assert_eq!(
+            restore("b", "0", &["REPLACE"])?
+                .execute(&backend, &mut Session::default())
+                .await,
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.pttl(b"b"), -1);
+        restore("b", "100000", &["REPLACE"])?
+            .execute(&backend, &mut Session::default())
+            .await;
+        assert!(backend.pttl(b"b") > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_from_resp_array() -> Result<()> {
+        let command = |args: &[&str]| {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+        };
+        let migrate: Migrate = command(&["migrate", "h", "7000", "a", "0", "0"]).try_into()?;
+        assert_eq!(migrate.port, 7000);
+        assert_eq!(migrate.keys, vec!["a"]);
+        assert_eq!(migrate.options.timeout, DEFAULT_MIGRATE_TIMEOUT);
+        let migrate: Migrate = command(&[
+            "migrate", "h", "7000", "", "0", "50", "COPY", "replace", "keys", "a", "b",
+        ])
+        .try_into()?;
+        assert_eq!(migrate.keys, vec!["a", "b"]);
+        assert!(migrate.options.copy && migrate.options.replace);
+        assert_eq!(migrate.options.timeout, Duration::from_millis(50));
+        assert!(Migrate::try_from(command(&["migrate", "h", "7000", "a", "1", "0"])).is_err());
+        assert!(Migrate::try_from(command(&["migrate", "h", "7000", "", "0", "0"])).is_err());
+        assert!(Migrate::try_from(command(&[
+            "migrate", "h", "7000", "a", "0", "0", "keys", "b"
+        ]))
+        .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keyspace_commands() {
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("1").into());
+        backend.sadd("b".into(), vec![BulkString::from("m").into()]);
+        let keys = |keys: &[&str]| keys.iter().map(|k| Key::from(*k)).collect::<Vec<_>>();
+
+        let cmd = Exists {
+            keys: keys(&["a", "b", "a", "c"]),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(3)
+        );
+
+        let cmd = Del {
+            keys: keys(&["a", "a", "c"]),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(1)
+        );
+
+        let cmd = Unlink { keys: keys(&["b"]) };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(1)
+        );
+
+        let cmd = Touch {
+            keys: keys(&["a", "b"]),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(0)
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use super::{
+    extract_args, validate_command, CommandError, CommandExecutor, Latency, LatencySubcommand,
+};
+use crate::{BulkString, RespArray, RespFrame};
+
+impl CommandExecutor for Latency {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match self.subcommand {
+            // [event, time of the latest spike, its latency, the worst latency] per event
+            LatencySubcommand::Latest => {
+                let events = backend.latency.events();
+                RespArray::new(
+                    events
+                        .into_iter()
+                        .filter_map(|(name, event)| {
+                            let latest = event.samples.back()?;
+                            Some(
+                                RespArray::new(vec![
+                                    BulkString::from(name).into(),
+                                    RespFrame::Integer(latest.time as i64),
+                                    RespFrame::Integer(latest.latency_ms as i64),
+                                    RespFrame::Integer(event.max_ms as i64),
+                                ])
+                                .into(),
+                            )
+                        })
+                        .collect::<Vec<RespFrame>>(),
+                )
+                .into()
+            }
+            // [time, latency] per spike, oldest first
+            LatencySubcommand::History(event) => {
+                let samples = backend
+                    .latency
+                    .event(&event)
+                    .map(|event| event.samples)
+                    .unwrap_or_default();
+                RespArray::new(
+                    samples
+                        .into_iter()
+                        .map(|sample| {
+                            RespArray::new(vec![
+                                RespFrame::Integer(sample.time as i64),
+                                RespFrame::Integer(sample.latency_ms as i64),
+                            ])
+                            .into()
+                        })
+                        .collect::<Vec<RespFrame>>(),
+                )
+                .into()
+            }
+            LatencySubcommand::Reset(events) => {
+                RespFrame::Integer(backend.latency.reset(&events) as i64)
+            }
+        }
+    }
+}
+
+// LATENCY LATEST, LATENCY HISTORY event and LATENCY RESET [event ...]
+impl TryFrom<RespArray> for Latency {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["latency"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(RespFrame::BulkString(s)) => s.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid subcommand".to_string(),
+                ))
+            }
+        };
+        let mut events = Vec::new();
+        for arg in args {
+            match arg {
+                RespFrame::BulkString(event) => events.push(String::from_utf8(event.0.to_vec())?),
+                _ => return Err(CommandError::InvalidArgument("Invalid event".to_string())),
+            }
+        }
+        let subcommand = match (subcommand.as_slice(), events.len()) {
+            (b"latest", 0) => LatencySubcommand::Latest,
+            (b"history", 1) => LatencySubcommand::History(events.remove(0)),
+            (b"reset", _) => LatencySubcommand::Reset(events),
+            (s, _) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unknown subcommand or wrong number of arguments for '{}'",
+                    String::from_utf8_lossy(s)
+                )))
+            }
+        };
+        Ok(Latency { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{set_mock_now_ms, Backend, Session, LATENCY_COMMAND};
+    use std::time::Duration;
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_latency_command() -> anyhow::Result<()> {
+        assert_eq!(
+            Latency::try_from(command(&["latency", "HISTORY", "command"]))?.subcommand,
+            LatencySubcommand::History("command".to_string())
+        );
+        assert_eq!(
+            Latency::try_from(command(&["latency", "reset"]))?.subcommand,
+            LatencySubcommand::Reset(vec![])
+        );
+        assert!(Latency::try_from(command(&["latency", "history"])).is_err());
+        assert!(Latency::try_from(command(&["latency", "latest", "command"])).is_err());
+        assert!(Latency::try_from(command(&["latency", "doctor"])).is_err());
+
+        set_mock_now_ms(Some(1_000_000));
+        let backend = Backend::new();
+        let mut session = Session::default();
+        backend
+            .config_set("latency-monitor-threshold", "10")
+            .unwrap();
+        backend
+            .latency
+            .record(LATENCY_COMMAND, Duration::from_millis(50));
+        set_mock_now_ms(Some(1_002_000));
+        backend
+            .latency
+            .record(LATENCY_COMMAND, Duration::from_millis(20));
+
+        let cmd = Latency::try_from(command(&["latency", "latest"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespArray::new(vec![RespArray::new(vec![
+                BulkString::from("command").into(),
+                RespFrame::Integer(1002),
+                RespFrame::Integer(20),
+                RespFrame::Integer(50),
+            ])
+            .into()])
+            .into()
+        );
+
+        let cmd = Latency::try_from(command(&["latency", "history", "command"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespArray::new(vec![
+                RespArray::new(vec![RespFrame::Integer(1000), RespFrame::Integer(50)]).into(),
+                RespArray::new(vec![RespFrame::Integer(1002), RespFrame::Integer(20)]).into(),
+            ])
+            .into()
+        );
+
+        let cmd = Latency::try_from(command(&["latency", "reset", "command", "nope"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(1)
+        );
+        let cmd = Latency::try_from(command(&["latency", "history", "command"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespArray::new(vec![]).into()
+        );
+        set_mock_now_ms(None);
+        Ok(())
+    }
+}
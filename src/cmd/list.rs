@@ -0,0 +1,518 @@
+use super::{
+    extract_args, extract_integer, extract_keys, extract_timeout, validate_command, BLMove, BLPop,
+    BRPop, CommandError, CommandExecutor, LLen, LMove, LPop, LPush, LRange, RPop, RPush, TimeUnit,
+};
+use crate::{
+    Backend, BulkString, Key, ListEnd, RespArray, RespFrame, RespNull, RespNullArray, NOTIFY_LIST,
+};
+use std::time::Duration;
+
+impl CommandExecutor for LPush {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let len = backend.lpush(self.key.clone(), self.values);
+        backend.notify_keyspace_event(NOTIFY_LIST, "lpush", &self.key);
+        RespFrame::Integer(len as i64)
+    }
+}
+
+impl CommandExecutor for RPush {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let len = backend.rpush(self.key.clone(), self.values);
+        backend.notify_keyspace_event(NOTIFY_LIST, "rpush", &self.key);
+        RespFrame::Integer(len as i64)
+    }
+}
+
+impl CommandExecutor for LPop {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        pop(backend, &self.key, self.count, ListEnd::Left)
+    }
+}
+
+impl CommandExecutor for RPop {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        pop(backend, &self.key, self.count, ListEnd::Right)
+    }
+}
+
+impl CommandExecutor for LLen {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.llen(&self.key) as i64)
+    }
+}
+
+impl CommandExecutor for LRange {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespArray::new(backend.lrange(&self.key, self.start, self.stop)).into()
+    }
+}
+
+impl CommandExecutor for BLPop {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let ret = backend
+            .blocking_pop(&self.keys, self.timeout, ListEnd::Left)
+            .await;
+        blocking_pop_reply(backend, ret, ListEnd::Left)
+    }
+}
+
+impl CommandExecutor for BRPop {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let ret = backend
+            .blocking_pop(&self.keys, self.timeout, ListEnd::Right)
+            .await;
+        blocking_pop_reply(backend, ret, ListEnd::Right)
+    }
+}
+
+impl CommandExecutor for LMove {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let ret = backend.lmove(&self.source, &self.destination, self.from, self.to);
+        self.reply(backend, ret)
+    }
+}
+
+impl CommandExecutor for BLMove {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let lmove = self.lmove;
+        let ret = backend
+            .blocking_move(
+                &lmove.source,
+                &lmove.destination,
+                lmove.from,
+                lmove.to,
+                self.timeout,
+            )
+            .await;
+        lmove.reply(backend, ret)
+    }
+}
+
+impl LMove {
+    fn reply(&self, backend: &Backend, ret: Option<RespFrame>) -> RespFrame {
+        match ret {
+            Some(value) => {
+                backend.notify_keyspace_event(NOTIFY_LIST, pop_event(self.from), &self.source);
+                let push = match self.to {
+                    ListEnd::Left => "lpush",
+                    ListEnd::Right => "rpush",
+                };
+                backend.notify_keyspace_event(NOTIFY_LIST, push, &self.destination);
+                value
+            }
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+fn pop(backend: &Backend, key: &[u8], count: Option<usize>, end: ListEnd) -> RespFrame {
+    let ret = backend.pop(key, count.unwrap_or(1), end);
+    if ret.is_some() {
+        backend.notify_keyspace_event(NOTIFY_LIST, pop_event(end), key);
+    }
+    match (ret, count) {
+        (Some(mut values), None) => values.pop().unwrap_or(RespFrame::Null(RespNull)),
+        (Some(values), Some(_)) => RespArray::new(values).into(),
+        (None, None) => RespFrame::Null(RespNull),
+        (None, Some(_)) => RespNullArray.into(),
+    }
+}
+
+fn blocking_pop_reply(backend: &Backend, ret: Option<(Key, RespFrame)>, end: ListEnd) -> RespFrame {
+    match ret {
+        Some((key, value)) => {
+            backend.notify_keyspace_event(NOTIFY_LIST, pop_event(end), &key);
+            RespArray::new(vec![BulkString::from(key).into(), value]).into()
+        }
+        None => RespNullArray.into(),
+    }
+}
+
+fn pop_event(end: ListEnd) -> &'static str {
+    match end {
+        ListEnd::Left => "lpop",
+        ListEnd::Right => "rpop",
+    }
+}
+
+impl TryFrom<RespArray> for LPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lpush"])?;
+        let (key, values) = extract_key_and_values(value)?;
+        Ok(LPush { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for RPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["rpush"])?;
+        let (key, values) = extract_key_and_values(value)?;
+        Ok(RPush { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for LPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lpop"])?;
+        let (key, count) = extract_key_and_count(value)?;
+        Ok(LPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for RPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["rpop"])?;
+        let (key, count) = extract_key_and_count(value)?;
+        Ok(RPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for LLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["llen"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match args.next() {
+            Some(RespFrame::BulkString(key)) => Ok(LLen { key: key.into() }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lrange"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(start), Some(stop)) => Ok(LRange {
+                key: key.into(),
+                start: extract_integer(start)?,
+                stop: extract_integer(stop)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, start or stop".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BLPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["blpop"])?;
+        let (keys, timeout) = extract_keys_and_timeout(value)?;
+        Ok(BLPop { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BRPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["brpop"])?;
+        let (keys, timeout) = extract_keys_and_timeout(value)?;
+        Ok(BRPop { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for LMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let rpoplpush = matches!(value.first(), Some(RespFrame::BulkString(name)) if name.eq_ignore_ascii_case(b"rpoplpush"));
+        validate_command(&value, &[if rpoplpush { "rpoplpush" } else { "lmove" }])?;
+        let args = extract_args(value, 1)?;
+        extract_move(args, rpoplpush)
+    }
+}
+
+impl TryFrom<RespArray> for BLMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let brpoplpush = matches!(value.first(), Some(RespFrame::BulkString(name)) if name.eq_ignore_ascii_case(b"brpoplpush"));
+        validate_command(&value, &[if brpoplpush { "brpoplpush" } else { "blmove" }])?;
+        let mut args = extract_args(value, 1)?;
+        let timeout = match args.pop() {
+            Some(timeout) => extract_timeout(timeout, TimeUnit::Seconds)?,
+            None => return Err(CommandError::InvalidArgument("Invalid timeout".to_string())),
+        };
+        Ok(BLMove {
+            lmove: extract_move(args, brpoplpush)?,
+            timeout: (!timeout.is_zero()).then_some(timeout),
+        })
+    }
+}
+
+// source destination, then LEFT|RIGHT LEFT|RIGHT unless it is RPOPLPUSH
+fn extract_move(args: Vec<RespFrame>, rpoplpush: bool) -> Result<LMove, CommandError> {
+    let mut args = args.into_iter();
+    let mut keys = extract_keys(args.by_ref().take(2).collect())?.into_iter();
+    let (source, destination) = (keys.next().unwrap(), keys.next().unwrap());
+    let (from, to) = match rpoplpush {
+        true => (ListEnd::Right, ListEnd::Left),
+        false => (extract_end(args.next())?, extract_end(args.next())?),
+    };
+    Ok(LMove {
+        source,
+        destination,
+        from,
+        to,
+    })
+}
+
+fn extract_end(frame: Option<RespFrame>) -> Result<ListEnd, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(end)) if end.eq_ignore_ascii_case(b"left") => Ok(ListEnd::Left),
+        Some(RespFrame::BulkString(end)) if end.eq_ignore_ascii_case(b"right") => {
+            Ok(ListEnd::Right)
+        }
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+fn extract_key_and_values(value: RespArray) -> Result<(Key, Vec<RespFrame>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    match args.next() {
+        Some(RespFrame::BulkString(key)) => {
+            let values: Vec<RespFrame> = args.collect();
+            if values.is_empty() {
+                return Err(CommandError::InvalidArgument(
+                    "command must have at least 1 element".to_string(),
+                ));
+            }
+            Ok((key.into(), values))
+        }
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid key or elements".to_string(),
+        )),
+    }
+}
+
+pub(super) fn extract_key_and_count(
+    value: RespArray,
+) -> Result<(Key, Option<usize>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    match (args.next(), args.next(), args.next()) {
+        (Some(RespFrame::BulkString(key)), count, None) => {
+            let count = match count {
+                Some(count) => {
+                    let count = extract_integer(count)?;
+                    if count < 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "value is out of range, must be positive".to_string(),
+                        ));
+                    }
+                    Some(count as usize)
+                }
+                None => None,
+            };
+            Ok((key.into(), count))
+        }
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid key or count".to_string(),
+        )),
+    }
+}
+
+// the last argument is the timeout in seconds (may be fractional), 0 blocks forever
+pub(super) fn extract_keys_and_timeout(
+    value: RespArray,
+) -> Result<(Vec<Key>, Option<Duration>), CommandError> {
+    let mut args = extract_args(value, 1)?;
+    let timeout = match args.pop() {
+        Some(timeout) => extract_timeout(timeout, TimeUnit::Seconds)?,
+        None => return Err(CommandError::InvalidArgument("Invalid timeout".to_string())),
+    };
+
+    let keys = extract_keys(args)?;
+    if keys.is_empty() {
+        return Err(CommandError::InvalidArgument(
+            "command must have at least 1 key".to_string(),
+        ));
+    }
+    Ok((keys, (!timeout.is_zero()).then_some(timeout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespDecode, Session};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_lpush_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nlpush\r\n$4\r\nlist\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: LPush = frame.try_into()?;
+        assert_eq!(result.key, "list");
+        assert_eq!(
+            result.values,
+            vec![BulkString::from("a").into(), BulkString::from("b").into()]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lpush_binary_key() -> Result<()> {
+        let backend = Backend::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$5\r\nlpush\r\n$3\r\n\xff\x00k\r\n$1\r\na\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: LPush = frame.try_into()?;
+        assert_eq!(result.key.as_bytes(), b"\xff\x00k");
+        result.execute(&backend, &mut Session::default()).await;
+
+        assert_eq!(
+            backend.lrange(b"\xff\x00k", 0, -1),
+            vec![BulkString::from("a").into()]
+        );
+        // the lossy form used for logging is another key
+        assert_eq!(backend.llen("\u{fffd}\u{0}k".as_bytes()), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blpop_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nblpop\r\n$1\r\na\r\n$1\r\nb\r\n$3\r\n0.5\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: BLPop = frame.try_into()?;
+        assert_eq!(result.keys, vec!["a", "b"]);
+        assert_eq!(result.timeout, Some(Duration::from_millis(500)));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$5\r\nblpop\r\n$1\r\na\r\n$2\r\n-1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(BLPop::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmove_from_resp_array() -> Result<()> {
+        let command = |args: &[&str]| {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+        };
+        let lmove: LMove = command(&["lmove", "a", "b", "left", "RIGHT"]).try_into()?;
+        assert_eq!((lmove.from, lmove.to), (ListEnd::Left, ListEnd::Right));
+        let lmove: LMove = command(&["RPOPLPUSH", "a", "b"]).try_into()?;
+        assert_eq!(
+            lmove,
+            LMove {
+                source: "a".into(),
+                destination: "b".into(),
+                from: ListEnd::Right,
+                to: ListEnd::Left,
+            }
+        );
+        assert!(LMove::try_from(command(&["lmove", "a", "b", "up", "left"])).is_err());
+        assert!(LMove::try_from(command(&["rpoplpush", "a", "b", "left"])).is_err());
+
+        let blmove: BLMove = command(&["blmove", "a", "b", "left", "left", "0.5"]).try_into()?;
+        assert_eq!(blmove.timeout, Some(Duration::from_millis(500)));
+        let blmove: BLMove = command(&["brpoplpush", "a", "b", "0"]).try_into()?;
+        assert_eq!(blmove.timeout, None);
+        assert_eq!(blmove.lmove.from, ListEnd::Right);
+        assert!(BLMove::try_from(command(&["blmove", "a", "b", "left", "left"])).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_pop_commands() {
+        let backend = Backend::new();
+        let cmd = RPush {
+            key: "list".into(),
+            values: vec![BulkString::from("a").into(), BulkString::from("b").into()],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(2)
+        );
+
+        let cmd = LRange {
+            key: "list".into(),
+            start: 0,
+            stop: -1,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![
+                BulkString::from("a").into(),
+                BulkString::from("b").into()
+            ])
+            .into()
+        );
+
+        let cmd = RPop {
+            key: "list".into(),
+            count: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            BulkString::from("b").into()
+        );
+
+        let cmd = LPop {
+            key: "list".into(),
+            count: Some(2),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![BulkString::from("a").into()]).into()
+        );
+
+        let cmd = LPop {
+            key: "list".into(),
+            count: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Null(RespNull)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blpop_command() {
+        let backend = Backend::new();
+        let cmd = BLPop {
+            keys: vec!["list".into()],
+            timeout: Some(Duration::from_millis(10)),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespNullArray.into()
+        );
+
+        backend.rpush("list".into(), vec![BulkString::from("a").into()]);
+        let cmd = BLPop {
+            keys: vec!["other".into(), "list".to_string().into()],
+            timeout: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![
+                BulkString::from("list").into(),
+                BulkString::from("a").into()
+            ])
+            .into()
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use super::{validate_command, CommandError, CommandExecutor, Lolwut};
+use crate::{BulkString, RespArray, RespFrame};
+
+const ROWS: usize = 16;
+
+impl CommandExecutor for Lolwut {
+    async fn execute(self, _backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        BulkString::from(art()).into()
+    }
+}
+
+// a Sierpinski triangle and the version, a cell is set where the binomial coefficient of
+// its row and column is odd
+fn art() -> String {
+    let mut art = String::new();
+    for row in 0..ROWS {
+        art.push_str(&" ".repeat(ROWS - row - 1));
+        for col in 0..=row {
+            art.push(if col & row == col { '*' } else { ' ' });
+            art.push(' ');
+        }
+        art.truncate(art.trim_end().len());
+        art.push('\n');
+    }
+    art.push_str(&format!(
+        "\nsimple-redis ver. {}\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    art
+}
+
+// LOLWUT [VERSION version] [...], the arguments only pick and tune the art in Redis
+impl TryFrom<RespArray> for Lolwut {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lolwut"])?;
+        Ok(Lolwut)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, Session};
+
+    #[tokio::test]
+    async fn test_lolwut() {
+        let backend = Backend::new();
+        let RespFrame::BulkString(art) = Lolwut.execute(&backend, &mut Session::default()).await
+        else {
+            panic!("LOLWUT should reply with a bulk string");
+        };
+        let art = String::from_utf8(Vec::from(art.0)).unwrap();
+        let mut lines = art.lines();
+        assert_eq!(
+            lines.next(),
+            Some(format!("{}*", " ".repeat(ROWS - 1)).as_str())
+        );
+        assert!(art.ends_with(&format!("ver. {}\n", env!("CARGO_PKG_VERSION"))));
+    }
+}
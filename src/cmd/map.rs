@@ -1,12 +1,15 @@
-use super::{extract_args, validate_command, CommandExecutor, Set, RESP_OK};
+use super::{
+    extract_args, extract_integer, validate_command, Append, CommandExecutor, GetRange, IncrBy,
+    Set, SetRange, RESP_OK,
+};
 use crate::{
     cmd::{CommandError, Get},
-    RespArray, RespFrame, RespNull,
+    KeyType, RespArray, RespFrame, RespNull, SetCondition, SimpleError, NOTIFY_STRING,
 };
 
 impl CommandExecutor for Get {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
-        match backend.get(&self.key) {
+    async fn execute(self, backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        match backend.get_cached(&self.key, &mut session.get_cache) {
             Some(value) => value,
             None => RespFrame::Null(RespNull),
         }
@@ -14,37 +17,182 @@ impl CommandExecutor for Get {
 }
 
 impl CommandExecutor for Set {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
-        backend.set(self.key, self.value);
-        RESP_OK.clone()
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        // SET replaces any value, but GET can't reply with one of another type
+        if self.get && !backend.keys_have_type(std::slice::from_ref(&self.key), KeyType::String) {
+            return CommandError::WrongType.into();
+        }
+        let (set, old) = backend.set_with(self.key.clone(), self.value, self.condition);
+        if set {
+            backend.notify_keyspace_event(NOTIFY_STRING, "set", &self.key);
+        }
+        match (self.get, set) {
+            (true, _) => old.unwrap_or(RespFrame::Null(RespNull)),
+            (false, true) => RESP_OK.clone(),
+            (false, false) => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for GetRange {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        backend.getrange(&self.key, self.start, self.end).into()
+    }
+}
+
+impl CommandExecutor for SetRange {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let written = !self.value.is_empty();
+        match backend.setrange(self.key.clone(), self.offset, &self.value) {
+            Ok(len) => {
+                if written {
+                    backend.notify_keyspace_event(NOTIFY_STRING, "setrange", &self.key);
+                }
+                RespFrame::Integer(len as i64)
+            }
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for Append {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.append(self.key.clone(), &self.value) {
+            Ok(len) => {
+                backend.notify_keyspace_event(NOTIFY_STRING, "append", &self.key);
+                RespFrame::Integer(len as i64)
+            }
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for IncrBy {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.incr_by(self.key.clone(), self.increment) {
+            Ok(value) => {
+                let event = if self.increment < 0 {
+                    "decrby"
+                } else {
+                    "incrby"
+                };
+                backend.notify_keyspace_event(NOTIFY_STRING, event, &self.key);
+                RespFrame::Integer(value)
+            }
+            Err(e) => SimpleError::new(e).into(),
+        }
     }
 }
 
 impl TryFrom<RespArray> for Get {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["get"], 1)?;
+        validate_command(&value, &["get"])?;
 
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(Get {
-                key: String::from_utf8(key.0)?,
-            }),
+            Some(RespFrame::BulkString(key)) => Ok(Get { key: key.into() }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
     }
 }
 
+// SET key value [NX | XX] [GET]
 impl TryFrom<RespArray> for Set {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["set"], 2)?;
+        validate_command(&value, &["set"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, value) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(value)) => (key.into(), value),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid key or value".to_string(),
+                ))
+            }
+        };
+        let mut condition = SetCondition::Always;
+        let mut get = false;
+        for arg in args {
+            let RespFrame::BulkString(option) = arg else {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            };
+            match option.to_ascii_lowercase().as_slice() {
+                b"nx" if condition != SetCondition::Xx => condition = SetCondition::Nx,
+                b"xx" if condition != SetCondition::Nx => condition = SetCondition::Xx,
+                b"get" => get = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(Set {
+            key,
+            value,
+            condition,
+            get,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GetRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["getrange"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(start), Some(end)) => Ok(GetRange {
+                key: key.into(),
+                start: extract_integer(start)?,
+                end: extract_integer(end)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, start or end".to_string(),
+            )),
+        }
+    }
+}
+
+// SETRANGE key offset value
+impl TryFrom<RespArray> for SetRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["setrange"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(offset),
+                Some(RespFrame::BulkString(value)),
+            ) => {
+                let offset = usize::try_from(extract_integer(offset)?).map_err(|_| {
+                    CommandError::InvalidArgument("offset is out of range".to_string())
+                })?;
+                Ok(SetRange {
+                    key: key.into(),
+                    offset,
+                    value: Vec::from(value.0),
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, offset or value".to_string(),
+            )),
+        }
+    }
+}
+
+// APPEND key value
+impl TryFrom<RespArray> for Append {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["append"])?;
 
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(value)) => Ok(Set {
-                key: String::from_utf8(key.0)?,
-                value,
+            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(value))) => Ok(Append {
+                key: key.into(),
+                value: Vec::from(value.0),
             }),
             _ => Err(CommandError::InvalidArgument(
                 "Invalid key or value".to_string(),
@@ -53,9 +201,45 @@ impl TryFrom<RespArray> for Set {
     }
 }
 
+// INCR key | DECR key | INCRBY key increment | DECRBY key decrement
+impl TryFrom<RespArray> for IncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(name)) => name.to_ascii_lowercase(),
+            _ => Vec::new(),
+        };
+        let name = match name.as_slice() {
+            b"decr" => "decr",
+            b"incrby" => "incrby",
+            b"decrby" => "decrby",
+            _ => "incr",
+        };
+        validate_command(&value, &[name])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.into(),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let increment = match args.next() {
+            Some(increment) => extract_integer(increment)?,
+            None => 1,
+        };
+        let increment = match name {
+            "decr" | "decrby" => increment.checked_neg().ok_or_else(|| {
+                CommandError::InvalidArgument("decrement would overflow".to_string())
+            })?,
+            _ => increment,
+        };
+        Ok(IncrBy { key, increment })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Session;
     use crate::{Backend, RespDecode};
     use anyhow::Result;
     use bytes::BytesMut;
@@ -84,25 +268,101 @@ mod tests {
         assert_eq!(result.key, "hello");
         assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
 
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n$2\r\nNX\r\n$3\r\nget\r\n",
+        );
+        let result: Set = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.condition, SetCondition::Nx);
+        assert!(result.get);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*5\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n$2\r\nnx\r\n$2\r\nxx\r\n");
+        assert!(Set::try_from(RespArray::decode(&mut buf)?).is_err());
+
         Ok(())
     }
 
-    #[test]
-    fn test_set_get_command() -> Result<()> {
+    #[tokio::test]
+    async fn test_set_get_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = Set {
-            key: "hello".to_string(),
+            key: "hello".into(),
             value: RespFrame::BulkString(b"world".into()),
+            condition: SetCondition::Always,
+            get: false,
         };
-        let result = cmd.execute(&backend);
+        let result = cmd.execute(&backend, &mut Session::default()).await;
         assert_eq!(result, RESP_OK.clone());
 
         let cmd = Get {
-            key: "hello".to_string(),
+            key: "hello".into(),
         };
-        let result = cmd.execute(&backend);
+        let result = cmd.execute(&backend, &mut Session::default()).await;
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         Ok(())
     }
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| crate::BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_incr_decr_commands() -> Result<()> {
+        let backend = Backend::new();
+        let mut session = Session::default();
+        for (args, expected) in [
+            (&["incr", "n"][..], 1),
+            (&["INCRBY", "n", "10"][..], 11),
+            (&["decr", "n"][..], 10),
+            (&["decrby", "n", "-5"][..], 15),
+        ] {
+            let cmd = IncrBy::try_from(command(args))?;
+            assert_eq!(
+                cmd.execute(&backend, &mut session).await,
+                RespFrame::Integer(expected)
+            );
+        }
+        assert!(IncrBy::try_from(command(&["incrby", "n", "x"])).is_err());
+        assert!(IncrBy::try_from(command(&["incr", "n", "1"])).is_err());
+        assert!(IncrBy::try_from(command(&["decrby", "n", &i64::MIN.to_string()])).is_err());
+
+        let get = Get { key: "n".into() };
+        assert_eq!(
+            get.execute(&backend, &mut session).await,
+            RespFrame::BulkString(b"15".into())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_setrange_append_commands() -> Result<()> {
+        let backend = Backend::new();
+        let mut session = Session::default();
+        let cmd = SetRange::try_from(command(&["setrange", "k", "6", "world"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(11)
+        );
+        let cmd = Append::try_from(command(&["APPEND", "k", "!"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespFrame::Integer(12)
+        );
+        assert!(SetRange::try_from(command(&["setrange", "k", "-1", "x"])).is_err());
+        assert!(Append::try_from(command(&["append", "k"])).is_err());
+
+        backend.config_set("proto-max-bulk-len", "12").unwrap();
+        let cmd = Append::try_from(command(&["append", "k", "!"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            SimpleError::new(crate::STRING_TOO_LONG_ERROR).into()
+        );
+        Ok(())
+    }
 }
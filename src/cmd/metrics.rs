@@ -0,0 +1,74 @@
+use super::{extract_args, validate_command, CommandError, CommandExecutor, Metrics};
+use crate::{Backend, BulkString, RespArray, RespFrame, RespMap, Session, SimpleString};
+
+impl CommandExecutor for Metrics {
+    // METRICS KEYS: the key counts by prefix, a map under RESP3 and a flat array of
+    // prefix/count under RESP2
+    async fn execute(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        let counts = backend.key_prefixes.counts();
+        if session.protocol() >= 3 {
+            let mut map = RespMap::new();
+            for (prefix, count) in counts {
+                map.insert(SimpleString::new(prefix), RespFrame::Integer(count as i64));
+            }
+            map.into()
+        } else {
+            let mut array = Vec::with_capacity(counts.len() * 2);
+            for (prefix, count) in counts {
+                array.push(BulkString::from(prefix).into());
+                array.push(RespFrame::Integer(count as i64));
+            }
+            RespArray::new(array).into()
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Metrics {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["metrics"])?;
+        match extract_args(value, 1)?.first() {
+            Some(RespFrame::BulkString(s)) if s.eq_ignore_ascii_case(b"keys") => Ok(Metrics),
+            _ => Err(CommandError::InvalidArgument(
+                "Unknown METRICS subcommand, only KEYS is supported".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_metrics_keys() {
+        assert!(Metrics::try_from(command(&["metrics", "KEYS"])).is_ok());
+        assert!(Metrics::try_from(command(&["metrics", "nope"])).is_err());
+        assert!(Metrics::try_from(command(&["metrics"])).is_err());
+
+        let backend = Backend::new();
+        let mut session = Session::default();
+        for key in ["user:1", "user:2", "order:1"] {
+            backend.set(key.into(), BulkString::from("v").into());
+            backend.record_key_access(&[key.into()], true);
+        }
+        assert_eq!(
+            Metrics.execute(&backend, &mut session).await,
+            RespArray::new(vec![
+                BulkString::from("order").into(),
+                RespFrame::Integer(1),
+                BulkString::from("user").into(),
+                RespFrame::Integer(2),
+            ])
+            .into()
+        );
+    }
+}
@@ -1,105 +1,1058 @@
+mod alias;
+mod bitmap;
+mod client;
+mod cluster;
+mod command;
+mod config;
+mod debug;
 mod echo;
+mod expire;
+mod function;
+mod geo;
+mod hello;
+mod help;
+mod hll;
 mod hmap;
+mod hotkeys;
+mod info;
+mod keyspace;
+mod latency;
+mod list;
+mod lolwut;
 mod map;
+mod metrics;
+mod object;
+mod pubsub;
+mod replication;
+mod save;
 mod set;
+mod slowlog;
+mod sort;
+mod stream;
+mod stream_group;
+mod table;
+mod time;
+mod zset;
 
-use crate::{Backend, RespArray, RespError, RespFrame, SimpleString};
+use crate::migrate::MigrateOptions;
+use crate::{
+    Backend, BitOperation, BitRange, BulkString, ClusterError, GeoSearchOptions, GeoShape, GeoUnit,
+    Key, LexBound, ListEnd, PauseMode, ReplyMode, RespArray, RespError, RespFrame, ScoreBound,
+    Session, SetCondition, ShutdownPolicy, SimpleError, SimpleString, SortOptions, StreamFields,
+    StreamId, StreamIdSpec, ZAddCondition, ZAggregate, WRONGTYPE_ERROR,
+};
 use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
+use std::{collections::HashMap, time::Duration};
 use thiserror::Error;
 
+use alias::{Alias, Legacy};
+pub(crate) use replication::propagated_command;
+pub use table::{command_spec, CommandFlags, CommandSpec, KeySpec};
+pub use time::{extract_expire_at, extract_timeout, TimeUnit};
+
 // you could also use once_cell instead of lazy_static
 lazy_static! {
-    static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
+    pub(crate) static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
 }
 
+// every error encodes to the reply of Redis, starting with the prefix client libraries
+// match on: ERR for the generic errors, WRONGTYPE, MOVED and so on for the others
 #[derive(Error, Debug)]
 pub enum CommandError {
-    #[error("Invalid command: {0}")]
+    #[error("ERR {0}")]
     InvalidCommand(String),
-    #[error("Invalid argument: {0}")]
+    #[error("ERR {0}")]
     InvalidArgument(String),
-    #[error("Unknown command: {0}")]
+    #[error("ERR unknown command {0}")]
     UnknownCommand(String),
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+    #[error("{}", WRONGTYPE_ERROR)]
+    WrongType,
+    #[error(transparent)]
+    Cluster(#[from] ClusterError),
 
-    #[error("{0}")]
+    #[error("ERR Protocol error: {0}")]
     RespError(#[from] RespError),
-    #[error("Utf8 error: {0}")]
+    #[error("ERR invalid UTF-8 argument: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
 }
 
+impl From<CommandError> for RespFrame {
+    fn from(e: CommandError) -> Self {
+        SimpleError::new(e.to_string()).into()
+    }
+}
+
+// commands may wait (e.g. blocking pops), the server only needs the futures of the concrete
+// command types, which are all Send, so the missing Send bound on the trait is fine
+#[allow(async_fn_in_trait)]
 #[enum_dispatch]
 pub trait CommandExecutor {
-    fn execute(self, backend: &Backend) -> RespFrame;
+    async fn execute(self, backend: &Backend, session: &mut Session) -> RespFrame;
 }
 
 #[enum_dispatch(CommandExecutor)]
 #[derive(Debug)]
 pub enum Command {
+    Legacy(Legacy),
     Get(Get),
     Set(Set),
+    GetRange(GetRange),
+    SetRange(SetRange),
+    Append(Append),
+    IncrBy(IncrBy),
     HGet(HGet),
     HMGet(HMGet),
     HSet(HSet),
     HGetAll(HGetAll),
+    HSetNx(HSetNx),
+    HRandField(HRandField),
     Echo(Echo),
+    Ping(Ping),
+    Lolwut(Lolwut),
     SAdd(SAdd),
     SIsMember(SIsMember),
+    SMIsMember(SMIsMember),
+    SInterCard(SInterCard),
+    PfAdd(PfAdd),
+    PfCount(PfCount),
+    PfMerge(PfMerge),
+    PfDebug(PfDebug),
+    Object(Object),
+    Slowlog(Slowlog),
+    Latency(Latency),
+    CommandInfo(CommandInfo),
+    ConfigGet(ConfigGet),
+    ConfigSet(ConfigSet),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
+    Publish(Publish),
+    SSubscribe(SSubscribe),
+    SUnsubscribe(SUnsubscribe),
+    SPublish(SPublish),
+    LPush(LPush),
+    RPush(RPush),
+    LPop(LPop),
+    RPop(RPop),
+    LLen(LLen),
+    LRange(LRange),
+    BLPop(BLPop),
+    LMove(LMove),
+    BLMove(BLMove),
+    BRPop(BRPop),
+    Client(Client),
+    Reset(Reset),
+    Quit(Quit),
+    Hello(Hello),
+    XAdd(XAdd),
+    XRange(XRange),
+    XRevRange(XRevRange),
+    XLen(XLen),
+    XRead(XRead),
+    XGroup(XGroup),
+    XReadGroup(XReadGroup),
+    XAck(XAck),
+    XPending(XPending),
+    XClaim(XClaim),
+    XAutoClaim(XAutoClaim),
+    Save(Save),
+    Shutdown(Shutdown),
+    SetBit(SetBit),
+    GetBit(GetBit),
+    BitCount(BitCount),
+    BitOp(BitOp),
+    BitPos(BitPos),
+    Exists(Exists),
+    Type(Type),
+    Del(Del),
+    Unlink(Unlink),
+    Touch(Touch),
+    CopyKey(CopyKey),
+    Dump(Dump),
+    Restore(Restore),
+    Expire(Expire),
+    Ttl(Ttl),
+    ExpireTime(ExpireTime),
+    Persist(Persist),
+    Migrate(Migrate),
+    ZAdd(ZAdd),
+    ZRem(ZRem),
+    ZScore(ZScore),
+    ZRange(ZRange),
+    ZIncrBy(ZIncrBy),
+    ZCard(ZCard),
+    ZCount(ZCount),
+    ZRangeByLex(ZRangeByLex),
+    ZPopMin(ZPopMin),
+    ZPopMax(ZPopMax),
+    BZPopMin(BZPopMin),
+    BZPopMax(BZPopMax),
+    ZUnionStore(ZUnionStore),
+    ZInterStore(ZInterStore),
+    GeoAdd(GeoAdd),
+    GeoPos(GeoPos),
+    GeoDist(GeoDist),
+    GeoSearch(GeoSearch),
+    GeoSearchStore(GeoSearchStore),
+    FCall(FCall),
+    PSync(PSync),
+    ReplConf(ReplConf),
+    Role(Role),
+    ReplicaOf(ReplicaOf),
+    Wait(Wait),
+    Cluster(Cluster),
+    Asking(Asking),
+    Metrics(Metrics),
+    Info(Info),
+    Hotkeys(Hotkeys),
+    DebugCommand(DebugCommand),
+    Help(Help),
+    Sort(Sort),
 }
 
 #[derive(Debug)]
 pub struct Get {
-    key: String,
+    key: Key,
 }
 
 #[derive(Debug)]
 pub struct Set {
-    key: String,
+    key: Key,
     value: RespFrame,
+    condition: SetCondition,
+    // reply with the old value instead of OK
+    get: bool,
+}
+
+#[derive(Debug)]
+pub struct GetRange {
+    key: Key,
+    start: i64,
+    end: i64,
+}
+
+#[derive(Debug)]
+pub struct SetRange {
+    key: Key,
+    offset: usize,
+    value: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Append {
+    key: Key,
+    value: Vec<u8>,
+}
+
+// INCR, DECR, INCRBY and DECRBY, DECR negates the increment
+#[derive(Debug)]
+pub struct IncrBy {
+    key: Key,
+    increment: i64,
 }
 
 #[derive(Debug)]
 pub struct HGet {
-    key: String,
-    field: String,
+    key: Key,
+    field: Vec<u8>,
 }
 
 #[derive(Debug)]
 pub struct HMGet {
-    key: String,
-    fields: Vec<String>,
+    key: Key,
+    fields: Vec<Vec<u8>>,
 }
 
 #[derive(Debug)]
 pub struct HSet {
-    key: String,
-    field: String,
+    key: Key,
+    field: Vec<u8>,
     value: RespFrame,
 }
 
 #[derive(Debug)]
 pub struct HGetAll {
-    key: String,
+    key: Key,
     sort: bool,
 }
 
+#[derive(Debug)]
+pub struct HSetNx {
+    key: Key,
+    field: Vec<u8>,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct HRandField {
+    key: Key,
+    // None replies a single field instead of an array
+    count: Option<i64>,
+    with_values: bool,
+}
+
 #[derive(Debug)]
 pub struct Echo {
-    message: String,
+    message: BulkString,
+}
+
+#[derive(Debug)]
+pub struct Ping {
+    // replied instead of PONG
+    message: Option<BulkString>,
 }
 
+// LOLWUT [VERSION version], there is only one art here
+#[derive(Debug)]
+pub struct Lolwut;
+
 #[derive(Debug)]
 pub struct SAdd {
-    key: String,
+    key: Key,
     members: Vec<RespFrame>,
 }
 
 #[derive(Debug)]
 pub struct SIsMember {
-    key: String,
+    key: Key,
     member: RespFrame,
 }
 
+#[derive(Debug)]
+pub struct SMIsMember {
+    key: Key,
+    members: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct SInterCard {
+    keys: Vec<Key>,
+    // 0 is no limit
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct PfAdd {
+    key: Key,
+    elements: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PfCount {
+    keys: Vec<Key>,
+}
+
+#[derive(Debug)]
+pub struct PfMerge {
+    dest: Key,
+    sources: Vec<Key>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PfDebugSubcommand {
+    Encoding,
+    ToDense,
+    GetReg,
+}
+
+#[derive(Debug)]
+pub struct PfDebug {
+    subcommand: PfDebugSubcommand,
+    key: Key,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ObjectSubcommand {
+    Encoding,
+    IdleTime,
+    Freq,
+}
+
+#[derive(Debug)]
+pub struct Object {
+    subcommand: ObjectSubcommand,
+    key: Key,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SlowlogSubcommand {
+    Get(Option<usize>),
+    Len,
+    Reset,
+}
+
+#[derive(Debug)]
+pub struct Slowlog {
+    subcommand: SlowlogSubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LatencySubcommand {
+    Latest,
+    History(String),
+    // the events to reset, all of them when empty
+    Reset(Vec<String>),
+}
+
+#[derive(Debug)]
+pub struct Latency {
+    subcommand: LatencySubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommandInfoSubcommand {
+    All,
+    Count,
+    Info(Vec<String>),
+    List,
+}
+
+#[derive(Debug)]
+pub struct CommandInfo {
+    subcommand: CommandInfoSubcommand,
+}
+
+#[derive(Debug)]
+pub struct XAdd {
+    key: Key,
+    id: StreamIdSpec,
+    fields: StreamFields,
+}
+
+#[derive(Debug)]
+pub struct XRange {
+    key: Key,
+    start: StreamId,
+    end: StreamId,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct XRevRange {
+    key: Key,
+    end: StreamId,
+    start: StreamId,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct XLen {
+    key: Key,
+}
+
+#[derive(Debug)]
+pub struct XRead {
+    count: Option<usize>,
+    // BLOCK 0 blocks forever
+    block: Option<Duration>,
+    // a None ID is `$`, only entries added after the command was issued
+    streams: Vec<(Key, Option<StreamId>)>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum XGroupSubcommand {
+    // a None ID is `$`, the group only gets the entries added after its creation
+    Create {
+        id: Option<StreamId>,
+        mkstream: bool,
+    },
+    Destroy,
+    CreateConsumer(Vec<u8>),
+    DelConsumer(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub struct XGroup {
+    subcommand: XGroupSubcommand,
+    key: Key,
+    group: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct XReadGroup {
+    group: Vec<u8>,
+    consumer: Vec<u8>,
+    count: Option<usize>,
+    // BLOCK 0 blocks forever
+    block: Option<Duration>,
+    noack: bool,
+    // a None ID is `>`, the entries never delivered to the group
+    streams: Vec<(Key, Option<StreamId>)>,
+}
+
+#[derive(Debug)]
+pub struct XAck {
+    key: Key,
+    group: Vec<u8>,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct XPendingRange {
+    min_idle: u64,
+    start: StreamId,
+    end: StreamId,
+    count: usize,
+    consumer: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct XPending {
+    key: Key,
+    group: Vec<u8>,
+    // None is the summary form
+    range: Option<XPendingRange>,
+}
+
+#[derive(Debug)]
+pub struct XClaim {
+    key: Key,
+    group: Vec<u8>,
+    consumer: Vec<u8>,
+    min_idle: u64,
+    ids: Vec<StreamId>,
+    justid: bool,
+}
+
+#[derive(Debug)]
+pub struct XAutoClaim {
+    key: Key,
+    group: Vec<u8>,
+    consumer: Vec<u8>,
+    min_idle: u64,
+    start: StreamId,
+    count: usize,
+    justid: bool,
+}
+
+#[derive(Debug)]
+pub struct Save;
+
+#[derive(Debug, PartialEq)]
+pub struct Shutdown {
+    // the configured shutdown-on-sigterm policy doesn't apply, only NOSAVE or SAVE
+    policy: ShutdownPolicy,
+}
+
+#[derive(Debug)]
+pub struct Exists {
+    keys: Vec<Key>,
+}
+
+#[derive(Debug)]
+pub struct Type {
+    key: Key,
+}
+
+#[derive(Debug)]
+pub struct Del {
+    keys: Vec<Key>,
+}
+
+#[derive(Debug)]
+pub struct Unlink {
+    keys: Vec<Key>,
+}
+
+#[derive(Debug)]
+pub struct Touch {
+    keys: Vec<Key>,
+}
+
+// COPY, named so it doesn't shadow the Copy trait
+#[derive(Debug, PartialEq)]
+pub struct CopyKey {
+    source: Key,
+    destination: Key,
+    replace: bool,
+}
+
+#[derive(Debug)]
+pub struct Dump {
+    key: Key,
+}
+
+// EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT
+#[derive(Debug)]
+pub struct Expire {
+    key: Key,
+    // the unix time in milliseconds the key expires at, 0 for a time that isn't positive
+    at_ms: u64,
+}
+
+// TTL and PTTL
+#[derive(Debug)]
+pub struct Ttl {
+    key: Key,
+    unit: TimeUnit,
+}
+
+// EXPIRETIME and PEXPIRETIME, the absolute unix time the key expires at
+#[derive(Debug)]
+pub struct ExpireTime {
+    key: Key,
+    unit: TimeUnit,
+}
+
+#[derive(Debug)]
+pub struct Persist {
+    key: Key,
+}
+
+#[derive(Debug)]
+pub struct Restore {
+    key: Key,
+    // milliseconds, 0 for none
+    ttl: u64,
+    payload: Vec<u8>,
+    replace: bool,
+}
+
+#[derive(Debug)]
+pub struct Migrate {
+    host: String,
+    port: u16,
+    keys: Vec<Key>,
+    options: MigrateOptions,
+}
+
+#[derive(Debug)]
+pub struct SetBit {
+    key: Key,
+    offset: u64,
+    value: bool,
+}
+
+#[derive(Debug)]
+pub struct GetBit {
+    key: Key,
+    offset: u64,
+}
+
+#[derive(Debug)]
+pub struct BitCount {
+    key: Key,
+    // always has an end
+    range: Option<BitRange>,
+}
+
+#[derive(Debug)]
+pub struct BitOp {
+    operation: BitOperation,
+    dest: Key,
+    keys: Vec<Key>,
+}
+
+#[derive(Debug)]
+pub struct BitPos {
+    key: Key,
+    bit: bool,
+    range: Option<BitRange>,
+}
+
+#[derive(Debug)]
+pub struct ZAdd {
+    key: Key,
+    condition: ZAddCondition,
+    ch: bool,
+    members: Vec<(f64, Vec<u8>)>,
+}
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: Key,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: Key,
+    member: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ZRange {
+    key: Key,
+    start: i64,
+    stop: i64,
+    rev: bool,
+    withscores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZIncrBy {
+    key: Key,
+    increment: f64,
+    member: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ZCard {
+    key: Key,
+}
+
+#[derive(Debug)]
+pub struct ZCount {
+    key: Key,
+    min: ScoreBound,
+    max: ScoreBound,
+}
+
+#[derive(Debug)]
+pub struct ZRangeByLex {
+    key: Key,
+    min: LexBound,
+    max: LexBound,
+    offset: usize,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ZPopMin {
+    key: Key,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ZPopMax {
+    key: Key,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct BZPopMin {
+    keys: Vec<Key>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BZPopMax {
+    keys: Vec<Key>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct ZUnionStore {
+    destination: Key,
+    keys: Vec<Key>,
+    weights: Vec<f64>,
+    aggregate: ZAggregate,
+}
+
+#[derive(Debug)]
+pub struct ZInterStore {
+    destination: Key,
+    keys: Vec<Key>,
+    weights: Vec<f64>,
+    aggregate: ZAggregate,
+}
+
+#[derive(Debug)]
+pub struct GeoAdd {
+    key: Key,
+    condition: ZAddCondition,
+    ch: bool,
+    // longitude, latitude, member
+    members: Vec<(f64, f64, Vec<u8>)>,
+}
+
+#[derive(Debug)]
+pub struct GeoPos {
+    key: Key,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct GeoDist {
+    key: Key,
+    member1: Vec<u8>,
+    member2: Vec<u8>,
+    unit: GeoUnit,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GeoCenter {
+    Member(Vec<u8>),
+    LonLat(f64, f64),
+}
+
+#[derive(Debug)]
+pub struct GeoSearch {
+    key: Key,
+    center: GeoCenter,
+    // in meters
+    shape: GeoShape,
+    // the unit of the shape, distances are replied in it
+    unit: GeoUnit,
+    options: GeoSearchOptions,
+    withcoord: bool,
+    withdist: bool,
+    withhash: bool,
+}
+
+#[derive(Debug)]
+pub struct GeoSearchStore {
+    destination: Key,
+    // without any WITH option
+    search: GeoSearch,
+    // store the distances in the unit of the shape instead of the geohashes
+    storedist: bool,
+}
+
+#[derive(Debug)]
+pub struct FCall {
+    function: String,
+    keys: Vec<Key>,
+    args: Vec<RespFrame>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PSync {
+    // ? and -1 when the replica has no history
+    replid: String,
+    offset: i64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplConf {
+    ListeningPort(u16),
+    IpAddress(String),
+    Capa(Vec<String>),
+    Ack(u64),
+}
+
+#[derive(Debug)]
+pub struct Role;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ClusterSubcommand {
+    Info,
+    Slots,
+    Shards,
+    KeySlot(Key),
+    MyId,
+}
+
+#[derive(Debug)]
+pub struct Cluster {
+    subcommand: ClusterSubcommand,
+}
+
+#[derive(Debug)]
+pub struct Asking;
+
+// METRICS KEYS, the only subcommand
+#[derive(Debug)]
+pub struct Metrics;
+
+// INFO [section ...], lowercase, none for the default sections
+#[derive(Debug)]
+pub struct Info {
+    sections: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Sort {
+    key: Key,
+    options: SortOptions,
+    // the list the result is stored in instead of being replied
+    store: Option<Key>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HotkeysSubcommand {
+    // the count most accessed keys
+    Get(usize),
+    Reset,
+}
+
+#[derive(Debug)]
+pub struct Hotkeys {
+    subcommand: HotkeysSubcommand,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DebugSubcommand {
+    Sleep(Duration),
+    Object(Key),
+    SetActiveExpire(bool),
+    Reload,
+    QuickSave,
+}
+
+// DEBUG, named so it doesn't shadow the Debug trait
+#[derive(Debug)]
+pub struct DebugCommand {
+    subcommand: DebugSubcommand,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Wait {
+    numreplicas: usize,
+    // None waits forever
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ReplicaOf {
+    // None for NO ONE
+    master: Option<(String, u16)>,
+}
+
+#[derive(Debug)]
+pub struct Hello {
+    protocol: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ClientSubcommand {
+    Reply(ReplyMode),
+    NoEvict(bool),
+    NoTouch(bool),
+    Pause(Duration, PauseMode),
+    Unpause,
+}
+
+#[derive(Debug)]
+pub struct Client {
+    subcommand: ClientSubcommand,
+}
+
+// <command> HELP, the usage lines of the command
+#[derive(Debug)]
+pub struct Help {
+    command: &'static str,
+    lines: &'static [&'static str],
+}
+
+#[derive(Debug)]
+pub struct Reset;
+
+// the connection closes once the reply is sent
+#[derive(Debug)]
+pub struct Quit;
+
+#[derive(Debug)]
+pub struct ConfigGet {
+    parameters: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ConfigSet {
+    parameter: String,
+    value: String,
+}
+
+#[derive(Debug)]
+pub struct Subscribe {
+    channels: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Unsubscribe {
+    channels: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PUnsubscribe {
+    patterns: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Publish {
+    channel: Vec<u8>,
+    message: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SSubscribe {
+    channels: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SUnsubscribe {
+    channels: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SPublish {
+    channel: Vec<u8>,
+    message: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct LPush {
+    key: Key,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct RPush {
+    key: Key,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct LPop {
+    key: Key,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct RPop {
+    key: Key,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct LLen {
+    key: Key,
+}
+
+#[derive(Debug)]
+pub struct LRange {
+    key: Key,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct BLPop {
+    keys: Vec<Key>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BRPop {
+    keys: Vec<Key>,
+    timeout: Option<Duration>,
+}
+
+// LMOVE, and RPOPLPUSH which is LMOVE RIGHT LEFT
+#[derive(Debug, PartialEq)]
+pub struct LMove {
+    source: Key,
+    destination: Key,
+    from: ListEnd,
+    to: ListEnd,
+}
+
+// BLMOVE, and BRPOPLPUSH which is BLMOVE RIGHT LEFT
+#[derive(Debug, PartialEq)]
+pub struct BLMove {
+    lmove: LMove,
+    timeout: Option<Duration>,
+}
+
 impl TryFrom<RespFrame> for Command {
     type Error = CommandError;
     fn try_from(v: RespFrame) -> Result<Self, Self::Error> {
@@ -112,72 +1065,325 @@ impl TryFrom<RespFrame> for Command {
     }
 }
 
+// parses the arguments of a command into it
+type Parser = fn(RespArray) -> Result<Command, CommandError>;
+
+#[derive(Debug)]
+enum Dispatch {
+    Command(Parser),
+    Alias(&'static Alias),
+}
+
+// a command with its spec, so one lookup of its name gives both how to parse it and how
+// the connection handles it
+#[derive(Debug)]
+pub struct CommandEntry {
+    dispatch: Dispatch,
+    pub spec: &'static CommandSpec,
+}
+
+macro_rules! parser {
+    ($command:ty) => {
+        |v| Ok(<$command>::try_from(v)?.into())
+    };
+}
+
+// longer names can't be commands, they are uppercased on the stack to look them up
+const MAX_COMMAND_NAME_LEN: usize = 32;
+
+lazy_static! {
+    // every command by its uppercase name
+    static ref DISPATCH: HashMap<Vec<u8>, CommandEntry> = {
+        let parsers: &[(&'static str, Parser)] = &[
+        ("GET", parser!(Get)),
+        ("SET", parser!(Set)),
+        ("GETRANGE", parser!(GetRange)),
+        ("SETRANGE", parser!(SetRange)),
+        ("APPEND", parser!(Append)),
+        ("INCR", parser!(IncrBy)),
+        ("DECR", parser!(IncrBy)),
+        ("INCRBY", parser!(IncrBy)),
+        ("DECRBY", parser!(IncrBy)),
+        ("HGET", parser!(HGet)),
+        ("HSET", parser!(HSet)),
+        ("HMGET", parser!(HMGet)),
+        ("HGETALL", parser!(HGetAll)),
+        ("HSETNX", parser!(HSetNx)),
+        ("HRANDFIELD", parser!(HRandField)),
+        ("ECHO", parser!(Echo)),
+        ("PING", parser!(Ping)),
+        ("LOLWUT", parser!(Lolwut)),
+        ("SADD", parser!(SAdd)),
+        ("SISMEMBER", parser!(SIsMember)),
+        ("SMISMEMBER", parser!(SMIsMember)),
+        ("SINTERCARD", parser!(SInterCard)),
+        ("PFADD", parser!(PfAdd)),
+        ("PFCOUNT", parser!(PfCount)),
+        ("PFMERGE", parser!(PfMerge)),
+        ("PFDEBUG", parser!(PfDebug)),
+        ("OBJECT", parser!(Object)),
+        ("SLOWLOG", parser!(Slowlog)),
+        ("LATENCY", parser!(Latency)),
+        ("SUBSCRIBE", parser!(Subscribe)),
+        ("UNSUBSCRIBE", parser!(Unsubscribe)),
+        ("PSUBSCRIBE", parser!(PSubscribe)),
+        ("PUNSUBSCRIBE", parser!(PUnsubscribe)),
+        ("PUBLISH", parser!(Publish)),
+        ("SSUBSCRIBE", parser!(SSubscribe)),
+        ("SUNSUBSCRIBE", parser!(SUnsubscribe)),
+        ("SPUBLISH", parser!(SPublish)),
+        ("LPUSH", parser!(LPush)),
+        ("RPUSH", parser!(RPush)),
+        ("LPOP", parser!(LPop)),
+        ("RPOP", parser!(RPop)),
+        ("LLEN", parser!(LLen)),
+        ("LRANGE", parser!(LRange)),
+        ("BLPOP", parser!(BLPop)),
+        ("BRPOP", parser!(BRPop)),
+        ("LMOVE", parser!(LMove)),
+        ("RPOPLPUSH", parser!(LMove)),
+        ("BLMOVE", parser!(BLMove)),
+        ("BRPOPLPUSH", parser!(BLMove)),
+        ("XADD", parser!(XAdd)),
+        ("XRANGE", parser!(XRange)),
+        ("XREVRANGE", parser!(XRevRange)),
+        ("XLEN", parser!(XLen)),
+        ("XREAD", parser!(XRead)),
+        ("XGROUP", parser!(XGroup)),
+        ("XREADGROUP", parser!(XReadGroup)),
+        ("XACK", parser!(XAck)),
+        ("XPENDING", parser!(XPending)),
+        ("XCLAIM", parser!(XClaim)),
+        ("XAUTOCLAIM", parser!(XAutoClaim)),
+        ("SAVE", parser!(Save)),
+        ("SHUTDOWN", parser!(Shutdown)),
+        ("SETBIT", parser!(SetBit)),
+        ("GETBIT", parser!(GetBit)),
+        ("BITCOUNT", parser!(BitCount)),
+        ("BITOP", parser!(BitOp)),
+        ("BITPOS", parser!(BitPos)),
+        ("EXISTS", parser!(Exists)),
+        ("TYPE", parser!(Type)),
+        ("DEL", parser!(Del)),
+        ("UNLINK", parser!(Unlink)),
+        ("TOUCH", parser!(Touch)),
+        ("COPY", parser!(CopyKey)),
+        ("DUMP", parser!(Dump)),
+        ("RESTORE", parser!(Restore)),
+        ("MIGRATE", parser!(Migrate)),
+        ("EXPIRE", parser!(Expire)),
+        ("PEXPIRE", parser!(Expire)),
+        ("EXPIREAT", parser!(Expire)),
+        ("PEXPIREAT", parser!(Expire)),
+        ("TTL", parser!(Ttl)),
+        ("PTTL", parser!(Ttl)),
+        ("EXPIRETIME", parser!(ExpireTime)),
+        ("PEXPIRETIME", parser!(ExpireTime)),
+        ("PERSIST", parser!(Persist)),
+        ("ZADD", parser!(ZAdd)),
+        ("ZREM", parser!(ZRem)),
+        ("ZSCORE", parser!(ZScore)),
+        ("ZRANGE", parser!(ZRange)),
+        ("ZINCRBY", parser!(ZIncrBy)),
+        ("ZCARD", parser!(ZCard)),
+        ("ZCOUNT", parser!(ZCount)),
+        ("ZRANGEBYLEX", parser!(ZRangeByLex)),
+        ("ZPOPMIN", parser!(ZPopMin)),
+        ("ZPOPMAX", parser!(ZPopMax)),
+        ("BZPOPMIN", parser!(BZPopMin)),
+        ("BZPOPMAX", parser!(BZPopMax)),
+        ("ZUNIONSTORE", parser!(ZUnionStore)),
+        ("ZINTERSTORE", parser!(ZInterStore)),
+        ("GEOADD", parser!(GeoAdd)),
+        ("GEOPOS", parser!(GeoPos)),
+        ("GEODIST", parser!(GeoDist)),
+        ("GEOSEARCH", parser!(GeoSearch)),
+        ("GEOSEARCHSTORE", parser!(GeoSearchStore)),
+        ("FCALL", parser!(FCall)),
+        ("HELLO", parser!(Hello)),
+        ("CLIENT", parser!(Client)),
+        ("PSYNC", parser!(PSync)),
+        ("REPLCONF", parser!(ReplConf)),
+        ("ROLE", parser!(Role)),
+        ("REPLICAOF", parser!(ReplicaOf)),
+        ("SLAVEOF", parser!(ReplicaOf)),
+        ("WAIT", parser!(Wait)),
+        ("CLUSTER", parser!(Cluster)),
+        ("ASKING", parser!(Asking)),
+        ("RESET", parser!(Reset)),
+        ("QUIT", parser!(Quit)),
+        ("METRICS", parser!(Metrics)),
+        ("INFO", parser!(Info)),
+        ("HOTKEYS", parser!(Hotkeys)),
+        ("DEBUG", parser!(DebugCommand)),
+        ("SORT", parser!(Sort)),
+        ("SORT_RO", parser!(Sort)),
+        ("COMMAND", parser!(CommandInfo)),
+        ("CONFIG", parse_config),
+        ];
+        let entry = |name: &str, dispatch| {
+            let spec = command_spec(&name.to_ascii_lowercase())
+                .unwrap_or_else(|| panic!("no spec for {}", name));
+            (name.to_ascii_uppercase().into_bytes(), CommandEntry { dispatch, spec })
+        };
+        let mut dispatch: HashMap<Vec<u8>, CommandEntry> = parsers
+            .iter()
+            .map(|(name, parser)| entry(name, Dispatch::Command(*parser)))
+            .collect();
+        dispatch.extend(
+            alias::ALIASES
+                .iter()
+                .map(|alias| entry(alias.name, Dispatch::Alias(alias))),
+        );
+        dispatch
+    };
+}
+
+// the command by its name in any case, uppercased on the stack to look it up
+pub fn lookup_command(name: &[u8]) -> Option<&'static CommandEntry> {
+    let mut buf = [0; MAX_COMMAND_NAME_LEN];
+    let upper = buf.get_mut(..name.len())?;
+    upper.copy_from_slice(name);
+    upper.make_ascii_uppercase();
+    DISPATCH.get(&*upper)
+}
+
+impl CommandEntry {
+    // the arguments parsed as this command, whose name they start with
+    pub fn parse(&self, v: RespArray) -> Result<Command, CommandError> {
+        match self.dispatch {
+            Dispatch::Command(parser) => match help::parse_help(&v) {
+                Some(help) => Ok(help.into()),
+                None => parser(v),
+            },
+            Dispatch::Alias(alias) => alias.resolve(v),
+        }
+    }
+}
+
 impl TryFrom<RespArray> for Command {
     type Error = CommandError;
     fn try_from(v: RespArray) -> Result<Self, Self::Error> {
-        match v.first() {
-            Some(RespFrame::BulkString(ref cmd)) => {
-                let ascii_lowercase: &[u8] = &cmd.to_ascii_lowercase();
-                match ascii_lowercase {
-                    b"get" => Ok(Get::try_from(v)?.into()),
-                    b"set" => Ok(Set::try_from(v)?.into()),
-                    b"hget" => Ok(HGet::try_from(v)?.into()),
-                    b"hset" => Ok(HSet::try_from(v)?.into()),
-                    b"hmget" => Ok(HMGet::try_from(v)?.into()),
-                    b"hgetall" => Ok(HGetAll::try_from(v)?.into()),
-                    b"echo" => Ok(Echo::try_from(v)?.into()),
-                    b"sadd" => Ok(SAdd::try_from(v)?.into()),
-                    b"sismember" => Ok(SIsMember::try_from(v)?.into()),
-                    _ => Err(CommandError::UnknownCommand(
-                        String::from_utf8_lossy(ascii_lowercase).into(),
-                    )),
-                }
-            }
-            _ => Err(CommandError::InvalidCommand(
+        let Some(RespFrame::BulkString(name)) = v.first() else {
+            return Err(CommandError::InvalidCommand(
                 "Command must have a BulkString as the first argument".to_string(),
-            )),
+            ));
+        };
+        match lookup_command(name) {
+            Some(entry) => entry.parse(v),
+            None => Err(unknown_command(&v)),
         }
     }
 }
 
-fn validate_command(
-    value: &RespArray,
-    names: &[&'static str],
-    n_args: usize,
-) -> Result<(), CommandError> {
-    if value.len() != n_args + names.len() {
-        return Err(CommandError::InvalidArgument(format!(
-            "{} command must have exactly {} argument",
-            names.join(" "),
-            n_args
-        )));
+// the error Redis replies to a command it doesn't know
+fn unknown_command(v: &RespArray) -> CommandError {
+    let arg = |frame: &RespFrame| match frame {
+        RespFrame::BulkString(s) => String::from_utf8_lossy(s).into_owned(),
+        frame => format!("{:?}", frame),
+    };
+    let args: String = v
+        .iter()
+        .skip(1)
+        .map(|frame| format!("'{}' ", arg(frame)))
+        .collect();
+    CommandError::UnknownCommand(format!(
+        "'{}', with args beginning with: {}",
+        v.first().map(arg).unwrap_or_default(),
+        args
+    ))
+}
+
+fn parse_config(v: RespArray) -> Result<Command, CommandError> {
+    match extract_subcommand(&v).as_deref() {
+        Some(b"get") => Ok(ConfigGet::try_from(v)?.into()),
+        Some(b"set") => Ok(ConfigSet::try_from(v)?.into()),
+        subcommand => Err(CommandError::InvalidArgument(format!(
+            "Unknown subcommand or wrong number of arguments for '{}'",
+            String::from_utf8_lossy(subcommand.unwrap_or_default())
+        ))),
     }
+}
 
-    for (i, name) in names.iter().enumerate() {
-        match value[i] {
-            RespFrame::BulkString(ref cmd) => {
-                if cmd.as_ref().to_ascii_lowercase() != name.as_bytes() {
+// checks the name of the command, and of its subcommand when given two names, then the
+// number of arguments against the arity in the command table
+fn validate_command(value: &RespArray, names: &[&'static str]) -> Result<(), CommandError> {
+    let name = || names.join("|");
+    for (i, expected) in names.iter().enumerate() {
+        match value.get(i) {
+            Some(RespFrame::BulkString(cmd)) => {
+                if !cmd.eq_ignore_ascii_case(expected.as_bytes()) {
                     return Err(CommandError::InvalidCommand(format!(
                         "Invalid command: expected {}, got {}",
-                        name,
+                        expected,
                         String::from_utf8_lossy(cmd.as_ref())
                     )));
                 }
             }
-            _ => {
+            Some(_) => {
                 return Err(CommandError::InvalidCommand(
                     "Command must have a BulkString as the first argument".to_string(),
                 ))
             }
+            None => return Err(CommandError::WrongArity(name())),
         }
     }
-    Ok(())
+    let spec = match names {
+        [name] => command_spec(name),
+        _ => command_spec(&name()),
+    };
+    match spec {
+        Some(spec) if !spec.check_arity(value.len()) => Err(CommandError::WrongArity(name())),
+        _ => Ok(()),
+    }
 }
 
 fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.0.into_iter().skip(start).collect::<Vec<RespFrame>>())
 }
 
+// lowercased second element of the command, for commands with subcommands
+fn extract_subcommand(value: &RespArray) -> Option<Vec<u8>> {
+    match value.get(1) {
+        Some(RespFrame::BulkString(s)) => Some(s.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+fn extract_integer(frame: RespFrame) -> Result<i64, CommandError> {
+    match frame {
+        RespFrame::BulkString(s) => String::from_utf8(Vec::from(s.0))?.parse().map_err(|_| {
+            CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+        }),
+        RespFrame::Integer(i) => Ok(i),
+        _ => Err(CommandError::InvalidArgument(
+            "value is not an integer or out of range".to_string(),
+        )),
+    }
+}
+
+// keys are binary safe, any bulk string is one
+fn extract_keys(args: Vec<RespFrame>) -> Result<Vec<Key>, CommandError> {
+    args.into_iter()
+        .map(|k| match k {
+            RespFrame::BulkString(k) => Ok(k.into()),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        })
+        .collect()
+}
+
+// binary safe arguments that aren't keys, like channels and sorted set members
+fn extract_bytes(args: Vec<RespFrame>) -> Result<Vec<Vec<u8>>, CommandError> {
+    args.into_iter()
+        .map(|s| match s {
+            RespFrame::BulkString(s) => Ok(Vec::from(s.0)),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid argument".to_string(),
+            )),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -185,8 +1391,8 @@ mod tests {
     use anyhow::Result;
     use bytes::BytesMut;
 
-    #[test]
-    fn test_command() -> Result<()> {
+    #[tokio::test]
+    async fn test_command() -> Result<()> {
         let mut buf = BytesMut::new();
         buf.extend_from_slice(b"*2\r\n$3\r\nget\r\n$5\r\nhello\r\n");
 
@@ -196,9 +1402,102 @@ mod tests {
 
         let backend = Backend::new();
 
-        let ret = cmd.execute(&backend);
+        let ret = cmd.execute(&backend, &mut Session::default()).await;
         assert_eq!(ret, RespFrame::Null(RespNull));
 
         Ok(())
     }
+
+    #[test]
+    fn test_dispatch() {
+        let command = |args: &[&str]| {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| crate::BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+        };
+        assert!(matches!(
+            Command::try_from(command(&["gEt", "k"])),
+            Ok(Command::Get(_))
+        ));
+        assert!(matches!(
+            Command::try_from(command(&["SetNx", "k", "v"])),
+            Ok(Command::Legacy(_))
+        ));
+        assert!(matches!(
+            Command::try_from(command(&["config", "get", "port"])),
+            Ok(Command::ConfigGet(_))
+        ));
+        assert!(Command::try_from(command(&["config", "nope"])).is_err());
+
+        let err = Command::try_from(command(&["foo", "a", "b"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR unknown command 'foo', with args beginning with: 'a' 'b' "
+        );
+        let long = "x".repeat(MAX_COMMAND_NAME_LEN + 1);
+        assert!(matches!(
+            Command::try_from(command(&[&long])),
+            Err(CommandError::UnknownCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_arity() {
+        let command = |args: &[&str]| {
+            Command::try_from(RespArray::new(
+                args.iter()
+                    .map(|arg| crate::BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            ))
+        };
+        let arity_error = |args: &[&str]| match command(args) {
+            Err(CommandError::WrongArity(name)) => name,
+            ret => panic!("{:?} parsed to {:?}", args, ret),
+        };
+        assert_eq!(arity_error(&["GET"]), "get");
+        assert_eq!(arity_error(&["get", "a", "b"]), "get");
+        assert_eq!(arity_error(&["sadd", "s"]), "sadd");
+        assert_eq!(arity_error(&["rpoplpush", "a"]), "rpoplpush");
+        assert_eq!(arity_error(&["setnx", "k"]), "setnx");
+        assert_eq!(arity_error(&["config", "set", "port"]), "config|set");
+        assert_eq!(
+            CommandError::WrongArity("get".to_string()).to_string(),
+            "ERR wrong number of arguments for 'get' command"
+        );
+
+        assert!(command(&["sadd", "s", "a", "b", "c"]).is_ok());
+        assert!(command(&["del", "a"]).is_ok());
+        assert!(command(&["slaveof", "no", "one"]).is_ok());
+    }
+    #[test]
+    fn test_error_prefixes() {
+        let reply = |e: CommandError| match RespFrame::from(e) {
+            RespFrame::Error(e) => e.0,
+            frame => panic!("{:?} is not an error", frame),
+        };
+        assert_eq!(
+            reply(CommandError::InvalidArgument("syntax error".to_string())),
+            "ERR syntax error"
+        );
+        assert!(reply(CommandError::WrongType).starts_with("WRONGTYPE "));
+        assert_eq!(
+            reply(
+                ClusterError::Moved {
+                    slot: 3999,
+                    addr: "127.0.0.1:6381".to_string()
+                }
+                .into()
+            ),
+            "MOVED 3999 127.0.0.1:6381"
+        );
+        assert!(reply(RespError::NotComplete.into()).starts_with("ERR Protocol error"));
+        let err = Command::try_from(RespArray::new(vec![
+            BulkString::from("get").into(),
+            RespFrame::Integer(1),
+        ]))
+        .unwrap_err();
+        assert!(reply(err).starts_with("ERR "));
+    }
 }
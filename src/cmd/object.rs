@@ -0,0 +1,216 @@
+use super::{
+    extract_args, validate_command, CommandError, CommandExecutor, Object, ObjectSubcommand,
+};
+use crate::{BulkString, RespArray, RespFrame, RespNull};
+
+impl CommandExecutor for Object {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match self.subcommand {
+            ObjectSubcommand::Encoding => match backend.object_encoding(&self.key) {
+                Some(encoding) => BulkString::from(encoding).into(),
+                None => RespFrame::Null(RespNull),
+            },
+            ObjectSubcommand::IdleTime => match backend.object_idletime(&self.key) {
+                Some(idle) => RespFrame::Integer(idle as i64),
+                None => RespFrame::Null(RespNull),
+            },
+            ObjectSubcommand::Freq => match backend.object_freq(&self.key) {
+                Some(freq) => RespFrame::Integer(freq as i64),
+                None => RespFrame::Null(RespNull),
+            },
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Object {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["object"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(subcommand)), Some(RespFrame::BulkString(key))) => {
+                let subcommand = match subcommand.to_ascii_lowercase().as_slice() {
+                    b"encoding" => ObjectSubcommand::Encoding,
+                    b"idletime" => ObjectSubcommand::IdleTime,
+                    b"freq" => ObjectSubcommand::Freq,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(format!(
+                            "Unknown OBJECT subcommand '{}'",
+                            String::from_utf8_lossy(&subcommand)
+                        )))
+                    }
+                };
+                Ok(Object {
+                    subcommand,
+                    key: key.into(),
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid subcommand or key".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecode};
+    use crate::{Key, Session};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_object_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nobject\r\n$8\r\nENCODING\r\n$3\r\nhll\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Object = frame.try_into()?;
+        assert_eq!(result.subcommand, ObjectSubcommand::Encoding);
+        assert_eq!(result.key, "hll");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_object_encoding_command() {
+        let backend = Backend::new();
+        backend.pfadd("hll".into(), &[b"a".to_vec()]);
+        backend.set("str".into(), RespFrame::BulkString(b"world".into()));
+
+        let cmd = Object {
+            subcommand: ObjectSubcommand::Encoding,
+            key: "hll".into(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            BulkString::from("sparse").into()
+        );
+
+        let cmd = Object {
+            subcommand: ObjectSubcommand::Encoding,
+            key: "str".into(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            BulkString::from("raw").into()
+        );
+
+        // small sets are listpacks until they grow past set-max-listpack-entries
+        backend.config_set("set-max-listpack-entries", "2").unwrap();
+        let encoding = |backend: Backend, key: &'static str| async move {
+            let cmd = Object {
+                subcommand: ObjectSubcommand::Encoding,
+                key: key.into(),
+            };
+            cmd.execute(&backend, &mut Session::default()).await
+        };
+        backend.sadd("set".into(), vec![BulkString::from("a").into()]);
+        assert_eq!(
+            encoding(backend.clone(), "set").await,
+            BulkString::from("listpack").into()
+        );
+        backend.sadd(
+            "set".into(),
+            vec![BulkString::from("b").into(), BulkString::from("c").into()],
+        );
+        assert_eq!(
+            encoding(backend.clone(), "set").await,
+            BulkString::from("hashtable").into()
+        );
+
+        // so are small lists, past list-max-listpack-size, and hashes
+        backend.config_set("list-max-listpack-size", "2").unwrap();
+        backend.lpush("list".into(), vec![BulkString::from("a").into()]);
+        assert_eq!(
+            encoding(backend.clone(), "list").await,
+            BulkString::from("listpack").into()
+        );
+        backend.lpush(
+            "list".into(),
+            vec![BulkString::from("b").into(), BulkString::from("c").into()],
+        );
+        assert_eq!(
+            encoding(backend.clone(), "list").await,
+            BulkString::from("quicklist").into()
+        );
+        backend.config_set("hash-max-listpack-value", "4").unwrap();
+        backend.hset("hash".into(), b"f".to_vec(), BulkString::from("v").into());
+        assert_eq!(
+            encoding(backend.clone(), "hash").await,
+            BulkString::from("listpack").into()
+        );
+        backend.hset(
+            "hash".into(),
+            b"f".to_vec(),
+            BulkString::from("long").into(),
+        );
+        assert_eq!(
+            encoding(backend.clone(), "hash").await,
+            BulkString::from("listpack").into()
+        );
+        backend.hset(
+            "hash".into(),
+            b"f".to_vec(),
+            BulkString::from("longer").into(),
+        );
+        assert_eq!(
+            encoding(backend.clone(), "hash").await,
+            BulkString::from("hashtable").into()
+        );
+
+        let cmd = Object {
+            subcommand: ObjectSubcommand::Encoding,
+            key: "missing".into(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Null(RespNull)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_object_idletime_freq_command() {
+        let backend = Backend::new();
+        let keys = [Key::from("k")];
+        crate::set_mock_now_ms(Some(1_000_000));
+        backend.set("k".into(), BulkString::from("v").into());
+        backend.record_key_access(&keys, true);
+        let object = |subcommand| Object {
+            subcommand,
+            key: "k".into(),
+        };
+
+        crate::set_mock_now_ms(Some(1_000_000 + 90_000));
+        assert_eq!(
+            object(ObjectSubcommand::IdleTime)
+                .execute(&backend, &mut Session::default())
+                .await,
+            RespFrame::Integer(90)
+        );
+        // new keys start at 5 and lose 1 per idle minute
+        assert_eq!(
+            object(ObjectSubcommand::Freq)
+                .execute(&backend, &mut Session::default())
+                .await,
+            RespFrame::Integer(4)
+        );
+
+        // the first hits always count
+        backend.record_key_access(&keys, false);
+        assert_eq!(backend.object_idletime(b"k"), Some(0));
+        assert_eq!(backend.object_freq(b"k"), Some(5));
+        for _ in 0..1000 {
+            backend.record_key_access(&keys, false);
+        }
+        let freq = backend.object_freq(b"k").unwrap();
+        assert!(freq > 5 && freq < 40, "freq {}", freq);
+
+        assert_eq!(backend.object_freq(b"missing"), None);
+        assert_eq!(backend.object_idletime(b"missing"), None);
+        crate::set_mock_now_ms(None);
+    }
+}
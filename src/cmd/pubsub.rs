@@ -0,0 +1,481 @@
+use super::{
+    extract_args, extract_bytes, validate_command, CommandError, CommandExecutor, PSubscribe,
+    PUnsubscribe, Publish, SPublish, SSubscribe, SUnsubscribe, Subscribe, Unsubscribe,
+};
+use crate::{BulkString, RespArray, RespFrame, Session};
+
+impl CommandExecutor for Subscribe {
+    async fn execute(self, backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        let mut replies = Vec::with_capacity(self.channels.len());
+        for channel in self.channels {
+            if session.channels.insert(channel.clone()) {
+                backend
+                    .pubsub
+                    .subscribe(channel.clone(), session.id(), session.sender());
+            }
+            replies.push(subscription_reply("subscribe", Some(channel), session));
+        }
+        reply_all(session, replies)
+    }
+}
+
+impl CommandExecutor for Unsubscribe {
+    async fn execute(self, backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        let channels = if self.channels.is_empty() {
+            session.channels.iter().cloned().collect()
+        } else {
+            self.channels
+        };
+        if channels.is_empty() {
+            return subscription_reply("unsubscribe", None, session);
+        }
+
+        let mut replies = Vec::with_capacity(channels.len());
+        for channel in channels {
+            if session.channels.remove(&channel) {
+                backend.pubsub.unsubscribe(&channel, session.id());
+            }
+            replies.push(subscription_reply("unsubscribe", Some(channel), session));
+        }
+        reply_all(session, replies)
+    }
+}
+
+impl CommandExecutor for PSubscribe {
+    async fn execute(self, backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        let mut replies = Vec::with_capacity(self.patterns.len());
+        for pattern in self.patterns {
+            if session.patterns.insert(pattern.clone()) {
+                backend
+                    .pubsub
+                    .psubscribe(pattern.clone(), session.id(), session.sender());
+            }
+            replies.push(subscription_reply("psubscribe", Some(pattern), session));
+        }
+        reply_all(session, replies)
+    }
+}
+
+impl CommandExecutor for PUnsubscribe {
+    async fn execute(self, backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        let patterns = if self.patterns.is_empty() {
+            session.patterns.iter().cloned().collect()
+        } else {
+            self.patterns
+        };
+        if patterns.is_empty() {
+            return subscription_reply("punsubscribe", None, session);
+        }
+
+        let mut replies = Vec::with_capacity(patterns.len());
+        for pattern in patterns {
+            if session.patterns.remove(&pattern) {
+                backend.pubsub.punsubscribe(&pattern, session.id());
+            }
+            replies.push(subscription_reply("punsubscribe", Some(pattern), session));
+        }
+        reply_all(session, replies)
+    }
+}
+
+impl CommandExecutor for Publish {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let receivers = backend.pubsub.publish(&self.channel, &self.message);
+        RespFrame::Integer(receivers as i64)
+    }
+}
+
+// sharded pub/sub: in a cluster a shard channel lives on the node of its slot. Every shard
+// channel is served here, by the same broker as the other channels but in its own namespace.
+impl CommandExecutor for SSubscribe {
+    async fn execute(self, backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        let mut replies = Vec::with_capacity(self.channels.len());
+        for channel in self.channels {
+            if session.shard_channels.insert(channel.clone()) {
+                backend
+                    .pubsub
+                    .ssubscribe(channel.clone(), session.id(), session.sender());
+            }
+            replies.push(shard_subscription_reply(
+                "ssubscribe",
+                Some(channel),
+                session,
+            ));
+        }
+        reply_all(session, replies)
+    }
+}
+
+impl CommandExecutor for SUnsubscribe {
+    async fn execute(self, backend: &crate::Backend, session: &mut crate::Session) -> RespFrame {
+        let channels = if self.channels.is_empty() {
+            session.shard_channels.iter().cloned().collect()
+        } else {
+            self.channels
+        };
+        if channels.is_empty() {
+            return shard_subscription_reply("sunsubscribe", None, session);
+        }
+
+        let mut replies = Vec::with_capacity(channels.len());
+        for channel in channels {
+            if session.shard_channels.remove(&channel) {
+                backend.pubsub.sunsubscribe(&channel, session.id());
+            }
+            replies.push(shard_subscription_reply(
+                "sunsubscribe",
+                Some(channel),
+                session,
+            ));
+        }
+        reply_all(session, replies)
+    }
+}
+
+impl CommandExecutor for SPublish {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let receivers = backend.pubsub.spublish(&self.channel, &self.message);
+        RespFrame::Integer(receivers as i64)
+    }
+}
+
+impl TryFrom<RespArray> for Subscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["subscribe"])?;
+
+        let channels = extract_bytes(extract_args(value, 1)?)?;
+        if channels.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "subscribe command must have at least 1 channel".to_string(),
+            ));
+        }
+        Ok(Subscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for Unsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["unsubscribe"])?;
+
+        let channels = extract_bytes(extract_args(value, 1)?)?;
+        Ok(Unsubscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for PSubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["psubscribe"])?;
+
+        let patterns = extract_bytes(extract_args(value, 1)?)?;
+        if patterns.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "psubscribe command must have at least 1 pattern".to_string(),
+            ));
+        }
+        Ok(PSubscribe { patterns })
+    }
+}
+
+impl TryFrom<RespArray> for PUnsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["punsubscribe"])?;
+
+        let patterns = extract_bytes(extract_args(value, 1)?)?;
+        Ok(PUnsubscribe { patterns })
+    }
+}
+
+impl TryFrom<RespArray> for Publish {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["publish"])?;
+        parse_publish(value)
+    }
+}
+
+// PUBLISH and SPUBLISH channel message
+fn parse_publish(value: RespArray) -> Result<Publish, CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    match (args.next(), args.next()) {
+        (Some(RespFrame::BulkString(channel)), Some(RespFrame::BulkString(message))) => {
+            Ok(Publish {
+                channel: Vec::from(channel.0),
+                message: Vec::from(message.0),
+            })
+        }
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid channel or message".to_string(),
+        )),
+    }
+}
+
+impl TryFrom<RespArray> for SSubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ssubscribe"])?;
+
+        let channels = extract_bytes(extract_args(value, 1)?)?;
+        if channels.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "ssubscribe command must have at least 1 channel".to_string(),
+            ));
+        }
+        Ok(SSubscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for SUnsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["sunsubscribe"])?;
+
+        let channels = extract_bytes(extract_args(value, 1)?)?;
+        Ok(SUnsubscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for SPublish {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["spublish"])?;
+        let Publish { channel, message } = parse_publish(value)?;
+        Ok(SPublish { channel, message })
+    }
+}
+
+fn subscription_reply(kind: &str, name: Option<Vec<u8>>, session: &Session) -> RespFrame {
+    let count = session.subscription_count();
+    subscription_count_reply(kind, name, count, session)
+}
+
+// the shard channels are counted apart from the others, like in Redis
+fn shard_subscription_reply(kind: &str, name: Option<Vec<u8>>, session: &Session) -> RespFrame {
+    let count = session.shard_subscription_count();
+    subscription_count_reply(kind, name, count, session)
+}
+
+fn subscription_count_reply(
+    kind: &str,
+    name: Option<Vec<u8>>,
+    count: usize,
+    session: &Session,
+) -> RespFrame {
+    let name = match name {
+        Some(name) => BulkString::new(name),
+        None => BulkString::null(),
+    };
+    session.as_push(
+        RespArray::new(vec![
+            BulkString::from(kind).into(),
+            name.into(),
+            RespFrame::Integer(count as i64),
+        ])
+        .into(),
+    )
+}
+
+// a (un)subscribe command replies once per channel, all but the last reply are pushed
+fn reply_all(session: &Session, mut replies: Vec<RespFrame>) -> RespFrame {
+    let last = replies.pop().unwrap_or_else(|| RespArray::new([]).into());
+    for reply in replies {
+        session.push(reply);
+    }
+    last
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecode, RespPush};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_subscribe_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Subscribe = frame.try_into()?;
+        assert_eq!(result.channels, vec![b"a".to_vec(), b"b".to_vec()]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_publish_unsubscribe_commands() {
+        let backend = Backend::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut session = Session::new("127.0.0.1:1234", tx);
+
+        let cmd = Subscribe {
+            channels: vec![b"a".to_vec(), b"b".to_vec()],
+        };
+        let ret = cmd.execute(&backend, &mut session).await;
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespArray::new(vec![
+                BulkString::from("subscribe").into(),
+                BulkString::from("a").into(),
+                RespFrame::Integer(1),
+            ])
+            .into()
+        );
+        assert_eq!(
+            ret,
+            RespArray::new(vec![
+                BulkString::from("subscribe").into(),
+                BulkString::from("b").into(),
+                RespFrame::Integer(2),
+            ])
+            .into()
+        );
+
+        let cmd = Publish {
+            channel: b"a".to_vec(),
+            message: b"hello".to_vec(),
+        };
+        let ret = cmd.execute(&backend, &mut Session::default()).await;
+        assert_eq!(ret, RespFrame::Integer(1));
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespArray::new(vec![
+                BulkString::from("message").into(),
+                BulkString::from("a").into(),
+                BulkString::from("hello").into(),
+            ])
+            .into()
+        );
+
+        let cmd = Unsubscribe { channels: vec![] };
+        cmd.execute(&backend, &mut session).await;
+        assert_eq!(session.subscription_count(), 0);
+
+        let cmd = Unsubscribe { channels: vec![] };
+        let ret = cmd.execute(&backend, &mut session).await;
+        assert_eq!(
+            ret,
+            RespArray::new(vec![
+                BulkString::from("unsubscribe").into(),
+                BulkString::null().into(),
+                RespFrame::Integer(0),
+            ])
+            .into()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_resp3_push() {
+        let backend = Backend::new();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut session = Session::new("127.0.0.1:1234", tx);
+        session.set_protocol(3);
+
+        let cmd = Subscribe {
+            channels: vec![b"a".to_vec()],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespPush::new(vec![
+                BulkString::from("subscribe").into(),
+                BulkString::from("a").into(),
+                RespFrame::Integer(1),
+            ])
+            .into()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shard_channel_commands() {
+        let backend = Backend::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut session = Session::new("127.0.0.1:1234", tx);
+        Subscribe {
+            channels: vec![b"a".to_vec()],
+        }
+        .execute(&backend, &mut session)
+        .await;
+
+        let cmd: SSubscribe = RespArray::new(vec![
+            BulkString::from("ssubscribe").into(),
+            BulkString::from("a").into(),
+        ])
+        .try_into()
+        .unwrap();
+        // counted apart from the channel subscribed with SUBSCRIBE
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespArray::new(vec![
+                BulkString::from("ssubscribe").into(),
+                BulkString::from("a").into(),
+                RespFrame::Integer(1),
+            ])
+            .into()
+        );
+
+        let cmd = SPublish {
+            channel: b"a".to_vec(),
+            message: b"hello".to_vec(),
+        };
+        let ret = cmd.execute(&backend, &mut Session::default()).await;
+        assert_eq!(ret, RespFrame::Integer(1));
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespArray::new(vec![
+                BulkString::from("smessage").into(),
+                BulkString::from("a").into(),
+                BulkString::from("hello").into(),
+            ])
+            .into()
+        );
+        assert!(rx.try_recv().is_err());
+
+        let cmd = SUnsubscribe { channels: vec![] };
+        assert_eq!(
+            cmd.execute(&backend, &mut session).await,
+            RespArray::new(vec![
+                BulkString::from("sunsubscribe").into(),
+                BulkString::from("a").into(),
+                RespFrame::Integer(0),
+            ])
+            .into()
+        );
+        assert_eq!(session.subscription_count(), 1);
+        assert!(session.is_subscribed());
+    }
+
+    #[tokio::test]
+    async fn test_psubscribe_command() {
+        let backend = Backend::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut session = Session::new("127.0.0.1:1234", tx);
+
+        let cmd = PSubscribe {
+            patterns: vec![b"news.*".to_vec()],
+        };
+        cmd.execute(&backend, &mut session).await;
+        assert_eq!(backend.pubsub.publish(b"news.tech", b"hi"), 1);
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespArray::new(vec![
+                BulkString::from("pmessage").into(),
+                BulkString::from("news.*").into(),
+                BulkString::from("news.tech").into(),
+                BulkString::from("hi").into(),
+            ])
+            .into()
+        );
+
+        let cmd = PUnsubscribe {
+            patterns: vec![b"news.*".to_vec()],
+        };
+        cmd.execute(&backend, &mut session).await;
+        assert_eq!(backend.pubsub.publish(b"news.tech", b"hi"), 0);
+    }
+}
@@ -0,0 +1,630 @@
+use super::{
+    extract_args, extract_integer, extract_timeout, validate_command, CommandError,
+    CommandExecutor, Expire, PSync, ReplConf, ReplicaOf, Role, TimeUnit, Wait,
+};
+use crate::{
+    replica::replicaof, Backend, BulkString, PsyncReply, RespArray, RespFrame, Session, SimpleError,
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+impl CommandExecutor for PSync {
+    // the connection handler takes over the connection for PSYNC, see PSync::start
+    async fn execute(self, _backend: &Backend, _session: &mut Session) -> RespFrame {
+        SimpleError::new("ERR PSYNC is only accepted on a client connection").into()
+    }
+}
+
+impl PSync {
+    // register the replica, returns what to write to it before the stream: the reply line
+    // and the snapshot or the missing part of the backlog
+    pub(crate) async fn start(
+        self,
+        backend: &Backend,
+        session: &Session,
+    ) -> (Vec<u8>, UnboundedReceiver<Vec<u8>>) {
+        // the address the replica announced, with the port it connects from
+        let addr = match session.ip_address() {
+            Some(ip) => {
+                let port = session.addr().rsplit_once(':').map_or("", |(_, port)| port);
+                format!("{}:{}", ip, port)
+            }
+            None => session.addr().to_string(),
+        };
+        let (reply, stream) = backend
+            .psync(
+                session.id(),
+                addr,
+                session.listening_port(),
+                &self.replid,
+                self.offset,
+            )
+            .await;
+        let head = match reply {
+            // the snapshot is sent like a bulk string without the trailing CRLF, as Redis does
+            PsyncReply::FullResync {
+                replid,
+                offset,
+                snapshot,
+            } => {
+                let mut head = format!(
+                    "+FULLRESYNC {} {}\r\n${}\r\n",
+                    replid,
+                    offset,
+                    snapshot.len()
+                )
+                .into_bytes();
+                head.extend_from_slice(&snapshot);
+                head
+            }
+            PsyncReply::Continue { replid, backlog } => {
+                let mut head = format!("+CONTINUE {}\r\n", replid).into_bytes();
+                head.extend_from_slice(&backlog);
+                head
+            }
+        };
+        (head, stream)
+    }
+}
+
+impl CommandExecutor for ReplConf {
+    async fn execute(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        match self {
+            ReplConf::ListeningPort(port) => session.set_listening_port(port),
+            ReplConf::IpAddress(ip) => session.set_ip_address(ip),
+            // nothing depends on the capabilities of replicas yet
+            ReplConf::Capa(_) => {}
+            // replicas don't read replies to their ACKs
+            ReplConf::Ack(offset) => backend.replication.ack(session.id(), offset),
+        }
+        super::RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for Role {
+    async fn execute(self, backend: &Backend, _session: &mut Session) -> RespFrame {
+        if let Some(master) = backend.replication.master() {
+            return RespArray::new(vec![
+                BulkString::from("slave").into(),
+                BulkString::from(master.host).into(),
+                RespFrame::Integer(master.port as i64),
+                BulkString::from(master.state.name()).into(),
+                RespFrame::Integer(master.offset),
+            ])
+            .into();
+        }
+        let replicas = backend
+            .replication
+            .replicas()
+            .into_iter()
+            .map(|replica| {
+                let (ip, port) = replica
+                    .addr
+                    .rsplit_once(':')
+                    .unwrap_or((replica.addr.as_str(), ""));
+                let port = replica
+                    .listening_port
+                    .map(|port| port.to_string())
+                    .unwrap_or_else(|| port.to_string());
+                RespArray::new(vec![
+                    BulkString::from(ip).into(),
+                    BulkString::from(port).into(),
+                    BulkString::from(replica.ack_offset.to_string()).into(),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(vec![
+            BulkString::from("master").into(),
+            RespFrame::Integer(backend.replication.offset() as i64),
+            RespArray::new(replicas).into(),
+        ])
+        .into()
+    }
+}
+
+impl CommandExecutor for ReplicaOf {
+    async fn execute(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        if session.is_master_link() {
+            return SimpleError::new("ERR Command is not valid when client is a replica.").into();
+        }
+        replicaof(backend, self.master);
+        super::RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for Wait {
+    async fn execute(self, backend: &Backend, _session: &mut Session) -> RespFrame {
+        if backend.replication.is_replica() {
+            return SimpleError::new("ERR WAIT cannot be used with replica instances.").into();
+        }
+        // everything written so far, including the writes of this client
+        let offset = backend.replication.offset();
+        let acked = backend
+            .replication
+            .wait_for_acks(self.numreplicas, offset, self.timeout)
+            .await;
+        RespFrame::Integer(acked as i64)
+    }
+}
+
+impl TryFrom<RespArray> for PSync {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["psync"])?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(replid)), Some(offset), None) => Ok(PSync {
+                replid: String::from_utf8(Vec::from(replid.0))?,
+                offset: extract_integer(offset)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid replication id or offset".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ReplConf {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["replconf"])?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        let option = match args.next() {
+            Some(RespFrame::BulkString(option)) => option.to_ascii_lowercase(),
+            _ => return Err(CommandError::InvalidArgument("Invalid option".to_string())),
+        };
+        let mut values = args.collect::<Vec<RespFrame>>();
+        match (option.as_slice(), values.len()) {
+            (b"listening-port", 1) => {
+                let port = extract_integer(values.into_iter().next().unwrap())?;
+                u16::try_from(port)
+                    .map(ReplConf::ListeningPort)
+                    .map_err(|_| CommandError::InvalidArgument("Invalid port".to_string()))
+            }
+            // the address to reach the replica at, e.g. behind NAT
+            (b"ip-address", 1) => Ok(ReplConf::IpAddress(bulk_string(values.pop())?)),
+            (b"ack", 1) => {
+                let offset = extract_integer(values.into_iter().next().unwrap())?;
+                u64::try_from(offset)
+                    .map(ReplConf::Ack)
+                    .map_err(|_| CommandError::InvalidArgument("Invalid offset".to_string()))
+            }
+            // capa can be repeated: REPLCONF capa eof capa psync2
+            (b"capa", n) if !n.is_multiple_of(2) => {
+                let mut values = values.into_iter();
+                let mut capabilities = vec![bulk_string(values.next())?];
+                while let (Some(RespFrame::BulkString(capa)), Some(value)) =
+                    (values.next(), values.next())
+                {
+                    if !capa.eq_ignore_ascii_case(b"capa") {
+                        return Err(CommandError::InvalidArgument(
+                            "Invalid REPLCONF capa".to_string(),
+                        ));
+                    }
+                    capabilities.push(bulk_string(Some(value))?);
+                }
+                Ok(ReplConf::Capa(capabilities))
+            }
+            (option, _) => Err(CommandError::InvalidArgument(format!(
+                "Unrecognized REPLCONF option: {}",
+                String::from_utf8_lossy(option)
+            ))),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Role {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["role"])?;
+        Ok(Role)
+    }
+}
+
+// WAIT numreplicas timeout, the timeout is in milliseconds and 0 blocks forever
+impl TryFrom<RespArray> for Wait {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["wait"])?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(numreplicas), Some(timeout), None) => {
+                let numreplicas = usize::try_from(extract_integer(numreplicas)?).map_err(|_| {
+                    CommandError::InvalidArgument("numreplicas is negative".to_string())
+                })?;
+                let timeout = extract_timeout(timeout, TimeUnit::Millis)?;
+                Ok(Wait {
+                    numreplicas,
+                    timeout: (!timeout.is_zero()).then_some(timeout),
+                })
+            }
+            _ => Err(CommandError::WrongArity("wait".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ReplicaOf {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let slaveof = matches!(value.first(), Some(RespFrame::BulkString(name)) if name.eq_ignore_ascii_case(b"slaveof"));
+        validate_command(&value, &[if slaveof { "slaveof" } else { "replicaof" }])?;
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(no)), Some(RespFrame::BulkString(one)), None)
+                if no.eq_ignore_ascii_case(b"no") && one.eq_ignore_ascii_case(b"one") =>
+            {
+                Ok(ReplicaOf { master: None })
+            }
+            (Some(RespFrame::BulkString(host)), Some(port), None) => {
+                let port = u16::try_from(extract_integer(port)?).map_err(|_| {
+                    CommandError::InvalidArgument("Invalid master port".to_string())
+                })?;
+                Ok(ReplicaOf {
+                    master: Some((String::from_utf8(Vec::from(host.0))?, port)),
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid master host or port".to_string(),
+            )),
+        }
+    }
+}
+
+// the command to send to replicas for a write that ran with the reply given, None when it
+// changed nothing that has to be replayed. Commands whose effect depends on timing or on the
+// clock are rewritten to what they did.
+pub(crate) fn propagated_command(command: RespArray, reply: &RespFrame) -> Option<RespArray> {
+    if matches!(reply, RespFrame::Error(_)) {
+        return None;
+    }
+    let name = match command.first() {
+        Some(RespFrame::BulkString(name)) => name.to_ascii_lowercase(),
+        _ => return None,
+    };
+    match name.as_slice() {
+        // a blocking pop that got an element is a pop of the list it came from
+        b"blpop" | b"brpop" => match reply {
+            RespFrame::Array(popped) if popped.len() == 2 => {
+                let pop = if name == b"blpop" { "lpop" } else { "rpop" };
+                Some(RespArray::new(vec![
+                    BulkString::from(pop).into(),
+                    popped[0].clone(),
+                ]))
+            }
+            _ => None,
+        },
+        // a blocking sorted set pop that got a member is a pop of the set it came from
+        b"bzpopmin" | b"bzpopmax" => match reply {
+            RespFrame::Array(popped) if popped.len() == 3 => {
+                let pop = if name == b"bzpopmin" {
+                    "zpopmin"
+                } else {
+                    "zpopmax"
+                };
+                Some(RespArray::new(vec![
+                    BulkString::from(pop).into(),
+                    popped[0].clone(),
+                ]))
+            }
+            _ => None,
+        },
+        // a blocking move that got an element is a move, replicas must not wait
+        b"blmove" | b"brpoplpush" => match reply {
+            RespFrame::BulkString(_) => {
+                let mut args = command.0;
+                args[0] = BulkString::from("lmove").into();
+                args.pop();
+                if name == b"brpoplpush" {
+                    args.push(BulkString::from("right").into());
+                    args.push(BulkString::from("left").into());
+                }
+                Some(RespArray::new(args))
+            }
+            _ => None,
+        },
+        // replicas expire the key at the same time, however late they apply the command
+        b"expire" | b"pexpire" | b"expireat" | b"pexpireat" => match reply {
+            RespFrame::Integer(1) => {
+                let expire = Expire::try_from(command).ok()?;
+                Some(RespArray::new(vec![
+                    BulkString::from("pexpireat").into(),
+                    expire.key.clone().into(),
+                    BulkString::from(expire.at_ms.to_string()).into(),
+                ]))
+            }
+            _ => None,
+        },
+        // SORT only writes with STORE, which replies with the length of the list
+        b"sort" => match reply {
+            RespFrame::Integer(_) => Some(command),
+            _ => None,
+        },
+        // the ID that was generated
+        b"xadd" => {
+            let mut args = command.0;
+            if let (RespFrame::BulkString(_), Some(id)) = (reply, args.get_mut(2)) {
+                *id = reply.clone();
+            }
+            Some(RespArray::new(args))
+        }
+        // replicas claim what was claimed here whatever the idle times are there, the deleted
+        // entries are dropped from their PEL by XCLAIM too
+        b"xautoclaim" => match reply {
+            RespFrame::Array(reply) if reply.len() == 3 && command.len() >= 6 => {
+                let (RespFrame::Array(claimed), RespFrame::Array(deleted)) = (&reply[1], &reply[2])
+                else {
+                    return None;
+                };
+                let ids: Vec<RespFrame> = claimed
+                    .iter()
+                    .filter_map(|claimed| match claimed {
+                        RespFrame::Array(entry) => entry.first().cloned(),
+                        id => Some(id.clone()),
+                    })
+                    .chain(deleted.iter().cloned())
+                    .collect();
+                if ids.is_empty() {
+                    return None;
+                }
+                let justid = command[6..].iter().any(
+                    |arg| matches!(arg, RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"justid")),
+                );
+                let mut args = vec![BulkString::from("xclaim").into()];
+                args.extend_from_slice(&command[1..4]);
+                args.push(BulkString::from("0").into());
+                args.extend(ids);
+                if justid {
+                    args.push(BulkString::from("justid").into());
+                }
+                Some(RespArray::new(args))
+            }
+            _ => None,
+        },
+        // replicas must not wait, and there is nothing to replay when nothing was read
+        b"xreadgroup" => match reply {
+            RespFrame::Null(_) | RespFrame::NullArray(_) => None,
+            _ => {
+                let mut args = command.0.into_iter();
+                let mut kept = Vec::new();
+                while let Some(arg) = args.next() {
+                    match &arg {
+                        RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"block") => {
+                            args.next();
+                        }
+                        _ => kept.push(arg),
+                    }
+                }
+                Some(RespArray::new(kept))
+            }
+        },
+        _ => Some(command),
+    }
+}
+
+fn bulk_string(frame: Option<RespFrame>) -> Result<String, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(s)) => Ok(String::from_utf8(Vec::from(s.0))?),
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid argument".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespDecode, RespNull, RespNullArray};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::time::Duration;
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[test]
+    fn test_replication_commands_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$5\r\nPSYNC\r\n$1\r\n?\r\n$2\r\n-1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: PSync = frame.try_into()?;
+        assert_eq!(
+            result,
+            PSync {
+                replid: "?".to_string(),
+                offset: -1
+            }
+        );
+
+        let result: ReplConf = command(&["replconf", "listening-port", "6380"]).try_into()?;
+        assert_eq!(result, ReplConf::ListeningPort(6380));
+        let result: ReplConf = command(&["replconf", "ip-address", "10.0.0.2"]).try_into()?;
+        assert_eq!(result, ReplConf::IpAddress("10.0.0.2".to_string()));
+        let result: ReplConf =
+            command(&["replconf", "capa", "eof", "capa", "psync2"]).try_into()?;
+        assert_eq!(
+            result,
+            ReplConf::Capa(vec!["eof".to_string(), "psync2".to_string()])
+        );
+        let result: ReplConf = command(&["replconf", "ACK", "42"]).try_into()?;
+        assert_eq!(result, ReplConf::Ack(42));
+        assert!(ReplConf::try_from(command(&["replconf", "ack", "-1"])).is_err());
+        assert!(ReplConf::try_from(command(&["replconf", "nope", "1"])).is_err());
+
+        let result: ReplicaOf = command(&["replicaof", "127.0.0.1", "6380"]).try_into()?;
+        assert_eq!(
+            result,
+            ReplicaOf {
+                master: Some(("127.0.0.1".to_string(), 6380))
+            }
+        );
+        let result: ReplicaOf = command(&["slaveof", "NO", "one"]).try_into()?;
+        assert_eq!(result, ReplicaOf { master: None });
+        assert!(ReplicaOf::try_from(command(&["replicaof", "h", "99999"])).is_err());
+        assert!(ReplicaOf::try_from(command(&["replicaof", "h"])).is_err());
+
+        let result: Wait = command(&["wait", "1", "100"]).try_into()?;
+        assert_eq!(
+            result,
+            Wait {
+                numreplicas: 1,
+                timeout: Some(Duration::from_millis(100))
+            }
+        );
+        let result: Wait = command(&["wait", "2", "0"]).try_into()?;
+        assert_eq!(result.timeout, None);
+        assert!(Wait::try_from(command(&["wait", "1", "-1"])).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_role() {
+        let backend = Backend::new();
+        let mut session = Session::new("127.0.0.1:50000", tokio::sync::mpsc::unbounded_channel().0);
+        ReplConf::ListeningPort(6380)
+            .execute(&backend, &mut session)
+            .await;
+        let (head, _stream) = PSync {
+            replid: "?".to_string(),
+            offset: -1,
+        }
+        .start(&backend, &session)
+        .await;
+        assert!(head.starts_with(b"+FULLRESYNC "));
+        ReplConf::Ack(7).execute(&backend, &mut session).await;
+
+        let role = Role.execute(&backend, &mut session).await;
+        assert_eq!(
+            role,
+            RespArray::new(vec![
+                BulkString::from("master").into(),
+                RespFrame::Integer(0),
+                RespArray::new(vec![command(&["127.0.0.1", "6380", "7"]).into()]).into(),
+            ])
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_propagated_command() {
+        let popped: RespFrame = command(&["l", "a"]).into();
+        assert_eq!(
+            propagated_command(command(&["blpop", "l", "k", "0"]), &popped),
+            Some(command(&["lpop", "l"]))
+        );
+        assert_eq!(
+            propagated_command(command(&["brpop", "l", "1"]), &RespNull.into()),
+            None
+        );
+        let popped: RespFrame = command(&["z", "a", "1"]).into();
+        assert_eq!(
+            propagated_command(command(&["bzpopmax", "k", "z", "0"]), &popped),
+            Some(command(&["zpopmax", "z"]))
+        );
+        let moved: RespFrame = BulkString::from("a").into();
+        assert_eq!(
+            propagated_command(command(&["blmove", "l", "m", "LEFT", "RIGHT", "0"]), &moved),
+            Some(command(&["lmove", "l", "m", "LEFT", "RIGHT"]))
+        );
+        assert_eq!(
+            propagated_command(command(&["brpoplpush", "l", "m", "0"]), &moved),
+            Some(command(&["lmove", "l", "m", "right", "left"]))
+        );
+        assert_eq!(
+            propagated_command(
+                command(&["blmove", "l", "m", "left", "left", "1"]),
+                &RespNull.into()
+            ),
+            None
+        );
+        assert_eq!(
+            propagated_command(
+                command(&["xadd", "s", "*", "f", "v"]),
+                &BulkString::from("5-0").into()
+            ),
+            Some(command(&["xadd", "s", "5-0", "f", "v"]))
+        );
+        assert_eq!(
+            propagated_command(
+                command(&[
+                    "xreadgroup",
+                    "group",
+                    "g",
+                    "c",
+                    "block",
+                    "0",
+                    "streams",
+                    "s",
+                    ">"
+                ]),
+                &RespArray::new(vec![command(&["s"]).into()]).into()
+            ),
+            Some(command(&[
+                "xreadgroup",
+                "group",
+                "g",
+                "c",
+                "streams",
+                "s",
+                ">"
+            ]))
+        );
+        assert_eq!(
+            propagated_command(
+                command(&["xautoclaim", "s", "g", "c", "1000", "0", "count", "5"]),
+                &RespArray::new(vec![
+                    BulkString::from("0-0").into(),
+                    RespArray::new(vec![command(&["1-0", "f", "v"]).into()]).into(),
+                    command(&["2-0"]).into(),
+                ])
+                .into()
+            ),
+            Some(command(&["xclaim", "s", "g", "c", "0", "1-0", "2-0"]))
+        );
+        assert_eq!(
+            propagated_command(
+                command(&["xautoclaim", "s", "g", "c", "1000", "0", "justid"]),
+                &RespArray::new(vec![
+                    BulkString::from("0-0").into(),
+                    RespArray::new(vec![]).into(),
+                    RespArray::new(vec![]).into(),
+                ])
+                .into()
+            ),
+            None
+        );
+        assert_eq!(
+            propagated_command(
+                command(&["xreadgroup", "group", "g", "c", "streams", "s", ">"]),
+                &RespNullArray.into()
+            ),
+            None
+        );
+        assert_eq!(
+            propagated_command(
+                command(&["set", "a", "1"]),
+                &SimpleError::new("ERR no").into()
+            ),
+            None
+        );
+        assert_eq!(
+            propagated_command(command(&["del", "a"]), &RespFrame::Integer(0)),
+            Some(command(&["del", "a"]))
+        );
+        assert_eq!(
+            propagated_command(
+                command(&["expireat", "a", "1700000000"]),
+                &RespFrame::Integer(1)
+            ),
+            Some(command(&["pexpireat", "a", "1700000000000"]))
+        );
+        assert_eq!(
+            propagated_command(command(&["expire", "a", "10"]), &RespFrame::Integer(0)),
+            None
+        );
+    }
+}
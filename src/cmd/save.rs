@@ -0,0 +1,114 @@
+use super::{
+    extract_args, validate_command, CommandError, CommandExecutor, Save, Shutdown, RESP_OK,
+};
+use crate::{RespArray, RespFrame, ShutdownPolicy, SimpleError};
+use tracing::warn;
+
+impl CommandExecutor for Save {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.save() {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => {
+                warn!("failed to save the snapshot: {}", e);
+                SimpleError::new(format!("ERR {}", e)).into()
+            }
+        }
+    }
+}
+
+// the server stops once the connections are done with their commands, this one closes
+// without a reply like in Redis
+impl CommandExecutor for Shutdown {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        backend.request_shutdown(self.policy);
+        RESP_OK.clone()
+    }
+}
+
+impl TryFrom<RespArray> for Save {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["save"])?;
+        Ok(Save)
+    }
+}
+
+impl TryFrom<RespArray> for Shutdown {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["shutdown"])?;
+        let args = extract_args(value, 1)?;
+        let policy = match args.as_slice() {
+            [] => ShutdownPolicy::Default,
+            [RespFrame::BulkString(arg)] => match arg.to_ascii_lowercase().as_slice() {
+                b"nosave" => ShutdownPolicy::NoSave,
+                b"save" => ShutdownPolicy::Save,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(Shutdown { policy })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, RespDecode, Session};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_save_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nSAVE\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let _: Save = frame.try_into()?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_save_command() {
+        let path =
+            std::env::temp_dir().join(format!("simple-redis-save-{}.srdb", std::process::id()));
+        let backend = Backend::new();
+        backend.set_dbfilename(path.to_string_lossy().into_owned());
+        backend.set("k".into(), BulkString::from("v").into());
+
+        assert_eq!(
+            Save.execute(&backend, &mut Session::default()).await,
+            RESP_OK.clone()
+        );
+        let restored = Backend::new();
+        restored
+            .restore_snapshot(&std::fs::read(&path).unwrap())
+            .unwrap();
+        assert_eq!(restored.get(b"k"), Some(BulkString::from("v").into()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_command() -> Result<()> {
+        let command = |args: &[&str]| {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+        };
+        let shutdown: Shutdown = command(&["shutdown", "NOSAVE"]).try_into()?;
+        assert_eq!(shutdown.policy, ShutdownPolicy::NoSave);
+        let shutdown: Shutdown = command(&["shutdown"]).try_into()?;
+        assert_eq!(shutdown.policy, ShutdownPolicy::Default);
+        assert!(Shutdown::try_from(command(&["shutdown", "later"])).is_err());
+        assert!(Shutdown::try_from(command(&["shutdown", "save", "nosave"])).is_err());
+
+        let backend = Backend::new();
+        shutdown.execute(&backend, &mut Session::default()).await;
+        assert!(backend.is_shutting_down());
+        assert_eq!(backend.shutdown_requested().await, ShutdownPolicy::Default);
+        Ok(())
+    }
+}
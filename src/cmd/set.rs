@@ -1,34 +1,54 @@
-use crate::{RespArray, RespFrame};
+use crate::{RespArray, RespFrame, NOTIFY_SET};
 
 use super::{
-    extract_args, validate_command, CommandError, CommandExecutor, SAdd, SIsMember, RESP_OK,
+    extract_args, extract_integer, extract_keys, validate_command, CommandError, CommandExecutor,
+    SAdd, SInterCard, SIsMember, SMIsMember, RESP_OK,
 };
 
 impl CommandExecutor for SAdd {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
         backend.sadd(self.key.to_owned(), self.members);
+        backend.notify_keyspace_event(NOTIFY_SET, "sadd", &self.key);
         RESP_OK.clone()
     }
 }
 
 impl CommandExecutor for SIsMember {
-    fn execute(self, backend: &crate::Backend) -> RespFrame {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
         let ret = backend.s_is_member(&self.key, self.member);
         RespFrame::Boolean(ret)
     }
 }
 
+impl CommandExecutor for SMIsMember {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let ret = backend.smismember(&self.key, &self.members);
+        RespArray::new(
+            ret.into_iter()
+                .map(|member| RespFrame::Integer(member as i64))
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+}
+
+impl CommandExecutor for SInterCard {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.sintercard(&self.keys, self.limit) as i64)
+    }
+}
+
 impl TryFrom<RespArray> for SAdd {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["sadd"], value.len() - 1)?;
+        validate_command(&value, &["sadd"])?;
 
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(key)) => {
                 let members = args.collect();
                 Ok(SAdd {
-                    key: String::from_utf8_lossy(&key.0).into(),
+                    key: key.into(),
                     members,
                 })
             }
@@ -42,12 +62,12 @@ impl TryFrom<RespArray> for SAdd {
 impl TryFrom<RespArray> for SIsMember {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["sismember"], value.len() - 1)?;
+        validate_command(&value, &["sismember"])?;
 
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(member)) => Ok(SIsMember {
-                key: String::from_utf8(key.0)?,
+                key: key.into(),
                 member,
             }),
             _ => Err(CommandError::InvalidArgument(
@@ -57,9 +77,70 @@ impl TryFrom<RespArray> for SIsMember {
     }
 }
 
+impl TryFrom<RespArray> for SMIsMember {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["smismember"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match args.next() {
+            Some(RespFrame::BulkString(key)) if args.len() > 0 => Ok(SMIsMember {
+                key: key.into(),
+                members: args.collect(),
+            }),
+            _ => Err(CommandError::WrongArity("smismember".to_string())),
+        }
+    }
+}
+
+// SINTERCARD numkeys key [key ...] [LIMIT limit]
+impl TryFrom<RespArray> for SInterCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["sintercard"])?;
+
+        let mut args = extract_args(value, 1)?;
+        if args.is_empty() {
+            return Err(CommandError::WrongArity("sintercard".to_string()));
+        }
+        let mut rest = args.split_off(1);
+        let numkeys = extract_integer(args.remove(0))?;
+        if numkeys <= 0 {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ));
+        }
+        if numkeys as usize > rest.len() {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let options = rest.split_off(numkeys as usize);
+        let limit = match options.as_slice() {
+            [] => 0,
+            [RespFrame::BulkString(option), limit] if option.eq_ignore_ascii_case(b"limit") => {
+                match extract_integer(limit.clone())? {
+                    limit if limit < 0 => {
+                        return Err(CommandError::InvalidArgument(
+                            "LIMIT can't be negative".to_string(),
+                        ))
+                    }
+                    limit => limit as usize,
+                }
+            }
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(SInterCard {
+            keys: extract_keys(rest)?,
+            limit,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Session;
     use anyhow::Result;
     use dashmap::DashMap;
 
@@ -87,7 +168,28 @@ mod tests {
     }
 
     #[test]
-    fn test_sadd() -> Result<()> {
+    fn test_nan_set_member() {
+        let backend = Backend::new();
+        let members: Vec<RespFrame> = [f64::NAN, -f64::NAN, 0.0, -0.0, 1e300, 2e300]
+            .into_iter()
+            .map(|f| ApproximateFloat(f).into())
+            .collect();
+        backend.sadd("myset".into(), members);
+
+        // NaN is a single member that can be found again, as is zero
+        assert_eq!(
+            backend
+                .value::<crate::MemberSet>(b"myset")
+                .map(|set| set.len()),
+            Some(4)
+        );
+        assert!(backend.s_is_member(b"myset", ApproximateFloat(f64::NAN).into()));
+        assert!(backend.s_is_member(b"myset", ApproximateFloat(0.0).into()));
+        assert!(!backend.s_is_member(b"myset", ApproximateFloat(3e300).into()));
+    }
+
+    #[tokio::test]
+    async fn test_sadd() -> Result<()> {
         let backend = Backend::new();
         let vec = vec![
             RespFrame::BulkString("sadd".into()),
@@ -99,15 +201,72 @@ mod tests {
         let cmd = RespArray(vec);
 
         let cmd = SAdd::try_from(cmd)?;
-        cmd.execute(&backend);
+        cmd.execute(&backend, &mut Session::default()).await;
 
-        println!("{:?}", &backend.set);
+        println!("{:?}", &backend.db);
 
-        assert!(backend.s_is_member("myset", RespFrame::BulkString("a".into())));
-        assert!(backend.s_is_member("myset", RespFrame::BulkString("b".into())));
-        assert!(backend.s_is_member("myset", RespFrame::BulkString("c".into())));
-        assert!(!backend.s_is_member("myset", RespFrame::BulkString("d".into())));
+        assert!(backend.s_is_member(b"myset", RespFrame::BulkString("a".into())));
+        assert!(backend.s_is_member(b"myset", RespFrame::BulkString("b".into())));
+        assert!(backend.s_is_member(b"myset", RespFrame::BulkString("c".into())));
+        assert!(!backend.s_is_member(b"myset", RespFrame::BulkString("d".into())));
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_smismember_and_sintercard() -> Result<()> {
+        let backend = Backend::new();
+        let members = |members: &[&str]| -> Vec<RespFrame> {
+            members
+                .iter()
+                .map(|m| RespFrame::BulkString((*m).into()))
+                .collect()
+        };
+        backend.sadd("a".into(), members(&["x", "y", "z", "w"]));
+        backend.sadd("b".into(), members(&["y", "z", "w", "v"]));
+        backend.sadd("c".into(), members(&["z", "w", "u"]));
+
+        let args = |args: &[&str]| RespArray::new(members(args));
+
+        let cmd = SMIsMember::try_from(args(&["smismember", "a", "x", "v", "x"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![
+                RespFrame::Integer(1),
+                RespFrame::Integer(0),
+                RespFrame::Integer(1)
+            ])
+            .into()
+        );
+        let cmd = SMIsMember::try_from(args(&["smismember", "nope", "x"]))?;
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![RespFrame::Integer(0)]).into()
+        );
+        assert!(SMIsMember::try_from(args(&["smismember", "a"])).is_err());
+
+        for (cmd, expected) in [
+            (&["sintercard", "1", "a"][..], 4),
+            (&["sintercard", "2", "a", "b"], 3),
+            (&["sintercard", "3", "a", "b", "c"], 2),
+            (&["sintercard", "3", "a", "b", "c", "LIMIT", "1"], 1),
+            (&["sintercard", "2", "a", "b", "limit", "0"], 3),
+            (&["sintercard", "2", "a", "nope"], 0),
+        ] {
+            let cmd = SInterCard::try_from(args(cmd))?;
+            assert_eq!(
+                cmd.execute(&backend, &mut Session::default()).await,
+                RespFrame::Integer(expected)
+            );
+        }
+        for cmd in [
+            &["sintercard", "0", "a"][..],
+            &["sintercard", "3", "a", "b"],
+            &["sintercard", "1", "a", "limit", "-1"],
+            &["sintercard", "1", "a", "b"],
+        ] {
+            assert!(SInterCard::try_from(args(cmd)).is_err());
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,143 @@
+use super::{
+    extract_args, validate_command, CommandError, CommandExecutor, Slowlog, SlowlogSubcommand,
+    RESP_OK,
+};
+use crate::{RespArray, RespFrame};
+
+const SLOWLOG_GET_DEFAULT_COUNT: usize = 10;
+
+impl CommandExecutor for Slowlog {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match self.subcommand {
+            SlowlogSubcommand::Get(count) => {
+                let entries = backend.slowlog.get(count);
+                RespArray::new(entries.into_iter().map(RespFrame::from).collect::<Vec<_>>()).into()
+            }
+            SlowlogSubcommand::Len => RespFrame::Integer(backend.slowlog.len() as i64),
+            SlowlogSubcommand::Reset => {
+                backend.slowlog.reset();
+                RESP_OK.clone()
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Slowlog {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["slowlog"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let subcommand = match args.next() {
+            Some(RespFrame::BulkString(s)) => s.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid subcommand".to_string(),
+                ))
+            }
+        };
+        let subcommand = match (subcommand.as_slice(), args.next(), args.next()) {
+            (b"get", None, None) => SlowlogSubcommand::Get(Some(SLOWLOG_GET_DEFAULT_COUNT)),
+            (b"get", Some(RespFrame::BulkString(count)), None) => {
+                let count: i64 = String::from_utf8(Vec::from(count.0))?
+                    .parse()
+                    .map_err(|_| {
+                        CommandError::InvalidArgument("count should be an integer".to_string())
+                    })?;
+                match count {
+                    -1 => SlowlogSubcommand::Get(None),
+                    c if c >= 0 => SlowlogSubcommand::Get(Some(c as usize)),
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "count should be greater than or equal to -1".to_string(),
+                        ))
+                    }
+                }
+            }
+            (b"len", None, None) => SlowlogSubcommand::Len,
+            (b"reset", None, None) => SlowlogSubcommand::Reset,
+            (s, _, _) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unknown subcommand or wrong number of arguments for '{}'",
+                    String::from_utf8_lossy(s)
+                )))
+            }
+        };
+        Ok(Slowlog { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Session;
+    use crate::{Backend, BulkString, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::time::Duration;
+
+    #[test]
+    fn test_slowlog_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$7\r\nslowlog\r\n$3\r\nGET\r\n$2\r\n-1\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Slowlog = frame.try_into()?;
+        assert_eq!(result.subcommand, SlowlogSubcommand::Get(None));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$7\r\nslowlog\r\n$3\r\nlen\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Slowlog = frame.try_into()?;
+        assert_eq!(result.subcommand, SlowlogSubcommand::Len);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_slowlog_commands() {
+        let backend = Backend::new();
+        backend.slowlog.set_log_slower_than(0);
+        let args = vec![BulkString::from("get").into(), BulkString::from("k").into()];
+        backend
+            .slowlog
+            .record(&args, Duration::from_micros(5), "127.0.0.1:6379");
+
+        let cmd = Slowlog {
+            subcommand: SlowlogSubcommand::Len,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(1)
+        );
+
+        let cmd = Slowlog {
+            subcommand: SlowlogSubcommand::Get(None),
+        };
+        let expected = RespArray::new(vec![RespArray::new(vec![
+            RespFrame::Integer(0),
+            RespFrame::Integer(backend.slowlog.get(None)[0].timestamp as i64),
+            RespFrame::Integer(5),
+            RespArray::new(args).into(),
+            BulkString::from("127.0.0.1:6379").into(),
+            BulkString::from("").into(),
+        ])
+        .into()]);
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            expected.into()
+        );
+
+        let cmd = Slowlog {
+            subcommand: SlowlogSubcommand::Reset,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RESP_OK.clone()
+        );
+        assert!(backend.slowlog.is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+use super::{extract_args, extract_integer, validate_command, CommandError, CommandExecutor, Sort};
+use crate::{RespArray, RespFrame, SimpleError, SortOptions, NOTIFY_LIST};
+
+impl CommandExecutor for Sort {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let sorted = match backend.sort(&self.key, &self.options) {
+            Ok(sorted) => sorted,
+            Err(e) => return SimpleError::new(e).into(),
+        };
+        match self.store {
+            Some(dest) => {
+                let stored = backend.sort_store(dest.clone(), sorted);
+                if stored > 0 {
+                    backend.notify_keyspace_event(NOTIFY_LIST, "sortstore", &dest);
+                }
+                RespFrame::Integer(stored as i64)
+            }
+            None => RespArray::new(sorted).into(),
+        }
+    }
+}
+
+// SORT key [BY pattern] [LIMIT offset count] [GET pattern [GET pattern ...]] [ASC | DESC]
+// [ALPHA] [STORE destination], the options in any order. SORT is a write for the STORE it
+// may have, SORT_RO takes the same options but STORE, for read-only replicas.
+impl TryFrom<RespArray> for Sort {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(name)) if name.eq_ignore_ascii_case(b"sort_ro") => "sort_ro",
+            _ => "sort",
+        };
+        validate_command(&value, &[name])?;
+        let syntax = || CommandError::InvalidArgument("syntax error".to_string());
+        let mut args = extract_args(value, 1)?.into_iter();
+        let Some(RespFrame::BulkString(key)) = args.next() else {
+            return Err(CommandError::InvalidArgument("Invalid key".to_string()));
+        };
+        let mut options = SortOptions::default();
+        let mut store = None;
+        while let Some(arg) = args.next() {
+            let RespFrame::BulkString(arg) = arg else {
+                return Err(syntax());
+            };
+            match arg.to_ascii_lowercase().as_slice() {
+                b"asc" => options.desc = false,
+                b"desc" => options.desc = true,
+                b"alpha" => options.alpha = true,
+                b"limit" => {
+                    let (Some(offset), Some(count)) = (args.next(), args.next()) else {
+                        return Err(syntax());
+                    };
+                    options.limit = Some((extract_integer(offset)?, extract_integer(count)?));
+                }
+                b"store" if name == "sort_ro" => return Err(syntax()),
+                option @ (b"by" | b"get" | b"store") => {
+                    let Some(RespFrame::BulkString(arg)) = args.next() else {
+                        return Err(syntax());
+                    };
+                    match option {
+                        b"by" => options.by = Some(arg.to_vec()),
+                        b"get" => options.get.push(arg.to_vec()),
+                        _ => store = Some(arg.into()),
+                    }
+                }
+                _ => return Err(syntax()),
+            }
+        }
+        Ok(Sort {
+            key: key.into(),
+            options,
+            store,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, BulkString, Key, Session};
+    use anyhow::Result;
+
+    fn command(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_sort() -> Result<()> {
+        let sort: Sort = command(&[
+            "sort", "l", "BY", "w_*", "limit", "0", "2", "get", "#", "GET", "h_*->f", "desc",
+            "alpha", "store", "d",
+        ])
+        .try_into()?;
+        assert_eq!(sort.key, Key::from("l"));
+        assert_eq!(sort.store, Some(Key::from("d")));
+        assert_eq!(
+            sort.options,
+            SortOptions {
+                by: Some(b"w_*".to_vec()),
+                limit: Some((0, 2)),
+                get: vec![b"#".to_vec(), b"h_*->f".to_vec()],
+                desc: true,
+                alpha: true,
+            }
+        );
+        assert!(Sort::try_from(command(&["sort", "l", "limit", "0"])).is_err());
+        assert!(Sort::try_from(command(&["sort", "l", "nope"])).is_err());
+
+        let backend = Backend::new();
+        backend.rpush(
+            "l".into(),
+            vec![
+                BulkString::from("2").into(),
+                BulkString::from("1").into(),
+                BulkString::from("3").into(),
+            ],
+        );
+        let sort: Sort = command(&["sort", "l", "desc"]).try_into()?;
+        assert_eq!(
+            sort.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![
+                BulkString::from("3").into(),
+                BulkString::from("2").into(),
+                BulkString::from("1").into(),
+            ])
+            .into()
+        );
+        let sort: Sort = command(&["sort", "l", "store", "l"]).try_into()?;
+        assert_eq!(
+            sort.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(3)
+        );
+        assert_eq!(
+            backend.lrange(b"l", 0, -1),
+            vec![
+                BulkString::from("1").into(),
+                BulkString::from("2").into(),
+                BulkString::from("3").into(),
+            ]
+        );
+        Ok(())
+    }
+}
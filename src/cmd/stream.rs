@@ -0,0 +1,498 @@
+use super::{
+    extract_args, extract_integer, extract_timeout, validate_command, CommandError,
+    CommandExecutor, TimeUnit, XAdd, XLen, XRange, XRead, XRevRange,
+};
+use crate::{
+    BulkString, Key, RespArray, RespFrame, RespNullArray, SimpleError, StreamFields, StreamId,
+    StreamIdSpec, NOTIFY_STREAM,
+};
+
+impl CommandExecutor for XAdd {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.xadd(self.key.clone(), self.id, self.fields) {
+            Ok(id) => {
+                backend.notify_keyspace_event(NOTIFY_STREAM, "xadd", &self.key);
+                BulkString::from(id.to_string()).into()
+            }
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl CommandExecutor for XRange {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        entries_reply(backend.xrange(&self.key, self.start, self.end, self.count, false))
+    }
+}
+
+impl CommandExecutor for XRevRange {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        entries_reply(backend.xrange(&self.key, self.start, self.end, self.count, true))
+    }
+}
+
+impl CommandExecutor for XLen {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.xlen(&self.key) as i64)
+    }
+}
+
+impl CommandExecutor for XRead {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let streams: Vec<(Key, StreamId)> = self
+            .streams
+            .into_iter()
+            .map(|(key, id)| {
+                let id = id.unwrap_or_else(|| backend.stream_last_id(&key));
+                (key, id)
+            })
+            .collect();
+
+        let ret = match self.block {
+            Some(block) => {
+                let timeout = (!block.is_zero()).then_some(block);
+                backend.blocking_xread(&streams, self.count, timeout).await
+            }
+            None => backend.xread(&streams, self.count),
+        };
+        streams_reply(ret)
+    }
+}
+
+// each stream is a two elements array of the key and its entries, nil when there are none
+pub(super) fn streams_reply(streams: Vec<(Key, Vec<(StreamId, StreamFields)>)>) -> RespFrame {
+    if streams.is_empty() {
+        return RespNullArray.into();
+    }
+    RespArray::new(
+        streams
+            .into_iter()
+            .map(|(key, entries)| {
+                RespArray::new(vec![BulkString::from(key).into(), entries_reply(entries)]).into()
+            })
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+// each entry is a two elements array of the ID and the flat list of fields and values
+pub(super) fn entries_reply(entries: Vec<(StreamId, StreamFields)>) -> RespFrame {
+    RespArray::new(
+        entries
+            .into_iter()
+            .map(|(id, fields)| {
+                let fields = fields
+                    .into_iter()
+                    .flat_map(|(field, value)| [BulkString::from(field).into(), value])
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(vec![
+                    BulkString::from(id.to_string()).into(),
+                    RespArray::new(fields).into(),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+impl TryFrom<RespArray> for XAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xadd"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, id) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(id))) => {
+                let id = String::from_utf8(Vec::from(id.0))?;
+                let id = match id.as_str() {
+                    "*" => StreamIdSpec::Auto,
+                    id => match id.strip_suffix("-*") {
+                        Some(ms) => StreamIdSpec::AutoSeq(ms.parse().map_err(|_| {
+                            CommandError::InvalidArgument(
+                                "Invalid stream ID specified as stream command argument"
+                                    .to_string(),
+                            )
+                        })?),
+                        None => StreamIdSpec::Explicit(
+                            id.parse().map_err(CommandError::InvalidArgument)?,
+                        ),
+                    },
+                };
+                (key.into(), id)
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid key or ID".to_string(),
+                ))
+            }
+        };
+
+        let mut fields = Vec::new();
+        while let Some(field) = args.next() {
+            match (field, args.next()) {
+                (RespFrame::BulkString(field), Some(value)) => {
+                    fields.push((Vec::from(field), value))
+                }
+                _ => return Err(CommandError::WrongArity("xadd".to_string())),
+            }
+        }
+        if fields.is_empty() {
+            return Err(CommandError::WrongArity("xadd".to_string()));
+        }
+        Ok(XAdd { key, id, fields })
+    }
+}
+
+impl TryFrom<RespArray> for XRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xrange"])?;
+        let (key, start, end, count) = extract_range(value, false)?;
+        Ok(XRange {
+            key,
+            start,
+            end,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRevRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xrevrange"])?;
+        let (key, start, end, count) = extract_range(value, true)?;
+        Ok(XRevRange {
+            key,
+            end,
+            start,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xlen"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match args.next() {
+            Some(RespFrame::BulkString(key)) => Ok(XLen { key: key.into() }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for XRead {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xread"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (mut count, mut block) = (None, None);
+        loop {
+            let option = match args.next() {
+                Some(RespFrame::BulkString(option)) => option.to_ascii_lowercase(),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "syntax error, STREAMS is missing".to_string(),
+                    ))
+                }
+            };
+            match (option.as_slice(), args.next()) {
+                (b"count", Some(n)) => count = Some(extract_count(n)?),
+                (b"block", Some(ms)) => block = Some(extract_timeout(ms, TimeUnit::Millis)?),
+                (b"streams", Some(first)) => {
+                    let rest: Vec<RespFrame> = std::iter::once(first).chain(args).collect();
+                    return Ok(XRead {
+                        count,
+                        block,
+                        streams: extract_streams(rest, "xread", "$")?,
+                    });
+                }
+                _ => {
+                    return Err(CommandError::InvalidArgument("syntax error".to_string()));
+                }
+            }
+        }
+    }
+}
+
+// key, start, end and COUNT of XRANGE, XREVRANGE takes end before start
+fn extract_range(
+    value: RespArray,
+    rev: bool,
+) -> Result<(Key, StreamId, StreamId, Option<usize>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let (key, first, second) = match (args.next(), args.next(), args.next()) {
+        (Some(RespFrame::BulkString(key)), Some(first), Some(second)) => {
+            (key.into(), first, second)
+        }
+        _ => {
+            return Err(CommandError::InvalidArgument(
+                "Invalid key, start or end".to_string(),
+            ))
+        }
+    };
+    let (start, end) = match rev {
+        false => (
+            extract_range_bound(first, true)?,
+            extract_range_bound(second, false)?,
+        ),
+        true => (
+            extract_range_bound(second, true)?,
+            extract_range_bound(first, false)?,
+        ),
+    };
+
+    let count = match (args.next(), args.next(), args.next()) {
+        (None, _, _) => None,
+        (Some(RespFrame::BulkString(option)), Some(n), None)
+            if option.eq_ignore_ascii_case(b"count") =>
+        {
+            Some(extract_count(n)?)
+        }
+        _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+    };
+    Ok((key, start, end, count))
+}
+
+// `-` and `+` are the smallest and greatest IDs, `(` makes the bound exclusive and
+// an ID without sequence number covers the whole millisecond
+pub(super) fn extract_range_bound(frame: RespFrame, start: bool) -> Result<StreamId, CommandError> {
+    let bound = match frame {
+        RespFrame::BulkString(s) => String::from_utf8(Vec::from(s.0))?,
+        _ => return Err(CommandError::InvalidArgument("Invalid ID".to_string())),
+    };
+    let invalid = || {
+        CommandError::InvalidArgument("invalid start or end ID for XRANGE/XREVRANGE".to_string())
+    };
+    match bound.as_str() {
+        "-" => Ok(StreamId::MIN),
+        "+" => Ok(StreamId::MAX),
+        bound => {
+            let (exclusive, bound) = match bound.strip_prefix('(') {
+                Some(bound) => (true, bound),
+                None => (false, bound),
+            };
+            let mut id: StreamId = bound.parse().map_err(CommandError::InvalidArgument)?;
+            if !start && !bound.contains('-') {
+                id.seq = u64::MAX;
+            }
+            match (exclusive, start) {
+                (false, _) => Ok(id),
+                (true, true) => id.next().ok_or_else(invalid),
+                (true, false) => id.prev().ok_or_else(invalid),
+            }
+        }
+    }
+}
+
+pub(super) fn extract_count(frame: RespFrame) -> Result<usize, CommandError> {
+    let n = extract_integer(frame)?;
+    if n < 0 {
+        return Err(CommandError::InvalidArgument(
+            "value is out of range, must be positive".to_string(),
+        ));
+    }
+    Ok(n as usize)
+}
+
+// after STREAMS come all the keys, then one ID for every key. The special ID (`$` for XREAD,
+// `>` for XREADGROUP) is None.
+pub(super) fn extract_streams(
+    args: Vec<RespFrame>,
+    command: &str,
+    special: &str,
+) -> Result<Vec<(Key, Option<StreamId>)>, CommandError> {
+    if !args.len().is_multiple_of(2) {
+        return Err(CommandError::InvalidArgument(format!(
+            "Unbalanced '{}' list of streams: for each stream key an ID or '{}' must be specified",
+            command, special
+        )));
+    }
+    let mut keys = args;
+    let ids = keys.split_off(keys.len() / 2);
+    keys.into_iter()
+        .zip(ids)
+        .map(|(key, id)| match (key, id) {
+            (RespFrame::BulkString(key), RespFrame::BulkString(id)) => {
+                let id = String::from_utf8(Vec::from(id.0))?;
+                let id = match id.as_str() {
+                    id if id == special => None,
+                    id => Some(id.parse().map_err(CommandError::InvalidArgument)?),
+                };
+                Ok((key.into(), id))
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or ID".to_string(),
+            )),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecode, Session};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::time::Duration;
+
+    #[test]
+    fn test_xadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nxadd\r\n$1\r\ns\r\n$3\r\n5-*\r\n$1\r\na\r\n$1\r\n1\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: XAdd = frame.try_into()?;
+        assert_eq!(result.key, "s");
+        assert_eq!(result.id, StreamIdSpec::AutoSeq(5));
+        assert_eq!(
+            result.fields,
+            vec![(b"a".to_vec(), BulkString::from("1").into())]
+        );
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nxadd\r\n$1\r\ns\r\n$1\r\n*\r\n$1\r\na\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(XAdd::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xrange_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$6\r\nxrange\r\n$1\r\ns\r\n$2\r\n(5\r\n$1\r\n7\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: XRange = frame.try_into()?;
+        assert_eq!(result.start, StreamId::new(5, 1));
+        assert_eq!(result.end, StreamId::new(7, u64::MAX));
+        assert_eq!(result.count, Some(2));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$9\r\nxrevrange\r\n$1\r\ns\r\n$1\r\n+\r\n$1\r\n-\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: XRevRange = frame.try_into()?;
+        assert_eq!(result.start, StreamId::MIN);
+        assert_eq!(result.end, StreamId::MAX);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xread_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*8\r\n$5\r\nxread\r\n$5\r\nBLOCK\r\n$3\r\n100\r\n$7\r\nstreams\r\n$1\r\na\r\n$1\r\nb\r\n$3\r\n1-1\r\n$1\r\n$\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: XRead = frame.try_into()?;
+        assert_eq!(result.count, None);
+        assert_eq!(result.block, Some(Duration::from_millis(100)));
+        assert_eq!(
+            result.streams,
+            vec![("a".into(), Some(StreamId::new(1, 1))), ("b".into(), None)]
+        );
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$5\r\nxread\r\n$7\r\nstreams\r\n$1\r\na\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(XRead::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_commands() {
+        let backend = Backend::new();
+        let cmd = XAdd {
+            key: "s".into(),
+            id: StreamIdSpec::Explicit(StreamId::new(1, 1)),
+            fields: vec![(b"a".to_vec(), BulkString::from("1").into())],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            BulkString::from("1-1").into()
+        );
+
+        let cmd = XAdd {
+            key: "s".into(),
+            id: StreamIdSpec::Explicit(StreamId::new(1, 1)),
+            fields: vec![(b"a".to_vec(), BulkString::from("2").into())],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            SimpleError::new(
+                "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+            )
+            .into()
+        );
+
+        let cmd = XLen { key: "s".into() };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(1)
+        );
+
+        let entry: RespFrame = RespArray::new(vec![
+            BulkString::from("1-1").into(),
+            RespArray::new(vec![
+                BulkString::from("a").into(),
+                BulkString::from("1").into(),
+            ])
+            .into(),
+        ])
+        .into();
+        let cmd = XRange {
+            key: "s".into(),
+            start: StreamId::MIN,
+            end: StreamId::MAX,
+            count: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![entry.clone()]).into()
+        );
+
+        let cmd = XRead {
+            count: None,
+            block: None,
+            streams: vec![("s".into(), Some(StreamId::MIN))],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![RespArray::new(vec![
+                BulkString::from("s").into(),
+                RespArray::new(vec![entry]).into(),
+            ])
+            .into()])
+            .into()
+        );
+
+        // `$` only sees entries added after the command
+        let cmd = XRead {
+            count: None,
+            block: Some(Duration::from_millis(10)),
+            streams: vec![("s".into(), None)],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespNullArray.into()
+        );
+    }
+}
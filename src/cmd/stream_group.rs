@@ -0,0 +1,839 @@
+use super::{
+    extract_args, extract_timeout,
+    stream::{entries_reply, extract_count, extract_range_bound, extract_streams, streams_reply},
+    validate_command, CommandError, CommandExecutor, TimeUnit, XAck, XAutoClaim, XClaim, XGroup,
+    XGroupSubcommand, XPending, XPendingRange, XReadGroup, RESP_OK,
+};
+use crate::{
+    BulkString, Key, RespArray, RespFrame, RespNull, SimpleError, StreamId, NOTIFY_STREAM,
+};
+
+const DEFAULT_AUTOCLAIM_COUNT: usize = 100;
+
+impl CommandExecutor for XGroup {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let (ret, event) = match self.subcommand {
+            XGroupSubcommand::Create { id, mkstream } => (
+                backend
+                    .xgroup_create(&self.key, &self.group, id, mkstream)
+                    .map(|_| RESP_OK.clone()),
+                "xgroup-create",
+            ),
+            XGroupSubcommand::Destroy => (
+                Ok(RespFrame::Integer(
+                    backend.xgroup_destroy(&self.key, &self.group) as i64,
+                )),
+                "xgroup-destroy",
+            ),
+            XGroupSubcommand::CreateConsumer(consumer) => (
+                backend
+                    .xgroup_createconsumer(&self.key, &self.group, &consumer)
+                    .map(|created| RespFrame::Integer(created as i64)),
+                "xgroup-createconsumer",
+            ),
+            XGroupSubcommand::DelConsumer(consumer) => (
+                backend
+                    .xgroup_delconsumer(&self.key, &self.group, &consumer)
+                    .map(|pending| RespFrame::Integer(pending as i64)),
+                "xgroup-delconsumer",
+            ),
+        };
+        match ret {
+            Ok(ret) => {
+                backend.notify_keyspace_event(NOTIFY_STREAM, event, &self.key);
+                ret
+            }
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for XReadGroup {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let ret = match self.block {
+            Some(block) => {
+                let timeout = (!block.is_zero()).then_some(block);
+                backend
+                    .blocking_xreadgroup(
+                        &self.group,
+                        &self.consumer,
+                        &self.streams,
+                        self.count,
+                        self.noack,
+                        timeout,
+                    )
+                    .await
+            }
+            None => backend.xreadgroup(
+                &self.group,
+                &self.consumer,
+                &self.streams,
+                self.count,
+                self.noack,
+            ),
+        };
+        match ret {
+            Ok(ret) => streams_reply(ret),
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for XAck {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.xack(&self.key, &self.group, &self.ids) as i64)
+    }
+}
+
+impl CommandExecutor for XPending {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let id_or_null = |id: Option<StreamId>| match id {
+            Some(id) => BulkString::from(id.to_string()).into(),
+            None => RespFrame::Null(RespNull),
+        };
+        match self.range {
+            // count, smallest and greatest IDs, then every consumer with its number of entries
+            None => match backend.xpending_summary(&self.key, &self.group) {
+                Ok(summary) => {
+                    let consumers = match summary.consumers.is_empty() {
+                        true => RespFrame::Null(RespNull),
+                        false => RespArray::new(
+                            summary
+                                .consumers
+                                .into_iter()
+                                .map(|(consumer, count)| {
+                                    RespArray::new(vec![
+                                        BulkString::from(consumer).into(),
+                                        BulkString::from(count.to_string()).into(),
+                                    ])
+                                    .into()
+                                })
+                                .collect::<Vec<RespFrame>>(),
+                        )
+                        .into(),
+                    };
+                    RespArray::new(vec![
+                        RespFrame::Integer(summary.count as i64),
+                        id_or_null(summary.range.map(|(first, _)| first)),
+                        id_or_null(summary.range.map(|(_, last)| last)),
+                        consumers,
+                    ])
+                    .into()
+                }
+                Err(e) => SimpleError::new(e).into(),
+            },
+            // ID, consumer, idle time and delivery count of every entry
+            Some(range) => match backend.xpending_range(
+                &self.key,
+                &self.group,
+                range.start,
+                range.end,
+                range.count,
+                range.consumer.as_deref(),
+                range.min_idle,
+            ) {
+                Ok(pending) => RespArray::new(
+                    pending
+                        .into_iter()
+                        .map(|detail| {
+                            RespArray::new(vec![
+                                BulkString::from(detail.id.to_string()).into(),
+                                BulkString::from(detail.consumer).into(),
+                                RespFrame::Integer(detail.idle as i64),
+                                RespFrame::Integer(detail.delivery_count as i64),
+                            ])
+                            .into()
+                        })
+                        .collect::<Vec<RespFrame>>(),
+                )
+                .into(),
+                Err(e) => SimpleError::new(e).into(),
+            },
+        }
+    }
+}
+
+impl CommandExecutor for XClaim {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.xclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            &self.ids,
+            self.justid,
+        ) {
+            Ok(claimed) => {
+                if !claimed.is_empty() {
+                    backend.notify_keyspace_event(NOTIFY_STREAM, "xclaim", &self.key);
+                }
+                match self.justid {
+                    true => RespArray::new(
+                        claimed
+                            .into_iter()
+                            .map(|(id, _)| BulkString::from(id.to_string()).into())
+                            .collect::<Vec<RespFrame>>(),
+                    )
+                    .into(),
+                    false => entries_reply(claimed),
+                }
+            }
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+// the cursor to continue from, the claimed entries (their IDs with JUSTID) and the IDs of the
+// pending entries that were deleted from the stream
+impl CommandExecutor for XAutoClaim {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.xautoclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            self.start,
+            self.count,
+            self.justid,
+        ) {
+            Ok(ret) => {
+                if !ret.claimed.is_empty() || !ret.deleted.is_empty() {
+                    backend.notify_keyspace_event(NOTIFY_STREAM, "xautoclaim", &self.key);
+                }
+                let ids = |ids: Vec<StreamId>| {
+                    RespArray::new(
+                        ids.into_iter()
+                            .map(|id| BulkString::from(id.to_string()).into())
+                            .collect::<Vec<RespFrame>>(),
+                    )
+                    .into()
+                };
+                let claimed = match self.justid {
+                    true => ids(ret.claimed.into_iter().map(|(id, _)| id).collect()),
+                    false => entries_reply(ret.claimed),
+                };
+                RespArray::new(vec![
+                    BulkString::from(ret.next.to_string()).into(),
+                    claimed,
+                    ids(ret.deleted),
+                ])
+                .into()
+            }
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for XGroup {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xgroup"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (subcommand, key, group) = match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(subcommand)),
+                Some(RespFrame::BulkString(key)),
+                Some(RespFrame::BulkString(group)),
+            ) => (
+                subcommand.to_ascii_lowercase(),
+                key.into(),
+                Vec::from(group),
+            ),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid subcommand, key or group".to_string(),
+                ))
+            }
+        };
+        let rest: Vec<RespFrame> = args.collect();
+        let subcommand = match (subcommand.as_slice(), rest.as_slice()) {
+            (b"create", [RespFrame::BulkString(id), options @ ..]) => {
+                let mkstream = match options {
+                    [] => false,
+                    [RespFrame::BulkString(option)] if option.eq_ignore_ascii_case(b"mkstream") => {
+                        true
+                    }
+                    _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                };
+                let id = match id.as_ref() {
+                    b"$" => None,
+                    id => Some(
+                        String::from_utf8(id.to_vec())?
+                            .parse()
+                            .map_err(CommandError::InvalidArgument)?,
+                    ),
+                };
+                XGroupSubcommand::Create { id, mkstream }
+            }
+            (b"destroy", []) => XGroupSubcommand::Destroy,
+            (b"createconsumer", [RespFrame::BulkString(consumer)]) => {
+                XGroupSubcommand::CreateConsumer(consumer.to_vec())
+            }
+            (b"delconsumer", [RespFrame::BulkString(consumer)]) => {
+                XGroupSubcommand::DelConsumer(consumer.to_vec())
+            }
+            (s, _) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Unknown subcommand or wrong number of arguments for '{}'",
+                    String::from_utf8_lossy(s)
+                )))
+            }
+        };
+        Ok(XGroup {
+            subcommand,
+            key,
+            group,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XReadGroup {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xreadgroup"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (group, consumer) = match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(option)),
+                Some(RespFrame::BulkString(group)),
+                Some(RespFrame::BulkString(consumer)),
+            ) if option.eq_ignore_ascii_case(b"group") => (Vec::from(group), Vec::from(consumer)),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Missing GROUP option for XREADGROUP".to_string(),
+                ))
+            }
+        };
+
+        let (mut count, mut block, mut noack) = (None, None, false);
+        loop {
+            let option = match args.next() {
+                Some(RespFrame::BulkString(option)) => option.to_ascii_lowercase(),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "syntax error, STREAMS is missing".to_string(),
+                    ))
+                }
+            };
+            if option == b"noack" {
+                noack = true;
+                continue;
+            }
+            match (option.as_slice(), args.next()) {
+                (b"count", Some(n)) => count = Some(extract_count(n)?),
+                (b"block", Some(ms)) => block = Some(extract_timeout(ms, TimeUnit::Millis)?),
+                (b"streams", Some(first)) => {
+                    let rest: Vec<RespFrame> = std::iter::once(first).chain(args).collect();
+                    return Ok(XReadGroup {
+                        group,
+                        consumer,
+                        count,
+                        block,
+                        noack,
+                        streams: extract_streams(rest, "xreadgroup", ">")?,
+                    });
+                }
+                _ => {
+                    return Err(CommandError::InvalidArgument("syntax error".to_string()));
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for XAck {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xack"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, group) = extract_key_and_group(&mut args)?;
+        let ids = extract_ids(args)?;
+        if ids.is_empty() {
+            return Err(CommandError::WrongArity("xack".to_string()));
+        }
+        Ok(XAck { key, group, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XPending {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xpending"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, group) = extract_key_and_group(&mut args)?;
+        let mut rest: Vec<RespFrame> = args.collect();
+        if rest.is_empty() {
+            return Ok(XPending {
+                key,
+                group,
+                range: None,
+            });
+        }
+
+        let min_idle = match rest.first() {
+            Some(RespFrame::BulkString(option)) if option.eq_ignore_ascii_case(b"idle") => {
+                if rest.len() < 2 {
+                    return Err(CommandError::InvalidArgument("syntax error".to_string()));
+                }
+                let idle = rest.remove(1);
+                rest.remove(0);
+                extract_count(idle)? as u64
+            }
+            _ => 0,
+        };
+        let mut rest = rest.into_iter();
+        let range = match (
+            rest.next(),
+            rest.next(),
+            rest.next(),
+            rest.next(),
+            rest.next(),
+        ) {
+            (Some(start), Some(end), Some(count), consumer, None) => XPendingRange {
+                min_idle,
+                start: extract_range_bound(start, true)?,
+                end: extract_range_bound(end, false)?,
+                count: extract_count(count)?,
+                consumer: match consumer {
+                    Some(RespFrame::BulkString(consumer)) => Some(Vec::from(consumer)),
+                    Some(_) => {
+                        return Err(CommandError::InvalidArgument(
+                            "Invalid consumer".to_string(),
+                        ))
+                    }
+                    None => None,
+                },
+            },
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(XPending {
+            key,
+            group,
+            range: Some(range),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XClaim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xclaim"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, group) = extract_key_and_group(&mut args)?;
+        let (consumer, min_idle) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(consumer)), Some(min_idle)) => {
+                (Vec::from(consumer), extract_count(min_idle)? as u64)
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid consumer or min-idle-time".to_string(),
+                ))
+            }
+        };
+
+        let mut ids: Vec<RespFrame> = args.collect();
+        let justid = match ids.last() {
+            Some(RespFrame::BulkString(option)) if option.eq_ignore_ascii_case(b"justid") => {
+                ids.pop();
+                true
+            }
+            _ => false,
+        };
+        let ids = extract_ids(ids.into_iter())?;
+        if ids.is_empty() {
+            return Err(CommandError::WrongArity("xclaim".to_string()));
+        }
+        Ok(XClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            justid,
+        })
+    }
+}
+
+// XAUTOCLAIM key group consumer min-idle-time start [COUNT count] [JUSTID]
+impl TryFrom<RespArray> for XAutoClaim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xautoclaim"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, group) = extract_key_and_group(&mut args)?;
+        let (consumer, min_idle, start) = match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(consumer)), Some(min_idle), Some(start)) => (
+                Vec::from(consumer),
+                extract_count(min_idle)? as u64,
+                extract_range_bound(start, true)?,
+            ),
+            _ => return Err(CommandError::WrongArity("xautoclaim".to_string())),
+        };
+
+        let mut count = DEFAULT_AUTOCLAIM_COUNT;
+        let mut justid = false;
+        while let Some(arg) = args.next() {
+            match arg {
+                RespFrame::BulkString(option) if option.eq_ignore_ascii_case(b"count") => {
+                    count = args.next().map(extract_count).transpose()?.unwrap_or(0);
+                    if count == 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "COUNT must be > 0".to_string(),
+                        ));
+                    }
+                }
+                RespFrame::BulkString(option) if option.eq_ignore_ascii_case(b"justid") => {
+                    justid = true
+                }
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(XAutoClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            start,
+            count,
+            justid,
+        })
+    }
+}
+
+fn extract_key_and_group(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<(Key, Vec<u8>), CommandError> {
+    match (args.next(), args.next()) {
+        (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(group))) => {
+            Ok((key.into(), Vec::from(group)))
+        }
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid key or group".to_string(),
+        )),
+    }
+}
+
+fn extract_ids(args: impl Iterator<Item = RespFrame>) -> Result<Vec<StreamId>, CommandError> {
+    args.map(|id| match id {
+        RespFrame::BulkString(id) => String::from_utf8(Vec::from(id.0))?
+            .parse()
+            .map_err(CommandError::InvalidArgument),
+        _ => Err(CommandError::InvalidArgument("Invalid ID".to_string())),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecode, Session, StreamIdSpec};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_xgroup_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$6\r\nxgroup\r\n$6\r\nCREATE\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\n$\r\n$8\r\nMKSTREAM\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: XGroup = frame.try_into()?;
+        assert_eq!(result.key, "s");
+        assert_eq!(result.group, b"g");
+        assert_eq!(
+            result.subcommand,
+            XGroupSubcommand::Create {
+                id: None,
+                mkstream: true
+            }
+        );
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nxgroup\r\n$7\r\ndestroy\r\n$1\r\ns\r\n$1\r\ng\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: XGroup = frame.try_into()?;
+        assert_eq!(result.subcommand, XGroupSubcommand::Destroy);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nxgroup\r\n$5\r\nsetid\r\n$1\r\ns\r\n$1\r\ng\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(XGroup::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xreadgroup_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*10\r\n$10\r\nxreadgroup\r\n$5\r\nGROUP\r\n$1\r\ng\r\n$1\r\nc\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n$5\r\nNOACK\r\n$7\r\nSTREAMS\r\n$1\r\ns\r\n$1\r\n>\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: XReadGroup = frame.try_into()?;
+        assert_eq!(result.group, b"g");
+        assert_eq!(result.consumer, b"c");
+        assert_eq!(result.count, Some(2));
+        assert!(result.noack);
+        assert_eq!(result.streams, vec![("s".into(), None)]);
+
+        // `$` is only for XREAD
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$10\r\nxreadgroup\r\n$5\r\nGROUP\r\n$1\r\ng\r\n$1\r\nc\r\n$7\r\nSTREAMS\r\n$1\r\ns\r\n$1\r\n$\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(XReadGroup::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xpending_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*9\r\n$8\r\nxpending\r\n$1\r\ns\r\n$1\r\ng\r\n$4\r\nIDLE\r\n$2\r\n10\r\n$1\r\n-\r\n$1\r\n+\r\n$1\r\n5\r\n$1\r\nc\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: XPending = frame.try_into()?;
+        assert_eq!(
+            result.range,
+            Some(XPendingRange {
+                min_idle: 10,
+                start: StreamId::MIN,
+                end: StreamId::MAX,
+                count: 5,
+                consumer: Some(b"c".to_vec()),
+            })
+        );
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$8\r\nxpending\r\n$1\r\ns\r\n$1\r\ng\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: XPending = frame.try_into()?;
+        assert_eq!(result.range, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xautoclaim_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*9\r\n$10\r\nxautoclaim\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\nc\r\n$2\r\n10\r\n$3\r\n0-1\r\n$5\r\nCOUNT\r\n$1\r\n5\r\n$6\r\nJUSTID\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: XAutoClaim = frame.try_into()?;
+        assert_eq!(result.min_idle, 10);
+        assert_eq!(result.start, StreamId::new(0, 1));
+        assert_eq!(result.count, 5);
+        assert!(result.justid);
+
+        buf.extend_from_slice(
+            b"*6\r\n$10\r\nxautoclaim\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\nc\r\n$1\r\n0\r\n$1\r\n-\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: XAutoClaim = frame.try_into()?;
+        assert_eq!(result.count, DEFAULT_AUTOCLAIM_COUNT);
+        assert!(!result.justid);
+
+        buf.extend_from_slice(
+            b"*8\r\n$10\r\nxautoclaim\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\nc\r\n$1\r\n0\r\n$1\r\n-\r\n$5\r\ncount\r\n$1\r\n0\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(XAutoClaim::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_binary_fields_and_names() -> Result<()> {
+        let command = |args: &[&[u8]]| {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+        };
+        let backend = Backend::new();
+        let (field, group, consumer) = (b"\xfff", b"\xffg", b"\xffc");
+        let cmd: crate::cmd::XAdd = command(&[b"xadd", b"s", b"1-1", field, b"v"]).try_into()?;
+        cmd.execute(&backend, &mut Session::default()).await;
+        let cmd: XGroup = command(&[b"xgroup", b"create", b"s", group, b"0"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RESP_OK.clone()
+        );
+
+        let cmd: XReadGroup = command(&[
+            b"xreadgroup",
+            b"group",
+            group,
+            consumer,
+            b"streams",
+            b"s",
+            b">",
+        ])
+        .try_into()?;
+        let entry = RespArray::new(vec![
+            BulkString::from("1-1").into(),
+            RespArray::new(vec![
+                BulkString::from(field).into(),
+                BulkString::from("v").into(),
+            ])
+            .into(),
+        ]);
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![RespArray::new(vec![
+                BulkString::from("s").into(),
+                RespArray::new(vec![entry.into()]).into(),
+            ])
+            .into()])
+            .into()
+        );
+        let summary = backend.xpending_summary(b"s", group).unwrap();
+        assert_eq!(summary.consumers, vec![(consumer.to_vec(), 1)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_consumer_group_commands() {
+        let backend = Backend::new();
+        backend
+            .xadd(
+                "s".into(),
+                StreamIdSpec::Explicit(StreamId::new(1, 1)),
+                vec![(b"a".to_vec(), BulkString::from("1").into())],
+            )
+            .unwrap();
+
+        let create = || XGroup {
+            subcommand: XGroupSubcommand::Create {
+                id: Some(StreamId::MIN),
+                mkstream: false,
+            },
+            key: "s".into(),
+            group: b"g".to_vec(),
+        };
+        assert_eq!(
+            create().execute(&backend, &mut Session::default()).await,
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            create().execute(&backend, &mut Session::default()).await,
+            SimpleError::new("BUSYGROUP Consumer Group name already exists").into()
+        );
+
+        let entry: RespFrame = RespArray::new(vec![
+            BulkString::from("1-1").into(),
+            RespArray::new(vec![
+                BulkString::from("a").into(),
+                BulkString::from("1").into(),
+            ])
+            .into(),
+        ])
+        .into();
+        let cmd = XReadGroup {
+            group: b"g".to_vec(),
+            consumer: b"c".to_vec(),
+            count: None,
+            block: None,
+            noack: false,
+            streams: vec![("s".into(), None)],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![RespArray::new(vec![
+                BulkString::from("s").into(),
+                RespArray::new(vec![entry]).into(),
+            ])
+            .into()])
+            .into()
+        );
+
+        let cmd = XPending {
+            key: "s".into(),
+            group: b"g".to_vec(),
+            range: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![
+                RespFrame::Integer(1),
+                BulkString::from("1-1").into(),
+                BulkString::from("1-1").into(),
+                RespArray::new(vec![RespArray::new(vec![
+                    BulkString::from("c").into(),
+                    BulkString::from("1").into(),
+                ])
+                .into()])
+                .into(),
+            ])
+            .into()
+        );
+
+        let cmd = XClaim {
+            key: "s".into(),
+            group: b"g".to_vec(),
+            consumer: b"d".to_vec(),
+            min_idle: 0,
+            ids: vec![StreamId::new(1, 1)],
+            justid: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![BulkString::from("1-1").into()]).into()
+        );
+
+        let cmd = XAutoClaim {
+            key: "s".into(),
+            group: b"g".to_vec(),
+            consumer: b"e".to_vec(),
+            min_idle: 0,
+            start: StreamId::MIN,
+            count: 10,
+            justid: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![
+                BulkString::from("0-0").into(),
+                RespArray::new(vec![BulkString::from("1-1").into()]).into(),
+                RespArray::new(vec![]).into(),
+            ])
+            .into()
+        );
+
+        let cmd = XAck {
+            key: "s".into(),
+            group: b"g".to_vec(),
+            ids: vec![StreamId::new(1, 1)],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(1)
+        );
+
+        let cmd = XPending {
+            key: "s".into(),
+            group: b"nope".to_vec(),
+            range: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            SimpleError::new("NOGROUP No such key 's' or consumer group 'nope'").into()
+        );
+    }
+}
@@ -0,0 +1,533 @@
+// Command table: what the dispatcher needs to know about a command before running it,
+// how many arguments it takes, the positions of its keys and whether it writes. Like the key specs of Redis, keys are
+// found from the raw arguments, so nothing has to be parsed twice.
+
+use crate::{Key, KeyType, RespArray, RespFrame};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+// what kind of command it is, shared by everything that treats commands by kind: the
+// dispatcher, replication, read-only mode and the COMMAND introspection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CommandFlags(u32);
+
+impl CommandFlags {
+    pub const NONE: CommandFlags = CommandFlags(0);
+    // the command modifies its keys
+    pub const WRITE: CommandFlags = CommandFlags(1 << 0);
+    // the command may use more memory, it is refused when over maxmemory
+    pub const DENYOOM: CommandFlags = CommandFlags(1 << 1);
+    // the command may wait for other clients, it can't hold up replication while it does
+    pub const BLOCKING: CommandFlags = CommandFlags(1 << 2);
+    // the command inspects its keys without accessing them, their idle time and access
+    // frequency stay as they were
+    pub const NOTOUCH: CommandFlags = CommandFlags(1 << 3);
+    // the command reads its keys and never modifies them
+    pub const READONLY: CommandFlags = CommandFlags(1 << 4);
+    // the command administers the server rather than the data
+    pub const ADMIN: CommandFlags = CommandFlags(1 << 5);
+    // the command is part of publish/subscribe
+    pub const PUBSUB: CommandFlags = CommandFlags(1 << 6);
+
+    // the names of the flags, like COMMAND INFO shows them
+    const NAMES: &'static [(CommandFlags, &'static str)] = &[
+        (Self::WRITE, "write"),
+        (Self::READONLY, "readonly"),
+        (Self::DENYOOM, "denyoom"),
+        (Self::ADMIN, "admin"),
+        (Self::PUBSUB, "pubsub"),
+        (Self::BLOCKING, "blocking"),
+        (Self::NOTOUCH, "no_touch"),
+    ];
+
+    pub const fn union(self, other: CommandFlags) -> CommandFlags {
+        CommandFlags(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: CommandFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES
+            .iter()
+            .filter(move |(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+    }
+}
+
+impl std::ops::BitOr for CommandFlags {
+    type Output = CommandFlags;
+
+    fn bitor(self, other: CommandFlags) -> CommandFlags {
+        self.union(other)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySpec {
+    None,
+    // first key, last key (negative counts from the end) and step
+    Range(usize, isize, usize),
+    // the keys follow the STREAMS argument, they are the first half of what follows it
+    Streams,
+    // the argument at the index is the number of keys, which follow it
+    NumKeys(usize),
+    // the destination key at the index, then the number of source keys and the keys, like
+    // ZUNIONSTORE
+    DestNumKeys(usize),
+    // the key at the index, or the arguments after KEYS when it is empty, like MIGRATE
+    KeyOrKeys(usize),
+    // the key at the index and the destination after STORE, like SORT. The keys BY and GET
+    // refer to are left out, like in Redis.
+    KeyAndStore(usize),
+}
+
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    // the number of arguments with the name, or at least as many when negative, like the
+    // arity of Redis
+    pub arity: i32,
+    pub flags: CommandFlags,
+    pub keys: KeySpec,
+    // the type its keys must hold, None for the commands on keys of any type
+    pub key_type: Option<KeyType>,
+    // the first key is a destination replaced whatever it held, like ZUNIONSTORE's
+    pub stores: bool,
+}
+
+const W: CommandFlags = CommandFlags::WRITE;
+const WD: CommandFlags = W.union(CommandFlags::DENYOOM);
+const WB: CommandFlags = W.union(CommandFlags::BLOCKING);
+const WBD: CommandFlags = WB.union(CommandFlags::DENYOOM);
+const R: CommandFlags = CommandFlags::READONLY;
+const A: CommandFlags = CommandFlags::ADMIN;
+const P: CommandFlags = CommandFlags::PUBSUB;
+const NONE: CommandFlags = CommandFlags::NONE;
+const ONE: KeySpec = KeySpec::Range(1, 1, 1);
+const ALL: KeySpec = KeySpec::Range(1, -1, 1);
+
+pub(super) const COMMANDS: &[CommandSpec] = &[
+    spec("get", 2, R, ONE).of(KeyType::String),
+    // SET replaces a key of any type, with GET it refuses other types itself
+    spec("set", -3, WD, ONE),
+    spec("getrange", 4, R, ONE).of(KeyType::String),
+    spec("setrange", 4, WD, ONE).of(KeyType::String),
+    spec("append", 3, WD, ONE).of(KeyType::String),
+    spec("incr", 2, WD, ONE).of(KeyType::String),
+    spec("decr", 2, WD, ONE).of(KeyType::String),
+    spec("incrby", 3, WD, ONE).of(KeyType::String),
+    spec("decrby", 3, WD, ONE).of(KeyType::String),
+    spec("setnx", 3, WD, ONE),
+    spec("getset", 3, WD, ONE),
+    spec("substr", 4, R, ONE).of(KeyType::String),
+    spec("hget", 3, R, ONE).of(KeyType::Hash),
+    spec("hset", 4, WD, ONE).of(KeyType::Hash),
+    spec("hmget", -3, R, ONE).of(KeyType::Hash),
+    spec("hgetall", 2, R, ONE).of(KeyType::Hash),
+    spec("hsetnx", 4, WD, ONE).of(KeyType::Hash),
+    spec("hrandfield", -2, R, ONE).of(KeyType::Hash),
+    spec("echo", 2, NONE, KeySpec::None),
+    spec("ping", -1, NONE, KeySpec::None),
+    spec("lolwut", -1, NONE, KeySpec::None),
+    spec("sadd", -3, WD, ONE).of(KeyType::Set),
+    spec("sismember", 3, R, ONE).of(KeyType::Set),
+    spec("smismember", -3, R, ONE).of(KeyType::Set),
+    spec("sintercard", -3, R, KeySpec::NumKeys(1)).of(KeyType::Set),
+    spec("pfadd", -2, WD, ONE).of(KeyType::HyperLogLog),
+    spec("pfcount", -2, R, ALL).of(KeyType::HyperLogLog),
+    spec("pfmerge", -2, WD, ALL).of(KeyType::HyperLogLog),
+    spec("pfdebug", 3, W, KeySpec::Range(2, 2, 1)).of(KeyType::HyperLogLog),
+    spec(
+        "object",
+        -2,
+        R.union(CommandFlags::NOTOUCH),
+        KeySpec::Range(2, 2, 1),
+    ),
+    spec("slowlog", -2, A, KeySpec::None),
+    spec("latency", -2, A, KeySpec::None),
+    spec("subscribe", -2, P, KeySpec::None),
+    spec("unsubscribe", -1, P, KeySpec::None),
+    spec("psubscribe", -2, P, KeySpec::None),
+    spec("punsubscribe", -1, P, KeySpec::None),
+    spec("publish", 3, P, KeySpec::None),
+    spec("ssubscribe", -2, P, KeySpec::None),
+    spec("sunsubscribe", -1, P, KeySpec::None),
+    spec("spublish", 3, P, KeySpec::None),
+    spec("lpush", -3, WD, ONE).of(KeyType::List),
+    spec("rpush", -3, WD, ONE).of(KeyType::List),
+    spec("lpop", -2, W, ONE).of(KeyType::List),
+    spec("rpop", -2, W, ONE).of(KeyType::List),
+    spec("llen", 2, R, ONE).of(KeyType::List),
+    spec("lrange", 4, R, ONE).of(KeyType::List),
+    spec("blpop", -3, WB, KeySpec::Range(1, -2, 1)).of(KeyType::List),
+    spec("brpop", -3, WB, KeySpec::Range(1, -2, 1)).of(KeyType::List),
+    spec("lmove", 5, WD, KeySpec::Range(1, 2, 1)).of(KeyType::List),
+    spec("rpoplpush", 3, WD, KeySpec::Range(1, 2, 1)).of(KeyType::List),
+    spec("blmove", 6, WBD, KeySpec::Range(1, 2, 1)).of(KeyType::List),
+    spec("brpoplpush", 4, WBD, KeySpec::Range(1, 2, 1)).of(KeyType::List),
+    spec("xadd", -5, WD, ONE).of(KeyType::Stream),
+    spec("xrange", -4, R, ONE).of(KeyType::Stream),
+    spec("xrevrange", -4, R, ONE).of(KeyType::Stream),
+    spec("xlen", 2, R, ONE).of(KeyType::Stream),
+    spec(
+        "xread",
+        -4,
+        R.union(CommandFlags::BLOCKING),
+        KeySpec::Streams,
+    )
+    .of(KeyType::Stream),
+    spec("xgroup", -2, WD, KeySpec::Range(2, 2, 1)).of(KeyType::Stream),
+    spec("xreadgroup", -7, WB, KeySpec::Streams).of(KeyType::Stream),
+    spec("xack", -4, W, ONE).of(KeyType::Stream),
+    spec("xpending", -3, R, ONE).of(KeyType::Stream),
+    spec("xclaim", -6, W, ONE).of(KeyType::Stream),
+    spec("xautoclaim", -6, W, ONE).of(KeyType::Stream),
+    spec("save", 1, A, KeySpec::None),
+    spec("shutdown", -1, A, KeySpec::None),
+    spec("setbit", 4, WD, ONE).of(KeyType::String),
+    spec("getbit", 3, R, ONE).of(KeyType::String),
+    spec("bitcount", -2, R, ONE).of(KeyType::String),
+    spec("bitop", -4, WD, KeySpec::Range(2, -1, 1)).storing(KeyType::String),
+    spec("bitpos", -3, R, ONE).of(KeyType::String),
+    spec("exists", -2, R, ALL),
+    spec("type", 2, R.union(CommandFlags::NOTOUCH), ONE),
+    spec("del", -2, W, ALL),
+    spec("unlink", -2, W, ALL),
+    spec("touch", -2, R, ALL),
+    spec("copy", -3, WD, KeySpec::Range(1, 2, 1)),
+    spec("dump", 2, R, ONE),
+    spec("restore", -4, WD, ONE),
+    spec("migrate", -6, W, KeySpec::KeyOrKeys(3)),
+    // sorts a list, a set or a sorted set, it refuses other types itself
+    spec("sort", -2, WD, KeySpec::KeyAndStore(1)),
+    spec("sort_ro", -2, R, ONE),
+    spec("expire", 3, W, ONE),
+    spec("pexpire", 3, W, ONE),
+    spec("expireat", 3, W, ONE),
+    spec("pexpireat", 3, W, ONE),
+    spec("ttl", 2, R, ONE),
+    spec("pttl", 2, R, ONE),
+    spec("expiretime", 2, R, ONE),
+    spec("pexpiretime", 2, R, ONE),
+    spec("persist", 2, W, ONE),
+    spec("zadd", -4, WD, ONE).of(KeyType::ZSet),
+    spec("zrem", -3, W, ONE).of(KeyType::ZSet),
+    spec("zscore", 3, R, ONE).of(KeyType::ZSet),
+    spec("zrange", -4, R, ONE).of(KeyType::ZSet),
+    spec("zincrby", 4, WD, ONE).of(KeyType::ZSet),
+    spec("zcard", 2, R, ONE).of(KeyType::ZSet),
+    spec("zcount", 4, R, ONE).of(KeyType::ZSet),
+    spec("zrangebylex", -4, R, ONE).of(KeyType::ZSet),
+    spec("zpopmin", -2, W, ONE).of(KeyType::ZSet),
+    spec("zpopmax", -2, W, ONE).of(KeyType::ZSet),
+    spec("bzpopmin", -3, WB, KeySpec::Range(1, -2, 1)).of(KeyType::ZSet),
+    spec("bzpopmax", -3, WB, KeySpec::Range(1, -2, 1)).of(KeyType::ZSet),
+    spec("zunionstore", -4, WD, KeySpec::DestNumKeys(1)).storing(KeyType::ZSet),
+    spec("zinterstore", -4, WD, KeySpec::DestNumKeys(1)).storing(KeyType::ZSet),
+    spec("geoadd", -5, WD, ONE).of(KeyType::ZSet),
+    spec("geopos", -2, R, ONE).of(KeyType::ZSet),
+    spec("geodist", -4, R, ONE).of(KeyType::ZSet),
+    spec("geosearch", -7, R, ONE).of(KeyType::ZSet),
+    spec("geosearchstore", -8, WD, KeySpec::Range(1, 2, 1)).storing(KeyType::ZSet),
+    // functions may write anything
+    spec("fcall", -3, WD, KeySpec::NumKeys(2)),
+    spec("hello", -1, NONE, KeySpec::None),
+    spec("client", -2, NONE, KeySpec::None),
+    spec("reset", 1, NONE, KeySpec::None),
+    spec("quit", -1, NONE, KeySpec::None),
+    spec("config", -2, A, KeySpec::None),
+    // subcommands with their own arity, named like Redis does
+    spec("config|get", -3, A, KeySpec::None),
+    spec("config|set", 4, A, KeySpec::None),
+    spec("psync", 3, A, KeySpec::None),
+    spec("replconf", -1, A, KeySpec::None),
+    spec("role", 1, NONE, KeySpec::None),
+    spec("replicaof", 3, A, KeySpec::None),
+    spec("slaveof", 3, A, KeySpec::None),
+    spec("wait", 3, NONE, KeySpec::None),
+    spec("cluster", -2, NONE, KeySpec::None),
+    spec("asking", 1, NONE, KeySpec::None),
+    spec("metrics", 2, NONE, KeySpec::None),
+    spec("info", -1, NONE, KeySpec::None),
+    spec("hotkeys", -1, A, KeySpec::None),
+    spec("debug", -2, A, KeySpec::None),
+    spec("command", -1, NONE, KeySpec::None),
+];
+
+lazy_static! {
+    static ref COMMAND_TABLE: HashMap<&'static str, &'static CommandSpec> =
+        COMMANDS.iter().map(|spec| (spec.name, spec)).collect();
+}
+
+const fn spec(name: &'static str, arity: i32, flags: CommandFlags, keys: KeySpec) -> CommandSpec {
+    CommandSpec {
+        name,
+        arity,
+        flags,
+        keys,
+        key_type: None,
+        stores: false,
+    }
+}
+
+// the spec of a command by its lowercase name
+pub fn command_spec(name: &str) -> Option<&'static CommandSpec> {
+    COMMAND_TABLE.get(name).copied()
+}
+
+impl CommandSpec {
+    // a command on keys of key_type
+    const fn of(self, key_type: KeyType) -> CommandSpec {
+        CommandSpec {
+            key_type: Some(key_type),
+            ..self
+        }
+    }
+
+    // a command storing a value of key_type in its first key, from keys of key_type
+    const fn storing(self, key_type: KeyType) -> CommandSpec {
+        CommandSpec {
+            key_type: Some(key_type),
+            stores: true,
+            ..self
+        }
+    }
+
+    // the keys that must hold key_type, out of the keys of the command
+    pub fn typed_keys<'a>(&self, keys: &'a [Key]) -> &'a [Key] {
+        match (self.key_type, self.stores) {
+            (None, _) => &[],
+            (Some(_), true) => keys.get(1..).unwrap_or_default(),
+            (Some(_), false) => keys,
+        }
+    }
+
+    // whether the command takes that many arguments, its name included
+    pub fn check_arity(&self, len: usize) -> bool {
+        match self.arity {
+            arity if arity < 0 => len >= arity.unsigned_abs() as usize,
+            arity => len == arity as usize,
+        }
+    }
+
+    pub fn is_write(&self) -> bool {
+        self.flags.contains(CommandFlags::WRITE)
+    }
+
+    pub fn is_denyoom(&self) -> bool {
+        self.flags.contains(CommandFlags::DENYOOM)
+    }
+
+    pub fn is_blocking(&self) -> bool {
+        self.flags.contains(CommandFlags::BLOCKING)
+    }
+
+    pub fn is_notouch(&self) -> bool {
+        self.flags.contains(CommandFlags::NOTOUCH)
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.flags.contains(CommandFlags::READONLY)
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.flags.contains(CommandFlags::ADMIN)
+    }
+
+    pub fn is_pubsub(&self) -> bool {
+        self.flags.contains(CommandFlags::PUBSUB)
+    }
+
+    // the keys in the arguments of the command, the command name included. Arguments that
+    // aren't bulk strings are skipped, the command will reject them when parsing.
+    pub fn keys(&self, args: &RespArray) -> Vec<Key> {
+        let len = args.len();
+        let positions: Vec<usize> = match self.keys {
+            KeySpec::None => vec![],
+            KeySpec::Range(first, last, step) => {
+                let last = if last < 0 { len as isize + last } else { last };
+                if last < first as isize {
+                    vec![]
+                } else {
+                    (first..=last as usize).step_by(step).collect()
+                }
+            }
+            KeySpec::Streams => {
+                let streams = args.iter().position(|arg| {
+                    matches!(arg, RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"streams"))
+                });
+                match streams {
+                    Some(pos) => {
+                        let count = (len - pos - 1) / 2;
+                        (pos + 1..pos + 1 + count).collect()
+                    }
+                    None => vec![],
+                }
+            }
+            KeySpec::NumKeys(index) => numkeys_positions(args, index),
+            KeySpec::DestNumKeys(index) => std::iter::once(index)
+                .chain(numkeys_positions(args, index + 1))
+                .collect(),
+            KeySpec::KeyOrKeys(index) => match args.get(index) {
+                Some(RespFrame::BulkString(key)) if key.is_empty() => {
+                    let keys = args.iter().position(|arg| {
+                        matches!(arg, RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"keys"))
+                    });
+                    keys.map(|pos| (pos + 1..len).collect()).unwrap_or_default()
+                }
+                _ => vec![index],
+            },
+            KeySpec::KeyAndStore(index) => std::iter::once(index)
+                .chain(store_position(args, index + 1))
+                .collect(),
+        };
+        positions
+            .into_iter()
+            .filter_map(|pos| match args.get(pos) {
+                Some(RespFrame::BulkString(key)) => Some(Key::from(&key[..])),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// the positions of the keys following the number of keys at the index
+fn numkeys_positions(args: &RespArray, index: usize) -> Vec<usize> {
+    let numkeys = match args.get(index) {
+        Some(RespFrame::BulkString(n)) => std::str::from_utf8(n).ok().and_then(|n| n.parse().ok()),
+        Some(RespFrame::Integer(n)) => usize::try_from(*n).ok(),
+        _ => None,
+    };
+    match numkeys {
+        Some(n) => (index + 1..(index + 1 + n).min(args.len())).collect(),
+        None => vec![],
+    }
+}
+
+// the position of the destination after STORE, from the options at start on. The arguments
+// of the other options are skipped, a pattern may well be "store".
+fn store_position(args: &RespArray, start: usize) -> Option<usize> {
+    let mut pos = start;
+    let mut store = None;
+    while let Some(arg) = args.get(pos) {
+        let RespFrame::BulkString(arg) = arg else {
+            break;
+        };
+        pos += match arg.to_ascii_lowercase().as_slice() {
+            b"limit" => 3,
+            b"by" | b"get" => 2,
+            b"store" => {
+                store = Some(pos + 1).filter(|&dest| dest < args.len());
+                2
+            }
+            _ => 1,
+        };
+    }
+    store
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn args(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[test]
+    fn test_command_keys() {
+        let keys = |cmd: &[&str]| command_spec(cmd[0]).unwrap().keys(&args(cmd));
+
+        assert_eq!(keys(&["set", "a", "1"]), vec!["a"]);
+        assert_eq!(keys(&["del", "a", "b"]), vec!["a", "b"]);
+        assert_eq!(keys(&["blpop", "a", "b", "0"]), vec!["a", "b"]);
+        assert_eq!(keys(&["bitop", "and", "d", "a", "b"]), vec!["d", "a", "b"]);
+        assert_eq!(
+            keys(&["xread", "count", "1", "streams", "a", "b", "0", "0"]),
+            vec!["a", "b"]
+        );
+        assert_eq!(keys(&["fcall", "f", "1", "a", "arg"]), vec!["a"]);
+        assert_eq!(
+            keys(&["sintercard", "2", "a", "b", "limit", "1"]),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            keys(&["zunionstore", "d", "2", "a", "b", "weights", "1", "2"]),
+            vec!["d", "a", "b"]
+        );
+        assert_eq!(keys(&["migrate", "h", "1", "a", "0", "10"]), vec!["a"]);
+        assert_eq!(
+            keys(&["migrate", "h", "1", "", "0", "10", "copy", "keys", "a", "b"]),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            keys(&["sort", "a", "by", "store", "limit", "0", "1", "store", "d"]),
+            vec!["a", "d"]
+        );
+        assert!(keys(&["echo", "a"]).is_empty());
+
+        assert!(command_spec("set").unwrap().is_denyoom());
+        assert!(command_spec("del").unwrap().is_write());
+        assert!(!command_spec("del").unwrap().is_denyoom());
+        assert!(command_spec("blpop").unwrap().is_blocking());
+        assert!(command_spec("nope").is_none());
+    }
+
+    // a command with keys either only reads them or writes them
+    #[test]
+    fn test_command_flags() {
+        for spec in COMMANDS {
+            assert!(
+                !(spec.is_readonly() && spec.is_write()),
+                "{} is readonly and write",
+                spec.name
+            );
+            if spec.keys != KeySpec::None {
+                assert!(
+                    spec.is_readonly() || spec.is_write(),
+                    "{} is neither readonly nor write",
+                    spec.name
+                );
+            }
+        }
+        assert!(command_spec("config|set").unwrap().is_admin());
+        assert!(command_spec("publish").unwrap().is_pubsub());
+        assert_eq!(
+            (CommandFlags::WRITE | CommandFlags::DENYOOM)
+                .names()
+                .collect::<Vec<_>>(),
+            vec!["write", "denyoom"]
+        );
+    }
+
+    #[test]
+    fn test_check_arity() {
+        let get = command_spec("get").unwrap();
+        assert!(!get.check_arity(1));
+        assert!(get.check_arity(2));
+        assert!(!get.check_arity(3));
+        let del = command_spec("del").unwrap();
+        assert!(!del.check_arity(1));
+        assert!(del.check_arity(2));
+        assert!(del.check_arity(10));
+    }
+
+    // every command the dispatcher knows carries its own spec
+    #[test]
+    fn test_command_table_complete() {
+        for (name, entry) in super::super::DISPATCH.iter() {
+            let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+            assert_eq!(entry.spec.name, name);
+        }
+        let spec = super::super::lookup_command(b"gEt").unwrap().spec;
+        assert!(std::ptr::eq(spec, command_spec("get").unwrap()));
+        assert!(super::super::lookup_command(b"nope").is_none());
+    }
+}
@@ -0,0 +1,173 @@
+// Timeouts and expire times given as command arguments. Everything is checked for overflow
+// where it is parsed: a timeout must give a deadline tokio's Instant can hold, and like an
+// expire time a unix time in milliseconds that fits in an i64, like in Redis, so the
+// commands using them never do unchecked time arithmetic on client input.
+
+use super::CommandError;
+use crate::{backend::now_ms, RespFrame};
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Seconds,
+    Millis,
+}
+
+// a timeout of a blocking command: seconds with decimals (BLPOP) or whole milliseconds
+// (XREAD BLOCK, WAIT). Commands take 0 as blocking forever.
+pub fn extract_timeout(frame: RespFrame, unit: TimeUnit) -> Result<Duration, CommandError> {
+    let s = match frame {
+        RespFrame::BulkString(s) => String::from_utf8(Vec::from(s.0))?,
+        RespFrame::Integer(i) => i.to_string(),
+        _ => return Err(CommandError::InvalidArgument("Invalid timeout".to_string())),
+    };
+    let timeout = match unit {
+        TimeUnit::Seconds => {
+            let secs = s.parse::<f64>().map_err(|_| {
+                CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+            })?;
+            if secs < 0.0 {
+                return Err(CommandError::InvalidArgument(
+                    "timeout is negative".to_string(),
+                ));
+            }
+            Duration::try_from_secs_f64(secs).map_err(|_| timeout_out_of_range())?
+        }
+        TimeUnit::Millis => {
+            let ms = s.parse::<i64>().map_err(|_| {
+                CommandError::InvalidArgument(
+                    "timeout is not an integer or out of range".to_string(),
+                )
+            })?;
+            if ms < 0 {
+                return Err(CommandError::InvalidArgument(
+                    "timeout is negative".to_string(),
+                ));
+            }
+            Duration::from_millis(ms as u64)
+        }
+    };
+    Instant::now()
+        .checked_add(timeout)
+        .ok_or_else(timeout_out_of_range)?;
+    if u128::from(now_ms()) + timeout.as_millis() > i64::MAX as u128 {
+        return Err(timeout_out_of_range());
+    }
+    Ok(timeout)
+}
+
+// an expire time as EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT take it: a number of seconds
+// or milliseconds from now, or a unix time with absolute. Returns the unix time in
+// milliseconds it expires at, the standard error of the command when it isn't positive or
+// overflows.
+pub fn extract_expire_at(
+    frame: RespFrame,
+    unit: TimeUnit,
+    absolute: bool,
+    now_ms: u64,
+    command: &str,
+) -> Result<u64, CommandError> {
+    let invalid =
+        || CommandError::InvalidArgument(format!("invalid expire time in '{}' command", command));
+    let value = match frame {
+        RespFrame::BulkString(s) => {
+            String::from_utf8(Vec::from(s.0))?
+                .parse::<i64>()
+                .map_err(|_| {
+                    CommandError::InvalidArgument(
+                        "value is not an integer or out of range".to_string(),
+                    )
+                })?
+        }
+        RespFrame::Integer(i) => i,
+        _ => return Err(invalid()),
+    };
+    if value <= 0 {
+        return Err(invalid());
+    }
+    let ms = match unit {
+        TimeUnit::Seconds => value.checked_mul(1000).ok_or_else(invalid)?,
+        TimeUnit::Millis => value,
+    } as u64;
+    let at = match absolute {
+        true => ms,
+        false => ms.checked_add(now_ms).ok_or_else(invalid)?,
+    };
+    match at <= i64::MAX as u64 {
+        true => Ok(at),
+        false => Err(invalid()),
+    }
+}
+
+fn timeout_out_of_range() -> CommandError {
+    CommandError::InvalidArgument("timeout is out of range".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn arg(s: &str) -> RespFrame {
+        BulkString::from(s).into()
+    }
+
+    #[test]
+    fn test_extract_timeout() {
+        assert_eq!(
+            extract_timeout(arg("0.5"), TimeUnit::Seconds).unwrap(),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            extract_timeout(arg("0"), TimeUnit::Seconds).unwrap(),
+            Duration::ZERO
+        );
+        assert_eq!(
+            extract_timeout(arg("100"), TimeUnit::Millis).unwrap(),
+            Duration::from_millis(100)
+        );
+        assert!(extract_timeout(arg("-1"), TimeUnit::Seconds).is_err());
+        assert!(extract_timeout(arg("-1"), TimeUnit::Millis).is_err());
+        assert!(extract_timeout(arg("0.5"), TimeUnit::Millis).is_err());
+        assert!(extract_timeout(arg("nan"), TimeUnit::Seconds).is_err());
+        assert!(extract_timeout(arg("inf"), TimeUnit::Seconds).is_err());
+        // would overflow when added to now
+        assert!(extract_timeout(arg("1e30"), TimeUnit::Seconds).is_err());
+        assert!(extract_timeout(arg(&u64::MAX.to_string()), TimeUnit::Millis).is_err());
+        assert_eq!(
+            extract_timeout(arg(&i64::MAX.to_string()), TimeUnit::Millis)
+                .unwrap_err()
+                .to_string(),
+            "ERR timeout is out of range"
+        );
+    }
+
+    #[test]
+    fn test_extract_expire_at() {
+        let now = 1_700_000_000_000;
+        assert_eq!(
+            extract_expire_at(arg("10"), TimeUnit::Seconds, false, now, "expire").unwrap(),
+            now + 10_000
+        );
+        assert_eq!(
+            extract_expire_at(arg("10"), TimeUnit::Millis, false, now, "pexpire").unwrap(),
+            now + 10
+        );
+        assert_eq!(
+            extract_expire_at(arg("1800000000"), TimeUnit::Seconds, true, now, "expireat").unwrap(),
+            1_800_000_000_000
+        );
+        let err = extract_expire_at(arg("0"), TimeUnit::Seconds, false, now, "expire").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR invalid expire time in 'expire' command"
+        );
+        assert!(extract_expire_at(arg("-5"), TimeUnit::Millis, false, now, "pexpire").is_err());
+        let max = i64::MAX.to_string();
+        assert!(extract_expire_at(arg(&max), TimeUnit::Seconds, false, now, "expire").is_err());
+        assert!(extract_expire_at(arg(&max), TimeUnit::Millis, false, now, "pexpire").is_err());
+        assert!(extract_expire_at(arg(&max), TimeUnit::Millis, true, now, "pexpireat").is_ok());
+        assert!(extract_expire_at(arg("x"), TimeUnit::Millis, false, now, "pexpire").is_err());
+    }
+}
@@ -0,0 +1,901 @@
+use super::{
+    extract_args, extract_bytes, extract_integer, extract_keys,
+    list::{extract_key_and_count, extract_keys_and_timeout},
+    validate_command, BZPopMax, BZPopMin, CommandError, CommandExecutor, ZAdd, ZCard, ZCount,
+    ZIncrBy, ZInterStore, ZPopMax, ZPopMin, ZRange, ZRangeByLex, ZRem, ZScore, ZUnionStore,
+};
+use crate::{
+    Backend, BulkString, Key, LexBound, RespArray, RespFrame, RespNull, RespNullArray, ScoreBound,
+    SimpleError, ZAddCondition, ZAggregate, NOTIFY_GENERIC, NOTIFY_ZSET,
+};
+
+impl CommandExecutor for ZAdd {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let changed = backend.zadd(self.key.clone(), self.members, self.condition, self.ch);
+        if changed > 0 {
+            backend.notify_keyspace_event(NOTIFY_ZSET, "zadd", &self.key);
+        }
+        RespFrame::Integer(changed as i64)
+    }
+}
+
+impl CommandExecutor for ZRem {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let removed = backend.zrem(&self.key, &self.members);
+        if removed > 0 {
+            backend.notify_keyspace_event(NOTIFY_ZSET, "zrem", &self.key);
+            if !backend.key_exists(&self.key) {
+                backend.notify_keyspace_event(NOTIFY_GENERIC, "del", &self.key);
+            }
+        }
+        RespFrame::Integer(removed as i64)
+    }
+}
+
+impl CommandExecutor for ZScore {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.zscore(&self.key, &self.member) {
+            Some(score) => BulkString::from(format_score(score)).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for ZRange {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let members = backend.zrange(&self.key, self.start, self.stop, self.rev);
+        let withscores = self.withscores;
+        RespArray::new(
+            members
+                .into_iter()
+                .flat_map(|(member, score)| {
+                    let member = BulkString::from(member).into();
+                    let score = withscores.then(|| BulkString::from(format_score(score)).into());
+                    std::iter::once(member).chain(score)
+                })
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+}
+
+impl CommandExecutor for ZIncrBy {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        match backend.zincrby(self.key.clone(), self.increment, self.member) {
+            Ok(score) => {
+                backend.notify_keyspace_event(NOTIFY_ZSET, "zincr", &self.key);
+                BulkString::from(format_score(score)).into()
+            }
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZCard {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.zcard(&self.key) as i64)
+    }
+}
+
+impl CommandExecutor for ZCount {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        RespFrame::Integer(backend.zcount(&self.key, self.min, self.max) as i64)
+    }
+}
+
+impl CommandExecutor for ZRangeByLex {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let members = backend.zrangebylex(&self.key, &self.min, &self.max, self.offset, self.count);
+        RespArray::new(
+            members
+                .into_iter()
+                .map(|member| BulkString::from(member).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+}
+
+impl CommandExecutor for ZPopMin {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        zpop(backend, &self.key, self.count, false)
+    }
+}
+
+impl CommandExecutor for ZPopMax {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        zpop(backend, &self.key, self.count, true)
+    }
+}
+
+impl CommandExecutor for BZPopMin {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let ret = backend.blocking_zpop(&self.keys, self.timeout, false).await;
+        blocking_zpop_reply(backend, ret, false)
+    }
+}
+
+impl CommandExecutor for BZPopMax {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let ret = backend.blocking_zpop(&self.keys, self.timeout, true).await;
+        blocking_zpop_reply(backend, ret, true)
+    }
+}
+
+impl CommandExecutor for ZUnionStore {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let stored = backend.zunionstore(
+            self.destination.clone(),
+            &self.keys,
+            &self.weights,
+            self.aggregate,
+        );
+        if stored > 0 {
+            backend.notify_keyspace_event(NOTIFY_ZSET, "zunionstore", &self.destination);
+        }
+        RespFrame::Integer(stored as i64)
+    }
+}
+
+impl CommandExecutor for ZInterStore {
+    async fn execute(self, backend: &crate::Backend, _session: &mut crate::Session) -> RespFrame {
+        let stored = backend.zinterstore(
+            self.destination.clone(),
+            &self.keys,
+            &self.weights,
+            self.aggregate,
+        );
+        if stored > 0 {
+            backend.notify_keyspace_event(NOTIFY_ZSET, "zinterstore", &self.destination);
+        }
+        RespFrame::Integer(stored as i64)
+    }
+}
+
+// the popped members and their scores in a flat array, empty when there is nothing to pop
+fn zpop(backend: &Backend, key: &[u8], count: Option<usize>, max: bool) -> RespFrame {
+    let popped = backend.zpop(key, count.unwrap_or(1), max);
+    if !popped.is_empty() {
+        zpop_notify(backend, key, max);
+    }
+    RespArray::new(
+        popped
+            .into_iter()
+            .flat_map(|(member, score)| {
+                [
+                    BulkString::from(member).into(),
+                    BulkString::from(format_score(score)).into(),
+                ]
+            })
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+fn blocking_zpop_reply(
+    backend: &Backend,
+    ret: Option<(Key, Vec<u8>, f64)>,
+    max: bool,
+) -> RespFrame {
+    match ret {
+        Some((key, member, score)) => {
+            zpop_notify(backend, &key, max);
+            RespArray::new(vec![
+                BulkString::from(key).into(),
+                BulkString::from(member).into(),
+                BulkString::from(format_score(score)).into(),
+            ])
+            .into()
+        }
+        None => RespNullArray.into(),
+    }
+}
+
+fn zpop_notify(backend: &Backend, key: &[u8], max: bool) {
+    let event = if max { "zpopmax" } else { "zpopmin" };
+    backend.notify_keyspace_event(NOTIFY_ZSET, event, key);
+    if !backend.key_exists(key) {
+        backend.notify_keyspace_event(NOTIFY_GENERIC, "del", key);
+    }
+}
+
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zadd"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.into(),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let (condition, ch) = extract_zadd_flags(&mut args)?;
+
+        let args: Vec<RespFrame> = args.collect();
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let mut members = Vec::with_capacity(args.len() / 2);
+        let mut args = args.into_iter();
+        while let (Some(score), Some(member)) = (args.next(), args.next()) {
+            match member {
+                RespFrame::BulkString(member) => {
+                    members.push((extract_score(score)?, Vec::from(member)))
+                }
+                _ => return Err(CommandError::InvalidArgument("Invalid member".to_string())),
+            }
+        }
+        Ok(ZAdd {
+            key,
+            condition,
+            ch,
+            members,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zrem"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match args.next() {
+            Some(RespFrame::BulkString(key)) if args.len() > 0 => Ok(ZRem {
+                key: key.into(),
+                members: extract_bytes(args.collect())?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "zrem command must have a key and at least one member".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zscore"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(member))) => Ok(ZScore {
+                key: key.into(),
+                member: Vec::from(member),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or member".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zrange"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, start, stop) = match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(start), Some(stop)) => {
+                (key.into(), extract_integer(start)?, extract_integer(stop)?)
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid key, start or stop".to_string(),
+                ))
+            }
+        };
+        let (mut rev, mut withscores) = (false, false);
+        for arg in args {
+            match arg {
+                RespFrame::BulkString(arg) if arg.eq_ignore_ascii_case(b"rev") => rev = true,
+                RespFrame::BulkString(arg) if arg.eq_ignore_ascii_case(b"withscores") => {
+                    withscores = true
+                }
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(ZRange {
+            key,
+            start,
+            stop,
+            rev,
+            withscores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZIncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zincrby"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(increment),
+                Some(RespFrame::BulkString(member)),
+            ) => Ok(ZIncrBy {
+                key: key.into(),
+                increment: extract_score(increment)?,
+                member: Vec::from(member),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, increment or member".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcard"])?;
+
+        match extract_args(value, 1)?.into_iter().next() {
+            Some(RespFrame::BulkString(key)) => Ok(ZCard { key: key.into() }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcount"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(min), Some(max)) => Ok(ZCount {
+                key: key.into(),
+                min: extract_score_bound(min)?,
+                max: extract_score_bound(max)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, min or max".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZRangeByLex {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zrangebylex"])?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, min, max) = match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(min), Some(max)) => {
+                (key.into(), extract_lex_bound(min)?, extract_lex_bound(max)?)
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid key, min or max".to_string(),
+                ))
+            }
+        };
+        // LIMIT offset count, a negative offset selects nothing and a negative count everything
+        let (offset, count) = match (args.next(), args.next(), args.next(), args.next()) {
+            (None, ..) => (0, None),
+            (Some(RespFrame::BulkString(limit)), Some(offset), Some(count), None)
+                if limit.eq_ignore_ascii_case(b"limit") =>
+            {
+                match (extract_integer(offset)?, extract_integer(count)?) {
+                    (offset, _) if offset < 0 => (0, Some(0)),
+                    (offset, count) => (offset as usize, usize::try_from(count).ok()),
+                }
+            }
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(ZRangeByLex {
+            key,
+            min,
+            max,
+            offset,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMin {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zpopmin"])?;
+        let (key, count) = extract_key_and_count(value)?;
+        Ok(ZPopMin { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMax {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zpopmax"])?;
+        let (key, count) = extract_key_and_count(value)?;
+        Ok(ZPopMax { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for BZPopMin {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bzpopmin"])?;
+        let (keys, timeout) = extract_keys_and_timeout(value)?;
+        Ok(BZPopMin { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BZPopMax {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bzpopmax"])?;
+        let (keys, timeout) = extract_keys_and_timeout(value)?;
+        Ok(BZPopMax { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for ZUnionStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zunionstore"])?;
+        let (destination, keys, weights, aggregate) = extract_zstore(value, "zunionstore")?;
+        Ok(ZUnionStore {
+            destination,
+            keys,
+            weights,
+            aggregate,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZInterStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zinterstore"])?;
+        let (destination, keys, weights, aggregate) = extract_zstore(value, "zinterstore")?;
+        Ok(ZInterStore {
+            destination,
+            keys,
+            weights,
+            aggregate,
+        })
+    }
+}
+
+// destination numkeys key [key ...] [WEIGHTS weight [weight ...]] [AGGREGATE SUM | MIN | MAX]
+fn extract_zstore(
+    value: RespArray,
+    name: &str,
+) -> Result<(Key, Vec<Key>, Vec<f64>, ZAggregate), CommandError> {
+    let syntax = || CommandError::InvalidArgument("syntax error".to_string());
+    let mut args = extract_args(value, 1)?.into_iter();
+    let (destination, numkeys) = match (args.next(), args.next()) {
+        (Some(RespFrame::BulkString(dest)), Some(numkeys)) => {
+            (dest.into(), extract_integer(numkeys)?)
+        }
+        _ => {
+            return Err(CommandError::InvalidArgument(
+                "Invalid destination or numkeys".to_string(),
+            ))
+        }
+    };
+    if numkeys <= 0 {
+        return Err(CommandError::InvalidArgument(format!(
+            "at least 1 input key is needed for '{}' command",
+            name
+        )));
+    }
+    let keys = extract_keys(args.by_ref().take(numkeys as usize).collect())?;
+    if keys.len() < numkeys as usize {
+        return Err(syntax());
+    }
+
+    let (mut weights, mut aggregate) = (Vec::new(), ZAggregate::Sum);
+    while let Some(arg) = args.next() {
+        let RespFrame::BulkString(arg) = arg else {
+            return Err(syntax());
+        };
+        match arg.to_ascii_lowercase().as_slice() {
+            b"weights" if weights.is_empty() => {
+                for _ in 0..keys.len() {
+                    let weight = args.next().ok_or_else(syntax)?;
+                    weights.push(extract_score(weight).map_err(|_| {
+                        CommandError::InvalidArgument("weight value is not a float".to_string())
+                    })?);
+                }
+            }
+            b"aggregate" => {
+                aggregate = match args.next() {
+                    Some(RespFrame::BulkString(a)) => match a.to_ascii_lowercase().as_slice() {
+                        b"sum" => ZAggregate::Sum,
+                        b"min" => ZAggregate::Min,
+                        b"max" => ZAggregate::Max,
+                        _ => return Err(syntax()),
+                    },
+                    _ => return Err(syntax()),
+                }
+            }
+            _ => return Err(syntax()),
+        }
+    }
+    Ok((destination, keys, weights, aggregate))
+}
+
+// [NX | XX] [CH], shared with GEOADD
+pub(super) fn extract_zadd_flags(
+    args: &mut std::iter::Peekable<impl Iterator<Item = RespFrame>>,
+) -> Result<(ZAddCondition, bool), CommandError> {
+    let (mut nx, mut xx, mut ch) = (false, false, false);
+    while let Some(RespFrame::BulkString(flag)) = args.peek() {
+        match flag.to_ascii_lowercase().as_slice() {
+            b"nx" => nx = true,
+            b"xx" => xx = true,
+            b"ch" => ch = true,
+            _ => break,
+        }
+        args.next();
+    }
+    let condition = match (nx, xx) {
+        (true, true) => {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ))
+        }
+        (true, false) => ZAddCondition::Nx,
+        (false, true) => ZAddCondition::Xx,
+        (false, false) => ZAddCondition::Always,
+    };
+    Ok((condition, ch))
+}
+
+// a float, inf and -inf included, NaN is not a valid score
+pub(super) fn extract_score(frame: RespFrame) -> Result<f64, CommandError> {
+    let score = match frame {
+        RespFrame::BulkString(s) => String::from_utf8(Vec::from(s.0))?.parse::<f64>().ok(),
+        RespFrame::Integer(i) => Some(i as f64),
+        _ => None,
+    };
+    match score {
+        Some(score) if !score.is_nan() => Ok(score),
+        _ => Err(CommandError::InvalidArgument(
+            "value is not a valid float".to_string(),
+        )),
+    }
+}
+
+// a score with an optional `(` for an exclusive bound
+fn extract_score_bound(frame: RespFrame) -> Result<ScoreBound, CommandError> {
+    let error = || CommandError::InvalidArgument("min or max is not a float".to_string());
+    let bound = match frame {
+        RespFrame::BulkString(s) => String::from_utf8(Vec::from(s.0))?,
+        RespFrame::Integer(i) => i.to_string(),
+        _ => return Err(error()),
+    };
+    let (bound, exclusive) = match bound.strip_prefix('(') {
+        Some(bound) => (bound, true),
+        None => (bound.as_str(), false),
+    };
+    match bound.parse::<f64>() {
+        Ok(score) if !score.is_nan() => Ok(ScoreBound { score, exclusive }),
+        _ => Err(error()),
+    }
+}
+
+// `-`, `+`, or a member prefixed with `[` for inclusive or `(` for exclusive
+fn extract_lex_bound(frame: RespFrame) -> Result<LexBound, CommandError> {
+    let bound = match frame {
+        RespFrame::BulkString(s) => Vec::from(s),
+        _ => Vec::new(),
+    };
+    match bound.split_first() {
+        Some((b'-', [])) => Ok(LexBound::Min),
+        Some((b'+', [])) => Ok(LexBound::Max),
+        Some((b'[', member)) => Ok(LexBound::Inclusive(member.to_vec())),
+        Some((b'(', member)) => Ok(LexBound::Exclusive(member.to_vec())),
+        _ => Err(CommandError::InvalidArgument(
+            "min or max not valid string range item".to_string(),
+        )),
+    }
+}
+
+// scores are replied as strings, integral scores without a fraction like Redis
+fn format_score(score: f64) -> String {
+    score.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, RespDecode, Session};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_zadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$4\r\nzadd\r\n$1\r\nz\r\n$2\r\nNX\r\n$2\r\nch\r\n$3\r\n1.5\r\n$1\r\na\r\n$4\r\n-inf\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(ZAdd::try_from(frame).is_err());
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*8\r\n$4\r\nzadd\r\n$1\r\nz\r\n$2\r\nNX\r\n$2\r\nch\r\n$3\r\n1.5\r\n$1\r\na\r\n$4\r\n-inf\r\n$1\r\nb\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZAdd = frame.try_into()?;
+        assert_eq!(result.key, "z");
+        assert_eq!(result.condition, ZAddCondition::Nx);
+        assert!(result.ch);
+        assert_eq!(
+            result.members,
+            vec![(1.5, b"a".to_vec()), (f64::NEG_INFINITY, b"b".to_vec())]
+        );
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nzadd\r\n$1\r\nz\r\n$3\r\nnan\r\n$1\r\na\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(ZAdd::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_zset_commands() {
+        let backend = Backend::new();
+        let cmd = ZAdd {
+            key: "z".into(),
+            condition: ZAddCondition::Always,
+            ch: false,
+            members: vec![(2.0, b"b".to_vec()), (1.5, b"a".to_vec())],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(2)
+        );
+
+        let cmd = ZScore {
+            key: "z".into(),
+            member: b"b".to_vec(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            BulkString::from("2").into()
+        );
+
+        let cmd = ZRange {
+            key: "z".into(),
+            start: 0,
+            stop: -1,
+            rev: true,
+            withscores: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![
+                BulkString::from("b").into(),
+                BulkString::from("2").into(),
+                BulkString::from("a").into(),
+                BulkString::from("1.5").into(),
+            ])
+            .into()
+        );
+
+        let cmd = ZRem {
+            key: "z".into(),
+            members: vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()],
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(2)
+        );
+        assert!(!backend.key_exists(b"z"));
+    }
+
+    #[test]
+    fn test_zset_range_bounds_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nzcount\r\n$1\r\nz\r\n$4\r\n(1.5\r\n$4\r\n+inf\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZCount = frame.try_into()?;
+        assert_eq!(
+            result.min,
+            ScoreBound {
+                score: 1.5,
+                exclusive: true
+            }
+        );
+        assert_eq!(
+            result.max,
+            ScoreBound {
+                score: f64::INFINITY,
+                exclusive: false
+            }
+        );
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nzcount\r\n$1\r\nz\r\n$1\r\n(\r\n$1\r\n1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(ZCount::try_from(frame).is_err());
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$11\r\nzrangebylex\r\n$1\r\nz\r\n$2\r\n(a\r\n$2\r\n[c\r\n$5\r\nLIMIT\r\n$1\r\n1\r\n$2\r\n-1\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZRangeByLex = frame.try_into()?;
+        assert_eq!(result.min, LexBound::Exclusive(b"a".to_vec()));
+        assert_eq!(result.max, LexBound::Inclusive(b"c".to_vec()));
+        assert_eq!((result.offset, result.count), (1, None));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$11\r\nzrangebylex\r\n$1\r\nz\r\n$1\r\na\r\n$1\r\n+\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(ZRangeByLex::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_binary_members() -> Result<()> {
+        let command = |args: &[&[u8]]| {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+        };
+        let backend = Backend::new();
+        let member = b"\xff\x00m";
+        let cmd: ZAdd = command(&[b"zadd", b"z", b"1", member]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(1)
+        );
+        let cmd: ZScore = command(&[b"zscore", b"z", member]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            BulkString::from("1").into()
+        );
+        let cmd: ZRange = command(&[b"zrange", b"z", b"0", b"-1"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![BulkString::from(member).into()]).into()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_zincrby_and_zcount() {
+        let backend = Backend::new();
+        let cmd = ZIncrBy {
+            key: "z".into(),
+            increment: 3.0,
+            member: b"a".to_vec(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            BulkString::from("3").into()
+        );
+        let cmd = ZIncrBy {
+            key: "z".into(),
+            increment: f64::INFINITY,
+            member: b"b".to_vec(),
+        };
+        cmd.execute(&backend, &mut Session::default()).await;
+        let cmd = ZIncrBy {
+            key: "z".into(),
+            increment: f64::NEG_INFINITY,
+            member: b"b".to_vec(),
+        };
+        assert!(matches!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Error(_)
+        ));
+
+        let cmd = ZCard { key: "z".into() };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(2)
+        );
+        let cmd = ZCount {
+            key: "z".into(),
+            min: ScoreBound {
+                score: 3.0,
+                exclusive: true,
+            },
+            max: ScoreBound {
+                score: f64::INFINITY,
+                exclusive: false,
+            },
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespFrame::Integer(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_zpop_commands() {
+        let backend = Backend::new();
+        let members = vec![(1.0, b"a".to_vec()), (2.0, b"b".to_vec())];
+        backend.zstore("z".into(), members);
+
+        let cmd = ZPopMax {
+            key: "z".into(),
+            count: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![
+                BulkString::from("b").into(),
+                BulkString::from("2").into(),
+            ])
+            .into()
+        );
+
+        let cmd = BZPopMin {
+            keys: vec!["missing".into(), b"z".to_vec().into()],
+            timeout: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![
+                BulkString::from("z").into(),
+                BulkString::from("a").into(),
+                BulkString::from("1").into(),
+            ])
+            .into()
+        );
+        assert!(!backend.key_exists(b"z"));
+
+        let cmd = ZPopMin {
+            key: "z".into(),
+            count: Some(2),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespArray::new(vec![]).into()
+        );
+
+        let cmd = BZPopMax {
+            keys: vec!["z".into()],
+            timeout: Some(std::time::Duration::from_millis(10)),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut Session::default()).await,
+            RespNullArray.into()
+        );
+    }
+
+    #[test]
+    fn test_zunionstore_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*10\r\n$11\r\nzunionstore\r\n$1\r\nd\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$7\r\nweights\r\n$1\r\n2\r\n$3\r\n0.5\r\n$9\r\naggregate\r\n$3\r\nmax\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZUnionStore = frame.try_into()?;
+        assert_eq!(result.destination, "d");
+        assert_eq!(result.keys, vec!["a", "b"]);
+        assert_eq!(result.weights, vec![2.0, 0.5]);
+        assert_eq!(result.aggregate, ZAggregate::Max);
+
+        // a weight for every key
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$11\r\nzinterstore\r\n$1\r\nd\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$7\r\nweights\r\n$1\r\n2\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(ZInterStore::try_from(frame).is_err());
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$11\r\nzinterstore\r\n$1\r\nd\r\n$1\r\n0\r\n$1\r\na\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(ZInterStore::try_from(frame).is_err());
+
+        Ok(())
+    }
+}
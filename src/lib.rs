@@ -1,8 +1,19 @@
 mod backend;
+mod migrate;
+mod replica;
 mod resp;
+mod session;
+#[cfg(test)]
+mod simulation;
 
+pub mod cli;
+pub mod client;
 pub mod cmd;
+pub mod logging;
 pub mod network;
+pub mod server;
 
 pub use backend::*;
 pub use resp::*;
+pub use server::{Server, ServerBuilder};
+pub use session::{ReplyMode, Session};
@@ -0,0 +1,393 @@
+// Logging of the server, set up from the config parameters:
+//
+//   loglevel      trace, debug, info, warn, error or off, changes at runtime with CONFIG SET
+//   logfile       the file to log to, empty for stdout
+//   log-format    text, or json with one object per line for log collectors
+//   log-rotation  never, or daily to start logfile.YYYY-MM-DD every day (UTC)
+//
+// Only the level can change once logging is set up, the others are read by init.
+
+use crate::Backend;
+use anyhow::Result;
+use std::{
+    fmt::{self, Write as _},
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{field::Field, level_filters::LevelFilter, Event, Subscriber};
+use tracing_subscriber::{
+    fmt::{format, FmtContext, FormatEvent, FormatFields},
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    reload,
+    util::SubscriberInitExt,
+    Registry,
+};
+
+pub const LOG_LEVELS: &[&str] = &["trace", "debug", "info", "warn", "error", "off"];
+pub const LOG_FORMATS: &[&str] = &["text", "json"];
+pub const LOG_ROTATIONS: &[&str] = &["never", "daily"];
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// the level filter of the installed subscriber, for CONFIG SET loglevel
+static LEVEL_HANDLE: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogRotation {
+    #[default]
+    Never,
+    Daily,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogConfig {
+    pub level: LevelFilter,
+    pub file: String,
+    pub format: LogFormat,
+    pub rotation: LogRotation,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: LevelFilter::DEBUG,
+            file: String::new(),
+            format: LogFormat::default(),
+            rotation: LogRotation::default(),
+        }
+    }
+}
+
+impl LogFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+impl LogRotation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogRotation::Never => "never",
+            LogRotation::Daily => "daily",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "never" => Some(LogRotation::Never),
+            "daily" => Some(LogRotation::Daily),
+            _ => None,
+        }
+    }
+}
+
+pub fn level_name(level: LevelFilter) -> &'static str {
+    match level {
+        LevelFilter::TRACE => "trace",
+        LevelFilter::DEBUG => "debug",
+        LevelFilter::INFO => "info",
+        LevelFilter::WARN => "warn",
+        LevelFilter::ERROR => "error",
+        _ => "off",
+    }
+}
+
+pub fn level_from_name(name: &str) -> Option<LevelFilter> {
+    match name {
+        "trace" => Some(LevelFilter::TRACE),
+        "debug" => Some(LevelFilter::DEBUG),
+        "info" => Some(LevelFilter::INFO),
+        "warn" => Some(LevelFilter::WARN),
+        "error" => Some(LevelFilter::ERROR),
+        "off" => Some(LevelFilter::OFF),
+        _ => None,
+    }
+}
+
+// install the global subscriber as the backend's log config says
+pub fn init(backend: &Backend) -> Result<()> {
+    let config = backend.log_config();
+    let (filter, handle) = reload::Layer::new(config.level);
+    let layer = tracing_subscriber::fmt::layer().event_format(EventFormat {
+        format: config.format,
+        text: format::Format::default(),
+    });
+    let registry = tracing_subscriber::registry().with(filter);
+    if config.file.is_empty() {
+        registry.with(layer).try_init()?;
+    } else {
+        let file = RollingFile::open(config.file, config.rotation)?;
+        registry
+            .with(layer.with_ansi(false).with_writer(Mutex::new(file)))
+            .try_init()?;
+    }
+    let _ = LEVEL_HANDLE.set(handle);
+    Ok(())
+}
+
+// the level of the installed subscriber, nothing to do before init
+pub(crate) fn reload_level(level: LevelFilter) {
+    if let Some(handle) = LEVEL_HANDLE.get() {
+        let _ = handle.reload(level);
+    }
+}
+
+struct EventFormat {
+    format: LogFormat,
+    text: format::Format,
+}
+
+impl<S, N> FormatEvent<S, N> for EventFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: format::Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        if self.format == LogFormat::Text {
+            return self.text.format_event(ctx, writer, event);
+        }
+        let metadata = event.metadata();
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        writeln!(
+            writer,
+            "{{\"timestamp\":\"{}\",\"level\":\"{}\",\"target\":{},\"fields\":{{{}}}}}",
+            rfc3339(SystemTime::now()),
+            metadata.level(),
+            json_string(metadata.target()),
+            fields.0
+        )
+    }
+}
+
+// the fields of an event as the members of a JSON object
+#[derive(Default)]
+struct JsonFields(String);
+
+impl tracing::field::Visit for JsonFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, json_string(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, value.to_string());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, value.to_string());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, json_string(&format!("{:?}", value)));
+    }
+}
+
+impl JsonFields {
+    fn push(&mut self, field: &Field, value: String) {
+        if !self.0.is_empty() {
+            self.0.push(',');
+        }
+        let _ = write!(self.0, "{}:{}", json_string(field.name()), value);
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// the log file, with daily rotation a new one named after the date when the day changes
+struct RollingFile {
+    path: String,
+    rotation: LogRotation,
+    day: u64,
+    file: File,
+}
+
+impl RollingFile {
+    fn open(path: String, rotation: LogRotation) -> io::Result<Self> {
+        let day = today();
+        let file = open_append(&file_name(&path, rotation, day))?;
+        Ok(RollingFile {
+            path,
+            rotation,
+            day,
+            file,
+        })
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.rotation == LogRotation::Daily {
+            let day = today();
+            if day != self.day {
+                self.file = open_append(&file_name(&self.path, self.rotation, day))?;
+                self.day = day;
+            }
+        }
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &str) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn file_name(path: &str, rotation: LogRotation, day: u64) -> String {
+    match rotation {
+        LogRotation::Never => path.to_string(),
+        LogRotation::Daily => {
+            let (year, month, day) = civil_date(day);
+            format!("{}.{:04}-{:02}-{:02}", path, year, month, day)
+        }
+    }
+}
+
+// days since the unix epoch
+fn today() -> u64 {
+    unix_seconds(SystemTime::now()) / SECONDS_PER_DAY
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_date(seconds / SECONDS_PER_DAY);
+    let seconds_of_day = seconds % SECONDS_PER_DAY;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_micros()
+    )
+}
+
+// year, month and day of the days since the unix epoch, Howard Hinnant's civil_from_days
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        // 2024-02-29
+        assert_eq!(civil_date(19_782), (2024, 2, 29));
+        assert_eq!(
+            rfc3339(
+                UNIX_EPOCH
+                    + Duration::from_micros(19_782 * SECONDS_PER_DAY * 1_000_000 + 3_723_000_042)
+            ),
+            "2024-02-29T01:02:03.000042Z"
+        );
+        assert_eq!(
+            file_name("redis.log", LogRotation::Never, 19_782),
+            "redis.log"
+        );
+        assert_eq!(
+            file_name("redis.log", LogRotation::Daily, 19_782),
+            "redis.log.2024-02-29"
+        );
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+
+    #[test]
+    fn test_log_config() {
+        let backend = Backend::new();
+        assert_eq!(backend.log_config(), LogConfig::default());
+        backend.config_set("loglevel", "WARN").unwrap();
+        backend.config_set("log-format", "json").unwrap();
+        backend.config_set("log-rotation", "daily").unwrap();
+        backend.config_set("logfile", "/tmp/redis.log").unwrap();
+        assert_eq!(
+            backend.log_config(),
+            LogConfig {
+                level: LevelFilter::WARN,
+                file: "/tmp/redis.log".to_string(),
+                format: LogFormat::Json,
+                rotation: LogRotation::Daily,
+            }
+        );
+        assert_eq!(
+            backend.config_get("loglevel").map(|v| v.to_string()),
+            Some("warn".to_string())
+        );
+        assert!(backend.config_set("loglevel", "loud").is_err());
+    }
+}
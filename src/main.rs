@@ -1,34 +1,85 @@
 use anyhow::Result;
-use simple_redis::{network, Backend};
-use tokio::net::TcpListener;
-use tracing::{info, warn};
+use simple_redis::{
+    cli::{self, Mode},
+    logging, Backend, Server,
+};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tracing::{error, info};
+
+static SIGTERM_RECEIVED: AtomicBool = AtomicBool::new(false);
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing::subscriber::set_global_default(
-        tracing_subscriber::FmtSubscriber::builder()
-            .with_max_level(tracing::Level::DEBUG)
-            .finish(),
-    )?;
+    // logging is set up from the config, errors in it go to stderr
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let backend = match options.shards {
+        Some(shards) => Backend::with_shards(shards),
+        None => Backend::new(),
+    };
+    if let Err(e) = cli::apply_config(&backend, &options.config) {
+        eprintln!("Invalid configuration: {}", e);
+        std::process::exit(1);
+    }
+    logging::init(&backend)?;
+    match options.mode {
+        Mode::Serve => {}
+        Mode::CheckConfig => exit_with(cli::check_config(&backend), "Configuration is valid"),
+        Mode::TestMemory(megabytes) => exit_with(
+            cli::test_memory(megabytes),
+            &format!("Tested {} MB of memory", megabytes),
+        ),
+    }
 
-    let addr = "0.0.0.0:6379";
-    info!("Simple-Redis-Server is listening on {}", addr);
-    let listener = TcpListener::bind(addr).await?;
+    let server = Server::builder()
+        .backend(backend)
+        .shutdown_signal(async {
+            sigterm().await;
+            info!("Received SIGTERM");
+        })
+        .build()
+        .await?;
+    if server.run().await.is_err() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
 
-    let backend = Backend::new();
-    loop {
-        let (stream, raddr) = listener.accept().await?;
-        info!("Accepted connection from: {}", raddr);
-        let cloned_backend = backend.clone();
-        tokio::spawn(async move {
-            match network::stream_handler(stream, cloned_backend).await {
-                Ok(_) => {
-                    info!("Connection from {} exited", raddr);
-                }
-                Err(e) => {
-                    warn!("handle error for {}: {:?}", raddr, e);
-                }
-            }
-        });
+fn exit_with(result: Result<(), String>, success: &str) -> ! {
+    match result {
+        Ok(()) => {
+            info!("{}", success);
+            std::process::exit(0);
+        }
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+extern "C" fn on_sigterm(_: libc::c_int) {
+    SIGTERM_RECEIVED.store(true, Ordering::SeqCst);
+}
+
+// resolves once the process receives SIGTERM. The handler only sets a flag, which is
+// all a signal handler can safely do, and the flag is polled from here.
+async fn sigterm() {
+    let handler: extern "C" fn(libc::c_int) = on_sigterm;
+    // SAFETY: the handler is async-signal-safe, it only stores to an atomic
+    unsafe {
+        libc::signal(libc::SIGTERM, handler as libc::sighandler_t);
+    }
+    let mut interval = tokio::time::interval(Duration::from_millis(100));
+    while !SIGTERM_RECEIVED.load(Ordering::SeqCst) {
+        interval.tick().await;
     }
 }
@@ -0,0 +1,177 @@
+// MIGRATE: move keys to another instance. The keys are dumped here and sent to the
+// target as pipelined RESTORE commands on a connection of their own, then deleted here
+// once the target restored them, unless they are only copied. Connecting and every
+// read and write have to finish within the timeout.
+
+use crate::{
+    Backend, BulkString, Key, RespArray, RespEncode, RespFrame, RespFrameDecoder, NOTIFY_GENERIC,
+};
+use bytes::BytesMut;
+use std::{future::Future, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+const CONNECT_ERROR: &str = "IOERR error or timeout connecting to the client";
+const IO_ERROR: &str = "IOERR error or timeout reading to target instance";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MigrateOptions {
+    pub(crate) copy: bool,
+    pub(crate) replace: bool,
+    pub(crate) timeout: Duration,
+}
+
+// the keys migrated, none when none of them exist. Fails on the first error of the
+// target, the keys it restored before are migrated nevertheless.
+pub(crate) async fn migrate(
+    backend: &Backend,
+    host: &str,
+    port: u16,
+    keys: &[Key],
+    options: MigrateOptions,
+) -> Result<Vec<Key>, String> {
+    let dumped: Vec<(Key, Vec<u8>)> = keys
+        .iter()
+        .filter_map(|key| backend.dump(key).map(|payload| (key.clone(), payload)))
+        .collect();
+    if dumped.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut stream = within(options.timeout, TcpStream::connect((host, port)))
+        .await
+        .map_err(|_| CONNECT_ERROR.to_string())?;
+    let mut buf = Vec::new();
+    for (key, payload) in dumped.iter() {
+        let mut args: Vec<RespFrame> = vec![
+            BulkString::from("RESTORE").into(),
+            key.clone().into(),
+            BulkString::from("0").into(),
+            BulkString::new(payload.clone()).into(),
+        ];
+        if options.replace {
+            args.push(BulkString::from("REPLACE").into());
+        }
+        buf.extend_from_slice(&RespArray::new(args).encode());
+    }
+    within(options.timeout, stream.write_all(&buf))
+        .await
+        .map_err(|_| IO_ERROR.to_string())?;
+
+    let mut decoder = RespFrameDecoder::new();
+    let mut read_buf = BytesMut::new();
+    let mut migrated = Vec::with_capacity(dumped.len());
+    let mut error = None;
+    for (key, _) in dumped {
+        let reply = loop {
+            match decoder.decode(&mut read_buf) {
+                Ok(Some(frame)) => break frame,
+                Ok(None) => {}
+                Err(_) => return finish(backend, migrated, options, Some(IO_ERROR.to_string())),
+            }
+            match within(options.timeout, stream.read_buf(&mut read_buf)).await {
+                Ok(n) if n > 0 => {}
+                _ => return finish(backend, migrated, options, Some(IO_ERROR.to_string())),
+            }
+        };
+        match reply {
+            RespFrame::Error(e) => {
+                error.get_or_insert_with(|| {
+                    format!("ERR Target instance replied with error: {}", e.as_str())
+                });
+            }
+            _ => migrated.push(key),
+        }
+    }
+    finish(backend, migrated, options, error)
+}
+
+// delete what was migrated unless it is copied
+fn finish(
+    backend: &Backend,
+    migrated: Vec<Key>,
+    options: MigrateOptions,
+    error: Option<String>,
+) -> Result<Vec<Key>, String> {
+    if !options.copy {
+        for key in backend.del(&migrated) {
+            backend.notify_keyspace_event(NOTIFY_GENERIC, "del", &key);
+        }
+    }
+    match error {
+        Some(e) => Err(e),
+        None => Ok(migrated),
+    }
+}
+
+async fn within<T, E>(timeout: Duration, fut: impl Future<Output = Result<T, E>>) -> Result<T, ()> {
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(Ok(v)) => Ok(v),
+        _ => Err(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Server;
+
+    #[tokio::test]
+    async fn test_migrate() -> anyhow::Result<()> {
+        let server = Server::builder().addr("127.0.0.1:0").build().await?;
+        let port = server.local_addr()?.port();
+        let target = server.backend().clone();
+        tokio::spawn(server.run());
+
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("1").into());
+        backend.rpush("b".into(), vec![BulkString::from("x").into()]);
+        let options = MigrateOptions {
+            copy: true,
+            replace: false,
+            timeout: Duration::from_secs(1),
+        };
+        let keys = vec![Key::from("a"), Key::from("b"), Key::from("c")];
+        assert_eq!(
+            migrate(&backend, "127.0.0.1", port, &keys, options).await,
+            Ok(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(target.get(b"a"), Some(BulkString::from("1").into()));
+        assert_eq!(target.dump(b"b"), backend.dump(b"b"));
+        assert!(backend.key_exists(b"a"));
+
+        // the keys exist on the target now
+        let options = MigrateOptions {
+            copy: false,
+            ..options
+        };
+        let result = migrate(&backend, "127.0.0.1", port, &keys, options).await;
+        assert!(result.unwrap_err().contains("BUSYKEY"));
+        assert!(backend.key_exists(b"a"));
+
+        backend.set("a".into(), BulkString::from("2").into());
+        let options = MigrateOptions {
+            replace: true,
+            ..options
+        };
+        assert_eq!(
+            migrate(&backend, "127.0.0.1", port, &keys[..1], options).await,
+            Ok(vec!["a".into()])
+        );
+        assert!(!backend.key_exists(b"a"));
+        assert_eq!(target.get(b"a"), Some(BulkString::from("2").into()));
+        assert_eq!(
+            migrate(&backend, "127.0.0.1", port, &keys[2..], options).await,
+            Ok(vec![])
+        );
+
+        // nothing listens on port 1
+        let result = migrate(&backend, "127.0.0.1", 1, &keys[1..2], options).await;
+        assert_eq!(result, Err(CONNECT_ERROR.to_string()));
+        assert!(backend.key_exists(b"b"));
+        target.request_shutdown(crate::ShutdownPolicy::NoSave);
+        Ok(())
+    }
+}
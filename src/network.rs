@@ -1,21 +1,61 @@
 use crate::{
-    cmd::{Command, CommandExecutor},
-    Backend, RespDecode, RespEncode, RespError, RespFrame,
+    backend::{LATENCY_COMMAND, LATENCY_FAST_COMMAND, NOTIFY_STRING},
+    cmd::{
+        lookup_command, propagated_command, Command, CommandEntry, CommandError, CommandExecutor,
+        CommandSpec, PSync, RESP_OK,
+    },
+    Backend, BulkString, DecoderLimits, Key, RespArray, RespEncode, RespFrame, RespFrameDecoder,
+    RespNull, Session, SimpleError,
 };
 use anyhow::Result;
 use futures::SinkExt;
-use tokio::net::TcpStream;
+use std::{collections::VecDeque, time::Instant};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+    sync::mpsc,
+};
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
-use tracing::info;
+use tracing::{field, info, info_span, Instrument};
 
-#[derive(Debug)]
-struct RespFrameCodec;
+const READONLY_ERROR: &str = "READONLY You can't write against a read only replica.";
+const READONLY_SERVER_ERROR: &str = "READONLY You can't write against a read only server.";
+const MAXCLIENTS_ERROR: &str = "-ERR max number of clients reached\r\n";
+// the frames a paused connection reads ahead, the rest waits in the socket
+const PAUSED_QUEUE_LEN: usize = 1024;
+// what a RESP2 connection can run while it is subscribed
+const SUBSCRIBED_COMMANDS: &[&str] = &[
+    "subscribe",
+    "psubscribe",
+    "unsubscribe",
+    "punsubscribe",
+    "ssubscribe",
+    "sunsubscribe",
+    "ping",
+    "quit",
+    "reset",
+];
+
+// a request that can't be decoded: there is no telling where the next one starts, so the
+// connection is closed after the error is replied, like Redis does
+#[derive(Debug, thiserror::Error)]
+#[error("Protocol error: {0}")]
+struct ProtocolError(String);
+
+#[derive(Debug, Default)]
+struct RespFrameCodec {
+    decoder: RespFrameDecoder,
+    // the RESP version the client negotiated with HELLO, replies are downgraded below 3
+    protocol: u8,
+}
 
 #[derive(Debug)]
-struct RedisRequest {
+struct RedisRequest<'a> {
     frame: RespFrame,
+    entry: Option<&'static CommandEntry>,
     backend: Backend,
+    session: &'a mut Session,
 }
 
 #[derive(Debug)]
@@ -24,44 +64,590 @@ struct RedisResponse {
 }
 
 pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
+    let client_addr = stream
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_default();
+    connection_handler(stream, client_addr, backend).await
+}
+
+// serve a client over any byte stream, so transports other than plain TCP (a TLS session,
+// a unix socket) share the handling of connections. client_addr is what CLIENT LIST and
+// the logs show for it.
+pub async fn connection_handler<S>(
+    mut stream: S,
+    client_addr: String,
+    backend: Backend,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if backend.stats.connection_opened() > backend.maxclients() {
+        backend.stats.connection_closed();
+        backend.stats.connection_rejected();
+        stream.write_all(MAXCLIENTS_ERROR.as_bytes()).await?;
+        return Ok(());
+    }
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let mut session = Session::new(client_addr, sender);
+    let ret = serve(stream, &backend, &mut session, receiver).await;
+    session.close(&backend);
+    backend.stats.connection_closed();
+    ret
+}
+
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    backend: &Backend,
+    session: &mut Session,
+    mut receiver: mpsc::UnboundedReceiver<RespFrame>,
+) -> Result<()> {
     // how to get a frame from the stream?
-    let mut framed = Framed::new(stream, RespFrameCodec);
+    let mut framed = Framed::new(stream, RespFrameCodec::new(backend.decoder_limits()));
+    let mut last_interaction = tokio::time::Instant::now();
+    let pushes = session.sender();
+    // what the client sent while its command was held by CLIENT PAUSE
+    let mut queued = VecDeque::new();
     loop {
-        match framed.next().await {
-            Some(Ok(frame)) => {
-                info!("Received frame: {:?}", frame);
-                let request = RedisRequest {
-                    frame,
-                    backend: backend.clone(),
-                };
-                let response = request_handler(request).await?;
-                info!("Sending response: {:?}", response.frame);
-                framed.send(response.frame).await?;
+        // with timeout set, idle clients are dropped so leaked connections don't pile up.
+        // Subscribers are idle by design and keep their connection, like in Redis.
+        let timeout = backend.client_timeout();
+        let idle_deadline = match timeout.is_zero() || session.is_subscribed() {
+            true => None,
+            false => last_interaction.checked_add(timeout),
+        };
+        tokio::select! {
+            // on shutdown the connection closes between commands, never in the middle of one
+            biased;
+            _ = backend.shutdown_requested() => return Ok(()),
+            frame = next_frame(&mut framed, &mut queued) => match frame {
+                Some(Ok(frame)) => {
+                    last_interaction = tokio::time::Instant::now();
+                    info!("Received frame: {:?}", frame);
+                    // clients send requests, never errors
+                    if matches!(frame, RespFrame::Error(_)) {
+                        let e = ProtocolError("unexpected error frame".to_string());
+                        return protocol_error(framed, session, e).await;
+                    }
+                    let entry = command_entry(&frame);
+                    let name = entry.map_or("", |entry| entry.spec.name);
+                    if name == "psync" {
+                        match Command::try_from(frame) {
+                            Ok(Command::PSync(psync)) => {
+                                return serve_replica(framed, backend, session, psync).await
+                            }
+                            Ok(_) => unreachable!(),
+                            Err(e) => {
+                                framed.send(e.into()).await?;
+                                continue;
+                            }
+                        }
+                    }
+                    let write = entry.is_some_and(|entry| entry.spec.is_write());
+                    if !session.is_master_link() && backend.is_paused(write) {
+                        let open = wait_while_paused(&mut framed, backend, write, &mut queued).await?;
+                        if !open {
+                            return Ok(());
+                        }
+                    }
+                    let request = RedisRequest {
+                        frame,
+                        entry,
+                        backend: backend.clone(),
+                        session,
+                    };
+                    let skipped = request.session.take_reply_skip();
+                    let response = request_handler(request).await?;
+                    // HELLO switches the protocol from its own reply on
+                    framed.codec_mut().protocol = session.protocol();
+                    if name == "shutdown" && backend.is_shutting_down() {
+                        return Ok(());
+                    }
+                    // frames pushed while executing (e.g. subscribe confirmations) go first
+                    while let Ok(frame) = receiver.try_recv() {
+                        pushes.sent(&frame);
+                        framed.feed(session.as_push(frame)).await?;
+                    }
+                    // CLIENT REPLY OFF/SKIP: the command still runs, only its reply is
+                    // dropped. Pushes are delivered all the same, like the RESP3
+                    // confirmation a SUBSCRIBE replies with.
+                    let push = matches!(response.frame, RespFrame::Push(_));
+                    if (skipped || !session.replies_enabled()) && !push {
+                        framed.flush().await?;
+                        if name == "quit" {
+                            return close(framed).await;
+                        }
+                        continue;
+                    }
+                    info!("Sending response: {:?}", response.frame);
+                    framed.send(response.frame).await?;
+                    if name == "quit" {
+                        return close(framed).await;
+                    }
+                }
+                Some(Err(e)) => match e.downcast::<ProtocolError>() {
+                    Ok(e) => return protocol_error(framed, session, e).await,
+                    Err(e) => return Err(e),
+                },
+                None => return Ok(()),
+            },
+            Some(frame) = receiver.recv() => {
+                pushes.sent(&frame);
+                // over client-output-buffer-limit, what is still queued is dropped with it
+                if pushes.overflowed() {
+                    info!("Closing {}, over its output buffer limit", session.addr());
+                    return Ok(());
+                }
+                framed.send(session.as_push(frame)).await?;
+            }
+            _ = idle(idle_deadline) => {
+                info!("Closing idle connection {}", session.addr());
+                return Ok(());
             }
-            Some(Err(e)) => return Err(e),
-            None => return Ok(()),
         }
     }
 }
 
-async fn request_handler(request: RedisRequest) -> Result<RedisResponse> {
-    let (frame, backend) = (request.frame, request.backend);
-    let cmd_result = Command::try_from(frame);
-    let frame = match cmd_result {
-        Ok(cmd) => {
-            info!("Executing command: {:?}", cmd);
-            cmd.execute(&backend)
+// the frames queued while the connection was paused first, then those from the stream
+async fn next_frame<S: AsyncRead + AsyncWrite + Unpin>(
+    framed: &mut Framed<S, RespFrameCodec>,
+    queued: &mut VecDeque<Result<RespFrame>>,
+) -> Option<Result<RespFrame>> {
+    match queued.pop_front() {
+        Some(frame) => Some(frame),
+        None => framed.next().await,
+    }
+}
+
+// reply the error, unless CLIENT REPLY drops it, and close the connection
+async fn protocol_error<S: AsyncRead + AsyncWrite + Unpin>(
+    mut framed: Framed<S, RespFrameCodec>,
+    session: &mut Session,
+    e: ProtocolError,
+) -> Result<()> {
+    info!("Closing {}: {}", session.addr(), e);
+    if !session.take_reply_skip() && session.replies_enabled() {
+        framed
+            .send(SimpleError::new(format!("ERR {}", e)).into())
+            .await?;
+    }
+    close(framed).await
+}
+
+// hold the command until CLIENT PAUSE lets it run, reading what the client sends meanwhile
+// into the queue, up to PAUSED_QUEUE_LEN frames. Returns false when the connection closes.
+async fn wait_while_paused<S: AsyncRead + AsyncWrite + Unpin>(
+    framed: &mut Framed<S, RespFrameCodec>,
+    backend: &Backend,
+    write: bool,
+    queued: &mut VecDeque<Result<RespFrame>>,
+) -> Result<bool> {
+    let unpaused = backend.wait_for_unpause(write);
+    tokio::pin!(unpaused);
+    loop {
+        tokio::select! {
+            biased;
+            _ = backend.shutdown_requested() => return Ok(false),
+            _ = &mut unpaused => return Ok(true),
+            // nothing is read after an error, it is replied once the frames before it ran
+            frame = framed.next(), if queued.len() < PAUSED_QUEUE_LEN
+                && !queued.back().is_some_and(Result::is_err) => match frame {
+                Some(frame) => queued.push_back(frame),
+                None => return Ok(false),
+            },
         }
-        Err(e) => RespFrame::Error(crate::SimpleError(e.to_string())),
-    };
+    }
+}
+
+// QUIT: the reply is flushed already, shut the socket down from this side so the client
+// sees the end of the stream instead of having to drop the connection itself
+async fn close<S: AsyncRead + AsyncWrite + Unpin>(
+    mut framed: Framed<S, RespFrameCodec>,
+) -> Result<()> {
+    framed.flush().await?;
+    framed.get_mut().shutdown().await?;
+    Ok(())
+}
 
+// resolves at the deadline, never without one
+async fn idle(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+// a connection that sent PSYNC is a replica from then on: it gets the snapshot or the
+// part of the backlog it is missing, then the stream of writes. Commands it sends, i.e.
+// REPLCONF ACK, still run but get no reply.
+async fn serve_replica<S: AsyncRead + AsyncWrite + Unpin>(
+    mut framed: Framed<S, RespFrameCodec>,
+    backend: &Backend,
+    session: &mut Session,
+    psync: PSync,
+) -> Result<()> {
+    let (head, mut stream) = psync.start(backend, session).await;
+    info!("Replica {} connected", session.addr());
+    // the codec only writes frames, the stream is raw bytes
+    framed.get_mut().write_all(&head).await?;
+    loop {
+        tokio::select! {
+            _ = backend.shutdown_requested() => return Ok(()),
+            bytes = stream.recv() => match bytes {
+                Some(bytes) => framed.get_mut().write_all(&bytes).await?,
+                None => return Ok(()),
+            },
+            frame = framed.next() => match frame {
+                Some(Ok(frame)) => {
+                    dispatch(frame, backend, session).await;
+                }
+                Some(Err(e)) => return Err(e),
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+async fn request_handler(request: RedisRequest<'_>) -> Result<RedisResponse> {
+    let RedisRequest {
+        frame,
+        entry,
+        backend,
+        session,
+    } = request;
+    let frame = dispatch_entry(frame, entry, &backend, session).await;
     Ok(RedisResponse { frame })
 }
 
+// parse and run the command in the frame, with the bookkeeping every command gets:
+// maxmemory, key access tracking, command stats and the slowlog
+pub async fn dispatch(frame: RespFrame, backend: &Backend, session: &mut Session) -> RespFrame {
+    let entry = command_entry(&frame);
+    dispatch_entry(frame, entry, backend, session).await
+}
+
+// dispatch without the GET/SET fast path, what the benchmark measures the fast path against
+pub async fn dispatch_generic(
+    frame: RespFrame,
+    backend: &Backend,
+    session: &mut Session,
+) -> RespFrame {
+    let entry = command_entry(&frame);
+    dispatch_command(frame, entry, backend, session).await
+}
+
+// dispatch, for a command already looked up
+async fn dispatch_entry(
+    frame: RespFrame,
+    entry: Option<&'static CommandEntry>,
+    backend: &Backend,
+    session: &mut Session,
+) -> RespFrame {
+    if let Some(e) = subscribed_error(&frame, entry, session) {
+        return e;
+    }
+    // the fast path doesn't route keys in a cluster
+    if backend.sample_trace() {
+        return traced_dispatch(frame, entry, backend, session).await;
+    }
+    if backend.cluster.is_enabled() {
+        return dispatch_command(frame, entry, backend, session).await;
+    }
+    match fast_path(frame, backend, session).await {
+        Ok(ret) => ret,
+        Err(frame) => dispatch_command(frame, entry, backend, session).await,
+    }
+}
+
+// run the command in a span a tracing subscriber can export, e.g. to OpenTelemetry: the
+// client, the command, its first key and number of arguments, and how long it took
+async fn traced_dispatch(
+    frame: RespFrame,
+    entry: Option<&'static CommandEntry>,
+    backend: &Backend,
+    session: &mut Session,
+) -> RespFrame {
+    let name = command_name(&frame);
+    let (args, key) = match (&frame, entry) {
+        (RespFrame::Array(args), entry) => (
+            args.len().saturating_sub(1),
+            entry.and_then(|entry| entry.spec.keys(args).into_iter().next()),
+        ),
+        _ => (0, None),
+    };
+    let span = info_span!(
+        "command",
+        client = session.addr(),
+        command = name.as_str(),
+        key = key.map(|key| key.to_string()).unwrap_or_default(),
+        args,
+        duration_us = field::Empty,
+        error = field::Empty,
+    );
+    let start = Instant::now();
+    let ret = dispatch_command(frame, entry, backend, session)
+        .instrument(span.clone())
+        .await;
+    span.record("duration_us", start.elapsed().as_micros() as u64);
+    if let RespFrame::Error(e) = &ret {
+        span.record("error", e.as_str());
+    }
+    ret
+}
+
+async fn dispatch_command(
+    frame: RespFrame,
+    entry: Option<&'static CommandEntry>,
+    backend: &Backend,
+    session: &mut Session,
+) -> RespFrame {
+    let spec = entry.map(|entry| entry.spec);
+    let name = spec.map_or("", |spec| spec.name);
+    let write = spec.is_some_and(CommandSpec::is_write);
+    let blocking = spec.is_some_and(CommandSpec::is_blocking);
+    if let Some(e) = write.then(|| write_error(backend, session)).flatten() {
+        return SimpleError::new(e).into();
+    }
+    // blocking commands can't hold up a full sync while they wait, they check for replicas
+    // once they are done
+    let _barrier = match write && !blocking {
+        true => Some(backend.replication.write_barrier().await),
+        false => None,
+    };
+    let published = write && (blocking || backend.follows_writes());
+    // keep the arguments around only when they may end up in the slowlog or an effect
+    let args = match &frame {
+        RespFrame::Array(args) if backend.slowlog.is_enabled() || published => Some(args.clone()),
+        _ => None,
+    };
+    let keys = match (&frame, spec) {
+        (RespFrame::Array(args), Some(spec)) => spec.keys(args),
+        _ => vec![],
+    };
+    let asking = session.take_asking();
+    if backend.cluster.is_enabled() && !session.is_master_link() {
+        if let Err(e) = backend.cluster_route(&keys, asking) {
+            return CommandError::from(e).into();
+        }
+    }
+    let cmd = match (frame, entry) {
+        (RespFrame::Array(args), Some(entry)) => entry.parse(args),
+        (frame, _) => Command::try_from(frame),
+    };
+    let cmd = match cmd {
+        Ok(cmd) => cmd,
+        Err(e) => return e.into(),
+    };
+    backend.expire_if_needed(&keys);
+    if spec.is_some_and(CommandSpec::is_readonly) {
+        for key in &keys {
+            backend.stats.keyspace_lookup(backend.key_exists(key));
+        }
+    }
+    if let Some((spec, key_type)) = spec.and_then(|spec| Some((spec, spec.key_type?))) {
+        if !backend.keys_have_type(spec.typed_keys(&keys), key_type) {
+            return CommandError::WrongType.into();
+        }
+    }
+    // a replica applies what its master wrote whatever its own memory, the master evicts
+    // for both and replicates the DELs
+    if spec.is_some_and(CommandSpec::is_denyoom) && !session.is_master_link() {
+        if let Err(e) = backend.evict_for_write() {
+            return SimpleError::new(e).into();
+        }
+    }
+    info!("Executing command: {:?}", cmd);
+    let start = Instant::now();
+    let ret = cmd.execute(backend, session).await;
+    backend.stats.record(name, start.elapsed());
+    if !blocking {
+        backend.latency.record(LATENCY_COMMAND, start.elapsed());
+    }
+    // CLIENT NO-TOUCH spares the keys the client reads, TOUCH still touches them
+    let no_touch = session.no_touch() && !write && name != "touch";
+    if !spec.is_some_and(CommandSpec::is_notouch) && !no_touch {
+        backend.record_key_access(&keys, write);
+    }
+    if !keys.is_empty() && backend.key_stats.sample() {
+        backend.record_key_stats(&keys, write);
+    }
+    if let Some(args) = args {
+        if backend.slowlog.is_enabled() {
+            backend
+                .slowlog
+                .record(&args, start.elapsed(), session.addr());
+        }
+        if published {
+            if let Some(command) = propagated_command(args, &ret) {
+                backend.publish_effect(command);
+            }
+        }
+    }
+    ret
+}
+
+// GET key and SET key value, the bulk of most workloads, run straight from the frame
+// without building a command. Anything else, including SET with options, is given back
+// for the generic path.
+async fn fast_path(
+    frame: RespFrame,
+    backend: &Backend,
+    session: &mut Session,
+) -> Result<RespFrame, RespFrame> {
+    let RespFrame::Array(RespArray(mut args)) = frame else {
+        return Err(frame);
+    };
+    let name = match args.as_slice() {
+        [RespFrame::BulkString(name), RespFrame::BulkString(_)]
+            if name.eq_ignore_ascii_case(b"get") =>
+        {
+            "get"
+        }
+        [RespFrame::BulkString(name), RespFrame::BulkString(_), RespFrame::BulkString(_)]
+            if name.eq_ignore_ascii_case(b"set") =>
+        {
+            "set"
+        }
+        _ => return Err(RespArray(args).into()),
+    };
+
+    let start = Instant::now();
+    let value = (name == "set").then(|| args.pop()).flatten();
+    let key = match args.pop() {
+        Some(RespFrame::BulkString(key)) => Key::from(key),
+        _ => Key::default(),
+    };
+    // the value shares its bytes, it is only kept in case the command ends up in the slowlog
+    let logged_value = value.clone().filter(|_| backend.slowlog.is_enabled());
+    let ret = match value {
+        None => {
+            backend.expire_if_needed(std::slice::from_ref(&key));
+            let value = backend.get_cached(&key, &mut session.get_cache);
+            let hit = value.is_some() || backend.key_exists(&key);
+            backend.stats.keyspace_lookup(hit);
+            match value {
+                Some(value) => value,
+                None if hit => CommandError::WrongType.into(),
+                None => RespFrame::Null(RespNull),
+            }
+        }
+        Some(value) => {
+            if let Some(e) = write_error(backend, session) {
+                return Ok(SimpleError::new(e).into());
+            }
+            let _barrier = backend.replication.write_barrier().await;
+            if !session.is_master_link() {
+                if let Err(e) = backend.evict_for_write() {
+                    return Ok(SimpleError::new(e).into());
+                }
+            }
+            if backend.follows_writes() {
+                backend.publish_effect(RespArray::new(vec![
+                    BulkString::from("set").into(),
+                    key.clone().into(),
+                    value.clone(),
+                ]));
+            }
+            backend.set(key.clone(), value);
+            backend.notify_keyspace_event(NOTIFY_STRING, "set", &key);
+            RESP_OK.clone()
+        }
+    };
+    backend.stats.record(name, start.elapsed());
+    backend
+        .latency
+        .record(LATENCY_FAST_COMMAND, start.elapsed());
+    if name == "set" || !session.no_touch() {
+        backend.record_key_access(std::slice::from_ref(&key), name == "set");
+    }
+    if backend.key_stats.sample() {
+        backend.record_key_stats(std::slice::from_ref(&key), name == "set");
+    }
+    if backend.slowlog.is_slow(start.elapsed()) {
+        args.push(key.into());
+        args.extend(logged_value);
+        backend
+            .slowlog
+            .record(&args, start.elapsed(), session.addr());
+    }
+    Ok(ret)
+}
+
+// a RESP2 connection with subscriptions only gets pushes, so it can only run the commands
+// that manage them until it unsubscribes from everything or resets. RESP3 tells replies and
+// pushes apart, its connections run any command like in Redis.
+fn subscribed_error(
+    frame: &RespFrame,
+    entry: Option<&'static CommandEntry>,
+    session: &Session,
+) -> Option<RespFrame> {
+    if session.protocol() >= 3 || !session.is_subscribed() {
+        return None;
+    }
+    if entry.is_some_and(|entry| SUBSCRIBED_COMMANDS.contains(&entry.spec.name)) {
+        return None;
+    }
+    Some(SimpleError::new(format!(
+        "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+        command_name(frame)
+    ))
+    .into())
+}
+
+// why a write is refused: the server is read-only, or a read-only replica, which only
+// takes writes from its master
+fn write_error(backend: &Backend, session: &Session) -> Option<&'static str> {
+    if session.is_master_link() {
+        return None;
+    }
+    if backend.read_only() {
+        return Some(READONLY_SERVER_ERROR);
+    }
+    (backend.replication.is_replica() && backend.replication.read_only()).then_some(READONLY_ERROR)
+}
+
+// the command in the frame, with its spec
+fn command_entry(frame: &RespFrame) -> Option<&'static CommandEntry> {
+    match frame {
+        RespFrame::Array(args) => match args.first() {
+            Some(RespFrame::BulkString(name)) => lookup_command(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// lowercase name of the command in the frame as sent, for errors and traces, which also
+// name the commands that don't exist
+fn command_name(frame: &RespFrame) -> String {
+    match frame {
+        RespFrame::Array(args) => match args.first() {
+            Some(RespFrame::BulkString(name)) => {
+                String::from_utf8_lossy(&name.to_ascii_lowercase()).into_owned()
+            }
+            _ => String::new(),
+        },
+        _ => String::new(),
+    }
+}
+
+impl RespFrameCodec {
+    fn new(limits: DecoderLimits) -> Self {
+        Self {
+            decoder: RespFrameDecoder::with_limits(limits),
+            protocol: 2,
+        }
+    }
+}
+
 impl Encoder<RespFrame> for RespFrameCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, item: RespFrame, dst: &mut bytes::BytesMut) -> Result<()> {
+        let item = match self.protocol {
+            3.. => item,
+            _ => item.into_resp2(),
+        };
         let encoded = item.encode();
         dst.extend_from_slice(&encoded);
         Ok(())
@@ -73,10 +659,618 @@ impl Decoder for RespFrameCodec {
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<RespFrame>> {
-        match RespFrame::decode(src) {
-            Ok(frame) => Ok(Some(frame)),
-            Err(RespError::NotComplete) => Ok(None),
-            Err(e) => Err(e.into()),
+        self.decoder
+            .decode(src)
+            .map_err(|e| ProtocolError(e.to_string()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, ShutdownPolicy, SimpleString, WRONGTYPE_ERROR};
+
+    fn command(args: &[&str]) -> RespFrame {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+
+    fn session() -> Session {
+        Session::new(String::new(), mpsc::unbounded_channel().0)
+    }
+
+    #[tokio::test]
+    async fn test_connection_handler_in_memory() -> Result<()> {
+        let backend = Backend::new();
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(
+            server,
+            "memory".to_string(),
+            backend.clone(),
+        ));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(command(&["set", "a", "1"])).await?;
+        assert_eq!(framed.next().await.transpose()?, Some(RESP_OK.clone()));
+        framed.send(command(&["get", "a"])).await?;
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(BulkString::from("1").into())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replies_follow_the_protocol() -> Result<()> {
+        let backend = Backend::new();
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(
+            server,
+            "memory".to_string(),
+            backend.clone(),
+        ));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(command(&["get", "missing"])).await?;
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(BulkString::null().into())
+        );
+        // HELLO 3 gets its reply in RESP3 already
+        framed.send(command(&["hello", "3"])).await?;
+        assert!(matches!(
+            framed.next().await.transpose()?,
+            Some(RespFrame::Map(_))
+        ));
+        framed.send(command(&["get", "missing"])).await?;
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(RespFrame::Null(RespNull))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_pause() -> Result<()> {
+        let backend = Backend::new();
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(
+            server,
+            "paused".to_string(),
+            backend.clone(),
+        ));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed
+            .send(command(&["client", "pause", "60000", "write"]))
+            .await?;
+        assert_eq!(framed.next().await.transpose()?, Some(RESP_OK.clone()));
+        // reads go on, a write is held with what the client sends after it
+        framed.send(command(&["get", "a"])).await?;
+        // a RESP2 client gets the null bulk string
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(BulkString::null().into())
+        );
+        framed.send(command(&["set", "a", "1"])).await?;
+        framed.send(command(&["get", "a"])).await?;
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(!backend.key_exists(b"a"));
+
+        backend.unpause_clients();
+        assert_eq!(framed.next().await.transpose()?, Some(RESP_OK.clone()));
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(BulkString::from("1").into())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connection_handler_unix_socket() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("simple-redis-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        let backend = Backend::new();
+        let client = tokio::net::UnixStream::connect(&path).await?;
+        let (server, _) = listener.accept().await?;
+        tokio::spawn(connection_handler(server, "unix:0".to_string(), backend));
+
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(command(&["set", "a", "1"])).await?;
+        assert_eq!(framed.next().await.transpose()?, Some(RESP_OK.clone()));
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_connection_commands() -> Result<()> {
+        let backend = Backend::new();
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(server, "a".to_string(), backend.clone()));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(command(&["subscribe", "news"])).await?;
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(RespFrame::Array(RespArray::new(vec![
+                BulkString::from("subscribe").into(),
+                BulkString::from("news").into(),
+                RespFrame::Integer(1),
+            ])))
+        );
+
+        framed.send(command(&["get", "a"])).await?;
+        let Some(RespFrame::Error(e)) = framed.next().await.transpose()? else {
+            panic!("GET must be refused while subscribed");
+        };
+        assert!(e.starts_with("ERR Can't execute 'get'"));
+        framed.send(command(&["ping"])).await?;
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(command(&["pong", ""]))
+        );
+
+        // RESET leaves subscribe mode
+        framed.send(command(&["reset"])).await?;
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(SimpleString::new("RESET").into())
+        );
+        assert!(!backend.pubsub.has_subscribers());
+        framed.send(command(&["get", "a"])).await?;
+        // a RESP2 client gets the null bulk string
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(BulkString::null().into())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quit_closes_the_connection() -> Result<()> {
+        let backend = Backend::new();
+        let (client, server) = tokio::io::duplex(1024);
+        let handler = tokio::spawn(connection_handler(server, "a".to_string(), backend.clone()));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(command(&["quit", "now"])).await?;
+        assert_eq!(framed.next().await.transpose()?, Some(RESP_OK.clone()));
+        assert!(framed.next().await.is_none());
+        handler.await??;
+        assert_eq!(backend.stats().connected_clients, 0);
+
+        // without replies there is no +OK, the connection closes all the same
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(server, "b".to_string(), backend));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(command(&["client", "reply", "off"])).await?;
+        framed.send(command(&["quit"])).await?;
+        assert!(framed.next().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_reply() -> Result<()> {
+        let backend = Backend::new();
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(server, "a".to_string(), backend.clone()));
+        // OFF drops every reply, errors included, the commands still run
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(command(&["client", "reply", "off"])).await?;
+        framed.send(command(&["set", "a", "1"])).await?;
+        framed.send(command(&["nope"])).await?;
+        // SKIP drops the reply of the next command only, its own reply as well
+        framed.send(command(&["client", "reply", "skip"])).await?;
+        framed.send(command(&["incr", "a"])).await?;
+        framed.send(command(&["get", "a"])).await?;
+        framed.send(command(&["client", "reply", "skip"])).await?;
+        framed.send(command(&["get", "a"])).await?;
+        framed.send(command(&["client", "reply", "on"])).await?;
+        assert_eq!(framed.next().await.transpose()?, Some(RESP_OK.clone()));
+        framed.send(command(&["get", "a"])).await?;
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(BulkString::from("2").into())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_reply_off_still_pushes() -> Result<()> {
+        let backend = Backend::new();
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(server, "a".to_string(), backend.clone()));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(command(&["hello", "3"])).await?;
+        assert!(matches!(
+            framed.next().await.transpose()?,
+            Some(RespFrame::Map(_))
+        ));
+        framed.send(command(&["client", "reply", "off"])).await?;
+        framed.send(command(&["subscribe", "news"])).await?;
+        let confirmation = framed.next().await.transpose()?;
+        assert!(matches!(confirmation, Some(RespFrame::Push(_))));
+
+        // the message is a push, not a reply, OFF doesn't drop it
+        backend.pubsub.publish(b"news", b"hi");
+        let Some(RespFrame::Push(message)) = framed.next().await.transpose()? else {
+            panic!("expected a push");
+        };
+        assert_eq!(message.last(), Some(&BulkString::from("hi").into()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_protocol_error_closes_the_connection() -> Result<()> {
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("1").into());
+        // the payload of a bulk string over the limit is never run as a request
+        backend.config_set("proto-max-bulk-len", "16").unwrap();
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(server, "a".to_string(), backend.clone()));
+        client
+            .write_all(b"*1\r\n$4\r\nping\r\n$30\r\nxx\r\n*1\r\n$8\r\nflushall\r\n")
+            .await?;
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        assert_eq!(
+            framed.next().await.transpose()?,
+            Some(SimpleString::new("PONG").into())
+        );
+        assert!(matches!(
+            framed.next().await.transpose()?,
+            Some(RespFrame::Error(e)) if e.starts_with("ERR Protocol error")
+        ));
+        assert!(framed.next().await.is_none());
+        assert!(backend.key_exists(b"a"));
+
+        // so is an error frame sent by the client
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(server, "b".to_string(), backend));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(SimpleError::new("ERR nope").into()).await?;
+        framed.send(command(&["ping"])).await?;
+        assert!(matches!(
+            framed.next().await.transpose()?,
+            Some(RespFrame::Error(e)) if e.starts_with("ERR Protocol error")
+        ));
+        assert!(framed.next().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_maxclients() -> Result<()> {
+        let backend = Backend::new();
+        backend.set_maxclients(1);
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(server, "a".to_string(), backend.clone()));
+        let mut first = Framed::new(client, RespFrameCodec::default());
+        first.send(command(&["set", "a", "1"])).await?;
+        assert_eq!(first.next().await.transpose()?, Some(RESP_OK.clone()));
+
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(server, "b".to_string(), backend.clone()));
+        let mut second = Framed::new(client, RespFrameCodec::default());
+        assert_eq!(
+            second.next().await.transpose()?,
+            Some(SimpleError::new("ERR max number of clients reached").into())
+        );
+        let stats = backend.stats();
+        assert_eq!(stats.connected_clients, 1);
+        assert_eq!(stats.rejected_connections, 1);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_timeout() -> Result<()> {
+        let backend = Backend::new();
+        backend.set_client_timeout(std::time::Duration::from_secs(10));
+        let (client, server) = tokio::io::duplex(1024);
+        let handler = tokio::spawn(connection_handler(server, "a".to_string(), backend));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(command(&["set", "a", "1"])).await?;
+        assert_eq!(framed.next().await.transpose()?, Some(RESP_OK.clone()));
+
+        let start = tokio::time::Instant::now();
+        assert!(framed.next().await.is_none());
+        assert!(start.elapsed() >= std::time::Duration::from_secs(10));
+        handler.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_is_disconnected() -> Result<()> {
+        let backend = Backend::new();
+        backend
+            .config_set("client-output-buffer-limit", "pubsub 4kb 0 0")
+            .unwrap();
+        let (client, server) = tokio::io::duplex(1024);
+        let handler = tokio::spawn(connection_handler(server, "a".to_string(), backend.clone()));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+        framed.send(command(&["subscribe", "news"])).await?;
+        assert!(framed.next().await.is_some());
+
+        // nothing is read meanwhile, the messages queue up until the limit
+        let message = [b'x'; 100];
+        let mut published = 0;
+        while backend.pubsub.publish(b"news", &message) == 1 {
+            published += 1;
+        }
+        let mut received = 0;
+        while let Some(frame) = framed.next().await {
+            frame?;
+            received += 1;
+        }
+        assert!(received < published);
+        handler.await??;
+        assert!(!backend.pubsub.has_subscribers());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_connections() -> Result<()> {
+        let backend = Backend::new();
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(server, "a".to_string(), backend.clone()));
+        let mut idle = Framed::new(client, RespFrameCodec::default());
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::spawn(connection_handler(server, "b".to_string(), backend.clone()));
+        let mut framed = Framed::new(client, RespFrameCodec::default());
+
+        framed.send(command(&["shutdown", "nosave"])).await?;
+        assert!(framed.next().await.is_none());
+        assert!(idle.next().await.is_none());
+        assert_eq!(backend.shutdown_requested().await, ShutdownPolicy::NoSave);
+        assert!(backend.drain_connections().await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trace_sampling() {
+        let backend = Backend::new();
+        let mut session = session();
+        assert!(!backend.sample_trace());
+        backend.set_trace_sample_rate(3);
+        let sampled = (0..9).filter(|_| backend.sample_trace()).count();
+        assert_eq!(sampled, 3);
+
+        // traced commands run like the others
+        backend.set_trace_sample_rate(1);
+        let ret = dispatch(command(&["set", "a", "1"]), &backend, &mut session).await;
+        assert_eq!(ret, RESP_OK.clone());
+        let ret = dispatch(command(&["get", "a"]), &backend, &mut session).await;
+        assert_eq!(ret, BulkString::from("1").into());
+        assert_eq!(backend.stats().commands["get"].calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_keyspace_hits() {
+        let backend = Backend::new();
+        let mut session = session();
+        assert_eq!(backend.stats().keyspace_hit_ratio(), None);
+
+        // writes look nothing up, every key of a read counts, whatever its type
+        dispatch(command(&["hset", "h", "f", "v"]), &backend, &mut session).await;
+        dispatch(command(&["hget", "h", "f"]), &backend, &mut session).await;
+        dispatch(command(&["hget", "nope", "f"]), &backend, &mut session).await;
+        dispatch(
+            command(&["exists", "h", "nope", "x"]),
+            &backend,
+            &mut session,
+        )
+        .await;
+        dispatch(command(&["llen", "h"]), &backend, &mut session).await;
+        let stats = backend.stats();
+        assert_eq!((stats.keyspace_hits, stats.keyspace_misses), (3, 3));
+        assert_eq!(stats.keyspace_hit_ratio(), Some(0.5));
+
+        let info = dispatch(command(&["info", "stats"]), &backend, &mut session).await;
+        let RespFrame::BulkString(info) = info else {
+            panic!("unexpected reply {:?}", info);
+        };
+        let info = String::from_utf8_lossy(&info);
+        assert!(info.contains("keyspace_hits:3\r\nkeyspace_misses:3\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_fast_path() {
+        let backend = Backend::new();
+        let mut session = session();
+
+        let ret = dispatch(command(&["SET", "a", "1"]), &backend, &mut session).await;
+        assert_eq!(ret, RESP_OK.clone());
+        let ret = dispatch(command(&["get", "a"]), &backend, &mut session).await;
+        assert_eq!(ret, BulkString::from("1").into());
+        let ret = dispatch(command(&["get", "b"]), &backend, &mut session).await;
+        assert_eq!(ret, RespFrame::Null(RespNull));
+        let stats = backend.stats();
+        assert_eq!(stats.commands["get"].calls, 2);
+        assert_eq!((stats.keyspace_hits, stats.keyspace_misses), (1, 1));
+        assert!(backend.key_meta(b"a").is_some());
+
+        // the slowlog is on by default and GET still takes the fast path, the only one
+        // that goes through the GET cache
+        assert!(backend.slowlog.is_enabled());
+        backend.config_set("get-cache-size", "16").unwrap();
+        dispatch(command(&["get", "a"]), &backend, &mut session).await;
+        assert_eq!(backend.get_cache_stats().misses, 1);
+
+        // and records its commands in the slowlog with their arguments
+        backend.config_set("slowlog-log-slower-than", "0").unwrap();
+        let ret = dispatch(command(&["SET", "a", "2"]), &backend, &mut session).await;
+        assert_eq!(ret, RESP_OK.clone());
+        dispatch(command(&["get", "a"]), &backend, &mut session).await;
+        assert_eq!(backend.get_cache_stats().misses, 2);
+        let args: Vec<_> = backend
+            .slowlog
+            .get(None)
+            .into_iter()
+            .map(|entry| RespFrame::from(RespArray::new(entry.args)))
+            .collect();
+        assert_eq!(args, [command(&["get", "a"]), command(&["SET", "a", "2"])]);
+
+        // not GET or SET with exactly their arguments, the generic path answers
+        assert!(
+            fast_path(command(&["get", "a", "b"]), &backend, &mut session)
+                .await
+                .is_err()
+        );
+        assert!(fast_path(command(&["echo", "a"]), &backend, &mut session)
+            .await
+            .is_err());
+        let ret = dispatch(command(&["get", "a", "b"]), &backend, &mut session).await;
+        assert!(matches!(ret, RespFrame::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_type() {
+        let backend = Backend::new();
+        let mut session = session();
+        let wrongtype: RespFrame = SimpleError::new(WRONGTYPE_ERROR).into();
+        let type_of = |name: &str| SimpleString::new(name).into();
+        let steps: Vec<(&[&str], RespFrame)> = vec![
+            (&["hset", "h", "f", "v"], RESP_OK.clone()),
+            // through the fast path and the generic one
+            (&["get", "h"], wrongtype.clone()),
+            (&["lpush", "h", "x"], wrongtype.clone()),
+            (&["incr", "h"], wrongtype.clone()),
+            (&["set", "h", "v", "get"], wrongtype.clone()),
+            (&["type", "h"], type_of("hash")),
+            // SET replaces a key of any type, and so do the commands storing in a key
+            (&["set", "h", "v"], RESP_OK.clone()),
+            (&["type", "h"], type_of("string")),
+            (&["hget", "h", "f"], wrongtype.clone()),
+            (&["zadd", "z", "1", "a"], RespFrame::Integer(1)),
+            (&["zunionstore", "h", "1", "z"], RespFrame::Integer(1)),
+            (&["type", "h"], type_of("zset")),
+            (&["set", "s", "v"], RESP_OK.clone()),
+            (&["zunionstore", "z", "1", "s"], wrongtype.clone()),
+            (&["type", "missing"], type_of("none")),
+        ];
+        for (args, expected) in steps {
+            let ret = dispatch(command(args), &backend, &mut session).await;
+            assert_eq!(ret, expected, "{:?}", args);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_only_server() {
+        let backend = Backend::new();
+        backend.set("a".into(), BulkString::from("1").into());
+        backend.config_set("read-only", "yes").unwrap();
+        let mut session = session();
+
+        // through the fast path and the generic one
+        for args in [&["set", "a", "2"][..], &["del", "a"], &["incr", "a"]] {
+            let ret = dispatch(command(args), &backend, &mut session).await;
+            assert_eq!(ret, SimpleError::new(READONLY_SERVER_ERROR).into());
+        }
+        let ret = dispatch(command(&["get", "a"]), &backend, &mut session).await;
+        assert_eq!(ret, BulkString::from("1").into());
+        let ret = dispatch(command(&["publish", "news", "hi"]), &backend, &mut session).await;
+        assert_eq!(ret, RespFrame::Integer(0));
+        let ret = dispatch(
+            command(&["object", "encoding", "a"]),
+            &backend,
+            &mut session,
+        )
+        .await;
+        assert_eq!(ret, BulkString::from("int").into());
+
+        let ret = dispatch(
+            command(&["config", "set", "read-only", "no"]),
+            &backend,
+            &mut session,
+        )
+        .await;
+        assert_eq!(ret, RESP_OK.clone());
+        let ret = dispatch(command(&["set", "a", "2"]), &backend, &mut session).await;
+        assert_eq!(ret, RESP_OK.clone());
+    }
+
+    #[tokio::test]
+    async fn test_cluster_redirect() {
+        let backend = Backend::new();
+        let mut session = session();
+        backend.config_set("port", "7000").unwrap();
+        backend.config_set("cluster-enabled", "yes").unwrap();
+        let slot = crate::key_hash_slot(b"foo");
+        backend
+            .config_set(
+                "cluster-topology",
+                &format!(
+                    "127.0.0.1:7000@0-{} 127.0.0.1:7001@{}>127.0.0.1:7000,{}-16383",
+                    slot - 1,
+                    slot,
+                    slot + 1
+                ),
+            )
+            .unwrap();
+
+        // GET and SET go through the routing too
+        let ret = dispatch(command(&["set", "foo", "1"]), &backend, &mut session).await;
+        assert_eq!(
+            ret,
+            SimpleError::new(format!("MOVED {} 127.0.0.1:7001", slot)).into()
+        );
+        let ret = dispatch(command(&["asking"]), &backend, &mut session).await;
+        assert_eq!(ret, RESP_OK.clone());
+        let ret = dispatch(command(&["set", "foo", "1"]), &backend, &mut session).await;
+        assert_eq!(ret, RESP_OK.clone());
+        // ASKING is for one command only
+        let ret = dispatch(command(&["get", "foo"]), &backend, &mut session).await;
+        assert!(matches!(ret, RespFrame::Error(e) if e.starts_with("MOVED")));
+        let ret = dispatch(command(&["echo", "foo"]), &backend, &mut session).await;
+        assert_eq!(ret, BulkString::from("foo").into());
+    }
+
+    // the fast path is only faster, GET and SET reply and store the same either way
+    #[tokio::test]
+    async fn test_fast_path_matches_generic() {
+        let commands: &[&[&str]] = &[
+            &["get", "a"],
+            &["SET", "a", "1"],
+            &["Get", "a"],
+            &["set", "a", "2"],
+            &["hset", "h", "f", "v"],
+            &["get", "h"],
+            &["set", "h", "3"],
+            &["get", "h"],
+            &["get", "a", "b"],
+            &["set", "a"],
+        ];
+        let (fast, generic) = (Backend::new(), Backend::new());
+        let (mut fast_session, mut generic_session) = (session(), session());
+        for args in commands {
+            let expected = dispatch_generic(command(args), &generic, &mut generic_session).await;
+            let ret = dispatch(command(args), &fast, &mut fast_session).await;
+            assert_eq!(ret, expected, "{:?}", args);
+        }
+        for key in [b"a".as_slice(), b"h"] {
+            assert_eq!(fast.get(key), generic.get(key));
+            assert_eq!(
+                fast.key_meta(key).is_some(),
+                generic.key_meta(key).is_some()
+            );
+        }
+    }
+
+    // GET replies with the stored value itself, however large, not a copy of it
+    #[tokio::test]
+    async fn test_get_shares_large_value() {
+        let backend = Backend::new();
+        let mut session = session();
+        backend.set(
+            "key".into(),
+            BulkString::new(vec![b'x'; 1024 * 1024]).into(),
+        );
+        let stored = backend.get(b"key");
+        let ret = dispatch(command(&["get", "key"]), &backend, &mut session).await;
+        match (ret, stored) {
+            (RespFrame::BulkString(ret), Some(RespFrame::BulkString(stored))) => {
+                assert_eq!(ret.as_ptr(), stored.as_ptr())
+            }
+            (ret, stored) => panic!("unexpected {:?} for {:?}", ret, stored),
         }
     }
 }
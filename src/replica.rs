@@ -0,0 +1,271 @@
+// Replica side of replication: the link to the master set with REPLICAOF. The link
+// connects out, does the handshake (REPLCONF listening-port and capa, PSYNC), loads
+// the snapshot of a full sync and then applies the stream of writes through the same
+// dispatcher as clients, on a session marked as the master's so read-only replicas accept
+// them. It acknowledges the offset it applied every second and on REPLCONF GETACK, and
+// reconnects with PSYNC of the replid and offset it has when the link drops.
+//
+// Every byte of the stream counts in the offset, including what isn't applied: the PINGs
+// a master sends to keep the link alive and its SELECTs of database 0, the only one
+// there is. A SELECT of another database, or a command the replica fails to apply, means
+// the replica no longer follows the master: the link is dropped and synced again.
+
+use crate::{
+    network::dispatch, Backend, BulkString, LinkState, RespArray, RespEncode, RespFrame,
+    RespFrameDecoder, Session,
+};
+use anyhow::{bail, Result};
+use bytes::{Buf, BytesMut};
+use std::time::Duration;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use tracing::{info, warn};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const ACK_INTERVAL: Duration = Duration::from_secs(1);
+
+// replicate host:port, or become a master again with None
+pub(crate) fn replicaof(backend: &Backend, master: Option<(String, u16)>) {
+    let generation = backend.replication.set_master(master.clone());
+    if let Some((host, port)) = master {
+        let task = tokio::spawn(run_link(backend.clone(), host, port, generation));
+        backend
+            .replication
+            .attach_link_task(generation, task.abort_handle());
+    }
+}
+
+async fn run_link(backend: Backend, host: String, port: u16, generation: u64) {
+    while backend.replication.is_current_link(generation) {
+        if let Err(e) = sync(&backend, &host, port, generation).await {
+            warn!("replication link to {}:{} failed: {}", host, port, e);
+        }
+        backend
+            .replication
+            .update_link(generation, |info| info.state = LinkState::Connect);
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn sync(backend: &Backend, host: &str, port: u16, generation: u64) -> Result<()> {
+    let replication = &backend.replication;
+    let mut link = Link {
+        stream: TcpStream::connect((host, port)).await?,
+        buf: BytesMut::new(),
+        decoder: RespFrameDecoder::new(),
+        consumed: 0,
+    };
+    replication.update_link(generation, |info| info.state = LinkState::Connecting);
+
+    link.send(&["replconf", "listening-port", &backend.port().to_string()])
+        .await?;
+    link.expect_ok().await?;
+    link.send(&["replconf", "capa", "psync2"]).await?;
+    link.expect_ok().await?;
+
+    let Some(master) = replication.master() else {
+        return Ok(());
+    };
+    let (replid, offset) = match master.replid {
+        Some(replid) => (replid, master.offset),
+        None => ("?".to_string(), -1),
+    };
+    link.send(&["psync", &replid, &offset.to_string()]).await?;
+    let reply = link.read_line().await?;
+    let mut offset = match reply.split(' ').collect::<Vec<_>>().as_slice() {
+        ["+FULLRESYNC", replid, offset] => {
+            let offset: i64 = offset.parse()?;
+            if offset < 0 {
+                bail!("invalid offset in {}", reply);
+            }
+            replication.update_link(generation, |info| info.state = LinkState::Sync);
+            let len = match link.read_line().await?.strip_prefix('$') {
+                Some(len) => len.parse()?,
+                None => bail!("expected the snapshot after {}", reply),
+            };
+            let snapshot = link.read_exact(len).await?;
+            if !replication.is_current_link(generation) {
+                return Ok(());
+            }
+            backend.restore_snapshot(&snapshot)?;
+            let replid = replid.to_string();
+            replication.update_link(generation, |info| info.replid = Some(replid));
+            offset
+        }
+        // only a replica that has an offset asks to continue
+        ["+CONTINUE", _] | ["+CONTINUE"] if offset < 0 => {
+            bail!("unexpected {} without an offset", reply)
+        }
+        ["+CONTINUE", rest @ ..] => {
+            if let [replid] = rest {
+                let replid = replid.to_string();
+                replication.update_link(generation, |info| info.replid = Some(replid));
+            }
+            offset
+        }
+        _ => bail!("unexpected PSYNC reply: {}", reply),
+    };
+    replication.update_link(generation, |info| {
+        info.state = LinkState::Connected;
+        info.offset = offset;
+    });
+    info!("replicating {}:{} from offset {}", host, port, offset);
+
+    let mut session = Session::default();
+    session.set_master_link(true);
+    let mut ack = tokio::time::interval(ACK_INTERVAL);
+    loop {
+        while let Some((frame, len)) = link.next_frame()? {
+            if !replication.is_current_link(generation) {
+                return Ok(());
+            }
+            match stream_command(&frame)? {
+                // the offset acknowledged doesn't include the GETACK itself
+                StreamCommand::GetAck => {
+                    link.send(&["replconf", "ack", &offset.to_string()]).await?;
+                }
+                StreamCommand::Skip => {}
+                StreamCommand::Apply => {
+                    if let RespFrame::Error(e) = dispatch(frame, backend, &mut session).await {
+                        bail!("failed to apply the command at offset {}: {}", offset, e.0);
+                    }
+                }
+            }
+            offset += len as i64;
+            replication.update_link(generation, |info| info.offset = offset);
+        }
+        tokio::select! {
+            read = link.fill() => read?,
+            _ = ack.tick() => {
+                link.send(&["replconf", "ack", &offset.to_string()]).await?;
+            }
+        }
+    }
+}
+
+// what the replica does with a command of the stream
+#[derive(Debug, PartialEq)]
+enum StreamCommand {
+    GetAck,
+    Skip,
+    Apply,
+}
+
+fn stream_command(frame: &RespFrame) -> Result<StreamCommand> {
+    let RespFrame::Array(args) = frame else {
+        return Ok(StreamCommand::Apply);
+    };
+    let args: Vec<&[u8]> = args
+        .iter()
+        .map(|arg| match arg {
+            RespFrame::BulkString(arg) => arg.as_ref(),
+            _ => b"".as_slice(),
+        })
+        .collect();
+    let Some(name) = args.first() else {
+        return Ok(StreamCommand::Apply);
+    };
+    Ok(match (name.to_ascii_lowercase().as_slice(), &args[1..]) {
+        (b"replconf", [option, _]) if option.eq_ignore_ascii_case(b"getack") => {
+            StreamCommand::GetAck
+        }
+        (b"ping", _) => StreamCommand::Skip,
+        (b"select", [b"0"]) => StreamCommand::Skip,
+        (b"select", [db]) => bail!(
+            "the master selected database {}, only 0 exists",
+            String::from_utf8_lossy(db)
+        ),
+        _ => StreamCommand::Apply,
+    })
+}
+
+struct Link {
+    stream: TcpStream,
+    buf: BytesMut,
+    decoder: RespFrameDecoder,
+    // bytes of the frame being decoded consumed so far
+    consumed: usize,
+}
+
+impl Link {
+    async fn send(&mut self, args: &[&str]) -> Result<()> {
+        let command = RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        );
+        self.stream.write_all(&command.encode()).await?;
+        Ok(())
+    }
+
+    // read more of the stream into the buffer
+    async fn fill(&mut self) -> Result<()> {
+        if self.stream.read_buf(&mut self.buf).await? == 0 {
+            bail!("connection closed by the master");
+        }
+        Ok(())
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        loop {
+            if let Some(end) = self.buf.windows(2).position(|w| w == b"\r\n") {
+                let line = String::from_utf8(self.buf[..end].to_vec())?;
+                self.buf.advance(end + 2);
+                return Ok(line);
+            }
+            self.fill().await?;
+        }
+    }
+
+    async fn expect_ok(&mut self) -> Result<()> {
+        let line = self.read_line().await?;
+        if line.starts_with('-') {
+            bail!("master replied {}", line);
+        }
+        Ok(())
+    }
+
+    async fn read_exact(&mut self, len: usize) -> Result<Vec<u8>> {
+        while self.buf.len() < len {
+            self.fill().await?;
+        }
+        Ok(self.buf.split_to(len).to_vec())
+    }
+
+    // the next complete frame in the buffer and its length in the stream
+    fn next_frame(&mut self) -> Result<Option<(RespFrame, usize)>> {
+        let before = self.buf.len();
+        let frame = self.decoder.decode(&mut self.buf)?;
+        self.consumed += before - self.buf.len();
+        Ok(frame.map(|frame| (frame, std::mem::take(&mut self.consumed))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_command() {
+        let command = |args: &[&str]| {
+            let frame: RespFrame = RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into();
+            stream_command(&frame).map_err(|e| e.to_string())
+        };
+        assert_eq!(
+            command(&["REPLCONF", "GETACK", "*"]),
+            Ok(StreamCommand::GetAck)
+        );
+        assert_eq!(command(&["replconf", "ack", "1"]), Ok(StreamCommand::Apply));
+        assert_eq!(command(&["set", "getack", "*"]), Ok(StreamCommand::Apply));
+        assert_eq!(command(&["PING"]), Ok(StreamCommand::Skip));
+        assert_eq!(command(&["select", "0"]), Ok(StreamCommand::Skip));
+        assert!(command(&["SELECT", "3"]).is_err());
+    }
+}
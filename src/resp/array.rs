@@ -1,65 +1,51 @@
 use std::ops::Deref;
 
-use bytes::{Buf, BytesMut};
+use bytes::BytesMut;
 
 use crate::{RespDecode, RespEncode, RespError, RespFrame};
 
-use super::{calc_total_length, parse_length, BUF_CAP, CRLF_LEN};
+use super::{
+    decode_aggregate, extract_fixed_data, frame_length, parse_length, BUF_CAP, MAX_NESTING_DEPTH,
+};
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct RespArray(pub(crate) Vec<RespFrame>);
 
+// the nil reply of RESP2, e.g. a blocking pop that timed out, unlike an empty array
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct RespNullArray;
+
 // - array: "*<number-of-elements>\r\n<element-1>...<element-n>"
 impl RespEncode for RespArray {
     fn encode(self) -> Vec<u8> {
-        if self.0.is_empty() {
-            b"*-1\r\n".to_vec()
-        } else {
-            let mut buf = Vec::with_capacity(BUF_CAP);
-            buf.extend_from_slice(&format!("*{}\r\n", self.0.len()).into_bytes());
-            for frame in self.0 {
-                buf.extend_from_slice(&frame.encode());
-            }
-            buf
+        let mut buf = Vec::with_capacity(BUF_CAP);
+        buf.extend_from_slice(&format!("*{}\r\n", self.0.len()).into_bytes());
+        for frame in self.0 {
+            buf.extend_from_slice(&frame.encode());
         }
+        buf
     }
 }
 
 // - array: "*<number-of-elements>\r\n<element-1>...<element-n>"
 // - "*2\r\n$3\r\nget\r\n$5\r\nhello\r\n"
-// or null array: "*-1\r\n"
 impl RespDecode for RespArray {
     const PREFIX: &'static str = "*";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        if len < 0 {
-            Ok(RespArray::null())
-        } else {
-            let len = len as usize;
-            let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
-
-            if buf.len() < total_len {
-                return Err(RespError::NotComplete);
-            }
-
-            buf.advance(end + CRLF_LEN);
-
-            let mut frames = Vec::with_capacity(len);
-            for _ in 0..len {
-                frames.push(RespFrame::decode(buf)?);
-            }
-
-            Ok(RespArray::new(frames))
+        // the prefix is checked before anything is consumed
+        parse_length(buf, Self::PREFIX)?;
+        match decode_aggregate(buf, MAX_NESTING_DEPTH)? {
+            RespFrame::Array(frame) => Ok(frame),
+            frame => Err(RespError::InvalidFrameType(format!(
+                "expect: Array, got: {:?}",
+                frame
+            ))),
         }
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        if len < 0 {
-            Ok(4)
-        } else {
-            calc_total_length(buf, end, len as usize, Self::PREFIX)
-        }
+        parse_length(buf, Self::PREFIX)?;
+        frame_length(buf, MAX_NESTING_DEPTH)
     }
 }
 
@@ -67,9 +53,24 @@ impl RespArray {
     pub fn new(s: impl Into<Vec<RespFrame>>) -> Self {
         RespArray(s.into())
     }
+}
 
-    pub fn null() -> Self {
-        RespArray(Vec::new())
+// - null array: "*-1\r\n"
+impl RespEncode for RespNullArray {
+    fn encode(self) -> Vec<u8> {
+        b"*-1\r\n".to_vec()
+    }
+}
+
+impl RespDecode for RespNullArray {
+    const PREFIX: &'static str = "*";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        extract_fixed_data(buf, "*-1\r\n", "NullArray")?;
+        Ok(RespNullArray)
+    }
+
+    fn expect_length(_buf: &[u8]) -> Result<usize, RespError> {
+        Ok(5)
     }
 }
 
@@ -102,23 +103,28 @@ mod tests {
     }
 
     #[test]
-    fn test_null_array() {
-        assert_eq!(RespArray::null(), RespArray(Vec::new()));
+    fn test_null_array_encode() {
+        let frame: RespFrame = RespNullArray.into();
+        assert_eq!(frame.encode(), b"*-1\r\n");
     }
 
     #[test]
-    fn test_null_array_encode() {
-        let frame: RespFrame = RespArray::null().into();
-        assert_eq!(frame.encode(), b"*-1\r\n");
+    fn test_empty_array_encode() {
+        let frame: RespFrame = RespArray::new(vec![]).into();
+        assert_eq!(frame.encode(), b"*0\r\n");
     }
 
     #[test]
     fn test_null_array_decode() -> Result<()> {
         let mut buf = BytesMut::new();
-        buf.extend_from_slice(b"*-1\r\n");
+        buf.extend_from_slice(b"*-1\r\n*0\r\n");
 
-        let frame = RespArray::decode(&mut buf)?;
-        assert_eq!(frame, RespArray::null());
+        let frame = RespNullArray::decode(&mut BytesMut::from(&buf[..]))?;
+        assert_eq!(frame, RespNullArray);
+
+        assert_eq!(RespFrame::decode(&mut buf)?, RespNullArray.into());
+        assert_eq!(RespFrame::decode(&mut buf)?, RespArray::new(vec![]).into());
+        assert!(RespArray::decode(&mut BytesMut::from(&b"*-1\r\n"[..])).is_err());
 
         Ok(())
     }
@@ -1,18 +1,23 @@
 use std::ops::Deref;
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
 use super::{parse_length, CRLF_LEN};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
-pub struct BulkString(pub(crate) Vec<u8>);
+// values up to this size are copied out of the read buffer, larger ones keep the part of
+// it they were read into instead of copying it
+const ZERO_COPY_MIN_LEN: usize = 16 * 1024;
+
+// the data is shared, a clone, e.g. of a value GET replies, doesn't copy it
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BulkString(pub(crate) Bytes);
 
 // - bulk string: "$<length>\r\n<data>\r\n" or null bulk string: "$-1\r\n"
 impl RespEncode for BulkString {
     fn encode(self) -> Vec<u8> {
-        if self.len() == 0 {
+        if self.is_empty() {
             b"$-1\r\n".to_vec()
         } else {
             let mut buf = Vec::with_capacity(self.len() + 16);
@@ -29,6 +34,7 @@ impl RespDecode for BulkString {
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         let (end, len) = parse_length(buf, Self::PREFIX)?;
         if len < 0 {
+            buf.advance(end + CRLF_LEN);
             Ok(BulkString::null())
         } else {
             let len = len as usize;
@@ -39,8 +45,13 @@ impl RespDecode for BulkString {
 
             buf.advance(end + CRLF_LEN);
 
-            let data = buf.split_to(len + CRLF_LEN);
-            Ok(BulkString::new(data[..len].to_vec()))
+            let data = buf.split_to(len);
+            buf.advance(CRLF_LEN);
+            // a small value would keep the whole buffer it was read into alive
+            if len < ZERO_COPY_MIN_LEN {
+                return Ok(BulkString::from(&data[..]));
+            }
+            Ok(BulkString(data.freeze()))
         }
     }
 
@@ -56,7 +67,11 @@ impl RespDecode for BulkString {
 
 impl BulkString {
     pub fn new(s: impl Into<Vec<u8>>) -> Self {
-        BulkString(s.into())
+        BulkString(Bytes::from(s.into()))
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.0
     }
 
     pub fn null() -> Self {
@@ -71,7 +86,7 @@ impl AsRef<[u8]> for BulkString {
 }
 
 impl Deref for BulkString {
-    type Target = Vec<u8>;
+    type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -80,25 +95,43 @@ impl Deref for BulkString {
 
 impl From<&str> for BulkString {
     fn from(s: &str) -> Self {
-        BulkString(s.as_bytes().to_vec())
+        BulkString(Bytes::copy_from_slice(s.as_bytes()))
     }
 }
 
 impl From<String> for BulkString {
     fn from(s: String) -> Self {
-        BulkString(s.into_bytes())
+        BulkString(Bytes::from(s))
+    }
+}
+
+impl From<Vec<u8>> for BulkString {
+    fn from(s: Vec<u8>) -> Self {
+        BulkString(Bytes::from(s))
     }
 }
 
 impl From<&[u8]> for BulkString {
     fn from(s: &[u8]) -> Self {
-        BulkString(s.to_vec())
+        BulkString(Bytes::copy_from_slice(s))
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for BulkString {
     fn from(s: &[u8; N]) -> Self {
-        BulkString(s.to_vec())
+        BulkString(Bytes::copy_from_slice(s))
+    }
+}
+
+impl From<Bytes> for BulkString {
+    fn from(s: Bytes) -> Self {
+        BulkString(s)
+    }
+}
+
+impl From<BulkString> for Vec<u8> {
+    fn from(s: BulkString) -> Self {
+        s.0.into()
     }
 }
 
@@ -140,6 +173,25 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bulk_string_shares_data() -> Result<()> {
+        let value = vec![b'x'; ZERO_COPY_MIN_LEN];
+        let mut buf = BytesMut::new();
+        let header = format!("${}\r\n", value.len());
+        buf.extend_from_slice(header.as_bytes());
+        buf.extend_from_slice(&value);
+        buf.extend_from_slice(b"\r\n");
+        let start = buf.as_ptr() as usize + header.len();
+
+        // a large value stays where it was read, a clone doesn't copy it
+        let frame = BulkString::decode(&mut buf)?;
+        assert_eq!(frame.as_ref(), value.as_slice());
+        assert_eq!(frame.as_ptr() as usize, start);
+        assert_eq!(frame.clone().as_ptr(), frame.as_ptr());
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn test_null_bulk_string() -> Result<()> {
         assert_eq!(BulkString::null(), BulkString::new(b""));
@@ -156,6 +208,13 @@ mod tests {
 
         let frame = BulkString::decode(&mut buf)?;
         assert_eq!(frame, BulkString::null());
+        assert!(buf.is_empty());
+
+        // what follows the null is decoded next
+        buf.extend_from_slice(b"$-1\r\n:1\r\n");
+        assert_eq!(RespFrame::decode(&mut buf)?, BulkString::null().into());
+        assert_eq!(RespFrame::decode(&mut buf)?, RespFrame::Integer(1));
+        assert!(buf.is_empty());
         Ok(())
     }
 }
@@ -0,0 +1,314 @@
+use bytes::{Buf, BytesMut};
+
+use crate::{BulkString, RespDecode, RespError, RespFrame, RespNullArray};
+
+use super::{aggregate, aggregate_elements, aggregate_prefix, parse_length, CRLF, CRLF_LEN};
+
+// bulk strings are accepted up to 512MB, like proto-max-bulk-len of Redis
+pub const MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+pub const MAX_NESTING_DEPTH: usize = 32;
+pub const MAX_AGGREGATE_LEN: usize = 1024 * 1024;
+// a header alone doesn't get to reserve more than this many elements or bytes, the rest
+// is allocated as the data arrives
+const MAX_PREALLOCATED_ELEMENTS: usize = 1024;
+const MAX_PREALLOCATED_BULK: usize = 64 * 1024;
+
+// what a peer may send, checked against the headers before anything is allocated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderLimits {
+    pub max_bulk_len: usize,
+    // arrays, maps, sets and pushes inside each other
+    pub max_nesting_depth: usize,
+    // elements of an array, set or push, pairs of a map
+    pub max_aggregate_len: usize,
+}
+
+impl Default for DecoderLimits {
+    fn default() -> Self {
+        Self {
+            max_bulk_len: MAX_BULK_LEN,
+            max_nesting_depth: MAX_NESTING_DEPTH,
+            max_aggregate_len: MAX_AGGREGATE_LEN,
+        }
+    }
+}
+
+// Decodes frames from a stream as the bytes arrive. RespFrame::decode needs the whole frame
+// in the buffer and scans it again on every read until it is complete, this keeps what it
+// has decoded so far instead: arrays element by element, and the payload of a bulk string
+// is moved out of the read buffer into a buffer of its announced size. A 100MB value is
+// read with the read buffer staying small and without scanning it more than once. Maps,
+// sets and pushes are decoded like arrays, so what is nested in them is checked against
+// the limits the same way.
+//
+// Other frame types are short and are decoded whole.
+#[derive(Debug, Default)]
+pub struct RespFrameDecoder {
+    // the aggregates being decoded, innermost last: their prefix, the elements expected
+    // (twice the pairs of a map) and the elements decoded so far
+    arrays: Vec<(&'static str, usize, Vec<RespFrame>)>,
+    // the payload of a bulk string being read and its length
+    bulk: Option<(Vec<u8>, usize)>,
+    limits: DecoderLimits,
+}
+
+impl RespFrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limits(limits: DecoderLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    // the next frame, Ok(None) when the buffer has been consumed without completing one.
+    // What was consumed is kept for the next call.
+    pub fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        loop {
+            let frame = match self.bulk.take() {
+                Some((mut data, len)) => {
+                    let take = (len - data.len()).min(buf.len());
+                    data.extend_from_slice(&buf[..take]);
+                    buf.advance(take);
+                    if data.len() < len || buf.len() < CRLF_LEN {
+                        self.bulk = Some((data, len));
+                        return Ok(None);
+                    }
+                    if !buf.starts_with(CRLF) {
+                        return Err(RespError::InvalidFrame(
+                            "bulk string is not terminated by CRLF".to_string(),
+                        ));
+                    }
+                    buf.advance(CRLF_LEN);
+                    BulkString::new(data).into()
+                }
+                None => match buf.first().map(|&byte| (byte, aggregate_prefix(byte))) {
+                    None => return Ok(None),
+                    Some((_, Some(prefix))) => {
+                        let Some(count) = header(buf, prefix)? else {
+                            return Ok(None);
+                        };
+                        let len = aggregate_elements(prefix, count)?;
+                        if len > 0 {
+                            self.check_aggregate(count as usize, self.arrays.len() + 1)?;
+                            let elements = len.min(MAX_PREALLOCATED_ELEMENTS);
+                            self.arrays
+                                .push((prefix, len, Vec::with_capacity(elements)));
+                            continue;
+                        }
+                        match count {
+                            ..0 => RespNullArray.into(),
+                            _ => aggregate(prefix, Vec::new()),
+                        }
+                    }
+                    Some((b'$', _)) => match header(buf, BulkString::PREFIX)? {
+                        None => return Ok(None),
+                        Some(len) if len >= 0 => {
+                            let len = len as usize;
+                            if len > self.limits.max_bulk_len {
+                                return Err(RespError::InvalidFrameLength(len as isize));
+                            }
+                            let data = Vec::with_capacity(len.min(MAX_PREALLOCATED_BULK));
+                            self.bulk = Some((data, len));
+                            continue;
+                        }
+                        Some(_) => BulkString::null().into(),
+                    },
+                    Some(_) => match RespFrame::decode(buf) {
+                        Ok(frame) => frame,
+                        Err(RespError::NotComplete) => return Ok(None),
+                        Err(e) => return Err(e),
+                    },
+                },
+            };
+            if let Some(frame) = self.complete(frame) {
+                return Ok(Some(frame));
+            }
+        }
+    }
+
+    fn check_aggregate(&self, len: usize, depth: usize) -> Result<(), RespError> {
+        if len > self.limits.max_aggregate_len {
+            return Err(RespError::InvalidFrameLength(len as isize));
+        }
+        if depth > self.limits.max_nesting_depth {
+            return Err(RespError::InvalidFrame(format!(
+                "nested deeper than {} aggregates",
+                self.limits.max_nesting_depth
+            )));
+        }
+        Ok(())
+    }
+
+    // add a decoded frame to the array it is in, returns the outermost frame it completes
+    fn complete(&mut self, mut frame: RespFrame) -> Option<RespFrame> {
+        while let Some((_, len, elements)) = self.arrays.last_mut() {
+            elements.push(frame);
+            if elements.len() < *len {
+                return None;
+            }
+            let (prefix, _, elements) = self.arrays.pop()?;
+            frame = aggregate(prefix, elements);
+        }
+        Some(frame)
+    }
+}
+
+// consume the length header of an array or bulk string, None when it isn't complete yet
+fn header(buf: &mut BytesMut, prefix: &str) -> Result<Option<isize>, RespError> {
+    match parse_length(buf, prefix) {
+        Ok((end, len)) => {
+            buf.advance(end + CRLF_LEN);
+            Ok(Some(len))
+        }
+        Err(RespError::NotComplete) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespArray, RespEncode, RespMap, SimpleString};
+    use anyhow::Result;
+
+    #[test]
+    fn test_decoder_byte_by_byte() -> Result<()> {
+        let mut map = RespMap::new();
+        map.insert("k", BulkString::from("v").into());
+        let frames: Vec<RespFrame> = vec![
+            RespArray::new(vec![
+                BulkString::from("set").into(),
+                BulkString::from("key").into(),
+                BulkString::from("value").into(),
+            ])
+            .into(),
+            RespArray::new(vec![
+                RespFrame::Integer(1),
+                RespArray::new(vec![BulkString::null().into()]).into(),
+                SimpleString::new("OK").into(),
+            ])
+            .into(),
+            RespNullArray.into(),
+            RespArray::new(vec![]).into(),
+            map.into(),
+        ];
+        let encoded: Vec<u8> = frames.iter().flat_map(|f| f.clone().encode()).collect();
+
+        let mut decoder = RespFrameDecoder::new();
+        let mut buf = BytesMut::new();
+        let mut decoded = Vec::new();
+        for byte in encoded {
+            buf.extend_from_slice(&[byte]);
+            while let Some(frame) = decoder.decode(&mut buf)? {
+                decoded.push(frame);
+            }
+        }
+        assert_eq!(decoded, frames);
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_large_bulk_string() -> Result<()> {
+        const CHUNK: usize = 4096;
+        let value = vec![b'x'; 10 * 1024 * 1024];
+        let encoded = RespFrame::from(RespArray::new(vec![
+            BulkString::from("set").into(),
+            BulkString::from("key").into(),
+            BulkString::new(value.clone()).into(),
+        ]))
+        .encode();
+
+        let mut decoder = RespFrameDecoder::new();
+        let mut buf = BytesMut::with_capacity(CHUNK);
+        let mut decoded = None;
+        for chunk in encoded.chunks(CHUNK) {
+            buf.extend_from_slice(chunk);
+            if let Some(frame) = decoder.decode(&mut buf)? {
+                decoded = Some(frame);
+            }
+            // the payload doesn't pile up in the read buffer
+            assert!(buf.len() < CHUNK);
+        }
+        assert_eq!(
+            decoded,
+            Some(
+                RespArray::new(vec![
+                    BulkString::from("set").into(),
+                    BulkString::from("key").into(),
+                    BulkString::new(value).into(),
+                ])
+                .into()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_limits() {
+        let limits = DecoderLimits {
+            max_bulk_len: 8,
+            max_nesting_depth: 2,
+            max_aggregate_len: 3,
+        };
+        let decode = |data: &[u8]| {
+            let mut decoder = RespFrameDecoder::with_limits(limits);
+            decoder.decode(&mut BytesMut::from(data))
+        };
+        assert!(decode(b"$8\r\n12345678\r\n").is_ok());
+        assert_eq!(
+            decode(b"$9\r\n").unwrap_err(),
+            RespError::InvalidFrameLength(9)
+        );
+        // rejected from the header, before the elements arrive
+        assert_eq!(
+            decode(b"*1000000000\r\n").unwrap_err(),
+            RespError::InvalidFrameLength(1000000000)
+        );
+        assert_eq!(
+            decode(b"%4\r\n").unwrap_err(),
+            RespError::InvalidFrameLength(4)
+        );
+        assert!(decode(b"*1\r\n*1\r\n:1\r\n").is_ok());
+        assert!(decode(b"*1\r\n*1\r\n*1\r\n").is_err());
+        assert!(decode(b"*1\r\n*1\r\n~1\r\n").is_err());
+        assert!(decode(b"*1\r\n~1\r\n:1\r\n").is_ok());
+        assert!(decode(b"*1\r\n~1\r\n*1\r\n:1\r\n").is_err());
+        assert!(decode(b"%1\r\n+k\r\n~1\r\n*1\r\n:1\r\n").is_err());
+        // what is nested in maps, sets and pushes is checked as it arrives too
+        assert_eq!(
+            decode(b"%1\r\n+k\r\n$1000000\r\n").unwrap_err(),
+            RespError::InvalidFrameLength(1000000)
+        );
+        assert_eq!(
+            decode(b"~1\r\n*100\r\n").unwrap_err(),
+            RespError::InvalidFrameLength(100)
+        );
+        assert_eq!(
+            decode(b">2\r\n+a\r\n%100\r\n").unwrap_err(),
+            RespError::InvalidFrameLength(100)
+        );
+
+        // the payload of a large bulk string isn't allocated from its header
+        let mut decoder = RespFrameDecoder::new();
+        let mut buf = BytesMut::from(&b"$536870912\r\n"[..]);
+        assert_eq!(decoder.decode(&mut buf), Ok(None));
+        let (data, _) = decoder.bulk.as_ref().unwrap();
+        assert!(data.capacity() <= MAX_PREALLOCATED_BULK);
+    }
+
+    #[test]
+    fn test_decoder_errors() {
+        let mut decoder = RespFrameDecoder::new();
+        let mut buf = BytesMut::from(&b"$3\r\nabcde"[..]);
+        assert!(decoder.decode(&mut buf).is_err());
+
+        let mut decoder = RespFrameDecoder::new();
+        let mut buf = BytesMut::from(format!("${}\r\n", MAX_BULK_LEN + 1).as_bytes());
+        assert!(decoder.decode(&mut buf).is_err());
+    }
+}
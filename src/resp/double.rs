@@ -1,4 +1,5 @@
 use std::{
+    cmp::Ordering,
     hash::{Hash, Hasher},
     ops::Deref,
 };
@@ -9,12 +10,27 @@ use crate::{RespDecode, RespEncode, RespError};
 
 use super::{extract_simple_frame_data, CRLF_LEN};
 
+// Doubles compare exactly, in the total order of f64::total_cmp, so that frames holding them
+// can be set members and map keys: -0.0 is 0.0 and every NaN is the same value, sorted after
+// +inf. Equal values hash the same.
 #[derive(Debug, Clone)]
 pub struct ApproximateFloat(pub f64);
 
+impl ApproximateFloat {
+    fn canonical(&self) -> f64 {
+        if self.0.is_nan() {
+            f64::NAN
+        } else if self.0 == 0.0 {
+            0.0
+        } else {
+            self.0
+        }
+    }
+}
+
 impl PartialEq for ApproximateFloat {
     fn eq(&self, other: &Self) -> bool {
-        (self.0 - other.0).abs() < 1e-18 // Change precision as needed
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -22,14 +38,19 @@ impl Eq for ApproximateFloat {}
 
 impl Hash for ApproximateFloat {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        // Convert to a fixed precision before hashing to maintain consistency
-        ((self.0 * 1e18).round() as i64).hash(state)
+        self.canonical().to_bits().hash(state)
     }
 }
 
 impl PartialOrd for ApproximateFloat {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.0.partial_cmp(&other.0)
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ApproximateFloat {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.canonical().total_cmp(&other.canonical())
     }
 }
 
@@ -41,6 +62,18 @@ impl Deref for ApproximateFloat {
     }
 }
 
+impl From<f64> for ApproximateFloat {
+    fn from(f: f64) -> Self {
+        ApproximateFloat(f)
+    }
+}
+
+impl From<ApproximateFloat> for f64 {
+    fn from(f: ApproximateFloat) -> Self {
+        f.0
+    }
+}
+
 // - double: ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
 impl RespEncode for ApproximateFloat {
     fn encode(self) -> Vec<u8> {
@@ -93,6 +126,10 @@ mod tests {
 
         let frame: RespFrame = ApproximateFloat(-1.23456e-9).into();
         assert_eq!(&frame.encode(), b",-1.23456e-9\r\n");
+
+        let frame: RespFrame = 1.5.into();
+        assert_eq!(frame, RespFrame::Double(ApproximateFloat(1.5)));
+        assert_eq!(f64::from(ApproximateFloat::from(-2.5)), -2.5);
     }
 
     #[test]
@@ -109,4 +146,31 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_double_eq_hash() {
+        let hash = |f: f64| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            ApproximateFloat(f).hash(&mut hasher);
+            hasher.finish()
+        };
+        let nan = ApproximateFloat(f64::NAN);
+        assert_eq!(nan, nan.clone());
+        assert_eq!(nan, ApproximateFloat(-f64::NAN));
+        assert_eq!(hash(f64::NAN), hash(-f64::NAN));
+        assert_eq!(ApproximateFloat(-0.0), ApproximateFloat(0.0));
+        assert_eq!(hash(-0.0), hash(0.0));
+        // large values used to hash the same, and close values used to be equal
+        assert_ne!(hash(100.0), hash(200.0));
+        assert_ne!(ApproximateFloat(1e-20), ApproximateFloat(2e-20));
+
+        let mut values: Vec<_> = [f64::NAN, 1.0, f64::INFINITY, -0.0, f64::NEG_INFINITY]
+            .into_iter()
+            .map(ApproximateFloat)
+            .collect();
+        values.sort();
+        let sorted: Vec<f64> = values.iter().map(|f| f.0).collect();
+        assert_eq!(sorted[..4], [f64::NEG_INFINITY, 0.0, 1.0, f64::INFINITY]);
+        assert!(sorted[4].is_nan());
+    }
 }
@@ -1,23 +1,27 @@
 use crate::{
-    ApproximateFloat, BulkString, RespArray, RespDecode, RespError, RespMap, RespNull, RespSet,
-    SimpleError, SimpleString,
+    ApproximateFloat, BulkString, RespArray, RespDecode, RespError, RespMap, RespNull,
+    RespNullArray, RespPush, RespSet, SimpleError, SimpleString,
 };
 use bytes::BytesMut;
 use enum_dispatch::enum_dispatch;
 
+use super::{decode_aggregate, frame_length, MAX_NESTING_DEPTH};
+
 #[enum_dispatch(RespEncode)]
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub enum RespFrame {
     SimpleString(SimpleString),
     Error(SimpleError),
     Integer(i64),
     BulkString(BulkString),
     Array(RespArray),
+    NullArray(RespNullArray),
     Null(RespNull),
     Boolean(bool),
     Double(ApproximateFloat),
     Map(RespMap),
     Set(RespSet),
+    Push(RespPush),
 }
 
 impl RespDecode for RespFrame {
@@ -41,10 +45,6 @@ impl RespDecode for RespFrame {
                 let frame = BulkString::decode(buf)?;
                 Ok(frame.into())
             }
-            Some(b'*') => {
-                let frame = RespArray::decode(buf)?;
-                Ok(frame.into())
-            }
             Some(b'_') => {
                 let frame = RespNull::decode(buf)?;
                 Ok(frame.into())
@@ -57,14 +57,8 @@ impl RespDecode for RespFrame {
                 let frame = ApproximateFloat::decode(buf)?;
                 Ok(frame.into())
             }
-            Some(b'%') => {
-                let frame = RespMap::decode(buf)?;
-                Ok(frame.into())
-            }
-            Some(b'~') => {
-                let frame = RespSet::decode(buf)?;
-                Ok(frame.into())
-            }
+            // aggregates are decoded with everything nested in them
+            Some(b'*' | b'%' | b'~' | b'>') => decode_aggregate(buf, MAX_NESTING_DEPTH),
             None => Err(RespError::NotComplete),
             _ => Err(RespError::InvalidFrameType(format!(
                 "expect_length: unknown frame type: {:?}",
@@ -76,9 +70,7 @@ impl RespDecode for RespFrame {
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
         let mut iter = buf.iter().peekable();
         match iter.peek() {
-            Some(b'*') => RespArray::expect_length(buf),
-            Some(b'~') => RespSet::expect_length(buf),
-            Some(b'%') => RespMap::expect_length(buf),
+            Some(b'*' | b'%' | b'~' | b'>') => frame_length(buf, MAX_NESTING_DEPTH),
             Some(b'$') => BulkString::expect_length(buf),
             Some(b':') => i64::expect_length(buf),
             Some(b'+') => SimpleString::expect_length(buf),
@@ -86,30 +78,94 @@ impl RespDecode for RespFrame {
             Some(b'#') => bool::expect_length(buf),
             Some(b',') => ApproximateFloat::expect_length(buf),
             Some(b'_') => RespNull::expect_length(buf),
-            _ => Err(RespError::NotComplete),
+            None => Err(RespError::NotComplete),
+            _ => Err(RespError::InvalidFrameType(format!(
+                "expect_length: unknown frame type: {:?}",
+                buf
+            ))),
         }
     }
 }
 
+impl RespFrame {
+    // the frame as a RESP2 client reads it, by the compatibility rules of RESP3: maps are
+    // flat arrays of key/value, sets and pushes arrays, doubles bulk strings, booleans the
+    // integers 1 and 0, and null the null bulk string
+    pub fn into_resp2(self) -> RespFrame {
+        match self {
+            RespFrame::Array(array) => RespArray::new(into_resp2(array.0)).into(),
+            RespFrame::Set(set) => RespArray::new(into_resp2(set.0)).into(),
+            RespFrame::Push(push) => RespArray::new(into_resp2(push.0)).into(),
+            RespFrame::Map(map) => RespArray::new(
+                map.into_iter()
+                    .flat_map(|(key, value)| [key.into_resp2(), value.into_resp2()])
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Double(double) => BulkString::from(double.0.to_string()).into(),
+            RespFrame::Boolean(b) => RespFrame::Integer(b as i64),
+            RespFrame::Null(_) => BulkString::null().into(),
+            frame => frame,
+        }
+    }
+}
+
+fn into_resp2(frames: Vec<RespFrame>) -> Vec<RespFrame> {
+    frames.into_iter().map(RespFrame::into_resp2).collect()
+}
+
 impl From<&str> for RespFrame {
     fn from(s: &str) -> Self {
         SimpleString(s.to_string()).into()
     }
 }
 
+// a double, so code building frames from f64 doesn't need to know about ApproximateFloat
+impl From<f64> for RespFrame {
+    fn from(f: f64) -> Self {
+        ApproximateFloat::from(f).into()
+    }
+}
+
 impl From<&[u8]> for RespFrame {
     fn from(s: &[u8]) -> Self {
-        BulkString(s.to_vec()).into()
+        BulkString::from(s).into()
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for RespFrame {
     fn from(s: &[u8; N]) -> Self {
-        BulkString(s.to_vec()).into()
+        BulkString::from(s).into()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // TODO: Add tests
+    use super::*;
+
+    #[test]
+    fn test_into_resp2() {
+        let mut map = RespMap::new();
+        map.insert("score", 1.5.into());
+        map.insert(
+            "members",
+            RespSet::new(vec![true.into(), RespNull.into()]).into(),
+        );
+        let frame: RespFrame = RespArray::new(vec![map.into(), 3.0.into()]).into();
+        assert_eq!(
+            frame.into_resp2(),
+            RespArray::new(vec![
+                RespArray::new(vec![
+                    SimpleString::new("score").into(),
+                    BulkString::from("1.5").into(),
+                    SimpleString::new("members").into(),
+                    RespArray::new(vec![RespFrame::Integer(1), BulkString::null().into()]).into(),
+                ])
+                .into(),
+                BulkString::from("3").into(),
+            ])
+            .into()
+        );
+        assert_eq!(RespFrame::Integer(7).into_resp2(), RespFrame::Integer(7));
+    }
 }
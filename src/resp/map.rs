@@ -1,24 +1,29 @@
-use bytes::{Buf, BytesMut};
+use bytes::BytesMut;
 
-use crate::{RespDecode, RespEncode, RespError, RespFrame, SimpleString};
+use crate::{RespDecode, RespEncode, RespError, RespFrame};
 use std::{
-    collections::BTreeMap,
-    ops::{Deref, DerefMut},
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    ops::Index,
 };
 
-use super::{calc_total_length, parse_length, BUF_CAP, CRLF_LEN};
+use super::{decode_aggregate, frame_length, parse_length, BUF_CAP, MAX_NESTING_DEPTH};
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Hash)]
-pub struct RespMap(pub(crate) BTreeMap<String, RespFrame>);
+// entries are kept in the order they were inserted, like the maps Redis replies with, with an
+// index of the keys for lookups. Any frame can be a key as RESP3 allows.
+#[derive(Debug, Clone)]
+pub struct RespMap {
+    entries: Vec<(RespFrame, RespFrame)>,
+    index: HashMap<RespFrame, usize>,
+}
 
 // - map: "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
-// we only support string key which encode to SimpleString
 impl RespEncode for RespMap {
     fn encode(self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(BUF_CAP);
         buf.extend_from_slice(&format!("%{}\r\n", self.len()).into_bytes());
-        for (key, value) in self.0 {
-            buf.extend_from_slice(&SimpleString::new(key).encode());
+        for (key, value) in self.entries {
+            buf.extend_from_slice(&key.encode());
             buf.extend_from_slice(&value.encode());
         }
         buf
@@ -29,36 +34,80 @@ impl RespEncode for RespMap {
 impl RespDecode for RespMap {
     const PREFIX: &'static str = "%";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        let len = len as usize;
-        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
+        // the prefix is checked before anything is consumed
+        parse_length(buf, Self::PREFIX)?;
+        match decode_aggregate(buf, MAX_NESTING_DEPTH)? {
+            RespFrame::Map(frame) => Ok(frame),
+            frame => Err(RespError::InvalidFrameType(format!(
+                "expect: Map, got: {:?}",
+                frame
+            ))),
+        }
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        parse_length(buf, Self::PREFIX)?;
+        frame_length(buf, MAX_NESTING_DEPTH)
+    }
+}
 
-        if buf.len() < total_len {
-            return Err(RespError::NotComplete);
+impl RespMap {
+    pub fn new() -> Self {
+        RespMap {
+            entries: Vec::new(),
+            index: HashMap::new(),
         }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
 
-        buf.advance(end + CRLF_LEN);
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
 
-        let mut frames = RespMap::new();
-        for _ in 0..len {
-            let key = SimpleString::decode(buf)?;
-            let value = RespFrame::decode(buf)?;
-            frames.insert(key.0, value);
+    // a key that is already there keeps its position and gets the new value, the old one is
+    // returned
+    pub fn insert(&mut self, key: impl Into<RespFrame>, value: RespFrame) -> Option<RespFrame> {
+        let key = key.into();
+        match self.index.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
         }
+    }
 
-        Ok(frames)
+    pub fn get(&self, key: &RespFrame) -> Option<&RespFrame> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
     }
 
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        let len = len as usize;
-        calc_total_length(buf, end, len, Self::PREFIX)
+    pub fn contains_key(&self, key: &RespFrame) -> bool {
+        self.index.contains_key(key)
     }
-}
 
-impl RespMap {
-    pub fn new() -> Self {
-        RespMap(BTreeMap::new())
+    // the entries after it move up, so the order of the others is kept
+    pub fn remove(&mut self, key: &RespFrame) -> Option<RespFrame> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (key, _) in &self.entries[i..] {
+            if let Some(position) = self.index.get_mut(key) {
+                *position -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    // the entry at a position in insertion order
+    pub fn get_index(&self, index: usize) -> Option<(&RespFrame, &RespFrame)> {
+        self.entries.get(index).map(|(key, value)| (key, value))
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&RespFrame, &RespFrame)> {
+        self.entries.iter().map(|(key, value)| (key, value))
     }
 }
 
@@ -68,39 +117,77 @@ impl Default for RespMap {
     }
 }
 
-impl Deref for RespMap {
-    type Target = BTreeMap<String, RespFrame>;
+// the index follows from the entries, two maps are the same entries in the same order
+impl PartialEq for RespMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl Eq for RespMap {}
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+impl PartialOrd for RespMap {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-impl DerefMut for RespMap {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl Ord for RespMap {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.entries.cmp(&other.entries)
+    }
+}
+
+impl Hash for RespMap {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.entries.hash(state);
+    }
+}
+
+impl<K: Into<RespFrame>> FromIterator<(K, RespFrame)> for RespMap {
+    fn from_iter<I: IntoIterator<Item = (K, RespFrame)>>(iter: I) -> Self {
+        let mut map = RespMap::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl IntoIterator for RespMap {
+    type Item = (RespFrame, RespFrame);
+    type IntoIter = std::vec::IntoIter<(RespFrame, RespFrame)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+// map["name"] for the simple string keys of the maps replied to commands
+impl Index<&str> for RespMap {
+    type Output = RespFrame;
+
+    fn index(&self, key: &str) -> &Self::Output {
+        self.get(&key.into()).expect("key not in map")
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{ApproximateFloat, BulkString};
+    use crate::{ApproximateFloat, BulkString, RespArray, SimpleString};
     use anyhow::Result;
 
     #[test]
     fn test_map_encode() {
         let mut map = RespMap::new();
-        map.insert(
-            "hello".to_string(),
-            BulkString::new("world".to_string()).into(),
-        );
-        map.insert("foo".to_string(), ApproximateFloat(-123456.789).into());
+        map.insert("hello", BulkString::new("world".to_string()).into());
+        map.insert("foo", ApproximateFloat(-123456.789).into());
 
         let frame: RespFrame = map.into();
         assert_eq!(
             &frame.encode(),
-            b"%2\r\n+foo\r\n,-123456.789\r\n+hello\r\n$5\r\nworld\r\n"
+            b"%2\r\n+hello\r\n$5\r\nworld\r\n+foo\r\n,-123456.789\r\n"
         );
     }
 
@@ -111,13 +198,61 @@ mod tests {
 
         let frame = RespMap::decode(&mut buf)?;
         let mut map = RespMap::new();
-        map.insert(
-            "hello".to_string(),
-            BulkString::new(b"world".to_vec()).into(),
-        );
-        map.insert("foo".to_string(), BulkString::new(b"bar".to_vec()).into());
+        map.insert("hello", BulkString::new(b"world".to_vec()).into());
+        map.insert("foo", BulkString::new(b"bar".to_vec()).into());
         assert_eq!(frame, map);
 
         Ok(())
     }
+
+    #[test]
+    fn test_map_frame_keys() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"%3\r\n:2\r\n+two\r\n$3\r\none\r\n:1\r\n*1\r\n:0\r\n#t\r\n");
+
+        let map = RespMap::decode(&mut buf)?;
+        let entries: Vec<_> = map.clone().into_iter().collect();
+        assert_eq!(
+            entries,
+            vec![
+                (RespFrame::Integer(2), SimpleString::new("two").into()),
+                (BulkString::from("one").into(), RespFrame::Integer(1)),
+                (
+                    RespArray::new(vec![RespFrame::Integer(0)]).into(),
+                    true.into()
+                ),
+            ]
+        );
+        assert_eq!(map.get(&RespFrame::Integer(2)), Some(&"two".into()));
+        assert_eq!(map.get(&"one".into()), None);
+        assert_eq!(
+            RespFrame::from(map).encode(),
+            b"%3\r\n:+2\r\n+two\r\n$3\r\none\r\n:+1\r\n*1\r\n:+0\r\n#t\r\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_insertion_order() {
+        let mut map: RespMap = ["c", "a", "b"]
+            .into_iter()
+            .map(|key| (key, RespFrame::Integer(0)))
+            .collect();
+        assert_eq!(
+            map.insert("a", RespFrame::Integer(1)),
+            Some(RespFrame::Integer(0))
+        );
+        assert_eq!(map.remove(&"c".into()), Some(RespFrame::Integer(0)));
+        map.insert("c", RespFrame::Integer(2));
+        let entries: Vec<_> = map.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("a".into(), RespFrame::Integer(1)),
+                ("b".into(), RespFrame::Integer(0)),
+                ("c".into(), RespFrame::Integer(2)),
+            ]
+        );
+        assert_eq!(map["b"], RespFrame::Integer(0));
+    }
 }
@@ -1,16 +1,19 @@
 mod array;
 mod bool;
 mod bulk_string;
+mod decoder;
 mod double;
 mod frame;
 mod integer;
 mod map;
 mod null;
+mod push;
 mod set;
 mod simple_error;
 mod simple_string;
 
 use bytes::{Buf, BytesMut};
+use decoder::MAX_NESTING_DEPTH;
 use enum_dispatch::enum_dispatch;
 use thiserror::Error;
 
@@ -19,8 +22,16 @@ const CRLF: &[u8] = b"\r\n";
 const CRLF_LEN: usize = CRLF.len();
 
 pub use self::{
-    array::RespArray, bulk_string::BulkString, double::ApproximateFloat, frame::RespFrame,
-    map::RespMap, null::RespNull, set::RespSet, simple_error::SimpleError,
+    array::{RespArray, RespNullArray},
+    bulk_string::BulkString,
+    decoder::{DecoderLimits, RespFrameDecoder},
+    double::ApproximateFloat,
+    frame::RespFrame,
+    map::RespMap,
+    null::RespNull,
+    push::RespPush,
+    set::RespSet,
+    simple_error::SimpleError,
     simple_string::SimpleString,
 };
 
@@ -113,34 +124,134 @@ fn parse_length(buf: &[u8], prefix: &str) -> Result<(usize, isize), RespError> {
     Ok((end, s.parse()?))
 }
 
-fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result<usize, RespError> {
-    let mut total = end + CRLF_LEN;
-    let mut data = &buf[total..];
-    match prefix {
-        "*" | "~" => {
-            // find nth CRLF in the buffer, for array and set, we need to find 1 CRLF for each element
-            for _ in 0..len {
+// the aggregate types and their prefix, a map's elements alternate a key and a value
+fn aggregate_prefix(byte: u8) -> Option<&'static str> {
+    match byte {
+        b'*' => Some(RespArray::PREFIX),
+        b'~' => Some(RespSet::PREFIX),
+        b'>' => Some(RespPush::PREFIX),
+        b'%' => Some(RespMap::PREFIX),
+        _ => None,
+    }
+}
+
+// elements of an aggregate with the given header count, twice the count for a map
+fn aggregate_elements(prefix: &str, count: isize) -> Result<usize, RespError> {
+    match (prefix, count) {
+        // the null array
+        ("*", ..0) => Ok(0),
+        (_, ..0) => Err(RespError::InvalidFrameLength(count)),
+        ("%", _) => Ok(count as usize * 2),
+        _ => Ok(count as usize),
+    }
+}
+
+fn check_depth(depth: usize, max_depth: usize) -> Result<(), RespError> {
+    if depth > max_depth {
+        return Err(RespError::InvalidFrame(format!(
+            "nested deeper than {} aggregates",
+            max_depth
+        )));
+    }
+    Ok(())
+}
+
+// length of the complete frame at the start of the buffer. Aggregates are walked with a stack
+// of the elements each still expects instead of recursing into them, so a deeply nested frame
+// can't overflow the stack; nesting beyond max_depth is an error.
+fn frame_length(buf: &[u8], max_depth: usize) -> Result<usize, RespError> {
+    // elements still expected by each open aggregate, innermost last
+    let mut open: Vec<usize> = Vec::new();
+    let mut total = 0;
+    loop {
+        let data = &buf[total..];
+        match data.first().copied().and_then(aggregate_prefix) {
+            Some(prefix) => {
+                let (end, count) = parse_length(data, prefix)?;
+                total += end + CRLF_LEN;
+                let elements = aggregate_elements(prefix, count)?;
+                if elements > 0 {
+                    check_depth(open.len() + 1, max_depth)?;
+                    open.push(elements);
+                    continue;
+                }
+            }
+            None => {
                 let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
+                if data.len() < len {
+                    return Err(RespError::NotComplete);
+                }
                 total += len;
             }
-            Ok(total)
         }
-        "%" => {
-            // find nth CRLF in the buffer. For map, we need to find 2 CRLF for each key-value pair
-            for _ in 0..len {
-                let len = SimpleString::expect_length(data)?;
+        // an element is complete, and with it any aggregate it was the last element of
+        loop {
+            let Some(left) = open.last_mut() else {
+                return Ok(total);
+            };
+            *left -= 1;
+            if *left > 0 {
+                break;
+            }
+            open.pop();
+        }
+    }
+}
 
-                data = &data[len..];
-                total += len;
+// decode the aggregate at the start of the buffer once it is complete, filling the aggregates
+// it contains from a stack rather than by recursion like frame_length
+fn decode_aggregate(buf: &mut BytesMut, max_depth: usize) -> Result<RespFrame, RespError> {
+    frame_length(buf, max_depth)?;
 
-                let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
-                total += len;
+    // open aggregates, innermost last: prefix, elements expected and elements decoded
+    let mut open: Vec<(&str, usize, Vec<RespFrame>)> = Vec::new();
+    loop {
+        let mut frame = match buf.first().copied().and_then(aggregate_prefix) {
+            Some(prefix) => {
+                let (end, count) = parse_length(buf, prefix)?;
+                buf.advance(end + CRLF_LEN);
+                let len = aggregate_elements(prefix, count)?;
+                if len > 0 {
+                    open.push((prefix, len, Vec::with_capacity(len)));
+                    continue;
+                }
+                if count < 0 {
+                    RespNullArray.into()
+                } else {
+                    aggregate(prefix, Vec::new())
+                }
+            }
+            None => RespFrame::decode(buf)?,
+        };
+        loop {
+            let Some((_, len, elements)) = open.last_mut() else {
+                return Ok(frame);
+            };
+            elements.push(frame);
+            if elements.len() < *len {
+                break;
+            }
+            let Some((prefix, _, elements)) = open.pop() else {
+                unreachable!()
+            };
+            frame = aggregate(prefix, elements);
+        }
+    }
+}
+
+fn aggregate(prefix: &str, elements: Vec<RespFrame>) -> RespFrame {
+    match prefix {
+        RespArray::PREFIX => RespArray::new(elements).into(),
+        RespSet::PREFIX => RespSet::new(elements).into(),
+        RespPush::PREFIX => RespPush::new(elements).into(),
+        _ => {
+            let mut map = RespMap::new();
+            let mut elements = elements.into_iter();
+            while let (Some(key), Some(value)) = (elements.next(), elements.next()) {
+                map.insert(key, value);
             }
-            Ok(total)
+            map.into()
         }
-        _ => Ok(len + CRLF_LEN),
     }
 }
 
@@ -152,17 +263,56 @@ mod tests {
     #[test]
     fn test_calc_array_length() -> Result<()> {
         let buf = b"*2\r\n$3\r\nset\r\n$5\r\nhello\r\n";
-        let (end, len) = parse_length(buf, "*")?;
-        let len = len as usize;
-        let total_len = calc_total_length(buf, end, len, "*")?;
-        assert_eq!(total_len, buf.len());
+        assert_eq!(frame_length(buf, MAX_NESTING_DEPTH)?, buf.len());
 
         let buf = b"*2\r\n$3\r\nset\r\n";
-        let (end, len) = parse_length(buf, "*")?;
-        let len = len as usize;
-        let ret = calc_total_length(buf, end, len, "*");
+        let ret = frame_length(buf, MAX_NESTING_DEPTH);
         assert_eq!(ret.unwrap_err(), RespError::NotComplete);
 
+        let buf = b"*2\r\n$3\r\nset\r\n$5\r\nhel";
+        let ret = frame_length(buf, MAX_NESTING_DEPTH);
+        assert_eq!(ret.unwrap_err(), RespError::NotComplete);
+
+        let buf = b"%1\r\n+k\r\n*2\r\n~1\r\n:1\r\n*0\r\n:2\r\n";
+        assert_eq!(frame_length(buf, MAX_NESTING_DEPTH)?, buf.len() - 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nesting_depth() -> Result<()> {
+        let nested = |depth: usize| {
+            let mut buf = BytesMut::from("*1\r\n".repeat(depth).as_bytes());
+            buf.extend_from_slice(b":1\r\n");
+            buf
+        };
+        let mut buf = nested(MAX_NESTING_DEPTH);
+        let mut frame = RespFrame::decode(&mut buf)?;
+        assert!(buf.is_empty());
+        for _ in 0..MAX_NESTING_DEPTH {
+            let RespFrame::Array(mut array) = frame else {
+                panic!("expected an array");
+            };
+            frame = array.0.remove(0);
+        }
+        assert_eq!(frame, RespFrame::Integer(1));
+
+        assert!(matches!(
+            RespFrame::decode(&mut nested(MAX_NESTING_DEPTH + 1)),
+            Err(RespError::InvalidFrame(_))
+        ));
+        // deep enough to overflow the stack when decoded recursively, and rejected before the
+        // end of the frame arrives
+        let mut buf = BytesMut::from("*1\r\n".repeat(1_000_000).as_bytes());
+        assert!(matches!(
+            RespFrame::decode(&mut buf),
+            Err(RespError::InvalidFrame(_))
+        ));
+        let mut buf = BytesMut::from("%1\r\n:1\r\n".repeat(1_000_000).as_bytes());
+        assert!(matches!(
+            RespFrame::decode(&mut buf),
+            Err(RespError::InvalidFrame(_))
+        ));
         Ok(())
     }
 }
@@ -1,11 +1,11 @@
-use bytes::{Buf, BytesMut};
+use bytes::BytesMut;
 
 use crate::{RespDecode, RespEncode, RespError, RespFrame};
 use std::ops::Deref;
 
-use super::{calc_total_length, parse_length, BUF_CAP, CRLF_LEN};
+use super::{decode_aggregate, frame_length, parse_length, BUF_CAP, MAX_NESTING_DEPTH};
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct RespSet(pub(crate) Vec<RespFrame>);
 
 // - set: "~<number-of-elements>\r\n<element-1>...<element-n>"
@@ -24,29 +24,20 @@ impl RespEncode for RespSet {
 impl RespDecode for RespSet {
     const PREFIX: &'static str = "~";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        let len = len as usize;
-
-        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
-
-        if buf.len() < total_len {
-            return Err(RespError::NotComplete);
-        }
-
-        buf.advance(end + CRLF_LEN);
-
-        let mut frames = Vec::new();
-        for _ in 0..len {
-            frames.push(RespFrame::decode(buf)?);
+        // the prefix is checked before anything is consumed
+        parse_length(buf, Self::PREFIX)?;
+        match decode_aggregate(buf, MAX_NESTING_DEPTH)? {
+            RespFrame::Set(frame) => Ok(frame),
+            frame => Err(RespError::InvalidFrameType(format!(
+                "expect: Set, got: {:?}",
+                frame
+            ))),
         }
-
-        Ok(RespSet::new(frames))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        let len = len as usize;
-        calc_total_length(buf, end, len, Self::PREFIX)
+        parse_length(buf, Self::PREFIX)?;
+        frame_length(buf, MAX_NESTING_DEPTH)
     }
 }
 
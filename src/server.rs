@@ -0,0 +1,368 @@
+// The server as a library: what main.rs runs, so applications and integration tests can
+// start one in-process, e.g. on a random port:
+//
+//   let server = Server::builder().addr("127.0.0.1:0").build().await?;
+//   let addr = server.local_addr()?;
+//   tokio::spawn(server.run());
+//
+// run() stops on SHUTDOWN, when the shutdown signal given to the builder resolves, or
+// when Backend::request_shutdown is called, the handle embedders keep to stop it.
+
+use crate::{network, Backend, ShutdownPolicy, Stats};
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use socket2::{SockRef, TcpKeepalive};
+use std::{future::Future, net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{lookup_host, unix, TcpListener, TcpSocket, TcpStream, UnixListener, UnixStream},
+    task::JoinSet,
+};
+use tracing::{error, info, warn};
+
+#[derive(Default)]
+pub struct ServerBuilder {
+    addr: Option<String>,
+    backend: Option<Backend>,
+    config: Vec<(String, String)>,
+    shutdown_signal: Option<BoxFuture<'static, ()>>,
+}
+
+pub struct Server {
+    backend: Backend,
+    // one or, with tcp-listeners, several sockets bound to the same port with SO_REUSEPORT
+    listeners: Vec<TcpListener>,
+    unix_listener: Option<UnixListener>,
+    unixsocket: String,
+    shutdown_signal: Option<BoxFuture<'static, ()>>,
+}
+
+impl ServerBuilder {
+    // the address to listen on, 0.0.0.0 and the port config parameter by default
+    pub fn addr(mut self, addr: impl Into<String>) -> Self {
+        self.addr = Some(addr.into());
+        self
+    }
+
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    // a config parameter applied like CONFIG SET when the server is built
+    pub fn config(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.push((name.into(), value.into()));
+        self
+    }
+
+    // shut down with the shutdown-on-sigterm policy once signal resolves
+    pub fn shutdown_signal(mut self, signal: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.shutdown_signal = Some(Box::pin(signal));
+        self
+    }
+
+    // apply the config, bind the listeners and load the snapshot
+    pub async fn build(self) -> Result<Server> {
+        let backend = self.backend.unwrap_or_default();
+        for (name, value) in &self.config {
+            backend
+                .config_set(name, value)
+                .map_err(|e| anyhow!("{} {}: {}", name, value, e))?;
+        }
+        let addr = self
+            .addr
+            .unwrap_or_else(|| format!("0.0.0.0:{}", backend.port()));
+        let listeners = bind_tcp(&addr, backend.tcp_listeners()).await?;
+        // replication and cluster mode need the port actually listened on
+        backend.set_port(listeners[0].local_addr()?.port());
+        info!(
+            "Simple-Redis-Server is listening on {} ({} listeners)",
+            addr,
+            listeners.len()
+        );
+
+        let unixsocket = backend.unixsocket();
+        let unix_listener = match unixsocket.is_empty() {
+            true => None,
+            false => {
+                // a socket file left behind by a previous run would make bind fail
+                let _ = std::fs::remove_file(&unixsocket);
+                info!("Simple-Redis-Server is listening on {}", unixsocket);
+                Some(UnixListener::bind(&unixsocket)?)
+            }
+        };
+
+        // refuse to start rather than serving without the persisted data
+        if backend.load()? {
+            info!("Loaded snapshot from {}", backend.dbfilename());
+        }
+        Ok(Server {
+            backend,
+            listeners,
+            unix_listener,
+            unixsocket,
+            shutdown_signal: self.shutdown_signal,
+        })
+    }
+}
+
+impl Server {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::default()
+    }
+
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    // metrics for embedders, see Backend::stats
+    pub fn stats(&self) -> Stats {
+        self.backend.stats()
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listeners[0].local_addr()?)
+    }
+
+    // serve until a shutdown is requested, then let the connections finish their commands
+    // and persist as the shutdown policy says. Fails when persisting does, the writes
+    // since the last snapshot may be lost then.
+    pub async fn run(mut self) -> Result<()> {
+        let backend = self.backend.clone();
+        if let Some(signal) = self.shutdown_signal.take() {
+            let backend = backend.clone();
+            tokio::spawn(async move {
+                signal.await;
+                backend.request_shutdown(backend.shutdown_on_sigterm());
+            });
+        }
+        // tasks that run as long as the server, they are aborted when the set is dropped
+        let mut background = JoinSet::new();
+        background.spawn(backend.clone().run_active_expire());
+        background.spawn(backend.clone().run_consumer_cleanup());
+        // every TCP listener has its own acceptor task, so accepting scales over the cores
+        let mut acceptors = JoinSet::new();
+        for listener in std::mem::take(&mut self.listeners) {
+            acceptors.spawn(accept_tcp(listener, backend.clone()));
+        }
+        let policy = loop {
+            tokio::select! {
+                Some(acceptor) = acceptors.join_next() => {
+                    // an acceptor only returns when accept fails
+                    acceptor??;
+                }
+                accepted = accept_unix(self.unix_listener.as_ref()) => {
+                    let (stream, _) = accepted?;
+                    // unix socket clients have no address, Redis shows them as path:0
+                    let raddr = format!("{}:0", self.unixsocket);
+                    info!("Accepted connection on: {}", self.unixsocket);
+                    spawn_connection(stream, raddr, &backend);
+                }
+                policy = backend.shutdown_requested() => break policy,
+            }
+        };
+        // the listeners close with their acceptors
+        acceptors.shutdown().await;
+        background.shutdown().await;
+        self.shutdown(policy).await
+    }
+
+    async fn shutdown(self, policy: ShutdownPolicy) -> Result<()> {
+        info!("Shutting down ({})", policy.name());
+        if self.unix_listener.is_some() {
+            let _ = std::fs::remove_file(&self.unixsocket);
+        }
+        if !self.backend.drain_connections().await {
+            warn!("Connections still open after shutdown-timeout, closing them");
+        }
+        self.backend.shutdown(policy).await.map_err(|e| {
+            error!("Exiting, writes since the last snapshot may be lost");
+            e.into()
+        })
+    }
+}
+
+fn spawn_connection<S>(stream: S, raddr: String, backend: &Backend)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let cloned_backend = backend.clone();
+    tokio::spawn(async move {
+        match network::connection_handler(stream, raddr.clone(), cloned_backend).await {
+            Ok(_) => {
+                info!("Connection from {} exited", raddr);
+            }
+            Err(e) => {
+                warn!("handle error for {}: {:?}", raddr, e);
+            }
+        }
+    });
+}
+
+// with more than one listener, each is its own socket on the address with SO_REUSEPORT
+// and the kernel spreads the incoming connections over them
+async fn bind_tcp(addr: &str, count: usize) -> Result<Vec<TcpListener>> {
+    if count <= 1 {
+        return Ok(vec![TcpListener::bind(addr).await?]);
+    }
+    let mut addr = lookup_host(addr)
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("{} doesn't resolve to an address", addr))?;
+    let mut listeners = Vec::with_capacity(count);
+    for _ in 0..count {
+        let socket = match addr {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        socket.set_reuseaddr(true)?;
+        socket.set_reuseport(true)?;
+        socket.bind(addr)?;
+        // the backlog TcpListener::bind uses
+        let listener = socket.listen(1024)?;
+        // with port 0 the others share the port the first one got
+        addr = listener.local_addr()?;
+        listeners.push(listener);
+    }
+    Ok(listeners)
+}
+
+// accept connections until accept fails
+async fn accept_tcp(listener: TcpListener, backend: Backend) -> std::io::Result<()> {
+    loop {
+        let (stream, raddr) = listener.accept().await?;
+        if let Err(e) = configure_socket(&stream, &backend) {
+            warn!("Failed to set socket options for {}: {}", raddr, e);
+        }
+        let raddr = raddr.to_string();
+        info!("Accepted connection from: {}", raddr);
+        spawn_connection(stream, raddr, &backend);
+    }
+}
+
+// tcp-nodelay: replies are small and written as soon as they are ready, like Redis don't
+// hold them back until the client acks the previous ones. tcp-keepalive: probe idle peers,
+// so connections to clients that went away without closing them get closed.
+fn configure_socket(stream: &TcpStream, backend: &Backend) -> std::io::Result<()> {
+    stream.set_nodelay(backend.tcp_nodelay())?;
+    let socket = SockRef::from(stream);
+    let keepalive = backend.tcp_keepalive();
+    if keepalive.is_zero() {
+        return socket.set_keepalive(false);
+    }
+    // like Redis, the probes are a third of the idle time apart
+    let params = TcpKeepalive::new()
+        .with_time(keepalive)
+        .with_interval((keepalive / 3).max(Duration::from_secs(1)));
+    socket.set_tcp_keepalive(&params)
+}
+
+// the next connection on the unix socket, never resolves when there is none
+async fn accept_unix(
+    listener: Option<&UnixListener>,
+) -> std::io::Result<(UnixStream, unix::SocketAddr)> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::oneshot,
+    };
+
+    #[tokio::test]
+    async fn test_server() -> Result<()> {
+        let (stop, signal) = oneshot::channel();
+        let server = Server::builder()
+            .addr("127.0.0.1:0")
+            .config("shutdown-on-sigterm", "nosave")
+            .shutdown_signal(async move {
+                let _ = signal.await;
+            })
+            .build()
+            .await?;
+        let addr = server.local_addr()?;
+        let backend = server.backend().clone();
+        assert_eq!(backend.port(), addr.port());
+        assert_eq!(server.stats().keys.total(), 0);
+        let running = tokio::spawn(server.run());
+
+        let mut client = TcpStream::connect(addr).await?;
+        client
+            .write_all(b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n")
+            .await?;
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"+OK\r\n");
+        assert_eq!(backend.get(b"k"), Some(BulkString::from("v").into()));
+
+        stop.send(()).unwrap();
+        running.await??;
+        assert_eq!(client.read(&mut buf).await?, 0);
+
+        assert!(Server::builder()
+            .addr("127.0.0.1:0")
+            .config("port", "none")
+            .build()
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_configure_socket() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let _client = TcpStream::connect(listener.local_addr()?).await?;
+        let (stream, _) = listener.accept().await?;
+        let backend = Backend::new();
+
+        configure_socket(&stream, &backend)?;
+        assert!(stream.nodelay()?);
+        assert!(SockRef::from(&stream).keepalive()?);
+
+        backend.config_set("tcp-nodelay", "no").unwrap();
+        backend.config_set("tcp-keepalive", "0").unwrap();
+        configure_socket(&stream, &backend)?;
+        assert!(!stream.nodelay()?);
+        assert!(!SockRef::from(&stream).keepalive()?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reuseport_listeners() -> Result<()> {
+        let listeners = bind_tcp("127.0.0.1:0", 4).await?;
+        assert_eq!(listeners.len(), 4);
+        let port = listeners[0].local_addr()?.port();
+        assert!(listeners
+            .iter()
+            .all(|listener| listener.local_addr().unwrap().port() == port));
+        drop(listeners);
+
+        let server = Server::builder()
+            .addr("127.0.0.1:0")
+            .config("tcp-listeners", "4")
+            .config("shutdown-on-sigterm", "nosave")
+            .build()
+            .await?;
+        let addr = server.local_addr()?;
+        let backend = server.backend().clone();
+        let running = tokio::spawn(server.run());
+        for _ in 0..8 {
+            let mut client = TcpStream::connect(addr).await?;
+            client.write_all(b"*1\r\n$4\r\nping\r\n").await?;
+            let mut buf = [0; 7];
+            client.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"+PONG\r\n");
+        }
+        backend.request_shutdown(ShutdownPolicy::NoSave);
+        running.await??;
+        Ok(())
+    }
+}
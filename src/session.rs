@@ -0,0 +1,325 @@
+use crate::{Backend, GetCache, PushSender, RespFrame, RespPush};
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+// CLIENT REPLY mode: ON replies to everything, OFF to nothing and SKIP skips the next reply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplyMode {
+    #[default]
+    On,
+    Off,
+    Skip,
+}
+
+// per-connection state, commands that depend on the connection (e.g. SUBSCRIBE) use it
+#[derive(Debug)]
+pub struct Session {
+    id: u64,
+    addr: String,
+    // frames pushed to the client outside of the request/response flow
+    sender: PushSender,
+    pub(crate) channels: HashSet<Vec<u8>>,
+    pub(crate) patterns: HashSet<Vec<u8>>,
+    // SSUBSCRIBE channels
+    pub(crate) shard_channels: HashSet<Vec<u8>>,
+    reply_mode: ReplyMode,
+    // RESP protocol version negotiated with HELLO
+    protocol: u8,
+    pub(crate) get_cache: GetCache,
+    // REPLCONF listening-port of a replica
+    listening_port: Option<u16>,
+    // REPLCONF ip-address of a replica announcing another address than the one it connects from
+    ip_address: Option<String>,
+    // the link of a replica to its master, which may write to a read-only replica
+    master_link: bool,
+    // ASKING was sent, for the next command only
+    asking: bool,
+    // CLIENT NO-EVICT: the client is never disconnected to free memory. Only recorded, no
+    // client is evicted here, but proxies set it and expect OK
+    no_evict: bool,
+    // CLIENT NO-TOUCH: reads of the client leave the idle time and access frequency of
+    // their keys as they were
+    no_touch: bool,
+}
+
+impl Session {
+    pub fn new(addr: impl Into<String>, sender: UnboundedSender<RespFrame>) -> Self {
+        Session {
+            id: NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed),
+            addr: addr.into(),
+            sender: PushSender::new(sender),
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            shard_channels: HashSet::new(),
+            reply_mode: ReplyMode::default(),
+            protocol: 2,
+            get_cache: GetCache::default(),
+            listening_port: None,
+            ip_address: None,
+            master_link: false,
+            asking: false,
+            no_evict: false,
+            no_touch: false,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    pub fn sender(&self) -> PushSender {
+        self.sender.clone()
+    }
+
+    // queue a frame to be written to the client before the reply of the current command
+    pub fn push(&self, frame: RespFrame) {
+        // the receiver is gone only when the connection is closing, nothing to deliver then
+        self.sender.send(frame);
+    }
+
+    pub fn protocol(&self) -> u8 {
+        self.protocol
+    }
+
+    pub fn set_protocol(&mut self, protocol: u8) {
+        self.protocol = protocol;
+    }
+
+    // out-of-band data (e.g. pub/sub messages) is a push frame under RESP3
+    // and a plain array under RESP2
+    pub fn as_push(&self, frame: RespFrame) -> RespFrame {
+        match frame {
+            RespFrame::Array(array) if self.protocol >= 3 => RespPush::new(array.0).into(),
+            frame => frame,
+        }
+    }
+
+    pub fn listening_port(&self) -> Option<u16> {
+        self.listening_port
+    }
+
+    pub fn set_listening_port(&mut self, port: u16) {
+        self.listening_port = Some(port);
+    }
+
+    pub fn ip_address(&self) -> Option<&str> {
+        self.ip_address.as_deref()
+    }
+
+    pub fn set_ip_address(&mut self, ip: String) {
+        self.ip_address = Some(ip);
+    }
+
+    pub fn is_master_link(&self) -> bool {
+        self.master_link
+    }
+
+    pub fn set_master_link(&mut self, master_link: bool) {
+        self.master_link = master_link;
+    }
+
+    pub fn set_asking(&mut self) {
+        self.asking = true;
+    }
+
+    // whether the command being run follows ASKING, resets it
+    pub fn take_asking(&mut self) -> bool {
+        std::mem::take(&mut self.asking)
+    }
+
+    pub fn no_evict(&self) -> bool {
+        self.no_evict
+    }
+
+    pub fn set_no_evict(&mut self, no_evict: bool) {
+        self.no_evict = no_evict;
+    }
+
+    pub fn no_touch(&self) -> bool {
+        self.no_touch
+    }
+
+    pub fn set_no_touch(&mut self, no_touch: bool) {
+        self.no_touch = no_touch;
+    }
+
+    pub fn reply_mode(&self) -> ReplyMode {
+        self.reply_mode
+    }
+
+    // SKIP while OFF changes nothing, like in Redis
+    pub fn set_reply_mode(&mut self, mode: ReplyMode) {
+        if mode == ReplyMode::Skip && self.reply_mode == ReplyMode::Off {
+            return;
+        }
+        self.reply_mode = mode;
+    }
+
+    // called before executing a command: true if a previous CLIENT REPLY SKIP asked to
+    // suppress the reply of this command, the mode goes back to ON afterwards
+    pub fn take_reply_skip(&mut self) -> bool {
+        if self.reply_mode == ReplyMode::Skip {
+            self.reply_mode = ReplyMode::On;
+            return true;
+        }
+        false
+    }
+
+    // whether frames should be written to the client at all
+    pub fn replies_enabled(&self) -> bool {
+        self.reply_mode == ReplyMode::On
+    }
+
+    // the channels and patterns subscribed, the count (P)SUBSCRIBE replies with
+    pub fn subscription_count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    // the shard channels subscribed, the count SSUBSCRIBE replies with
+    pub fn shard_subscription_count(&self) -> usize {
+        self.shard_channels.len()
+    }
+
+    // whether the client gets pushes from any subscription
+    pub fn is_subscribed(&self) -> bool {
+        self.subscription_count() + self.shard_subscription_count() > 0
+    }
+
+    // RESET: back to the state of a new connection, without the subscriptions. The id, the
+    // address and the replication role of the connection stay.
+    pub fn reset(&mut self, backend: &Backend) {
+        for channel in self.channels.drain() {
+            backend.pubsub.unsubscribe(&channel, self.id);
+        }
+        for pattern in self.patterns.drain() {
+            backend.pubsub.punsubscribe(&pattern, self.id);
+        }
+        for channel in self.shard_channels.drain() {
+            backend.pubsub.sunsubscribe(&channel, self.id);
+        }
+        self.reply_mode = ReplyMode::default();
+        self.protocol = 2;
+        self.get_cache = GetCache::default();
+        self.asking = false;
+        self.no_evict = false;
+        self.no_touch = false;
+    }
+
+    // release everything the session registered in the backend
+    pub fn close(&mut self, backend: &Backend) {
+        for channel in self.channels.drain() {
+            backend.pubsub.unsubscribe(&channel, self.id);
+        }
+        for pattern in self.patterns.drain() {
+            backend.pubsub.punsubscribe(&pattern, self.id);
+        }
+        for channel in self.shard_channels.drain() {
+            backend.pubsub.sunsubscribe(&channel, self.id);
+        }
+        backend.replication.remove_replica(self.id);
+    }
+}
+
+impl Default for Session {
+    // a session not attached to any connection, pushed frames are dropped
+    fn default() -> Self {
+        let (sender, _) = mpsc::unbounded_channel();
+        Session::new("", sender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray};
+
+    #[test]
+    fn test_session_ids_are_unique() {
+        let a = Session::default();
+        let b = Session::default();
+        assert_ne!(a.id(), b.id());
+    }
+
+    #[test]
+    fn test_session_push_and_close() {
+        let backend = Backend::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut session = Session::new("127.0.0.1:1234", tx);
+        session.push(BulkString::from("hello").into());
+        assert_eq!(rx.try_recv().unwrap(), BulkString::from("hello").into());
+
+        backend
+            .pubsub
+            .subscribe(b"news".to_vec(), session.id(), session.sender());
+        session.channels.insert(b"news".to_vec());
+        session.close(&backend);
+        assert_eq!(session.subscription_count(), 0);
+        assert!(!backend.pubsub.has_subscribers());
+    }
+
+    #[test]
+    fn test_session_as_push() {
+        let mut session = Session::default();
+        let frame: RespFrame = RespArray::new(vec![BulkString::from("message").into()]).into();
+        assert_eq!(session.as_push(frame.clone()), frame);
+
+        session.set_protocol(3);
+        assert_eq!(
+            session.as_push(frame),
+            RespPush::new(vec![BulkString::from("message").into()]).into()
+        );
+    }
+
+    #[test]
+    fn test_session_reply_skip() {
+        let mut session = Session::default();
+        assert!(!session.take_reply_skip());
+        assert!(session.replies_enabled());
+
+        session.set_reply_mode(ReplyMode::Skip);
+        assert!(!session.replies_enabled());
+        assert!(session.take_reply_skip());
+        assert_eq!(session.reply_mode(), ReplyMode::On);
+        assert!(!session.take_reply_skip());
+
+        session.set_reply_mode(ReplyMode::Off);
+        assert!(!session.take_reply_skip());
+        assert!(!session.replies_enabled());
+        session.set_reply_mode(ReplyMode::Skip);
+        assert_eq!(session.reply_mode(), ReplyMode::Off);
+    }
+
+    #[test]
+    fn test_session_reset() {
+        let backend = Backend::new();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut session = Session::new("127.0.0.1:1234", tx);
+        backend
+            .pubsub
+            .subscribe(b"news".to_vec(), session.id(), session.sender());
+        session.channels.insert(b"news".to_vec());
+        session.set_protocol(3);
+        session.set_reply_mode(ReplyMode::Off);
+        session.set_no_evict(true);
+        session.set_no_touch(true);
+        let id = session.id();
+
+        session.reset(&backend);
+        assert_eq!(session.id(), id);
+        assert_eq!(session.subscription_count(), 0);
+        assert!(!backend.pubsub.has_subscribers());
+        assert_eq!(session.protocol(), 2);
+        assert!(session.replies_enabled());
+        assert!(!session.no_evict());
+        assert!(!session.no_touch());
+    }
+}
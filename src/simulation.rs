@@ -0,0 +1,226 @@
+// Deterministic simulation for tests: clients send commands through the same dispatcher
+// as the network layer, on the current-thread runtime of the test, with tokio's clock
+// paused and the wall clock mocked. Nothing moves unless the script says so, which makes
+// interleavings like a blocking pop racing its timeout reproducible.
+//
+// Run it from #[tokio::test(start_paused = true)]. After every step the commands in flight
+// run until they complete or block, their replies are collected in completion order.
+
+use crate::{
+    backend::set_mock_now_ms, network::dispatch, Backend, BulkString, RespArray, RespFrame,
+    RespNullArray, Session,
+};
+use std::time::Duration;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver},
+    task::JoinHandle,
+};
+
+// unix time the mock wall clock starts at
+const SIMULATION_START_MS: u64 = 1_700_000_000_000;
+// yields for the commands in flight to settle, each one lets every ready task run once
+const SETTLE_YIELDS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    // client and command arguments
+    Send(usize, Vec<String>),
+    // move both clocks forward
+    Advance(Duration),
+}
+
+struct Client {
+    // None while a command is in flight
+    session: Option<Session>,
+    task: Option<JoinHandle<(Session, RespFrame)>>,
+    pushes: UnboundedReceiver<RespFrame>,
+}
+
+pub struct Simulation {
+    backend: Backend,
+    clients: Vec<Client>,
+    now_ms: u64,
+    replies: Vec<(usize, RespFrame)>,
+}
+
+impl Simulation {
+    pub fn new(clients: usize) -> Self {
+        set_mock_now_ms(Some(SIMULATION_START_MS));
+        let clients = (0..clients)
+            .map(|i| {
+                let (sender, pushes) = mpsc::unbounded_channel();
+                Client {
+                    session: Some(Session::new(format!("sim:{}", i), sender)),
+                    task: None,
+                    pushes,
+                }
+            })
+            .collect();
+        Simulation {
+            backend: Backend::new(),
+            clients,
+            now_ms: SIMULATION_START_MS,
+            replies: Vec::new(),
+        }
+    }
+
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        self.now_ms
+    }
+
+    // a client waits for its reply before sending again, like a client without pipelining
+    pub async fn send(&mut self, client: usize, args: &[&str]) {
+        let mut session = self.clients[client]
+            .session
+            .take()
+            .unwrap_or_else(|| panic!("client {} is waiting for a reply", client));
+        let frame: RespFrame = RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into();
+        let backend = self.backend.clone();
+        self.clients[client].task = Some(tokio::spawn(async move {
+            let reply = dispatch(frame, &backend, &mut session).await;
+            (session, reply)
+        }));
+        self.settle().await;
+    }
+
+    pub async fn advance(&mut self, duration: Duration) {
+        self.now_ms += duration.as_millis() as u64;
+        set_mock_now_ms(Some(self.now_ms));
+        tokio::time::advance(duration).await;
+        self.settle().await;
+    }
+
+    pub async fn run(&mut self, script: &[Step]) {
+        for step in script {
+            match step {
+                Step::Send(client, args) => {
+                    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+                    self.send(*client, &args).await;
+                }
+                Step::Advance(duration) => self.advance(*duration).await,
+            }
+        }
+    }
+
+    // the replies received since the last call, in the order they completed
+    pub fn replies(&mut self) -> Vec<(usize, RespFrame)> {
+        std::mem::take(&mut self.replies)
+    }
+
+    // frames pushed to the client, e.g. pub/sub messages
+    pub fn pushes(&mut self, client: usize) -> Vec<RespFrame> {
+        let mut pushes = Vec::new();
+        while let Ok(frame) = self.clients[client].pushes.try_recv() {
+            pushes.push(frame);
+        }
+        pushes
+    }
+
+    pub fn is_blocked(&self, client: usize) -> bool {
+        self.clients[client].task.is_some()
+    }
+
+    async fn settle(&mut self) {
+        for _ in 0..SETTLE_YIELDS {
+            tokio::task::yield_now().await;
+        }
+        for (i, client) in self.clients.iter_mut().enumerate() {
+            if !client.task.as_ref().is_some_and(|task| task.is_finished()) {
+                continue;
+            }
+            let (session, reply) = client.task.take().unwrap().await.unwrap();
+            client.session = Some(session);
+            self.replies.push((i, reply));
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        set_mock_now_ms(None);
+    }
+}
+
+// script steps from string literals
+pub fn send(client: usize, args: &[&str]) -> Step {
+    Step::Send(client, args.iter().map(|arg| arg.to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(s: &str) -> RespFrame {
+        BulkString::from(s).into()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_blocking_pop_served_before_timeout() {
+        let mut sim = Simulation::new(2);
+        sim.run(&[
+            send(0, &["blpop", "l", "1"]),
+            Step::Advance(Duration::from_millis(999)),
+            send(1, &["rpush", "l", "a"]),
+        ])
+        .await;
+        assert_eq!(
+            sim.replies(),
+            vec![
+                (0, RespArray::new(vec![bulk("l"), bulk("a")]).into()),
+                (1, RespFrame::Integer(1)),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_blocking_pop_times_out() {
+        let mut sim = Simulation::new(2);
+        sim.send(0, &["blpop", "l", "1"]).await;
+        assert!(sim.is_blocked(0));
+        sim.advance(Duration::from_secs(1)).await;
+        assert_eq!(sim.replies(), vec![(0, RespNullArray.into())]);
+
+        // the push comes too late and stays in the list
+        sim.send(1, &["rpush", "l", "a"]).await;
+        assert_eq!(sim.replies(), vec![(1, RespFrame::Integer(1))]);
+        assert_eq!(sim.backend().llen(b"l"), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mock_clock() {
+        let mut sim = Simulation::new(1);
+        sim.send(0, &["xadd", "s", "*", "f", "v"]).await;
+        sim.advance(Duration::from_millis(5)).await;
+        sim.send(0, &["xadd", "s", "*", "f", "v"]).await;
+        assert_eq!(
+            sim.replies(),
+            vec![(0, bulk("1700000000000-0")), (0, bulk("1700000000005-0")),]
+        );
+        assert_eq!(sim.now_ms(), SIMULATION_START_MS + 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pushes() {
+        let mut sim = Simulation::new(2);
+        sim.run(&[
+            send(0, &["subscribe", "ch"]),
+            send(1, &["publish", "ch", "hi"]),
+        ])
+        .await;
+        assert_eq!(sim.replies().len(), 2);
+        assert_eq!(
+            sim.pushes(0),
+            vec![RespArray::new(vec![bulk("message"), bulk("ch"), bulk("hi")]).into()]
+        );
+        assert!(sim.pushes(1).is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use bytes::BytesMut;
+use futures::StreamExt;
+use simple_redis::{BulkString, RespArray, RespDecode, RespFrame, RespPush, Server};
+use std::net::SocketAddr;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+async fn start_server() -> Result<SocketAddr> {
+    let server = Server::builder().addr("127.0.0.1:0").build().await?;
+    let addr = server.local_addr()?;
+    tokio::spawn(server.run());
+    Ok(addr)
+}
+
+fn command(args: &[&str]) -> Vec<u8> {
+    let mut buf = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        buf.extend_from_slice(format!("${}\r\n{}\r\n", arg.len(), arg).as_bytes());
+    }
+    buf
+}
+
+async fn read_frame(stream: &mut TcpStream, buf: &mut BytesMut) -> Result<RespFrame> {
+    loop {
+        if !buf.is_empty() {
+            if let Ok(frame) = RespFrame::decode(buf) {
+                return Ok(frame);
+            }
+        }
+        if stream.read_buf(buf).await? == 0 {
+            anyhow::bail!("connection closed");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_resp2_pubsub_with_redis_rs() -> Result<()> {
+    let addr = start_server().await?;
+    let client = redis::Client::open(format!("redis://{}", addr))?;
+
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe("news").await?;
+
+    let mut publisher = TcpStream::connect(addr).await?;
+    publisher
+        .write_all(&command(&["publish", "news", "hello"]))
+        .await?;
+    let mut buf = BytesMut::new();
+    assert_eq!(
+        read_frame(&mut publisher, &mut buf).await?,
+        RespFrame::Integer(1)
+    );
+
+    let msg = pubsub.on_message().next().await.unwrap();
+    assert_eq!(msg.get_channel_name(), "news");
+    assert_eq!(msg.get_payload::<String>()?, "hello");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resp3_pubsub_push_frames() -> Result<()> {
+    let addr = start_server().await?;
+    let mut subscriber = TcpStream::connect(addr).await?;
+    let mut buf = BytesMut::new();
+
+    subscriber.write_all(&command(&["hello", "3"])).await?;
+    match read_frame(&mut subscriber, &mut buf).await? {
+        RespFrame::Map(map) => assert_eq!(map["proto"], RespFrame::Integer(3)),
+        frame => panic!("unexpected HELLO reply: {:?}", frame),
+    }
+
+    subscriber
+        .write_all(&command(&["subscribe", "news"]))
+        .await?;
+    assert_eq!(
+        read_frame(&mut subscriber, &mut buf).await?,
+        RespPush::new(vec![
+            BulkString::from("subscribe").into(),
+            BulkString::from("news").into(),
+            RespFrame::Integer(1),
+        ])
+        .into()
+    );
+
+    let mut publisher = TcpStream::connect(addr).await?;
+    let mut publisher_buf = BytesMut::new();
+    publisher
+        .write_all(&command(&["publish", "news", "hello"]))
+        .await?;
+    assert_eq!(
+        read_frame(&mut publisher, &mut publisher_buf).await?,
+        RespFrame::Integer(1)
+    );
+
+    assert_eq!(
+        read_frame(&mut subscriber, &mut buf).await?,
+        RespPush::new(vec![
+            BulkString::from("message").into(),
+            BulkString::from("news").into(),
+            BulkString::from("hello").into(),
+        ])
+        .into()
+    );
+
+    // a RESP2 subscriber on the same channel still gets plain arrays
+    let mut resp2 = TcpStream::connect(addr).await?;
+    let mut resp2_buf = BytesMut::new();
+    resp2.write_all(&command(&["subscribe", "news"])).await?;
+    read_frame(&mut resp2, &mut resp2_buf).await?;
+    publisher
+        .write_all(&command(&["publish", "news", "again"]))
+        .await?;
+    assert_eq!(
+        read_frame(&mut resp2, &mut resp2_buf).await?,
+        RespArray::new(vec![
+            BulkString::from("message").into(),
+            BulkString::from("news").into(),
+            BulkString::from("again").into(),
+        ])
+        .into()
+    );
+    Ok(())
+}
@@ -0,0 +1,327 @@
+use anyhow::Result;
+use bytes::{Buf, BytesMut};
+use simple_redis::{
+    client::{Client, ClientError},
+    Backend, BulkString, RespArray, RespDecode, RespFrame, Server,
+};
+use std::{net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+async fn start_server(backend: Backend) -> Result<SocketAddr> {
+    let server = Server::builder()
+        .addr("127.0.0.1:0")
+        .backend(backend)
+        .build()
+        .await?;
+    let addr = server.local_addr()?;
+    tokio::spawn(server.run());
+    Ok(addr)
+}
+
+fn command(args: &[&str]) -> Vec<u8> {
+    let mut buf = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        buf.extend_from_slice(format!("${}\r\n{}\r\n", arg.len(), arg).as_bytes());
+    }
+    buf
+}
+
+fn array(args: &[&str]) -> RespFrame {
+    RespArray::new(
+        args.iter()
+            .map(|arg| BulkString::from(*arg).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+async fn read_frame(stream: &mut TcpStream, buf: &mut BytesMut) -> Result<RespFrame> {
+    loop {
+        if !buf.is_empty() {
+            if let Ok(frame) = RespFrame::decode(buf) {
+                return Ok(frame);
+            }
+        }
+        if stream.read_buf(buf).await? == 0 {
+            anyhow::bail!("connection closed");
+        }
+    }
+}
+
+async fn read_line(stream: &mut TcpStream, buf: &mut BytesMut) -> Result<String> {
+    loop {
+        if let Some(end) = buf.windows(2).position(|w| w == b"\r\n") {
+            let line = String::from_utf8(buf[..end].to_vec())?;
+            buf.advance(end + 2);
+            return Ok(line);
+        }
+        if stream.read_buf(buf).await? == 0 {
+            anyhow::bail!("connection closed");
+        }
+    }
+}
+
+async fn read_exact(stream: &mut TcpStream, buf: &mut BytesMut, len: usize) -> Result<Vec<u8>> {
+    while buf.len() < len {
+        if stream.read_buf(buf).await? == 0 {
+            anyhow::bail!("connection closed");
+        }
+    }
+    Ok(buf.split_to(len).to_vec())
+}
+
+#[tokio::test]
+async fn test_replica_full_sync_and_stream() -> Result<()> {
+    let backend = Backend::new();
+    backend.set("a".to_string().into(), BulkString::from("1").into());
+    let addr = start_server(backend).await?;
+
+    let mut replica = TcpStream::connect(addr).await?;
+    let mut replica_buf = BytesMut::new();
+    replica
+        .write_all(&command(&["replconf", "listening-port", "6380"]))
+        .await?;
+    assert_eq!(read_line(&mut replica, &mut replica_buf).await?, "+OK");
+    replica.write_all(&command(&["psync", "?", "-1"])).await?;
+    let line = read_line(&mut replica, &mut replica_buf).await?;
+    let parts: Vec<&str> = line.split(' ').collect();
+    assert_eq!(parts[0], "+FULLRESYNC");
+    assert_eq!(parts[1].len(), 40);
+    assert_eq!(parts[2], "0");
+    let len: usize = read_line(&mut replica, &mut replica_buf).await?[1..].parse()?;
+    let snapshot = read_exact(&mut replica, &mut replica_buf, len).await?;
+    let loaded = Backend::new();
+    loaded.restore_snapshot(&snapshot)?;
+    assert_eq!(loaded.get(b"a"), Some(BulkString::from("1").into()));
+
+    let mut client = TcpStream::connect(addr).await?;
+    let mut client_buf = BytesMut::new();
+    client.write_all(&command(&["set", "b", "2"])).await?;
+    read_frame(&mut client, &mut client_buf).await?;
+    client.write_all(&command(&["get", "b"])).await?;
+    read_frame(&mut client, &mut client_buf).await?;
+    client.write_all(&command(&["rpush", "l", "x"])).await?;
+    read_frame(&mut client, &mut client_buf).await?;
+    client.write_all(&command(&["blpop", "l", "0"])).await?;
+    read_frame(&mut client, &mut client_buf).await?;
+
+    // reads aren't replicated and the blocking pop is replayed as a plain pop
+    assert_eq!(
+        read_frame(&mut replica, &mut replica_buf).await?,
+        array(&["set", "b", "2"])
+    );
+    assert_eq!(
+        read_frame(&mut replica, &mut replica_buf).await?,
+        array(&["rpush", "l", "x"])
+    );
+    assert_eq!(
+        read_frame(&mut replica, &mut replica_buf).await?,
+        array(&["lpop", "l"])
+    );
+
+    let offset = (command(&["set", "b", "2"]).len()
+        + command(&["rpush", "l", "x"]).len()
+        + command(&["lpop", "l"]).len())
+    .to_string();
+    replica
+        .write_all(&command(&["replconf", "ack", &offset]))
+        .await?;
+    // the ACK has no reply, wait for ROLE to see it
+    loop {
+        client.write_all(&command(&["role"])).await?;
+        let role = read_frame(&mut client, &mut client_buf).await?;
+        let RespFrame::Array(role) = role else {
+            panic!("ROLE must reply with an array");
+        };
+        assert_eq!(role[1], RespFrame::Integer(offset.parse()?));
+        if role[2] == RespArray::new(vec![array(&["127.0.0.1", "6380", &offset])]).into() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    Ok(())
+}
+
+async fn request(stream: &mut TcpStream, buf: &mut BytesMut, args: &[&str]) -> Result<RespFrame> {
+    stream.write_all(&command(args)).await?;
+    read_frame(stream, buf).await
+}
+
+#[tokio::test]
+async fn test_replicaof() -> Result<()> {
+    let master = Backend::new();
+    master.set("a".to_string().into(), BulkString::from("1").into());
+    let master_addr = start_server(master).await?;
+    let replica_addr = start_server(Backend::new()).await?;
+
+    let mut replica = TcpStream::connect(replica_addr).await?;
+    let mut replica_buf = BytesMut::new();
+    let port = master_addr.port().to_string();
+    request(
+        &mut replica,
+        &mut replica_buf,
+        &["replicaof", "127.0.0.1", &port],
+    )
+    .await?;
+
+    let mut client = TcpStream::connect(master_addr).await?;
+    let mut client_buf = BytesMut::new();
+    request(&mut client, &mut client_buf, &["set", "b", "2"]).await?;
+    // the snapshot brings a, the stream b
+    loop {
+        let reply = request(&mut replica, &mut replica_buf, &["get", "b"]).await?;
+        if reply == BulkString::from("2").into() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert_eq!(
+        request(&mut replica, &mut replica_buf, &["get", "a"]).await?,
+        BulkString::from("1").into()
+    );
+    // the replica answers the GETACK sent by WAIT
+    assert_eq!(
+        request(&mut client, &mut client_buf, &["wait", "1", "0"]).await?,
+        RespFrame::Integer(1)
+    );
+    let RespFrame::Array(role) = request(&mut replica, &mut replica_buf, &["role"]).await? else {
+        panic!("ROLE must reply with an array");
+    };
+    assert_eq!(role[0], BulkString::from("slave").into());
+    assert_eq!(role[3], BulkString::from("connected").into());
+
+    let RespFrame::Error(e) = request(&mut replica, &mut replica_buf, &["set", "c", "3"]).await?
+    else {
+        panic!("a read-only replica must reject writes");
+    };
+    assert!(e.starts_with("READONLY"));
+
+    request(&mut replica, &mut replica_buf, &["replicaof", "no", "one"]).await?;
+    request(&mut replica, &mut replica_buf, &["set", "c", "3"]).await?;
+    assert_eq!(
+        request(&mut replica, &mut replica_buf, &["get", "c"]).await?,
+        BulkString::from("3").into()
+    );
+    let RespFrame::Array(role) = request(&mut replica, &mut replica_buf, &["role"]).await? else {
+        panic!("ROLE must reply with an array");
+    };
+    assert_eq!(role[0], BulkString::from("master").into());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sort_on_read_only_replica() -> Result<()> {
+    let master_addr = start_server(Backend::new()).await?;
+    let replica_addr = start_server(Backend::new()).await?;
+    let port = master_addr.port().to_string();
+    let mut replica = Client::connect(replica_addr).await?;
+    replica.command(&["replicaof", "127.0.0.1", &port]).await?;
+    let mut client = Client::connect(master_addr).await?;
+    client.command(&["rpush", "l", "2", "3", "1"]).await?;
+    client.command(&["wait", "1", "0"]).await?;
+
+    // SORT is a write for its STORE, SORT_RO sorts on a read-only replica
+    assert_eq!(
+        replica.command(&["sort_ro", "l"]).await?,
+        RespArray::new(vec![
+            BulkString::from("1").into(),
+            BulkString::from("2").into(),
+            BulkString::from("3").into(),
+        ])
+        .into()
+    );
+    let RespFrame::Error(e) = replica.command(&["sort", "l"]).await? else {
+        panic!("a read-only replica must reject SORT");
+    };
+    assert!(e.starts_with("READONLY"));
+    let RespFrame::Error(e) = replica.command(&["sort_ro", "l", "store", "d"]).await? else {
+        panic!("SORT_RO must reject STORE");
+    };
+    assert!(e.starts_with("ERR syntax error"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replica_ignores_maxmemory() -> Result<()> {
+    let master_addr = start_server(Backend::new()).await?;
+    let replica_backend = Backend::new();
+    replica_backend.config_set("maxmemory", "1").unwrap();
+    replica_backend
+        .config_set("maxmemory-policy", "noeviction")
+        .unwrap();
+    let replica_addr = start_server(replica_backend).await?;
+    let port = master_addr.port().to_string();
+    let mut replica = Client::connect(replica_addr).await?;
+    replica.command(&["replicaof", "127.0.0.1", &port]).await?;
+
+    // every write of the stream is over maxmemory on the replica, it applies them anyway
+    // instead of dropping the link on OOM
+    let mut client = Client::connect(master_addr).await?;
+    for i in 0..10 {
+        let key = format!("k{}", i);
+        client.command(&["set", &key, "v"]).await?;
+        client.command(&["hset", "h", &key, "v"]).await?;
+    }
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while replica.command(&["hget", "h", "k9"]).await? != BulkString::from("v").into() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "the replica stopped applying the stream"
+        );
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(
+        replica.command(&["get", "k9"]).await?,
+        BulkString::from("v").into()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_write_with_replicas() -> Result<()> {
+    let master_addr = start_server(Backend::new()).await?;
+    let replica_addr = start_server(Backend::new()).await?;
+    let port = master_addr.port().to_string();
+    let mut replica = Client::connect(replica_addr).await?;
+    replica.command(&["replicaof", "127.0.0.1", &port]).await?;
+
+    let mut client = Client::connect(master_addr).await?;
+    let reply = client
+        .write_with_replicas(&["set", "a", "1"], 1, None)
+        .await?;
+    assert_eq!(
+        reply,
+        RespFrame::from(simple_redis::SimpleString::new("OK"))
+    );
+    // acknowledged by the replica, so it reads the write
+    assert_eq!(
+        replica.command(&["get", "a"]).await?,
+        BulkString::from("1").into()
+    );
+
+    let result = client
+        .write_with_replicas(&["set", "a", "2"], 2, Some(Duration::from_millis(100)))
+        .await;
+    assert!(matches!(
+        result,
+        Err(ClientError::NotReplicated {
+            acked: 1,
+            required: 2
+        })
+    ));
+    // a failed write is its error, and both replies are read so the connection stays in step
+    assert!(matches!(
+        client
+            .write_with_replicas(&["incr", "a", "b"], 1, None)
+            .await,
+        Err(ClientError::Server(_))
+    ));
+    assert_eq!(
+        client.command(&["get", "a"]).await?,
+        BulkString::from("2").into()
+    );
+    Ok(())
+}